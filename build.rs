@@ -1,5 +1,80 @@
-use lalrpop;
-
 fn main() {
-    lalrpop::process_root().unwrap();
-}
\ No newline at end of file
+    // Cargo reruns the script when the grammar changes rather than on any change to the package,
+    // as the header written below is part of the package.
+    lalrpop::Configuration::new()
+        .emit_rerun_directives(true)
+        .process_current_dir()
+        .unwrap();
+
+    builtins();
+
+    #[cfg(feature = "ffi")]
+    header();
+}
+
+/// Writes the names and arities of the predicates the solver dispatches on in `builtins::call`
+/// and the `control` functions of the core crate and of `host`, sorted, to `builtins.rs` in `OUT_DIR` for `xref::is_builtin`.
+fn builtins() {
+    let mut builtins = Vec::new();
+
+    for (path, function) in [
+        ("src/builtins.rs", "pub(crate) fn call("),
+        ("core/src/solve.rs", "fn control<"),
+        ("src/host.rs", "fn control("),
+    ] {
+        println!("cargo:rerun-if-changed={}", path);
+        let source = std::fs::read_to_string(path).unwrap();
+        let body = source
+            .lines()
+            .skip_while(|line| !line.starts_with(function))
+            .take_while(|line| *line != "}");
+
+        for line in body {
+            let line = line.trim_start();
+
+            if !line.starts_with("(\"") && !line.starts_with("| (\"") {
+                continue;
+            }
+
+            // The patterns of a match arm, such as `("assert", 1) | ("assertz", 1) =>`.
+            let patterns = line.split("=>").next().unwrap_or_default();
+
+            for pattern in patterns.split('|') {
+                let pattern = pattern.trim().trim_start_matches('(').trim_end_matches(')');
+
+                if let Some((name, arity)) = pattern.rsplit_once(", ") {
+                    if let (Some(name), Ok(arity)) = (
+                        name.strip_prefix('"')
+                            .and_then(|name| name.strip_suffix('"')),
+                        arity.parse::<usize>(),
+                    ) {
+                        builtins.push((name.replace("\\\\", "\\"), arity));
+                    }
+                }
+            }
+        }
+    }
+
+    builtins.sort();
+    builtins.dedup();
+
+    let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("builtins.rs");
+    std::fs::write(
+        out,
+        format!("const BUILTINS: &[(&str, usize)] = &{:?};\n", builtins),
+    )
+    .unwrap();
+}
+
+/// Writes the C header declaring the functions of `src/ffi.rs` to `include/bfg_prolog.h`.
+#[cfg(feature = "ffi")]
+fn header() {
+    let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    cbindgen::generate(&dir)
+        .expect("Could not generate the C header")
+        .write_to_file(std::path::Path::new(&dir).join("include/bfg_prolog.h"));
+}
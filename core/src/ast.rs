@@ -0,0 +1,756 @@
+//! The representation of terms and clauses, which needs nothing from `std` beyond `alloc`.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+
+/// A term, in the one form the parser, the solver and the writer share. An atom is an `Atom` with
+/// no arguments, so that it is never told apart from a compound term by anything but its arity.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Term {
+    Var(Var),
+    Atom(Atom),
+    Number(Number),
+    String(String),
+    PartialString(String, Box<Term>),
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Var(pub String, pub usize);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Const(pub String);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Atom {
+    pub name: Const,
+    pub arity: Arity,
+    pub args: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Assertion {
+    pub head: Atom,
+    pub clause: Clause,
+}
+
+pub type Arity = usize;
+pub type Clause = Vec<Atom>;
+
+impl Assertion {
+    pub fn new(head: Atom, clause: Clause) -> Self {
+        Assertion { head, clause }
+    }
+
+    /// Builds a clause from a term read as `Head :- Body` or as a fact.
+    pub fn from_term(t: Term) -> Option<Self> {
+        match t {
+            Term::Atom(Atom {
+                name: Const(ref name),
+                ref args,
+                ..
+            }) if name == ":-" && args.len() == 2 => match &args[0] {
+                Term::Atom(head) => Some(Assertion::new(head.clone(), goals(args[1].clone()))),
+                _ => None,
+            },
+            Term::Atom(head) => Some(Assertion::new(head, vec![])),
+            _ => None,
+        }
+    }
+
+    /// The term `from_term` builds the clause back from: `Head :- Body`, or the head of a fact.
+    pub fn to_term(&self) -> Term {
+        let body = self
+            .clause
+            .iter()
+            .rev()
+            .cloned()
+            .map(Term::Atom)
+            .reduce(|rest, goal| Term::Atom(Atom::new(",", vec![goal, rest])));
+
+        match body {
+            Some(body) => op(":-", Term::Atom(self.head.clone()), body),
+            None => Term::Atom(self.head.clone()),
+        }
+    }
+}
+
+/// Builds the term for the binary operator `name` applied to `x` and `y`.
+pub fn op(name: &str, x: Term, y: Term) -> Term {
+    Term::Atom(Atom::new(name, vec![x, y]))
+}
+
+/// `t` with the compound terms nested deeper than `depth` left out as `...`, and the elements of a
+/// list past the first `depth` as a `...` tail. A depth of 0 leaves all of `t` in.
+pub fn elide(t: &Term, depth: usize) -> Term {
+    if depth == 0 {
+        return t.clone();
+    }
+
+    let ellipsis = || Term::Atom(Atom::new("...", vec![]));
+
+    match t {
+        Term::Atom(a) if a.arity > 0 && depth == 1 => ellipsis(),
+        Term::Atom(a) if a.name.0 == "." && a.arity == 2 => {
+            let mut items = Vec::new();
+            let mut rest = t;
+
+            while let Term::Atom(cell) = rest {
+                if cell.name.0 != "." || cell.arity != 2 {
+                    break;
+                }
+
+                if items.len() == depth {
+                    return Term::list(items, ellipsis());
+                }
+
+                items.push(elide(&cell.args[0], depth - 1));
+                rest = &cell.args[1];
+            }
+
+            Term::list(items, elide(rest, depth))
+        }
+        Term::Atom(a) => Term::Atom(Atom::new(
+            &a.name.0,
+            a.args.iter().map(|t| elide(t, depth - 1)).collect(),
+        )),
+        t => t.clone(),
+    }
+}
+
+/// Flattens a conjunction into the goals it is made of, wrapping variables and other
+/// non-callable terms in `call/1`.
+pub fn goals(t: Term) -> Clause {
+    let mut goals = Vec::new();
+    let mut t = t;
+
+    loop {
+        match t {
+            Term::Atom(Atom {
+                name: Const(ref name),
+                ref args,
+                ..
+            }) if name == "," && args.len() == 2 => {
+                goals.extend(self::goals(args[0].clone()));
+                t = args[1].clone();
+            }
+            Term::Atom(a) => {
+                goals.push(a);
+                return goals;
+            }
+            t => {
+                goals.push(Atom::new("call", vec![t]));
+                return goals;
+            }
+        }
+    }
+}
+
+impl Atom {
+    pub fn new(name: &str, args: Vec<Term>) -> Self {
+        Atom {
+            name: Const::new(name),
+            arity: args.len(),
+            args,
+        }
+    }
+}
+
+impl Term {
+    pub fn nil() -> Self {
+        Term::Atom(Atom::new("[]", vec![]))
+    }
+
+    pub fn cons(head: Term, tail: Term) -> Self {
+        Term::Atom(Atom::new(".", vec![head, tail]))
+    }
+
+    pub fn list(items: Vec<Term>, tail: Term) -> Self {
+        items
+            .into_iter()
+            .rev()
+            .fold(tail, |tail, head| Term::cons(head, tail))
+    }
+
+    /// Builds a code list whose prefix is stored as a single chunk of text, with `tail` following
+    /// the last code.
+    pub fn partial_string(text: &str, tail: Term) -> Self {
+        if text.is_empty() {
+            return tail;
+        }
+
+        match tail {
+            Term::PartialString(rest, tail) => {
+                Term::PartialString(format!("{}{}", text, rest), tail)
+            }
+            tail => Term::PartialString(String::from(text), Box::new(tail)),
+        }
+    }
+
+    pub fn is_nil(&self) -> bool {
+        match self {
+            Term::Atom(Atom {
+                name: Const(name),
+                arity: 0,
+                ..
+            }) => name == "[]",
+            _ => false,
+        }
+    }
+}
+
+impl Var {
+    pub fn new(name: &str, n: usize) -> Self {
+        Var(String::from(name), n)
+    }
+
+    /// The variable an `_` at byte offset `at` of a clause is read as, which differs from every
+    /// other `_` in the clause and is left out of the answers to a query.
+    pub fn anonymous(at: usize) -> Self {
+        Var(format!("_{}", at), 0)
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        match self.0.strip_prefix('_') {
+            Some(digits) => !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()),
+            None => false,
+        }
+    }
+}
+
+impl Const {
+    pub fn new(name: &str) -> Self {
+        Const(String::from(name))
+    }
+}
+
+impl Number {
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim_start();
+
+        if let Ok(i) = s.parse() {
+            return Some(Number::Int(i));
+        }
+
+        match s.parse::<f64>() {
+            Ok(f) if s.contains('.') && f.is_finite() => Some(Number::Float(f)),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Number::Int(x), Number::Int(y)) => x.cmp(y),
+            (Number::Float(x), Number::Float(y)) => x.total_cmp(y),
+            // By value first, and a float before an integer of the same value, as in the standard
+            // order of terms.
+            (Number::Float(x), Number::Int(y)) => x.total_cmp(&(*y as f64)).then(Ordering::Less),
+            (Number::Int(x), Number::Float(y)) => (*x as f64).total_cmp(y).then(Ordering::Greater),
+        }
+    }
+}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Number::Int(i) => i.hash(state),
+            Number::Float(f) => f.to_bits().hash(state),
+        }
+    }
+}
+
+/// Returns true if the atom `name` has to be written between single quotes in order to be read
+/// back as the same atom.
+pub fn atom_needs_quotes(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        None => true,
+        Some(c) if is_atom_start(c) => !chars.all(|c| c.is_alphanumeric() || c == '_'),
+        Some(_) if name == "[]" || name == "!" || name == ";" || name == "{}" => false,
+        Some(_) => name == "." || !name.chars().all(is_symbol_char),
+    }
+}
+
+fn is_atom_start(c: char) -> bool {
+    c.is_lowercase() || (c.is_alphabetic() && !c.is_uppercase())
+}
+
+fn is_symbol_char(c: char) -> bool {
+    "+-*/\\^<>=~:.?@#&$".contains(c)
+}
+
+fn quote_text(text: &str, quote: char) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push(quote);
+
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c == quote => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push(quote);
+    quoted
+}
+
+/// Formats an atom name the way it has to be written to be read back in.
+pub fn quote_atom(name: &str) -> String {
+    if atom_needs_quotes(name) {
+        quote_text(name, '\'')
+    } else {
+        String::from(name)
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        match self {
+            Number::Int(i) => Ok(write!(f, "{}", i)?),
+            Number::Float(x) => {
+                let s = format!("{:?}", x);
+
+                match s.find('e') {
+                    Some(i) if !s[..i].contains('.') => Ok(write!(f, "{}.0{}", &s[..i], &s[i..])?),
+                    _ => Ok(write!(f, "{}", s)?),
+                }
+            }
+        }
+    }
+}
+
+/// The operators the parser reads, as `(priority, type, name)`, which the writer consults to put
+/// terms back the way they are read.
+pub const OPERATORS: &[(usize, &str, &str)] = &[
+    (1200, "xfx", ":-"),
+    (1200, "xfx", "-->"),
+    (1200, "fx", ":-"),
+    (1200, "fx", "?-"),
+    (1150, "fx", "table"),
+    (1150, "fx", "meta_predicate"),
+    (1150, "fx", "persistent"),
+    (1150, "fx", "thread_local"),
+    (1150, "fx", "dynamic"),
+    (1150, "fx", "parallel"),
+    (1100, "xfy", ";"),
+    (1050, "xfy", "->"),
+    (1050, "xfy", "*->"),
+    (1000, "xfy", ","),
+    (900, "fy", "\\+"),
+    (760, "yfx", "#<==>"),
+    (750, "xfy", "#==>"),
+    (750, "xfx", "#<=="),
+    (740, "yfx", "#\\/"),
+    (730, "yfx", "#\\"),
+    (720, "yfx", "#/\\"),
+    (710, "fy", "#\\"),
+    (700, "xfx", "="),
+    (700, "xfx", "\\="),
+    (700, "xfx", "=="),
+    (700, "xfx", "\\=="),
+    (700, "xfx", "@<"),
+    (700, "xfx", "@>"),
+    (700, "xfx", "@=<"),
+    (700, "xfx", "@>="),
+    (700, "xfx", "=.."),
+    (700, "xfx", "is"),
+    (700, "xfx", "=:="),
+    (700, "xfx", "=\\="),
+    (700, "xfx", "<"),
+    (700, "xfx", ">"),
+    (700, "xfx", "=<"),
+    (700, "xfx", ">="),
+    (700, "xfx", "#="),
+    (700, "xfx", "#\\="),
+    (700, "xfx", "#<"),
+    (700, "xfx", "#>"),
+    (700, "xfx", "#=<"),
+    (700, "xfx", "#>="),
+    (700, "xfx", "in"),
+    (700, "xfx", "ins"),
+    (500, "yfx", "+"),
+    (500, "yfx", "-"),
+    (500, "yfx", "/\\"),
+    (500, "yfx", "\\/"),
+    (500, "yfx", "xor"),
+    (450, "xfx", ".."),
+    (400, "yfx", "*"),
+    (400, "yfx", "/"),
+    (400, "yfx", "//"),
+    (400, "yfx", "mod"),
+    (400, "yfx", "rem"),
+    (400, "yfx", "div"),
+    (400, "yfx", "<<"),
+    (400, "yfx", ">>"),
+    (200, "xfx", "**"),
+    (200, "xfy", "^"),
+    (200, "xfy", ":"),
+    (200, "fy", "-"),
+    (200, "fy", "+"),
+    (200, "fy", "\\"),
+];
+
+/// The priority of the infix operator `name`, with the highest priorities its left and right
+/// arguments may have.
+fn infix_op(name: &str) -> Option<(usize, usize, usize)> {
+    OPERATORS
+        .iter()
+        .filter(|(_, _, op)| *op == name)
+        .find_map(|&(p, kind, _)| match kind {
+            "xfx" => Some((p, p - 1, p - 1)),
+            "xfy" => Some((p, p - 1, p)),
+            "yfx" => Some((p, p, p - 1)),
+            _ => None,
+        })
+}
+
+/// The priority of the prefix operator `name`, with the highest priority its argument may have.
+fn prefix_op(name: &str) -> Option<(usize, usize)> {
+    OPERATORS
+        .iter()
+        .filter(|(_, _, op)| *op == name)
+        .find_map(|&(p, kind, _)| match kind {
+            "fy" => Some((p, p)),
+            "fx" => Some((p, p - 1)),
+            _ => None,
+        })
+}
+
+/// How a term is written, as the options of `write_term/2` say.
+#[derive(Debug, Clone, Copy)]
+pub struct WriteOptions {
+    /// Whether atoms and strings are quoted where they have to be to be read back.
+    pub quoted: bool,
+    /// Whether operators are written in functional notation, as `+(1, 2)`.
+    pub ignore_ops: bool,
+    /// How deeply terms are written before the rest is left out as `...`, where 0 has no limit.
+    pub max_depth: usize,
+    /// The priority of the context the term is written in, above which it is put in parentheses.
+    pub priority: usize,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            quoted: true,
+            ignore_ops: false,
+            max_depth: 0,
+            priority: 1200,
+        }
+    }
+}
+
+/// Displays a term the way `write_term/2` writes it with the options given.
+pub struct Written<'a>(pub &'a Term, pub WriteOptions);
+
+/// Displays a term the way `write/1` does, without quoting atoms and strings.
+pub struct Unquoted<'a>(pub &'a Term);
+
+impl Display for Term {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        Ok(write!(f, "{}", Written(self, WriteOptions::default()))?)
+    }
+}
+
+impl<'a> Display for Unquoted<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        let options = WriteOptions {
+            quoted: false,
+            ..WriteOptions::default()
+        };
+
+        Ok(write!(f, "{}", Written(self.0, options))?)
+    }
+}
+
+impl<'a> Display for Written<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        let Written(t, options) = self;
+        let mut out = String::new();
+        write_term(
+            &mut out,
+            &elide(t, options.max_depth),
+            options,
+            options.priority,
+        );
+
+        Ok(write!(f, "{}", out)?)
+    }
+}
+
+fn is_alphanumeric_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Appends `token` to `out`, with a space between them where they would run together into one
+/// token when read back.
+fn push_token(out: &mut String, token: &str) {
+    if let (Some(last), Some(first)) = (out.chars().last(), token.chars().next()) {
+        if (is_symbol_char(last) && is_symbol_char(first))
+            || (is_alphanumeric_char(last) && is_alphanumeric_char(first))
+        {
+            out.push(' ');
+        }
+    }
+
+    out.push_str(token);
+}
+
+fn write_atom(out: &mut String, name: &str, options: &WriteOptions) {
+    if options.quoted {
+        push_token(out, &quote_atom(name));
+    } else {
+        push_token(out, name);
+    }
+}
+
+/// Writes `t` as the argument of an operator, where an atom that is an operator itself is put in
+/// parentheses so as not to be read as one.
+fn write_operand(out: &mut String, t: &Term, options: &WriteOptions, priority: usize) {
+    match t {
+        Term::Atom(a) if a.arity == 0 && OPERATORS.iter().any(|(_, _, op)| *op == a.name.0) => {
+            out.push('(');
+            write_atom(out, &a.name.0, options);
+            out.push(')');
+        }
+        t => write_term(out, t, options, priority),
+    }
+}
+
+/// Writes `t` in a context of priority `priority`, putting it in parentheses if it is an operator
+/// term of a higher one.
+fn write_term(out: &mut String, t: &Term, options: &WriteOptions, priority: usize) {
+    let a = match t {
+        Term::Var(Var(name, n)) if *n == 0 => return push_token(out, name),
+        Term::Var(Var(name, n)) => return push_token(out, &format!("{}{}", name, n)),
+        Term::Number(n) => return push_token(out, &n.to_string()),
+        Term::String(s) if options.quoted => return push_token(out, &quote_text(s, '"')),
+        Term::String(s) => return out.push_str(s),
+        Term::PartialString(text, tail) => {
+            let codes: Vec<_> = text
+                .chars()
+                .map(|c| Term::Number(Number::Int(c as i64)))
+                .collect();
+            return write_list(out, &codes, tail, options);
+        }
+        Term::Atom(a) => a,
+    };
+
+    let name = &a.name.0[..];
+
+    match &a.args[..] {
+        [] => write_atom(out, name, options),
+        [_, _] if name == "." => {
+            let mut items = Vec::new();
+            let mut tail = t;
+
+            while let Term::Atom(Atom {
+                name: Const(name),
+                args,
+                ..
+            }) = tail
+            {
+                if name != "." || args.len() != 2 {
+                    break;
+                }
+
+                items.push(args[0].clone());
+                tail = &args[1];
+            }
+
+            write_list(out, &items, tail, options)
+        }
+        [Term::Number(Number::Int(i))] if name == "$VAR" && *i >= 0 => {
+            let letter = char::from(b'A' + (i % 26) as u8);
+
+            match i / 26 {
+                0 => push_token(out, &letter.to_string()),
+                suffix => push_token(out, &format!("{}{}", letter, suffix)),
+            }
+        }
+        [arg] if name == "{}" && !options.ignore_ops => {
+            out.push('{');
+            write_term(out, arg, options, 1200);
+            out.push('}');
+        }
+        [x, y] if !options.ignore_ops && infix_op(name).is_some() => {
+            let (p, left, right) = infix_op(name).unwrap_or_default();
+            let open = p > priority;
+
+            if open {
+                out.push('(');
+            }
+
+            write_operand(out, x, options, left);
+
+            if name == "," {
+                out.push_str(", ");
+            } else if p >= 700 || name.starts_with(is_atom_start) {
+                out.push(' ');
+                out.push_str(name);
+                out.push(' ');
+            } else {
+                push_token(out, name);
+            }
+
+            write_operand(out, y, options, right);
+
+            if open {
+                out.push(')');
+            }
+        }
+        [x] if !options.ignore_ops && prefix_op(name).is_some() => {
+            let (p, arg) = prefix_op(name).unwrap_or_default();
+            let open = p > priority;
+
+            if open {
+                out.push('(');
+            }
+
+            push_token(out, name);
+
+            let mut operand = String::new();
+            write_operand(&mut operand, x, options, arg);
+
+            // `- 1` is not the number -1, and `-(a)` would be read as a compound term.
+            if name.starts_with(is_atom_start)
+                || operand.starts_with('(')
+                || matches!(x, Term::Number(_))
+            {
+                out.push(' ');
+            }
+
+            push_token(out, &operand);
+
+            if open {
+                out.push(')');
+            }
+        }
+        args => {
+            write_atom(out, name, options);
+            out.push('(');
+
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+
+                write_term(out, arg, options, 999);
+            }
+
+            out.push(')');
+        }
+    }
+}
+
+fn write_list(out: &mut String, items: &[Term], tail: &Term, options: &WriteOptions) {
+    out.push('[');
+
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+
+        write_term(out, item, options, 999);
+    }
+
+    match tail {
+        Term::PartialString(..) => {
+            let mut rest = String::new();
+            write_term(&mut rest, tail, options, 999);
+            out.push_str(", ");
+            out.push_str(&rest[1..]);
+            return;
+        }
+        t if t.is_nil() => (),
+        t => {
+            out.push('|');
+            write_term(out, t, options, 999);
+        }
+    }
+
+    out.push(']');
+}
+
+impl Display for Var {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        Ok(write!(f, "{}", Term::Var(self.clone()))?)
+    }
+}
+
+impl Display for Const {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        Ok(write!(f, "{}", Term::Atom(Atom::new(&self.0, vec![])))?)
+    }
+}
+
+impl Display for Atom {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        Ok(write!(f, "{}", Term::Atom(self.clone()))?)
+    }
+}
+
+/// Strips the surrounding quotes from a quoted atom or string literal and resolves its escape
+/// sequences.
+pub fn unquote(quoted: &str) -> String {
+    let mut chars = quoted.chars();
+    let quote = chars.next();
+    let mut text = String::with_capacity(quoted.len());
+    let body = chars.as_str();
+    let mut chars = body[..body.len() - 1].chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some('r') => text.push('\r'),
+                Some('0') => text.push('\0'),
+                Some('\n') => (),
+                Some(c) => text.push(c),
+                None => text.push('\\'),
+            },
+            c if Some(c) == quote && chars.peek() == Some(&c) => {
+                chars.next();
+                text.push(c);
+            }
+            c => text.push(c),
+        }
+    }
+
+    text
+}
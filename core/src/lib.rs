@@ -0,0 +1,13 @@
+//! The parts of bfg-prolog that build with `core` and `alloc` alone, re-exported by the main
+//! crate, so that programs can be read into terms and solved on targets without `std`.
+//!
+//! The solver runs the control constructs itself and leaves everything else, such as builtins
+//! that do I/O and the state the main crate keeps for each thread, to the `Host` it runs in.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod ast;
+pub mod solve;
+pub mod unify;
@@ -0,0 +1,931 @@
+//! The solver: resolution of goals against the clauses of a program, with choicepoints, cut,
+//! exceptions and the control constructs. What it cannot do with `alloc` alone, such as builtins
+//! that do I/O or state kept for each thread, it leaves to the `Host` it runs in.
+
+use crate::ast::{self, Assertion, Atom, Clause, Const, Number, Term, Var};
+use crate::unify::{renumber_atom, term_vars, Environment, OccursCheck, UnifyErr};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+pub type KnowledgeBase = Vec<Assertion>;
+
+/// A way a goal can succeed: the bindings it makes and the goals left to run for it.
+pub type Branch<H> = (Environment<H>, Vec<Atom>);
+
+/// The bindings, goals and depth a search goes on from.
+pub type State<H> = (Environment<H>, Clause, usize);
+
+#[derive(Debug, Clone)]
+pub enum SolveErr {
+    NoSolution,
+    Exception(Term),
+}
+
+#[derive(Debug, Clone)]
+pub struct Choicepoint<H> {
+    /// Numbers the choicepoints in the order they are made, which is the order of the stack
+    /// whichever of them a search strategy goes back to first.
+    pub id: usize,
+    pub assertions: Option<KnowledgeBase>,
+    pub environment: Environment<H>,
+    pub clause: Clause,
+    pub depth: usize,
+}
+
+static CHOICEPOINTS: AtomicUsize = AtomicUsize::new(0);
+static FRESH: AtomicUsize = AtomicUsize::new(1);
+
+impl<H: Host> Choicepoint<H> {
+    pub fn new(
+        assertions: Option<KnowledgeBase>,
+        environment: Environment<H>,
+        clause: Clause,
+        depth: usize,
+    ) -> Self {
+        Choicepoint {
+            id: CHOICEPOINTS.fetch_add(1, Ordering::Relaxed),
+            assertions,
+            environment,
+            clause,
+            depth,
+        }
+    }
+}
+
+/// What the solver leaves to the program it is built into. Each default does what a solver with
+/// nothing but `alloc` does: it searches depth first, has no builtins beyond the control
+/// constructs and raises an error for a call to an unknown procedure.
+///
+/// A host is a type with no values, named by `Environment<H>`, so it derives the traits that
+/// environments derive.
+pub trait Host: Sized + Clone + Debug + PartialEq + Eq {
+    /// What unification does with a variable and a term that contains it.
+    fn occurs_check() -> OccursCheck {
+        OccursCheck::False
+    }
+
+    /// Reads more of `t` if it is a term read lazily, such as a code list read from a file,
+    /// returning the error to raise if it cannot be read, or None if `t` is not one.
+    fn expand(_t: &Term) -> Option<Result<Term, Term>> {
+        None
+    }
+
+    /// Writes the bindings of `env` as the answer to a query.
+    fn write_answer(env: &Environment<Self>, f: &mut Formatter) -> FmtResult {
+        let mut bindings: Vec<_> = env
+            .bindings()
+            .iter()
+            .filter(|(x, _)| x.1 == 0 && !x.is_anonymous())
+            .collect();
+        bindings.sort();
+
+        if bindings.is_empty() {
+            return write!(f, "Yes");
+        }
+
+        for (Var(x, _), t) in bindings {
+            write!(f, "\n{} = {}", x, env.substitute_term(t))?;
+        }
+
+        write!(f, " ")
+    }
+
+    /// Takes a number that no renaming apart has taken before.
+    fn fresh() -> usize {
+        FRESH.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Called as a search starts and as it returns, which searches run by builtins nest within.
+    fn enter() {}
+
+    fn leave() {}
+
+    /// Takes the goal to prove next from `goals`, which has the first of them last.
+    fn next_goal(goals: &mut Clause) -> Option<Atom> {
+        goals.pop()
+    }
+
+    /// Takes the choicepoint to go back to from `ch`.
+    fn next_choicepoint(ch: &mut Vec<Choicepoint<Self>>) -> Option<Choicepoint<Self>> {
+        ch.pop()
+    }
+
+    /// A goal to run before the next one, such as one that raises an exception for an interrupt.
+    fn interrupt() -> Option<Atom> {
+        None
+    }
+
+    /// Whether to stop before the next goal as if a solution had been found, for a search that
+    /// is run a few steps at a time.
+    fn pause() -> bool {
+        false
+    }
+
+    /// The goal `a` is run as, such as `a` qualified with the module it is called from.
+    fn qualify(_a: &Atom) -> Option<Atom> {
+        None
+    }
+
+    /// The clauses `a` is resolved with in place of those of the program, such as those of a
+    /// predicate that is local to a thread.
+    fn local_clauses(_a: &Atom) -> Option<KnowledgeBase> {
+        None
+    }
+
+    /// The clauses of `kb` that `a` is resolved with in the order they are tried, which is the
+    /// last first, or None to try them all in the order of the program.
+    fn order_clauses(_a: &Atom, _kb: &[Assertion]) -> Option<KnowledgeBase> {
+        None
+    }
+
+    /// Whether `a` suspends the search as if it had found a solution, as a yield in an engine
+    /// does.
+    fn suspend(_env: &Environment<Self>, _a: &Atom) -> bool {
+        false
+    }
+
+    /// Runs `a` if it is a control construct of the host, returning the state to continue from,
+    /// which is None when `a` fails.
+    fn control(
+        _env: &Environment<Self>,
+        _kb: &[Assertion],
+        _a: &Atom,
+        _ch: &mut Vec<Choicepoint<Self>>,
+        _c: &Clause,
+        _n: usize,
+    ) -> Option<Option<State<Self>>> {
+        None
+    }
+
+    /// Runs `a` if it is a builtin of the host, returning every way it succeeds.
+    fn call(
+        _env: &Environment<Self>,
+        _kb: &[Assertion],
+        _a: &Atom,
+        _n: usize,
+    ) -> Option<Vec<Branch<Self>>> {
+        None
+    }
+
+    /// Whether a goal `depth` steps into the proof may be resolved.
+    fn admits(_depth: usize) -> bool {
+        true
+    }
+
+    /// Whether `a` repeats one of the goals it was called from, and fails for it.
+    fn loops(_env: &Environment<Self>, _a: &Atom, _c: &Clause) -> bool {
+        false
+    }
+
+    /// Whether the goals resolved with a clause are followed by `$exit/1`, for `loops` to tell
+    /// when a call is done.
+    fn loop_check() -> bool {
+        false
+    }
+
+    /// The clauses of a library that defines `a`, which the program does not, loading the
+    /// library first if it is not yet.
+    fn autoload(_a: &Atom) -> Option<KnowledgeBase> {
+        None
+    }
+
+    /// Whether a call to `a`, which has no clauses, fails rather than raising an existence
+    /// error.
+    fn unknown_fails(_a: &Atom) -> bool {
+        false
+    }
+
+    /// Called for each clause a goal is resolved with.
+    fn enter_clause(_clause: &Assertion) {}
+
+    /// Whether `a` calls a predicate declared to succeed exactly once.
+    fn is_det(_a: &Atom) -> bool {
+        false
+    }
+}
+
+/// Calls `H::leave` when a search returns, however it returns.
+struct Level<H: Host>(core::marker::PhantomData<H>);
+
+impl<H: Host> Level<H> {
+    fn enter() -> Self {
+        H::enter();
+        Level(core::marker::PhantomData)
+    }
+}
+
+impl<H: Host> Drop for Level<H> {
+    fn drop(&mut self) {
+        H::leave();
+    }
+}
+
+/// The number the next choicepoint made is given, which a cut back to it drops along with every
+/// later one.
+pub fn barrier() -> usize {
+    CHOICEPOINTS.load(Ordering::Relaxed)
+}
+
+/// Where the choicepoint numbered `barrier` is in `ch`, or would be if it is gone.
+pub fn position<H>(ch: &[Choicepoint<H>], barrier: usize) -> usize {
+    ch.partition_point(|c| c.id < barrier)
+}
+
+impl<H: Host> Environment<H> {
+    fn reduce_atom(
+        &self,
+        a: &Atom,
+        asrl: &[Assertion],
+    ) -> Option<(KnowledgeBase, Environment<H>, Clause)> {
+        // Tables run the clauses of a tabled predicate through `$tabled/1`.
+        let a = match (&a.name.0[..], &a.args[..]) {
+            ("$tabled", [Term::Atom(a)]) => a,
+            _ => a,
+        };
+        let mut asrl = asrl.to_vec();
+        let k = H::fresh();
+
+        while let Some(clause) = asrl.pop() {
+            let (b, lst) = (&clause.head, &clause.clause);
+            let next_env = self.unify_atoms(a, &renumber_atom(k, b));
+
+            match next_env {
+                Ok(next_env) => {
+                    H::enter_clause(&clause);
+                    return Some((
+                        asrl,
+                        next_env,
+                        lst.iter().map(|a| renumber_atom(k, a)).collect(),
+                    ));
+                }
+                Err(UnifyErr::NoUnify) => {
+                    continue;
+                }
+                Err(UnifyErr::Error(formal)) => {
+                    let error = error(formal);
+                    return Some((
+                        Vec::new(),
+                        self.clone(),
+                        vec![Atom::new("throw", vec![error])],
+                    ));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Proves the goals `c` against `kb`, going back to the choicepoints `ch` when a branch
+    /// fails, and returns the bindings of the first solution with the choicepoints left.
+    pub fn solve(
+        self,
+        mut ch: Vec<Choicepoint<H>>,
+        kb: &[Assertion],
+        asrl: Option<KnowledgeBase>,
+        mut c: Clause,
+        mut n: usize,
+    ) -> Result<(Environment<H>, Vec<Choicepoint<H>>), SolveErr> {
+        let mut env = self;
+        let mut next_asrl = asrl;
+        let _level = Level::<H>::enter();
+
+        while let Some(a) = H::next_goal(&mut c) {
+            if let Some(handler) = H::interrupt() {
+                c.push(a);
+                c.push(handler);
+                continue;
+            }
+
+            if H::pause() {
+                // Stops as if a solution had been found, leaving the goal to go on from as the
+                // last choicepoint.
+                c.push(a);
+                ch.push(Choicepoint::new(next_asrl, env.clone(), c, n));
+
+                return Ok((env, ch));
+            }
+
+            let a = H::qualify(&a).unwrap_or(a);
+            let Atom {
+                name: Const(ref atom_name),
+                arity,
+                ..
+            } = a;
+
+            let assertions = next_asrl.take();
+            let local = match assertions {
+                None => H::local_clauses(&a),
+                Some(_) => None,
+            };
+            let ordered = match (&assertions, &local) {
+                (None, None) => H::order_clauses(&a, kb),
+                _ => None,
+            };
+            let asrl = match (&assertions, &local, &ordered) {
+                (Some(assertions), _, _) => &assertions[..],
+                (None, Some(local), _) => &local[..],
+                (None, None, Some(ordered)) => &ordered[..],
+                (None, None, None) => kb,
+            };
+
+            if atom_name == "throw" && arity == 1 {
+                let ball = match env.substitute_term(&a.args[0]) {
+                    Term::Var(_) => error_in(&a, atom("instantiation_error")),
+                    ball => rename_fresh::<H>(&ball),
+                };
+
+                let (next_env, next_c, next_n) = unwind(&mut ch, ball, n)?;
+                next_asrl = None;
+                env = next_env;
+                c = next_c;
+                n = next_n;
+
+                continue;
+            }
+
+            if H::suspend(&env, &a) {
+                // Suspends the search as if it had found a solution, leaving the goals after
+                // the suspending one as the choicepoint that the next answer is looked for from.
+                ch.push(Choicepoint::new(None, env.clone(), c, n));
+
+                return Ok((env, ch));
+            }
+
+            let next = control(&env, kb, &a, &mut ch, &c, n)
+                .or_else(|| H::control(&env, kb, &a, &mut ch, &c, n));
+
+            if let Some(next) = next {
+                match next {
+                    None => {
+                        let (next_env, next_c, next_n) = backtrack(&mut ch, &mut next_asrl)?;
+                        env = next_env;
+                        c = next_c;
+                        n = next_n;
+                    }
+                    Some((next_env, next_c, next_n)) => {
+                        env = next_env;
+                        c = next_c;
+                        n = next_n;
+                    }
+                }
+
+                continue;
+            }
+
+            if let Some(branches) = H::call(&env, kb, &a, n) {
+                let mut branches = branches.into_iter();
+
+                match branches.next() {
+                    None => {
+                        let (next_env, next_c, next_n) = backtrack(&mut ch, &mut next_asrl)?;
+                        env = next_env;
+                        c = next_c;
+                        n = next_n;
+                    }
+                    Some((next_env, d)) => {
+                        let alternatives: Vec<_> = branches.collect();
+
+                        ch.extend(alternatives.into_iter().rev().map(|(mut alt_env, alt_d)| {
+                            let woken = alt_env.take_woken();
+                            let clause = push_goals(push_goals(c.clone(), &alt_d), &woken);
+
+                            Choicepoint::new(None, alt_env, clause, n + 1)
+                        }));
+
+                        env = next_env;
+                        let woken = env.take_woken();
+                        c = push_goals(push_goals(c, &d), &woken);
+                        n += 1;
+                    }
+                }
+
+                continue;
+            }
+
+            // A goal tried again with the clauses left for it was admitted when it was first tried.
+            let admitted = assertions.is_some() || H::admits(n);
+
+            let reduced = if !admitted || assertions.is_none() && H::loops(&env, &a, &c) {
+                None
+            } else if assertions.is_none() && local.is_none() && !is_defined(kb, &a) {
+                // A procedure with no clauses at all raises an error, where one whose clauses
+                // do not match fails, unless a library defines it or the host says to fail
+                // instead.
+                match H::autoload(&a) {
+                    Some(library) => env.reduce_atom(&a, &library),
+                    None if !H::unknown_fails(&a) => {
+                        let formal = ast::op("existence_error", atom("procedure"), indicator(&a));
+                        c.push(Atom::new("throw", vec![error_in(&a, formal)]));
+                        continue;
+                    }
+                    None => None,
+                }
+            } else {
+                env.reduce_atom(&a, asrl)
+            };
+
+            // A call to a predicate declared `det` leaves a choicepoint that raises an error if
+            // it fails, which its exit drops again.
+            let det = admitted && assertions.is_none() && H::is_det(&a);
+
+            match reduced {
+                None if det => {
+                    let error = determinism_error(&a, "fail");
+                    c.push(Atom::new("throw", vec![error]));
+                }
+                None => {
+                    let (next_env, next_c, next_n) = backtrack(&mut ch, &mut next_asrl)?;
+                    env = next_env;
+                    c = next_c;
+                    n = next_n;
+                }
+                Some((ch_asrl, next_env, d)) => {
+                    if det {
+                        let error = determinism_error(&a, "fail");
+
+                        let frame = Choicepoint::new(
+                            None,
+                            env.clone(),
+                            push_goals(c.clone(), &[Atom::new("throw", vec![error])]),
+                            n,
+                        );
+                        let id = frame.id;
+
+                        ch.push(frame);
+                        c.push(Atom::new(
+                            "$det_exit",
+                            vec![Term::Number(Number::Int(id as i64)), Term::Atom(a.clone())],
+                        ));
+                    }
+
+                    let barrier = barrier();
+                    let d: Clause = d.iter().map(|g| replace_cut(g, barrier)).collect();
+                    let exit =
+                        H::loop_check().then(|| Atom::new("$exit", vec![Term::Atom(a.clone())]));
+
+                    if !ch_asrl.is_empty() {
+                        let mut ch_clause = c.clone();
+                        ch_clause.push(a);
+
+                        ch.push(Choicepoint::new(Some(ch_asrl), env, ch_clause, n));
+                    }
+
+                    c.extend(exit);
+                    env = next_env;
+                    let woken = env.take_woken();
+                    c = push_goals(push_goals(c, &d), &woken);
+                    n += 1;
+                }
+            }
+        }
+
+        Ok((env, ch))
+    }
+}
+
+fn backtrack<H: Host>(
+    ch: &mut Vec<Choicepoint<H>>,
+    next_asrl: &mut Option<KnowledgeBase>,
+) -> Result<State<H>, SolveErr> {
+    match H::next_choicepoint(ch) {
+        None => Err(SolveErr::NoSolution),
+        Some(Choicepoint {
+            assertions: ch_asrl,
+            environment: env,
+            clause: gs,
+            depth: n,
+            ..
+        }) => {
+            *next_asrl = ch_asrl;
+            Ok((env, gs, n))
+        }
+    }
+}
+
+/// Pops choicepoints until one left by a `catch/3` whose catcher unifies with `ball`, returning
+/// the state to run its recovery goal from, or the exception if nothing catches it.
+fn unwind<H: Host>(
+    ch: &mut Vec<Choicepoint<H>>,
+    ball: Term,
+    n: usize,
+) -> Result<State<H>, SolveErr> {
+    while let Some(Choicepoint {
+        environment: env,
+        clause: mut gs,
+        ..
+    }) = ch.pop()
+    {
+        if let Some(Atom { name, args, .. }) = gs.pop() {
+            if name.0 != "$catch" || args.len() != 2 {
+                continue;
+            }
+
+            if let Ok(env) = env.unify_terms(&args[0], &ball) {
+                gs.push(Atom::new("call", vec![args[1].clone()]));
+                return Ok((env, gs, n + 1));
+            }
+        }
+    }
+
+    Err(SolveErr::Exception(ball))
+}
+
+/// Renames the goal a `catch/3` frame recovers with, which `unwind` only stops at as `$catch/2`.
+fn rename_catch<H: Host>(frame: &mut Choicepoint<H>, name: &str) {
+    if let Some(goal) = frame.clause.last_mut() {
+        *goal = Atom::new(name, goal.args.clone());
+    }
+}
+
+/// The predicate indicator `Name/Arity` of the procedure `a` calls, or `Module:Name/Arity` for
+/// a goal qualified with a module.
+pub fn indicator(a: &Atom) -> Term {
+    match (&a.name.0[..], &a.args[..]) {
+        (":", [module, Term::Atom(goal)]) => ast::op(":", module.clone(), indicator(goal)),
+        (name, args) => ast::op(
+            "/",
+            atom(name),
+            Term::Number(Number::Int(args.len() as i64)),
+        ),
+    }
+}
+
+/// Whether `kb` has clauses for the procedure `a` calls.
+pub fn is_defined(kb: &[Assertion], a: &Atom) -> bool {
+    let a = match (&a.name.0[..], &a.args[..]) {
+        ("$tabled", [Term::Atom(a)]) => a,
+        _ => a,
+    };
+
+    kb.iter().any(|b| {
+        b.head.name == a.name
+            && b.head.arity == a.arity
+            && (a.name.0 != ":" || indicator(&b.head) == indicator(a))
+    })
+}
+
+pub fn push_goals(mut c: Clause, goals: &[Atom]) -> Clause {
+    c.extend(goals.iter().rev().cloned());
+    c
+}
+
+fn cut(barrier: usize) -> Atom {
+    Atom::new("$cut", vec![Term::Number(Number::Int(barrier as i64))])
+}
+
+/// Makes every cut that is transparent in `goal` cut back to the choicepoint numbered
+/// `barrier`.
+pub fn replace_cut(goal: &Atom, barrier: usize) -> Atom {
+    let transparent = match (&goal.name.0[..], goal.arity) {
+        ("!", 0) => return cut(barrier),
+        (",", 2) | (";", 2) => 0,
+        ("->", 2) | ("*->", 2) => 1,
+        _ => return goal.clone(),
+    };
+
+    let args = goal
+        .args
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| match arg {
+            Term::Atom(a) if i >= transparent => Term::Atom(replace_cut(a, barrier)),
+            t => t.clone(),
+        })
+        .collect();
+
+    Atom::new(&goal.name.0, args)
+}
+
+fn fail() -> Atom {
+    Atom::new("fail", vec![])
+}
+
+fn atom(name: &str) -> Term {
+    Term::Atom(Atom::new(name, vec![]))
+}
+
+/// Appends `extra` to the arguments of `goal`, within the module qualification of `goal` if it
+/// has one, or returns None if `goal` qualifies something other than a goal.
+pub fn add_args(goal: Atom, extra: &[Term]) -> Option<Atom> {
+    match (&goal.name.0[..], &goal.args[..]) {
+        (":", [module, Term::Atom(inner)]) => Some(Atom::new(
+            ":",
+            vec![module.clone(), Term::Atom(add_args(inner.clone(), extra)?)],
+        )),
+        (":", _) => None,
+        _ => {
+            let mut args = goal.args;
+            args.extend_from_slice(extra);
+
+            Some(Atom::new(&goal.name.0, args))
+        }
+    }
+}
+
+/// The ISO error term `error(Formal, _)`.
+pub fn error(formal: Term) -> Term {
+    Term::Atom(Atom::new(
+        "error",
+        vec![formal, Term::Var(Var(String::from("_"), 0))],
+    ))
+}
+
+/// The ISO error term `error(Formal, context(Name/Arity, _))` raised by calling `goal`.
+pub fn error_in(goal: &Atom, formal: Term) -> Term {
+    let indicator = ast::op(
+        "/",
+        Term::Atom(Atom::new(&goal.name.0, vec![])),
+        Term::Number(Number::Int(goal.arity as i64)),
+    );
+    let context = Atom::new(
+        "context",
+        vec![indicator, Term::Var(Var(String::from("_"), 0))],
+    );
+
+    Term::Atom(Atom::new("error", vec![formal, Term::Atom(context)]))
+}
+
+/// The error a call to the `det` predicate `goal` raises for doing what `observed` says, `fail` or
+/// `nondet`, rather than succeeding once.
+pub fn determinism_error(goal: &Atom, observed: &str) -> Term {
+    let formal = Term::Atom(Atom::new(
+        "determinism_error",
+        vec![
+            indicator(goal),
+            atom("det"),
+            atom(observed),
+            atom("property_declaration"),
+        ],
+    ));
+
+    error_in(goal, formal)
+}
+
+/// Runs the control construct `a`, returning None if `a` is not one, or the state to continue
+/// from, which is None when `a` fails.
+fn control<H: Host>(
+    env: &Environment<H>,
+    kb: &[Assertion],
+    a: &Atom,
+    ch: &mut Vec<Choicepoint<H>>,
+    c: &Clause,
+    n: usize,
+) -> Option<Option<State<H>>> {
+    let args = &a.args;
+    let proceed = |goals: Clause| Some(Some((env.clone(), push_goals(c.clone(), &goals), n)));
+    let raise = |formal| proceed(vec![Atom::new("throw", vec![error_in(a, formal)])]);
+
+    match (&a.name.0[..], a.arity) {
+        ("true", 0) | ("!", 0) | ("$exit", 1) => proceed(vec![]),
+        ("fail", 0) | ("false", 0) | ("$catch", 2) | ("$exited_catch", 2) => Some(None),
+        ("$cut", 1) => {
+            if let Term::Number(Number::Int(barrier)) = args[0] {
+                ch.truncate(position(ch, barrier as usize));
+            }
+
+            proceed(vec![])
+        }
+        ("$soft_cut", 1) => {
+            if let Term::Number(Number::Int(barrier)) = args[0] {
+                let i = position(ch, barrier as usize);
+
+                if let Some(choicepoint) = ch.get_mut(i).filter(|c| c.id == barrier as usize) {
+                    choicepoint.clause = vec![fail()];
+                }
+            }
+
+            proceed(vec![])
+        }
+        (",", 2) => {
+            let mut goals = ast::goals(args[0].clone());
+            goals.extend(ast::goals(args[1].clone()));
+
+            proceed(goals)
+        }
+        (";", 2) => {
+            let frame = Choicepoint::new(
+                None,
+                env.clone(),
+                push_goals(c.clone(), &ast::goals(args[1].clone())),
+                n,
+            );
+            let barrier = frame.id;
+            ch.push(frame);
+
+            match &args[0] {
+                Term::Atom(Atom {
+                    name: Const(name),
+                    args: branch,
+                    ..
+                }) if (name == "->" || name == "*->") && branch.len() == 2 => {
+                    let mut goals = ast::goals(branch[0].clone());
+                    goals.push(if name == "->" {
+                        cut(barrier)
+                    } else {
+                        Atom::new("$soft_cut", vec![Term::Number(Number::Int(barrier as i64))])
+                    });
+                    goals.extend(ast::goals(branch[1].clone()));
+
+                    proceed(goals)
+                }
+                t => proceed(ast::goals(t.clone())),
+            }
+        }
+        ("->", 2) => {
+            let mut goals = ast::goals(args[0].clone());
+            goals.push(cut(barrier()));
+            goals.extend(ast::goals(args[1].clone()));
+
+            proceed(goals)
+        }
+        ("*->", 2) => {
+            let mut goals = ast::goals(args[0].clone());
+            goals.extend(ast::goals(args[1].clone()));
+
+            proceed(goals)
+        }
+        ("catch", 3) => {
+            let mut goals = c.clone();
+            goals.push(Atom::new("$catch", vec![args[1].clone(), args[2].clone()]));
+
+            let frame = Choicepoint::new(None, env.clone(), goals, n);
+            let barrier = frame.id;
+            ch.push(frame);
+
+            proceed(vec![
+                Atom::new("call", vec![args[0].clone()]),
+                Atom::new(
+                    "$catch_exit",
+                    vec![Term::Number(Number::Int(barrier as i64))],
+                ),
+            ])
+        }
+        ("$catch_exit", 1) => {
+            // A goal that leaves no choicepoints behind can no longer raise to its catch/3. One
+            // that leaves some cannot either until it is backtracked into, which the choicepoint
+            // pushed here marks by making the catch active again.
+            if let Term::Number(Number::Int(barrier)) = args[0] {
+                let i = position(ch, barrier as usize);
+                let active = ch
+                    .get(i)
+                    .filter(|frame| frame.id == barrier as usize)
+                    .and_then(|frame| frame.clause.last())
+                    .is_some_and(|goal| goal.name.0 == "$catch");
+
+                if active && ch.len() == i + 1 {
+                    ch.pop();
+                } else if active {
+                    rename_catch(&mut ch[i], "$exited_catch");
+
+                    let redo = Atom::new("$catch_redo", vec![args[0].clone()]);
+                    ch.push(Choicepoint::new(None, env.clone(), vec![redo], n));
+                }
+            }
+
+            proceed(vec![])
+        }
+        ("$catch_redo", 1) => {
+            if let Term::Number(Number::Int(barrier)) = args[0] {
+                let i = position(ch, barrier as usize);
+
+                if let Some(frame) = ch.get_mut(i).filter(|frame| frame.id == barrier as usize) {
+                    rename_catch(frame, "$catch");
+                }
+            }
+
+            Some(None)
+        }
+        ("\\+", 1) | ("not", 1) => {
+            let frame = Choicepoint::new(None, env.clone(), c.clone(), n);
+            let barrier = frame.id;
+            ch.push(frame);
+
+            let goal = Atom::new("call", vec![args[0].clone()]);
+            proceed(vec![goal, cut(barrier), fail()])
+        }
+        ("call", arity) if arity > 0 => {
+            let goal = match env.substitute_term(&args[0]) {
+                Term::Atom(goal) => goal,
+                Term::Var(_) => return raise(atom("instantiation_error")),
+                t => {
+                    return raise(Term::Atom(Atom::new(
+                        "type_error",
+                        vec![atom("callable"), t],
+                    )))
+                }
+            };
+
+            let goal = match add_args(goal, &args[1..]) {
+                Some(goal) => replace_cut(&goal, barrier()),
+                None => return Some(None),
+            };
+
+            proceed(vec![goal])
+        }
+        ("findall", 3) => {
+            let goal = Atom::new("call", vec![args[1].clone()]);
+            let solutions = match solve_all(env, kb, goal, n) {
+                Ok(solutions) => solutions,
+                Err(ball) => return proceed(vec![Atom::new("throw", vec![ball])]),
+            };
+            let results = solutions
+                .iter()
+                .map(|solution| rename_fresh::<H>(&solution.substitute_term(&args[0])))
+                .collect();
+
+            match env
+                .clone()
+                .unify_terms(&args[2], &Term::list(results, Term::nil()))
+            {
+                Ok(env) => Some(Some((env, c.clone(), n + 1))),
+                Err(_) => Some(None),
+            }
+        }
+        ("forall", 2) => {
+            let violation = ast::op(
+                ",",
+                args[0].clone(),
+                Term::Atom(Atom::new("\\+", vec![args[1].clone()])),
+            );
+            proceed(vec![Atom::new("\\+", vec![violation])])
+        }
+        _ => None,
+    }
+}
+
+/// Collects the bindings of every solution of `goal`, or the exception it raises.
+pub fn solve_all<H: Host>(
+    env: &Environment<H>,
+    kb: &[Assertion],
+    goal: Atom,
+    n: usize,
+) -> Result<Vec<Environment<H>>, Term> {
+    let mut solutions = Vec::new();
+    let mut s = env.clone().solve(Vec::new(), kb, None, vec![goal], n);
+
+    loop {
+        let (env, mut ch) = match s {
+            Ok(solution) => solution,
+            Err(SolveErr::NoSolution) => break,
+            Err(SolveErr::Exception(ball)) => return Err(ball),
+        };
+        solutions.push(env);
+
+        s = match ch.pop() {
+            None => break,
+            Some(Choicepoint {
+                assertions,
+                environment,
+                clause,
+                depth,
+                ..
+            }) => environment.solve(ch, kb, assertions, clause, depth),
+        };
+    }
+
+    Ok(solutions)
+}
+
+/// Finds the first solution of `goal` against `kb`, returning the bindings it makes.
+pub fn solve_once<H: Host>(kb: &[Assertion], goal: Atom) -> Option<Environment<H>> {
+    Environment::new()
+        .solve(Vec::new(), kb, None, vec![goal], 1)
+        .ok()
+        .map(|(env, _)| env)
+}
+
+/// Renames the variables of a term copied out of a finished search or a store apart from every
+/// other variable, keeping the ones that were distinct apart from each other.
+pub fn rename_fresh<H: Host>(t: &Term) -> Term {
+    let mut vars = Vec::new();
+    term_vars(t, &mut vars);
+    let renamed = vars
+        .into_iter()
+        .map(|x| {
+            let y = Var(x.0.clone(), H::fresh());
+            (x, y)
+        })
+        .collect();
+
+    rename_apart(t, &renamed)
+}
+
+/// Renames each variable of `t` as `renamed` pairs it, keeping its name and taking a new number.
+fn rename_apart(t: &Term, renamed: &BTreeMap<Var, Var>) -> Term {
+    match t {
+        Term::Var(x) => Term::Var(renamed.get(x).cloned().unwrap_or_else(|| x.clone())),
+        Term::Atom(a) => Term::Atom(Atom::new(
+            &a.name.0,
+            a.args.iter().map(|t| rename_apart(t, renamed)).collect(),
+        )),
+        Term::PartialString(text, tail) => {
+            Term::PartialString(text.clone(), Box::new(rename_apart(tail, renamed)))
+        }
+        t => t.clone(),
+    }
+}
@@ -0,0 +1,427 @@
+//! Bindings and unification, with the occurs check, attributed variables and partial strings.
+
+use crate::ast::{Atom, Const, Number, Term, Var};
+use crate::solve::Host;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::marker::PhantomData;
+use hashbrown::HashMap;
+
+/// The bindings of a branch of the search, with the attributes of its unbound variables and the
+/// goals that binding attributed variables has woken. `H` is the host the solver runs in, which
+/// says what unification does that depends on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Environment<H> {
+    bindings: HashMap<Var, Term>,
+    attributes: HashMap<Var, BTreeMap<String, Term>>,
+    woken: Vec<Atom>,
+    host: PhantomData<H>,
+}
+
+#[derive(Debug, Clone)]
+pub enum UnifyErr {
+    NoUnify,
+    /// Unifying raises the formal error, as binding a variable to a term that contains it does.
+    Error(Term),
+}
+
+/// What unification does with a variable and a term that contains it, as the `occurs_check`
+/// flag says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OccursCheck {
+    /// Binds the variable without searching the term for it, which makes a cyclic term of one
+    /// that contains it.
+    False,
+    /// Fails.
+    True,
+    /// Raises `occurs_check(Var, Term)`.
+    Error,
+}
+
+impl<H: Host> Display for Environment<H> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        H::write_answer(self, f)
+    }
+}
+
+impl<H: Host> Default for Environment<H> {
+    fn default() -> Self {
+        Environment::new()
+    }
+}
+
+impl<H: Host> Environment<H> {
+    pub fn new() -> Self {
+        Environment {
+            bindings: HashMap::new(),
+            attributes: HashMap::new(),
+            woken: Vec::new(),
+            host: PhantomData,
+        }
+    }
+
+    /// The variables bound in this branch, each with the term it is bound to.
+    pub fn bindings(&self) -> &HashMap<Var, Term> {
+        &self.bindings
+    }
+
+    /// Binds `x` to `t` as it is, without waking the attributes of `x`, for naming the variables
+    /// of an answer.
+    pub fn bind(&mut self, x: Var, t: Term) {
+        self.bindings.insert(x, t);
+    }
+
+    /// Binds `x` to `t`, scheduling the wakeup of any attributes `x` carries. An attributed
+    /// variable unified with a plain one keeps its attributes by having the plain one bound to it.
+    pub fn insert(&mut self, x: Var, t: Term) {
+        let (x, t) = match t {
+            Term::Var(y)
+                if self.attributes.contains_key(&x) && !self.attributes.contains_key(&y) =>
+            {
+                (y, Term::Var(x))
+            }
+            t => (x, t),
+        };
+
+        if let Some(attributes) = self.attributes.remove(&x) {
+            for (module, value) in attributes {
+                let module = Term::Atom(Atom::new(&module, vec![]));
+                self.woken
+                    .push(Atom::new("$wakeup", vec![module, value, t.clone()]));
+            }
+        }
+
+        self.bindings.insert(x, t);
+    }
+
+    /// The attributes of `x`, by module.
+    pub fn attributes(&self, x: &Var) -> Option<&BTreeMap<String, Term>> {
+        self.attributes.get(x)
+    }
+
+    pub fn get_attr(&self, x: &Var, module: &str) -> Option<&Term> {
+        self.attributes
+            .get(x)
+            .and_then(|attributes| attributes.get(module))
+    }
+
+    pub fn put_attr(&mut self, x: Var, module: &str, value: Term) {
+        self.attributes
+            .entry(x)
+            .or_default()
+            .insert(String::from(module), value);
+    }
+
+    pub fn del_attr(&mut self, x: &Var, module: &str) {
+        if let Some(attributes) = self.attributes.get_mut(x) {
+            attributes.remove(module);
+
+            if attributes.is_empty() {
+                self.attributes.remove(x);
+            }
+        }
+    }
+
+    pub fn attributed_vars(&self) -> Vec<&Var> {
+        let mut vars: Vec<_> = self.attributes.keys().collect();
+        vars.sort();
+        vars
+    }
+
+    pub fn take_woken(&mut self) -> Vec<Atom> {
+        core::mem::take(&mut self.woken)
+    }
+
+    pub fn lookup(&self, x: &Var) -> Term {
+        match self.bindings.get(x) {
+            Some(t) => t.clone(),
+            None => Term::Var(x.clone()),
+        }
+    }
+
+    pub fn substitute_term(&self, t: &Term) -> Term {
+        let mut t = t.clone();
+        let mut temp = t;
+
+        loop {
+            match temp {
+                Term::Var(x) => {
+                    t = self.lookup(&x);
+
+                    if Term::Var(x) == t {
+                        return t;
+                    }
+
+                    temp = t;
+                }
+                Term::Atom(mut a) => {
+                    let mut next_atoms = Vec::new();
+                    self.substitute_atom(&mut a, &mut next_atoms);
+
+                    while let Some(a) = next_atoms.pop() {
+                        self.substitute_atom(a, &mut next_atoms);
+                    }
+
+                    return Term::Atom(a);
+                }
+                Term::PartialString(text, tail) => {
+                    return Term::partial_string(&text, self.substitute_term(&tail));
+                }
+                _ => return temp,
+            }
+        }
+    }
+
+    fn substitute_atom<'a>(&self, a: &'a mut Atom, next: &mut Vec<&'a mut Atom>) {
+        for arg in &mut a.args {
+            match arg {
+                ref t @ Term::Var(_) | ref t @ Term::PartialString(..) => {
+                    *arg = self.substitute_term(t);
+                }
+                Term::Atom(ref mut a) => next.push(a),
+                _ => (),
+            }
+        }
+    }
+
+    pub fn unify_terms(self, t1: &Term, t2: &Term) -> Result<Self, UnifyErr> {
+        match (self.substitute_term(t1), self.substitute_term(t2)) {
+            (ref t1, ref t2) if t1 == t2 => Ok(self),
+            (Term::Var(y), t) | (t, Term::Var(y)) => {
+                // The term is only searched for the variable when the occurs check is on, as
+                // searching it on every binding is what makes the check slow.
+                let check = H::occurs_check();
+
+                if check != OccursCheck::False && occurs(&y, &t) {
+                    return Err(match check {
+                        OccursCheck::Error => UnifyErr::Error(occurs_check_error(y, t)),
+                        _ => UnifyErr::NoUnify,
+                    });
+                }
+
+                let mut env = self;
+                env.insert(y, t);
+
+                Ok(env)
+            }
+            (
+                Term::Atom(Atom {
+                    name: ref c1,
+                    args: ref ts1,
+                    ..
+                }),
+                Term::Atom(Atom {
+                    name: ref c2,
+                    args: ref ts2,
+                    ..
+                }),
+            ) if c1 == c2 => {
+                let mut next_atoms = Vec::new();
+                let mut env = self.unify_list_level(ts1, ts2, &mut next_atoms)?;
+
+                while let Some((a1, a2)) = next_atoms.pop() {
+                    if a1.name != a2.name {
+                        env = env.unify_terms(&Term::Atom(a1.clone()), &Term::Atom(a2.clone()))?;
+                        continue;
+                    }
+
+                    let next_env = env.unify_list_level(&a1.args, &a2.args, &mut next_atoms)?;
+                    env = next_env;
+                }
+
+                Ok(env)
+            }
+            (Term::PartialString(text, tail), t) | (t, Term::PartialString(text, tail)) => {
+                self.unify_partial_string(&text, &tail, t)
+            }
+            (t1, t2) => match (H::expand(&t1), H::expand(&t2)) {
+                (Some(t1), _) => self.unify_terms(&t1.map_err(UnifyErr::Error)?, &t2),
+                (None, Some(t2)) => self.unify_terms(&t1, &t2.map_err(UnifyErr::Error)?),
+                (None, None) => Err(UnifyErr::NoUnify),
+            },
+        }
+    }
+
+    pub fn dereference(&self, t: &Term) -> Term {
+        let mut t = t.clone();
+
+        while let Term::Var(x) = t {
+            t = self.lookup(&x);
+
+            if Term::Var(x) == t {
+                break;
+            }
+        }
+
+        t
+    }
+
+    fn unify_partial_string(self, text: &str, tail: &Term, other: Term) -> Result<Self, UnifyErr> {
+        let mut env = self;
+        let mut rest = text;
+        let mut other = other;
+
+        while let Some(c) = rest.chars().next() {
+            other = match env.dereference(&other) {
+                Term::Atom(Atom {
+                    name: Const(ref name),
+                    ref args,
+                    ..
+                }) if name == "." && args.len() == 2 => {
+                    env = env.unify_terms(&Term::Number(Number::Int(c as i64)), &args[0])?;
+                    rest = &rest[c.len_utf8()..];
+                    args[1].clone()
+                }
+                Term::PartialString(ref other_text, ref other_tail) => {
+                    let common = rest
+                        .char_indices()
+                        .zip(other_text.chars())
+                        .find(|((_, c1), c2)| c1 != c2)
+                        .map_or(rest.len().min(other_text.len()), |((i, _), _)| i);
+
+                    if common == 0 {
+                        return Err(UnifyErr::NoUnify);
+                    } else if common == rest.len() {
+                        let other =
+                            Term::partial_string(&other_text[common..], *other_tail.clone());
+                        return env.unify_terms(tail, &other);
+                    } else if common == other_text.len() {
+                        rest = &rest[common..];
+                        *other_tail.clone()
+                    } else {
+                        return Err(UnifyErr::NoUnify);
+                    }
+                }
+                t @ Term::Var(_) => {
+                    return env.unify_terms(&t, &Term::partial_string(rest, tail.clone()))
+                }
+                t => H::expand(&t)
+                    .ok_or(UnifyErr::NoUnify)?
+                    .map_err(UnifyErr::Error)?,
+            };
+        }
+
+        env.unify_terms(tail, &other)
+    }
+
+    fn unify_list_level<'a>(
+        self,
+        l1: &'a [Term],
+        l2: &'a [Term],
+        next_atoms: &mut Vec<(&'a Atom, &'a Atom)>,
+    ) -> Result<Self, UnifyErr> {
+        if l1.len() != l2.len() {
+            return Err(UnifyErr::NoUnify);
+        }
+
+        let terms = l1.iter().zip(l2.iter());
+        let mut env = self;
+
+        for (t1, t2) in terms {
+            if let (Term::Atom(ref a1), Term::Atom(ref a2)) = (t1, t2) {
+                next_atoms.push((a1, a2));
+            } else {
+                env = env.unify_terms(t1, t2)?;
+            }
+        }
+
+        Ok(env)
+    }
+
+    pub fn unify_lists(&self, l1: &[Term], l2: &[Term]) -> Result<Self, UnifyErr> {
+        if l1.len() != l2.len() {
+            return Err(UnifyErr::NoUnify);
+        }
+
+        l1.iter()
+            .zip(l2.iter())
+            .try_fold(self.clone(), |env, (t1, t2)| env.unify_terms(t1, t2))
+    }
+
+    pub fn unify_atoms(&self, a1: &Atom, a2: &Atom) -> Result<Self, UnifyErr> {
+        if a1.name == a2.name {
+            return self.unify_lists(&a1.args, &a2.args);
+        }
+
+        Err(UnifyErr::NoUnify)
+    }
+}
+
+/// The formal error `occurs_check(Var, Term)` raised for binding `x` to `t`, which contains it.
+pub fn occurs_check_error(x: Var, t: Term) -> Term {
+    Term::Atom(Atom::new("occurs_check", vec![Term::Var(x), t]))
+}
+
+pub fn occurs(x: &Var, t: &Term) -> bool {
+    match t {
+        Term::Var(y) => x == y,
+        Term::Atom(a) => occurs_atom(x, a),
+        Term::PartialString(_, tail) => occurs(x, tail),
+        _ => false,
+    }
+}
+
+fn occurs_atom(x: &Var, a: &Atom) -> bool {
+    let mut atom_queue = vec![a];
+
+    while let Some(a) = atom_queue.pop() {
+        for t in &a.args {
+            match t {
+                Term::Var(y) if x == y => return true,
+                Term::Atom(ref q) => atom_queue.push(q),
+                Term::PartialString(_, tail) if occurs(x, tail) => return true,
+                _ => (),
+            }
+        }
+    }
+
+    false
+}
+
+pub fn term_vars(t: &Term, vars: &mut Vec<Var>) {
+    match t {
+        Term::Var(x) if !vars.contains(x) => vars.push(x.clone()),
+        Term::Atom(a) => a.args.iter().for_each(|t| term_vars(t, vars)),
+        Term::PartialString(_, tail) => term_vars(tail, vars),
+        _ => (),
+    }
+}
+
+pub fn renumber_term(n: usize, t: &Term) -> Term {
+    match t {
+        Term::Var(Var(x, _)) => Term::Var(Var(x.clone(), n)),
+        Term::Atom(a) => Term::Atom(renumber_atom(n, a)),
+        Term::PartialString(text, tail) => {
+            Term::PartialString(text.clone(), Box::new(renumber_term(n, tail)))
+        }
+        c => c.clone(),
+    }
+}
+
+pub fn renumber_atom(n: usize, a: &Atom) -> Atom {
+    let mut a = a.clone();
+    let mut next_atoms = Vec::new();
+    renumber_atom_level(n, &mut a, &mut next_atoms);
+
+    while let Some(a) = next_atoms.pop() {
+        renumber_atom_level(n, a, &mut next_atoms);
+    }
+
+    a
+}
+
+fn renumber_atom_level<'a>(n: usize, a: &'a mut Atom, next: &mut Vec<&'a mut Atom>) {
+    for arg in &mut a.args {
+        match arg {
+            ref t @ Term::Var(_) | ref t @ Term::PartialString(..) => {
+                *arg = renumber_term(n, t);
+            }
+            Term::Atom(ref mut a) => next.push(a),
+            _ => (),
+        }
+    }
+}
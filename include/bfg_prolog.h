@@ -0,0 +1,117 @@
+#ifndef BFG_PROLOG_H
+#define BFG_PROLOG_H
+
+/* Generated by cbindgen from src/ffi.rs when the crate is built with the ffi feature. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ A program, to which sources are added with `bfg_consult`.
+ */
+typedef struct BfgMachine BfgMachine;
+
+/*
+ The search for the answers to a query, opened with `bfg_query_open`.
+ */
+typedef struct BfgQuery BfgQuery;
+
+/*
+ Creates a machine with an empty program.
+ */
+struct BfgMachine *bfg_machine_new(void);
+
+/*
+ Frees `machine`. The queries opened on it stay usable.
+
+ # Safety
+
+ `machine` must be null or a machine from `bfg_machine_new` that has not been freed.
+ */
+void bfg_machine_free(struct BfgMachine *machine);
+
+/*
+ Adds the clauses of `source` to the program of `machine`, after loading the files it asks for
+ and running its `initialization/1` goals. Returns 0, or -1 with the reason given by
+ `bfg_machine_error`.
+
+ # Safety
+
+ `machine` must be a machine from `bfg_machine_new`, and `source` a string ending in a NUL
+ character.
+ */
+int bfg_consult(struct BfgMachine *machine, const char *source);
+
+/*
+ Why the last call on `machine` failed, or null if it succeeded. The message lasts until the
+ next call on the machine.
+
+ # Safety
+
+ `machine` must be a machine from `bfg_machine_new`.
+ */
+const char *bfg_machine_error(const struct BfgMachine *machine);
+
+/*
+ Opens the query `goal`, such as `"parent(tom, X)."`, against the program `machine` has now.
+ Returns null if the query cannot be read, with the reason given by `bfg_machine_error`.
+
+ # Safety
+
+ `machine` must be a machine from `bfg_machine_new`, and `goal` a string ending in a NUL
+ character.
+ */
+struct BfgQuery *bfg_query_open(struct BfgMachine *machine, const char *goal);
+
+/*
+ Looks for the next answer to `query`. Returns 1 when one is found, 0 when there are no more,
+ and -1 when the query raised an exception, which `bfg_query_error` then gives.
+
+ # Safety
+
+ `query` must be a query from `bfg_query_open` that has not been closed.
+ */
+int bfg_query_next(struct BfgQuery *query);
+
+/*
+ The value the answer `query` is at gives the variable `name` of the query, written as the
+ toplevel writes it, or null if there is no answer or no such variable. The caller frees the
+ string with `bfg_string_free`.
+
+ # Safety
+
+ `query` must be a query from `bfg_query_open` that has not been closed, and `name` a string
+ ending in a NUL character.
+ */
+char *bfg_query_binding(const struct BfgQuery *query, const char *name);
+
+/*
+ The exception that ended `query`, or null if it raised none.
+
+ # Safety
+
+ `query` must be a query from `bfg_query_open` that has not been closed.
+ */
+const char *bfg_query_error(const struct BfgQuery *query);
+
+/*
+ Closes `query`, ending its search.
+
+ # Safety
+
+ `query` must be null or a query from `bfg_query_open` that has not been closed.
+ */
+void bfg_query_close(struct BfgQuery *query);
+
+/*
+ Frees a string returned by `bfg_query_binding`.
+
+ # Safety
+
+ `s` must be null or a string from `bfg_query_binding` that has not been freed.
+ */
+void bfg_string_free(char *s);
+
+#endif  /* BFG_PROLOG_H */
@@ -0,0 +1,10 @@
+fn main() {
+    // The grammar is the one the interpreter reads programs with, so that `prolog!` accepts
+    // exactly what `consult/1` does.
+    lalrpop::Configuration::new()
+        .set_in_dir("../src")
+        .set_out_dir(std::env::var("OUT_DIR").unwrap())
+        .emit_rerun_directives(true)
+        .process_file("../src/parser.lalrpop")
+        .unwrap();
+}
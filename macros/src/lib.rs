@@ -0,0 +1,328 @@
+//! Procedural macros for bfg-prolog, re-exported by the main crate.
+
+// The parser and the modules it builds terms with are shared with the main crate.
+use bfg_prolog_core::ast;
+#[allow(dead_code)]
+#[path = "../../src/dcg.rs"]
+mod dcg;
+#[allow(dead_code)]
+#[path = "../../src/tokenizer.rs"]
+mod tokenizer;
+
+use crate::ast::{Assertion, Atom, Number, Term};
+use lalrpop_util::{lalrpop_mod, ParseError};
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Fields, Generics, Ident, LitStr,
+};
+
+lalrpop_mod!(
+    #[allow(clippy::all, unused)]
+    parser
+);
+
+/// Reads a program given as a string literal when the crate using it is compiled, so that a
+/// syntax error in it fails the build. The macro expands to an expression of type
+/// `KnowledgeBase` holding the clauses as `consult/1` reads them, before the files named by its
+/// `include/1` and `use_module/1,2` directives are loaded.
+#[proc_macro]
+pub fn prolog(input: TokenStream) -> TokenStream {
+    let source = parse_macro_input!(input as LitStr);
+    let text = source.value();
+
+    match parser::CodeParser::new().parse(tokenizer::lex(&text)) {
+        Ok(kb) => {
+            let clauses = kb.iter().map(assertion_tokens);
+            quote!(::std::vec![#(#clauses),*]).into()
+        }
+        Err(e) => {
+            let (location, message) = match e {
+                ParseError::InvalidToken { location } => (location, String::from("invalid token")),
+                ParseError::UnrecognizedEOF { location, .. } => {
+                    (location, String::from("unexpected end of program"))
+                }
+                ParseError::UnrecognizedToken {
+                    token: (location, token, _),
+                    ..
+                }
+                | ParseError::ExtraToken {
+                    token: (location, token, _),
+                } => (location, format!("unexpected `{}`", token)),
+                ParseError::User { error } => (0, String::from(error)),
+            };
+
+            let before = &text[..location.min(text.len())];
+            let line = before.matches('\n').count() + 1;
+            let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+            let message = format!(
+                "syntax error at line {}, column {}: {}",
+                line, column, message
+            );
+
+            syn::Error::new(source.span(), message)
+                .to_compile_error()
+                .into()
+        }
+    }
+}
+
+fn assertion_tokens(a: &Assertion) -> TokenStream2 {
+    let head = atom_tokens(&a.head);
+    let goals = a.clause.iter().map(atom_tokens);
+
+    quote!(::bfg_prolog::ast::Assertion::new(#head, ::std::vec![#(#goals),*]))
+}
+
+fn atom_tokens(a: &Atom) -> TokenStream2 {
+    let name = &a.name.0;
+    let args = a.args.iter().map(term_tokens);
+
+    quote!(::bfg_prolog::ast::Atom::new(#name, ::std::vec![#(#args),*]))
+}
+
+fn term_tokens(t: &Term) -> TokenStream2 {
+    match t {
+        Term::Var(ast::Var(name, n)) => {
+            quote!(::bfg_prolog::ast::Term::Var(::bfg_prolog::ast::Var::new(#name, #n)))
+        }
+        Term::Atom(a) => {
+            let a = atom_tokens(a);
+            quote!(::bfg_prolog::ast::Term::Atom(#a))
+        }
+        Term::Number(Number::Int(i)) => {
+            quote!(::bfg_prolog::ast::Term::Number(::bfg_prolog::ast::Number::Int(#i)))
+        }
+        Term::Number(Number::Float(f)) => {
+            // Rebuilt from its bits so that the float is exactly the one that was read.
+            let bits = f.to_bits();
+            quote! {
+                ::bfg_prolog::ast::Term::Number(::bfg_prolog::ast::Number::Float(
+                    f64::from_bits(#bits),
+                ))
+            }
+        }
+        Term::String(s) => quote!(::bfg_prolog::ast::Term::String(::std::string::String::from(#s))),
+        Term::PartialString(text, tail) => {
+            let tail = term_tokens(tail);
+            quote! {
+                ::bfg_prolog::ast::Term::PartialString(
+                    ::std::string::String::from(#text),
+                    ::std::boxed::Box::new(#tail),
+                )
+            }
+        }
+    }
+}
+
+/// Derives `ToTerm` and `FromTerm`, and with them `From<T> for Term` and `TryFrom<Term> for T`,
+/// encoding a struct or an enum variant as a compound term whose arguments are its fields in
+/// order, or as an atom when it has none. The functor is the type or variant name in snake case,
+/// unless `#[prolog(name = "...")]` gives another.
+#[proc_macro_derive(PrologTerm, attributes(prolog))]
+pub fn derive_prolog_term(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match derive(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn derive(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ty = &input.ident;
+    let name = functor_name(&input.attrs, ty)?;
+
+    let (to_term, from_term) = match &input.data {
+        Data::Struct(data) => {
+            let to = write_fields(&name, &data.fields);
+            let pattern = pattern(&data.fields);
+            let read = read_fields(&name, quote!(#ty), &data.fields);
+
+            (
+                quote! {
+                    let #ty #pattern = self;
+                    #to
+                },
+                quote! {
+                    #read
+                    Err(::bfg_prolog::convert::TermError::new(#name, t))
+                },
+            )
+        }
+        Data::Enum(data) => {
+            let mut writes = Vec::new();
+            let mut reads = Vec::new();
+
+            for variant in &data.variants {
+                let v = &variant.ident;
+                let name = functor_name(&variant.attrs, v)?;
+                let pattern = pattern(&variant.fields);
+                let to = write_fields(&name, &variant.fields);
+
+                writes.push(quote!(#ty::#v #pattern => { #to }));
+                reads.push(read_fields(&name, quote!(#ty::#v), &variant.fields));
+            }
+
+            (
+                quote! {
+                    match self {
+                        #(#writes)*
+                    }
+                },
+                quote! {
+                    #(#reads)*
+                    Err(::bfg_prolog::convert::TermError::new(#name, t))
+                },
+            )
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "PrologTerm cannot be derived for unions",
+            ))
+        }
+    };
+
+    let to_generics = bounded(&input.generics, quote!(::bfg_prolog::convert::ToTerm));
+    let from_generics = bounded(&input.generics, quote!(::bfg_prolog::convert::FromTerm));
+    let (to_impl, ty_generics, to_where) = to_generics.split_for_impl();
+    let (from_impl, _, from_where) = from_generics.split_for_impl();
+
+    Ok(quote! {
+        impl #to_impl ::bfg_prolog::convert::ToTerm for #ty #ty_generics #to_where {
+            fn to_term(&self) -> ::bfg_prolog::ast::Term {
+                #to_term
+            }
+        }
+
+        impl #from_impl ::bfg_prolog::convert::FromTerm for #ty #ty_generics #from_where {
+            fn from_term(
+                t: &::bfg_prolog::ast::Term,
+            ) -> ::std::result::Result<Self, ::bfg_prolog::convert::TermError> {
+                #from_term
+            }
+        }
+
+        impl #to_impl ::std::convert::From<#ty #ty_generics> for ::bfg_prolog::ast::Term #to_where {
+            fn from(x: #ty #ty_generics) -> Self {
+                ::bfg_prolog::convert::ToTerm::to_term(&x)
+            }
+        }
+
+        impl #from_impl ::std::convert::TryFrom<::bfg_prolog::ast::Term> for #ty #ty_generics
+            #from_where
+        {
+            type Error = ::bfg_prolog::convert::TermError;
+
+            fn try_from(t: ::bfg_prolog::ast::Term) -> ::std::result::Result<Self, Self::Error> {
+                ::bfg_prolog::convert::FromTerm::from_term(&t)
+            }
+        }
+    })
+}
+
+/// The generics of the type with `bound` added to each of its type parameters.
+fn bounded(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+
+    generics
+}
+
+/// The name given by `#[prolog(name = "...")]`, or else `ident` in snake case.
+fn functor_name(attrs: &[Attribute], ident: &Ident) -> syn::Result<String> {
+    let mut name = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("prolog")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `name = \"...\"`"))
+            }
+        })?;
+    }
+
+    Ok(name.unwrap_or_else(|| snake_case(&ident.to_string())))
+}
+
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower) {
+                out.push('_');
+            }
+        }
+
+        out.extend(c.to_lowercase());
+    }
+
+    out
+}
+
+/// The names bound to each field when destructuring.
+fn bindings(fields: &Fields) -> Vec<Ident> {
+    (0..fields.len())
+        .map(|i| Ident::new(&format!("field{}", i), Span::call_site()))
+        .collect()
+}
+
+/// The pattern binding each field of a struct or variant to the names from `bindings`.
+fn pattern(fields: &Fields) -> TokenStream2 {
+    let names = bindings(fields);
+
+    match fields {
+        Fields::Named(named) => {
+            let fields = named.named.iter().map(|f| &f.ident);
+            quote!({ #(#fields: #names),* })
+        }
+        Fields::Unnamed(_) => quote!((#(#names),*)),
+        Fields::Unit => quote!(),
+    }
+}
+
+/// Builds the term for a struct or variant whose fields are bound to the names from `bindings`.
+fn write_fields(name: &str, fields: &Fields) -> TokenStream2 {
+    let names = bindings(fields);
+
+    quote! {
+        ::bfg_prolog::ast::Term::Atom(::bfg_prolog::ast::Atom::new(
+            #name,
+            vec![#(::bfg_prolog::convert::ToTerm::to_term(#names)),*],
+        ))
+    }
+}
+
+/// Returns the value built by `path` from the arguments of `t` if its functor is `name` with one
+/// argument for each field.
+fn read_fields(name: &str, path: TokenStream2, fields: &Fields) -> TokenStream2 {
+    let arity = fields.len();
+    let reads = (0..arity).map(|i| quote!(::bfg_prolog::convert::FromTerm::from_term(&args[#i])?));
+
+    let value = match fields {
+        Fields::Named(named) => {
+            let fields = named.named.iter().map(|f| &f.ident);
+            quote!(#path { #(#fields: #reads),* })
+        }
+        Fields::Unnamed(_) => quote!(#path(#(#reads),*)),
+        Fields::Unit => quote!(#path),
+    };
+
+    quote! {
+        if let Some(args) = ::bfg_prolog::convert::functor_args(t, #name, #arity) {
+            return Ok(#value);
+        }
+    }
+}
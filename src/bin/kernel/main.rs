@@ -0,0 +1,409 @@
+//! A Jupyter kernel, built with the `jupyter` feature and started by Jupyter with the connection
+//! file it writes. `jupyter kernelspec install jupyter --user --name bfg-prolog` installs the
+//! kernel spec in the `jupyter` directory, which runs `bfg-prolog-kernel` from the `PATH`.
+//!
+//! A cell that starts with `?-` is a query, whose answers are shown as the toplevel shows them.
+//! Any other cell is a program: its predicates take the place of those of the cells run before
+//! it, and its `initialization/1` goals run once it is loaded.
+
+mod wire;
+
+use bfg_prolog::ast;
+use bfg_prolog::ast::{Assertion, Const};
+use bfg_prolog::dcg;
+use bfg_prolog::loader::{consult_text, initialization_goals, Initialization};
+use bfg_prolog::tokenizer;
+use bfg_prolog::{
+    catch_interrupts, set_user_output, solve_quietly, solve_with_console, Console, KnowledgeBase,
+    QueryOptions,
+};
+use lalrpop_util::lalrpop_mod;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::mpsc;
+use tokio::sync::oneshot;
+use wire::{Message, Session, PROTOCOL_VERSION};
+use zeromq::{PubSocket, RepSocket, RouterSocket, Socket, SocketRecv, SocketSend};
+
+lalrpop_mod!(#[allow(clippy::all, unused_parens)] pub parser);
+
+/// How many answers to a query are shown before the search stops.
+const MAX_ANSWERS: usize = 100;
+
+/// A cell to run, and where to send what came of it.
+struct Job {
+    code: String,
+    done: oneshot::Sender<Outcome>,
+}
+
+/// What came of running a cell.
+#[derive(Default)]
+struct Outcome {
+    /// What the cell wrote to `user_output`.
+    output: String,
+    warnings: Vec<String>,
+    /// The answers to a query.
+    answers: Option<String>,
+    error: Option<String>,
+}
+
+/// Where `user_output` goes while a cell runs.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps the answers to a query, written as the toplevel writes them.
+#[derive(Default)]
+struct Answers {
+    answers: Vec<String>,
+    /// Whether the search stopped with answers left to look for.
+    stopped: bool,
+    error: Option<String>,
+}
+
+impl Console for Answers {
+    fn answer(&mut self, answer: &str, _last: bool) {
+        self.answers.push(String::from(answer));
+    }
+
+    fn more(&mut self) -> bool {
+        self.stopped = self.answers.len() >= MAX_ANSWERS;
+        !self.stopped
+    }
+
+    fn no(&mut self) {
+        self.answers.push(String::from("No"));
+    }
+
+    fn exception(&mut self, message: &str) {
+        self.error = Some(String::from(message));
+    }
+}
+
+impl Answers {
+    fn text(&self) -> Option<String> {
+        if self.answers.is_empty() {
+            return None;
+        }
+
+        let end = if self.stopped { " ;\n..." } else { "." };
+        Some(format!("{}{}", self.answers.join(" ;\n"), end))
+    }
+}
+
+/// Loads the program in `code`, whose predicates replace those `kb` has.
+fn consult(kb: &mut KnowledgeBase, code: &str, outcome: &mut Outcome) {
+    let cell = match consult_text(code) {
+        Ok(cell) => cell,
+        Err(e) => {
+            outcome.error = Some(e);
+            return;
+        }
+    };
+
+    let directive = |a: &Assertion| a.head.name.0 == ":-";
+    let defined: HashSet<(Const, usize)> = cell
+        .iter()
+        .filter(|a| !directive(a))
+        .map(|a| (a.head.name.clone(), a.head.arity))
+        .collect();
+
+    // Running a cell again leaves one copy of its directives.
+    kb.retain(|a| {
+        if directive(a) {
+            !cell.contains(a)
+        } else {
+            !defined.contains(&(a.head.name.clone(), a.head.arity))
+        }
+    });
+
+    let goals = initialization_goals(&cell);
+    *kb = cell.into_iter().chain(kb.drain(..)).collect();
+
+    for (when, goal) in goals {
+        if when == Initialization::AfterLoad && !solve_quietly(kb, goal.clone()) {
+            outcome.warnings.push(format!(
+                "Warning: initialization goal {} failed\n",
+                goal.args[0]
+            ));
+        }
+    }
+}
+
+fn query(kb: &[Assertion], code: &str, outcome: &mut Outcome) {
+    let goals = match parser::ClauseParser::new().parse(tokenizer::lex(code)) {
+        Ok(goals) => goals,
+        Err(e) => {
+            outcome.error = Some(e.to_string());
+            return;
+        }
+    };
+
+    let mut answers = Answers::default();
+    solve_with_console(&mut answers, kb, goals, &QueryOptions::default());
+    outcome.answers = answers.text();
+    outcome.error = answers.error;
+}
+
+/// Runs the cells sent to `jobs` one after another against the program they build up, on a
+/// thread of its own so that the kernel keeps answering while they run.
+fn engine(jobs: mpsc::Receiver<Job>) {
+    // Jupyter interrupts a kernel with SIGINT, which aborts the cell running.
+    if let Err(e) = catch_interrupts() {
+        eprintln!("Warning: interrupts cannot be caught: {}", e);
+    }
+
+    let output = Output::default();
+    set_user_output(Some(Box::new(output.clone())));
+    let mut kb = KnowledgeBase::new();
+
+    for job in jobs {
+        let mut outcome = Outcome::default();
+
+        match job.code.trim_start().strip_prefix("?-") {
+            Some(goals) => query(&kb, goals, &mut outcome),
+            None => consult(&mut kb, &job.code, &mut outcome),
+        }
+
+        let written = std::mem::take(&mut *output.0.borrow_mut());
+        outcome.output = String::from_utf8_lossy(&written).into_owned();
+        let _ = job.done.send(outcome);
+    }
+}
+
+struct Kernel {
+    session: Session,
+    iopub: PubSocket,
+    jobs: mpsc::Sender<Job>,
+    execution_count: u64,
+}
+
+impl Kernel {
+    async fn publish(&mut self, request: &Message, msg_type: &str, content: Value) {
+        let message = self.session.broadcast(request, msg_type, content);
+
+        if let Err(e) = self.iopub.send(message).await {
+            eprintln!("Warning: could not publish {}: {}", msg_type, e);
+        }
+    }
+
+    async fn execute(&mut self, request: &Message) -> Value {
+        let code = request.content["code"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        if !request.content["silent"].as_bool().unwrap_or(false) {
+            self.execution_count += 1;
+        }
+
+        let count = self.execution_count;
+        self.publish(
+            request,
+            "execute_input",
+            json!({"code": code, "execution_count": count}),
+        )
+        .await;
+
+        let (done, outcome) = oneshot::channel();
+        let outcome = match self.jobs.send(Job { code, done }) {
+            Ok(()) => outcome.await.unwrap_or_default(),
+            Err(_) => Outcome::default(),
+        };
+
+        if !outcome.output.is_empty() {
+            let content = json!({"name": "stdout", "text": outcome.output});
+            self.publish(request, "stream", content).await;
+        }
+
+        if !outcome.warnings.is_empty() {
+            let content = json!({"name": "stderr", "text": outcome.warnings.concat()});
+            self.publish(request, "stream", content).await;
+        }
+
+        if let Some(answers) = outcome.answers {
+            let content = json!({
+                "execution_count": count,
+                "data": {"text/plain": answers},
+                "metadata": {},
+            });
+            self.publish(request, "execute_result", content).await;
+        }
+
+        match outcome.error {
+            Some(error) => {
+                let content = json!({
+                    "ename": "PrologError",
+                    "evalue": error,
+                    "traceback": [error],
+                });
+                self.publish(request, "error", content).await;
+
+                json!({
+                    "status": "error",
+                    "execution_count": count,
+                    "ename": "PrologError",
+                    "evalue": error,
+                    "traceback": [error],
+                })
+            }
+            None => json!({
+                "status": "ok",
+                "execution_count": count,
+                "user_expressions": {},
+                "payload": [],
+            }),
+        }
+    }
+}
+
+fn kernel_info() -> Value {
+    json!({
+        "status": "ok",
+        "protocol_version": PROTOCOL_VERSION,
+        "implementation": "bfg-prolog",
+        "implementation_version": env!("CARGO_PKG_VERSION"),
+        "language_info": {
+            "name": "prolog",
+            "version": env!("CARGO_PKG_VERSION"),
+            "mimetype": "text/x-prolog",
+            "file_extension": ".pl",
+        },
+        "banner": format!("BFG Prolog {}", env!("CARGO_PKG_VERSION")),
+        "help_links": [],
+    })
+}
+
+/// Whether `code` can run as it is, which a query cannot until it ends with a full stop.
+fn is_complete(code: &str) -> Value {
+    let code = code.trim();
+
+    if code.starts_with("?-") && !code.ends_with('.') {
+        json!({"status": "incomplete", "indent": ""})
+    } else {
+        json!({"status": "complete"})
+    }
+}
+
+async fn bind(socket: &mut impl Socket, connection: &Value, port: &str) {
+    let endpoint = format!(
+        "{}://{}:{}",
+        connection["transport"].as_str().unwrap_or("tcp"),
+        connection["ip"].as_str().unwrap_or("127.0.0.1"),
+        connection[port]
+    );
+
+    if let Err(e) = socket.bind(&endpoint).await {
+        eprintln!("Cannot listen on {}: {}", endpoint, e);
+        std::process::exit(1);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let connection = match std::env::args().nth(1).map(std::fs::read_to_string) {
+        Some(Ok(text)) => serde_json::from_str(&text).unwrap_or(Value::Null),
+        Some(Err(e)) => {
+            eprintln!("Cannot read the connection file: {}", e);
+            std::process::exit(1);
+        }
+        None => {
+            eprintln!("Usage: bfg-prolog-kernel CONNECTION_FILE");
+            std::process::exit(2);
+        }
+    };
+
+    let (mut shell, mut control, mut stdin) = (
+        RouterSocket::new(),
+        RouterSocket::new(),
+        RouterSocket::new(),
+    );
+    let (mut iopub, mut heartbeat) = (PubSocket::new(), RepSocket::new());
+    bind(&mut shell, &connection, "shell_port").await;
+    bind(&mut control, &connection, "control_port").await;
+    bind(&mut stdin, &connection, "stdin_port").await;
+    bind(&mut iopub, &connection, "iopub_port").await;
+    bind(&mut heartbeat, &connection, "hb_port").await;
+
+    tokio::spawn(async move {
+        while let Ok(ping) = heartbeat.recv().await {
+            if heartbeat.send(ping).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let (jobs, queue) = mpsc::channel();
+    std::thread::spawn(move || engine(queue));
+
+    let mut kernel = Kernel {
+        session: Session::new(connection["key"].as_str().unwrap_or_default()),
+        iopub,
+        jobs,
+        execution_count: 0,
+    };
+
+    loop {
+        let (frames, on_control) = tokio::select! {
+            frames = shell.recv() => (frames, false),
+            frames = control.recv() => (frames, true),
+        };
+
+        let request = match frames
+            .map_err(|e| e.to_string())
+            .and_then(|frames| kernel.session.read(frames))
+        {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("Warning: dropped a message: {}", e);
+                continue;
+            }
+        };
+
+        kernel
+            .publish(&request, "status", json!({"execution_state": "busy"}))
+            .await;
+
+        let (msg_type, content) = match request.msg_type() {
+            "kernel_info_request" => ("kernel_info_reply", kernel_info()),
+            "execute_request" => ("execute_reply", kernel.execute(&request).await),
+            "is_complete_request" => (
+                "is_complete_reply",
+                is_complete(request.content["code"].as_str().unwrap_or_default()),
+            ),
+            "comm_info_request" => ("comm_info_reply", json!({"status": "ok", "comms": {}})),
+            "shutdown_request" => ("shutdown_reply", request.content.clone()),
+            _ => ("", Value::Null),
+        };
+
+        if !msg_type.is_empty() {
+            let reply = kernel.session.reply(&request, msg_type, content);
+            let socket = if on_control { &mut control } else { &mut shell };
+
+            if let Err(e) = socket.send(reply).await {
+                eprintln!("Warning: could not send {}: {}", msg_type, e);
+            }
+        }
+
+        kernel
+            .publish(&request, "status", json!({"execution_state": "idle"}))
+            .await;
+
+        if request.msg_type() == "shutdown_request" {
+            break;
+        }
+    }
+}
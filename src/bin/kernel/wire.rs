@@ -0,0 +1,169 @@
+//! Jupyter messages as they travel over ZeroMQ: the routing identities, a delimiter, the HMAC of
+//! the parts that follow, then the header, parent header, metadata and content as JSON.
+
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::convert::TryFrom;
+use zeromq::ZmqMessage;
+
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+/// The version of the messaging protocol the kernel speaks.
+pub const PROTOCOL_VERSION: &str = "5.3";
+
+/// A message received, or about to be sent.
+pub struct Message {
+    /// The peers the message is routed from, which a reply is routed back to.
+    pub identities: Vec<Bytes>,
+    pub header: Value,
+    pub content: Value,
+}
+
+impl Message {
+    pub fn msg_type(&self) -> &str {
+        self.header["msg_type"].as_str().unwrap_or_default()
+    }
+}
+
+/// The kernel's side of the conversation with a frontend: the key that signs its messages and
+/// the session they are sent in.
+pub struct Session {
+    key: Vec<u8>,
+    id: String,
+    sent: u64,
+}
+
+impl Session {
+    /// A session signing with `key`, where an empty key leaves messages unsigned.
+    pub fn new(key: &str) -> Self {
+        let started = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        Session {
+            key: key.as_bytes().to_vec(),
+            id: format!("{:x}-{:x}", std::process::id(), started.as_nanos()),
+            sent: 0,
+        }
+    }
+
+    fn sign(&self, parts: &[&[u8]]) -> String {
+        if self.key.is_empty() {
+            return String::new();
+        }
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key");
+
+        for part in parts {
+            mac.update(part);
+        }
+
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Reads the message sent as `frames`, which must be signed with the key of the session.
+    pub fn read(&self, frames: ZmqMessage) -> Result<Message, String> {
+        let frames = frames.into_vec();
+        let at = frames
+            .iter()
+            .position(|frame| frame.as_ref() == DELIMITER)
+            .ok_or("message without a delimiter")?;
+
+        let (signature, parts) = match &frames[at + 1..] {
+            [signature, header, parent_header, metadata, content, ..] => {
+                (signature, [header, parent_header, metadata, content])
+            }
+            _ => return Err(String::from("message with missing parts")),
+        };
+
+        if signature.as_ref() != self.sign(&parts.map(|part| part.as_ref())).as_bytes() {
+            return Err(String::from("message with a bad signature"));
+        }
+
+        let json = |part: &Bytes| serde_json::from_slice(part).unwrap_or(Value::Null);
+
+        Ok(Message {
+            identities: frames[..at].to_vec(),
+            header: json(parts[0]),
+            content: json(parts[3]),
+        })
+    }
+
+    fn message(
+        &mut self,
+        identities: Vec<Bytes>,
+        parent: &Message,
+        msg_type: &str,
+        content: Value,
+    ) -> ZmqMessage {
+        self.sent += 1;
+
+        let header = json!({
+            "msg_id": format!("{}-{}", self.id, self.sent),
+            "session": self.id,
+            "username": "kernel",
+            "date": chrono::Utc::now().to_rfc3339(),
+            "msg_type": msg_type,
+            "version": PROTOCOL_VERSION,
+        });
+        let parts = [header, parent.header.clone(), json!({}), content]
+            .map(|part| part.to_string().into_bytes());
+        let signature = self.sign(&[&parts[0], &parts[1], &parts[2], &parts[3]]);
+
+        let frames: Vec<Bytes> = identities
+            .into_iter()
+            .chain([Bytes::from_static(DELIMITER), Bytes::from(signature)])
+            .chain(parts.map(Bytes::from))
+            .collect();
+
+        ZmqMessage::try_from(frames).expect("a message has frames")
+    }
+
+    /// The reply of type `msg_type` to `request`, routed back to where it came from.
+    pub fn reply(&mut self, request: &Message, msg_type: &str, content: Value) -> ZmqMessage {
+        self.message(request.identities.clone(), request, msg_type, content)
+    }
+
+    /// A message of type `msg_type` for every frontend, sent while handling `request`.
+    pub fn broadcast(&mut self, request: &Message, msg_type: &str, content: Value) -> ZmqMessage {
+        let topic = Bytes::from(format!("kernel.{}.{}", self.id, msg_type));
+        self.message(vec![topic], request, msg_type, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reply_reads_back() {
+        let mut session = Session::new("secret");
+        let request = Message {
+            identities: vec![Bytes::from_static(b"frontend")],
+            header: json!({"msg_type": "kernel_info_request"}),
+            content: json!({}),
+        };
+
+        let frames = session.reply(&request, "kernel_info_reply", json!({"status": "ok"}));
+        let reply = session.read(frames).unwrap();
+
+        assert_eq!(reply.identities, request.identities);
+        assert_eq!(reply.msg_type(), "kernel_info_reply");
+        assert_eq!(reply.content["status"], "ok");
+
+        let mut forged = session
+            .reply(&request, "kernel_info_reply", json!({}))
+            .into_vec();
+        forged[6] = Bytes::from_static(b"{\"status\": \"error\"}");
+
+        assert!(Session::new("secret")
+            .read(ZmqMessage::try_from(forged).unwrap())
+            .is_err());
+    }
+}
@@ -0,0 +1,389 @@
+//! What the server knows of a document: its clauses with the tokens they were read from, where
+//! its predicates are defined, and what is wrong with it.
+
+use crate::parser::CodeParser;
+use bfg_prolog::ast::{unquote, Assertion};
+use bfg_prolog::loader::{load, singleton_vars};
+use bfg_prolog::tokenizer::{lex, tokenize, Token, TokenKind, TokenizeErr};
+use bfg_prolog::xref::{calls, Known};
+use lalrpop_util::ParseError;
+use std::path::Path;
+
+/// A position as the protocol counts it: a line from 0, and UTF-16 code units into the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error = 1,
+    Warning = 2,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// A clause of a document and the tokens it was read from, comments left out.
+struct Clause {
+    assertions: Vec<Assertion>,
+    tokens: Vec<Token>,
+}
+
+pub struct Document {
+    text: String,
+    /// The byte offset each line starts at.
+    lines: Vec<usize>,
+    tokens: Vec<Token>,
+    clauses: Vec<Clause>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// The name of the predicate an atom token stands for.
+fn name(token: &Token) -> Option<String> {
+    match token.kind {
+        TokenKind::Atom => Some(token.text.clone()),
+        TokenKind::QuotedAtom => Some(unquote(&token.text)),
+        _ => None,
+    }
+}
+
+/// The number of arguments of the compound term whose name is `tokens[0]`.
+fn arity(tokens: &[Token]) -> usize {
+    match tokens {
+        [name, open, rest @ ..] if open.text == "(" && name.span.end == open.span.start => {
+            let mut depth = 0;
+            let mut arity = 1;
+
+            for token in rest {
+                match &token.text[..] {
+                    "(" | "[" | "{" if token.kind == TokenKind::Punct => depth += 1,
+                    ")" | "]" | "}" if token.kind == TokenKind::Punct && depth == 0 => break,
+                    ")" | "]" | "}" if token.kind == TokenKind::Punct => depth -= 1,
+                    "," if token.kind == TokenKind::Punct && depth == 0 => arity += 1,
+                    _ => (),
+                }
+            }
+
+            arity
+        }
+        _ => 0,
+    }
+}
+
+/// The message for a syntax error, without the list of tokens that would have been expected.
+fn parse_error<T: std::fmt::Display, E: std::fmt::Display>(
+    error: &ParseError<usize, T, E>,
+) -> (usize, String) {
+    match error {
+        ParseError::InvalidToken { location } => (*location, String::from("Invalid token")),
+        ParseError::UnrecognizedEOF { location, .. } => {
+            (*location, String::from("Unexpected end of clause"))
+        }
+        ParseError::UnrecognizedToken {
+            token: (start, token, _),
+            ..
+        }
+        | ParseError::ExtraToken {
+            token: (start, token, _),
+        } => (*start, format!("Unexpected `{}`", token)),
+        ParseError::User { error } => (0, error.to_string()),
+    }
+}
+
+impl Document {
+    /// Reads the document `text`, taking the files it loads from `dir`.
+    pub fn new(text: String, dir: &Path) -> Self {
+        let lines = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let mut document = Document {
+            text,
+            lines,
+            tokens: Vec::new(),
+            clauses: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+
+        match tokenize(&document.text) {
+            Ok(tokens) => document.tokens = tokens,
+            Err(e) => {
+                let (at, message) = match e {
+                    TokenizeErr::UnexpectedChar(c, at) => (at, format!("Unexpected {:?}", c)),
+                    TokenizeErr::Unterminated(kind, at) => (
+                        at,
+                        format!("Unterminated {}", kind.name().replace('_', " ")),
+                    ),
+                };
+                document.error(at..at + 1, Severity::Error, message);
+                return document;
+            }
+        }
+
+        document.read_clauses();
+        document.check_singletons();
+        document.check_calls(dir);
+        document.diagnostics.sort_by_key(|d| d.range.start);
+        document
+    }
+
+    /// The position of the byte `offset` of the text.
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.lines.partition_point(|&start| start <= offset) - 1;
+        let character = self.text[self.lines[line]..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+
+        Position { line, character }
+    }
+
+    /// The byte offset of `position` in the text.
+    fn offset(&self, position: Position) -> usize {
+        let start = match self.lines.get(position.line) {
+            Some(&start) => start,
+            None => return self.text.len(),
+        };
+        let mut units = 0;
+
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= position.character || c == '\n' {
+                return start + i;
+            }
+
+            units += c.len_utf16();
+        }
+
+        self.text.len()
+    }
+
+    fn range(&self, span: std::ops::Range<usize>) -> Range {
+        Range {
+            start: self.position(span.start),
+            end: self.position(span.end),
+        }
+    }
+
+    fn error(&mut self, span: std::ops::Range<usize>, severity: Severity, message: String) {
+        let range = self.range(span);
+
+        self.diagnostics.push(Diagnostic {
+            range,
+            severity,
+            message,
+        });
+    }
+
+    /// Reads each clause on its own, so that a syntax error in one leaves the others readable.
+    fn read_clauses(&mut self) {
+        let tokens: Vec<_> = self
+            .tokens
+            .iter()
+            .filter(|token| token.kind != TokenKind::Comment)
+            .cloned()
+            .collect();
+
+        for tokens in tokens.split_inclusive(|token| token.kind == TokenKind::End) {
+            let (first, last) = (&tokens[0], &tokens[tokens.len() - 1]);
+            let start = first.span.start;
+
+            match CodeParser::new().parse(lex(&self.text[start..last.span.end])) {
+                Ok(assertions) => self.clauses.push(Clause {
+                    assertions,
+                    tokens: tokens.to_vec(),
+                }),
+                Err(e) => {
+                    let (at, message) = parse_error(&e);
+                    self.error(start + at..start + at + 1, Severity::Error, message);
+                }
+            }
+        }
+    }
+
+    /// Warns of the variables that appear once in a clause, other than those named with a
+    /// leading underscore.
+    fn check_singletons(&mut self) {
+        let singletons: Vec<_> = self
+            .clauses
+            .iter()
+            .flat_map(|clause| singleton_vars(&clause.tokens))
+            .map(|token| (token.span.clone(), token.text.clone()))
+            .collect();
+
+        for (span, var) in singletons {
+            let message = format!("Singleton variable {}", var);
+            self.error(span, Severity::Warning, message);
+        }
+    }
+
+    /// Warns of the calls to predicates that are neither defined by the program, which includes
+    /// the files it loads, nor in the library nor built in.
+    fn check_calls(&mut self, dir: &Path) {
+        // Clauses are kept last first, as the loader keeps them.
+        let kb: Vec<_> = self
+            .clauses
+            .iter()
+            .rev()
+            .flat_map(|clause| clause.assertions.iter().rev().cloned())
+            .collect();
+        let known = Known::new(&load(kb.clone(), dir).unwrap_or(kb));
+        let mut unknown = Vec::new();
+
+        for clause in &self.clauses {
+            // The goals of a clause come after its neck.
+            let neck = clause
+                .tokens
+                .iter()
+                .position(|t| t.kind == TokenKind::Atom && (t.text == ":-" || t.text == "-->"))
+                .unwrap_or(0);
+
+            for goal in clause.assertions.iter().flat_map(calls) {
+                if known.contains(&goal.name.0, goal.arity) {
+                    continue;
+                }
+
+                let token = clause.tokens[neck..]
+                    .iter()
+                    .find(|t| name(t).as_deref() == Some(&goal.name.0))
+                    .unwrap_or(&clause.tokens[0]);
+                let message = format!("Unknown procedure {}/{}", goal.name.0, goal.arity);
+                unknown.push((token.span.clone(), message));
+            }
+        }
+
+        for (span, message) in unknown {
+            self.error(span, Severity::Warning, message);
+        }
+    }
+
+    /// The predicates the document defines, each with the range of the head of its first clause.
+    pub fn definitions(&self) -> Vec<((String, usize), Range)> {
+        let mut definitions: Vec<((String, usize), Range)> = Vec::new();
+
+        for clause in &self.clauses {
+            let head = &clause.tokens[0];
+
+            for a in &clause.assertions {
+                let key = (a.head.name.0.clone(), a.head.arity);
+
+                if key.0 != ":-" && !definitions.iter().any(|(defined, _)| *defined == key) {
+                    definitions.push((key, self.range(head.span.clone())));
+                }
+            }
+        }
+
+        definitions
+    }
+
+    /// The index of the token `position` is in or just after.
+    fn token_at(&self, position: Position) -> Option<usize> {
+        let offset = self.offset(position);
+
+        self.tokens
+            .iter()
+            .position(|t| t.span.start <= offset && offset <= t.span.end)
+    }
+
+    /// The name and arity of the predicate named by the atom at `position`.
+    pub fn predicate_at(&self, position: Position) -> Option<(String, usize)> {
+        let i = self.token_at(position)?;
+        Some((name(&self.tokens[i])?, arity(&self.tokens[i..])))
+    }
+
+    /// The part of the name at `position` that comes before it, which completions start with.
+    pub fn prefix_at(&self, position: Position) -> String {
+        let offset = self.offset(position);
+
+        self.text[..offset]
+            .chars()
+            .rev()
+            .take_while(|&c| c.is_alphanumeric() || c == '_')
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(document: &Document) -> Vec<(usize, &str)> {
+        document
+            .diagnostics
+            .iter()
+            .map(|d| (d.range.start.line, &d.message[..]))
+            .collect()
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let text = "p(X) :- q(X, Y).\nq(1, 2).\nr :- s.\nt(.\nu(A) :- append(A, [], _B).\n";
+        let document = Document::new(String::from(text), Path::new("."));
+
+        assert_eq!(
+            messages(&document),
+            vec![
+                (0, "Singleton variable Y"),
+                (2, "Unknown procedure s/0"),
+                (3, "Unexpected `.`"),
+            ]
+        );
+        assert_eq!(
+            document.diagnostics[1].range,
+            Range {
+                start: Position {
+                    line: 2,
+                    character: 5
+                },
+                end: Position {
+                    line: 2,
+                    character: 6
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_definitions() {
+        let text = "% The parents.\nparent(tom, bob).\n\ngrandparent(X, Z) :-\n    parent(X, Y), parent(Y, Z).\n";
+        let document = Document::new(String::from(text), Path::new("."));
+        let at = Position {
+            line: 4,
+            character: 6,
+        };
+
+        assert!(document.diagnostics.is_empty());
+        assert_eq!(document.predicate_at(at), Some((String::from("parent"), 2)));
+        assert_eq!(document.prefix_at(at), "pa");
+        assert_eq!(
+            document.definitions()[0],
+            (
+                (String::from("parent"), 2),
+                Range {
+                    start: Position {
+                        line: 1,
+                        character: 0
+                    },
+                    end: Position {
+                        line: 1,
+                        character: 6
+                    },
+                }
+            )
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! A language server, built with the `lsp` feature, that speaks the Language Server Protocol over
+//! standard input and output. For the `.pl` files an editor opens it reports syntax errors, calls
+//! to unknown procedures and singleton variables, finds where predicates are defined and
+//! completes their names.
+
+mod analysis;
+
+use analysis::{Document, Position, Range};
+use bfg_prolog::ast;
+use bfg_prolog::dcg;
+use bfg_prolog::tokenizer;
+use bfg_prolog::xref::{builtins, Known};
+use lalrpop_util::lalrpop_mod;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+lalrpop_mod!(#[allow(clippy::all, unused_parens)] pub parser);
+
+/// The error code of a request for a method the server does not have.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Reads the next message, returning None at the end of the input.
+fn read_message(input: &mut impl BufRead) -> std::io::Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body).unwrap_or(Value::Null)))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn position(value: &Value) -> Position {
+    Position {
+        line: value["line"].as_u64().unwrap_or(0) as usize,
+        character: value["character"].as_u64().unwrap_or(0) as usize,
+    }
+}
+
+fn range(range: Range) -> Value {
+    let position = |p: Position| json!({"line": p.line, "character": p.character});
+    json!({"start": position(range.start), "end": position(range.end)})
+}
+
+/// The directory of the file at `uri`, which the files it loads are taken from.
+fn dir(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or_default();
+    let mut bytes = Vec::new();
+    let mut rest = path.bytes();
+
+    // Undoes the percent-encoding of the path.
+    while let Some(b) = rest.next() {
+        let hex = |b: Option<u8>| (b? as char).to_digit(16);
+
+        match b {
+            b'%' => match (hex(rest.next()), hex(rest.next())) {
+                (Some(hi), Some(lo)) => bytes.push((hi * 16 + lo) as u8),
+                _ => bytes.push(b),
+            },
+            b => bytes.push(b),
+        }
+    }
+
+    let path = PathBuf::from(String::from_utf8_lossy(&bytes).into_owned());
+    path.parent().map(PathBuf::from).unwrap_or_default()
+}
+
+#[derive(Default)]
+struct Server {
+    /// The open documents, by their URI.
+    documents: BTreeMap<String, Document>,
+    shutdown: bool,
+}
+
+impl Server {
+    fn open(&mut self, uri: &str, text: &str) -> Value {
+        let document = Document::new(text.to_string(), &dir(uri));
+        let diagnostics: Vec<_> = document
+            .diagnostics
+            .iter()
+            .map(|d| {
+                json!({
+                    "range": range(d.range),
+                    "severity": d.severity as u8,
+                    "source": "bfg-prolog",
+                    "message": d.message,
+                })
+            })
+            .collect();
+
+        self.documents.insert(uri.to_string(), document);
+        json!({
+            "method": "textDocument/publishDiagnostics",
+            "params": {"uri": uri, "diagnostics": diagnostics},
+        })
+    }
+
+    /// Where the predicate at `at` in the document `uri` is defined, looking in that document
+    /// first and for the same arity before any other.
+    fn definition(&self, uri: &str, at: Position) -> Value {
+        let (name, arity) = match self.documents.get(uri).and_then(|d| d.predicate_at(at)) {
+            Some(predicate) => predicate,
+            None => return Value::Null,
+        };
+
+        let documents = self
+            .documents
+            .iter()
+            .filter(|(other, _)| *other == uri)
+            .chain(self.documents.iter().filter(|(other, _)| *other != uri));
+        let definitions: Vec<_> = documents
+            .flat_map(|(uri, d)| d.definitions().into_iter().map(move |def| (uri, def)))
+            .filter(|(_, ((defined, _), _))| *defined == name)
+            .collect();
+
+        definitions
+            .iter()
+            .find(|(_, ((_, n), _))| *n == arity)
+            .or_else(|| definitions.first())
+            .map_or(
+                Value::Null,
+                |(uri, (_, r))| json!({"uri": uri, "range": range(*r)}),
+            )
+    }
+
+    /// The predicates whose names start with the name being written at `at`.
+    fn completion(&self, uri: &str, at: Position) -> Value {
+        let prefix = match self.documents.get(uri) {
+            Some(document) => document.prefix_at(at),
+            None => return json!([]),
+        };
+
+        let library = Known::new(&[]);
+        let mut predicates = BTreeSet::new();
+
+        for document in self.documents.values() {
+            predicates.extend(document.definitions().into_iter().map(|(key, _)| key));
+        }
+
+        predicates.extend(
+            library
+                .defined()
+                .map(|(name, arity)| (name.to_string(), arity)),
+        );
+        predicates.extend(builtins().map(|(name, arity)| (name.to_string(), arity)));
+
+        let items: Vec<_> = predicates
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(&prefix) && !name.starts_with('$'))
+            .map(|(name, arity)| {
+                json!({
+                    "label": name,
+                    "detail": format!("{}/{}", name, arity),
+                    "kind": 3,
+                })
+            })
+            .collect();
+
+        json!(items)
+    }
+
+    /// Handles `message`, returning the messages to send back.
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        let result = match message["method"].as_str().unwrap_or_default() {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": {"name": "bfg-prolog-lsp", "version": env!("CARGO_PKG_VERSION")},
+            }),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            "textDocument/definition" => self.definition(uri, position(&params["position"])),
+            "textDocument/completion" => self.completion(uri, position(&params["position"])),
+            "textDocument/didOpen" => {
+                return vec![self.open(
+                    uri,
+                    params["textDocument"]["text"].as_str().unwrap_or_default(),
+                )];
+            }
+            "textDocument/didChange" => {
+                // The whole text is sent with each change.
+                let changes = params["contentChanges"].as_array();
+                let text = changes
+                    .and_then(|c| c.last())
+                    .and_then(|c| c["text"].as_str());
+                return text.map(|text| self.open(uri, text)).into_iter().collect();
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![json!({
+                    "method": "textDocument/publishDiagnostics",
+                    "params": {"uri": uri, "diagnostics": []},
+                })];
+            }
+            "exit" => std::process::exit(if self.shutdown { 0 } else { 1 }),
+            method if message.get("id").is_none() || method.is_empty() => return Vec::new(),
+            method => {
+                return vec![json!({
+                    "id": message["id"],
+                    "error": {
+                        "code": METHOD_NOT_FOUND,
+                        "message": format!("Unknown method {}", method),
+                    },
+                })];
+            }
+        };
+
+        vec![json!({"id": message["id"], "result": result})]
+    }
+}
+
+fn main() {
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut output = std::io::stdout();
+    let mut server = Server::default();
+
+    while let Ok(Some(message)) = read_message(&mut input) {
+        for mut reply in server.handle(&message) {
+            reply["jsonrpc"] = json!("2.0");
+
+            if write_message(&mut output, &reply).is_err() {
+                return;
+            }
+        }
+    }
+}
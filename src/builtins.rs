@@ -0,0 +1,710 @@
+mod arith;
+mod atoms;
+mod chars;
+mod clpfd;
+mod clpqr;
+mod codecs;
+mod coroutining;
+mod counters;
+#[cfg(feature = "crypto")]
+mod crypto;
+mod csv;
+pub(crate) mod engines;
+mod files;
+mod format;
+mod http;
+mod json;
+pub(crate) mod locals;
+mod mutexes;
+pub(crate) mod persistency;
+mod process;
+pub(crate) mod propagation;
+mod random;
+#[cfg(feature = "re")]
+mod re;
+mod records;
+mod sockets;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod streams;
+mod system;
+pub(crate) mod terms;
+mod threads;
+mod time;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(feature = "toml")]
+mod toml;
+mod write;
+#[cfg(feature = "xml")]
+mod xml;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+use self::chars::Repr;
+pub(crate) use self::coroutining::residual_goals;
+pub(crate) use self::files::{io_error, qsave_program};
+pub(crate) use self::http::http_server;
+pub(crate) use self::streams::set_user_output;
+pub(crate) use self::system::{
+    answer_options, argv, double_quoted, flag, flag_directive, help, iso, occurs_check,
+    reset_flags, set_argv, show_coverage, xref,
+};
+pub(crate) use self::threads::{concurrent, thread_create};
+pub(crate) use self::write::listing;
+use crate::ast::{unquote, Atom, Clause, Const, Number, Term, Var, WriteOptions};
+use crate::parser::TermParser;
+use crate::tokenizer::{int_value, lex, tokenize, TokenKind, TokenizeErr};
+use crate::{fresh, lazy_list, renumber_term, Environment, UnifyErr};
+pub(crate) use bfg_prolog_core::solve::{error, error_in};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+pub(crate) type Branch = (Environment, Clause);
+
+pub(crate) fn call(env: &Environment, goal: &Atom) -> Option<Vec<Branch>> {
+    let args = &goal.args;
+
+    let branches = match (&goal.name.0[..], goal.arity) {
+        ("=", 2) => unify(env, &args[0], &args[1]),
+        ("unify_with_occurs_check", 2) => {
+            system::with_occurs_check(|| unify(env, &args[0], &args[1]))
+        }
+        ("var", 1) => match env.substitute_term(&args[0]) {
+            Term::Var(_) => vec![(env.clone(), vec![])],
+            _ => vec![],
+        },
+        ("nonvar", 1) => match env.substitute_term(&args[0]) {
+            Term::Var(_) => vec![],
+            _ => vec![(env.clone(), vec![])],
+        },
+        ("is", 2) => arith::is(env, &args[0], &args[1]),
+        ("=:=", 2) => arith::compare_values(env, &args[0], &args[1], Ordering::is_eq),
+        ("=\\=", 2) => arith::compare_values(env, &args[0], &args[1], Ordering::is_ne),
+        ("<", 2) => arith::compare_values(env, &args[0], &args[1], Ordering::is_lt),
+        (">", 2) => arith::compare_values(env, &args[0], &args[1], Ordering::is_gt),
+        ("=<", 2) => arith::compare_values(env, &args[0], &args[1], Ordering::is_le),
+        (">=", 2) => arith::compare_values(env, &args[0], &args[1], Ordering::is_ge),
+        ("succ", 2) => arith::succ(env, &args[0], &args[1]),
+        ("plus", 3) => arith::plus(env, &args[0], &args[1], &args[2]),
+        ("between", 3) => arith::between(env, &args[0], &args[1], &args[2]),
+        ("==", 2) => terms::compare_terms(env, &args[0], &args[1], Ordering::is_eq),
+        ("\\==", 2) => terms::compare_terms(env, &args[0], &args[1], Ordering::is_ne),
+        ("@<", 2) => terms::compare_terms(env, &args[0], &args[1], Ordering::is_lt),
+        ("@>", 2) => terms::compare_terms(env, &args[0], &args[1], Ordering::is_gt),
+        ("@=<", 2) => terms::compare_terms(env, &args[0], &args[1], Ordering::is_le),
+        ("@>=", 2) => terms::compare_terms(env, &args[0], &args[1], Ordering::is_ge),
+        ("compare", 3) => terms::compare3(env, &args[0], &args[1], &args[2]),
+        ("\\=", 2) => terms::not_unifiable(env, &args[0], &args[1]),
+        ("atom", 1) => terms::type_test(env, &args[0], terms::is_atom),
+        ("number", 1) => terms::type_test(env, &args[0], |t| matches!(t, Term::Number(_))),
+        ("string", 1) => terms::type_test(env, &args[0], |t| matches!(t, Term::String(_))),
+        ("integer", 1) => {
+            terms::type_test(env, &args[0], |t| matches!(t, Term::Number(Number::Int(_))))
+        }
+        ("float", 1) => terms::type_test(env, &args[0], |t| {
+            matches!(t, Term::Number(Number::Float(_)))
+        }),
+        ("atomic", 1) => terms::type_test(env, &args[0], |t| {
+            !matches!(t, Term::Var(_)) && !terms::is_compound(t)
+        }),
+        ("compound", 1) => terms::type_test(env, &args[0], terms::is_compound),
+        ("callable", 1) => terms::type_test(env, &args[0], |t| {
+            terms::is_atom(t) || terms::is_compound(t)
+        }),
+        ("is_list", 1) => terms::type_test(env, &args[0], terms::is_list),
+        // No term is taken to be cyclic, as only unifying with the occurs check off makes one.
+        ("acyclic_term", 1) => vec![(env.clone(), vec![])],
+        ("cyclic_term", 1) => vec![],
+        ("functor", 3) => terms::functor(env, &args[0], &args[1], &args[2]),
+        ("arg", 3) => terms::arg(env, &args[0], &args[1], &args[2]),
+        ("=..", 2) => terms::univ(env, &args[0], &args[1]),
+        ("numbervars", 3) => terms::numbervars(env, &args[0], &args[1], &args[2]),
+        ("copy_term", 2) => terms::copy_term(env, &args[0], &args[1]),
+        ("copy_term", 3) => coroutining::copy_term_goals(env, &args[0], &args[1], &args[2]),
+        ("#=", 2) | ("#\\=", 2) | ("#<", 2) | ("#>", 2) | ("#=<", 2) | ("#>=", 2) => {
+            clpfd::constrain(env, &goal.name.0, &args[0], &args[1])
+        }
+        ("#<==>", 2)
+        | ("#==>", 2)
+        | ("#<==", 2)
+        | ("#\\/", 2)
+        | ("#/\\", 2)
+        | ("#\\", 2)
+        | ("#\\", 1) => clpfd::reified(env, &Term::Atom(goal.clone())),
+        ("in", 2) => clpfd::ins(env, &args[..1], &args[1]),
+        ("ins", 2) => clpfd::in_list(env, &args[0], &args[1]),
+        ("all_different", 1) => clpfd::all_different(env, &args[0], false),
+        ("all_distinct", 1) => clpfd::all_different(env, &args[0], true),
+        ("sum", 3) => clpfd::sum(env, &args[0], &args[1], &args[2]),
+        ("global_cardinality", 2) => clpfd::global_cardinality(env, &args[0], &args[1]),
+        ("element", 3) => clpfd::element(env, &args[0], &args[1], &args[2]),
+        ("label", 1) => clpfd::labeling(env, &Term::nil(), &args[0]),
+        ("labeling", 2) => clpfd::labeling(env, &args[0], &args[1]),
+        ("{}", 1) => clpqr::constrain(env, &args[0]),
+        ("inf", 2) => clpqr::bound(env, &args[0], &args[1], -1),
+        ("sup", 2) => clpqr::bound(env, &args[0], &args[1], 1),
+        ("minimize", 1) => clpqr::optimize(env, &args[0], -1),
+        ("maximize", 1) => clpqr::optimize(env, &args[0], 1),
+        ("abolish_all_tables", 0) => {
+            crate::tabling::abolish_all();
+            vec![(env.clone(), vec![])]
+        }
+        ("dif", 2) => coroutining::dif(env, &args[0], &args[1]),
+        ("freeze", 2) => coroutining::freeze(env, &args[0], &args[1]),
+        ("when", 2) => coroutining::when(env, &args[0], &args[1]),
+        ("$when", 3) => coroutining::resume_when(env, goal),
+        ("put_attr", 3) => coroutining::put_attr(env, &args[0], &args[1], &args[2]),
+        ("get_attr", 3) => coroutining::get_attr(env, &args[0], &args[1], &args[2]),
+        ("del_attr", 2) => coroutining::del_attr(env, &args[0], &args[1]),
+        ("$wakeup", 3) => coroutining::wakeup(env, &args[0], &args[1], &args[2]),
+        ("propagate", 2) => propagation::propagate(env, &args[0], &args[1]),
+        ("$propagate", 1) => propagation::step(env, &args[0]),
+        ("phrase_from_file", 2) => match text(&env.substitute_term(&args[1])) {
+            Some(path) => match lazy_list::file_codes(&path) {
+                Ok(codes) => call_goal(env, &args[0], &[codes, Term::nil()]),
+                Err(formal) => throw(env, formal),
+            },
+            None => throw(env, instantiation_error()),
+        },
+        ("$lazy_list_rest", 2) => match lazy_list::force(&env.substitute_term(&args[0])) {
+            Ok(rest) => unify(env, &args[1], &rest),
+            Err(formal) => throw(env, formal),
+        },
+        ("prolog_tokens", 2) => prolog_tokens(env, &args[0], &args[1]),
+        ("memberchk", 2) => memberchk(env, &args[0], &args[1]),
+        ("phrase", 2) => call_goal(env, &args[0], &[args[1].clone(), Term::nil()]),
+        ("phrase", 3) => call_goal(env, &args[0], &args[1..]),
+        ("atom_string", 2) => atom_string(env, &args[0], &args[1]),
+        ("number_string", 2) => number_string(env, &args[0], &args[1]),
+        ("term_string", 2) => term_string(env, &args[0], &args[1]),
+        ("atom_length", 2) => atoms::length(env, &args[0], &args[1], "atom"),
+        ("string_length", 2) => atoms::length(env, &args[0], &args[1], "string"),
+        ("atom_concat", 3) => atoms::concat(env, &args[0], &args[1], &args[2], atom),
+        ("string_concat", 3) => atoms::concat(env, &args[0], &args[1], &args[2], string),
+        ("atom_codes", 2) => text_list(env, &args[0], &args[1], |t| Some(atom(t)), codes),
+        ("atom_chars", 2) => text_list(env, &args[0], &args[1], |t| Some(atom(t)), chars),
+        ("char_code", 2) => char_code(env, &args[0], &args[1]),
+        ("number_codes", 2) => text_list(env, &args[0], &args[1], number, codes),
+        ("string_codes", 2) => text_list(env, &args[0], &args[1], |t| Some(string(t)), codes),
+        ("string_chars", 2) => text_list(env, &args[0], &args[1], |t| Some(string(t)), chars),
+        #[cfg(feature = "re")]
+        ("re_match", 2) => re::re_match(env, &args[0], &args[1]),
+        #[cfg(feature = "re")]
+        ("re_match", 3) => re::re_match_captures(env, &args[0], &args[1], &args[2]),
+        #[cfg(feature = "re")]
+        ("re_replace", 4) => re::re_replace(env, &args[0], &args[1], &args[2], &args[3]),
+        ("base64", 2) => codecs::base64(env, &args[0], &args[1]),
+        ("hex_bytes", 2) => codecs::hex_bytes(env, &args[0], &args[1]),
+        ("uri_encoded", 3) => codecs::uri_encoded(env, &args[0], &args[1], &args[2]),
+        #[cfg(feature = "crypto")]
+        ("crypto_data_hash", 3) => crypto::crypto_data_hash(env, &args[0], &args[1], &args[2]),
+        ("write", 1) => write::write(env, None, &args[0], write::unquoted()),
+        ("write", 2) => write::write(env, Some(&args[0]), &args[1], write::unquoted()),
+        ("print", 1) | ("writeq", 1) => write::write(env, None, &args[0], WriteOptions::default()),
+        ("print", 2) | ("writeq", 2) => {
+            write::write(env, Some(&args[0]), &args[1], WriteOptions::default())
+        }
+        ("write_canonical", 1) => write::write(env, None, &args[0], write::canonical()),
+        ("write_canonical", 2) => write::write(env, Some(&args[0]), &args[1], write::canonical()),
+        ("write_term", 2) => write::write_term(env, None, &args[0], &args[1]),
+        ("write_term", 3) => write::write_term(env, Some(&args[0]), &args[1], &args[2]),
+        ("nl", 0) => write::nl(env, None),
+        ("nl", 1) => write::nl(env, Some(&args[0])),
+        ("portray_clause", 1) => write::portray_clause(env, &args[0]),
+        ("current_op", 3) => write::current_op(env, &args[0], &args[1], &args[2]),
+        ("format", 1) => format::format(env, None, &args[0], &Term::nil()),
+        ("format", 2) => format::format(env, None, &args[0], &args[1]),
+        ("format", 3) => format::format(env, Some(&args[0]), &args[1], &args[2]),
+        ("random", 1) => random::random(env, &args[0]),
+        ("random_between", 3) => random::random_between(env, &args[0], &args[1], &args[2]),
+        ("random_member", 2) => random::random_member(env, &args[0], &args[1]),
+        ("random_permutation", 2) => random::random_permutation(env, &args[0], &args[1]),
+        ("set_random", 1) => random::set_random(env, &args[0]),
+        ("gensym", 2) => counters::gensym(env, &args[0], &args[1]),
+        ("reset_gensym", 0) => counters::reset_gensym(env, None),
+        ("reset_gensym", 1) => counters::reset_gensym(env, Some(&args[0])),
+        ("flag", 3) => counters::flag(env, &args[0], &args[1], &args[2]),
+        ("recorda", 2) => records::record(env, &args[0], &args[1], None, true),
+        ("recorda", 3) => records::record(env, &args[0], &args[1], Some(&args[2]), true),
+        ("recordz", 2) => records::record(env, &args[0], &args[1], None, false),
+        ("recordz", 3) => records::record(env, &args[0], &args[1], Some(&args[2]), false),
+        ("recorded", 2) => records::recorded(env, &args[0], &args[1], None),
+        ("recorded", 3) => records::recorded(env, &args[0], &args[1], Some(&args[2])),
+        ("erase", 1) => records::erase(env, &args[0]),
+        ("instance", 2) => records::instance(env, &args[0], &args[1]),
+        ("engine_create", 3) => {
+            engines::engine_create(env, &args[0], &args[1], &args[2], &Term::nil())
+        }
+        ("engine_create", 4) => engines::engine_create(env, &args[0], &args[1], &args[2], &args[3]),
+        ("engine_destroy", 1) => engines::engine_destroy(env, &args[0]),
+        ("engine_yield", 1) => engines::engine_yield(env),
+        ("thread_join", 2) => threads::thread_join(env, &args[0], &args[1]),
+        ("thread_self", 1) => threads::thread_self(env, &args[0]),
+        ("thread_send_message", 2) => threads::thread_send_message(env, &args[0], &args[1]),
+        ("thread_get_message", 1) => threads::thread_get_message(env, None, &args[0]),
+        ("thread_get_message", 2) => threads::thread_get_message(env, Some(&args[0]), &args[1]),
+        ("mutex_create", 1) => mutexes::mutex_create(env, &args[0]),
+        ("mutex_destroy", 1) => mutexes::mutex_destroy(env, &args[0]),
+        ("mutex_lock", 1) => mutexes::mutex_lock(env, &args[0], true),
+        ("mutex_trylock", 1) => mutexes::mutex_lock(env, &args[0], false),
+        ("mutex_unlock", 1) => mutexes::mutex_unlock(env, &args[0]),
+        ("get_time", 1) => time::get_time(env, &args[0]),
+        ("sleep", 1) => time::sleep(env, &args[0]),
+        ("$push_time_limit", 2) => time::push_time_limit(env, &args[0], &args[1]),
+        ("$pop_time_limit", 1) => time::pop_time_limit(env, &args[0]),
+        ("$start_coverage", 0) => system::start_coverage(env),
+        #[cfg(feature = "time")]
+        ("stamp_date_time", 3) => time::stamp_date_time(env, &args[0], &args[1], &args[2]),
+        #[cfg(feature = "time")]
+        ("format_time", 3) => time::format_time(env, &args[0], &args[1], &args[2]),
+        ("tab", 1) => streams::tab(env, None, &args[0]),
+        ("tab", 2) => streams::tab(env, Some(&args[0]), &args[1]),
+        ("line_count", 2) => streams::line_count(env, &args[0], &args[1]),
+        ("line_position", 2) => streams::line_position(env, &args[0], &args[1]),
+        ("exists_file", 1) => files::exists_file(env, &args[0]),
+        ("exists_directory", 1) => files::exists_directory(env, &args[0]),
+        ("directory_files", 2) => files::directory_files(env, &args[0], &args[1]),
+        ("delete_file", 1) => files::delete_file(env, &args[0]),
+        ("make_directory", 1) => files::make_directory(env, &args[0]),
+        ("delete_directory", 1) => files::delete_directory(env, &args[0]),
+        ("size_file", 2) => files::size_file(env, &args[0], &args[1]),
+        ("absolute_file_name", 2) => files::absolute_file_name(env, &args[0], &args[1], None),
+        ("absolute_file_name", 3) => {
+            files::absolute_file_name(env, &args[0], &args[1], Some(&args[2]))
+        }
+        ("getenv", 2) => system::getenv(env, &args[0], &args[1]),
+        ("setenv", 2) => system::setenv(env, &args[0], &args[1]),
+        ("unsetenv", 1) => system::unsetenv(env, &args[0]),
+        ("current_prolog_flag", 2) => system::current_prolog_flag(env, &args[0], &args[1]),
+        ("set_prolog_flag", 2) => system::set_prolog_flag(env, &args[0], &args[1]),
+        ("on_signal", 3) => system::on_signal(env, &args[0], &args[1], &args[2]),
+        ("read_line_to_string", 2) => streams::read_line_to_string(env, &args[0], &args[1]),
+        ("read_string", 3) => streams::read_string(env, &args[0], &args[1], &args[2]),
+        ("fast_read", 2) => streams::fast_read(env, &args[0], &args[1]),
+        ("fast_write", 2) => streams::fast_write(env, &args[0], &args[1]),
+        ("flush_output", 0) => streams::flush_output(env, None),
+        ("flush_output", 1) => streams::flush_output(env, Some(&args[0])),
+        ("close", 1) => streams::close(env, &args[0]),
+        ("process_create", 3) => process::process_create(env, &args[0], &args[1], &args[2]),
+        ("process_wait", 2) | ("wait", 2) => process::process_wait(env, &args[0], &args[1]),
+        ("shell", 1) => process::shell(env, &args[0], None, None),
+        ("shell", 2) => process::shell(env, &args[0], Some(&args[1]), None),
+        ("shell", 3) => process::shell(env, &args[0], Some(&args[1]), Some(&args[2])),
+        ("tcp_connect", 3) => sockets::tcp_connect(env, &args[0], &args[1], &args[2]),
+        ("tcp_listen", 2) => sockets::tcp_listen(env, &args[0], &args[1]),
+        ("tcp_accept", 3) => sockets::tcp_accept(env, &args[0], &args[1], &args[2]),
+        ("tcp_close_socket", 1) => sockets::tcp_close_socket(env, &args[0]),
+        ("http_stop_server", 2) => http::http_stop_server(env, &args[0]),
+        #[cfg(feature = "tls")]
+        ("tls_connect", 3) => tls::tls_connect(env, &args[0], &args[1], &args[2], None),
+        #[cfg(feature = "tls")]
+        ("tls_connect", 4) => tls::tls_connect(env, &args[0], &args[1], &args[2], Some(&args[3])),
+        #[cfg(feature = "tls")]
+        ("tls_listen", 3) => tls::tls_listen(env, &args[0], &args[1], &args[2]),
+        ("json_read", 2) => json::json_read(env, &args[0], &args[1], false),
+        ("json_read_dict", 2) => json::json_read(env, &args[0], &args[1], true),
+        ("json_write", 2) => json::json_write(env, &args[0], &args[1]),
+        ("atom_json_term", 3) => json::atom_json_term(env, &args[0], &args[1], &args[2]),
+        ("csv_read_file", 3) => csv::csv_read_file(env, &args[0], &args[1], &args[2]),
+        ("csv_read_file_row", 3) => csv::csv_read_file_row(env, &args[0], 0, &args[1], &args[2]),
+        ("$csv_read_file_row", 4) => match args[1] {
+            Term::Number(Number::Int(offset)) => {
+                csv::csv_read_file_row(env, &args[0], offset as u64, &args[2], &args[3])
+            }
+            _ => vec![],
+        },
+        ("csv_read_row", 3) => csv::csv_read_row(env, &args[0], &args[1], &args[2]),
+        ("csv_write_file", 3) => csv::csv_write_file(env, &args[0], &args[1], &args[2]),
+        ("csv_write_stream", 3) => csv::csv_write_stream(env, &args[0], &args[1], &args[2]),
+        #[cfg(feature = "xml")]
+        ("load_xml", 3) => xml::load_xml(env, &args[0], &args[1], &args[2], false),
+        #[cfg(feature = "xml")]
+        ("load_html", 3) => xml::load_xml(env, &args[0], &args[1], &args[2], true),
+        #[cfg(feature = "xml")]
+        ("xml_write", 3) => xml::xml_write(env, &args[0], &args[1], &args[2]),
+        #[cfg(feature = "yaml")]
+        ("load_yaml", 3) => yaml::load_yaml(env, &args[0], &args[1], &args[2]),
+        #[cfg(feature = "toml")]
+        ("load_toml", 3) => toml::load_toml(env, &args[0], &args[1], &args[2]),
+        #[cfg(feature = "sqlite")]
+        ("sqlite_open", 2) => sqlite::sqlite_open(env, &args[0], &args[1]),
+        #[cfg(feature = "sqlite")]
+        ("sqlite_close", 1) => sqlite::sqlite_close(env, &args[0]),
+        #[cfg(feature = "sqlite")]
+        ("sqlite_query", 4) => sqlite::sqlite_query(env, &args[0], &args[1], &args[2], &args[3]),
+        ("char_type", 2) => chars::char_type(env, &args[0], &args[1], Repr::Char),
+        ("code_type", 2) => chars::char_type(env, &args[0], &args[1], Repr::Code),
+        ("db_attach", 2) => persistency::db_attach(env, &args[0]),
+        ("db_detach", 0) => persistency::db_detach(env),
+        ("db_sync", 1) => persistency::db_sync(env),
+        ("$retract_persistent", 1) => persistency::retract(env, &args[0]),
+        ("assert", 1) | ("assertz", 1) => locals::assert(env, &args[0], false),
+        ("asserta", 1) => locals::assert(env, &args[0], true),
+        ("retract", 1) => locals::retract(env, &args[0]),
+        ("retractall", 1) => locals::retractall(env, &args[0]),
+        ("$retract", 1) => locals::remove(env, &args[0]),
+        ("dynamic", 1) => locals::dynamic(env, &args[0]),
+        _ => return persistency::call(env, goal).map(|branches| in_context(goal, branches)),
+    };
+
+    Some(in_context(goal, branches))
+}
+
+fn unify(env: &Environment, t1: &Term, t2: &Term) -> Vec<Branch> {
+    match env.clone().unify_terms(t1, t2) {
+        Ok(env) => vec![(env, vec![])],
+        Err(UnifyErr::NoUnify) => vec![],
+        Err(UnifyErr::Error(formal)) => throw(env, formal),
+    }
+}
+
+/// Fills in the context of the error a builtin raises in place of `goal` with the predicate
+/// indicator of `goal`.
+pub(crate) fn in_context(goal: &Atom, mut branches: Vec<Branch>) -> Vec<Branch> {
+    if let [(_, goals)] = &mut branches[..] {
+        if let [raise] = &mut goals[..] {
+            if let ("throw", [Term::Atom(e)]) = (&raise.name.0[..], &raise.args[..]) {
+                if let ("error", [formal, Term::Var(_)]) = (&e.name.0[..], &e.args[..]) {
+                    *raise = Atom::new("throw", vec![error_in(goal, formal.clone())]);
+                }
+            }
+        }
+    }
+
+    branches
+}
+
+/// Raises `error(Formal, _)` in place of the goal.
+fn throw(env: &Environment, formal: Term) -> Vec<Branch> {
+    vec![(env.clone(), vec![Atom::new("throw", vec![error(formal)])])]
+}
+
+fn instantiation_error() -> Term {
+    atom("instantiation_error")
+}
+
+pub(crate) fn type_error(kind: &str, culprit: Term) -> Term {
+    Term::Atom(Atom::new("type_error", vec![atom(kind), culprit]))
+}
+
+fn domain_error(domain: &str, culprit: Term) -> Term {
+    Term::Atom(Atom::new("domain_error", vec![atom(domain), culprit]))
+}
+
+fn existence_error(kind: &str, culprit: Term) -> Term {
+    Term::Atom(Atom::new("existence_error", vec![atom(kind), culprit]))
+}
+
+fn representation_error(limit: &str) -> Term {
+    Term::Atom(Atom::new("representation_error", vec![atom(limit)]))
+}
+
+fn syntax_error(reason: &str) -> Term {
+    Term::Atom(Atom::new("syntax_error", vec![atom(reason)]))
+}
+
+fn call_goal(env: &Environment, goal: &Term, extra: &[Term]) -> Vec<Branch> {
+    match env.substitute_term(goal) {
+        Term::Atom(a) => {
+            let mut args = a.args;
+            args.extend_from_slice(extra);
+
+            vec![(env.clone(), vec![Atom::new(&a.name.0, args)])]
+        }
+        Term::Var(_) => throw(env, instantiation_error()),
+        goal => throw(env, type_error("callable", goal)),
+    }
+}
+
+/// Unifies `x` with the first element of `list` it unifies with, walking the list a cell at a
+/// time so that a partial list is searched as far as it goes and then has `x` added at its end.
+fn memberchk(env: &Environment, x: &Term, list: &Term) -> Vec<Branch> {
+    let mut list = env.dereference(list);
+
+    loop {
+        list = match list {
+            Term::Atom(Atom {
+                name: Const(ref name),
+                ref args,
+                ..
+            }) if name == "." && args.len() == 2 => {
+                let branches = unify(env, x, &args[0]);
+
+                if !branches.is_empty() {
+                    return branches;
+                }
+
+                env.dereference(&args[1])
+            }
+            Term::PartialString(ref chunk, ref tail) => {
+                for c in chunk.chars() {
+                    let branches = unify(env, x, &Term::Number(Number::Int(c as i64)));
+
+                    if !branches.is_empty() {
+                        return branches;
+                    }
+                }
+
+                env.dereference(tail)
+            }
+            Term::Var(_) => {
+                let rest = Term::Var(Var::new("_", fresh()));
+                return unify(env, &list, &Term::list(vec![x.clone()], rest));
+            }
+            t => match lazy_list::expand(&t) {
+                Some(Ok(t)) => t,
+                Some(Err(formal)) => return throw(env, formal),
+                None => return vec![],
+            },
+        };
+    }
+}
+
+fn atom(name: &str) -> Term {
+    Term::Atom(Atom::new(name, vec![]))
+}
+
+fn string(text: &str) -> Term {
+    Term::String(String::from(text))
+}
+
+fn text(t: &Term) -> Option<String> {
+    match t {
+        Term::Atom(Atom {
+            name: Const(name),
+            arity: 0,
+            ..
+        }) => Some(name.clone()),
+        Term::Number(n) => Some(n.to_string()),
+        Term::String(s) => Some(s.clone()),
+        Term::PartialString(..) | Term::Atom(_) => list_text(t),
+        _ => None,
+    }
+}
+
+fn list_text(t: &Term) -> Option<String> {
+    let mut text = String::new();
+    let mut t = t;
+
+    loop {
+        match t {
+            Term::PartialString(chunk, tail) => {
+                text.push_str(chunk);
+                t = tail;
+            }
+            Term::Atom(Atom {
+                name: Const(name),
+                args,
+                ..
+            }) if name == "." && args.len() == 2 => {
+                match &args[0] {
+                    Term::Number(Number::Int(code)) => {
+                        text.push(std::char::from_u32(*code as u32)?);
+                    }
+                    Term::Atom(Atom {
+                        name: Const(c),
+                        arity: 0,
+                        ..
+                    }) if c.chars().count() == 1 => text.push_str(c),
+                    _ => return None,
+                }
+
+                t = &args[1];
+            }
+            t if t.is_nil() => return Some(text),
+            _ => return None,
+        }
+    }
+}
+
+pub(crate) fn list_items(t: &Term) -> Option<Vec<Term>> {
+    let mut items = Vec::new();
+    let mut t = t;
+
+    loop {
+        match t {
+            Term::PartialString(chunk, tail) => {
+                items.extend(chunk.chars().map(|c| Term::Number(Number::Int(c as i64))));
+                t = tail;
+            }
+            Term::Atom(Atom {
+                name: Const(name),
+                args,
+                ..
+            }) if name == "." && args.len() == 2 => {
+                items.push(args[0].clone());
+                t = &args[1];
+            }
+            t if t.is_nil() => return Some(items),
+            _ => return None,
+        }
+    }
+}
+
+fn codes(text: &str) -> Term {
+    Term::partial_string(text, Term::nil())
+}
+
+fn chars(text: &str) -> Term {
+    let chars = text.chars().map(|c| atom(&c.to_string())).collect();
+    Term::list(chars, Term::nil())
+}
+
+fn number(text: &str) -> Option<Term> {
+    Number::parse(text.trim_end()).map(Term::Number)
+}
+
+fn text_list(
+    env: &Environment,
+    t: &Term,
+    l: &Term,
+    make: fn(&str) -> Option<Term>,
+    make_list: fn(&str) -> Term,
+) -> Vec<Branch> {
+    let t = env.substitute_term(t);
+
+    if let Some(text) = text(&t) {
+        return unify(env, &make_list(&text), l);
+    }
+
+    let l = env.substitute_term(l);
+    let list = match &l {
+        Term::String(text) => Some(text.clone()),
+        l => list_text(l),
+    };
+
+    match list.map(|text| make(&text)) {
+        Some(Some(made)) => unify(env, &t, &made),
+        Some(None) => throw(env, syntax_error("illegal_number")),
+        None if !matches!(t, Term::Var(_)) => throw(env, type_error("atomic", t)),
+        None if terms::is_partial_list(&l) => throw(env, instantiation_error()),
+        None => throw(env, type_error("list", l)),
+    }
+}
+
+fn atom_string(env: &Environment, a: &Term, s: &Term) -> Vec<Branch> {
+    let a = env.substitute_term(a);
+    let s = env.substitute_term(s);
+
+    match (text(&a), text(&s)) {
+        (Some(t), _) => unify(env, &Term::String(t), &s),
+        (None, Some(t)) => unify(env, &a, &atom(&t)),
+        _ if matches!(a, Term::Var(_)) => throw(env, instantiation_error()),
+        _ => throw(env, type_error("atom", a)),
+    }
+}
+
+fn number_string(env: &Environment, n: &Term, s: &Term) -> Vec<Branch> {
+    let n = env.substitute_term(n);
+    let s = env.substitute_term(s);
+
+    match (&n, text(&s)) {
+        (_, Some(t)) => match Number::parse(t.trim_end()) {
+            Some(number) => unify(env, &n, &Term::Number(number)),
+            None => throw(env, syntax_error("illegal_number")),
+        },
+        (Term::Number(number), None) => unify(env, &Term::String(number.to_string()), &s),
+        (Term::Var(_), None) if matches!(s, Term::Var(_)) => throw(env, instantiation_error()),
+        (Term::Var(_), None) => throw(env, type_error("string", s)),
+        _ => throw(env, type_error("number", n)),
+    }
+}
+
+fn char_code(env: &Environment, c: &Term, code: &Term) -> Vec<Branch> {
+    let c = env.substitute_term(c);
+    let code = env.substitute_term(code);
+
+    match (&c, &code) {
+        (Term::Var(_), Term::Var(_)) => throw(env, instantiation_error()),
+        (Term::Var(_), Term::Number(Number::Int(i))) => {
+            match u32::try_from(*i).ok().and_then(std::char::from_u32) {
+                Some(ch) => unify(env, &c, &atom(&ch.to_string())),
+                None => throw(env, representation_error("character_code")),
+            }
+        }
+        (Term::Var(_), _) => throw(env, type_error("integer", code)),
+        _ => match text(&c) {
+            Some(ref name) if terms::is_atom(&c) && name.chars().count() == 1 => {
+                let ch = name.chars().next().unwrap();
+                unify(env, &code, &Term::Number(Number::Int(ch as i64)))
+            }
+            _ => throw(env, type_error("character", c)),
+        },
+    }
+}
+
+fn parse_term(text: &str) -> Option<Term> {
+    let parser = TermParser::new();
+    let text = text.trim();
+
+    match parser.parse(lex(text)) {
+        Ok(t) => Some(t),
+        Err(_) if text.ends_with('.') => parser.parse(lex(&text[..text.len() - 1])).ok(),
+        Err(_) => None,
+    }
+}
+
+fn term_string(env: &Environment, s: &Term, t: &Term) -> Vec<Branch> {
+    let s = env.substitute_term(s);
+
+    match text(&s) {
+        Some(source) => match parse_term(&source) {
+            Some(parsed) => {
+                let parsed = double_quoted(&parsed, &flag("double_quotes"));
+                unify(env, &renumber_term(fresh(), &parsed), t)
+            }
+            None => throw(env, syntax_error("illegal_term")),
+        },
+        None if !matches!(s, Term::Var(_)) => throw(env, type_error("string", s)),
+        None => {
+            let t = env.substitute_term(t);
+            unify(env, &Term::String(t.to_string()), &s)
+        }
+    }
+}
+
+fn prolog_tokens(env: &Environment, source: &Term, tokens: &Term) -> Vec<Branch> {
+    let source = match env.substitute_term(source) {
+        Term::Var(_) => return throw(env, instantiation_error()),
+        source => match text(&source) {
+            Some(text) => text,
+            None => return throw(env, type_error("string", source)),
+        },
+    };
+
+    let tokens_found = match tokenize(&source) {
+        Ok(tokens) => tokens,
+        Err(TokenizeErr::UnexpectedChar(..)) => {
+            return throw(env, syntax_error("illegal_character"))
+        }
+        Err(TokenizeErr::Unterminated(..)) => return throw(env, syntax_error("end_of_file")),
+    };
+
+    let items = tokens_found
+        .into_iter()
+        .map(|token| {
+            let value = match token.kind {
+                TokenKind::QuotedAtom => atom(&unquote(&token.text)),
+                TokenKind::String => string(&unquote(&token.text)),
+                TokenKind::BackQuoted => codes(&unquote(&token.text)),
+                TokenKind::Int => match int_value(&token.text) {
+                    Some(n) => Term::Number(Number::Int(n)),
+                    None => atom(&token.text),
+                },
+                TokenKind::Float => number(&token.text).unwrap_or_else(|| atom(&token.text)),
+                TokenKind::Comment | TokenKind::QuasiQuotation => string(&token.text),
+                _ => atom(&token.text),
+            };
+
+            let position = |n: usize| Term::Number(Number::Int(n as i64));
+
+            Term::Atom(Atom::new(
+                token.kind.name(),
+                vec![value, position(token.line), position(token.column)],
+            ))
+        })
+        .collect();
+
+    unify(env, tokens, &Term::list(items, Term::nil()))
+}
@@ -0,0 +1,400 @@
+use super::{atom, instantiation_error, iso, throw, type_error, unify, Branch};
+use crate::ast::{op, Atom, Number, Term};
+use crate::Environment;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+/// Functions that are only defined on integers.
+const INTEGER_FUNCTIONS: &[&str] = &[
+    "\\", "msb", "succ", "//", "rem", "mod", "div", ">>", "<<", "/\\", "\\/", "xor", "gcd",
+];
+
+/// The evaluable functors of the standard and its corrigenda, the only ones there are when the
+/// `iso` flag is set.
+const ISO_FUNCTIONS: &[(&str, usize)] = &[
+    ("pi", 0),
+    ("-", 1),
+    ("+", 1),
+    ("abs", 1),
+    ("sign", 1),
+    ("float", 1),
+    ("float_integer_part", 1),
+    ("float_fractional_part", 1),
+    ("truncate", 1),
+    ("round", 1),
+    ("ceiling", 1),
+    ("floor", 1),
+    ("sqrt", 1),
+    ("sin", 1),
+    ("cos", 1),
+    ("tan", 1),
+    ("asin", 1),
+    ("acos", 1),
+    ("atan", 1),
+    ("exp", 1),
+    ("log", 1),
+    ("\\", 1),
+    ("+", 2),
+    ("-", 2),
+    ("*", 2),
+    ("/", 2),
+    ("//", 2),
+    ("rem", 2),
+    ("mod", 2),
+    ("div", 2),
+    ("min", 2),
+    ("max", 2),
+    ("**", 2),
+    ("^", 2),
+    ("atan2", 2),
+    ("atan", 2),
+    (">>", 2),
+    ("<<", 2),
+    ("/\\", 2),
+    ("\\/", 2),
+    ("xor", 2),
+];
+
+/// Evaluates the arithmetic expression `t`, returning the formal part of the ISO error it raises
+/// if it is unbound, not evaluable, or its value is undefined or overflows.
+pub(crate) fn eval(env: &Environment, t: &Term) -> Result<Number, Term> {
+    match env.substitute_term(t) {
+        Term::Number(n) => Ok(n),
+        Term::Var(_) => Err(instantiation_error()),
+        Term::Atom(a) => eval_atom(env, &a),
+        Term::PartialString(ref text, ref tail)
+            if text.chars().count() == 1 && tail.is_nil() && !iso() =>
+        {
+            Ok(Number::Int(text.chars().next().unwrap() as i64))
+        }
+        t => Err(type_error("evaluable", t)),
+    }
+}
+
+fn evaluation_error(kind: &str) -> Term {
+    Term::Atom(Atom::new("evaluation_error", vec![atom(kind)]))
+}
+
+fn overflow() -> Term {
+    evaluation_error("int_overflow")
+}
+
+fn eval_atom(env: &Environment, a: &Atom) -> Result<Number, Term> {
+    use self::Number::{Float, Int};
+
+    let name = &a.name.0[..];
+    let not_evaluable = || {
+        let indicator = op("/", atom(name), Term::Number(Int(a.args.len() as i64)));
+        type_error("evaluable", indicator)
+    };
+    let not_integer = |args: &[Number]| match args.iter().find(|x| matches!(x, Float(_))) {
+        Some(x) if INTEGER_FUNCTIONS.contains(&name) => type_error("integer", Term::Number(*x)),
+        _ => not_evaluable(),
+    };
+
+    if iso() && !ISO_FUNCTIONS.contains(&(name, a.args.len())) {
+        return Err(not_evaluable());
+    }
+
+    // Whether an infinite value comes of the arguments rather than of an overflow.
+    let infinite_args;
+
+    let value = match a.args.len() {
+        0 => {
+            infinite_args = matches!(name, "inf" | "infinite");
+
+            match name {
+                "pi" => Float(std::f64::consts::PI),
+                "e" => Float(std::f64::consts::E),
+                "inf" | "infinite" => Float(f64::INFINITY),
+                "nan" => Float(f64::NAN),
+                "epsilon" => Float(f64::EPSILON),
+                "max_tagged_integer" => Int(i64::MAX),
+                "min_tagged_integer" => Int(i64::MIN),
+                _ => return Err(not_evaluable()),
+            }
+        }
+        1 => {
+            let x = eval(env, &a.args[0])?;
+            infinite_args = float(x).is_infinite();
+
+            match (name, x) {
+                ("-", Int(x)) => Int(x.checked_neg().ok_or_else(overflow)?),
+                ("-", Float(x)) => Float(-x),
+                ("+", x) => x,
+                ("abs", Int(x)) => Int(x.checked_abs().ok_or_else(overflow)?),
+                ("abs", Float(x)) => Float(x.abs()),
+                ("sign", Int(x)) => Int(x.signum()),
+                ("sign", Float(x)) => Float(if x == 0.0 { 0.0 } else { x.signum() }),
+                ("\\", Int(x)) => Int(!x),
+                ("msb", Int(x)) if x > 0 => Int(63 - i64::from(x.leading_zeros())),
+                ("succ", Int(x)) => Int(x.checked_add(1).ok_or_else(overflow)?),
+                ("float", x) => Float(float(x)),
+                ("integer", Int(x)) => Int(x),
+                ("integer", Float(x)) => Int(to_int(x.round())?),
+                ("float_integer_part", x) => Float(float(x).trunc()),
+                ("float_fractional_part", x) => Float(float(x).fract()),
+                ("truncate", Int(x))
+                | ("round", Int(x))
+                | ("ceiling", Int(x))
+                | ("floor", Int(x)) => Int(x),
+                ("truncate", Float(x)) => Int(to_int(x.trunc())?),
+                ("round", Float(x)) => Int(to_int(x.round())?),
+                ("ceiling", Float(x)) => Int(to_int(x.ceil())?),
+                ("floor", Float(x)) => Int(to_int(x.floor())?),
+                ("sqrt", x) if float(x) >= 0.0 => Float(float(x).sqrt()),
+                ("sin", x) => Float(float(x).sin()),
+                ("cos", x) => Float(float(x).cos()),
+                ("tan", x) => Float(float(x).tan()),
+                ("asin", x) => Float(float(x).asin()),
+                ("acos", x) => Float(float(x).acos()),
+                ("atan", x) => Float(float(x).atan()),
+                ("exp", x) => Float(float(x).exp()),
+                ("log", x) if float(x) > 0.0 => Float(float(x).ln()),
+                ("log2", x) if float(x) > 0.0 => Float(float(x).log2()),
+                ("msb", Int(_)) | ("sqrt", _) | ("log", _) | ("log2", _) => {
+                    return Err(evaluation_error("undefined"))
+                }
+                (_, x) => return Err(not_integer(&[x])),
+            }
+        }
+        2 => {
+            let x = eval(env, &a.args[0])?;
+            let y = eval(env, &a.args[1])?;
+            infinite_args = float(x).is_infinite() || float(y).is_infinite();
+
+            match (name, x, y) {
+                ("+", Int(x), Int(y)) => Int(x.checked_add(y).ok_or_else(overflow)?),
+                ("-", Int(x), Int(y)) => Int(x.checked_sub(y).ok_or_else(overflow)?),
+                ("*", Int(x), Int(y)) => Int(x.checked_mul(y).ok_or_else(overflow)?),
+                ("+", x, y) => Float(float(x) + float(y)),
+                ("-", x, y) => Float(float(x) - float(y)),
+                ("*", x, y) => Float(float(x) * float(y)),
+                ("/", _, y) if float(y) == 0.0 => return Err(evaluation_error("zero_divisor")),
+                ("//", Int(_), Int(0))
+                | ("rem", Int(_), Int(0))
+                | ("mod", Int(_), Int(0))
+                | ("div", Int(_), Int(0)) => return Err(evaluation_error("zero_divisor")),
+                ("/", Int(x), Int(y)) if x.checked_rem(y) == Some(0) && !iso() => Int(x / y),
+                ("/", x, y) => Float(float(x) / float(y)),
+                ("//", Int(x), Int(y)) => Int(x.checked_div(y).ok_or_else(overflow)?),
+                ("rem", Int(x), Int(y)) => Int(x.checked_rem(y).ok_or_else(overflow)?),
+                ("mod", Int(x), Int(y)) => Int(x
+                    .checked_rem(y)
+                    .map(|r| {
+                        if r != 0 && (r < 0) != (y < 0) {
+                            r + y
+                        } else {
+                            r
+                        }
+                    })
+                    .ok_or_else(overflow)?),
+                ("div", Int(x), Int(y)) => {
+                    let q = x.checked_div(y).ok_or_else(overflow)?;
+                    Int(if q * y != x && (x < 0) != (y < 0) {
+                        q - 1
+                    } else {
+                        q
+                    })
+                }
+                ("min", x, y) => match compare(x, y) {
+                    Ordering::Greater => y,
+                    _ => x,
+                },
+                ("max", x, y) => match compare(x, y) {
+                    Ordering::Less => y,
+                    _ => x,
+                },
+                ("**", Int(x), Int(y)) if !iso() && y >= 0 => Int(int_pow(x, y)?),
+                ("^", Int(x), Int(y)) => Int(int_pow(x, y)?),
+                ("**", x, y) | ("^", x, y) if float(x) == 0.0 && float(y) < 0.0 => {
+                    return Err(evaluation_error("undefined"))
+                }
+                ("**", x, y) | ("^", x, y) => Float(float(x).powf(float(y))),
+                ("atan2", x, y) | ("atan", x, y) => Float(float(x).atan2(float(y))),
+                (">>", Int(x), Int(y)) => Int(shift(y, |y| x.checked_shr(y))?),
+                ("<<", Int(x), Int(y)) => Int(shift(y, |y| x.checked_shl(y))?),
+                ("/\\", Int(x), Int(y)) => Int(x & y),
+                ("\\/", Int(x), Int(y)) => Int(x | y),
+                ("xor", Int(x), Int(y)) => Int(x ^ y),
+                ("gcd", Int(x), Int(y)) => Int(gcd(x, y)),
+                ("copysign", x, y) => Float(float(x).copysign(float(y))),
+                (_, x, y) => return Err(not_integer(&[x, y])),
+            }
+        }
+        _ => return Err(not_evaluable()),
+    };
+
+    match value {
+        Float(f) if f.is_nan() && name != "nan" => Err(evaluation_error("undefined")),
+        Float(f) if f.is_infinite() && !infinite_args => Err(evaluation_error("float_overflow")),
+        value => Ok(value),
+    }
+}
+
+fn float(n: Number) -> f64 {
+    match n {
+        Number::Int(i) => i as f64,
+        Number::Float(f) => f,
+    }
+}
+
+fn to_int(f: f64) -> Result<i64, Term> {
+    if f.is_nan() {
+        Err(evaluation_error("undefined"))
+    } else if f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        Ok(f as i64)
+    } else {
+        Err(overflow())
+    }
+}
+
+fn int_pow(x: i64, y: i64) -> Result<i64, Term> {
+    match y {
+        y if y >= 0 => u32::try_from(y)
+            .ok()
+            .and_then(|y| x.checked_pow(y))
+            .ok_or_else(overflow),
+        _ if x == 1 => Ok(1),
+        _ if x == -1 => Ok(if y % 2 == 0 { 1 } else { -1 }),
+        _ if x == 0 => Err(evaluation_error("undefined")),
+        // The power is a fraction, which only a float can be.
+        _ => Err(type_error("float", Term::Number(Number::Int(x)))),
+    }
+}
+
+fn shift(y: i64, shift: impl Fn(u32) -> Option<i64>) -> Result<i64, Term> {
+    u32::try_from(y).ok().and_then(shift).ok_or_else(overflow)
+}
+
+fn gcd(mut x: i64, mut y: i64) -> i64 {
+    while y != 0 {
+        let r = x % y;
+        x = y;
+        y = r;
+    }
+
+    x.abs()
+}
+
+/// Compares two numbers by value, so that `1 =:= 1.0`.
+pub(crate) fn compare(x: Number, y: Number) -> Ordering {
+    match (x, y) {
+        (Number::Int(x), Number::Int(y)) => x.cmp(&y),
+        (x, y) => float(x).partial_cmp(&float(y)).unwrap_or(Ordering::Equal),
+    }
+}
+
+pub(super) fn is(env: &Environment, result: &Term, expr: &Term) -> Vec<Branch> {
+    match eval(env, expr) {
+        Ok(n) => unify(env, result, &Term::Number(n)),
+        Err(formal) => throw(env, formal),
+    }
+}
+
+pub(super) fn compare_values(
+    env: &Environment,
+    x: &Term,
+    y: &Term,
+    test: fn(Ordering) -> bool,
+) -> Vec<Branch> {
+    match eval(env, x).and_then(|x| Ok((x, eval(env, y)?))) {
+        Ok((x, y)) if test(compare(x, y)) => vec![(env.clone(), vec![])],
+        Ok(_) => vec![],
+        Err(formal) => throw(env, formal),
+    }
+}
+
+/// A natural number argument of `succ/2`, or the error raised for anything else bound.
+fn natural(env: &Environment, t: &Term) -> Result<Option<i64>, Term> {
+    match env.substitute_term(t) {
+        Term::Var(_) => Ok(None),
+        Term::Number(Number::Int(i)) if i >= 0 => Ok(Some(i)),
+        t @ Term::Number(Number::Int(_)) => Err(type_error("not_less_than_zero", t)),
+        t => Err(type_error("integer", t)),
+    }
+}
+
+pub(super) fn succ(env: &Environment, x: &Term, y: &Term) -> Vec<Branch> {
+    let args = natural(env, x).and_then(|x| Ok((x, natural(env, y)?)));
+
+    match args {
+        Ok((Some(x), _)) => match x.checked_add(1) {
+            Some(y1) => unify(env, y, &Term::Number(Number::Int(y1))),
+            None => throw(env, overflow()),
+        },
+        Ok((None, Some(0))) => vec![],
+        Ok((None, Some(y1))) => unify(env, x, &Term::Number(Number::Int(y1 - 1))),
+        Ok((None, None)) => throw(env, instantiation_error()),
+        Err(formal) => throw(env, formal),
+    }
+}
+
+/// An integer argument, None if it is unbound, or the error raised for anything else.
+fn integer(env: &Environment, t: &Term) -> Result<Option<i64>, Term> {
+    match env.substitute_term(t) {
+        Term::Var(_) => Ok(None),
+        Term::Number(Number::Int(i)) => Ok(Some(i)),
+        t => Err(type_error("integer", t)),
+    }
+}
+
+pub(super) fn plus(env: &Environment, x: &Term, y: &Term, z: &Term) -> Vec<Branch> {
+    let args = integer(env, x).and_then(|x| Ok((x, integer(env, y)?, integer(env, z)?)));
+
+    let (t, value) = match args {
+        Ok((Some(x), Some(y), _)) => (z, x.checked_add(y)),
+        Ok((Some(x), None, Some(z))) => (y, z.checked_sub(x)),
+        Ok((None, Some(y), Some(z))) => (x, z.checked_sub(y)),
+        Ok(_) => return throw(env, instantiation_error()),
+        Err(formal) => return throw(env, formal),
+    };
+
+    match value {
+        Some(value) => unify(env, t, &Term::Number(Number::Int(value))),
+        None => throw(env, overflow()),
+    }
+}
+
+/// Enumerates the integers from `low` to `high`, one per branch, leaving the rest of the range
+/// to a recursive call so that an unbounded range can be enumerated lazily.
+pub(super) fn between(env: &Environment, low: &Term, high: &Term, x: &Term) -> Vec<Branch> {
+    let low = match integer(env, low) {
+        Ok(Some(low)) => low,
+        Ok(None) => return throw(env, instantiation_error()),
+        Err(formal) => return throw(env, formal),
+    };
+
+    let high_term = env.substitute_term(high);
+    let high = match high_term {
+        Term::Number(Number::Int(high)) => high,
+        Term::Atom(ref a) if a.arity == 0 && (a.name.0 == "inf" || a.name.0 == "infinite") => {
+            i64::MAX
+        }
+        Term::Var(_) => return throw(env, instantiation_error()),
+        t => return throw(env, type_error("integer", t)),
+    };
+
+    match integer(env, x) {
+        Ok(Some(x)) if low <= x && x <= high => return vec![(env.clone(), vec![])],
+        Ok(Some(_)) => return vec![],
+        Ok(None) => (),
+        Err(formal) => return throw(env, formal),
+    }
+
+    if low > high {
+        return vec![];
+    }
+
+    let mut branches = unify(env, x, &Term::Number(Number::Int(low)));
+
+    if low < high {
+        let next = Atom::new(
+            "between",
+            vec![Term::Number(Number::Int(low + 1)), high_term, x.clone()],
+        );
+        branches.push((env.clone(), vec![next]));
+    }
+
+    branches
+}
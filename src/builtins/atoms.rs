@@ -0,0 +1,56 @@
+use super::terms::is_atom;
+use super::{domain_error, instantiation_error, iso, text, throw, type_error, unify, Branch};
+use crate::ast::{Number, Term};
+use crate::Environment;
+
+/// The length of the text `t`, which is an atom or a string as `kind` says, though any text is
+/// taken unless the `iso` flag asks for an atom to be an atom.
+pub(super) fn length(env: &Environment, t: &Term, l: &Term, kind: &str) -> Vec<Branch> {
+    let t = env.substitute_term(t);
+
+    match env.substitute_term(l) {
+        Term::Var(_) | Term::Number(Number::Int(0..)) => (),
+        l @ Term::Number(Number::Int(_)) => {
+            return throw(env, domain_error("not_less_than_zero", l))
+        }
+        l => return throw(env, type_error("integer", l)),
+    }
+
+    match (text(&t), t) {
+        (Some(_), t) if kind == "atom" && iso() && !is_atom(&t) => throw(env, type_error(kind, t)),
+        (Some(t), _) => unify(env, l, &Term::Number(Number::Int(t.chars().count() as i64))),
+        (None, Term::Var(_)) => throw(env, instantiation_error()),
+        (None, t) => throw(env, type_error(kind, t)),
+    }
+}
+
+pub(super) fn concat(
+    env: &Environment,
+    a: &Term,
+    b: &Term,
+    c: &Term,
+    make: fn(&str) -> Term,
+) -> Vec<Branch> {
+    let a = env.substitute_term(a);
+    let b = env.substitute_term(b);
+    let c = env.substitute_term(c);
+
+    match (text(&a), text(&b), text(&c)) {
+        (Some(prefix), Some(suffix), _) => unify(env, &c, &make(&(prefix + &suffix))),
+        (_, _, Some(whole)) => whole
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(whole.len()))
+            .flat_map(|i| {
+                unify(env, &a, &make(&whole[..i]))
+                    .into_iter()
+                    .flat_map(|(env, _)| unify(&env, &b, &make(&whole[i..])))
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+        (None, _, _) if !matches!(a, Term::Var(_)) => throw(env, type_error("atomic", a)),
+        (_, None, _) if !matches!(b, Term::Var(_)) => throw(env, type_error("atomic", b)),
+        (_, _, None) if matches!(c, Term::Var(_)) => throw(env, instantiation_error()),
+        _ => throw(env, type_error("atomic", c)),
+    }
+}
@@ -0,0 +1,201 @@
+use super::{atom, unify, Branch};
+use crate::ast::{Atom, Const, Number, Term, Var};
+use crate::{fresh, Environment};
+
+#[derive(Debug, Copy, Clone)]
+pub(super) enum Repr {
+    Char,
+    Code,
+}
+
+const TYPES: &[(&str, usize)] = &[
+    ("alnum", 0),
+    ("alpha", 0),
+    ("csym", 0),
+    ("csymf", 0),
+    ("ascii", 0),
+    ("white", 0),
+    ("cntrl", 0),
+    ("space", 0),
+    ("end_of_line", 0),
+    ("newline", 0),
+    ("graph", 0),
+    ("print", 0),
+    ("punct", 0),
+    ("period", 0),
+    ("quote", 0),
+    ("paren", 0),
+    ("upper", 0),
+    ("lower", 0),
+    ("digit", 1),
+    ("to_lower", 1),
+    ("to_upper", 1),
+    ("upper", 1),
+    ("lower", 1),
+    ("code", 1),
+];
+
+pub(super) fn char_type(env: &Environment, c: &Term, t: &Term, repr: Repr) -> Vec<Branch> {
+    let c = env.substitute_term(c);
+    let t = env.substitute_term(t);
+
+    match (decode(&c, repr), &t) {
+        (Some(ch), Term::Var(_)) => TYPES
+            .iter()
+            .map(|&(name, arity)| {
+                let args = vec![Term::Var(Var::new("_", fresh())); arity];
+                Term::Atom(Atom::new(name, args))
+            })
+            .flat_map(|template| {
+                unify(env, &t, &template)
+                    .into_iter()
+                    .flat_map(move |(env, _)| check(&env, ch, &template, repr))
+            })
+            .collect(),
+        (Some(ch), _) => check(env, ch, &t, repr),
+        (None, Term::Var(_)) => vec![],
+        (None, _) => {
+            let mut branches = Vec::new();
+
+            for ch in candidates(env, &t, repr) {
+                for (env, _) in unify(env, &c, &encode(ch, repr)) {
+                    branches.extend(check(&env, ch, &t, repr));
+                }
+            }
+
+            branches
+        }
+    }
+}
+
+fn check(env: &Environment, ch: char, t: &Term, repr: Repr) -> Vec<Branch> {
+    match t {
+        Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) if holds(ch, name, args.len()) => match (args.first(), value(ch, name, repr)) {
+            (Some(arg), Some(v)) => unify(env, arg, &v),
+            (None, _) => vec![(env.clone(), vec![])],
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+fn holds(ch: char, name: &str, arity: usize) -> bool {
+    match (name, arity) {
+        ("alnum", 0) => ch.is_alphanumeric(),
+        ("alpha", 0) | ("csym", 0) => ch.is_alphanumeric() || ch == '_',
+        ("csymf", 0) => ch.is_alphabetic() || ch == '_',
+        ("ascii", 0) => ch.is_ascii(),
+        ("white", 0) => ch == ' ' || ch == '\t',
+        ("cntrl", 0) => ch.is_control(),
+        ("space", 0) => ch.is_whitespace(),
+        ("end_of_line", 0) => ch == '\n' || ch == '\r',
+        ("newline", 0) => ch == '\n',
+        ("graph", 0) => !ch.is_whitespace() && !ch.is_control(),
+        ("print", 0) => !ch.is_control(),
+        ("punct", 0) => !ch.is_alphanumeric() && !ch.is_whitespace() && !ch.is_control(),
+        ("period", 0) => ch == '.' || ch == '!' || ch == '?',
+        ("quote", 0) => ch == '\'' || ch == '"' || ch == '`',
+        ("paren", 0) => ch == '(' || ch == ')',
+        ("upper", _) => ch.is_uppercase(),
+        ("lower", _) => ch.is_lowercase(),
+        ("digit", 1) => ch.is_ascii_digit(),
+        ("to_lower", 1) | ("to_upper", 1) | ("code", 1) => true,
+        _ => false,
+    }
+}
+
+fn value(ch: char, name: &str, repr: Repr) -> Option<Term> {
+    match name {
+        "digit" => ch
+            .to_digit(10)
+            .map(|weight| Term::Number(Number::Int(i64::from(weight)))),
+        "to_lower" | "upper" => Some(encode(to_lower(ch), repr)),
+        "to_upper" | "lower" => Some(encode(to_upper(ch), repr)),
+        "code" => Some(encode(ch, Repr::Code)),
+        _ => None,
+    }
+}
+
+fn candidates(env: &Environment, t: &Term, repr: Repr) -> Vec<char> {
+    let (name, arg) = match t {
+        Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) if args.len() == 1 => (&name[..], env.substitute_term(&args[0])),
+        _ => return vec![],
+    };
+
+    let ch = match (name, &arg) {
+        ("code", _) => decode(&arg, Repr::Code),
+        ("digit", Term::Number(Number::Int(weight))) if (0..10).contains(weight) => {
+            std::char::from_digit(*weight as u32, 10)
+        }
+        _ => decode(&arg, repr),
+    };
+
+    let mut chars = match (name, ch) {
+        (_, None) => vec![],
+        ("upper", Some(ch)) => vec![to_upper(ch)],
+        ("lower", Some(ch)) => vec![to_lower(ch)],
+        ("to_lower", Some(ch)) => vec![ch, to_upper(ch)],
+        ("to_upper", Some(ch)) => vec![ch, to_lower(ch)],
+        (_, Some(ch)) => vec![ch],
+    };
+
+    chars.dedup();
+    chars
+}
+
+fn to_lower(ch: char) -> char {
+    let mut lower = ch.to_lowercase();
+
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => ch,
+    }
+}
+
+fn to_upper(ch: char) -> char {
+    let mut upper = ch.to_uppercase();
+
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => u,
+        _ => ch,
+    }
+}
+
+fn decode(t: &Term, repr: Repr) -> Option<char> {
+    match (repr, t) {
+        (
+            Repr::Char,
+            Term::Atom(Atom {
+                name: Const(name),
+                arity: 0,
+                ..
+            }),
+        ) => {
+            let mut chars = name.chars();
+
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Some(ch),
+                _ => None,
+            }
+        }
+        (Repr::Code, Term::Number(Number::Int(code))) if *code >= 0 => {
+            std::char::from_u32(*code as u32)
+        }
+        _ => None,
+    }
+}
+
+fn encode(ch: char, repr: Repr) -> Term {
+    match repr {
+        Repr::Char => atom(&ch.to_string()),
+        Repr::Code => Term::Number(Number::Int(ch as i64)),
+    }
+}
@@ -0,0 +1,857 @@
+mod domain;
+mod functions;
+mod globals;
+mod reify;
+
+use self::domain::{Domain, INF, SUP};
+pub(super) use self::globals::{all_different, element, global_cardinality, sum};
+pub(super) use self::reify::reified;
+use super::propagation::{self, Priority, Queue};
+use super::{atom, list_items, throw, type_error, unify, Branch};
+use crate::ast::{op, Atom, Const, Number, Term, Var};
+use crate::Environment;
+use std::convert::TryFrom;
+
+const MODULE: &str = "clpfd";
+
+fn int(v: i64) -> Term {
+    Term::Number(Number::Int(v))
+}
+
+/// Reads the domain and propagators attached to `x`, stored as `clpfd(Domain, Propagators)`.
+fn attribute(env: &Environment, x: &Var) -> Option<(Domain, Vec<Term>)> {
+    match env.get_attr(x, MODULE)? {
+        Term::Atom(Atom { args, .. }) if args.len() == 2 => Some((
+            Domain::from_term(&args[0])?,
+            list_items(&args[1]).unwrap_or_default(),
+        )),
+        _ => None,
+    }
+}
+
+fn domain(env: &Environment, x: &Var) -> Domain {
+    attribute(env, x).map_or_else(Domain::full, |(domain, _)| domain)
+}
+
+fn store(env: &mut Environment, x: Var, domain: &Domain, props: Vec<Term>) {
+    let value = Atom::new(
+        MODULE,
+        vec![domain.to_term(), Term::list(props, Term::nil())],
+    );
+    env.put_attr(x, MODULE, Term::Atom(value));
+}
+
+/// Intersects the domain of `x` with `with`, queueing the propagators of `x` if its domain
+/// shrinks and binding `x` once a single value is left. Returns None if no value is left.
+fn narrow(env: &mut Environment, x: &Var, with: &Domain, queue: &mut Queue) -> Option<()> {
+    let (old, props) = attribute(env, x).unwrap_or_else(|| (Domain::full(), vec![]));
+    let new = old.intersect(with);
+
+    if new.is_empty() {
+        return None;
+    }
+
+    if new == old {
+        return Some(());
+    }
+
+    queue.extend(props.iter().cloned());
+
+    match new.value() {
+        Some(v) => {
+            env.del_attr(x, MODULE);
+            env.insert(x.clone(), int(v));
+        }
+        None => store(env, x.clone(), &new, props),
+    }
+
+    Some(())
+}
+
+/// Narrows `t` if it is a variable, or checks that it lies in `with` if it is an integer.
+fn narrow_term(env: &mut Environment, t: &Term, with: &Domain, queue: &mut Queue) -> Option<()> {
+    match env.substitute_term(t) {
+        Term::Var(x) => narrow(env, &x, with, queue),
+        Term::Number(Number::Int(v)) if with.contains(v) => Some(()),
+        _ => None,
+    }
+}
+
+/// The domain of a variable or an integer.
+fn term_domain(env: &Environment, t: &Term) -> Option<Domain> {
+    match env.substitute_term(t) {
+        Term::Var(x) => Some(domain(env, &x)),
+        Term::Number(Number::Int(v)) => Some(Domain::singleton(v)),
+        _ => None,
+    }
+}
+
+/// Attaches the propagator `p` to each of its variables.
+fn attach(env: &mut Environment, p: &Term) {
+    let mut vars = Vec::new();
+    crate::term_vars(p, &mut vars);
+
+    for x in vars {
+        let (domain, mut props) = attribute(env, &x).unwrap_or_else(|| (Domain::full(), vec![]));
+        props.push(p.clone());
+        store(env, x, &domain, props);
+    }
+}
+
+/// Attaches the propagator `p` and runs it.
+fn post(env: &Environment, p: Term) -> Vec<Branch> {
+    let mut env = env.clone();
+    attach(&mut env, &p);
+
+    match propagate(&mut env, queue_of(vec![p])) {
+        Some(()) => vec![(env, vec![])],
+        None => vec![],
+    }
+}
+
+/// How soon a propagator runs: linear and arithmetic propagators first, then the cheaper
+/// global constraints, then those that reason about Hall intervals or counts.
+fn priority(p: &Term) -> Priority {
+    match p {
+        Term::Atom(Atom {
+            name: Const(name), ..
+        }) => match &name[..] {
+            "$all_different" | "$element" => Priority::Medium,
+            "$all_distinct" | "$gcc" => Priority::Low,
+            _ => Priority::High,
+        },
+        _ => Priority::High,
+    }
+}
+
+fn queue_of(props: Vec<Term>) -> Queue {
+    let mut queue = Queue::new(priority);
+    queue.extend(props);
+    queue
+}
+
+/// Runs the propagators in `queue`, and those of every variable whose domain they shrink, until
+/// nothing changes.
+fn propagate(env: &mut Environment, queue: Queue) -> Option<()> {
+    propagation::run(env, queue, |env, prop, queue| {
+        if let Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) = prop
+        {
+            match (&name[..], &args[..]) {
+                ("$lin", [pairs, c, Term::Atom(rel)]) => {
+                    let c = match c {
+                        Term::Number(Number::Int(c)) => i128::from(*c),
+                        _ => return None,
+                    };
+
+                    linear(env, &list_items(pairs)?, c, &rel.name.0, queue)?;
+                }
+                ("$times", [x, y, z]) => times(env, x, y, z, queue)?,
+                ("$abs", [x, z]) => functions::abs(env, x, z, queue)?,
+                ("$min", [x, y, z]) => functions::min_max(env, x, y, z, false, queue)?,
+                ("$max", [x, y, z]) => functions::min_max(env, x, y, z, true, queue)?,
+                ("$mod", [x, y, z]) => functions::modulo(env, x, y, z, queue)?,
+                ("$div", [x, y, z]) => functions::quotient(env, x, y, z, queue)?,
+                ("$all_different", [xs]) => globals::propagate_different(env, xs, false, queue)?,
+                ("$all_distinct", [xs]) => globals::propagate_different(env, xs, true, queue)?,
+                ("$reified", [pairs, Term::Number(Number::Int(c)), Term::Atom(rel), b]) => {
+                    let pairs = list_items(pairs)?;
+                    reify::propagate_reified(env, &pairs, i128::from(*c), &rel.name.0, b, queue)?
+                }
+                ("$gcc", [xs, pairs]) => globals::propagate_cardinality(env, xs, pairs, queue)?,
+                ("$element", [i, xs, v]) => globals::propagate_element(env, i, xs, v, queue)?,
+                _ => (),
+            }
+        }
+
+        Some(())
+    })
+}
+
+/// The least and greatest value of a domain, with None standing for `inf` or `sup`.
+type Bounds = (Option<i128>, Option<i128>);
+
+fn bounds(env: &Environment, t: &Term) -> Option<Bounds> {
+    match t {
+        Term::Number(Number::Int(v)) => Some((Some(i128::from(*v)), Some(i128::from(*v)))),
+        Term::Var(x) => Some(limits(&domain(env, x))),
+        _ => None,
+    }
+}
+
+fn limits(domain: &Domain) -> Bounds {
+    let lo = Some(domain.min()).filter(|&l| l != INF).map(i128::from);
+    let hi = Some(domain.max()).filter(|&h| h != SUP).map(i128::from);
+
+    (lo, hi)
+}
+
+fn floor_div(x: i128, y: i128) -> i128 {
+    let q = x / y;
+
+    if q * y != x && (x < 0) != (y < 0) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+fn ceil_div(x: i128, y: i128) -> i128 {
+    let q = x / y;
+
+    if q * y != x && (x < 0) == (y < 0) {
+        q + 1
+    } else {
+        q
+    }
+}
+
+/// Reads the pairs `A*X` of a linear propagator, returning the terms whose variable is still
+/// unbound and folding the others into `c`.
+fn linear_terms<'a>(pairs: &'a [Term], c: &mut i128) -> Option<Vec<(i128, &'a Var)>> {
+    let mut terms = Vec::with_capacity(pairs.len());
+
+    for pair in pairs {
+        match pair {
+            Term::Atom(Atom { args, .. }) if args.len() == 2 => match (&args[0], &args[1]) {
+                (Term::Number(Number::Int(a)), Term::Number(Number::Int(v))) => {
+                    *c += i128::from(*a) * i128::from(*v)
+                }
+                (Term::Number(Number::Int(a)), Term::Var(x)) => terms.push((i128::from(*a), x)),
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+
+    Some(terms)
+}
+
+/// The least and greatest value of each a·x, with None for an unbounded side.
+fn ranges(env: &Environment, terms: &[(i128, &Var)]) -> Vec<Bounds> {
+    terms
+        .iter()
+        .map(|&(a, x)| {
+            let (lo, hi) = bounds(env, &Term::Var(x.clone())).unwrap_or((None, None));
+            let (lo, hi) = (lo.map(|l| a * l), hi.map(|h| a * h));
+
+            if a > 0 {
+                (lo, hi)
+            } else {
+                (hi, lo)
+            }
+        })
+        .collect()
+}
+
+/// Bounds propagation for `Σ a·x + c rel 0`, where `rel` is `=`, `\=` or `=<`, and domain
+/// propagation for an equality of two variables with unit coefficients.
+fn linear(
+    env: &mut Environment,
+    pairs: &[Term],
+    mut c: i128,
+    rel: &str,
+    queue: &mut Queue,
+) -> Option<()> {
+    let terms = linear_terms(pairs, &mut c)?;
+
+    if rel == "\\=" {
+        return match terms[..] {
+            [] if c == 0 => None,
+            [(a, x)] if c % a == 0 => match i64::try_from(-c / a) {
+                Ok(v) => {
+                    let without = domain(env, x).remove(v);
+                    narrow(env, x, &without, queue)
+                }
+                Err(_) => Some(()),
+            },
+            _ => Some(()),
+        };
+    }
+
+    let ranges = ranges(env, &terms);
+
+    let total = |side: fn(&Bounds) -> Option<i128>| {
+        let unbounded = ranges.iter().filter(|r| side(r).is_none()).count();
+        let sum: i128 = ranges.iter().filter_map(side).sum();
+        (sum, unbounded)
+    };
+
+    let (lo_sum, lo_unbounded) = total(|r| r.0);
+    let (hi_sum, hi_unbounded) = total(|r| r.1);
+
+    if terms.is_empty() {
+        return match rel {
+            "=" if c == 0 => Some(()),
+            "=<" if c <= 0 => Some(()),
+            _ => None,
+        };
+    }
+
+    for (&(a, x), &(lo, hi)) in terms.iter().zip(ranges.iter()) {
+        let others = |sum: i128, unbounded: usize, own: Option<i128>| match own {
+            Some(own) if unbounded == 0 => Some(sum - own),
+            None if unbounded == 1 => Some(sum),
+            _ => None,
+        };
+
+        let others_lo = others(lo_sum, lo_unbounded, lo);
+        let others_hi = others(hi_sum, hi_unbounded, hi);
+
+        // a·x lies within [-c - others_hi, -c - others_lo].
+        let ax_lo = if rel == "=" {
+            others_hi.map(|h| -c - h)
+        } else {
+            None
+        };
+        let ax_hi = others_lo.map(|l| -c - l);
+
+        let (x_lo, x_hi) = if a > 0 {
+            (
+                ax_lo.map(|l| ceil_div(l, a)),
+                ax_hi.map(|h| floor_div(h, a)),
+            )
+        } else {
+            (
+                ax_hi.map(|h| ceil_div(h, a)),
+                ax_lo.map(|l| floor_div(l, a)),
+            )
+        };
+
+        narrow(env, x, &Domain::full().restrict(x_lo, x_hi), queue)?;
+    }
+
+    // With two unit coefficients `x = ±y + k`, so each variable keeps only the values the other
+    // allows, holes included.
+    if let [(a, x), (b, y)] = terms[..] {
+        if rel == "=" && a.abs() == 1 && b.abs() == 1 {
+            for &(a, x, b, y) in &[(a, x, b, y), (b, y, a, x)] {
+                let (x, y) = (Term::Var(x.clone()), Term::Var(y.clone()));
+                let dy = term_domain(env, &y)?;
+                let dy = if a == b { dy.negate() } else { dy };
+                narrow_term(env, &x, &dy.shift(-c * a), queue)?;
+            }
+        }
+    }
+
+    Some(())
+}
+
+/// Bounds propagation for `x·y = z`.
+fn times(env: &mut Environment, x: &Term, y: &Term, z: &Term, queue: &mut Queue) -> Option<()> {
+    let (x_lo, x_hi) = bounds(env, x)?;
+    let (y_lo, y_hi) = bounds(env, y)?;
+    let (z_lo, z_hi) = bounds(env, z)?;
+
+    let corners = |a: [Option<i128>; 2], b: [Option<i128>; 2], f: fn(i128, i128) -> i128| {
+        let mut values = Vec::with_capacity(4);
+
+        for &p in &a {
+            for &q in &b {
+                values.push(f(p?, q?));
+            }
+        }
+
+        Some(values)
+    };
+
+    let mut restrict = |env: &mut Environment, t: &Term, lo: Option<i128>, hi: Option<i128>| {
+        narrow_term(env, t, &Domain::full().restrict(lo, hi), queue)
+    };
+
+    if let Some(products) = corners([x_lo, x_hi], [y_lo, y_hi], |p, q| p * q) {
+        let lo = products.iter().min().cloned();
+        let hi = products.iter().max().cloned();
+        restrict(env, z, lo, hi)?;
+    }
+
+    for &(t, (lo, hi), (d_lo, d_hi)) in &[
+        (x, (y_lo, y_hi), (x_lo, x_hi)),
+        (y, (x_lo, x_hi), (y_lo, y_hi)),
+    ] {
+        let excludes_zero = lo.is_some_and(|l| l > 0) || hi.is_some_and(|h| h < 0);
+
+        if !excludes_zero || (d_lo.is_some() && d_hi.is_some() && d_lo == d_hi) {
+            continue;
+        }
+
+        let z = env.substitute_term(z);
+        let (z_lo, z_hi) = bounds(env, &z).unwrap_or((z_lo, z_hi));
+
+        if let (Some(lows), Some(highs)) = (
+            corners([z_lo, z_hi], [lo, hi], ceil_div),
+            corners([z_lo, z_hi], [lo, hi], floor_div),
+        ) {
+            let t = env.substitute_term(t);
+            restrict(env, &t, lows.into_iter().min(), highs.into_iter().max())?;
+        }
+    }
+
+    Some(())
+}
+
+/// A linear expression `Σ a·x + c` under construction.
+#[derive(Default)]
+struct Linear {
+    terms: Vec<(i128, Var)>,
+    c: i128,
+}
+
+/// Reads arithmetic expressions over integers and variables into linear form, introducing an
+/// auxiliary variable for every non-linear product.
+struct Compiler<'a> {
+    env: &'a mut Environment,
+    /// The number the auxiliary variables are renamed apart with.
+    number: usize,
+    fresh: usize,
+    queue: Queue,
+    /// The error to raise for an expression that is not one over integers.
+    error: Option<Term>,
+}
+
+impl<'a> Compiler<'a> {
+    fn linearize(&mut self, t: &Term, scale: i128, into: &mut Linear) -> Option<()> {
+        let t = self.env.substitute_term(t);
+
+        let (name, args) = match t {
+            Term::Number(Number::Int(v)) => {
+                into.c += scale * i128::from(v);
+                return Some(());
+            }
+            Term::Var(x) => {
+                into.terms.push((scale, x));
+                return Some(());
+            }
+            Term::Atom(Atom {
+                name: Const(name),
+                args,
+                ..
+            }) => (name, args),
+            t => {
+                self.error = Some(type_error("evaluable", t));
+                return None;
+            }
+        };
+
+        match (&name[..], &args[..]) {
+            ("+", [x, y]) => {
+                self.linearize(x, scale, into)?;
+                self.linearize(y, scale, into)
+            }
+            ("-", [x, y]) => {
+                self.linearize(x, scale, into)?;
+                self.linearize(y, -scale, into)
+            }
+            ("-", [x]) => self.linearize(x, -scale, into),
+            ("+", [x]) => self.linearize(x, scale, into),
+            ("*", [x, y]) => {
+                let mut lx = Linear::default();
+                let mut ly = Linear::default();
+                self.linearize(x, 1, &mut lx)?;
+                self.linearize(y, 1, &mut ly)?;
+
+                match (lx.terms.is_empty(), ly.terms.is_empty()) {
+                    (true, _) => self.linearize(y, scale * lx.c, into),
+                    (_, true) => self.linearize(x, scale * ly.c, into),
+                    _ => {
+                        let x = self.var_of(lx)?;
+                        let y = self.var_of(ly)?;
+                        let z = self.fresh_var();
+
+                        self.post(prop("$times", vec![x, y, Term::Var(z.clone())]))?;
+                        into.terms.push((scale, z));
+                        Some(())
+                    }
+                }
+            }
+            ("abs", [x]) => self.function("$abs", &[x], scale, into),
+            ("min", [x, y]) => self.function("$min", &[x, y], scale, into),
+            ("max", [x, y]) => self.function("$max", &[x, y], scale, into),
+            ("mod", [x, y]) => self.function("$mod", &[x, y], scale, into),
+            ("//", [x, y]) => self.function("$div", &[x, y], scale, into),
+            _ => {
+                let indicator = op("/", atom(&name), int(args.len() as i64));
+                self.error = Some(type_error("evaluable", indicator));
+                None
+            }
+        }
+    }
+
+    /// Adds an auxiliary variable for the value of a function of `args`, which the propagator
+    /// `name` ties to them.
+    fn function(
+        &mut self,
+        name: &str,
+        args: &[&Term],
+        scale: i128,
+        into: &mut Linear,
+    ) -> Option<()> {
+        let mut vars = Vec::with_capacity(args.len() + 1);
+
+        for arg in args {
+            let mut l = Linear::default();
+            self.linearize(arg, 1, &mut l)?;
+            vars.push(self.var_of(l)?);
+        }
+
+        let z = self.fresh_var();
+        vars.push(Term::Var(z.clone()));
+        self.post(prop(name, vars))?;
+        into.terms.push((scale, z));
+
+        Some(())
+    }
+
+    /// Reads `l rel r`, where `rel` is one of `#=`, `#\=`, `#<`, `#>`, `#=<` and `#>=`, as
+    /// `Σ a·x + c rel' 0` with `rel'` one of `=`, `\=` and `=<`.
+    fn relation(&mut self, rel: &str, l: &Term, r: &Term) -> Option<(Linear, &'static str)> {
+        let (x, y, offset, rel) = match rel {
+            "#=" => (l, r, 0, "="),
+            "#\\=" => (l, r, 0, "\\="),
+            "#=<" => (l, r, 0, "=<"),
+            "#<" => (l, r, 1, "=<"),
+            "#>=" => (r, l, 0, "=<"),
+            "#>" => (r, l, 1, "=<"),
+            _ => return None,
+        };
+
+        let mut linear = Linear::default();
+        self.linearize(x, 1, &mut linear)?;
+        self.linearize(y, -1, &mut linear)?;
+        linear.c += offset;
+
+        Some((linear, rel))
+    }
+
+    fn fresh_var(&mut self) -> Var {
+        self.fresh += 1;
+        Var::new(&format!("_Q{}", self.fresh), self.number)
+    }
+
+    /// Returns a variable equal to the linear expression `l`.
+    fn var_of(&mut self, mut l: Linear) -> Option<Term> {
+        match l.terms[..] {
+            [(1, ref x)] if l.c == 0 => Some(Term::Var(x.clone())),
+            _ => {
+                let v = self.fresh_var();
+                l.terms.push((-1, v.clone()));
+                self.post_linear(l, "=")?;
+
+                Some(Term::Var(v))
+            }
+        }
+    }
+
+    /// Writes `l` as the list of pairs `A*X` and the constant of a linear propagator.
+    fn pairs(&self, l: Linear) -> Option<(Term, Term)> {
+        let mut terms: Vec<(i128, Var)> = Vec::with_capacity(l.terms.len());
+
+        for (a, x) in l.terms {
+            match terms.iter_mut().find(|(_, y)| *y == x) {
+                Some(term) => term.0 += a,
+                None => terms.push((a, x)),
+            }
+        }
+
+        let pairs = terms
+            .into_iter()
+            .filter(|&(a, _)| a != 0)
+            .map(|(a, x)| Some(op("*", int(i64::try_from(a).ok()?), Term::Var(x))))
+            .collect::<Option<Vec<_>>>()?;
+
+        let c = int(i64::try_from(l.c).ok()?);
+        Some((Term::list(pairs, Term::nil()), c))
+    }
+
+    fn post_linear(&mut self, l: Linear, rel: &str) -> Option<()> {
+        let (pairs, c) = self.pairs(l)?;
+        self.post(prop("$lin", vec![pairs, c, atom(rel)]))
+    }
+
+    /// Attaches the propagator `p` to each of its variables and queues it.
+    fn post(&mut self, p: Term) -> Option<()> {
+        attach(self.env, &p);
+        self.queue.push(p);
+        Some(())
+    }
+}
+
+fn prop(name: &str, args: Vec<Term>) -> Term {
+    Term::Atom(Atom::new(name, args))
+}
+
+/// Posts the arithmetic constraint `l rel r`, where `rel` is one of `#=`, `#\=`, `#<`, `#>`,
+/// `#=<` and `#>=`.
+pub(super) fn constrain(env: &Environment, rel: &str, l: &Term, r: &Term) -> Vec<Branch> {
+    let mut constrained = env.clone();
+
+    let (posted, error) = {
+        let mut compiler = Compiler {
+            env: &mut constrained,
+            number: crate::fresh(),
+            fresh: 0,
+            queue: Queue::new(priority),
+            error: None,
+        };
+
+        let posted = compiler
+            .relation(rel, l, r)
+            .and_then(|(linear, rel)| compiler.post_linear(linear, rel));
+        let error = compiler.error.take();
+        (posted.map(|_| compiler.queue), error)
+    };
+
+    if let Some(error) = error {
+        return throw(env, error);
+    }
+
+    let mut env = constrained;
+
+    match posted.and_then(|queue| propagate(&mut env, queue)) {
+        Some(()) => vec![(env, vec![])],
+        None => vec![],
+    }
+}
+
+/// Restricts each of `xs` to the domain `d`.
+pub(super) fn ins(env: &Environment, xs: &[Term], d: &Term) -> Vec<Branch> {
+    let domain = match Domain::from_term(&env.substitute_term(d)) {
+        Some(domain) => domain,
+        None => return vec![],
+    };
+
+    let mut env = env.clone();
+    let mut queue = Queue::new(priority);
+
+    for x in xs {
+        let within = match env.substitute_term(x) {
+            Term::Var(x) => narrow(&mut env, &x, &domain, &mut queue),
+            Term::Number(Number::Int(v)) if domain.contains(v) => Some(()),
+            _ => None,
+        };
+
+        if within.is_none() {
+            return vec![];
+        }
+    }
+
+    match propagate(&mut env, queue) {
+        Some(()) => vec![(env, vec![])],
+        None => vec![],
+    }
+}
+
+pub(super) fn in_list(env: &Environment, xs: &Term, d: &Term) -> Vec<Branch> {
+    match list_items(&env.substitute_term(xs)) {
+        Some(xs) => ins(env, &xs, d),
+        None => vec![],
+    }
+}
+
+/// Checks the value a constrained variable was unified with, or merges its domain and
+/// propagators into the variable it was unified with.
+pub(super) fn wakeup(env: &Environment, value: &Term, other: &Term) -> Vec<Branch> {
+    let (domain, props) = match value {
+        Term::Atom(Atom { args, .. }) if args.len() == 2 => match Domain::from_term(&args[0]) {
+            Some(domain) => (domain, list_items(&args[1]).unwrap_or_default()),
+            None => return vec![],
+        },
+        _ => return vec![],
+    };
+
+    let mut env = env.clone();
+
+    let woken = match env.substitute_term(other) {
+        Term::Number(Number::Int(v)) if domain.contains(v) => Some(queue_of(props)),
+        Term::Var(y) => {
+            let (other_domain, mut other_props) =
+                attribute(&env, &y).unwrap_or_else(|| (Domain::full(), vec![]));
+            other_props.extend(props.iter().cloned());
+            store(&mut env, y.clone(), &other_domain, other_props);
+
+            let mut queue = queue_of(props);
+            narrow(&mut env, &y, &domain, &mut queue).map(|_| queue)
+        }
+        _ => None,
+    };
+
+    match woken.and_then(|queue| propagate(&mut env, queue)) {
+        Some(()) => vec![(env, vec![])],
+        None => vec![],
+    }
+}
+
+/// Assigns a value to each of `vars` by search, choosing the variable and the order its values
+/// are tried in by `options`: one of `leftmost`, `ff`, `ffc`, `min` and `max`, and one of `up`
+/// and `down`.
+pub(super) fn labeling(env: &Environment, options: &Term, vars: &Term) -> Vec<Branch> {
+    let (mut strategy, mut down) = (String::from("leftmost"), false);
+
+    for option in list_items(&env.substitute_term(options)).unwrap_or_default() {
+        match option {
+            Term::Atom(Atom {
+                name: Const(ref name),
+                ref args,
+                ..
+            }) if args.is_empty() => match &name[..] {
+                "leftmost" | "ff" | "ffc" | "min" | "max" => strategy = name.clone(),
+                "up" => down = false,
+                "down" => down = true,
+                _ => return vec![],
+            },
+            _ => return vec![],
+        }
+    }
+
+    let items = match list_items(&env.substitute_term(vars)) {
+        Some(items) => items,
+        None => return vec![],
+    };
+
+    let mut candidates = Vec::new();
+
+    for item in items {
+        match item {
+            Term::Var(x) => match attribute(env, &x) {
+                Some((domain, props)) if domain.size().is_some() => {
+                    candidates.push((x, domain, props.len()))
+                }
+                _ => return vec![],
+            },
+            Term::Number(Number::Int(_)) => (),
+            _ => return vec![],
+        }
+    }
+
+    let size = |d: &Domain| d.size().unwrap_or(u64::MAX);
+
+    let chosen = match &strategy[..] {
+        "ff" => candidates.iter().min_by_key(|(_, d, _)| size(d)),
+        "ffc" => candidates
+            .iter()
+            .min_by_key(|(_, d, props)| (size(d), std::cmp::Reverse(*props))),
+        "min" => candidates.iter().min_by_key(|(_, d, _)| d.min()),
+        "max" => candidates
+            .iter()
+            .min_by_key(|(_, d, _)| std::cmp::Reverse(d.max())),
+        _ => candidates.first(),
+    };
+
+    let (x, domain) = match chosen {
+        Some((x, domain, _)) => (Term::Var(x.clone()), domain),
+        None => return vec![(env.clone(), vec![])],
+    };
+
+    let value = if down { domain.max() } else { domain.min() };
+    let again = Atom::new("labeling", vec![options.clone(), vars.clone()]);
+    let exclude = Atom::new("#\\=", vec![x.clone(), int(value)]);
+
+    let mut branches: Vec<Branch> = unify(env, &x, &int(value))
+        .into_iter()
+        .map(|(env, _)| (env, vec![again.clone()]))
+        .collect();
+    branches.push((env.clone(), vec![exclude, again]));
+
+    branches
+}
+
+/// Describes the domain and the propagators of a constrained variable as goals.
+pub(crate) fn residual_goals(env: &Environment, x: &Var) -> Vec<Term> {
+    let (domain, props) = match attribute(env, x) {
+        Some(attribute) => attribute,
+        None => return vec![],
+    };
+
+    let mut goals = Vec::new();
+
+    if domain != Domain::full() {
+        goals.push(op("in", Term::Var(x.clone()), domain.to_term()));
+    }
+
+    for p in props {
+        if let Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) = env.substitute_term(&p)
+        {
+            match (&name[..], &args[..]) {
+                ("$lin", [pairs, Term::Number(Number::Int(c)), Term::Atom(rel)]) => {
+                    let pairs = list_items(pairs).unwrap_or_default();
+                    goals.push(linear_goal(&pairs, *c, rel_name(rel)));
+                }
+                ("$times", [x, y, z]) => {
+                    goals.push(op("#=", op("*", x.clone(), y.clone()), z.clone()))
+                }
+                ("$abs", [x, z]) => goals.push(op("#=", prop("abs", vec![x.clone()]), z.clone())),
+                ("$min", [x, y, z]) | ("$max", [x, y, z]) | ("$mod", [x, y, z]) => {
+                    let f = prop(&name[1..], vec![x.clone(), y.clone()]);
+                    goals.push(op("#=", f, z.clone()))
+                }
+                ("$div", [x, y, z]) => {
+                    goals.push(op("#=", op("//", x.clone(), y.clone()), z.clone()))
+                }
+                ("$all_different", _) | ("$all_distinct", _) | ("$element", _) => {
+                    goals.push(prop(&name[1..], args.clone()))
+                }
+                ("$gcc", _) => goals.push(prop("global_cardinality", args.clone())),
+                ("$reified", [pairs, Term::Number(Number::Int(c)), Term::Atom(rel), b]) => {
+                    let goal =
+                        linear_goal(&list_items(pairs).unwrap_or_default(), *c, rel_name(rel));
+                    goals.push(match b {
+                        Term::Number(Number::Int(1)) => goal,
+                        Term::Number(Number::Int(0)) => Term::Atom(Atom::new("#\\", vec![goal])),
+                        b => op("#<==>", b.clone(), goal),
+                    })
+                }
+                _ => (),
+            }
+        }
+    }
+
+    goals
+}
+
+fn rel_name(rel: &Atom) -> &'static str {
+    match &rel.name.0[..] {
+        "=" => "#=",
+        "\\=" => "#\\=",
+        _ => "#=<",
+    }
+}
+
+/// Writes `Σ a·x + c rel 0` with every coefficient positive, moving terms across `rel`.
+fn linear_goal(pairs: &[Term], c: i64, rel: &str) -> Term {
+    let (mut lhs, mut rhs) = (Vec::new(), Vec::new());
+
+    for pair in pairs {
+        if let Term::Atom(Atom { args, .. }) = pair {
+            if let Term::Number(Number::Int(a)) = args[0] {
+                let side = if a > 0 { &mut lhs } else { &mut rhs };
+
+                side.push(match a.abs() {
+                    1 => args[1].clone(),
+                    a => op("*", int(a), args[1].clone()),
+                });
+            }
+        }
+    }
+
+    if c > 0 {
+        lhs.push(int(c));
+    } else if c < 0 {
+        rhs.push(int(-c));
+    }
+
+    let sum = |terms: Vec<Term>| {
+        terms
+            .into_iter()
+            .reduce(|sum, t| op("+", sum, t))
+            .unwrap_or_else(|| int(0))
+    };
+
+    op(rel, sum(lhs), sum(rhs))
+}
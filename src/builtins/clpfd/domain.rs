@@ -0,0 +1,229 @@
+use crate::ast::{op, Atom, Const, Number, Term};
+
+/// Stands for `inf` as a lower bound and `sup` as an upper bound.
+pub(super) const INF: i64 = i64::MIN;
+pub(super) const SUP: i64 = i64::MAX;
+
+/// A finite domain: sorted, disjoint and non-adjacent inclusive intervals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Domain(Vec<(i64, i64)>);
+
+impl Domain {
+    pub(super) fn full() -> Self {
+        Domain(vec![(INF, SUP)])
+    }
+
+    pub(super) fn empty() -> Self {
+        Domain(vec![])
+    }
+
+    pub(super) fn singleton(v: i64) -> Self {
+        Domain(vec![(v, v)])
+    }
+
+    fn normalize(mut intervals: Vec<(i64, i64)>) -> Self {
+        intervals.retain(|&(l, h)| l <= h);
+        intervals.sort_unstable();
+
+        let mut merged: Vec<(i64, i64)> = Vec::with_capacity(intervals.len());
+
+        for (l, h) in intervals {
+            match merged.last_mut() {
+                Some(last) if l <= last.1.saturating_add(1) => last.1 = last.1.max(h),
+                _ => merged.push((l, h)),
+            }
+        }
+
+        Domain(merged)
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(super) fn min(&self) -> i64 {
+        self.0.first().map_or(SUP, |&(l, _)| l)
+    }
+
+    pub(super) fn max(&self) -> i64 {
+        self.0.last().map_or(INF, |&(_, h)| h)
+    }
+
+    pub(super) fn value(&self) -> Option<i64> {
+        match self.0[..] {
+            [(l, h)] if l == h && l != INF && h != SUP => Some(l),
+            _ => None,
+        }
+    }
+
+    pub(super) fn contains(&self, v: i64) -> bool {
+        self.0.iter().any(|&(l, h)| l <= v && v <= h)
+    }
+
+    /// Returns the number of values in the domain, or None if it is infinite.
+    pub(super) fn size(&self) -> Option<u64> {
+        if self.min() == INF || self.max() == SUP {
+            return None;
+        }
+
+        Some(self.0.iter().map(|&(l, h)| (h - l) as u64 + 1).sum())
+    }
+
+    pub(super) fn intersect(&self, other: &Domain) -> Domain {
+        let mut intervals = Vec::new();
+
+        for &(l1, h1) in &self.0 {
+            for &(l2, h2) in &other.0 {
+                intervals.push((l1.max(l2), h1.min(h2)));
+            }
+        }
+
+        Domain::normalize(intervals)
+    }
+
+    pub(super) fn union(&self, other: &Domain) -> Domain {
+        Domain::normalize(self.0.iter().chain(other.0.iter()).cloned().collect())
+    }
+
+    pub(super) fn complement(&self) -> Domain {
+        let mut intervals = Vec::new();
+        let mut next = INF;
+
+        for &(l, h) in &self.0 {
+            if l != INF {
+                intervals.push((next, l - 1));
+            }
+
+            if h == SUP {
+                return Domain::normalize(intervals);
+            }
+
+            next = h + 1;
+        }
+
+        intervals.push((next, SUP));
+        Domain::normalize(intervals)
+    }
+
+    /// The negations of the values of the domain, with `inf` and `sup` trading places.
+    pub(super) fn negate(&self) -> Domain {
+        let negate = |v: i64| match v {
+            INF => SUP,
+            SUP => INF,
+            v => -v,
+        };
+
+        Domain::normalize(
+            self.0
+                .iter()
+                .map(|&(l, h)| (negate(h), negate(l)))
+                .collect(),
+        )
+    }
+
+    /// The values of the domain plus `k`, leaving out those that go beyond the finite values.
+    pub(super) fn shift(&self, k: i128) -> Domain {
+        let full = Domain::full();
+
+        self.0.iter().fold(Domain::empty(), |shifted, &(l, h)| {
+            let l = Some(l).filter(|&l| l != INF).map(|l| i128::from(l) + k);
+            let h = Some(h).filter(|&h| h != SUP).map(|h| i128::from(h) + k);
+            shifted.union(&full.restrict(l, h))
+        })
+    }
+
+    pub(super) fn remove(&self, v: i64) -> Domain {
+        self.intersect(&Domain::singleton(v).complement())
+    }
+
+    /// Restricts the domain to `[lo, hi]`, where None stands for an unbounded side. Bounds
+    /// beyond the range of finite values empty the domain.
+    pub(super) fn restrict(&self, lo: Option<i128>, hi: Option<i128>) -> Domain {
+        let lo = match lo {
+            None => INF,
+            Some(lo) if lo <= i128::from(INF) => INF,
+            Some(lo) if lo >= i128::from(SUP) => return Domain(vec![]),
+            Some(lo) => lo as i64,
+        };
+
+        let hi = match hi {
+            None => SUP,
+            Some(hi) if hi >= i128::from(SUP) => SUP,
+            Some(hi) if hi <= i128::from(INF) => return Domain(vec![]),
+            Some(hi) => hi as i64,
+        };
+
+        self.intersect(&Domain(vec![(lo, hi)]))
+    }
+
+    /// Enumerates the values of a finite domain in ascending order.
+    pub(super) fn values(&self) -> impl Iterator<Item = i64> + '_ {
+        self.0.iter().flat_map(|&(l, h)| l..=h)
+    }
+
+    /// Reads a domain written as integers, `L..H` ranges with `inf` and `sup` bounds, and their
+    /// unions with `\/`.
+    pub(super) fn from_term(t: &Term) -> Option<Domain> {
+        match t {
+            Term::Number(Number::Int(v)) => Some(Domain::singleton(*v)),
+            Term::Atom(Atom {
+                name: Const(name),
+                args,
+                ..
+            }) if args.len() == 2 => match &name[..] {
+                ".." => {
+                    let l = bound(&args[0], "inf", INF)?;
+                    let h = bound(&args[1], "sup", SUP)?;
+
+                    if l == SUP || h == INF {
+                        return None;
+                    }
+
+                    Some(Domain::normalize(vec![(l, h)]))
+                }
+                "\\/" => Some(Domain::from_term(&args[0])?.union(&Domain::from_term(&args[1])?)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub(super) fn to_term(&self) -> Term {
+        let interval = |&(l, h): &(i64, i64)| {
+            if l == h {
+                int(l)
+            } else {
+                let l = if l == INF { atom("inf") } else { int(l) };
+                let h = if h == SUP { atom("sup") } else { int(h) };
+                op("..", l, h)
+            }
+        };
+
+        let mut intervals = self.0.iter().map(interval);
+
+        match intervals.next() {
+            None => op("..", int(1), int(0)),
+            Some(first) => intervals.fold(first, |d, i| op("\\/", d, i)),
+        }
+    }
+}
+
+fn bound(t: &Term, infinity: &str, value: i64) -> Option<i64> {
+    match t {
+        Term::Number(Number::Int(v)) => Some(*v),
+        Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) if args.is_empty() && name == infinity => Some(value),
+        _ => None,
+    }
+}
+
+fn int(v: i64) -> Term {
+    Term::Number(Number::Int(v))
+}
+
+fn atom(name: &str) -> Term {
+    Term::Atom(Atom::new(name, vec![]))
+}
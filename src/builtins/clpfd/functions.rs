@@ -0,0 +1,132 @@
+use super::domain::Domain;
+use super::{limits, narrow_term, term_domain};
+use crate::ast::Term;
+use crate::builtins::propagation::Queue;
+use crate::Environment;
+
+/// Propagation for `abs(x) = z`: `z` keeps the values of `x` and their negations that are not
+/// negative, and `x` the values whose absolute value `z` can still take.
+pub(super) fn abs(env: &mut Environment, x: &Term, z: &Term, queue: &mut Queue) -> Option<()> {
+    let dx = term_domain(env, x)?;
+    let dz = dx.union(&dx.negate()).restrict(Some(0), None);
+    narrow_term(env, z, &dz, queue)?;
+
+    let dz = term_domain(env, z)?;
+    narrow_term(env, x, &dz.union(&dz.negate()), queue)
+}
+
+/// Propagation for `min(x, y) = z`, or `max(x, y) = z` if `max` is set, which is worked out as
+/// the minimum of the negated domains.
+pub(super) fn min_max(
+    env: &mut Environment,
+    x: &Term,
+    y: &Term,
+    z: &Term,
+    max: bool,
+    queue: &mut Queue,
+) -> Option<()> {
+    let flip = |d: Domain| if max { d.negate() } else { d };
+    let dx = flip(term_domain(env, x)?);
+    let dy = flip(term_domain(env, y)?);
+    let dz = flip(term_domain(env, z)?);
+
+    // The minimum is one of `x` and `y`, and lies between the lesser of their least values and
+    // the lesser of their greatest ones.
+    let lo = i128::from(dx.min().min(dy.min()));
+    let hi = i128::from(dx.max().min(dy.max()));
+    let mut dz = dz.intersect(&dx.union(&dy)).restrict(Some(lo), Some(hi));
+
+    // Neither can be less than the minimum, and once one of them is certainly the lesser the
+    // minimum is that one.
+    let mut dx = dx.restrict(Some(i128::from(dz.min())), None);
+    let mut dy = dy.restrict(Some(i128::from(dz.min())), None);
+
+    if dx.min() > dy.max() {
+        dz = dz.intersect(&dy);
+        dy = dy.intersect(&dz);
+    } else if dy.min() > dx.max() {
+        dz = dz.intersect(&dx);
+        dx = dx.intersect(&dz);
+    }
+
+    narrow_term(env, x, &flip(dx), queue)?;
+    narrow_term(env, y, &flip(dy), queue)?;
+    narrow_term(env, z, &flip(dz), queue)
+}
+
+/// Propagation for `x mod y = z`, which takes the sign of `y`: `z` lies strictly between zero
+/// and `y`, and is fixed once `x` and `y` are.
+pub(super) fn modulo(
+    env: &mut Environment,
+    x: &Term,
+    y: &Term,
+    z: &Term,
+    queue: &mut Queue,
+) -> Option<()> {
+    let dy = term_domain(env, y)?.remove(0);
+    narrow_term(env, y, &dy, queue)?;
+    let dx = term_domain(env, x)?;
+
+    if let (Some(a), Some(b)) = (dx.value(), dy.value()) {
+        let r = a.checked_rem(b)?;
+        let r = if r != 0 && (r < 0) != (b < 0) {
+            r + b
+        } else {
+            r
+        };
+        return narrow_term(env, z, &Domain::singleton(r), queue);
+    }
+
+    let (y_lo, y_hi) = limits(&dy);
+    let lo = if dy.min() > 0 {
+        Some(0)
+    } else {
+        y_lo.map(|l| l + 1)
+    };
+    let hi = if dy.max() < 0 {
+        Some(0)
+    } else {
+        y_hi.map(|h| h - 1)
+    };
+
+    // A value not below zero is its own remainder by anything greater.
+    let hi = match limits(&dx) {
+        (Some(x_lo), Some(x_hi)) if x_lo >= 0 && dy.min() > 0 => hi.map(|h| h.min(x_hi)),
+        _ => hi,
+    };
+
+    narrow_term(env, z, &Domain::full().restrict(lo, hi), queue)
+}
+
+/// Propagation for `x // y = z`, the quotient truncated toward zero: `z` lies between the
+/// quotients of the bounds of `x` and `y`, and `y` is never zero.
+pub(super) fn quotient(
+    env: &mut Environment,
+    x: &Term,
+    y: &Term,
+    z: &Term,
+    queue: &mut Queue,
+) -> Option<()> {
+    let dy = term_domain(env, y)?.remove(0);
+    narrow_term(env, y, &dy, queue)?;
+    let dx = term_domain(env, x)?;
+
+    let (x_lo, x_hi) = match limits(&dx) {
+        (Some(lo), Some(hi)) => (lo, hi),
+        _ => return Some(()),
+    };
+
+    let (lo, hi) = match limits(&dy) {
+        (Some(y_lo), Some(y_hi)) if dy.min() > 0 || dy.max() < 0 => {
+            let quotients = [x_lo / y_lo, x_lo / y_hi, x_hi / y_lo, x_hi / y_hi];
+            (*quotients.iter().min()?, *quotients.iter().max()?)
+        }
+        _ => {
+            // Dividing by anything but zero gives no more than the dividend in magnitude.
+            let m = x_lo.abs().max(x_hi.abs());
+            (-m, m)
+        }
+    };
+
+    narrow_term(env, z, &Domain::full().restrict(Some(lo), Some(hi)), queue)
+}
@@ -0,0 +1,291 @@
+use super::domain::Domain;
+use super::{attach, constrain, narrow, narrow_term, post, priority, prop, propagate, term_domain};
+use crate::ast::{op, Atom, Number, Term};
+use crate::builtins::propagation::Queue;
+use crate::builtins::{list_items, Branch};
+use crate::Environment;
+
+fn int(v: i64) -> Term {
+    Term::Number(Number::Int(v))
+}
+
+fn values(env: &Environment, xs: &Term) -> Option<Vec<Term>> {
+    Some(
+        list_items(&env.substitute_term(xs))?
+            .iter()
+            .map(|x| env.substitute_term(x))
+            .collect(),
+    )
+}
+
+pub(in crate::builtins) fn all_different(
+    env: &Environment,
+    xs: &Term,
+    strong: bool,
+) -> Vec<Branch> {
+    match values(env, xs) {
+        Some(xs) => {
+            let name = if strong {
+                "$all_distinct"
+            } else {
+                "$all_different"
+            };
+            post(env, prop(name, vec![Term::list(xs, Term::nil())]))
+        }
+        None => vec![],
+    }
+}
+
+/// Posts `Σ xs rel value`.
+pub(in crate::builtins) fn sum(
+    env: &Environment,
+    xs: &Term,
+    rel: &Term,
+    value: &Term,
+) -> Vec<Branch> {
+    let total = match values(env, xs) {
+        Some(xs) => xs
+            .into_iter()
+            .reduce(|sum, x| op("+", sum, x))
+            .unwrap_or_else(|| int(0)),
+        None => return vec![],
+    };
+
+    match env.substitute_term(rel) {
+        Term::Atom(Atom { name, args, .. }) if args.is_empty() => {
+            constrain(env, &name.0, &total, value)
+        }
+        _ => vec![],
+    }
+}
+
+/// Restricts each of `xs` to the keys of `pairs`, a list of `Key-Count`, and posts that each
+/// key is taken by Count of `xs`.
+pub(in crate::builtins) fn global_cardinality(
+    env: &Environment,
+    xs: &Term,
+    pairs: &Term,
+) -> Vec<Branch> {
+    let (xs, pairs) = match (values(env, xs), values(env, pairs)) {
+        (Some(xs), Some(pairs)) => (xs, pairs),
+        _ => return vec![],
+    };
+
+    let mut keys = Domain::empty();
+
+    for pair in &pairs {
+        match pair {
+            Term::Atom(Atom { name, args, .. }) if name.0 == "-" && args.len() == 2 => {
+                match args[0] {
+                    Term::Number(Number::Int(key)) => keys = keys.union(&Domain::singleton(key)),
+                    _ => return vec![],
+                }
+            }
+            _ => return vec![],
+        }
+    }
+
+    let mut env = env.clone();
+    let mut queue = Queue::new(priority);
+
+    for x in &xs {
+        if narrow_term(&mut env, x, &keys, &mut queue).is_none() {
+            return vec![];
+        }
+    }
+
+    let p = prop(
+        "$gcc",
+        vec![Term::list(xs, Term::nil()), Term::list(pairs, Term::nil())],
+    );
+
+    attach(&mut env, &p);
+    queue.push(p);
+
+    match propagate(&mut env, queue) {
+        Some(()) => vec![(env, vec![])],
+        None => vec![],
+    }
+}
+
+/// Posts that `v` is the `i`th of `xs`, counting from 1.
+pub(in crate::builtins) fn element(
+    env: &Environment,
+    i: &Term,
+    xs: &Term,
+    v: &Term,
+) -> Vec<Branch> {
+    match values(env, xs) {
+        Some(xs) if !xs.is_empty() => post(
+            env,
+            prop(
+                "$element",
+                vec![i.clone(), Term::list(xs, Term::nil()), v.clone()],
+            ),
+        ),
+        _ => vec![],
+    }
+}
+
+/// Removes the value of each bound variable from the others. The stronger `all_distinct` also
+/// fails when some interval holds fewer values than the variables confined to it, and removes
+/// the values of a full interval from the other variables.
+pub(super) fn propagate_different(
+    env: &mut Environment,
+    xs: &Term,
+    strong: bool,
+    queue: &mut Queue,
+) -> Option<()> {
+    let xs = list_items(xs)?;
+    let mut taken = Domain::empty();
+
+    for x in &xs {
+        if let Term::Number(Number::Int(v)) = x {
+            if taken.contains(*v) {
+                return None;
+            }
+
+            taken = taken.union(&Domain::singleton(*v));
+        }
+    }
+
+    let free = taken.complement();
+
+    for x in &xs {
+        if let Term::Var(x) = x {
+            narrow(env, x, &free, queue)?;
+        }
+    }
+
+    if !strong {
+        return Some(());
+    }
+
+    let domains: Vec<_> = xs
+        .iter()
+        .filter_map(|x| match env.substitute_term(x) {
+            Term::Var(x) => Some((x.clone(), super::domain(env, &x))),
+            _ => None,
+        })
+        .collect();
+
+    for (_, d) in &domains {
+        for (_, e) in &domains {
+            let (lo, hi) = (d.min(), e.max());
+
+            if lo > hi || d.size().is_none() || e.size().is_none() {
+                continue;
+            }
+
+            let hall = Domain::full().restrict(Some(lo.into()), Some(hi.into()));
+            let inside = domains
+                .iter()
+                .filter(|(_, d)| d.intersect(&hall) == *d)
+                .count() as i128;
+            let width = i128::from(hi) - i128::from(lo) + 1;
+
+            if inside > width {
+                return None;
+            }
+
+            if inside == width {
+                let outside = hall.complement();
+
+                for (x, d) in &domains {
+                    if d.intersect(&hall) != *d {
+                        narrow_term(env, &Term::Var(x.clone()), &outside, queue)?;
+                    }
+                }
+            }
+        }
+    }
+
+    Some(())
+}
+
+pub(super) fn propagate_cardinality(
+    env: &mut Environment,
+    xs: &Term,
+    pairs: &Term,
+    queue: &mut Queue,
+) -> Option<()> {
+    let xs = list_items(xs)?;
+
+    for pair in list_items(pairs)? {
+        let (key, count) = match pair {
+            Term::Atom(Atom { ref args, .. }) => match (&args[0], &args[1]) {
+                (Term::Number(Number::Int(key)), count) => (*key, count.clone()),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let xs: Vec<_> = xs.iter().map(|x| env.substitute_term(x)).collect();
+        let taken = xs.iter().filter(|x| **x == int(key)).count() as i128;
+        let open: Vec<_> = xs
+            .iter()
+            .filter_map(|x| match x {
+                Term::Var(x) if super::domain(env, x).contains(key) => Some(x.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let within = Domain::full().restrict(Some(taken), Some(taken + open.len() as i128));
+        narrow_term(env, &count, &within, queue)?;
+
+        match env.substitute_term(&count) {
+            Term::Number(Number::Int(count)) if i128::from(count) == taken => {
+                let without = Domain::singleton(key).complement();
+
+                for x in &open {
+                    narrow_term(env, &Term::Var(x.clone()), &without, queue)?;
+                }
+            }
+            Term::Number(Number::Int(count)) if i128::from(count) == taken + open.len() as i128 => {
+                for x in &open {
+                    narrow_term(env, &Term::Var(x.clone()), &Domain::singleton(key), queue)?;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Some(())
+}
+
+pub(super) fn propagate_element(
+    env: &mut Environment,
+    i: &Term,
+    xs: &Term,
+    v: &Term,
+    queue: &mut Queue,
+) -> Option<()> {
+    let xs = list_items(xs)?;
+    let indices = Domain::full().restrict(Some(1), Some(xs.len() as i128));
+    narrow_term(env, i, &indices, queue)?;
+
+    let v_domain = term_domain(env, v)?;
+    let mut possible = Domain::empty();
+    let mut reachable = Domain::empty();
+
+    for k in term_domain(env, i)?.values() {
+        let x_domain = term_domain(env, &xs[k as usize - 1])?;
+
+        if !x_domain.intersect(&v_domain).is_empty() {
+            possible = possible.union(&Domain::singleton(k));
+            reachable = reachable.union(&x_domain);
+        }
+    }
+
+    narrow_term(env, i, &possible, queue)?;
+    narrow_term(env, v, &reachable, queue)?;
+
+    if let Term::Number(Number::Int(k)) = env.substitute_term(i) {
+        let x = &xs[k as usize - 1];
+        let both = term_domain(env, x)?.intersect(&term_domain(env, v)?);
+        narrow_term(env, x, &both, queue)?;
+        narrow_term(env, v, &both, queue)?;
+    }
+
+    Some(())
+}
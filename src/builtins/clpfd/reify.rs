@@ -0,0 +1,236 @@
+use super::domain::Domain;
+use super::{
+    linear, linear_terms, narrow, narrow_term, priority, prop, propagate, ranges, Compiler, Linear,
+};
+use crate::ast::{op, Atom, Const, Number, Term};
+use crate::builtins::propagation::Queue;
+use crate::builtins::{atom, throw, Branch};
+use crate::Environment;
+use std::convert::TryFrom;
+
+fn int(v: i64) -> Term {
+    Term::Number(Number::Int(v))
+}
+
+impl<'a> Compiler<'a> {
+    /// Returns a 0/1 term that is 1 exactly when the constraint `t` holds.
+    fn reify(&mut self, t: &Term) -> Option<Term> {
+        let t = self.env.substitute_term(t);
+
+        let (name, args) = match t {
+            Term::Var(ref x) => {
+                narrow(self.env, x, &boolean(), &mut self.queue)?;
+                return Some(t);
+            }
+            Term::Number(Number::Int(0)) | Term::Number(Number::Int(1)) => return Some(t),
+            Term::Atom(Atom {
+                name: Const(name),
+                args,
+                ..
+            }) => (name, args),
+            _ => return None,
+        };
+
+        match (&name[..], &args[..]) {
+            ("#\\", [x]) => {
+                let x = self.reify(x)?;
+                let b = self.boolean_var()?;
+                self.post_sum(&[(1, &b), (1, &x)], -1, "=")?;
+
+                Some(b)
+            }
+            ("#/\\", [x, y]) => self.connective(x, y, |c, x, y, b| {
+                c.post_sum(&[(1, b), (-1, x)], 0, "=<")?;
+                c.post_sum(&[(1, b), (-1, y)], 0, "=<")?;
+                c.post_sum(&[(1, x), (1, y), (-1, b)], -1, "=<")
+            }),
+            ("#\\/", [x, y]) => self.connective(x, y, |c, x, y, b| {
+                c.post_sum(&[(1, x), (-1, b)], 0, "=<")?;
+                c.post_sum(&[(1, y), (-1, b)], 0, "=<")?;
+                c.post_sum(&[(1, b), (-1, x), (-1, y)], 0, "=<")
+            }),
+            ("#==>", [x, y]) => self.implication(x, y),
+            ("#<==", [x, y]) => self.implication(y, x),
+            ("#\\", [x, y]) => self.connective(x, y, |c, x, y, b| {
+                let carry = c.boolean_var()?;
+                c.post_sum(&[(1, x), (1, y), (-1, b), (-2, &carry)], 0, "=")
+            }),
+            ("#<==>", [x, y]) => self.connective(x, y, |c, x, y, b| {
+                let carry = c.boolean_var()?;
+                c.post_sum(&[(1, x), (1, y), (1, b), (-2, &carry)], -1, "=")
+            }),
+            (rel, [l, r]) => {
+                let (linear, rel) = self.relation(rel, l, r)?;
+                let b = self.boolean_var()?;
+                let (pairs, c) = self.pairs(linear)?;
+                self.post(prop("$reified", vec![pairs, c, atom(rel), b.clone()]))?;
+
+                Some(b)
+            }
+            _ => None,
+        }
+    }
+
+    /// Reifies `x` and `y` and ties the result of their combination to them with `post`.
+    fn connective(
+        &mut self,
+        x: &Term,
+        y: &Term,
+        post: fn(&mut Self, &Term, &Term, &Term) -> Option<()>,
+    ) -> Option<Term> {
+        let x = self.reify(x)?;
+        let y = self.reify(y)?;
+        let b = self.boolean_var()?;
+        post(self, &x, &y, &b)?;
+
+        Some(b)
+    }
+
+    fn implication(&mut self, x: &Term, y: &Term) -> Option<Term> {
+        self.connective(x, y, |c, x, y, b| {
+            c.post_sum(&[(1, x), (1, b)], -1, ">=")?;
+            c.post_sum(&[(1, y), (-1, b)], 0, "=<")?;
+            c.post_sum(&[(1, b), (1, x), (-1, y)], -1, "=<")
+        })
+    }
+
+    fn boolean_var(&mut self) -> Option<Term> {
+        let b = self.fresh_var();
+        narrow(self.env, &b, &boolean(), &mut self.queue)?;
+
+        Some(Term::Var(b))
+    }
+
+    /// Posts `Σ a·t + c rel 0` over 0/1 terms, where `>=` stands for `Σ a·t + c >= 0`.
+    fn post_sum(&mut self, terms: &[(i128, &Term)], c: i128, rel: &str) -> Option<()> {
+        let sign = if rel == ">=" { -1 } else { 1 };
+        let mut linear = Linear {
+            c: sign * c,
+            ..Linear::default()
+        };
+
+        for &(a, t) in terms {
+            match self.env.substitute_term(t) {
+                Term::Var(x) => linear.terms.push((sign * a, x)),
+                Term::Number(Number::Int(v)) => linear.c += sign * a * i128::from(v),
+                _ => return None,
+            }
+        }
+
+        let rel = if rel == ">=" { "=<" } else { rel };
+        self.post_linear(linear, rel)
+    }
+}
+
+fn boolean() -> Domain {
+    Domain::full().restrict(Some(0), Some(1))
+}
+
+/// Posts the boolean combination of constraints `goal`, which must hold.
+pub(in crate::builtins) fn reified(env: &Environment, goal: &Term) -> Vec<Branch> {
+    let mut constrained = env.clone();
+
+    let (posted, error) = {
+        let mut compiler = Compiler {
+            env: &mut constrained,
+            number: crate::fresh(),
+            fresh: 0,
+            queue: Queue::new(priority),
+            error: None,
+        };
+
+        let posted = compiler.reify(goal).and_then(|b| {
+            narrow_term(compiler.env, &b, &Domain::singleton(1), &mut compiler.queue)
+        });
+        let error = compiler.error.take();
+        (posted.map(|_| compiler.queue), error)
+    };
+
+    if let Some(error) = error {
+        return throw(env, error);
+    }
+
+    let mut env = constrained;
+
+    match posted.and_then(|queue| propagate(&mut env, queue)) {
+        Some(()) => vec![(env, vec![])],
+        None => vec![],
+    }
+}
+
+/// Propagation for `b ⇔ Σ a·x + c rel 0`: once `b` is known the constraint or its negation is
+/// enforced, and until then `b` is fixed as soon as the bounds decide the constraint.
+pub(super) fn propagate_reified(
+    env: &mut Environment,
+    pairs: &[Term],
+    c: i128,
+    rel: &str,
+    b: &Term,
+    queue: &mut Queue,
+) -> Option<()> {
+    match b {
+        Term::Number(Number::Int(1)) => return linear(env, pairs, c, rel, queue),
+        Term::Number(Number::Int(0)) => {
+            return match rel {
+                "=" => linear(env, pairs, c, "\\=", queue),
+                "\\=" => linear(env, pairs, c, "=", queue),
+                _ => {
+                    let negated: Vec<_> = pairs.iter().map(negate).collect::<Option<_>>()?;
+                    linear(env, &negated, 1 - c, "=<", queue)
+                }
+            };
+        }
+        Term::Var(_) => (),
+        _ => return None,
+    }
+
+    let mut c = c;
+    let terms = linear_terms(pairs, &mut c)?;
+    let ranges = ranges(env, &terms);
+
+    let lo = ranges
+        .iter()
+        .map(|r| r.0)
+        .sum::<Option<i128>>()
+        .map(|lo| lo + c);
+    let hi = ranges
+        .iter()
+        .map(|r| r.1)
+        .sum::<Option<i128>>()
+        .map(|hi| hi + c);
+
+    let excludes_zero = lo.is_some_and(|lo| lo > 0)
+        || hi.is_some_and(|hi| hi < 0)
+        || match terms[..] {
+            [(a, x)] => {
+                c % a != 0
+                    || !i64::try_from(-c / a).is_ok_and(|v| super::domain(env, x).contains(v))
+            }
+            _ => false,
+        };
+
+    let decided = match rel {
+        "=" if lo == Some(0) && hi == Some(0) => Some(1),
+        "=" if excludes_zero => Some(0),
+        "\\=" if lo == Some(0) && hi == Some(0) => Some(0),
+        "\\=" if excludes_zero => Some(1),
+        "=<" if hi.is_some_and(|hi| hi <= 0) => Some(1),
+        "=<" if lo.is_some_and(|lo| lo > 0) => Some(0),
+        _ => None,
+    };
+
+    match decided {
+        Some(value) => narrow_term(env, b, &Domain::singleton(value), queue),
+        None => Some(()),
+    }
+}
+
+fn negate(pair: &Term) -> Option<Term> {
+    match pair {
+        Term::Atom(Atom { args, .. }) => match args[0] {
+            Term::Number(Number::Int(a)) => Some(op("*", int(-a), args[1].clone())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
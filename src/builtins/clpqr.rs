@@ -0,0 +1,612 @@
+mod simplex;
+
+use self::simplex::{gcd, maximize, Outcome, Rat, Rel, Row};
+use super::{list_items, unify, Branch};
+use crate::ast::{op, Atom, Const, Number, Term, Var};
+use crate::Environment;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+const MODULE: &str = "clpqr";
+
+/// A linear expression `Σ a·x + c` without zero coefficients.
+#[derive(Debug, Clone, PartialEq)]
+struct Linear {
+    terms: BTreeMap<Var, Rat>,
+    c: Rat,
+}
+
+impl Linear {
+    fn constant(c: Rat) -> Self {
+        Linear {
+            terms: BTreeMap::new(),
+            c,
+        }
+    }
+
+    /// Adds `scale` times `other` to the expression.
+    fn add(&mut self, other: &Linear, scale: Rat) -> Option<()> {
+        for (x, a) in &other.terms {
+            let sum = self
+                .terms
+                .get(x)
+                .copied()
+                .unwrap_or(Rat::ZERO)
+                .add(a.mul(scale)?)?;
+
+            if sum.is_zero() {
+                self.terms.remove(x);
+            } else {
+                self.terms.insert(x.clone(), sum);
+            }
+        }
+
+        self.c = self.c.add(other.c.mul(scale)?)?;
+        Some(())
+    }
+
+    fn scale(&self, by: Rat) -> Option<Linear> {
+        let mut scaled = Linear::constant(Rat::ZERO);
+        scaled.add(self, by)?;
+
+        Some(scaled)
+    }
+}
+
+/// Reads the substituted expression `t` as a linear expression. Returns None if it is not
+/// linear.
+fn linearize(t: &Term) -> Option<Linear> {
+    match t {
+        Term::Var(x) => {
+            let mut linear = Linear::constant(Rat::ZERO);
+            linear.terms.insert(x.clone(), Rat::ONE);
+
+            Some(linear)
+        }
+        Term::Number(Number::Int(v)) => Some(Linear::constant(Rat::int(i128::from(*v)))),
+        Term::Number(Number::Float(f)) => Some(Linear::constant(Rat::from_f64(*f)?)),
+        Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) => match (&name[..], &args[..]) {
+            ("+", [x]) => linearize(x),
+            ("-", [x]) => linearize(x)?.scale(Rat::int(-1)),
+            ("+", [x, y]) | ("-", [x, y]) => {
+                let mut sum = linearize(x)?;
+                let sign = if name == "+" { 1 } else { -1 };
+                sum.add(&linearize(y)?, Rat::int(sign))?;
+
+                Some(sum)
+            }
+            ("*", [x, y]) => {
+                let (x, y) = (linearize(x)?, linearize(y)?);
+
+                match (x.terms.is_empty(), y.terms.is_empty()) {
+                    (true, _) => y.scale(x.c),
+                    (_, true) => x.scale(y.c),
+                    _ => None,
+                }
+            }
+            ("/", [x, y]) => {
+                let y = linearize(y)?;
+
+                if !y.terms.is_empty() {
+                    return None;
+                }
+
+                linearize(x)?.scale(Rat::ONE.div(y.c)?)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A constraint `Σ a·x + c rel 0`, where `rel` is one of `=`, `=<` and `<`.
+#[derive(Debug, Clone, PartialEq)]
+struct Constraint {
+    linear: Linear,
+    rel: &'static str,
+}
+
+impl Constraint {
+    /// Scales the constraint to coprime integers, with the first coefficient of an equation
+    /// positive.
+    fn normalize(self) -> Option<Constraint> {
+        let mut lcm = 1i128;
+        let mut divisor = 0i128;
+
+        for r in self.linear.terms.values().chain(Some(&self.linear.c)) {
+            lcm = (lcm / gcd(lcm, r.den)).checked_mul(r.den)?;
+        }
+
+        for r in self.linear.terms.values().chain(Some(&self.linear.c)) {
+            divisor = gcd(divisor, r.num.checked_mul(lcm / r.den)?);
+        }
+
+        let first = self.linear.terms.values().next().copied();
+        let sign = match first {
+            Some(a) if self.rel == "=" && a.signum() < 0 => -1,
+            _ => 1,
+        };
+
+        let scale = Rat::new(sign * lcm, divisor.max(1))?;
+
+        Some(Constraint {
+            linear: self.linear.scale(scale)?,
+            rel: self.rel,
+        })
+    }
+
+    /// Checks a constraint without variables.
+    fn holds(&self) -> bool {
+        match self.rel {
+            "=" => self.linear.c.is_zero(),
+            "=<" => self.linear.c.signum() <= 0,
+            _ => self.linear.c.signum() < 0,
+        }
+    }
+
+    /// Writes the constraint as `Σ a·x rel -c`, turning an inequality around if its first
+    /// coefficient is negative.
+    fn to_term(&self) -> Term {
+        let flip = self.rel != "="
+            && self
+                .linear
+                .terms
+                .values()
+                .next()
+                .is_some_and(|a| a.signum() < 0);
+        let rel = match self.rel {
+            "=<" if flip => ">=",
+            "<" if flip => ">",
+            rel => rel,
+        };
+        let linear = if flip {
+            self.linear
+                .scale(Rat::int(-1))
+                .unwrap_or_else(|| self.linear.clone())
+        } else {
+            self.linear.clone()
+        };
+
+        let mut lhs: Option<Term> = None;
+
+        for (x, &a) in &linear.terms {
+            let negative = a.signum() < 0;
+            let abs = if negative { a.neg() } else { a };
+            let t = match abs {
+                Rat::ONE => Term::Var(x.clone()),
+                abs => op("*", number(abs), Term::Var(x.clone())),
+            };
+
+            lhs = Some(match lhs {
+                None if negative => Term::Atom(Atom::new("-", vec![t])),
+                None => t,
+                Some(lhs) if negative => op("-", lhs, t),
+                Some(lhs) => op("+", lhs, t),
+            });
+        }
+
+        op(
+            rel,
+            lhs.unwrap_or_else(|| number(Rat::ZERO)),
+            number(linear.c.neg()),
+        )
+    }
+}
+
+/// Writes a rational as an integer, as `N/D`, or as a float if it does not fit either.
+fn number(r: Rat) -> Term {
+    match (i64::try_from(r.num), i64::try_from(r.den)) {
+        (Ok(num), Ok(1)) => Term::Number(Number::Int(num)),
+        (Ok(num), Ok(den)) => op(
+            "/",
+            Term::Number(Number::Int(num)),
+            Term::Number(Number::Int(den)),
+        ),
+        _ => Term::Number(Number::Float(r.to_f64())),
+    }
+}
+
+/// The value a variable fixed at `r` is bound to.
+fn value(r: Rat) -> Term {
+    match (i64::try_from(r.num), r.den) {
+        (Ok(num), 1) => Term::Number(Number::Int(num)),
+        _ => Term::Number(Number::Float(r.to_f64())),
+    }
+}
+
+/// Reads a conjunction of `=`, `=:=`, `=<`, `>=`, `<` and `>` comparisons between linear
+/// expressions into `into`.
+fn constraints(t: &Term, into: &mut Vec<Constraint>) -> Option<()> {
+    let (name, args) = match t {
+        Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) if args.len() == 2 => (name, args),
+        _ => return None,
+    };
+
+    let (rel, l, r) = match &name[..] {
+        "," => {
+            constraints(&args[0], into)?;
+            return constraints(&args[1], into);
+        }
+        "=" | "=:=" => ("=", &args[0], &args[1]),
+        "=<" => ("=<", &args[0], &args[1]),
+        ">=" => ("=<", &args[1], &args[0]),
+        "<" => ("<", &args[0], &args[1]),
+        ">" => ("<", &args[1], &args[0]),
+        _ => return None,
+    };
+
+    let mut linear = linearize(l)?;
+    linear.add(&linearize(r)?, Rat::int(-1))?;
+    into.push(Constraint { linear, rel });
+
+    Some(())
+}
+
+/// The constraints stored on `x` as `clpqr(Constraints)`.
+fn stored(env: &Environment, x: &Var) -> Vec<Term> {
+    match env.get_attr(x, MODULE) {
+        Some(Term::Atom(Atom { args, .. })) if args.len() == 1 => {
+            list_items(&args[0]).unwrap_or_default()
+        }
+        _ => vec![],
+    }
+}
+
+/// Gathers `new`, the constraints stored on `roots`, and transitively those stored on every
+/// variable they mention, dropping duplicates. Fails if a constraint without variables is false.
+fn gather(
+    env: &Environment,
+    mut new: Vec<Constraint>,
+    roots: &[Var],
+) -> Option<(Vec<Constraint>, Vec<Var>)> {
+    let mut store: Vec<Constraint> = Vec::new();
+    let mut vars: Vec<Var> = Vec::new();
+    let mut visit = roots.to_vec();
+
+    loop {
+        for x in visit.drain(..) {
+            if !vars.contains(&x) {
+                for t in stored(env, &x) {
+                    constraints(&env.substitute_term(&t), &mut new)?;
+                }
+
+                vars.push(x);
+            }
+        }
+
+        if new.is_empty() {
+            return Some((store, vars));
+        }
+
+        let c = new.remove(0).normalize()?;
+
+        if c.linear.terms.is_empty() {
+            if !c.holds() {
+                return None;
+            }
+        } else if !store.contains(&c) {
+            visit.extend(c.linear.terms.keys().cloned());
+            store.push(c);
+        }
+    }
+}
+
+/// Builds simplex rows over `vars` from `store`. When `strict` is set, an extra last variable ε
+/// in `[0, 1]` is added to the left-hand side of every strict inequality.
+fn rows(store: &[Constraint], vars: &[Var], strict: bool) -> Vec<Row> {
+    let n = vars.len() + strict as usize;
+    let mut rows = Vec::with_capacity(store.len() + 2);
+
+    for c in store {
+        let mut coeffs = vec![Rat::ZERO; n];
+
+        for (x, a) in &c.linear.terms {
+            if let Some(i) = vars.iter().position(|y| y == x) {
+                coeffs[i] = *a;
+            }
+        }
+
+        if strict && c.rel == "<" {
+            coeffs[n - 1] = Rat::ONE;
+        }
+
+        rows.push(Row {
+            coeffs,
+            rel: if c.rel == "=" { Rel::Eq } else { Rel::Le },
+            rhs: c.linear.c.neg(),
+        });
+    }
+
+    if strict {
+        for sign in [1, -1] {
+            let mut coeffs = vec![Rat::ZERO; n];
+            coeffs[n - 1] = Rat::int(sign);
+
+            rows.push(Row {
+                coeffs,
+                rel: Rel::Le,
+                rhs: if sign > 0 { Rat::ONE } else { Rat::ZERO },
+            });
+        }
+    }
+
+    rows
+}
+
+/// Checks that `store` is satisfiable and returns the variables it fixes to a single value.
+fn fixed(store: &[Constraint], vars: &[Var]) -> Option<Vec<(Var, Rat)>> {
+    let strict = store.iter().any(|c| c.rel == "<");
+    let rows = rows(store, vars, strict);
+    let n = vars.len() + strict as usize;
+
+    let mut objective = vec![Rat::ZERO; n];
+
+    if strict {
+        objective[n - 1] = Rat::ONE;
+    }
+
+    match maximize(&rows, n, &objective)? {
+        Outcome::Optimal(epsilon) if !strict || epsilon.signum() > 0 => (),
+        _ => return None,
+    }
+
+    let mut fixed = Vec::new();
+
+    for (i, x) in vars.iter().enumerate() {
+        let mut objective = vec![Rat::ZERO; n];
+        objective[i] = Rat::ONE;
+        let hi = maximize(&rows, n, &objective)?;
+        objective[i] = Rat::int(-1);
+        let lo = maximize(&rows, n, &objective)?;
+
+        if let (Outcome::Optimal(hi), Outcome::Optimal(lo)) = (hi, lo) {
+            if hi == lo.neg() {
+                fixed.push((x.clone(), hi));
+            }
+        }
+    }
+
+    Some(fixed)
+}
+
+/// Adds `new` to the constraints connected to it, fails if they are unsatisfiable, binds the
+/// variables they fix and stores what is left on the remaining variables.
+fn solve(env: &mut Environment, new: Vec<Constraint>) -> Option<()> {
+    let (store, vars) = gather(env, new, &[])?;
+    let fixed = fixed(&store, &vars)?;
+    let mut left: Vec<Constraint> = Vec::new();
+
+    for mut c in store {
+        for (x, v) in &fixed {
+            if let Some(a) = c.linear.terms.remove(x) {
+                c.linear.c = c.linear.c.add(a.mul(*v)?)?;
+            }
+        }
+
+        let c = c.normalize()?;
+
+        if !c.linear.terms.is_empty() && !left.contains(&c) {
+            left.push(c);
+        }
+    }
+
+    let goals = Term::list(left.iter().map(Constraint::to_term).collect(), Term::nil());
+
+    for x in vars {
+        env.del_attr(&x, MODULE);
+
+        if let Some((_, v)) = fixed.iter().find(|(y, _)| *y == x) {
+            env.insert(x, value(*v));
+        } else if left.iter().any(|c| c.linear.terms.contains_key(&x)) {
+            env.put_attr(
+                x,
+                MODULE,
+                Term::Atom(Atom::new(MODULE, vec![goals.clone()])),
+            );
+        }
+    }
+
+    Some(())
+}
+
+fn solved(mut env: Environment, new: Option<Vec<Constraint>>) -> Vec<Branch> {
+    match new.and_then(|new| solve(&mut env, new)) {
+        Some(()) => vec![(env, vec![])],
+        None => vec![],
+    }
+}
+
+/// Adds the linear constraints in `goal`, a conjunction of comparisons, to the store.
+pub(super) fn constrain(env: &Environment, goal: &Term) -> Vec<Branch> {
+    let mut new = Vec::new();
+    let parsed = constraints(&env.substitute_term(goal), &mut new).map(|_| new);
+
+    solved(env.clone(), parsed)
+}
+
+/// Solves the constraints of a variable again once it has been unified, stored as
+/// `clpqr(Constraints)` in `value`.
+pub(super) fn wakeup(env: &Environment, value: &Term) -> Vec<Branch> {
+    let goals = match value {
+        Term::Atom(Atom { args, .. }) if args.len() == 1 => {
+            list_items(&args[0]).unwrap_or_default()
+        }
+        _ => return vec![],
+    };
+
+    let mut new = Vec::new();
+    let parsed = goals
+        .iter()
+        .try_for_each(|goal| constraints(&env.substitute_term(goal), &mut new))
+        .map(|_| new);
+
+    solved(env.clone(), parsed)
+}
+
+/// The least value of `expr` under the stored constraints when `sign` is -1, or its greatest when
+/// `sign` is 1. Returns None if it is unbounded.
+fn optimum(env: &Environment, expr: &Term, sign: i128) -> Option<Rat> {
+    let expr = linearize(&env.substitute_term(expr))?;
+    let roots: Vec<Var> = expr.terms.keys().cloned().collect();
+    let (store, vars) = gather(env, vec![], &roots)?;
+
+    let objective = vars
+        .iter()
+        .map(|x| match expr.terms.get(x) {
+            Some(a) => a.mul(Rat::int(sign)),
+            None => Some(Rat::ZERO),
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    match maximize(&rows(&store, &vars, false), vars.len(), &objective)? {
+        Outcome::Optimal(v) => v.mul(Rat::int(sign))?.add(expr.c),
+        _ => None,
+    }
+}
+
+/// Unifies `bound` with the infimum (`sign` -1) or supremum (`sign` 1) of `expr`.
+pub(super) fn bound(env: &Environment, expr: &Term, bound: &Term, sign: i128) -> Vec<Branch> {
+    match optimum(env, expr, sign) {
+        Some(v) => unify(env, bound, &value(v)),
+        None => vec![],
+    }
+}
+
+/// Constrains `expr` to its infimum (`sign` -1) or supremum (`sign` 1).
+pub(super) fn optimize(env: &Environment, expr: &Term, sign: i128) -> Vec<Branch> {
+    let parsed = optimum(env, expr, sign).and_then(|v| {
+        let mut linear = linearize(&env.substitute_term(expr))?;
+        linear.c = linear.c.sub(v)?;
+
+        Some(vec![Constraint { linear, rel: "=" }])
+    });
+
+    solved(env.clone(), parsed)
+}
+
+/// Eliminates the `hidden` variables from `store`, solving equations for them where possible and
+/// combining the inequalities they occur in otherwise, then drops the inequalities the others
+/// imply.
+fn project(mut store: Vec<Constraint>, hidden: &[Var]) -> Option<Vec<Constraint>> {
+    for h in hidden {
+        let equation = store
+            .iter()
+            .position(|c| c.rel == "=" && c.linear.terms.contains_key(h));
+
+        if let Some(i) = equation {
+            let eq = store.remove(i);
+            let a = eq.linear.terms[h];
+
+            for c in store.iter_mut() {
+                if let Some(b) = c.linear.terms.get(h).copied() {
+                    c.linear.add(&eq.linear, b.div(a)?.neg())?;
+                }
+            }
+
+            continue;
+        }
+
+        let (with, mut without): (Vec<_>, Vec<_>) = store
+            .into_iter()
+            .partition(|c| c.linear.terms.contains_key(h));
+
+        for p in with.iter().filter(|c| c.linear.terms[h].signum() > 0) {
+            for q in with.iter().filter(|c| c.linear.terms[h].signum() < 0) {
+                let mut linear = p.linear.scale(q.linear.terms[h].neg())?;
+                linear.add(&q.linear, p.linear.terms[h])?;
+
+                let rel = if p.rel == "<" || q.rel == "<" {
+                    "<"
+                } else {
+                    "=<"
+                };
+                without.push(Constraint { linear, rel });
+            }
+        }
+
+        store = without;
+    }
+
+    let mut projected: Vec<Constraint> = Vec::new();
+
+    for c in store {
+        let c = c.normalize()?;
+
+        if !c.linear.terms.is_empty() && !projected.contains(&c) {
+            projected.push(c);
+        }
+    }
+
+    let mut i = 0;
+
+    while i < projected.len() {
+        if projected[i].rel != "=" && implied(&projected, i)? {
+            projected.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    Some(projected)
+}
+
+/// Checks whether the inequality `store[i]` follows from the rest of `store`.
+fn implied(store: &[Constraint], i: usize) -> Option<bool> {
+    let rest: Vec<Constraint> = store
+        .iter()
+        .enumerate()
+        .filter(|&(j, _)| j != i)
+        .map(|(_, c)| c.clone())
+        .collect();
+
+    let mut vars: Vec<Var> = Vec::new();
+
+    for x in store.iter().flat_map(|c| c.linear.terms.keys()) {
+        if !vars.contains(x) {
+            vars.push(x.clone());
+        }
+    }
+
+    let objective: Vec<Rat> = vars
+        .iter()
+        .map(|x| store[i].linear.terms.get(x).copied().unwrap_or(Rat::ZERO))
+        .collect();
+
+    Some(
+        match maximize(&rows(&rest, &vars, false), vars.len(), &objective)? {
+            Outcome::Optimal(v) => match v.add(store[i].linear.c)?.signum() {
+                0 => store[i].rel == "=<",
+                sign => sign < 0,
+            },
+            _ => false,
+        },
+    )
+}
+
+/// Describes the constraints connected to `x` as `{}/1` goals, projected onto the variables in
+/// `shown`.
+pub(super) fn residual_goals(env: &Environment, x: &Var, shown: &[Var]) -> Vec<Term> {
+    if !shown.contains(x) {
+        return vec![];
+    }
+
+    let projected = gather(env, vec![], std::slice::from_ref(x)).and_then(|(store, vars)| {
+        let hidden: Vec<Var> = vars.into_iter().filter(|y| !shown.contains(y)).collect();
+
+        project(store, &hidden)
+    });
+
+    projected
+        .unwrap_or_default()
+        .iter()
+        .map(|c| Term::Atom(Atom::new("{}", vec![c.to_term()])))
+        .collect()
+}
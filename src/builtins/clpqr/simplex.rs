@@ -0,0 +1,320 @@
+use std::cmp::Ordering;
+
+/// An exact rational number with a positive denominator, in lowest terms. Arithmetic returns
+/// None on overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct Rat {
+    pub(super) num: i128,
+    pub(super) den: i128,
+}
+
+pub(super) fn gcd(mut x: i128, mut y: i128) -> i128 {
+    while y != 0 {
+        let r = x % y;
+        x = y;
+        y = r;
+    }
+
+    x.abs()
+}
+
+impl Rat {
+    pub(super) const ZERO: Rat = Rat { num: 0, den: 1 };
+    pub(super) const ONE: Rat = Rat { num: 1, den: 1 };
+
+    pub(super) fn new(num: i128, den: i128) -> Option<Rat> {
+        if den == 0 {
+            return None;
+        }
+
+        let g = gcd(num, den);
+        let sign = if den < 0 { -1 } else { 1 };
+
+        Some(Rat {
+            num: sign * num / g,
+            den: sign * den / g,
+        })
+    }
+
+    pub(super) fn int(i: i128) -> Rat {
+        Rat { num: i, den: 1 }
+    }
+
+    /// Reads the shortest decimal representation of `f` exactly.
+    pub(super) fn from_f64(f: f64) -> Option<Rat> {
+        if !f.is_finite() {
+            return None;
+        }
+
+        let text = format!("{}", f);
+        let (whole, fraction) = match text.find('.') {
+            Some(dot) => (&text[..dot], &text[dot + 1..]),
+            None => (&text[..], ""),
+        };
+
+        let den = 10i128.checked_pow(fraction.len() as u32)?;
+        let digits: i128 = format!("{}{}", whole, fraction).parse().ok()?;
+
+        Rat::new(digits, den)
+    }
+
+    pub(super) fn is_zero(self) -> bool {
+        self.num == 0
+    }
+
+    pub(super) fn signum(self) -> i128 {
+        self.num.signum()
+    }
+
+    pub(super) fn add(self, other: Rat) -> Option<Rat> {
+        let num = self
+            .num
+            .checked_mul(other.den)?
+            .checked_add(other.num.checked_mul(self.den)?)?;
+
+        Rat::new(num, self.den.checked_mul(other.den)?)
+    }
+
+    pub(super) fn neg(self) -> Rat {
+        Rat {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+
+    pub(super) fn sub(self, other: Rat) -> Option<Rat> {
+        self.add(other.neg())
+    }
+
+    pub(super) fn mul(self, other: Rat) -> Option<Rat> {
+        let (g1, g2) = (
+            gcd(self.num, other.den).max(1),
+            gcd(other.num, self.den).max(1),
+        );
+        let num = (self.num / g1).checked_mul(other.num / g2)?;
+        let den = (self.den / g2).checked_mul(other.den / g1)?;
+
+        Rat::new(num, den)
+    }
+
+    pub(super) fn div(self, other: Rat) -> Option<Rat> {
+        self.mul(Rat::new(other.den, other.num)?)
+    }
+
+    pub(super) fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+}
+
+impl PartialOrd for Rat {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rat {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (
+            self.num.checked_mul(other.den),
+            other.num.checked_mul(self.den),
+        ) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            _ => self
+                .to_f64()
+                .partial_cmp(&other.to_f64())
+                .unwrap_or(Ordering::Equal),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Rel {
+    Le,
+    Eq,
+}
+
+/// A constraint `Σ coeffs[i]·x[i] rel rhs` over free variables.
+#[derive(Debug, Clone)]
+pub(super) struct Row {
+    pub(super) coeffs: Vec<Rat>,
+    pub(super) rel: Rel,
+    pub(super) rhs: Rat,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Outcome {
+    Infeasible,
+    Unbounded,
+    Optimal(Rat),
+}
+
+/// A simplex tableau: each row ends with its right-hand side, and the objective row is kept
+/// separately with the negated objective value in its last entry.
+struct Tableau {
+    rows: Vec<Vec<Rat>>,
+    basis: Vec<usize>,
+    objective: Vec<Rat>,
+}
+
+impl Tableau {
+    fn pivot(&mut self, r: usize, c: usize) -> Option<()> {
+        let p = self.rows[r][c];
+
+        for v in self.rows[r].iter_mut() {
+            *v = v.div(p)?;
+        }
+
+        let pivot_row = self.rows[r].clone();
+
+        for (i, row) in self.rows.iter_mut().enumerate() {
+            if i != r && !row[c].is_zero() {
+                let f = row[c];
+
+                for (v, p) in row.iter_mut().zip(pivot_row.iter()) {
+                    *v = v.sub(f.mul(*p)?)?;
+                }
+            }
+        }
+
+        if !self.objective[c].is_zero() {
+            let f = self.objective[c];
+
+            for (v, p) in self.objective.iter_mut().zip(pivot_row.iter()) {
+                *v = v.sub(f.mul(*p)?)?;
+            }
+        }
+
+        self.basis[r] = c;
+        Some(())
+    }
+
+    /// Maximizes the objective using Bland's rule, only letting the first `enter` columns
+    /// enter the basis. Returns false if the objective is unbounded.
+    fn optimize(&mut self, enter: usize) -> Option<bool> {
+        loop {
+            let c = match (0..enter).find(|&c| self.objective[c].signum() < 0) {
+                Some(c) => c,
+                None => return Some(true),
+            };
+
+            let rhs = self.objective.len() - 1;
+            let mut leaving: Option<(usize, Rat)> = None;
+
+            for (r, row) in self.rows.iter().enumerate() {
+                if row[c].signum() > 0 {
+                    let ratio = row[rhs].div(row[c])?;
+
+                    let better = match leaving {
+                        None => true,
+                        Some((l, best)) => {
+                            ratio < best || (ratio == best && self.basis[r] < self.basis[l])
+                        }
+                    };
+
+                    if better {
+                        leaving = Some((r, ratio));
+                    }
+                }
+            }
+
+            match leaving {
+                Some((r, _)) => self.pivot(r, c)?,
+                None => return Some(false),
+            }
+        }
+    }
+}
+
+/// Maximizes `Σ objective[i]·x[i]` subject to `rows` over `n` free variables. Returns None if
+/// the rationals involved overflow.
+pub(super) fn maximize(rows: &[Row], n: usize, objective: &[Rat]) -> Option<Outcome> {
+    // Each free variable is split into x⁺ - x⁻, every inequality gets a slack variable, and
+    // every row an artificial variable for the first phase.
+    let slacks = rows.iter().filter(|row| row.rel == Rel::Le).count();
+    let structural = 2 * n;
+    let artificial = structural + slacks;
+    let width = artificial + rows.len() + 1;
+
+    let mut tableau = Tableau {
+        rows: Vec::with_capacity(rows.len()),
+        basis: Vec::with_capacity(rows.len()),
+        objective: vec![Rat::ZERO; width],
+    };
+
+    let mut slack = structural;
+
+    for (i, row) in rows.iter().enumerate() {
+        let mut entries = vec![Rat::ZERO; width];
+
+        for (j, a) in row.coeffs.iter().enumerate() {
+            entries[2 * j] = *a;
+            entries[2 * j + 1] = a.neg();
+        }
+
+        if row.rel == Rel::Le {
+            entries[slack] = Rat::ONE;
+            slack += 1;
+        }
+
+        entries[width - 1] = row.rhs;
+
+        if row.rhs.signum() < 0 {
+            for v in entries.iter_mut() {
+                *v = v.neg();
+            }
+        }
+
+        entries[artificial + i] = Rat::ONE;
+        tableau.rows.push(entries);
+        tableau.basis.push(artificial + i);
+    }
+
+    // Phase one: maximize -Σ artificial, canonicalized against the artificial basis.
+    for row in &tableau.rows {
+        for (c, v) in tableau.objective.iter_mut().enumerate() {
+            if c < artificial || c == width - 1 {
+                *v = v.sub(row[c])?;
+            }
+        }
+    }
+
+    tableau.optimize(artificial)?;
+
+    if tableau.objective[width - 1].signum() != 0 {
+        return Some(Outcome::Infeasible);
+    }
+
+    for r in 0..tableau.rows.len() {
+        if tableau.basis[r] >= artificial {
+            if let Some(c) = (0..artificial).find(|&c| !tableau.rows[r][c].is_zero()) {
+                tableau.pivot(r, c)?;
+            }
+        }
+    }
+
+    // Phase two: the objective row holds -c, made canonical against the current basis.
+    tableau.objective = vec![Rat::ZERO; width];
+
+    for (j, c) in objective.iter().enumerate() {
+        tableau.objective[2 * j] = c.neg();
+        tableau.objective[2 * j + 1] = *c;
+    }
+
+    for r in 0..tableau.rows.len() {
+        let b = tableau.basis[r];
+        let f = tableau.objective[b];
+
+        if !f.is_zero() {
+            for c in 0..width {
+                let v = tableau.objective[c].sub(f.mul(tableau.rows[r][c])?)?;
+                tableau.objective[c] = v;
+            }
+        }
+    }
+
+    if !tableau.optimize(artificial)? {
+        return Some(Outcome::Unbounded);
+    }
+
+    Some(Outcome::Optimal(tableau.objective[width - 1]))
+}
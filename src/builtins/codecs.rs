@@ -0,0 +1,216 @@
+use super::{atom, text, unify, Branch};
+use crate::ast::{Atom, Const, Number, Term};
+use crate::Environment;
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const HEX: &[u8] = b"0123456789abcdef";
+
+pub(super) fn base64(env: &Environment, plain: &Term, encoded: &Term) -> Vec<Branch> {
+    let plain = env.substitute_term(plain);
+
+    if let Some(plain) = text(&plain) {
+        return unify(env, encoded, &atom(&base64_encode(plain.as_bytes())));
+    }
+
+    let decoded = text(&env.substitute_term(encoded))
+        .and_then(|encoded| base64_decode(&encoded))
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+
+    match decoded {
+        Some(decoded) => unify(env, &plain, &atom(&decoded)),
+        None => vec![],
+    }
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(BASE64[(n >> (18 - 6 * i)) as usize & 63]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u32> = encoded
+        .trim_end_matches('=')
+        .bytes()
+        .map(|b| BASE64.iter().position(|&d| d == b).map(|d| d as u32))
+        .collect::<Option<_>>()?;
+
+    if digits.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+
+    for chunk in digits.chunks(4) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |n, (i, d)| n | (d << (18 - 6 * i)));
+
+        for i in 0..chunk.len() - 1 {
+            bytes.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+pub(super) fn hex_bytes(env: &Environment, hex: &Term, bytes: &Term) -> Vec<Branch> {
+    let hex = env.substitute_term(hex);
+
+    if let Some(hex) = text(&hex) {
+        return match hex_decode(&hex) {
+            Some(decoded) => {
+                let decoded = decoded
+                    .into_iter()
+                    .map(|b| Term::Number(Number::Int(i64::from(b))))
+                    .collect();
+
+                unify(env, bytes, &Term::list(decoded, Term::nil()))
+            }
+            None => vec![],
+        };
+    }
+
+    match byte_list(&env.substitute_term(bytes)) {
+        Some(bytes) => unify(env, &hex, &atom(&hex_encode(&bytes))),
+        None => vec![],
+    }
+}
+
+pub(super) fn hex_encode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .flat_map(|b| {
+            vec![
+                char::from(HEX[(b >> 4) as usize]),
+                char::from(HEX[(b & 15) as usize]),
+            ]
+        })
+        .collect()
+}
+
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()?;
+
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+
+    Some(digits.chunks(2).map(|d| (d[0] << 4) | d[1]).collect())
+}
+
+pub(super) fn byte_list(t: &Term) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut t = t;
+
+    loop {
+        match t {
+            Term::Atom(Atom {
+                name: Const(name),
+                args,
+                ..
+            }) if name == "." && args.len() == 2 => {
+                match args[0] {
+                    Term::Number(Number::Int(b)) if (0..256).contains(&b) => bytes.push(b as u8),
+                    _ => return None,
+                }
+
+                t = &args[1];
+            }
+            Term::PartialString(chunk, tail) => {
+                for c in chunk.chars() {
+                    match c as u32 {
+                        code if code < 256 => bytes.push(code as u8),
+                        _ => return None,
+                    }
+                }
+
+                t = tail;
+            }
+            t if t.is_nil() => return Some(bytes),
+            _ => return None,
+        }
+    }
+}
+
+pub(super) fn uri_encoded(
+    env: &Environment,
+    component: &Term,
+    value: &Term,
+    encoded: &Term,
+) -> Vec<Branch> {
+    let component = match text(&env.substitute_term(component)) {
+        Some(component) => component,
+        None => return vec![],
+    };
+
+    let allowed: &str = match &component[..] {
+        "query_value" => "-._~!$'()*,;:@/?",
+        "fragment" => "-._~!$&'()*+,;=:@/?",
+        "path" => "-._~!$&'()*+,;=:@/",
+        "segment" => "-._~!$&'()*+,;=:@",
+        _ => return vec![],
+    };
+
+    let value = env.substitute_term(value);
+
+    if let Some(value) = text(&value) {
+        let mut uri = String::with_capacity(value.len());
+
+        for b in value.bytes() {
+            if b.is_ascii_alphanumeric() || allowed.as_bytes().contains(&b) {
+                uri.push(char::from(b));
+            } else {
+                uri.push_str(&format!("%{:02X}", b));
+            }
+        }
+
+        return unify(env, encoded, &atom(&uri));
+    }
+
+    let decoded = text(&env.substitute_term(encoded)).and_then(|uri| percent_decode(&uri));
+
+    match decoded {
+        Some(decoded) => unify(env, &value, &atom(&decoded)),
+        None => vec![],
+    }
+}
+
+fn percent_decode(uri: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(uri.len());
+    let mut rest = uri.as_bytes();
+
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,337 @@
+use super::{atom, list_items, text, unify, Branch};
+use crate::ast::{Atom, Const, Term, Var};
+use crate::sharing::free_vars;
+use crate::{fresh, rename_fresh, term_vars, Environment};
+
+const BUILTIN_MODULES: [&str; 5] = ["freeze", "when", "dif", "clpfd", "clpqr"];
+
+pub(super) fn dif(env: &Environment, x: &Term, y: &Term) -> Vec<Branch> {
+    let goal = Atom::new("dif", vec![x.clone(), y.clone()]);
+
+    match entailment(env, x, y) {
+        Some(true) => vec![],
+        Some(false) => vec![(env.clone(), vec![])],
+        None => {
+            let mut vars = Vec::new();
+            term_vars(&env.substitute_term(x), &mut vars);
+            term_vars(&env.substitute_term(y), &mut vars);
+
+            let mut env = env.clone();
+
+            for var in vars {
+                suspend(&mut env, var, "dif", &goal);
+            }
+
+            vec![(env, vec![])]
+        }
+    }
+}
+
+pub(super) fn freeze(env: &Environment, x: &Term, goal: &Term) -> Vec<Branch> {
+    match env.substitute_term(x) {
+        Term::Var(var) => {
+            let mut env = env.clone();
+            suspend(&mut env, var, "freeze", &callable(goal));
+
+            vec![(env, vec![])]
+        }
+        _ => vec![(env.clone(), vec![callable(goal)])],
+    }
+}
+
+pub(super) fn when(env: &Environment, condition: &Term, goal: &Term) -> Vec<Branch> {
+    let done = Term::Var(Var::new("_Done", fresh()));
+    let when = Atom::new("$when", vec![done, condition.clone(), goal.clone()]);
+
+    resume_when(env, &when)
+}
+
+pub(super) fn resume_when(env: &Environment, when: &Atom) -> Vec<Branch> {
+    let (done, condition, goal) = (&when.args[0], &when.args[1], &when.args[2]);
+
+    if let Term::Atom(_) = env.substitute_term(done) {
+        return vec![(env.clone(), vec![])];
+    }
+
+    let mut triggers = Vec::new();
+
+    match condition_holds(env, condition, &mut triggers) {
+        None => vec![],
+        Some(true) => match env.clone().unify_terms(done, &atom("true")) {
+            Ok(env) => vec![(env, vec![callable(goal)])],
+            Err(_) => vec![],
+        },
+        Some(false) => {
+            let mut env = env.clone();
+
+            for var in triggers {
+                suspend(&mut env, var, "when", when);
+            }
+
+            vec![(env, vec![])]
+        }
+    }
+}
+
+/// Decides a `when/2` condition, collecting the variables whose binding could change the outcome
+/// while it does not hold yet. Returns None for a malformed condition.
+fn condition_holds(env: &Environment, condition: &Term, triggers: &mut Vec<Var>) -> Option<bool> {
+    let (name, args) = match env.substitute_term(condition) {
+        Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) => (name, args),
+        _ => return None,
+    };
+
+    let mut vars = Vec::new();
+
+    match (&name[..], &args[..]) {
+        ("nonvar", [x]) => match x {
+            Term::Var(x) => triggers.push(x.clone()),
+            _ => return Some(true),
+        },
+        ("ground", [x]) => {
+            term_vars(x, &mut vars);
+
+            match vars.into_iter().next() {
+                Some(x) => triggers.push(x),
+                None => return Some(true),
+            }
+        }
+        ("?=", [x, y]) => match entailment(env, x, y) {
+            Some(_) => return Some(true),
+            None => {
+                term_vars(x, triggers);
+                term_vars(y, triggers);
+            }
+        },
+        (",", [a, b]) => {
+            return Some(condition_holds(env, a, triggers)? && condition_holds(env, b, triggers)?)
+        }
+        (";", [a, b]) => {
+            let mut either = Vec::new();
+
+            if condition_holds(env, a, &mut either)? || condition_holds(env, b, &mut either)? {
+                return Some(true);
+            }
+
+            triggers.extend(either);
+        }
+        _ => return None,
+    }
+
+    Some(false)
+}
+
+fn callable(goal: &Term) -> Atom {
+    match goal {
+        Term::Atom(goal) => goal.clone(),
+        goal => Atom::new("call", vec![goal.clone()]),
+    }
+}
+
+/// Returns whether `x` and `y` are already equal, can never become equal, or neither yet.
+fn entailment(env: &Environment, x: &Term, y: &Term) -> Option<bool> {
+    match env.clone().unify_terms(x, y) {
+        Err(_) => Some(false),
+        Ok(unified) if unified.bindings().len() == env.bindings().len() => Some(true),
+        Ok(_) => None,
+    }
+}
+
+fn suspend(env: &mut Environment, var: Var, module: &str, goal: &Atom) {
+    let goal = Term::Atom(goal.clone());
+    let mut goals = match env.get_attr(&var, module) {
+        Some(goals) => list_items(goals).unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    if !goals.contains(&goal) {
+        goals.push(goal);
+    }
+
+    env.put_attr(var, module, Term::list(goals, Term::nil()));
+}
+
+pub(super) fn wakeup(env: &Environment, module: &Term, value: &Term, other: &Term) -> Vec<Branch> {
+    let module = match module {
+        Term::Atom(Atom {
+            name: Const(module),
+            ..
+        }) => module,
+        _ => return vec![],
+    };
+
+    let goals = list_items(value).unwrap_or_default();
+
+    match (&module[..], env.substitute_term(other)) {
+        ("freeze", Term::Var(other)) => {
+            let mut env = env.clone();
+
+            for goal in goals {
+                suspend(&mut env, other.clone(), "freeze", &callable(&goal));
+            }
+
+            vec![(env, vec![])]
+        }
+        ("clpfd", _) => super::clpfd::wakeup(env, value, other),
+        ("clpqr", _) => super::clpqr::wakeup(env, value),
+        ("dif", _) | ("freeze", _) | ("when", _) => {
+            vec![(env.clone(), goals.iter().map(callable).collect())]
+        }
+        (_, other) => {
+            let hook = Atom::new("attr_unify_hook", vec![value.clone(), other]);
+            let goal = Atom::new(":", vec![atom(module), Term::Atom(hook)]);
+
+            vec![(env.clone(), vec![goal])]
+        }
+    }
+}
+
+pub(super) fn put_attr(env: &Environment, x: &Term, module: &Term, value: &Term) -> Vec<Branch> {
+    match (env.substitute_term(x), text(&env.substitute_term(module))) {
+        (Term::Var(x), Some(module)) => {
+            let mut env = env.clone();
+            env.put_attr(x, &module, env.substitute_term(value));
+
+            vec![(env, vec![])]
+        }
+        _ => vec![],
+    }
+}
+
+pub(super) fn get_attr(env: &Environment, x: &Term, module: &Term, value: &Term) -> Vec<Branch> {
+    match (env.substitute_term(x), text(&env.substitute_term(module))) {
+        (Term::Var(x), Some(module)) => match env.get_attr(&x, &module) {
+            Some(attribute) => unify(env, &attribute.clone(), value),
+            None => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+pub(super) fn del_attr(env: &Environment, x: &Term, module: &Term) -> Vec<Branch> {
+    match (env.substitute_term(x), text(&env.substitute_term(module))) {
+        (Term::Var(x), Some(module)) => {
+            let mut env = env.clone();
+            env.del_attr(&x, &module);
+
+            vec![(env, vec![])]
+        }
+        (_, Some(_)) => vec![(env.clone(), vec![])],
+        _ => vec![],
+    }
+}
+
+/// Describes the attributes of `var` as goals that would restore them, with constraints projected
+/// onto the variables in `shown`. Attributes of other modules are written as `put_attr/3` goals.
+fn attribute_goals(env: &Environment, var: &Var, shown: &[Var]) -> Vec<Term> {
+    let mut goals = Vec::new();
+    let attribute = |module| list_items(env.get_attr(var, module)?);
+
+    for goal in attribute("freeze").unwrap_or_default() {
+        goals.push(Term::Atom(Atom::new(
+            "freeze",
+            vec![Term::Var(var.clone()), env.substitute_term(&goal)],
+        )));
+    }
+
+    for goal in attribute("when").unwrap_or_default() {
+        if let Term::Atom(Atom { args, .. }) = env.substitute_term(&goal) {
+            if let Term::Var(_) = args[0] {
+                goals.push(Term::Atom(Atom::new("when", args[1..].to_vec())));
+            }
+        }
+    }
+
+    goals.extend(super::clpfd::residual_goals(env, var));
+    goals.extend(super::clpqr::residual_goals(env, var, shown));
+
+    for goal in attribute("dif").unwrap_or_default() {
+        if let Term::Atom(Atom { ref args, .. }) = env.substitute_term(&goal) {
+            if entailment(env, &args[0], &args[1]).is_none() {
+                goals.push(env.substitute_term(&goal));
+            }
+        }
+    }
+
+    for (module, value) in env.attributes(var).into_iter().flatten() {
+        if !BUILTIN_MODULES.contains(&&module[..]) {
+            goals.push(Term::Atom(Atom::new(
+                "put_attr",
+                vec![
+                    Term::Var(var.clone()),
+                    atom(module),
+                    env.substitute_term(value),
+                ],
+            )));
+        }
+    }
+
+    goals
+}
+
+fn unique(goals: Vec<Term>) -> Vec<Term> {
+    let mut unique = Vec::with_capacity(goals.len());
+
+    for goal in goals {
+        if !unique.contains(&goal) {
+            unique.push(goal);
+        }
+    }
+
+    unique
+}
+
+/// Collects the goals still suspended on unbound variables, as they should be shown alongside the
+/// bindings of an answer.
+pub(crate) fn residual_goals(env: &Environment) -> Vec<Term> {
+    let mut shown: Vec<Var> = env
+        .attributed_vars()
+        .into_iter()
+        .filter(|x| x.1 == 0)
+        .cloned()
+        .collect();
+
+    for (x, t) in env.bindings() {
+        if x.1 == 0 {
+            free_vars(env, t, &mut shown);
+        }
+    }
+
+    let residual = env
+        .attributed_vars()
+        .into_iter()
+        .flat_map(|var| attribute_goals(env, var, &shown))
+        .collect();
+
+    unique(residual)
+}
+
+/// Copies `t` with fresh variables that carry no attributes, and unifies `goals` with the copied
+/// goals that would give them back the attributes of the originals.
+pub(super) fn copy_term_goals(
+    env: &Environment,
+    t: &Term,
+    copy: &Term,
+    goals: &Term,
+) -> Vec<Branch> {
+    let t = env.substitute_term(t);
+    let mut vars = Vec::new();
+    term_vars(&t, &mut vars);
+
+    let residual = vars
+        .iter()
+        .flat_map(|var| attribute_goals(env, var, &vars))
+        .collect();
+
+    let pair = Term::Atom(Atom::new(
+        "-",
+        vec![t, Term::list(unique(residual), Term::nil())],
+    ));
+    let target = Term::Atom(Atom::new("-", vec![copy.clone(), goals.clone()]));
+
+    unify(env, &target, &rename_fresh(&pair))
+}
@@ -0,0 +1,82 @@
+use super::arith::eval;
+use super::{atom, text, unify, Branch};
+use crate::ast::{Number, Term};
+use crate::Environment;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+#[derive(Default)]
+struct Counters {
+    gensym: HashMap<String, u64>,
+    flags: HashMap<String, Term>,
+}
+
+thread_local! {
+    static COUNTERS: RefCell<Counters> = RefCell::new(Counters::default());
+}
+
+/// `gensym(Base, Unique)` makes the next atom of the sequence `Base1`, `Base2`, ...
+pub(super) fn gensym(env: &Environment, base: &Term, unique: &Term) -> Vec<Branch> {
+    let base = match text(&env.substitute_term(base)) {
+        Some(base) => base,
+        None => return vec![],
+    };
+
+    let n = COUNTERS.with(|counters| {
+        let mut counters = counters.borrow_mut();
+        let n = counters.gensym.entry(base.clone()).or_insert(0);
+        *n += 1;
+        *n
+    });
+
+    unify(env, unique, &atom(&format!("{}{}", base, n)))
+}
+
+/// Restarts the sequence of `base`, or of every base when there is none.
+pub(super) fn reset_gensym(env: &Environment, base: Option<&Term>) -> Vec<Branch> {
+    let base = match base.map(|base| text(&env.substitute_term(base))) {
+        Some(None) => return vec![],
+        Some(Some(base)) => Some(base),
+        None => None,
+    };
+
+    COUNTERS.with(|counters| {
+        let mut counters = counters.borrow_mut();
+
+        match base {
+            Some(base) => {
+                counters.gensym.remove(&base);
+            }
+            None => counters.gensym.clear(),
+        }
+    });
+
+    vec![(env.clone(), vec![])]
+}
+
+/// `flag(Key, Old, New)` unifies `Old` with the value of the counter `Key`, which starts at 0,
+/// and then sets it to the value of the arithmetic expression `New`.
+pub(super) fn flag(env: &Environment, key: &Term, old: &Term, new: &Term) -> Vec<Branch> {
+    let key = match env.substitute_term(key) {
+        Term::Var(_) => return vec![],
+        key => key.to_string(),
+    };
+
+    let value = COUNTERS.with(|counters| {
+        counters
+            .borrow()
+            .flags
+            .get(&key)
+            .cloned()
+            .unwrap_or(Term::Number(Number::Int(0)))
+    });
+
+    unify(env, old, &value)
+        .into_iter()
+        .filter_map(|(env, goals)| {
+            let value = Term::Number(eval(&env, new).ok()?);
+            COUNTERS.with(|counters| counters.borrow_mut().flags.insert(key.clone(), value));
+            Some((env, goals))
+        })
+        .collect()
+}
@@ -0,0 +1,73 @@
+use super::codecs::{byte_list, hex_encode};
+use super::{atom, list_items, text, unify, Branch};
+use crate::ast::{Atom, Const, Term};
+use crate::Environment;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
+
+const DEFAULT_ALGORITHM: &str = "sha256";
+
+pub(super) fn crypto_data_hash(
+    env: &Environment,
+    data: &Term,
+    hash: &Term,
+    options: &Term,
+) -> Vec<Branch> {
+    let data = env.substitute_term(data);
+    let bytes = match byte_list(&data) {
+        Some(bytes) => bytes,
+        None => match text(&data) {
+            Some(text) => text.into_bytes(),
+            None => return vec![],
+        },
+    };
+
+    let options = match list_items(&env.substitute_term(options)) {
+        Some(options) => options,
+        None => return vec![],
+    };
+
+    let mut env = env.clone();
+    let mut algorithm = String::from(DEFAULT_ALGORITHM);
+
+    for option in options {
+        match option {
+            Term::Atom(Atom {
+                name: Const(ref name),
+                ref args,
+                ..
+            }) if name == "algorithm" && args.len() == 1 => match &args[0] {
+                Term::Var(_) => match env.unify_terms(&args[0], &atom(DEFAULT_ALGORITHM)) {
+                    Ok(unified) => env = unified,
+                    Err(_) => return vec![],
+                },
+                t => match text(t) {
+                    Some(name) => algorithm = name,
+                    None => return vec![],
+                },
+            },
+            _ => (),
+        }
+    }
+
+    match digest(&algorithm, &bytes) {
+        Some(digest) => unify(&env, hash, &atom(&hex_encode(&digest))),
+        None => vec![],
+    }
+}
+
+fn digest(algorithm: &str, bytes: &[u8]) -> Option<Vec<u8>> {
+    let digest = match algorithm {
+        "md5" => Md5::digest(bytes).to_vec(),
+        "sha1" => Sha1::digest(bytes).to_vec(),
+        "sha224" => Sha224::digest(bytes).to_vec(),
+        "sha256" => Sha256::digest(bytes).to_vec(),
+        "sha384" => Sha384::digest(bytes).to_vec(),
+        "sha512" => Sha512::digest(bytes).to_vec(),
+        "crc32" => crc32fast::hash(bytes).to_be_bytes().to_vec(),
+        _ => return None,
+    };
+
+    Some(digest)
+}
@@ -0,0 +1,382 @@
+use super::files::io_error;
+use super::streams::stream;
+use super::{
+    atom, domain_error, existence_error, instantiation_error, list_items, text, throw, unify,
+    Branch,
+};
+use crate::ast::{Atom, Number, Term};
+use crate::Environment;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+
+struct Options {
+    separator: u8,
+    quote: u8,
+    strip: bool,
+    convert: bool,
+    functor: String,
+    arity: Option<usize>,
+}
+
+fn code(t: &Term) -> Option<u8> {
+    match t {
+        Term::Number(Number::Int(c)) => u8::try_from(*c).ok(),
+        t => {
+            let s = text(t)?;
+            match s.as_bytes() {
+                [c] => Some(*c),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// The options `separator(Char)`, `quote(Char)`, `strip(Bool)`, `convert(Bool)`,
+/// `functor(Name)` and `arity(N)`, where characters are given as codes or one-character atoms.
+fn options(env: &Environment, t: &Term) -> Option<Options> {
+    let mut options = Options {
+        separator: b',',
+        quote: b'"',
+        strip: false,
+        convert: true,
+        functor: String::from("row"),
+        arity: None,
+    };
+
+    for option in list_items(&env.substitute_term(t))? {
+        let (name, value) = match &option {
+            Term::Atom(a) if a.arity == 1 => (&a.name.0[..], &a.args[0]),
+            _ => return None,
+        };
+
+        match name {
+            "separator" => options.separator = code(value)?,
+            "quote" => options.quote = code(value)?,
+            "strip" => options.strip = text(value)? == "true",
+            "convert" => options.convert = text(value)? == "true",
+            "functor" => options.functor = text(value)?,
+            "arity" => match value {
+                Term::Number(Number::Int(n)) if *n >= 0 => options.arity = Some(*n as usize),
+                _ => return None,
+            },
+            _ => (),
+        }
+    }
+
+    Some(options)
+}
+
+/// Reads the records of a CSV source one at a time, counting the bytes it takes up.
+struct Records<'a> {
+    input: &'a mut dyn BufRead,
+    read: u64,
+}
+
+impl<'a> Records<'a> {
+    fn peek(&mut self) -> Option<u8> {
+        self.input.fill_buf().ok()?.first().copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.input.consume(1);
+        self.read += 1;
+        Some(b)
+    }
+
+    /// The fields of the next record, with whether each was quoted, or None at the end.
+    fn next(&mut self, options: &Options) -> Option<Vec<(String, bool)>> {
+        self.peek()?;
+
+        let mut fields = Vec::new();
+        let mut field = Vec::new();
+        let mut quoted = false;
+
+        loop {
+            match self.bump() {
+                None | Some(b'\n') => break,
+                Some(b'\r') if self.peek() == Some(b'\n') => {
+                    self.bump();
+                    break;
+                }
+                Some(b) if b == options.separator => {
+                    fields.push((String::from_utf8_lossy(&field).into_owned(), quoted));
+                    field.clear();
+                    quoted = false;
+                }
+                Some(b) if b == options.quote && field.iter().all(u8::is_ascii_whitespace) => {
+                    field.clear();
+                    quoted = true;
+
+                    // A doubled quote stands for one quote inside a quoted field.
+                    while let Some(b) = self.bump() {
+                        if b != options.quote {
+                            field.push(b);
+                        } else if self.peek() == Some(options.quote) {
+                            field.push(self.bump()?);
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                Some(b) => field.push(b),
+            }
+        }
+
+        fields.push((String::from_utf8_lossy(&field).into_owned(), quoted));
+        Some(fields)
+    }
+}
+
+fn field(value: &str, quoted: bool, options: &Options) -> Term {
+    let value = if options.strip && !quoted {
+        value.trim()
+    } else {
+        value
+    };
+
+    if options.convert && !quoted {
+        if let Ok(i) = value.parse() {
+            return Term::Number(Number::Int(i));
+        }
+
+        if let Ok(f) = value.parse::<f64>() {
+            if value.contains(|c: char| c.is_ascii_digit()) {
+                return Term::Number(Number::Float(f));
+            }
+        }
+    }
+
+    atom(value)
+}
+
+/// The row term for a record, or the error raised for one that does not have the wanted arity.
+fn row(fields: Vec<(String, bool)>, options: &Options) -> Result<Term, Term> {
+    if let Some(arity) = options.arity {
+        if fields.len() != arity {
+            let domain = Term::Atom(Atom::new(
+                "row_arity",
+                vec![Term::Number(Number::Int(arity as i64))],
+            ));
+            let culprit = Term::Number(Number::Int(fields.len() as i64));
+            return Err(Term::Atom(Atom::new("domain_error", vec![domain, culprit])));
+        }
+    }
+
+    let args = fields
+        .iter()
+        .map(|(value, quoted)| field(value, *quoted, options))
+        .collect();
+
+    Ok(Term::Atom(Atom::new(&options.functor, args)))
+}
+
+fn file_name(env: &Environment, t: &Term) -> Result<String, Term> {
+    text(&env.substitute_term(t)).ok_or_else(instantiation_error)
+}
+
+fn options_error(env: &Environment, options: &Term) -> Vec<Branch> {
+    let culprit = env.substitute_term(options);
+    throw(env, domain_error("csv_options", culprit))
+}
+
+/// `csv_read_file(File, Rows, Options)` reads every record of `File` into a list of rows.
+pub(super) fn csv_read_file(
+    env: &Environment,
+    file: &Term,
+    rows: &Term,
+    opts: &Term,
+) -> Vec<Branch> {
+    let name = match file_name(env, file) {
+        Ok(name) => name,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let options = match options(env, opts) {
+        Some(options) => options,
+        None => return options_error(env, opts),
+    };
+
+    let mut input = match File::open(&name) {
+        Ok(f) => BufReader::new(f),
+        Err(e) => return throw(env, io_error("open", "source_sink", &name, &e)),
+    };
+
+    let mut records = Records {
+        input: &mut input,
+        read: 0,
+    };
+    let mut items = Vec::new();
+
+    while let Some(fields) = records.next(&options) {
+        match row(fields, &options) {
+            Ok(row) => items.push(row),
+            Err(formal) => return throw(env, formal),
+        }
+    }
+
+    unify(env, rows, &Term::list(items, Term::nil()))
+}
+
+/// `csv_read_file_row(File, Row, Options)` enumerates the rows of `File` on backtracking,
+/// reading one record at a time from `offset` onwards.
+pub(super) fn csv_read_file_row(
+    env: &Environment,
+    file: &Term,
+    offset: u64,
+    t: &Term,
+    opts: &Term,
+) -> Vec<Branch> {
+    let name = match file_name(env, file) {
+        Ok(name) => name,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let options = match options(env, opts) {
+        Some(options) => options,
+        None => return options_error(env, opts),
+    };
+
+    let mut input = match File::open(&name) {
+        Ok(mut f) => match f.seek(SeekFrom::Start(offset)) {
+            Ok(_) => BufReader::new(f),
+            Err(_) => return vec![],
+        },
+        Err(e) => return throw(env, io_error("open", "source_sink", &name, &e)),
+    };
+
+    let mut records = Records {
+        input: &mut input,
+        read: 0,
+    };
+
+    let fields = match records.next(&options) {
+        Some(fields) => fields,
+        None => return vec![],
+    };
+
+    let next = Term::Number(Number::Int((offset + records.read) as i64));
+    let rest = Atom::new(
+        "$csv_read_file_row",
+        vec![atom(&name), next, t.clone(), opts.clone()],
+    );
+
+    match row(fields, &options) {
+        Ok(row) => {
+            let mut branches = unify(env, t, &row);
+            branches.push((env.clone(), vec![rest]));
+            branches
+        }
+        Err(formal) => throw(env, formal),
+    }
+}
+
+/// `csv_read_row(Stream, Row, Options)` reads the next record of `Stream`, or `end_of_file`.
+pub(super) fn csv_read_row(env: &Environment, s: &Term, t: &Term, opts: &Term) -> Vec<Branch> {
+    let options = match options(env, opts) {
+        Some(options) => options,
+        None => return options_error(env, opts),
+    };
+
+    let culprit = env.substitute_term(s);
+
+    let read = stream(&culprit).and_then(|s| {
+        s.read(|input| {
+            Records { input, read: 0 }
+                .next(&options)
+                .map(|fields| row(fields, &options))
+        })
+    });
+
+    match read {
+        Some(Some(Ok(row))) => unify(env, t, &row),
+        Some(Some(Err(formal))) => throw(env, formal),
+        Some(None) => unify(env, t, &atom("end_of_file")),
+        None => throw(env, existence_error("stream", culprit)),
+    }
+}
+
+/// The text of `rows` as CSV, quoting the fields that need it. Returns None if a row is not a
+/// compound term of text and numbers.
+fn csv_text(rows: &Term, options: &Options) -> Option<String> {
+    let quote = options.quote as char;
+    let separator = options.separator as char;
+    let mut out = String::new();
+
+    for r in list_items(rows)? {
+        let fields = match r {
+            Term::Atom(a) => a.args,
+            _ => return None,
+        };
+
+        for (i, f) in fields.iter().enumerate() {
+            if i > 0 {
+                out.push(separator);
+            }
+
+            let value = text(f)?;
+            let needs_quotes = value.contains([separator, quote, '\n', '\r'])
+                || value.starts_with(' ')
+                || value.ends_with(' ');
+
+            if needs_quotes {
+                out.push(quote);
+                out.push_str(&value.replace(quote, &format!("{}{}", quote, quote)));
+                out.push(quote);
+            } else {
+                out.push_str(&value);
+            }
+        }
+
+        out.push('\n');
+    }
+
+    Some(out)
+}
+
+/// `csv_write_file(File, Rows, Options)` writes `Rows` to `File`, one record per row.
+pub(super) fn csv_write_file(
+    env: &Environment,
+    file: &Term,
+    rows: &Term,
+    opts: &Term,
+) -> Vec<Branch> {
+    let name = match file_name(env, file) {
+        Ok(name) => name,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let options = match options(env, opts) {
+        Some(options) => options,
+        None => return options_error(env, opts),
+    };
+
+    let csv = match csv_text(&env.substitute_term(rows), &options) {
+        Some(csv) => csv,
+        None => return throw(env, instantiation_error()),
+    };
+
+    match File::create(&name).and_then(|mut f| f.write_all(csv.as_bytes())) {
+        Ok(()) => vec![(env.clone(), vec![])],
+        Err(e) => throw(env, io_error("open", "source_sink", &name, &e)),
+    }
+}
+
+/// `csv_write_stream(Stream, Rows, Options)` writes `Rows` to `Stream`.
+pub(super) fn csv_write_stream(
+    env: &Environment,
+    s: &Term,
+    rows: &Term,
+    opts: &Term,
+) -> Vec<Branch> {
+    let options = match options(env, opts) {
+        Some(options) => options,
+        None => return options_error(env, opts),
+    };
+
+    match csv_text(&env.substitute_term(rows), &options) {
+        Some(csv) => super::format::emit(env, Some(s), &csv),
+        None => throw(env, instantiation_error()),
+    }
+}
@@ -0,0 +1,305 @@
+use super::{
+    atom, existence_error, instantiation_error, list_items, throw, type_error, unify, Branch,
+};
+use crate::ast::{Assertion, Atom, Number, Term};
+use crate::{rename_fresh, Choicepoint, Environment, SolveErr};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+/// How far an engine has got with its goal.
+enum State {
+    Created,
+    /// Between answers, with the choicepoints to look for the next one from.
+    Suspended(Vec<Choicepoint>),
+    /// Looking for an answer, in a call to `engine_next/2` that has not returned yet.
+    Running,
+    Done,
+}
+
+struct Engine {
+    alias: Option<String>,
+    template: Term,
+    goal: Atom,
+    state: State,
+    /// Where renaming apart had got to when the engine last ran, which it goes on from in whatever
+    /// query asks it for its next answer.
+    fresh: usize,
+}
+
+#[derive(Default)]
+struct Engines {
+    engines: HashMap<usize, Engine>,
+    next: usize,
+}
+
+thread_local! {
+    static ENGINES: RefCell<Engines> = RefCell::new(Engines::default());
+    /// How many engines are running on this thread, one inside the other.
+    static RUNNING: Cell<usize> = const { Cell::new(0) };
+    /// The term passed to `engine_yield/1` by the innermost running engine.
+    static YIELDED: RefCell<Option<Term>> = const { RefCell::new(None) };
+}
+
+/// Whether a goal is running inside an engine, where `engine_yield/1` can suspend it.
+pub(crate) fn running() -> bool {
+    RUNNING.with(Cell::get) > 0
+}
+
+/// Makes the innermost running engine answer `t` to the `engine_next/2` that runs it.
+pub(crate) fn yield_term(t: Term) {
+    YIELDED.with(|yielded| *yielded.borrow_mut() = Some(t));
+}
+
+fn handle_term(id: usize, alias: Option<&str>) -> Term {
+    match alias {
+        Some(alias) => atom(alias),
+        None => Term::Atom(Atom::new(
+            "$engine",
+            vec![Term::Number(Number::Int(id as i64))],
+        )),
+    }
+}
+
+/// The engine named by `t`, or the error raised for a term that names none.
+fn engine_id(env: &Environment, t: &Term) -> Result<usize, Term> {
+    let t = env.substitute_term(t);
+
+    let id = ENGINES.with(|engines| {
+        let engines = engines.borrow();
+
+        match &t {
+            Term::Var(_) => Err(instantiation_error()),
+            Term::Atom(a) if a.name.0 == "$engine" && a.arity == 1 => match a.args[0] {
+                Term::Number(Number::Int(n)) if n >= 0 => Ok(Some(n as usize)),
+                _ => Ok(None),
+            },
+            Term::Atom(a) if a.arity == 0 => Ok(engines
+                .engines
+                .iter()
+                .find(|(_, engine)| engine.alias.as_deref() == Some(&a.name.0[..]))
+                .map(|(&id, _)| id)),
+            _ => Ok(None),
+        }
+        .map(|id| id.filter(|id| engines.engines.contains_key(id)))
+    })?;
+
+    id.ok_or_else(|| existence_error("engine", t))
+}
+
+/// `engine_create(Template, Goal, Engine, Options)` makes an engine that answers, each time
+/// `engine_next/2` asks, with `Template` as bound by the next solution of a copy of `Goal`.
+/// The option `alias(Name)` names the engine.
+pub(super) fn engine_create(
+    env: &Environment,
+    template: &Term,
+    goal: &Term,
+    engine: &Term,
+    options: &Term,
+) -> Vec<Branch> {
+    // Copied together, so that the template shares the variables of the goal.
+    let pair = rename_fresh(&env.substitute_term(&Term::Atom(Atom::new(
+        "-",
+        vec![template.clone(), goal.clone()],
+    ))));
+
+    let (template, goal) = match pair {
+        Term::Atom(mut a) => {
+            let goal = a.args.pop().unwrap();
+            (a.args.pop().unwrap(), goal)
+        }
+        _ => unreachable!(),
+    };
+
+    let goal = match goal {
+        Term::Atom(goal) => goal,
+        Term::Var(_) => return throw(env, instantiation_error()),
+        t => {
+            let error = type_error("callable", t);
+            return throw(env, error);
+        }
+    };
+
+    let alias = list_items(&env.substitute_term(options))
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|option| match option {
+            Term::Atom(a) if a.name.0 == "alias" && a.arity == 1 => match &a.args[0] {
+                Term::Atom(name) if name.arity == 0 => Some(name.name.0.clone()),
+                _ => None,
+            },
+            _ => None,
+        });
+
+    let created = ENGINES.with(|engines| {
+        let mut engines = engines.borrow_mut();
+
+        if let Some(alias) = &alias {
+            if engines
+                .engines
+                .values()
+                .any(|engine| engine.alias.as_ref() == Some(alias))
+            {
+                return None;
+            }
+        }
+
+        let id = engines.next;
+        engines.next += 1;
+        engines.engines.insert(
+            id,
+            Engine {
+                alias: alias.clone(),
+                template,
+                goal: Atom::new("call", vec![Term::Atom(goal)]),
+                state: State::Created,
+                fresh: 0,
+            },
+        );
+
+        Some(id)
+    });
+
+    match created {
+        Some(id) => unify(env, engine, &handle_term(id, alias.as_deref())),
+        None => {
+            let culprit = Term::Atom(Atom::new("alias", vec![atom(&alias.unwrap_or_default())]));
+            let error = Term::Atom(Atom::new(
+                "permission_error",
+                vec![atom("create"), atom("engine"), culprit],
+            ));
+            throw(env, error)
+        }
+    }
+}
+
+/// `engine_next(Engine, Term)` unifies `Term` with the next answer of `Engine`: the template as
+/// bound by the next solution of its goal, or the term the goal passes to `engine_yield/1`. It
+/// fails once the goal has no more solutions, and raises what the goal raises.
+pub(crate) fn engine_next(
+    env: &Environment,
+    kb: &[Assertion],
+    engine: &Term,
+    answer: &Term,
+) -> Vec<Branch> {
+    let id = match engine_id(env, engine) {
+        Ok(id) => id,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let started = ENGINES.with(|engines| {
+        let mut engines = engines.borrow_mut();
+        let engine = engines.engines.get_mut(&id)?;
+        let state = std::mem::replace(&mut engine.state, State::Running);
+        Some((
+            state,
+            engine.goal.clone(),
+            engine.template.clone(),
+            engine.fresh,
+        ))
+    });
+
+    let (state, goal, template, fresh) = match started {
+        Some(started) => started,
+        None => return vec![],
+    };
+
+    crate::resume_fresh(fresh);
+
+    let result = match state {
+        State::Created => run(|| Environment::new().solve(Vec::new(), kb, None, vec![goal], 1)),
+        State::Suspended(mut ch) => match ch.pop() {
+            Some(Choicepoint {
+                assertions,
+                environment,
+                clause,
+                depth,
+                ..
+            }) => run(|| environment.solve(ch, kb, assertions, clause, depth)),
+            None => Err(SolveErr::NoSolution),
+        },
+        State::Running => {
+            let error = Term::Atom(Atom::new(
+                "permission_error",
+                vec![atom("resume"), atom("engine"), env.substitute_term(engine)],
+            ));
+            set_state(id, State::Running);
+            return throw(env, error);
+        }
+        State::Done => Err(SolveErr::NoSolution),
+    };
+
+    let yielded = YIELDED.with(|yielded| yielded.borrow_mut().take());
+    let fresh = crate::fresh();
+
+    ENGINES.with(|engines| {
+        if let Some(engine) = engines.borrow_mut().engines.get_mut(&id) {
+            engine.fresh = fresh;
+        }
+    });
+
+    match result {
+        Ok((solution, ch)) => {
+            let next = match yielded {
+                Some(t) => t,
+                None => solution.substitute_term(&template),
+            };
+
+            set_state(
+                id,
+                if ch.is_empty() {
+                    State::Done
+                } else {
+                    State::Suspended(ch)
+                },
+            );
+
+            unify(env, answer, &rename_fresh(&next))
+        }
+        Err(SolveErr::NoSolution) => {
+            set_state(id, State::Done);
+            vec![]
+        }
+        Err(SolveErr::Exception(ball)) => {
+            set_state(id, State::Done);
+            vec![(env.clone(), vec![Atom::new("throw", vec![ball])])]
+        }
+    }
+}
+
+/// Looks for the next answer of an engine with `search`.
+fn run(
+    search: impl FnOnce() -> Result<(Environment, Vec<Choicepoint>), SolveErr>,
+) -> Result<(Environment, Vec<Choicepoint>), SolveErr> {
+    RUNNING.with(|running| running.set(running.get() + 1));
+    let result = search();
+    RUNNING.with(|running| running.set(running.get() - 1));
+    result
+}
+
+fn set_state(id: usize, state: State) {
+    ENGINES.with(|engines| {
+        if let Some(engine) = engines.borrow_mut().engines.get_mut(&id) {
+            engine.state = state;
+        }
+    });
+}
+
+/// `engine_destroy(Engine)` discards `Engine` and the solutions it has not given yet.
+pub(super) fn engine_destroy(env: &Environment, engine: &Term) -> Vec<Branch> {
+    match engine_id(env, engine) {
+        Ok(id) => {
+            ENGINES.with(|engines| engines.borrow_mut().engines.remove(&id));
+            vec![(env.clone(), vec![])]
+        }
+        Err(formal) => throw(env, formal),
+    }
+}
+
+/// `engine_yield(Term)` outside of an engine, where there is nothing to yield to.
+pub(super) fn engine_yield(env: &Environment) -> Vec<Branch> {
+    let error = Term::Atom(Atom::new(
+        "permission_error",
+        vec![atom("yield"), atom("engine"), atom("none")],
+    ));
+    throw(env, error)
+}
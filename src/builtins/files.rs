@@ -0,0 +1,278 @@
+use super::{atom, existence_error, instantiation_error, list_items, text, throw, unify, Branch};
+use crate::ast::{Assertion, Atom, Number, Term};
+use crate::{saved, Environment};
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+/// The file name `t` stands for, which is text or `/`-separated segments like `src/main`.
+fn file_name(t: &Term) -> Option<String> {
+    match t {
+        Term::Atom(a) if a.name.0 == "/" && a.arity == 2 => Some(format!(
+            "{}/{}",
+            file_name(&a.args[0])?,
+            file_name(&a.args[1])?
+        )),
+        t => text(t),
+    }
+}
+
+/// The ISO error for an I/O operation `action` on the `kind` named `name` that failed.
+pub(crate) fn io_error(action: &str, kind: &str, name: &str, error: &std::io::Error) -> Term {
+    match error.kind() {
+        ErrorKind::NotFound => existence_error(kind, atom(name)),
+        _ => Term::Atom(Atom::new(
+            "permission_error",
+            vec![atom(action), atom(kind), atom(name)],
+        )),
+    }
+}
+
+fn path_test(env: &Environment, t: &Term, test: fn(&Path) -> bool) -> Vec<Branch> {
+    match file_name(&env.substitute_term(t)) {
+        Some(name) if test(Path::new(&name)) => vec![(env.clone(), vec![])],
+        Some(_) => vec![],
+        None => throw(env, instantiation_error()),
+    }
+}
+
+pub(super) fn exists_file(env: &Environment, t: &Term) -> Vec<Branch> {
+    path_test(env, t, Path::is_file)
+}
+
+pub(super) fn exists_directory(env: &Environment, t: &Term) -> Vec<Branch> {
+    path_test(env, t, Path::is_dir)
+}
+
+/// Runs the file operation `op` on the file named by `t`, raising an error if it fails.
+fn file_op(
+    env: &Environment,
+    t: &Term,
+    action: &str,
+    kind: &str,
+    op: fn(&str) -> std::io::Result<()>,
+) -> Vec<Branch> {
+    let name = match file_name(&env.substitute_term(t)) {
+        Some(name) => name,
+        None => return throw(env, instantiation_error()),
+    };
+
+    match op(&name) {
+        Ok(()) => vec![(env.clone(), vec![])],
+        Err(e) => throw(env, io_error(action, kind, &name, &e)),
+    }
+}
+
+pub(super) fn delete_file(env: &Environment, t: &Term) -> Vec<Branch> {
+    file_op(env, t, "delete", "file", |name| std::fs::remove_file(name))
+}
+
+pub(super) fn make_directory(env: &Environment, t: &Term) -> Vec<Branch> {
+    file_op(env, t, "create", "directory", |name| {
+        std::fs::create_dir(name)
+    })
+}
+
+pub(super) fn delete_directory(env: &Environment, t: &Term) -> Vec<Branch> {
+    file_op(env, t, "delete", "directory", |name| {
+        std::fs::remove_dir(name)
+    })
+}
+
+/// Unifies `entries` with the names in the directory `dir`, including `.` and `..`, in sorted
+/// order.
+pub(super) fn directory_files(env: &Environment, dir: &Term, entries: &Term) -> Vec<Branch> {
+    let name = match file_name(&env.substitute_term(dir)) {
+        Some(name) => name,
+        None => return throw(env, instantiation_error()),
+    };
+
+    let read = std::fs::read_dir(&name).and_then(|dir| {
+        dir.map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<std::io::Result<Vec<_>>>()
+    });
+
+    match read {
+        Ok(mut names) => {
+            names.extend([String::from("."), String::from("..")]);
+            names.sort();
+
+            let names = names.iter().map(|name| atom(name)).collect();
+            unify(env, entries, &Term::list(names, Term::nil()))
+        }
+        Err(e) => throw(env, io_error("open", "directory", &name, &e)),
+    }
+}
+
+pub(super) fn size_file(env: &Environment, t: &Term, size: &Term) -> Vec<Branch> {
+    let name = match file_name(&env.substitute_term(t)) {
+        Some(name) => name,
+        None => return throw(env, instantiation_error()),
+    };
+
+    match std::fs::metadata(&name) {
+        Ok(metadata) => unify(env, size, &Term::Number(Number::Int(metadata.len() as i64))),
+        Err(e) => throw(env, io_error("access", "file", &name, &e)),
+    }
+}
+
+/// `qsave_program(File, Options)` saves the program `kb` to `File` as an image, or as an
+/// executable that runs it with `stand_alone(true)`. `goal(Goal)` makes `Goal` its entry point,
+/// as `initialization(Goal, main)` would.
+pub(crate) fn qsave_program(
+    env: &Environment,
+    kb: &[Assertion],
+    file: &Term,
+    opts: Option<&Term>,
+) -> Vec<Branch> {
+    let name = match file_name(&env.substitute_term(file)) {
+        Some(name) => name,
+        None => return throw(env, instantiation_error()),
+    };
+
+    let options = match opts.map(|opts| list_items(&env.substitute_term(opts))) {
+        Some(Some(options)) => options,
+        Some(None) => return throw(env, instantiation_error()),
+        None => vec![],
+    };
+
+    let mut program = kb.to_vec();
+    let mut stand_alone = false;
+
+    for option in options {
+        match option {
+            Term::Atom(a) if a.name.0 == "stand_alone" && a.arity == 1 => {
+                stand_alone = text(&a.args[0]).as_deref() == Some("true");
+            }
+            Term::Atom(a) if a.name.0 == "goal" && a.arity == 1 => {
+                // Written ahead of the program, so it is the entry point found first.
+                let init = Atom::new("initialization", vec![a.args[0].clone(), atom("main")]);
+                let directive = Atom::new(":-", vec![Term::Atom(init)]);
+                program.push(Assertion::new(directive, vec![]));
+            }
+            _ => (),
+        }
+    }
+
+    match saved::save(&program, Path::new(&name), stand_alone) {
+        Ok(()) => vec![(env.clone(), vec![])],
+        Err(e) => throw(env, io_error("open", "source_sink", &name, &e)),
+    }
+}
+
+/// Makes `path` absolute against `dir`, resolving `.` and `..` without touching the file system.
+fn absolute(path: &Path, dir: &Path) -> PathBuf {
+    let mut absolute = PathBuf::new();
+
+    for component in dir.join(path).components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            component => absolute.push(component),
+        }
+    }
+
+    absolute
+}
+
+#[derive(Default)]
+struct Options {
+    extensions: Vec<String>,
+    access: Option<String>,
+    file_type: Option<String>,
+    relative_to: Option<PathBuf>,
+    fail_silently: bool,
+}
+
+fn options(t: &Term) -> Option<Options> {
+    let mut options = Options::default();
+
+    for option in list_items(t)? {
+        let (name, value) = match &option {
+            Term::Atom(a) if a.arity == 1 => (&a.name.0[..], &a.args[0]),
+            _ => return None,
+        };
+
+        match name {
+            "extensions" => {
+                options.extensions = list_items(value)?.iter().map(text).collect::<Option<_>>()?;
+            }
+            "access" => options.access = text(value),
+            "file_type" => options.file_type = text(value),
+            "relative_to" => options.relative_to = file_name(value).map(PathBuf::from),
+            "file_errors" => options.fail_silently = text(value).as_deref() == Some("fail"),
+            _ => (),
+        }
+    }
+
+    Some(options)
+}
+
+/// `absolute_file_name(Spec, Absolute, Options)` finds the first absolute path for `Spec` that
+/// has one of the wanted extensions and meets the wanted file type and access.
+pub(super) fn absolute_file_name(
+    env: &Environment,
+    spec: &Term,
+    absolute_name: &Term,
+    opts: Option<&Term>,
+) -> Vec<Branch> {
+    let name = match file_name(&env.substitute_term(spec)) {
+        Some(name) => name,
+        None => return throw(env, instantiation_error()),
+    };
+
+    let options = match opts.map(|opts| options(&env.substitute_term(opts))) {
+        Some(Some(options)) => options,
+        Some(None) => return throw(env, instantiation_error()),
+        None => Options::default(),
+    };
+
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let dir = match options.relative_to {
+        Some(ref dir) => absolute(dir, &cwd),
+        None => cwd,
+    };
+
+    let mut extensions = match options.file_type.as_deref() {
+        Some("prolog") | Some("source") => vec![String::from("pl")],
+        Some("txt") => vec![String::from("txt")],
+        _ => vec![],
+    };
+    extensions.extend(options.extensions.iter().cloned());
+    extensions.push(String::new());
+
+    let fits = |path: &&PathBuf| match options.file_type.as_deref() {
+        Some("directory") => path.is_dir(),
+        Some("regular") | Some("prolog") | Some("source") | Some("txt") => path.is_file(),
+        _ => path.exists(),
+    };
+    let must_exist = !matches!(options.access.as_deref(), None | Some("none"));
+
+    let candidates: Vec<_> = extensions
+        .iter()
+        .map(|ext| {
+            let ext = ext.trim_start_matches('.');
+
+            if ext.is_empty() {
+                absolute(Path::new(&name), &dir)
+            } else {
+                absolute(Path::new(&format!("{}.{}", name, ext)), &dir)
+            }
+        })
+        .collect();
+
+    let found = candidates
+        .iter()
+        .find(fits)
+        .or_else(|| candidates.first().filter(|_| !must_exist));
+
+    match found {
+        Some(path) => unify(env, absolute_name, &atom(&path.to_string_lossy())),
+        None if options.fail_silently => vec![],
+        None => {
+            let error = existence_error("source_sink", atom(&name));
+            throw(env, error)
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use super::streams::{stream, Stream};
+use super::{atom, chars, codes, domain_error, list_items, string, text, throw, unify, Branch};
+use crate::ast::{Atom, Const, Number, Term, Unquoted};
+use crate::Environment;
+use std::convert::TryFrom;
+
+struct Output {
+    text: String,
+    indent: usize,
+    line_start: usize,
+    segment_start: usize,
+    fills: Vec<(usize, char)>,
+}
+
+impl Output {
+    fn new(indent: usize) -> Self {
+        Output {
+            text: String::new(),
+            indent,
+            line_start: 0,
+            segment_start: 0,
+            fills: Vec::new(),
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.text.push_str(s);
+
+        if let Some(i) = self.text.rfind('\n') {
+            if i >= self.line_start {
+                self.line_start = i + 1;
+                self.indent = 0;
+                self.segment_start = i + 1;
+                self.fills.clear();
+            }
+        }
+    }
+
+    fn column(&self, i: usize) -> usize {
+        self.indent + self.text[self.line_start..i].chars().count()
+    }
+
+    fn column_stop(&mut self, target: usize) {
+        let pad = target.saturating_sub(self.column(self.text.len()));
+
+        if pad > 0 {
+            if self.fills.is_empty() {
+                self.fills.push((self.text.len(), ' '));
+            }
+
+            let share = pad / self.fills.len();
+            let extra = pad % self.fills.len();
+            let last = self.fills.len() - 1;
+
+            for (i, (pos, c)) in self.fills.drain(..).enumerate().rev() {
+                let n = if i == last { share + extra } else { share };
+                self.text.insert_str(pos, &c.to_string().repeat(n));
+            }
+        }
+
+        self.segment_start = self.text.len();
+        self.fills.clear();
+    }
+}
+
+pub(super) fn format(
+    env: &Environment,
+    sink: Option<&Term>,
+    fmt: &Term,
+    args: &Term,
+) -> Vec<Branch> {
+    let fmt = match text(&env.substitute_term(fmt)) {
+        Some(fmt) => fmt,
+        None => return vec![],
+    };
+
+    let args = env.substitute_term(args);
+    let args = list_items(&args).unwrap_or_else(|| vec![args]);
+
+    // Column stops count from where the text starts on the line it is written to.
+    let column = match sink.map(|sink| env.substitute_term(sink)) {
+        None => Stream::UserOutput.column(),
+        Some(sink) => stream(&sink).map_or(0, Stream::column),
+    };
+
+    let output = match format_text(&fmt, args, column) {
+        Some(Ok(output)) => output,
+        Some(Err(formal)) => return throw(env, formal),
+        None => return vec![],
+    };
+
+    emit(env, sink, &output)
+}
+
+/// Writes `output` to `sink` the way `format/3` does, where no sink is standard output.
+pub(super) fn emit(env: &Environment, sink: Option<&Term>, output: &str) -> Vec<Branch> {
+    let sink = sink.map(|sink| env.substitute_term(sink));
+
+    match sink {
+        None => {
+            Stream::UserOutput.write(output);
+            vec![(env.clone(), vec![])]
+        }
+        Some(Term::Atom(Atom {
+            name: Const(ref kind),
+            ref args,
+            ..
+        })) if args.len() == 1 && kind != "$stream" => {
+            let made = match &kind[..] {
+                "atom" => atom(output),
+                "string" => string(output),
+                "codes" => codes(output),
+                "chars" => chars(output),
+                _ => return vec![],
+            };
+
+            unify(env, &args[0], &made)
+        }
+        Some(ref sink) => match stream(sink) {
+            Some(stream) if stream.write(output) => vec![(env.clone(), vec![])],
+            _ => vec![],
+        },
+    }
+}
+
+/// The largest count or column a directive may be given, past which it would only exhaust memory.
+const MAX_COUNT: usize = 1 << 20;
+
+/// Formats `args` as `fmt` says, or gives None if they do not fit it, or the error to raise for a
+/// count that is out of range.
+fn format_text(fmt: &str, args: Vec<Term>, column: usize) -> Option<Result<String, Term>> {
+    let mut out = Output::new(column);
+    let mut args = args.into_iter();
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            out.push_str(&c.to_string());
+            continue;
+        }
+
+        let mut numeric = None;
+
+        match chars.peek() {
+            Some('*') => {
+                chars.next();
+
+                numeric = match count(integer(&args.next()?)?) {
+                    Ok(n) => Some(n),
+                    Err(formal) => return Some(Err(formal)),
+                };
+            }
+            Some('`') => {
+                chars.next();
+                numeric = Some(chars.next()? as usize);
+            }
+            Some(d) if d.is_ascii_digit() => {
+                let mut n: i64 = 0;
+
+                while let Some(d) = chars.peek().and_then(|d| d.to_digit(10)) {
+                    n = n.saturating_mul(10).saturating_add(d as i64);
+                    chars.next();
+                }
+
+                numeric = match count(n) {
+                    Ok(n) => Some(n),
+                    Err(formal) => return Some(Err(formal)),
+                };
+            }
+            _ => (),
+        }
+
+        match chars.next()? {
+            'w' => out.push_str(&Unquoted(&args.next()?).to_string()),
+            'p' | 'q' => out.push_str(&args.next()?.to_string()),
+            'a' => out.push_str(&text(&args.next()?)?),
+            's' => out.push_str(&text(&args.next()?)?),
+            'd' => out.push_str(&format_integer(integer(&args.next()?)?, numeric, false)),
+            'D' => out.push_str(&format_integer(integer(&args.next()?)?, numeric, true)),
+            'f' => out.push_str(&format!(
+                "{:.*}",
+                numeric.unwrap_or(6),
+                float(&args.next()?)?
+            )),
+            'e' => out.push_str(&format_exponent(
+                float(&args.next()?)?,
+                numeric.unwrap_or(6),
+            )),
+            'g' => out.push_str(&format_general(float(&args.next()?)?, numeric.unwrap_or(6))),
+            'r' => out.push_str(&format_radix(integer(&args.next()?)?, numeric?, false)?),
+            'R' => out.push_str(&format_radix(integer(&args.next()?)?, numeric?, true)?),
+            'c' => {
+                let c = std::char::from_u32(integer(&args.next()?)? as u32)?;
+                out.push_str(&c.to_string().repeat(numeric.unwrap_or(1)));
+            }
+            'n' => out.push_str(&"\n".repeat(numeric.unwrap_or(1))),
+            '~' => out.push_str("~"),
+            'i' => {
+                args.next()?;
+            }
+            't' => {
+                let fill = numeric.map_or(Some(' '), |c| std::char::from_u32(c as u32))?;
+                out.fills.push((out.text.len(), fill));
+            }
+            '|' => {
+                let target = numeric.unwrap_or_else(|| out.column(out.text.len()));
+                out.column_stop(target);
+            }
+            '+' => {
+                let target = out.column(out.segment_start) + numeric.unwrap_or(8);
+                out.column_stop(target);
+            }
+            _ => return None,
+        }
+    }
+
+    if args.next().is_some() {
+        return None;
+    }
+
+    Some(Ok(out.text))
+}
+
+/// The numeric argument `n` of a directive as a count.
+fn count(n: i64) -> Result<usize, Term> {
+    match usize::try_from(n) {
+        Err(_) => Err(domain_error(
+            "not_less_than_zero",
+            Term::Number(Number::Int(n)),
+        )),
+        Ok(n) if n > MAX_COUNT => Err(Term::Atom(Atom::new(
+            "resource_error",
+            vec![atom("memory")],
+        ))),
+        Ok(n) => Ok(n),
+    }
+}
+
+fn integer(t: &Term) -> Option<i64> {
+    match t {
+        Term::Number(Number::Int(i)) => Some(*i),
+        _ => None,
+    }
+}
+
+fn float(t: &Term) -> Option<f64> {
+    match t {
+        Term::Number(Number::Int(i)) => Some(*i as f64),
+        Term::Number(Number::Float(f)) => Some(*f),
+        _ => None,
+    }
+}
+
+fn format_integer(i: i64, decimals: Option<usize>, group: bool) -> String {
+    let digits = i.unsigned_abs().to_string();
+    let decimals = decimals.unwrap_or(0);
+    let digits = format!("{:0>width$}", digits, width = decimals + 1);
+    let (int_part, frac_part) = digits.split_at(digits.len() - decimals);
+
+    let mut int_part = String::from(int_part);
+
+    if group {
+        let grouped: Vec<String> = int_part
+            .as_bytes()
+            .rchunks(3)
+            .rev()
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+            .collect();
+
+        int_part = grouped.join(",");
+    }
+
+    let sign = if i < 0 { "-" } else { "" };
+
+    if decimals > 0 {
+        format!("{}{}.{}", sign, int_part, frac_part)
+    } else {
+        format!("{}{}", sign, int_part)
+    }
+}
+
+fn format_exponent(x: f64, precision: usize) -> String {
+    // Infinities and NaN have no exponent to write.
+    if !x.is_finite() {
+        return format!("{}", x);
+    }
+
+    let s = format!("{:.*e}", precision, x);
+    let (mantissa, exponent) = s.split_at(s.find('e').unwrap());
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let sign = if exponent < 0 { '-' } else { '+' };
+
+    format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+}
+
+fn format_general(x: f64, precision: usize) -> String {
+    if !x.is_finite() {
+        return format!("{}", x);
+    }
+
+    let precision = precision.max(1);
+    let s = format!("{:.*e}", precision - 1, x);
+    let exponent: i32 = s[s.find('e').unwrap() + 1..].parse().unwrap();
+
+    let s = if exponent >= -4 && exponent < precision as i32 {
+        format!("{:.*}", (precision as i32 - 1 - exponent) as usize, x)
+    } else {
+        format_exponent(x, precision - 1)
+    };
+
+    let (mantissa, exponent) = s.split_at(s.find('e').unwrap_or(s.len()));
+
+    let mantissa = if mantissa.contains('.') {
+        mantissa.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        mantissa
+    };
+
+    format!("{}{}", mantissa, exponent)
+}
+
+fn format_radix(i: i64, radix: usize, upper: bool) -> Option<String> {
+    if !(2..=36).contains(&radix) {
+        return None;
+    }
+
+    let mut n = i.unsigned_abs();
+    let mut digits = Vec::new();
+
+    loop {
+        let d = std::char::from_digit((n % radix as u64) as u32, radix as u32)?;
+        digits.push(if upper { d.to_ascii_uppercase() } else { d });
+        n /= radix as u64;
+
+        if n == 0 {
+            break;
+        }
+    }
+
+    if i < 0 {
+        digits.push('-');
+    }
+
+    Some(digits.into_iter().rev().collect())
+}
@@ -0,0 +1,517 @@
+use super::locals::{self, Database};
+#[cfg(feature = "tls")]
+use super::tls::TlsStream;
+use super::{
+    atom, existence_error, instantiation_error, list_items, string, text, throw, type_error, Branch,
+};
+use crate::ast::{Assertion, Atom, Number, Term, Var};
+use crate::{Environment, KnowledgeBase};
+#[cfg(feature = "tls")]
+use rustls::ServerConfig;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+struct Server {
+    /// Where a connection reaches the server, to wake its acceptor once it is stopped.
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    acceptor: JoinHandle<()>,
+}
+
+/// The servers that are running, by port, which any thread may stop.
+static SERVERS: Mutex<BTreeMap<u16, Server>> = Mutex::new(BTreeMap::new());
+
+/// A request as read off a connection, before it is made into a term.
+struct Request {
+    method: String,
+    target: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+/// The longest body a request may have.
+const MAX_BODY: usize = 1 << 20;
+/// The most header lines a request may have.
+const MAX_HEADERS: usize = 100;
+/// The longest the request line or a header line may be.
+const MAX_LINE: u64 = 8 << 10;
+/// How long a connection may take to send its request or read the reply.
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// The most threads a server may run handlers on.
+const MAX_WORKERS: i64 = 256;
+
+/// Reads a line of the head of a request into `line`, giving back the status to reply with if
+/// it cannot be read, or `too_long` if it is longer than `MAX_LINE`.
+fn read_line(reader: &mut impl BufRead, line: &mut String, too_long: i64) -> Result<usize, i64> {
+    line.clear();
+
+    match reader.by_ref().take(MAX_LINE).read_line(line) {
+        Ok(read) if read as u64 == MAX_LINE && !line.ends_with('\n') => Err(too_long),
+        Ok(read) => Ok(read),
+        Err(_) => Err(400),
+    }
+}
+
+/// Reads a request, or gives back the status to reply with if it is malformed or larger than
+/// the limits above allow.
+fn read_request(reader: &mut impl BufRead) -> Result<Request, i64> {
+    let mut line = String::new();
+    read_line(reader, &mut line, 414)?;
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or(400)?.to_lowercase();
+    let target = parts.next().ok_or(400)?.to_string();
+    let version = parts.next().unwrap_or("HTTP/1.0").to_string();
+    let mut headers = Vec::new();
+
+    while read_line(reader, &mut line, 431)? > 0 {
+        let header = line.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if headers.len() == MAX_HEADERS {
+            return Err(431);
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let length = match headers.iter().find(|(name, _)| name == "content-length") {
+        Some((_, value)) => value.parse().map_err(|_| 400)?,
+        None => 0,
+    };
+
+    if length > MAX_BODY {
+        return Err(413);
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|_| 400)?;
+
+    Ok(Request {
+        method,
+        target,
+        version,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+fn pair(name: &str, value: &str) -> Term {
+    Term::Atom(Atom::new("=", vec![atom(name), atom(value)]))
+}
+
+/// Decodes a name or value of a query string, which is form data: each `+` is a space and each
+/// `%XX` the byte it gives in hex. A `%` that is not followed by two hex digits is kept as it is.
+fn decode(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 2;
+            }
+            (byte, _) => decoded.push(byte),
+        }
+
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The request as the list `[method(M), path(P), search(Query), http_version(V),
+/// headers(Headers), body(Body)]` that handlers are called with.
+fn request_term(request: &Request) -> Term {
+    let (path, query) = match request.target.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (&request.target[..], ""),
+    };
+
+    let search = query
+        .split('&')
+        .filter(|field| !field.is_empty())
+        .map(|field| match field.split_once('=') {
+            Some((name, value)) => pair(&decode(name), &decode(value)),
+            None => pair(&decode(field), ""),
+        })
+        .collect();
+
+    let headers = request
+        .headers
+        .iter()
+        .map(|(name, value)| pair(name, value))
+        .collect();
+
+    let property = |name: &str, value: Term| Term::Atom(Atom::new(name, vec![value]));
+
+    Term::list(
+        vec![
+            property("method", atom(&request.method)),
+            property("path", atom(path)),
+            property("search", Term::list(search, Term::nil())),
+            property("http_version", atom(&request.version)),
+            property("headers", Term::list(headers, Term::nil())),
+            property("body", string(&request.body)),
+        ],
+        Term::nil(),
+    )
+}
+
+/// A reply as it is written back to the client.
+struct Reply {
+    status: i64,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Reply {
+    fn plain(status: i64, body: &str) -> Reply {
+        Reply {
+            status,
+            headers: vec![],
+            body: String::from(body),
+        }
+    }
+}
+
+fn reason(status: i64) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        414 => "URI Too Long",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
+
+/// The status, headers and body of a reply term `reply(Status, Body)` or
+/// `reply(Status, Headers, Body)`, where `Headers` is a list of `Name = Value`.
+fn reply(t: &Term) -> Option<Reply> {
+    let args = match t {
+        Term::Atom(a) if a.name.0 == "reply" && (a.arity == 2 || a.arity == 3) => &a.args,
+        _ => return None,
+    };
+
+    let status = match args[0] {
+        Term::Number(Number::Int(status)) => status,
+        _ => return None,
+    };
+
+    let mut headers = Vec::new();
+
+    if args.len() == 3 {
+        for header in list_items(&args[1])? {
+            match header {
+                Term::Atom(a) if a.name.0 == "=" && a.arity == 2 => {
+                    headers.push((text(&a.args[0])?, text(&a.args[1])?));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    Some(Reply {
+        status,
+        headers,
+        body: text(args.last()?)?,
+    })
+}
+
+/// Runs `handler` on `request`, answering 404 if it fails and 500 if it raises an exception or
+/// replies with something that is not a reply term.
+fn respond(kb: &[Assertion], handler: &Term, request: &Request) -> Reply {
+    let answer = Term::Var(Var(String::from("Reply"), 0));
+    let goal = Atom::new(
+        "call",
+        vec![handler.clone(), request_term(request), answer.clone()],
+    );
+
+    match Environment::new().solve(Vec::new(), kb, None, vec![goal], 1) {
+        Ok((env, _)) => reply(&env.substitute_term(&answer))
+            .unwrap_or_else(|| Reply::plain(500, "Invalid reply")),
+        Err(crate::SolveErr::NoSolution) => Reply::plain(404, "Not Found"),
+        Err(crate::SolveErr::Exception(ball)) => Reply::plain(500, &ball.to_string()),
+    }
+}
+
+fn serve(kb: &[Assertion], handler: &Term, stream: &mut (impl Read + Write)) {
+    let request = read_request(&mut BufReader::new(&mut *stream));
+
+    let Reply {
+        status,
+        headers,
+        body,
+    } = match request {
+        Ok(request) => respond(kb, handler, &request),
+        Err(status) => Reply::plain(status, reason(status)),
+    };
+
+    let mut response = format!("HTTP/1.1 {} {}\r\n", status, reason(status));
+
+    if !headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+    {
+        response.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+    }
+
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+
+    response.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    ));
+
+    // The client may have hung up without waiting for the reply.
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// What a server needs to answer a connection.
+struct Site {
+    kb: KnowledgeBase,
+    database: Arc<Mutex<Database>>,
+    handler: Term,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<ServerConfig>>,
+}
+
+impl Site {
+    fn answer(&self, mut stream: TcpStream) {
+        // A client that stops sending or reading only holds up its worker for so long.
+        if stream.set_read_timeout(Some(TIMEOUT)).is_err()
+            || stream.set_write_timeout(Some(TIMEOUT)).is_err()
+        {
+            return;
+        }
+
+        #[cfg(feature = "tls")]
+        {
+            if let Some(config) = &self.tls {
+                if let Ok(mut stream) = super::tls::accept(config, stream) {
+                    serve(&self.kb, &self.handler, &mut stream);
+                    stream.close();
+                }
+                return;
+            }
+        }
+
+        serve(&self.kb, &self.handler, &mut stream);
+    }
+}
+
+fn worker(site: Arc<Site>, connections: Arc<Mutex<Receiver<TcpStream>>>) {
+    crate::reset(&site.kb);
+    locals::share(site.database.clone());
+
+    loop {
+        let next = connections.lock().map(|connections| connections.recv());
+
+        match next {
+            Ok(Ok(stream)) => site.answer(stream),
+            _ => break,
+        }
+    }
+}
+
+/// `http_server(Handler, Options)` starts serving HTTP in the background, answering each
+/// request with the reply of `call(Handler, Request, Reply)`. The options are `port(Port)`,
+/// where an unbound port is bound to one chosen by the system, `ip(Address)`, the address to
+/// listen on, which is `'127.0.0.1'` unless given, so that `ip('0.0.0.0')` is needed to listen on
+/// every interface, `workers(N)`, the number of threads from 1 to `MAX_WORKERS` that run handlers,
+/// and `ssl([certificate_file(File), key_file(File)])`, which serves HTTPS with the given
+/// certificate and key. The names and values of `search(Query)` are decoded from the query string
+/// as form data. A request whose line, headers or body are larger than the limits above is
+/// answered with 414, 431 or 413 without calling the handler.
+pub(crate) fn http_server(
+    env: &Environment,
+    kb: &[Assertion],
+    handler: &Term,
+    options: &Term,
+) -> Vec<Branch> {
+    let handler = env.substitute_term(handler);
+
+    if let Term::Var(_) = handler {
+        return throw(env, instantiation_error());
+    }
+
+    let mut port = None;
+    let mut ip = String::from("127.0.0.1");
+    let mut workers = 4;
+    #[cfg(feature = "tls")]
+    let mut tls = None;
+
+    for option in list_items(&env.substitute_term(options)).unwrap_or_default() {
+        match &option {
+            Term::Atom(a) if a.name.0 == "port" && a.arity == 1 => port = Some(a.args[0].clone()),
+            Term::Atom(a) if a.name.0 == "ip" && a.arity == 1 => match text(&a.args[0]) {
+                Some(address) => ip = address,
+                None => return throw(env, super::domain_error("http_option", option.clone())),
+            },
+            Term::Atom(a) if a.name.0 == "workers" && a.arity == 1 => match a.args[0] {
+                Term::Number(Number::Int(n)) if (1..=MAX_WORKERS).contains(&n) => {
+                    workers = n as usize
+                }
+                _ => return throw(env, super::domain_error("http_option", option.clone())),
+            },
+            #[cfg(feature = "tls")]
+            Term::Atom(a) if a.name.0 == "ssl" && a.arity == 1 => {
+                let options = list_items(&a.args[0]).unwrap_or_default();
+
+                match super::tls::server_config(&options) {
+                    Ok(config) => tls = Some(config),
+                    Err(formal) => return throw(env, formal),
+                }
+            }
+            #[cfg(not(feature = "tls"))]
+            Term::Atom(a) if a.name.0 == "ssl" && a.arity == 1 => {
+                let error = super::domain_error("http_option", option.clone());
+                return throw(env, error);
+            }
+            _ => (),
+        }
+    }
+
+    let requested = match port {
+        Some(Term::Number(Number::Int(port))) => port as u16,
+        _ => 0,
+    };
+
+    let listener = match TcpListener::bind((&ip[..], requested)) {
+        Ok(listener) => listener,
+        Err(_) => {
+            let culprit = Term::Number(Number::Int(i64::from(requested)));
+            let error = Term::Atom(Atom::new(
+                "permission_error",
+                vec![atom("bind"), atom("port"), culprit],
+            ));
+            return throw(env, error);
+        }
+    };
+
+    let mut address = match listener.local_addr() {
+        Ok(address) => address,
+        Err(_) => SocketAddr::from(([127, 0, 0, 1], requested)),
+    };
+    let bound = address.port();
+
+    // A server listening on every interface is reached on the loopback one.
+    if address.ip().is_unspecified() {
+        address.set_ip(IpAddr::from([127, 0, 0, 1]));
+    }
+
+    let env = match port {
+        Some(port) => match env
+            .clone()
+            .unify_terms(&port, &Term::Number(Number::Int(i64::from(bound))))
+        {
+            Ok(env) => env,
+            Err(_) => return vec![],
+        },
+        None => env.clone(),
+    };
+
+    let site = Arc::new(Site {
+        kb: kb.to_vec(),
+        database: locals::database(),
+        handler,
+        #[cfg(feature = "tls")]
+        tls,
+    });
+    let (sender, receiver) = channel();
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    for _ in 0..workers {
+        let (site, receiver) = (site.clone(), receiver.clone());
+        std::thread::spawn(move || worker(site, receiver));
+    }
+
+    let stopped = Arc::new(AtomicBool::new(false));
+    let stop = stopped.clone();
+
+    let acceptor = std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+
+            if let Ok(stream) = stream {
+                if sender.send(stream).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let server = Server {
+        address,
+        stopped,
+        acceptor,
+    };
+    servers().insert(bound, server);
+
+    vec![(env, vec![])]
+}
+
+fn servers() -> MutexGuard<'static, BTreeMap<u16, Server>> {
+    SERVERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `http_stop_server(Port, Options)` stops the server started on `Port`, by any thread, once the
+/// requests it is handling are answered.
+pub(super) fn http_stop_server(env: &Environment, port: &Term) -> Vec<Branch> {
+    let port = match env.substitute_term(port) {
+        Term::Number(Number::Int(port)) => port as u16,
+        Term::Var(_) => return throw(env, instantiation_error()),
+        t => return throw(env, type_error("integer", t)),
+    };
+
+    let server = servers().remove(&port);
+
+    match server {
+        Some(server) => {
+            server.stopped.store(true, Ordering::SeqCst);
+            // Wakes the acceptor so that it sees it has been stopped.
+            let _ = TcpStream::connect(server.address);
+            let _ = server.acceptor.join();
+
+            vec![(env.clone(), vec![])]
+        }
+        None => {
+            let culprit = Term::Number(Number::Int(i64::from(port)));
+            throw(env, existence_error("http_server", culprit))
+        }
+    }
+}
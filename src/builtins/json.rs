@@ -0,0 +1,410 @@
+use super::format::emit;
+use super::streams::stream;
+use super::{atom, existence_error, list_items, string, text, throw, type_error, unify, Branch};
+use crate::ast::{Atom, Const, Number, Term};
+use crate::Environment;
+use std::io::BufRead;
+
+/// How JSON values are represented as terms, which YAML and TOML documents are read as too.
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum Style {
+    Classic,
+    Dict,
+}
+
+impl Style {
+    /// The style asked for by the option `value_string_as(string)` of `options`.
+    pub(super) fn of(env: &Environment, options: &Term) -> Style {
+        let options = list_items(&env.substitute_term(options)).unwrap_or_default();
+        let dict = options.iter().any(|option| match option {
+            Term::Atom(a) if a.name.0 == "value_string_as" && a.arity == 1 => {
+                text(&a.args[0]).as_deref() == Some("string")
+            }
+            _ => false,
+        });
+
+        style(dict)
+    }
+
+    pub(super) fn constant(self, name: &str) -> Term {
+        match self {
+            Style::Classic => Term::Atom(Atom::new("@", vec![atom(name)])),
+            Style::Dict => atom(name),
+        }
+    }
+
+    pub(super) fn string(self, s: &str) -> Term {
+        match self {
+            Style::Classic => atom(s),
+            Style::Dict => string(s),
+        }
+    }
+}
+
+/// The object with the keys and values of `pairs`.
+pub(super) fn object(pairs: Vec<(String, Term)>) -> Term {
+    let pairs = pairs
+        .into_iter()
+        .map(|(key, value)| Term::Atom(Atom::new("=", vec![atom(&key), value])))
+        .collect();
+
+    Term::Atom(Atom::new("json", vec![Term::list(pairs, Term::nil())]))
+}
+
+struct Parser<'a> {
+    input: &'a mut dyn BufRead,
+    style: Style,
+}
+
+type Parsed<T> = Result<T, &'static str>;
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<u8> {
+        self.input.fill_buf().ok()?.first().copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.input.consume(1);
+        Some(b)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.input.consume(1);
+        }
+    }
+
+    fn expect(&mut self, word: &str) -> Parsed<()> {
+        for b in word.bytes() {
+            if self.bump() != Some(b) {
+                return Err("illegal_json");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn value(&mut self) -> Parsed<Term> {
+        self.skip_whitespace();
+
+        match self.peek().ok_or("unexpected_end_of_input")? {
+            b'{' => self.object(),
+            b'[' => self.array(),
+            b'"' => Ok(self.style.string(&self.string()?)),
+            b't' => self.expect("true").map(|_| self.style.constant("true")),
+            b'f' => self.expect("false").map(|_| self.style.constant("false")),
+            b'n' => self.expect("null").map(|_| self.style.constant("null")),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => Err("illegal_json"),
+        }
+    }
+
+    fn object(&mut self) -> Parsed<Term> {
+        self.bump();
+        let mut pairs = Vec::new();
+
+        self.skip_whitespace();
+
+        if self.peek() == Some(b'}') {
+            self.bump();
+        } else {
+            loop {
+                self.skip_whitespace();
+
+                if self.peek() != Some(b'"') {
+                    return Err("illegal_json");
+                }
+
+                let key = self.string()?;
+                self.skip_whitespace();
+                self.expect(":")?;
+                let value = self.value()?;
+
+                pairs.push((key, value));
+
+                self.skip_whitespace();
+
+                match self.bump() {
+                    Some(b',') => continue,
+                    Some(b'}') => break,
+                    _ => return Err("illegal_json"),
+                }
+            }
+        }
+
+        Ok(object(pairs))
+    }
+
+    fn array(&mut self) -> Parsed<Term> {
+        self.bump();
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+
+        if self.peek() == Some(b']') {
+            self.bump();
+        } else {
+            loop {
+                items.push(self.value()?);
+                self.skip_whitespace();
+
+                match self.bump() {
+                    Some(b',') => continue,
+                    Some(b']') => break,
+                    _ => return Err("illegal_json"),
+                }
+            }
+        }
+
+        Ok(Term::list(items, Term::nil()))
+    }
+
+    fn hex(&mut self) -> Parsed<u32> {
+        let mut code = 0;
+
+        for _ in 0..4 {
+            let digit = self
+                .bump()
+                .and_then(|b| (b as char).to_digit(16))
+                .ok_or("illegal_json")?;
+            code = code * 16 + digit;
+        }
+
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Parsed<String> {
+        self.bump();
+        let mut bytes = Vec::new();
+
+        loop {
+            match self.bump().ok_or("unexpected_end_of_input")? {
+                b'"' => break,
+                b'\\' => {
+                    let c = match self.bump().ok_or("unexpected_end_of_input")? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex()?;
+
+                            // A character outside the basic plane comes as a surrogate pair.
+                            if (0xD800..0xDC00).contains(&code) {
+                                self.expect("\\u")?;
+                                let low = self.hex()?;
+                                code = 0x10000 + ((code - 0xD800) << 10) + low.wrapping_sub(0xDC00);
+                            }
+
+                            std::char::from_u32(code).ok_or("illegal_json")?
+                        }
+                        _ => return Err("illegal_json"),
+                    };
+
+                    bytes.extend(c.to_string().bytes());
+                }
+                b => bytes.push(b),
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|_| "illegal_json")
+    }
+
+    fn number(&mut self) -> Parsed<Term> {
+        let mut text = String::new();
+
+        while let Some(b) = self.peek() {
+            match b {
+                b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E' => {
+                    text.push(b as char);
+                    self.bump();
+                }
+                _ => break,
+            }
+        }
+
+        let number = if text.contains(['.', 'e', 'E']) {
+            text.parse().ok().map(Number::Float)
+        } else {
+            text.parse().ok().map(Number::Int)
+        };
+
+        number.map(Term::Number).ok_or("illegal_json")
+    }
+}
+
+fn syntax_error(reason: &str) -> Term {
+    let json = Term::Atom(Atom::new("json", vec![atom(reason)]));
+    Term::Atom(Atom::new("syntax_error", vec![json]))
+}
+
+fn style(dict: bool) -> Style {
+    if dict {
+        Style::Dict
+    } else {
+        Style::Classic
+    }
+}
+
+/// `json_read(Stream, Term)` reads the next JSON value from `Stream`. An object is read as
+/// `json([Key=Value, ...])` with atom keys, an array as a list and a number as a number. Strings
+/// are read as atoms and the constants as `@(true)`, `@(false)` and `@(null)`, unless `dict` is
+/// set, as by `json_read_dict/2`, which reads strings as strings and the constants as atoms.
+pub(super) fn json_read(env: &Environment, t: &Term, value: &Term, dict: bool) -> Vec<Branch> {
+    let culprit = env.substitute_term(t);
+
+    let s = match stream(&culprit) {
+        Some(s) => s,
+        None => {
+            let error = existence_error("stream", culprit);
+            return throw(env, error);
+        }
+    };
+
+    let read = s.read(|input| {
+        Parser {
+            input,
+            style: style(dict),
+        }
+        .value()
+    });
+
+    match read {
+        Some(Ok(json)) => unify(env, value, &json),
+        Some(Err(reason)) => throw(env, syntax_error(reason)),
+        None => {
+            let error = Term::Atom(Atom::new(
+                "permission_error",
+                vec![atom("input"), atom("stream"), culprit],
+            ));
+            throw(env, error)
+        }
+    }
+}
+
+fn quote(s: &str, out: &mut String) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+/// Writes `t` as JSON to `out`, returning None if `t` is not a JSON term.
+fn write_json(t: &Term, out: &mut String) -> Option<()> {
+    match t {
+        Term::Number(n) => out.push_str(&n.to_string()),
+        Term::String(s) => quote(s, out),
+        Term::Atom(a) if a.name.0 == "@" && a.arity == 1 => match text(&a.args[0])?.as_str() {
+            c @ "true" | c @ "false" | c @ "null" => out.push_str(c),
+            _ => return None,
+        },
+        Term::Atom(a) if a.name.0 == "json" && a.arity == 1 => {
+            out.push('{');
+
+            for (i, pair) in list_items(&a.args[0])?.iter().enumerate() {
+                let (key, value) = match pair {
+                    Term::Atom(p) if (p.name.0 == "=" || p.name.0 == "-") && p.arity == 2 => {
+                        (&p.args[0], &p.args[1])
+                    }
+                    _ => return None,
+                };
+
+                if i > 0 {
+                    out.push(',');
+                }
+
+                quote(&text(key)?, out);
+                out.push(':');
+                write_json(value, out)?;
+            }
+
+            out.push('}');
+        }
+        Term::Atom(Atom {
+            name: Const(name),
+            arity: 0,
+            ..
+        }) => match &name[..] {
+            "true" | "false" | "null" => out.push_str(name),
+            "[]" => out.push_str("[]"),
+            _ => quote(name, out),
+        },
+        t => {
+            out.push('[');
+
+            for (i, item) in list_items(t)?.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+
+                write_json(item, out)?;
+            }
+
+            out.push(']');
+        }
+    }
+
+    Some(())
+}
+
+/// `json_write(Out, Term)` writes `Term` as JSON to the stream or sink `Out`.
+pub(super) fn json_write(env: &Environment, out: &Term, t: &Term) -> Vec<Branch> {
+    let t = env.substitute_term(t);
+    let mut json = String::new();
+
+    match write_json(&t, &mut json) {
+        Some(()) => emit(env, Some(out), &json),
+        None => throw(env, type_error("json_term", t)),
+    }
+}
+
+/// `atom_json_term(Text, Term, Options)` parses the JSON in `Text`, or writes `Term` as JSON
+/// to the atom `Text` if it is unbound. The option `value_string_as(string)` reads in the
+/// dict-style representation.
+pub(super) fn atom_json_term(
+    env: &Environment,
+    json: &Term,
+    t: &Term,
+    options: &Term,
+) -> Vec<Branch> {
+    let style = Style::of(env, options);
+
+    match text(&env.substitute_term(json)) {
+        Some(source) => {
+            let mut input = source.as_bytes();
+            let mut parser = Parser {
+                input: &mut input,
+                style,
+            };
+
+            let parsed = parser.value().and_then(|value| {
+                parser.skip_whitespace();
+                parser.peek().map_or(Ok(value), |_| Err("illegal_json"))
+            });
+
+            match parsed {
+                Ok(value) => unify(env, t, &value),
+                Err(reason) => throw(env, syntax_error(reason)),
+            }
+        }
+        None => json_write(env, &Term::Atom(Atom::new("atom", vec![json.clone()])), t),
+    }
+}
@@ -0,0 +1,403 @@
+use super::{atom, instantiation_error, throw, type_error, unify, Branch};
+use crate::ast::{Assertion, Atom, Const, Number, Term};
+use crate::xref::is_builtin;
+use crate::{rename_fresh, Environment, KnowledgeBase};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type Key = (Const, usize);
+
+/// The predicates declared `:- thread_local`, with the clauses each thread has added to them, and
+/// the dynamic predicates of the program, which every thread running it shares.
+#[derive(Default)]
+struct Locals {
+    declared: HashSet<Key>,
+    clauses: HashMap<Key, KnowledgeBase>,
+    dynamic: Arc<Mutex<Database>>,
+    /// The predicates the program defines that are neither thread-local nor dynamic.
+    statics: HashSet<Key>,
+}
+
+/// The predicates declared `:- dynamic`, or with `dynamic/1`, and their clauses, which start as
+/// those the program gives them and change as they are asserted and retracted.
+#[derive(Default)]
+pub(crate) struct Database {
+    /// The declarations and clauses of the program the database was read from.
+    program: (HashSet<Key>, HashMap<Key, KnowledgeBase>),
+    declared: HashSet<Key>,
+    clauses: HashMap<Key, KnowledgeBase>,
+}
+
+/// Where the clauses of a predicate that can be changed are kept.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Store {
+    Local,
+    Dynamic,
+}
+
+thread_local! {
+    static LOCALS: RefCell<Locals> = RefCell::new(Locals::default());
+}
+
+fn declare(spec: &Term, declared: &mut HashSet<Key>) {
+    if let Term::Atom(Atom { name, args, .. }) = spec {
+        match (&name.0[..], &args[..]) {
+            (",", [x, y]) => {
+                declare(x, declared);
+                declare(y, declared);
+            }
+            ("/", [Term::Atom(name), Term::Number(Number::Int(arity))]) if *arity >= 0 => {
+                declared.insert((name.name.clone(), *arity as usize));
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Reads the `:- thread_local Name/Arity` and `:- dynamic Name/Arity` declarations of `kb`.
+/// Every thread starts each program with no clauses for the thread-local predicates. The dynamic
+/// predicates keep the clauses asserted to them for as long as the program stays the same.
+pub(crate) fn reset(kb: &[Assertion]) {
+    let mut declared = HashSet::new();
+    let mut dynamic = HashSet::new();
+
+    for a in kb {
+        if a.head.name.0 == ":-" && a.head.arity == 1 {
+            if let Term::Atom(Atom { name, args, .. }) = &a.head.args[0] {
+                match (&name.0[..], &args[..]) {
+                    ("thread_local", [spec]) => declare(spec, &mut declared),
+                    ("dynamic", [spec]) => declare(spec, &mut dynamic),
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    // Kept last clause first, as the program is.
+    let mut clauses: HashMap<Key, KnowledgeBase> = HashMap::new();
+    let mut statics = HashSet::new();
+
+    for a in kb {
+        let key = (a.head.name.clone(), a.head.arity);
+
+        if dynamic.contains(&key) {
+            clauses.entry(key).or_default().push(a.clone());
+        } else if !declared.contains(&key) {
+            statics.insert(key);
+        }
+    }
+
+    LOCALS.with(|locals| {
+        let mut locals = locals.borrow_mut();
+        let program = (dynamic, clauses);
+        let database = if lock(&locals.dynamic).program == program {
+            locals.dynamic.clone()
+        } else {
+            Arc::new(Mutex::new(Database {
+                declared: program.0.clone(),
+                clauses: program.1.clone(),
+                program,
+            }))
+        };
+
+        *locals = Locals {
+            declared,
+            dynamic: database,
+            statics,
+            ..Locals::default()
+        }
+    });
+}
+
+fn lock(database: &Mutex<Database>) -> MutexGuard<'_, Database> {
+    database.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The dynamic predicates of the program the running thread runs, for the threads it starts to
+/// share with `share`.
+pub(crate) fn database() -> Arc<Mutex<Database>> {
+    LOCALS.with(|locals| locals.borrow().dynamic.clone())
+}
+
+/// Has the running thread, which has read the same program, assert to and retract from the
+/// dynamic predicates of the thread `database` was taken on.
+pub(crate) fn share(database: Arc<Mutex<Database>>) {
+    LOCALS.with(|locals| locals.borrow_mut().dynamic = database);
+}
+
+/// `dynamic(Spec)` declares the predicates `Name/Arity` of `Spec` dynamic, with no clauses if
+/// they have none yet. Static predicates cannot be made dynamic.
+pub(super) fn dynamic(env: &Environment, spec: &Term) -> Vec<Branch> {
+    let spec = env.substitute_term(spec);
+    let mut declared = HashSet::new();
+
+    match &spec {
+        Term::Var(_) => return throw(env, instantiation_error()),
+        Term::Atom(_) => declare(&spec, &mut declared),
+        _ => (),
+    }
+
+    if declared.is_empty() {
+        return throw(env, type_error("predicate_indicator", spec));
+    }
+
+    for key in &declared {
+        if let Err(formal) = locate(key.clone()) {
+            return throw(env, formal);
+        }
+    }
+
+    vec![(env.clone(), vec![])]
+}
+
+/// Splits a clause into its head and body, with `true` as the body of a fact.
+fn split(clause: &Term) -> (Term, Term) {
+    match clause {
+        Term::Atom(a) if a.name.0 == ":-" && a.arity == 2 => (a.args[0].clone(), a.args[1].clone()),
+        head => (head.clone(), atom("true")),
+    }
+}
+
+/// Whether `body` can be the body of a clause, where a variable is called as `call/1` calls it.
+fn callable(body: &Term) -> bool {
+    match body {
+        Term::Var(_) => true,
+        Term::Atom(a) if matches!(&a.name.0[..], "," | ";" | "->") && a.arity == 2 => {
+            a.args.iter().all(callable)
+        }
+        Term::Atom(_) => true,
+        _ => false,
+    }
+}
+
+/// The term `Head :- Body` for a stored clause.
+fn clause_term(a: &Assertion) -> Term {
+    let body = a
+        .clause
+        .iter()
+        .rev()
+        .cloned()
+        .map(Term::Atom)
+        .reduce(|rest, goal| Term::Atom(Atom::new(",", vec![goal, rest])))
+        .unwrap_or_else(|| atom("true"));
+
+    Term::Atom(Atom::new(":-", vec![Term::Atom(a.head.clone()), body]))
+}
+
+/// The predicate `head` is a clause of and where its clauses are kept, or the error raised for
+/// changing a static one.
+fn key(head: &Term) -> Result<(Key, Store), Term> {
+    match head {
+        Term::Var(_) => Err(instantiation_error()),
+        Term::Atom(a) => locate((a.name.clone(), a.arity)),
+        t => Err(type_error("callable", t.clone())),
+    }
+}
+
+/// Where the clauses of the predicate `key` are kept. A predicate that is neither thread-local,
+/// dynamic, defined by the program nor built in is made dynamic.
+fn locate(key: Key) -> Result<(Key, Store), Term> {
+    let store = LOCALS.with(|locals| {
+        let locals = locals.borrow();
+
+        if locals.declared.contains(&key) {
+            Some(Store::Local)
+        } else if locals.statics.contains(&key) || is_builtin(&key.0 .0, key.1) {
+            None
+        } else {
+            lock(&locals.dynamic).declared.insert(key.clone());
+            Some(Store::Dynamic)
+        }
+    });
+
+    match store {
+        Some(store) => Ok((key, store)),
+        None => {
+            let indicator = Term::Atom(Atom::new(
+                "/",
+                vec![
+                    Term::Atom(Atom::new(&key.0 .0, vec![])),
+                    Term::Number(Number::Int(key.1 as i64)),
+                ],
+            ));
+
+            Err(Term::Atom(Atom::new(
+                "permission_error",
+                vec![atom("modify"), atom("static_procedure"), indicator],
+            )))
+        }
+    }
+}
+
+/// Runs `f` on the clauses of the predicate `key`, kept last clause first in the order of the
+/// program the solver reads, and tells the tables that read them that they changed. The clauses
+/// of a dynamic predicate are locked for as long as `f` runs, so that threads change them one at
+/// a time.
+fn change<R>(key: &Key, store: Store, f: impl FnOnce(&mut KnowledgeBase) -> R) -> R {
+    crate::tabling::changed(&key.0, key.1);
+
+    match store {
+        Store::Local => LOCALS.with(|locals| {
+            let mut locals = locals.borrow_mut();
+            f(locals.clauses.entry(key.clone()).or_default())
+        }),
+        Store::Dynamic => f(lock(&database()).clauses.entry(key.clone()).or_default()),
+    }
+}
+
+/// `asserta(Clause)` and `assertz(Clause)` add `Clause` to a thread-local or dynamic predicate,
+/// before or after the clauses it has. Static predicates cannot be changed.
+pub(super) fn assert(env: &Environment, clause: &Term, first: bool) -> Vec<Branch> {
+    // Renamed so that distinct variables keep apart when the clause is renumbered.
+    let clause = rename_fresh(&env.substitute_term(clause));
+    let (head, body) = split(&clause);
+
+    if matches!(head, Term::Atom(_)) && !callable(&body) {
+        return throw(env, type_error("callable", clause));
+    }
+
+    let (key, store) = match key(&head) {
+        Ok(key) => key,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let assertion = match Assertion::from_term(clause) {
+        Some(assertion) => assertion,
+        None => return throw(env, instantiation_error()),
+    };
+
+    change(&key, store, |clauses| {
+        if first {
+            clauses.push(assertion);
+        } else {
+            clauses.insert(0, assertion);
+        }
+    });
+
+    vec![(env.clone(), vec![])]
+}
+
+fn stored(key: &Key, store: Store) -> KnowledgeBase {
+    match store {
+        Store::Local => LOCALS.with(|locals| {
+            let locals = locals.borrow();
+            locals.clauses.get(key).cloned().unwrap_or_default()
+        }),
+        Store::Dynamic => lock(&database())
+            .clauses
+            .get(key)
+            .cloned()
+            .unwrap_or_default(),
+    }
+}
+
+/// `retract(Clause)` removes the first clause of a thread-local or dynamic predicate that unifies
+/// with `Clause`, and the next one on backtracking.
+pub(super) fn retract(env: &Environment, clause: &Term) -> Vec<Branch> {
+    let (head, body) = split(&env.substitute_term(clause));
+
+    let (key, store) = match key(&head) {
+        Ok(key) => key,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let pattern = Term::Atom(Atom::new(":-", vec![head, body]));
+
+    stored(&key, store)
+        .iter()
+        .rev()
+        .flat_map(|a| {
+            let c = clause_term(a);
+            let retract = Atom::new("$retract", vec![c.clone()]);
+
+            unify(env, &pattern, &rename_fresh(&c))
+                .into_iter()
+                .map(move |(env, _)| (env, vec![retract.clone()]))
+        })
+        .collect()
+}
+
+/// `'$retract'(Clause)` removes `Clause`, failing if another retract got to it first.
+pub(super) fn remove(env: &Environment, clause: &Term) -> Vec<Branch> {
+    let removed = match key(&split(clause).0) {
+        Ok((key, store)) => change(&key, store, |clauses| {
+            let i = clauses.iter().position(|a| clause_term(a) == *clause);
+
+            if let Some(i) = i {
+                clauses.remove(i);
+            }
+
+            i.is_some()
+        }),
+        Err(_) => false,
+    };
+
+    if removed {
+        vec![(env.clone(), vec![])]
+    } else {
+        vec![]
+    }
+}
+
+/// `retractall(Head)` removes every clause of a thread-local or dynamic predicate whose head
+/// unifies with `Head`.
+pub(super) fn retractall(env: &Environment, head: &Term) -> Vec<Branch> {
+    let head = env.substitute_term(head);
+
+    let (key, store) = match key(&head) {
+        Ok(key) => key,
+        Err(formal) => return throw(env, formal),
+    };
+
+    change(&key, store, |clauses| {
+        clauses.retain(|a| {
+            let other = rename_fresh(&Term::Atom(a.head.clone()));
+            unify(env, &head, &other).is_empty()
+        })
+    });
+
+    vec![(env.clone(), vec![])]
+}
+
+/// The clauses the running thread sees for `goal`, if it calls a thread-local or dynamic
+/// predicate, which the solver then resolves the goal against in place of the program. The
+/// clauses are those there were when the goal was called, whatever the goal asserts or retracts.
+pub(crate) fn clauses(goal: &Atom) -> Option<KnowledgeBase> {
+    LOCALS.with(|locals| {
+        let locals = locals.borrow();
+        let key = (goal.name.clone(), goal.arity);
+
+        let clauses = if locals.declared.contains(&key) {
+            locals.clauses.get(&key).cloned()
+        } else {
+            let database = lock(&locals.dynamic);
+
+            if !database.declared.contains(&key) {
+                return None;
+            }
+
+            database.clauses.get(&key).cloned()
+        };
+
+        crate::tabling::reads(&key.0, key.1);
+        Some(clauses.unwrap_or_default())
+    })
+}
+
+/// The clauses of the dynamic predicate `Name/Arity`, last clause first.
+pub(crate) fn dynamic_clauses(name: &str, arity: usize) -> KnowledgeBase {
+    stored(&(Const::new(name), arity), Store::Dynamic)
+}
+
+/// Adds `clause` after the clauses of its predicate, which is made dynamic if it is not.
+pub(crate) fn add_dynamic(clause: Assertion) {
+    let key = (clause.head.name.clone(), clause.head.arity);
+    lock(&database()).declared.insert(key.clone());
+    change(&key, Store::Dynamic, |clauses| clauses.insert(0, clause));
+}
+
+/// Removes every clause of the dynamic predicate `Name/Arity`.
+pub(crate) fn clear_dynamic(name: &str, arity: usize) {
+    change(&(Const::new(name), arity), Store::Dynamic, Vec::clear);
+}
@@ -0,0 +1,205 @@
+use super::threads::current;
+use super::{atom, existence_error, instantiation_error, throw, type_error, unify, Branch};
+use crate::ast::{Atom, Number, Term};
+use crate::Environment;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// How long `mutex_lock/1` waits before giving the solver a chance to handle signals.
+const POLL: Duration = Duration::from_millis(100);
+
+/// A mutex, which the thread holding it can lock again, and which is free once that thread has
+/// unlocked it as many times as it locked it.
+struct Lock {
+    alias: Option<String>,
+    owner: Option<usize>,
+    count: usize,
+}
+
+static MUTEXES: Mutex<BTreeMap<usize, Lock>> = Mutex::new(BTreeMap::new());
+static UNLOCKED: Condvar = Condvar::new();
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+fn mutexes() -> MutexGuard<'static, BTreeMap<usize, Lock>> {
+    MUTEXES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The term a mutex is known by: its alias, or `'$mutex'(N)`.
+fn handle_term(id: usize, alias: Option<&str>) -> Term {
+    match alias {
+        Some(alias) => atom(alias),
+        None => Term::Atom(Atom::new(
+            "$mutex",
+            vec![Term::Number(Number::Int(id as i64))],
+        )),
+    }
+}
+
+fn insert(mutexes: &mut BTreeMap<usize, Lock>, alias: Option<String>) -> usize {
+    let id = NEXT.fetch_add(1, Ordering::SeqCst);
+
+    mutexes.insert(
+        id,
+        Lock {
+            alias,
+            owner: None,
+            count: 0,
+        },
+    );
+
+    id
+}
+
+/// The mutex named by `t`, or the error raised for a term that names none. An atom that names
+/// no mutex yet is made the alias of a new one.
+fn mutex_id(
+    env: &Environment,
+    mutexes: &mut BTreeMap<usize, Lock>,
+    t: &Term,
+) -> Result<usize, Term> {
+    let t = env.substitute_term(t);
+
+    let id = match &t {
+        Term::Var(_) => return Err(instantiation_error()),
+        Term::Atom(a) if a.name.0 == "$mutex" && a.arity == 1 => match a.args[0] {
+            Term::Number(Number::Int(n)) if n >= 0 => Some(n as usize),
+            _ => None,
+        },
+        Term::Atom(a) if a.arity == 0 => {
+            let named = mutexes
+                .iter()
+                .find(|(_, lock)| lock.alias.as_deref() == Some(&a.name.0[..]))
+                .map(|(&id, _)| id);
+
+            Some(named.unwrap_or_else(|| insert(mutexes, Some(a.name.0.clone()))))
+        }
+        _ => return Err(type_error("mutex", t)),
+    };
+
+    id.filter(|id| mutexes.contains_key(id))
+        .ok_or_else(|| existence_error("mutex", t))
+}
+
+/// `mutex_create(Mutex)` makes a new mutex, named `Mutex` if it is an atom.
+pub(super) fn mutex_create(env: &Environment, mutex: &Term) -> Vec<Branch> {
+    let mut mutexes = mutexes();
+
+    let alias = match env.substitute_term(mutex) {
+        Term::Var(_) => None,
+        Term::Atom(a) if a.arity == 0 => Some(a.name.0),
+        t => return throw(env, type_error("mutex", t)),
+    };
+
+    if let Some(alias) = &alias {
+        if mutexes
+            .values()
+            .any(|lock| lock.alias.as_ref() == Some(alias))
+        {
+            let error = Term::Atom(Atom::new(
+                "permission_error",
+                vec![atom("create"), atom("mutex"), atom(alias)],
+            ));
+            return throw(env, error);
+        }
+    }
+
+    let id = insert(&mut mutexes, alias.clone());
+    unify(env, mutex, &handle_term(id, alias.as_deref()))
+}
+
+/// `mutex_destroy(Mutex)` discards `Mutex`, which no thread may be holding.
+pub(super) fn mutex_destroy(env: &Environment, mutex: &Term) -> Vec<Branch> {
+    let mut mutexes = mutexes();
+
+    let id = match mutex_id(env, &mut mutexes, mutex) {
+        Ok(id) => id,
+        Err(formal) => return throw(env, formal),
+    };
+
+    if mutexes[&id].owner.is_some_and(|owner| owner != current()) {
+        let error = Term::Atom(Atom::new(
+            "permission_error",
+            vec![atom("destroy"), atom("mutex"), env.substitute_term(mutex)],
+        ));
+        return throw(env, error);
+    }
+
+    mutexes.remove(&id);
+    UNLOCKED.notify_all();
+
+    vec![(env.clone(), vec![])]
+}
+
+/// `mutex_lock(Mutex)` and `mutex_trylock(Mutex)` take `Mutex` for the running thread. When
+/// another thread holds it, `mutex_lock/1` waits for it to be unlocked and `mutex_trylock/1`
+/// fails.
+pub(super) fn mutex_lock(env: &Environment, mutex: &Term, wait: bool) -> Vec<Branch> {
+    let me = current();
+    let mut mutexes = mutexes();
+
+    let id = match mutex_id(env, &mut mutexes, mutex) {
+        Ok(id) => id,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let mut waited = false;
+
+    loop {
+        match mutexes.get_mut(&id) {
+            Some(lock) if lock.owner.is_none_or(|owner| owner == me) => {
+                lock.owner = Some(me);
+                lock.count += 1;
+                return vec![(env.clone(), vec![])];
+            }
+            Some(_) if wait && !waited => {
+                mutexes = match UNLOCKED.wait_timeout(mutexes, POLL) {
+                    Ok((mutexes, _)) => mutexes,
+                    Err(e) => e.into_inner().0,
+                };
+                waited = true;
+            }
+            Some(_) if wait => break,
+            Some(_) => return vec![],
+            None => {
+                let error = existence_error("mutex", env.substitute_term(mutex));
+                return throw(env, error);
+            }
+        }
+    }
+
+    // Waits again as a new goal, so that a signal can interrupt the wait.
+    let retry = Atom::new("mutex_lock", vec![mutex.clone()]);
+    vec![(env.clone(), vec![retry])]
+}
+
+/// `mutex_unlock(Mutex)` gives up one lock the running thread holds on `Mutex`.
+pub(super) fn mutex_unlock(env: &Environment, mutex: &Term) -> Vec<Branch> {
+    let me = current();
+    let mut mutexes = mutexes();
+
+    let id = match mutex_id(env, &mut mutexes, mutex) {
+        Ok(id) => id,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let lock = mutexes.get_mut(&id).unwrap();
+
+    if lock.owner != Some(me) {
+        let error = Term::Atom(Atom::new(
+            "permission_error",
+            vec![atom("unlock"), atom("mutex"), env.substitute_term(mutex)],
+        ));
+        return throw(env, error);
+    }
+
+    lock.count -= 1;
+
+    if lock.count == 0 {
+        lock.owner = None;
+        UNLOCKED.notify_all();
+    }
+
+    vec![(env.clone(), vec![])]
+}
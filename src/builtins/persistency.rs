@@ -0,0 +1,336 @@
+use super::{atom, instantiation_error, parse_term, text, throw, type_error, unify, Branch};
+use crate::ast::{Assertion, Atom, Const, Number, Term};
+use crate::Environment;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+
+type Key = (Const, usize);
+
+#[derive(Default)]
+struct Database {
+    /// The type of each argument of the predicates declared `:- persistent`.
+    declared: HashMap<Key, Vec<String>>,
+    facts: HashMap<Key, Vec<Term>>,
+    journal: Option<(String, File)>,
+}
+
+thread_local! {
+    static DATABASE: RefCell<Database> = RefCell::new(Database::default());
+}
+
+fn declare(spec: &Term, declared: &mut HashMap<Key, Vec<String>>) {
+    if let Term::Atom(Atom { name, args, .. }) = spec {
+        match (&name.0[..], &args[..]) {
+            (",", [x, y]) => {
+                declare(x, declared);
+                declare(y, declared);
+            }
+            _ => {
+                let types = args
+                    .iter()
+                    .map(|arg| match arg {
+                        Term::Atom(a) if a.name.0 == ":" && a.arity == 2 => {
+                            text(&a.args[1]).unwrap_or_else(|| String::from("any"))
+                        }
+                        _ => String::from("any"),
+                    })
+                    .collect();
+
+                declared.insert((name.clone(), args.len()), types);
+            }
+        }
+    }
+}
+
+/// Reads the `:- persistent Spec` declarations of `kb`, where `Spec` is like
+/// `fact(Name:atom, Age:integer)`. The facts stored so far are kept.
+pub(crate) fn reset(kb: &[Assertion]) {
+    let mut declared = HashMap::new();
+
+    for a in kb {
+        if a.head.name.0 == ":-" && a.head.arity == 1 {
+            if let Term::Atom(Atom { name, args, .. }) = &a.head.args[0] {
+                if name.0 == "persistent" && args.len() == 1 {
+                    declare(&args[0], &mut declared);
+                }
+            }
+        }
+    }
+
+    DATABASE.with(|db| db.borrow_mut().declared = declared);
+}
+
+fn ground(t: &Term) -> bool {
+    match t {
+        Term::Var(_) | Term::PartialString(..) => false,
+        Term::Atom(a) => a.args.iter().all(ground),
+        _ => true,
+    }
+}
+
+fn has_type(t: &Term, kind: &str) -> bool {
+    match (kind, t) {
+        ("atom", Term::Atom(a)) | ("atomic", Term::Atom(a)) => a.arity == 0,
+        ("integer", Term::Number(Number::Int(_))) => true,
+        ("nonneg", Term::Number(Number::Int(i))) => *i >= 0,
+        ("positive_integer", Term::Number(Number::Int(i))) => *i > 0,
+        ("float", Term::Number(Number::Float(_))) => true,
+        ("number", Term::Number(_)) | ("atomic", Term::Number(_)) => true,
+        ("string", Term::String(_)) | ("atomic", Term::String(_)) => true,
+        ("boolean", Term::Atom(a)) => a.arity == 0 && (a.name.0 == "true" || a.name.0 == "false"),
+        ("compound", Term::Atom(a)) => a.arity > 0,
+        ("callable", Term::Atom(_)) | ("any", _) => true,
+        _ => false,
+    }
+}
+
+/// Appends `entry(Fact)` to the journal, if the database is attached to one.
+fn journal(db: &mut Database, entry: &str, fact: &Term) -> std::io::Result<()> {
+    match &mut db.journal {
+        Some((_, file)) => writeln!(file, "{}({}).", entry, fact),
+        None => Ok(()),
+    }
+}
+
+fn io_error(file: &str) -> Term {
+    Term::Atom(Atom::new(
+        "permission_error",
+        vec![atom("write"), atom("source_sink"), atom(file)],
+    ))
+}
+
+/// Replays the `assert(Fact).` and `retract(Fact).` entries of the journal in `file`.
+fn replay(file: &File, facts: &mut HashMap<Key, Vec<Term>>) {
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        if let Some(Term::Atom(entry)) = parse_term(&line) {
+            let fact = match &entry.args[..] {
+                [Term::Atom(fact)] => fact,
+                _ => continue,
+            };
+
+            let stored = facts.entry((fact.name.clone(), fact.arity)).or_default();
+            let fact = Term::Atom(fact.clone());
+
+            match &entry.name.0[..] {
+                "assert" => stored.push(fact),
+                "retract" => {
+                    if let Some(i) = stored.iter().position(|f| *f == fact) {
+                        stored.remove(i);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// `db_attach(File, Options)` makes the facts of the persistent predicates those recorded in
+/// the journal `File`, creating it if need be, and records every later change there.
+pub(super) fn db_attach(env: &Environment, file: &Term) -> Vec<Branch> {
+    let name = match text(&env.substitute_term(file)) {
+        Some(name) => name,
+        None => return throw(env, instantiation_error()),
+    };
+
+    let file = match OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(&name)
+    {
+        Ok(file) => file,
+        Err(_) => return throw(env, io_error(&name)),
+    };
+
+    DATABASE.with(|db| {
+        let mut db = db.borrow_mut();
+        let mut facts = HashMap::new();
+        replay(&file, &mut facts);
+
+        for (name, arity) in db.declared.keys() {
+            crate::tabling::changed(name, *arity);
+        }
+
+        db.facts = facts;
+        db.journal = Some((name, file));
+    });
+
+    vec![(env.clone(), vec![])]
+}
+
+/// `db_detach` stops recording changes, keeping the facts as they are.
+pub(super) fn db_detach(env: &Environment) -> Vec<Branch> {
+    DATABASE.with(|db| db.borrow_mut().journal = None);
+    vec![(env.clone(), vec![])]
+}
+
+/// `db_sync(gc)` rewrites the journal as one `assert/1` entry for each fact there is now.
+pub(super) fn db_sync(env: &Environment) -> Vec<Branch> {
+    let rewritten = DATABASE.with(|db| {
+        let mut db = db.borrow_mut();
+        let name = match &db.journal {
+            Some((name, _)) => name.clone(),
+            None => return Ok(()),
+        };
+
+        let mut entries = String::new();
+
+        for facts in db.facts.values() {
+            for fact in facts {
+                entries.push_str(&format!("assert({}).\n", fact));
+            }
+        }
+
+        let compacted = File::create(&name)
+            .and_then(|mut f| f.write_all(entries.as_bytes()))
+            .and_then(|_| OpenOptions::new().read(true).append(true).open(&name));
+
+        match compacted {
+            Ok(file) => {
+                db.journal = Some((name, file));
+                Ok(())
+            }
+            Err(_) => Err(io_error(&name)),
+        }
+    });
+
+    match rewritten {
+        Ok(()) => vec![(env.clone(), vec![])],
+        Err(formal) => throw(env, formal),
+    }
+}
+
+/// What a call to a persistent predicate `Name` or its `assert_Name`, `retract_Name` or
+/// `retractall_Name` does.
+enum Operation {
+    Call,
+    Assert,
+    Retract,
+    RetractAll,
+}
+
+fn operation(goal: &Atom, declared: &HashMap<Key, Vec<String>>) -> Option<(Operation, Key)> {
+    let name = &goal.name.0[..];
+    let key = |name: &str| (Const(String::from(name)), goal.arity);
+
+    vec![
+        ("", Operation::Call),
+        ("assert_", Operation::Assert),
+        ("retract_", Operation::Retract),
+        ("retractall_", Operation::RetractAll),
+    ]
+    .into_iter()
+    .find_map(|(prefix, op)| {
+        let key = key(name.strip_prefix(prefix)?);
+        declared.contains_key(&key).then_some((op, key))
+    })
+}
+
+/// Runs `goal` if it calls or changes a persistent predicate.
+pub(super) fn call(env: &Environment, goal: &Atom) -> Option<Vec<Branch>> {
+    DATABASE.with(|db| {
+        let mut db = db.borrow_mut();
+        let (op, key) = operation(goal, &db.declared)?;
+        let fact = Term::Atom(Atom::new(&key.0 .0, goal.args.clone()));
+
+        let branches = match op {
+            Operation::Call => {
+                crate::tabling::reads(&key.0, key.1);
+
+                stored(&db, &key)
+                    .iter()
+                    .flat_map(|f| unify(env, &fact, f))
+                    .collect()
+            }
+            Operation::Assert => {
+                let fact = env.substitute_term(&fact);
+                let types = &db.declared[&key];
+
+                if !ground(&fact) {
+                    return Some(throw(env, instantiation_error()));
+                }
+
+                if let Some((arg, kind)) = goal
+                    .args
+                    .iter()
+                    .map(|arg| env.substitute_term(arg))
+                    .zip(types)
+                    .find(|(arg, kind)| !has_type(arg, kind))
+                {
+                    return Some(throw(env, type_error(kind, arg)));
+                }
+
+                if journal(&mut db, "assert", &fact).is_err() {
+                    let name = db.journal.as_ref().map_or("", |(name, _)| name);
+                    return Some(throw(env, io_error(name)));
+                }
+
+                crate::tabling::changed(&key.0, key.1);
+                db.facts.entry(key).or_default().push(fact);
+                vec![(env.clone(), vec![])]
+            }
+            // Each fact that unifies is removed when the branch for it is taken, so that
+            // backtracking into retract removes the next.
+            Operation::Retract => stored(&db, &key)
+                .iter()
+                .flat_map(|f| {
+                    let retract = Atom::new("$retract_persistent", vec![f.clone()]);
+
+                    unify(env, &fact, f)
+                        .into_iter()
+                        .map(move |(env, _)| (env, vec![retract.clone()]))
+                })
+                .collect(),
+            Operation::RetractAll => {
+                let matching: Vec<_> = stored(&db, &key)
+                    .into_iter()
+                    .filter(|f| !unify(env, &fact, f).is_empty())
+                    .collect();
+
+                for f in &matching {
+                    remove(&mut db, f);
+                }
+
+                vec![(env.clone(), vec![])]
+            }
+        };
+
+        Some(branches)
+    })
+}
+
+fn stored(db: &Database, key: &Key) -> Vec<Term> {
+    db.facts.get(key).cloned().unwrap_or_default()
+}
+
+/// Removes one copy of `fact`, returning false if there is none.
+fn remove(db: &mut Database, fact: &Term) -> bool {
+    let key = match fact {
+        Term::Atom(a) => (a.name.clone(), a.arity),
+        _ => return false,
+    };
+
+    let facts = db.facts.entry(key.clone()).or_default();
+
+    match facts.iter().position(|f| f == fact) {
+        Some(i) => {
+            facts.remove(i);
+            crate::tabling::changed(&key.0, key.1);
+            // A failed write leaves the journal behind the facts until the next db_sync(gc).
+            let _ = journal(db, "retract", fact);
+            true
+        }
+        None => false,
+    }
+}
+
+/// `'$retract_persistent'(Fact)` removes `Fact`, failing if another retract got to it first.
+pub(super) fn retract(env: &Environment, fact: &Term) -> Vec<Branch> {
+    if DATABASE.with(|db| remove(&mut db.borrow_mut(), fact)) {
+        vec![(env.clone(), vec![])]
+    } else {
+        vec![]
+    }
+}
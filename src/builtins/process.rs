@@ -0,0 +1,277 @@
+use super::streams::{open_reader, open_writer};
+use super::{
+    domain_error, existence_error, instantiation_error, list_items, text, throw, type_error, unify,
+    Branch,
+};
+use crate::ast::{Atom, Number, Term};
+use crate::Environment;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::process::{Child, Command, ExitStatus, Stdio};
+
+thread_local! {
+    static CHILDREN: RefCell<HashMap<u32, Child>> = RefCell::new(HashMap::new());
+}
+
+/// How one of the standard streams of a new process is connected.
+enum Connection {
+    Inherit,
+    Null,
+    Pipe(Term),
+}
+
+impl Connection {
+    fn stdio(&self) -> Stdio {
+        match self {
+            Connection::Inherit => Stdio::inherit(),
+            Connection::Null => Stdio::null(),
+            Connection::Pipe(_) => Stdio::piped(),
+        }
+    }
+}
+
+fn connection(t: &Term) -> Option<Connection> {
+    match t {
+        Term::Atom(a) if a.name.0 == "pipe" && a.arity == 1 => {
+            Some(Connection::Pipe(a.args[0].clone()))
+        }
+        t => match text(t)?.as_str() {
+            "std" => Some(Connection::Inherit),
+            "null" => Some(Connection::Null),
+            _ => None,
+        },
+    }
+}
+
+/// The program `t` names, where `path(Name)` is looked up on the `PATH`.
+fn executable(t: &Term) -> Option<String> {
+    match t {
+        Term::Atom(a) if a.name.0 == "path" && a.arity == 1 => text(&a.args[0]),
+        t => text(t),
+    }
+}
+
+fn status_term(status: ExitStatus) -> Term {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            let signal = Term::Number(Number::Int(i64::from(signal)));
+            return Term::Atom(Atom::new("killed", vec![signal]));
+        }
+    }
+
+    let code = Term::Number(Number::Int(i64::from(status.code().unwrap_or(-1))));
+    Term::Atom(Atom::new("exit", vec![code]))
+}
+
+/// `process_create(Exe, Args, Options)` runs `Exe` with `Args`. The options `stdin(Spec)`,
+/// `stdout(Spec)` and `stderr(Spec)` connect its standard streams, where `Spec` is `std`, `null`
+/// or `pipe(Stream)`; `cwd(Dir)` sets its working directory; and `process(Pid)` leaves it running
+/// to be waited for with `process_wait/2`. Without `process(Pid)`, a process with no pipes is
+/// waited for, raising an error if it fails.
+pub(super) fn process_create(
+    env: &Environment,
+    exe: &Term,
+    args: &Term,
+    options: &Term,
+) -> Vec<Branch> {
+    let exe_term = env.substitute_term(exe);
+    let program = match executable(&exe_term) {
+        Some(program) => program,
+        None => return throw(env, instantiation_error()),
+    };
+
+    let args = match list_items(&env.substitute_term(args))
+        .and_then(|args| args.iter().map(text).collect::<Option<Vec<_>>>())
+    {
+        Some(args) => args,
+        None => return throw(env, instantiation_error()),
+    };
+
+    let mut command = Command::new(&program);
+    command.args(&args);
+
+    let mut streams = [
+        Connection::Inherit,
+        Connection::Inherit,
+        Connection::Inherit,
+    ];
+    let mut pid = None;
+
+    for option in list_items(&env.substitute_term(options)).unwrap_or_default() {
+        let (name, value) = match &option {
+            Term::Atom(a) if a.arity == 1 => (&a.name.0[..], &a.args[0]),
+            _ => return throw(env, domain_error("process_option", option)),
+        };
+
+        let i = match name {
+            "stdin" => 0,
+            "stdout" => 1,
+            "stderr" => 2,
+            "process" => {
+                pid = Some(value.clone());
+                continue;
+            }
+            "cwd" => {
+                if let Some(dir) = text(value) {
+                    command.current_dir(dir);
+                }
+                continue;
+            }
+            _ => return throw(env, domain_error("process_option", option)),
+        };
+
+        streams[i] = match connection(value) {
+            Some(connection) => connection,
+            None => return throw(env, domain_error("process_stream", value.clone())),
+        };
+    }
+
+    command
+        .stdin(streams[0].stdio())
+        .stdout(streams[1].stdio())
+        .stderr(streams[2].stdio());
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(_) => {
+            let error = existence_error("source_sink", exe_term);
+            return throw(env, error);
+        }
+    };
+
+    let mut env = env.clone();
+    let mut pipes = Vec::new();
+
+    if let Connection::Pipe(ref t) = streams[0] {
+        pipes.push((t, open_writer(child.stdin.take().unwrap())));
+    }
+
+    if let Connection::Pipe(ref t) = streams[1] {
+        pipes.push((t, open_reader(child.stdout.take().unwrap())));
+    }
+
+    if let Connection::Pipe(ref t) = streams[2] {
+        pipes.push((t, open_reader(child.stderr.take().unwrap())));
+    }
+
+    let waits = pid.is_none() && pipes.is_empty();
+
+    if let Some(pid) = pid {
+        let id = Term::Number(Number::Int(i64::from(child.id())));
+
+        env = match env.unify_terms(&pid, &id) {
+            Ok(env) => env,
+            Err(_) => return vec![],
+        };
+    }
+
+    for (t, stream) in pipes {
+        env = match env.unify_terms(t, &stream) {
+            Ok(env) => env,
+            Err(_) => return vec![],
+        };
+    }
+
+    if waits {
+        return match child.wait().map(status_term) {
+            Ok(status) if status == exit_code(0) => vec![(env, vec![])],
+            Ok(status) => {
+                let error = Term::Atom(Atom::new("process_error", vec![exe_term, status]));
+                throw(&env, error)
+            }
+            Err(_) => vec![],
+        };
+    }
+
+    CHILDREN.with(|children| children.borrow_mut().insert(child.id(), child));
+
+    vec![(env, vec![])]
+}
+
+fn exit_code(code: i64) -> Term {
+    Term::Atom(Atom::new("exit", vec![Term::Number(Number::Int(code))]))
+}
+
+/// `process_wait(Pid, Status)` waits for a process started by `process_create/3` to finish,
+/// unifying `Status` with `exit(Code)` or `killed(Signal)`.
+pub(super) fn process_wait(env: &Environment, pid: &Term, status: &Term) -> Vec<Branch> {
+    let pid = match env.substitute_term(pid) {
+        Term::Number(Number::Int(pid)) => pid as u32,
+        Term::Var(_) => return throw(env, instantiation_error()),
+        t => return throw(env, type_error("integer", t)),
+    };
+
+    let child = CHILDREN.with(|children| children.borrow_mut().remove(&pid));
+
+    match child.map(|mut child| child.wait()) {
+        Some(Ok(exit)) => unify(env, status, &status_term(exit)),
+        Some(Err(_)) => vec![],
+        None => {
+            let culprit = Term::Number(Number::Int(i64::from(pid)));
+            throw(env, existence_error("process", culprit))
+        }
+    }
+}
+
+fn shell_command(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+
+    let mut shell = Command::new(shell);
+    shell.args([flag, command]);
+    shell
+}
+
+/// `shell(Command, Status)` runs `Command` through the system shell and unifies `Status` with
+/// its exit code. Without `Status`, it succeeds only if the command exits with 0. With `Output`,
+/// the standard output of the command is captured as a string instead of passed through.
+pub(super) fn shell(
+    env: &Environment,
+    command: &Term,
+    status: Option<&Term>,
+    output: Option<&Term>,
+) -> Vec<Branch> {
+    let command = match text(&env.substitute_term(command)) {
+        Some(command) => command,
+        None => return throw(env, instantiation_error()),
+    };
+
+    let mut shell = shell_command(&command);
+
+    let (code, captured) = if output.is_some() {
+        match shell.stderr(Stdio::inherit()).output() {
+            Ok(out) => (
+                out.status.code(),
+                Some(String::from_utf8_lossy(&out.stdout).into_owned()),
+            ),
+            Err(_) => return vec![],
+        }
+    } else {
+        match shell.status() {
+            Ok(status) => (status.code(), None),
+            Err(_) => return vec![],
+        }
+    };
+
+    let code = i64::from(code.unwrap_or(-1));
+
+    let branches = match status {
+        Some(status) => unify(env, status, &Term::Number(Number::Int(code))),
+        None if code == 0 => vec![(env.clone(), vec![])],
+        None => vec![],
+    };
+
+    match (output, captured) {
+        (Some(output), Some(captured)) => branches
+            .into_iter()
+            .flat_map(|(env, _)| unify(&env, output, &super::string(&captured)))
+            .collect(),
+        _ => branches,
+    }
+}
@@ -0,0 +1,196 @@
+use super::{domain_error, instantiation_error, list_items, text, throw, type_error, Branch};
+use crate::ast::{Atom, Term, Var};
+use crate::{fresh, Environment};
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+
+/// The attribute module a run of `propagate/2` keeps its queue under, on a variable of its own.
+const MODULE: &str = "$propagation";
+
+thread_local! {
+    /// The variable of the last run of `propagate/2` started on this thread, which is running for
+    /// as long as the bindings of the search still give it a queue.
+    static RUN: RefCell<Option<Var>> = const { RefCell::new(None) };
+}
+
+/// How soon a queued propagator runs. Cheap propagators run first, so that expensive ones see
+/// domains that are already as narrow as the cheap ones can make them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+/// Propagators waiting to run, first in first out within each priority. A propagator that is
+/// already waiting is not queued again.
+pub(crate) struct Queue {
+    levels: [VecDeque<Term>; 3],
+    /// The propagators waiting in any of the levels.
+    queued: HashSet<Term>,
+    priority: fn(&Term) -> Priority,
+}
+
+impl Queue {
+    /// Creates an empty queue that ranks propagators with `priority`.
+    pub(crate) fn new(priority: fn(&Term) -> Priority) -> Self {
+        Queue {
+            levels: Default::default(),
+            queued: HashSet::new(),
+            priority,
+        }
+    }
+
+    pub(crate) fn push(&mut self, p: Term) {
+        let priority = (self.priority)(&p);
+        self.push_at(priority, p);
+    }
+
+    /// Queues `p` with the priority `priority` rather than the one the queue ranks it with.
+    fn push_at(&mut self, priority: Priority, p: Term) {
+        if self.queued.insert(p.clone()) {
+            self.levels[priority as usize].push_back(p);
+        }
+    }
+
+    /// Takes the oldest of the most urgent propagators.
+    pub(crate) fn pop(&mut self) -> Option<Term> {
+        let p = self.levels.iter_mut().find_map(VecDeque::pop_front)?;
+        self.queued.remove(&p);
+        Some(p)
+    }
+
+    /// The queue a run of `propagate/2` keeps as the term `'$queue'(High, Medium, Low)`.
+    fn from_term(t: &Term) -> Self {
+        let mut queue = Queue::new(|_| Priority::High);
+
+        if let Term::Atom(Atom { args, .. }) = t {
+            let priorities = [Priority::High, Priority::Medium, Priority::Low];
+
+            for (priority, level) in priorities.iter().zip(args) {
+                for p in list_items(level).unwrap_or_default() {
+                    queue.push_at(*priority, p);
+                }
+            }
+        }
+
+        queue
+    }
+
+    fn to_term(&self) -> Term {
+        let levels = self
+            .levels
+            .iter()
+            .map(|level| Term::list(level.iter().cloned().collect(), Term::nil()))
+            .collect();
+
+        Term::Atom(Atom::new("$queue", levels))
+    }
+}
+
+impl Extend<Term> for Queue {
+    fn extend<I: IntoIterator<Item = Term>>(&mut self, props: I) {
+        for p in props {
+            self.push(p);
+        }
+    }
+}
+
+/// Runs the propagators in `queue` with `step` until a fixpoint is reached, that is until no
+/// step queues any more of them. Fails as soon as a step fails.
+pub(crate) fn run(
+    env: &mut Environment,
+    mut queue: Queue,
+    mut step: impl FnMut(&mut Environment, &Term, &mut Queue) -> Option<()>,
+) -> Option<()> {
+    while let Some(p) = queue.pop() {
+        let p = env.substitute_term(&p);
+        step(env, &p, &mut queue)?;
+    }
+
+    Some(())
+}
+
+/// The variable of the run of `propagate/2` going on in `env`, if one is.
+fn running(env: &Environment) -> Option<Var> {
+    // A run that was backtracked out of, or left by an exception, has no queue in `env`.
+    RUN.with(|run| {
+        run.borrow()
+            .clone()
+            .filter(|x| env.get_attr(x, MODULE).is_some())
+    })
+}
+
+/// `propagate(Priority, Propagators)` queues the goals `Propagators` with the priority `high`,
+/// `medium` or `low`, each of them unless it is waiting already, and runs the queue until it is
+/// empty. Called while a run is going on, as from an `attr_unify_hook/2` that a propagator wakes,
+/// it adds to the queue of that run instead.
+pub(super) fn propagate(env: &Environment, priority: &Term, props: &Term) -> Vec<Branch> {
+    let (priority, props) = (env.substitute_term(priority), env.substitute_term(props));
+
+    let priority = match text(&priority).as_deref() {
+        _ if matches!(priority, Term::Var(_)) => return throw(env, instantiation_error()),
+        Some("high") => Priority::High,
+        Some("medium") => Priority::Medium,
+        Some("low") => Priority::Low,
+        _ => return throw(env, domain_error("propagation_priority", priority)),
+    };
+
+    let props = match list_items(&props) {
+        Some(props) => props,
+        None if matches!(props, Term::Var(_)) => return throw(env, instantiation_error()),
+        None => return throw(env, type_error("list", props)),
+    };
+
+    let (x, goals) = match running(env) {
+        Some(x) => (x, vec![]),
+        None => {
+            let x = Var::new("_", fresh());
+            RUN.with(|run| *run.borrow_mut() = Some(x.clone()));
+            (x.clone(), vec![step_goal(x)])
+        }
+    };
+
+    let mut queue = env
+        .get_attr(&x, MODULE)
+        .map_or_else(|| Queue::new(|_| Priority::High), Queue::from_term);
+
+    for p in props {
+        queue.push_at(priority, p);
+    }
+
+    let mut env = env.clone();
+    env.put_attr(x, MODULE, queue.to_term());
+
+    vec![(env, goals)]
+}
+
+fn step_goal(x: Var) -> Atom {
+    Atom::new("$propagate", vec![Term::Var(x)])
+}
+
+/// `'$propagate'(Run)` calls the next propagator of the run of `propagate/2` whose queue `Run`
+/// keeps, and then itself again, until the queue is empty.
+pub(super) fn step(env: &Environment, run: &Term) -> Vec<Branch> {
+    let x = match env.substitute_term(run) {
+        Term::Var(x) => x,
+        _ => return vec![],
+    };
+
+    let mut queue = match env.get_attr(&x, MODULE) {
+        Some(queue) => Queue::from_term(&env.substitute_term(queue)),
+        None => return vec![(env.clone(), vec![])],
+    };
+    let mut env = env.clone();
+
+    match queue.pop() {
+        Some(p) => {
+            env.put_attr(x.clone(), MODULE, queue.to_term());
+            vec![(env, vec![Atom::new("call", vec![p]), step_goal(x)])]
+        }
+        None => {
+            env.del_attr(&x, MODULE);
+            vec![(env, vec![])]
+        }
+    }
+}
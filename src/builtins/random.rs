@@ -0,0 +1,101 @@
+use super::{list_items, unify, Branch};
+use crate::ast::{Number, Term};
+use crate::Environment;
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+thread_local! {
+    static STATE: Cell<u64> = Cell::new(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+    );
+}
+
+/// The next number of a splitmix64 sequence.
+fn next() -> u64 {
+    STATE.with(|state| {
+        let s = state.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state.set(s);
+
+        let mut z = s;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
+
+/// A uniformly chosen number in `0..bound`, which must not be zero.
+fn below(bound: u64) -> u64 {
+    let zone = u64::MAX - u64::MAX % bound;
+
+    loop {
+        let r = next();
+
+        if r < zone {
+            return r % bound;
+        }
+    }
+}
+
+pub(super) fn random(env: &Environment, x: &Term) -> Vec<Branch> {
+    let f = (next() >> 11) as f64 / (1u64 << 53) as f64;
+    unify(env, x, &Term::Number(Number::Float(f)))
+}
+
+pub(super) fn random_between(env: &Environment, low: &Term, high: &Term, x: &Term) -> Vec<Branch> {
+    match (env.substitute_term(low), env.substitute_term(high)) {
+        (Term::Number(Number::Int(low)), Term::Number(Number::Int(high))) if low <= high => {
+            let span = (high as i128 - low as i128 + 1) as u128;
+            let r = if span > u128::from(u64::MAX) {
+                next()
+            } else {
+                below(span as u64)
+            };
+
+            unify(
+                env,
+                x,
+                &Term::Number(Number::Int(low.wrapping_add(r as i64))),
+            )
+        }
+        _ => vec![],
+    }
+}
+
+pub(super) fn random_member(env: &Environment, x: &Term, list: &Term) -> Vec<Branch> {
+    match list_items(&env.substitute_term(list)) {
+        Some(items) if !items.is_empty() => {
+            unify(env, x, &items[below(items.len() as u64) as usize])
+        }
+        _ => vec![],
+    }
+}
+
+pub(super) fn random_permutation(env: &Environment, list: &Term, perm: &Term) -> Vec<Branch> {
+    let mut items = match list_items(&env.substitute_term(list)) {
+        Some(items) => items,
+        None => return vec![],
+    };
+
+    for i in (1..items.len()).rev() {
+        items.swap(i, below(i as u64 + 1) as usize);
+    }
+
+    unify(env, perm, &Term::list(items, Term::nil()))
+}
+
+/// `set_random(seed(N))` restarts the sequence so that the numbers drawn after it are repeatable.
+pub(super) fn set_random(env: &Environment, option: &Term) -> Vec<Branch> {
+    match env.substitute_term(option) {
+        Term::Atom(a) if a.name.0 == "seed" && a.arity == 1 => match &a.args[0] {
+            Term::Number(Number::Int(seed)) => {
+                STATE.with(|state| state.set(*seed as u64));
+                vec![(env.clone(), vec![])]
+            }
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
@@ -0,0 +1,155 @@
+use super::{
+    atom, instantiation_error, string, syntax_error, text, throw, type_error, unify, Branch,
+};
+use crate::ast::{Atom, Const, Term};
+use crate::Environment;
+use regex::{Regex, RegexBuilder};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+/// How many compiled patterns each thread keeps, the least recently used going first.
+const CACHE_SIZE: usize = 64;
+
+thread_local! {
+    /// Compiled patterns by their source and flags, with when each was last used.
+    static CACHE: RefCell<HashMap<(String, String), (Regex, u64)>> = RefCell::new(HashMap::new());
+    static USES: Cell<u64> = const { Cell::new(0) };
+}
+
+struct Pattern {
+    regex: Regex,
+    global: bool,
+}
+
+/// Compiles `pattern`, text or `Text/Flags`, returning the formal error to raise if it is not
+/// one or does not compile.
+fn compile(env: &Environment, pattern: &Term) -> Result<Pattern, Term> {
+    let (source, flags) = match env.substitute_term(pattern) {
+        Term::Atom(Atom {
+            name: Const(ref name),
+            ref args,
+            ..
+        }) if name == "/" && args.len() == 2 => (required(&args[0])?, required(&args[1])?),
+        t => (required(&t)?, String::new()),
+    };
+
+    let regex = CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let key = (source, flags.clone());
+        let now = USES.with(|uses| {
+            uses.set(uses.get() + 1);
+            uses.get()
+        });
+
+        if let Some((regex, used)) = cache.get_mut(&key) {
+            *used = now;
+            return Ok(regex.clone());
+        }
+
+        let regex = RegexBuilder::new(&key.0)
+            .case_insensitive(flags.contains('i'))
+            .multi_line(flags.contains('m'))
+            .dot_matches_new_line(flags.contains('s'))
+            .ignore_whitespace(flags.contains('x'))
+            .build()
+            .map_err(|_| syntax_error("illegal_regex"))?;
+
+        if cache.len() >= CACHE_SIZE {
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+            }
+        }
+
+        cache.insert(key, (regex.clone(), now));
+        Ok(regex)
+    })?;
+
+    Ok(Pattern {
+        regex,
+        global: flags.contains('g'),
+    })
+}
+
+/// The text of `t`, or the error to raise for it not being text.
+fn required(t: &Term) -> Result<String, Term> {
+    match t {
+        Term::Var(_) => Err(instantiation_error()),
+        t => text(t).ok_or_else(|| type_error("text", t.clone())),
+    }
+}
+
+pub(super) fn re_match(env: &Environment, pattern: &Term, subject: &Term) -> Vec<Branch> {
+    match (compile(env, pattern), text(&env.substitute_term(subject))) {
+        (Err(formal), _) => throw(env, formal),
+        (Ok(p), Some(subject)) if p.regex.is_match(&subject) => vec![(env.clone(), vec![])],
+        _ => vec![],
+    }
+}
+
+pub(super) fn re_match_captures(
+    env: &Environment,
+    pattern: &Term,
+    subject: &Term,
+    captures: &Term,
+) -> Vec<Branch> {
+    let (p, subject) = match (compile(env, pattern), text(&env.substitute_term(subject))) {
+        (Err(formal), _) => return throw(env, formal),
+        (Ok(p), Some(subject)) => (p, subject),
+        _ => return vec![],
+    };
+
+    p.regex
+        .captures_iter(&subject)
+        .take(if p.global { usize::MAX } else { 1 })
+        .flat_map(|caps| {
+            // A named group is given as `Name=Value`, and any other by its value alone.
+            let groups = caps
+                .iter()
+                .zip(p.regex.capture_names())
+                .map(|(group, name)| {
+                    let value = match group {
+                        Some(m) => string(m.as_str()),
+                        None => Term::nil(),
+                    };
+
+                    match name {
+                        Some(name) => Term::Atom(Atom::new("=", vec![atom(name), value])),
+                        None => value,
+                    }
+                })
+                .collect();
+
+            unify(env, captures, &Term::list(groups, Term::nil()))
+        })
+        .collect()
+}
+
+pub(super) fn re_replace(
+    env: &Environment,
+    pattern: &Term,
+    with: &Term,
+    subject: &Term,
+    result: &Term,
+) -> Vec<Branch> {
+    let p = match compile(env, pattern) {
+        Ok(p) => p,
+        Err(formal) => return throw(env, formal),
+    };
+    let with = text(&env.substitute_term(with));
+    let subject = text(&env.substitute_term(subject));
+
+    match (with, subject) {
+        (Some(with), Some(subject)) => {
+            let limit = if p.global { 0 } else { 1 };
+            let replaced = p.regex.replacen(&subject, limit, &with[..]);
+
+            unify(env, result, &string(&replaced))
+        }
+        _ => vec![],
+    }
+}
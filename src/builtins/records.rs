@@ -0,0 +1,207 @@
+use super::{instantiation_error, throw, type_error, unify, Branch};
+use crate::ast::{Atom, Const, Number, Term, Var};
+use crate::{rename_fresh, Environment};
+use std::cell::RefCell;
+
+/// What a record is filed under: an integer, or the name and arity of an atom or compound term.
+#[derive(PartialEq)]
+enum Key {
+    Int(i64),
+    Functor(Const, usize),
+}
+
+struct Record {
+    key: Key,
+    /// The key as `recorded/3` gives it back, with fresh arguments for a compound key.
+    key_term: Term,
+    id: usize,
+    value: Term,
+}
+
+#[derive(Default)]
+struct Records {
+    records: Vec<Record>,
+    next: usize,
+}
+
+thread_local! {
+    static RECORDS: RefCell<Records> = RefCell::new(Records::default());
+}
+
+fn key(t: &Term) -> Result<(Key, Term), Term> {
+    match t {
+        Term::Var(_) => Err(instantiation_error()),
+        Term::Number(Number::Int(i)) => Ok((Key::Int(*i), t.clone())),
+        Term::Atom(a) => {
+            let args = (0..a.arity)
+                .map(|i| Term::Var(Var::new(&format!("_K{}", i), 0)))
+                .collect();
+
+            Ok((
+                Key::Functor(a.name.clone(), a.arity),
+                Term::Atom(Atom::new(&a.name.0, args)),
+            ))
+        }
+        _ => Err(type_error("key", t.clone())),
+    }
+}
+
+fn reference(id: usize) -> Term {
+    Term::Atom(Atom::new(
+        "$record",
+        vec![Term::Number(Number::Int(id as i64))],
+    ))
+}
+
+/// The record named by the reference `t`, if it has not been erased.
+fn referenced(env: &Environment, t: &Term) -> Result<Option<usize>, Term> {
+    match env.substitute_term(t) {
+        Term::Var(_) => Err(instantiation_error()),
+        Term::Atom(a) if a.name.0 == "$record" && a.arity == 1 => match a.args[0] {
+            Term::Number(Number::Int(id)) if id >= 0 => Ok(Some(id as usize)),
+            _ => Ok(None),
+        },
+        t => Err(type_error("db_reference", t)),
+    }
+}
+
+/// `recorda(Key, Value, Ref)` and `recordz(Key, Value, Ref)` store a copy of `Value` under
+/// `Key`, before or after the records already there, unifying `Ref` with a reference to it.
+pub(super) fn record(
+    env: &Environment,
+    k: &Term,
+    value: &Term,
+    r: Option<&Term>,
+    first: bool,
+) -> Vec<Branch> {
+    let (key, key_term) = match key(&env.substitute_term(k)) {
+        Ok(key) => key,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let value = env.substitute_term(value);
+
+    let id = RECORDS.with(|records| {
+        let mut records = records.borrow_mut();
+        let id = records.next;
+        records.next += 1;
+
+        let at = if first {
+            records
+                .records
+                .iter()
+                .position(|r| r.key == key)
+                .unwrap_or(0)
+        } else {
+            records.records.len()
+        };
+
+        records.records.insert(
+            at,
+            Record {
+                key,
+                key_term,
+                id,
+                value,
+            },
+        );
+
+        id
+    });
+
+    match r {
+        Some(r) => unify(env, r, &reference(id)),
+        None => vec![(env.clone(), vec![])],
+    }
+}
+
+/// `recorded(Key, Value, Ref)` unifies `Value` with a copy of each record under `Key`, or
+/// under any key when `Key` is unbound, oldest first after those put in front by `recorda`.
+pub(super) fn recorded(env: &Environment, k: &Term, value: &Term, r: Option<&Term>) -> Vec<Branch> {
+    let wanted = match env.substitute_term(k) {
+        Term::Var(_) => None,
+        k => match key(&k) {
+            Ok((key, _)) => Some(key),
+            Err(formal) => return throw(env, formal),
+        },
+    };
+
+    let found: Vec<_> = RECORDS.with(|records| {
+        records
+            .borrow()
+            .records
+            .iter()
+            .filter(|r| wanted.as_ref().is_none_or(|key| r.key == *key))
+            .map(|r| {
+                (
+                    rename_fresh(&r.key_term),
+                    rename_fresh(&r.value),
+                    reference(r.id),
+                )
+            })
+            .collect()
+    });
+
+    found
+        .into_iter()
+        .flat_map(|(key_term, copy, id)| {
+            unify(env, k, &key_term)
+                .into_iter()
+                .flat_map(move |(env, _)| unify(&env, value, &copy))
+                .flat_map(move |(env, _)| match r {
+                    Some(r) => unify(&env, r, &id),
+                    None => vec![(env, vec![])],
+                })
+        })
+        .collect()
+}
+
+/// `erase(Ref)` removes the record `Ref`, failing if it is already gone.
+pub(super) fn erase(env: &Environment, r: &Term) -> Vec<Branch> {
+    let id = match referenced(env, r) {
+        Ok(Some(id)) => id,
+        Ok(None) => return vec![],
+        Err(formal) => return throw(env, formal),
+    };
+
+    let erased = RECORDS.with(|records| {
+        let records = &mut records.borrow_mut().records;
+
+        match records.iter().position(|r| r.id == id) {
+            Some(i) => {
+                records.remove(i);
+                true
+            }
+            None => false,
+        }
+    });
+
+    if erased {
+        vec![(env.clone(), vec![])]
+    } else {
+        vec![]
+    }
+}
+
+/// `instance(Ref, Value)` unifies `Value` with a copy of the record `Ref`.
+pub(super) fn instance(env: &Environment, r: &Term, value: &Term) -> Vec<Branch> {
+    let id = match referenced(env, r) {
+        Ok(Some(id)) => id,
+        Ok(None) => return vec![],
+        Err(formal) => return throw(env, formal),
+    };
+
+    let copy = RECORDS.with(|records| {
+        records
+            .borrow()
+            .records
+            .iter()
+            .find(|r| r.id == id)
+            .map(|r| rename_fresh(&r.value))
+    });
+
+    match copy {
+        Some(copy) => unify(env, value, &copy),
+        None => vec![],
+    }
+}
@@ -0,0 +1,234 @@
+use super::streams::{open_reader, open_writer};
+use super::{
+    atom, domain_error, existence_error, instantiation_error, text, throw, type_error, unify,
+    Branch,
+};
+use crate::ast::{Atom, Number, Term};
+use crate::Environment;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::Write;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::rc::Rc;
+
+/// Turns an accepted connection into the terms naming its input and output streams.
+pub(super) type Opener = Rc<dyn Fn(TcpStream) -> std::io::Result<(Term, Term)>>;
+
+struct Listener {
+    listener: TcpListener,
+    open: Opener,
+}
+
+#[derive(Default)]
+struct Sockets {
+    listeners: HashMap<usize, Listener>,
+    next: usize,
+}
+
+thread_local! {
+    static SOCKETS: RefCell<Sockets> = RefCell::new(Sockets::default());
+}
+
+/// The writing half of a connection, which tells the peer no more data is coming once closed.
+struct SocketWriter(TcpStream);
+
+impl Write for SocketWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Drop for SocketWriter {
+    fn drop(&mut self) {
+        // The peer may already have closed the connection.
+        let _ = self.0.shutdown(Shutdown::Write);
+    }
+}
+
+/// The `socket_error(Reason)` term for a failed socket operation, e.g.
+/// `socket_error(connection_refused)`.
+pub(super) fn socket_error(error: &std::io::Error) -> Term {
+    let mut reason = String::new();
+
+    for c in format!("{:?}", error.kind()).chars() {
+        if c.is_uppercase() && !reason.is_empty() {
+            reason.push('_');
+        }
+        reason.extend(c.to_lowercase());
+    }
+
+    Term::Atom(Atom::new("socket_error", vec![atom(&reason)]))
+}
+
+pub(super) fn port(t: &Term) -> Option<u16> {
+    match t {
+        Term::Number(Number::Int(n)) => u16::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+/// The host and port named by `Host:Port`, or by a lone `Port` on the `default` host.
+pub(super) fn address<'a>(t: &'a Term, default: &str) -> Result<(String, &'a Term), Term> {
+    match t {
+        Term::Atom(a) if a.name.0 == ":" && a.arity == 2 => match text(&a.args[0]) {
+            Some(host) => Ok((host, &a.args[1])),
+            None => Err(instantiation_error()),
+        },
+        Term::Var(_) | Term::Number(_) => Ok((String::from(default), t)),
+        t => Err(domain_error("socket_address", t.clone())),
+    }
+}
+
+/// Opens plain streams reading from and writing to `stream`.
+fn open_plain(stream: TcpStream) -> std::io::Result<(Term, Term)> {
+    let reader = stream.try_clone()?;
+
+    Ok((open_reader(reader), open_writer(SocketWriter(stream))))
+}
+
+/// Unifies `input` and `output` with the streams of a connection, or raises the error opening
+/// them failed with.
+pub(super) fn connected(
+    env: &Environment,
+    streams: std::io::Result<(Term, Term)>,
+    input: &Term,
+    output: &Term,
+) -> Vec<Branch> {
+    match streams {
+        Ok((input_stream, output_stream)) => unify(env, input, &input_stream)
+            .into_iter()
+            .flat_map(|(env, _)| unify(&env, output, &output_stream))
+            .collect(),
+        Err(e) => throw(env, socket_error(&e)),
+    }
+}
+
+/// `tcp_connect(Host:Port, In, Out)` connects to a server, unifying `In` and `Out` with the
+/// streams of the connection.
+pub(super) fn tcp_connect(
+    env: &Environment,
+    addr: &Term,
+    input: &Term,
+    output: &Term,
+) -> Vec<Branch> {
+    let addr = env.substitute_term(addr);
+    let (host, port_term) = match address(&addr, "localhost") {
+        Ok(address) => address,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let port = match (port(port_term), port_term) {
+        (Some(port), _) => port,
+        (None, Term::Var(_)) => return throw(env, instantiation_error()),
+        (None, t) => return throw(env, type_error("integer", t.clone())),
+    };
+
+    let streams = TcpStream::connect((host.as_str(), port)).and_then(open_plain);
+    connected(env, streams, input, output)
+}
+
+/// `tcp_listen(Address, Socket)` listens for connections on `Host:Port`, or on `Port` on all
+/// interfaces. An unbound port is bound to one chosen by the system.
+pub(super) fn tcp_listen(env: &Environment, addr: &Term, socket: &Term) -> Vec<Branch> {
+    listen(env, addr, socket, Rc::new(open_plain))
+}
+
+/// Listens on `addr`, opening the streams of each connection accepted with `open`.
+pub(super) fn listen(env: &Environment, addr: &Term, socket: &Term, open: Opener) -> Vec<Branch> {
+    let addr = env.substitute_term(addr);
+    let (host, port_term) = match address(&addr, "0.0.0.0") {
+        Ok(address) => address,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let port = match (port(port_term), port_term) {
+        (Some(port), _) => port,
+        (None, Term::Var(_)) => 0,
+        (None, t) => return throw(env, type_error("integer", t.clone())),
+    };
+
+    let listener = match TcpListener::bind((host.as_str(), port)) {
+        Ok(listener) => listener,
+        Err(e) => return throw(env, socket_error(&e)),
+    };
+
+    let bound = listener.local_addr().map(|a| a.port()).unwrap_or(port);
+
+    let n = SOCKETS.with(|sockets| {
+        let mut sockets = sockets.borrow_mut();
+        let n = sockets.next;
+        sockets.next += 1;
+        sockets.listeners.insert(n, Listener { listener, open });
+        n
+    });
+
+    let handle = Term::Atom(Atom::new(
+        "$socket",
+        vec![Term::Number(Number::Int(n as i64))],
+    ));
+
+    unify(env, port_term, &Term::Number(Number::Int(i64::from(bound))))
+        .into_iter()
+        .flat_map(|(env, _)| unify(&env, socket, &handle))
+        .collect()
+}
+
+/// The listener named by `t`, or the error raised for a term that names none.
+fn listener<T>(
+    env: &Environment,
+    t: &Term,
+    f: impl FnOnce(&mut Sockets, usize) -> Option<T>,
+) -> Result<T, Term> {
+    let t = env.substitute_term(t);
+
+    let n = match &t {
+        Term::Atom(a) if a.name.0 == "$socket" && a.arity == 1 => match a.args[0] {
+            Term::Number(Number::Int(n)) if n >= 0 => Some(n as usize),
+            _ => None,
+        },
+        Term::Var(_) => return Err(instantiation_error()),
+        _ => None,
+    };
+
+    n.and_then(|n| SOCKETS.with(|sockets| f(&mut sockets.borrow_mut(), n)))
+        .ok_or_else(|| existence_error("socket", t))
+}
+
+/// `tcp_accept(Socket, In, Out)` waits for a connection on a socket made by `tcp_listen/2` or
+/// `tls_listen/3`.
+pub(super) fn tcp_accept(
+    env: &Environment,
+    socket: &Term,
+    input: &Term,
+    output: &Term,
+) -> Vec<Branch> {
+    let accepted = listener(env, socket, |sockets, n| {
+        let Listener { listener, open } = sockets.listeners.get(&n)?;
+        Some(
+            listener
+                .try_clone()
+                .map(|listener| (listener, open.clone())),
+        )
+    });
+
+    match accepted {
+        Ok(Ok((listener, open))) => {
+            let streams = listener.accept().and_then(|(stream, _)| open(stream));
+            connected(env, streams, input, output)
+        }
+        Ok(Err(e)) => throw(env, socket_error(&e)),
+        Err(formal) => throw(env, formal),
+    }
+}
+
+pub(super) fn tcp_close_socket(env: &Environment, socket: &Term) -> Vec<Branch> {
+    match listener(env, socket, |sockets, n| sockets.listeners.remove(&n)) {
+        Ok(_) => vec![(env.clone(), vec![])],
+        Err(formal) => throw(env, formal),
+    }
+}
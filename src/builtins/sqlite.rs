@@ -0,0 +1,170 @@
+use super::{atom, existence_error, instantiation_error, list_items, text, throw, unify, Branch};
+use crate::ast::{Atom, Number, Term};
+use crate::Environment;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params_from_iter, Connection};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+#[derive(Default)]
+struct Connections {
+    open: HashMap<usize, Connection>,
+    next: usize,
+}
+
+thread_local! {
+    static CONNECTIONS: RefCell<Connections> = RefCell::new(Connections::default());
+}
+
+/// The text stored for SQL `NULL`, as ODBC interfaces to Prolog have it.
+const NULL: &str = "$null$";
+
+fn sqlite_error(error: &rusqlite::Error) -> Term {
+    Term::Atom(Atom::new("sqlite_error", vec![atom(&error.to_string())]))
+}
+
+/// `sqlite_open(File, Connection)` opens the SQLite database in `File`, creating it if need be,
+/// or an in-memory database for `':memory:'`.
+pub(super) fn sqlite_open(env: &Environment, file: &Term, connection: &Term) -> Vec<Branch> {
+    let file = match text(&env.substitute_term(file)) {
+        Some(file) => file,
+        None => return throw(env, instantiation_error()),
+    };
+
+    match Connection::open(&file) {
+        Ok(conn) => {
+            let n = CONNECTIONS.with(|connections| {
+                let mut connections = connections.borrow_mut();
+                let n = connections.next;
+                connections.next += 1;
+                connections.open.insert(n, conn);
+                n
+            });
+
+            let t = Term::Atom(Atom::new(
+                "$sqlite",
+                vec![Term::Number(Number::Int(n as i64))],
+            ));
+            unify(env, connection, &t)
+        }
+        Err(e) => throw(env, sqlite_error(&e)),
+    }
+}
+
+/// The connection named by `t`, or the error raised for a term that names none.
+fn connection<T>(
+    env: &Environment,
+    t: &Term,
+    f: impl FnOnce(&mut Connections, usize) -> Option<T>,
+) -> Result<T, Term> {
+    let t = env.substitute_term(t);
+
+    let n = match &t {
+        Term::Atom(a) if a.name.0 == "$sqlite" && a.arity == 1 => match a.args[0] {
+            Term::Number(Number::Int(n)) if n >= 0 => Some(n as usize),
+            _ => None,
+        },
+        Term::Var(_) => return Err(instantiation_error()),
+        _ => None,
+    };
+
+    n.and_then(|n| CONNECTIONS.with(|connections| f(&mut connections.borrow_mut(), n)))
+        .ok_or_else(|| existence_error("sqlite_connection", t))
+}
+
+/// `sqlite_close(Connection)` closes a connection made by `sqlite_open/2`.
+pub(super) fn sqlite_close(env: &Environment, t: &Term) -> Vec<Branch> {
+    match connection(env, t, |connections, n| connections.open.remove(&n)) {
+        Ok(_) => vec![(env.clone(), vec![])],
+        Err(formal) => throw(env, formal),
+    }
+}
+
+/// The SQL value a parameter term stands for, which is None for one that is unbound.
+fn parameter(t: &Term) -> Option<Value> {
+    Some(match t {
+        Term::Number(Number::Int(i)) => Value::Integer(*i),
+        Term::Number(Number::Float(f)) => Value::Real(*f),
+        Term::Var(_) => return None,
+        t => match text(t)? {
+            s if s == NULL => Value::Null,
+            s => Value::Text(s),
+        },
+    })
+}
+
+fn column(value: ValueRef) -> Term {
+    match value {
+        ValueRef::Null => atom(NULL),
+        ValueRef::Integer(i) => Term::Number(Number::Int(i)),
+        ValueRef::Real(f) => Term::Number(Number::Float(f)),
+        ValueRef::Text(s) => atom(&String::from_utf8_lossy(s)),
+        ValueRef::Blob(bytes) => Term::list(
+            bytes
+                .iter()
+                .map(|b| Term::Number(Number::Int(i64::from(*b))))
+                .collect(),
+            Term::nil(),
+        ),
+    }
+}
+
+/// Runs `sql` with `parameters` bound to its placeholders, returning its rows, or
+/// `affected(N)` for a statement that returns none.
+fn run(conn: &Connection, sql: &str, parameters: &[Value]) -> rusqlite::Result<Vec<Term>> {
+    let mut statement = conn.prepare(sql)?;
+    let columns = statement.column_count();
+
+    if columns == 0 {
+        let affected = statement.execute(params_from_iter(parameters))?;
+        let n = Term::Number(Number::Int(affected as i64));
+        return Ok(vec![Term::Atom(Atom::new("affected", vec![n]))]);
+    }
+
+    let mut rows = statement.query(params_from_iter(parameters))?;
+    let mut all = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        let values = (0..columns)
+            .map(|i| row.get_ref(i).map(column))
+            .collect::<rusqlite::Result<_>>()?;
+
+        all.push(Term::Atom(Atom::new("row", values)));
+    }
+
+    Ok(all)
+}
+
+/// `sqlite_query(Connection, SQL, Parameters, Row)` runs `SQL` with the numbers and text of
+/// `Parameters` bound to its `?` placeholders, and enumerates its results as
+/// `row(Column, ...)` on backtracking. A statement that returns no rows succeeds once with
+/// `Row = affected(N)`. `NULL` is `'$null$'` both ways.
+pub(super) fn sqlite_query(
+    env: &Environment,
+    conn: &Term,
+    sql: &Term,
+    parameters: &Term,
+    row: &Term,
+) -> Vec<Branch> {
+    let sql = match text(&env.substitute_term(sql)) {
+        Some(sql) => sql,
+        None => return throw(env, instantiation_error()),
+    };
+
+    let parameters = match list_items(&env.substitute_term(parameters))
+        .and_then(|items| items.iter().map(parameter).collect::<Option<Vec<_>>>())
+    {
+        Some(parameters) => parameters,
+        None => return throw(env, instantiation_error()),
+    };
+
+    let rows = connection(env, conn, |connections, n| {
+        Some(run(connections.open.get(&n)?, &sql, &parameters))
+    });
+
+    match rows {
+        Ok(Ok(rows)) => rows.iter().flat_map(|r| unify(env, row, r)).collect(),
+        Ok(Err(e)) => throw(env, sqlite_error(&e)),
+        Err(formal) => throw(env, formal),
+    }
+}
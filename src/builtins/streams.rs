@@ -0,0 +1,395 @@
+use super::arith::eval;
+use super::{
+    atom, existence_error, instantiation_error, string, text, throw, type_error, unify, Branch,
+};
+use crate::ast::{Atom, Number, Term};
+use crate::{fastrw, fresh, renumber_term, Environment};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// A text stream: one of the standard streams, or a handle `'$stream'(N)` opened at run time.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum Stream {
+    UserInput,
+    UserOutput,
+    UserError,
+    Handle(usize),
+}
+
+enum Handle {
+    Reader(BufReader<Box<dyn Read>>),
+    Writer(Box<dyn Write>),
+}
+
+#[derive(Clone, Copy, Default)]
+struct Position {
+    lines: usize,
+    column: usize,
+}
+
+#[derive(Default)]
+struct Streams {
+    handles: HashMap<usize, Handle>,
+    positions: HashMap<Stream, Position>,
+    next: usize,
+}
+
+thread_local! {
+    static STREAMS: RefCell<Streams> = RefCell::new(Streams::default());
+    /// Where `user_output` goes instead of standard output, if anywhere.
+    static USER_OUTPUT: RefCell<Option<Box<dyn Write>>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn set_user_output(writer: Option<Box<dyn Write>>) {
+    USER_OUTPUT.with(|output| *output.borrow_mut() = writer);
+}
+
+/// Writes `bytes` to where `user_output` goes, returning false if they cannot be written.
+fn write_user_output(bytes: &[u8]) -> bool {
+    USER_OUTPUT.with(|output| match &mut *output.borrow_mut() {
+        Some(writer) => writer.write_all(bytes).is_ok(),
+        None => {
+            let mut stdout = std::io::stdout();
+            stdout.write_all(bytes).is_ok() && stdout.flush().is_ok()
+        }
+    })
+}
+
+fn open(handle: Handle) -> Term {
+    let n = STREAMS.with(|streams| {
+        let mut streams = streams.borrow_mut();
+        let n = streams.next;
+        streams.next += 1;
+        streams.handles.insert(n, handle);
+        n
+    });
+
+    Term::Atom(Atom::new(
+        "$stream",
+        vec![Term::Number(Number::Int(n as i64))],
+    ))
+}
+
+/// Registers `reader` as an input stream, returning the term that names it.
+pub(super) fn open_reader(reader: impl Read + 'static) -> Term {
+    open(Handle::Reader(BufReader::new(Box::new(reader))))
+}
+
+/// Registers `writer` as an output stream, returning the term that names it.
+pub(super) fn open_writer(writer: impl Write + 'static) -> Term {
+    open(Handle::Writer(Box::new(writer)))
+}
+
+impl Stream {
+    fn position(self) -> Position {
+        STREAMS.with(|streams| {
+            streams
+                .borrow()
+                .positions
+                .get(&self)
+                .copied()
+                .unwrap_or_default()
+        })
+    }
+
+    /// The column the next character written to the stream lands in, counting from 0.
+    pub(super) fn column(self) -> usize {
+        self.position().column
+    }
+
+    /// Writes `output` to the stream, keeping track of the line and column it ends on. Returns
+    /// false if the stream cannot be written to.
+    pub(super) fn write(self, output: &str) -> bool {
+        let written = match self {
+            Stream::UserInput => false,
+            Stream::UserOutput => write_user_output(output.as_bytes()),
+            Stream::UserError => {
+                eprint!("{}", output);
+                true
+            }
+            Stream::Handle(n) => {
+                STREAMS.with(|streams| match streams.borrow_mut().handles.get_mut(&n) {
+                    Some(Handle::Writer(w)) => w.write_all(output.as_bytes()).is_ok(),
+                    _ => false,
+                })
+            }
+        };
+
+        if written {
+            STREAMS.with(|streams| {
+                let mut streams = streams.borrow_mut();
+                let position = streams.positions.entry(self).or_default();
+
+                for c in output.chars() {
+                    match c {
+                        '\n' => {
+                            position.lines += 1;
+                            position.column = 0;
+                        }
+                        '\r' => position.column = 0,
+                        '\t' => position.column = (position.column | 7) + 1,
+                        '\u{8}' => position.column = position.column.saturating_sub(1),
+                        _ => position.column += 1,
+                    }
+                }
+            });
+        }
+
+        written
+    }
+
+    /// Writes the raw `bytes` to the stream, returning false if it cannot be written to.
+    pub(super) fn write_bytes(self, bytes: &[u8]) -> bool {
+        match self {
+            Stream::UserInput => false,
+            Stream::UserOutput => write_user_output(bytes),
+            Stream::UserError => std::io::stderr().write_all(bytes).is_ok(),
+            Stream::Handle(n) => {
+                STREAMS.with(|streams| match streams.borrow_mut().handles.get_mut(&n) {
+                    Some(Handle::Writer(w)) => w.write_all(bytes).is_ok(),
+                    _ => false,
+                })
+            }
+        }
+    }
+
+    /// Reads from the stream with `read`, returning None if it is not an input stream.
+    pub(super) fn read<T>(self, read: impl FnOnce(&mut dyn BufRead) -> T) -> Option<T> {
+        match self {
+            Stream::UserInput => Some(read(&mut std::io::stdin().lock())),
+            Stream::Handle(n) => {
+                STREAMS.with(|streams| match streams.borrow_mut().handles.get_mut(&n) {
+                    Some(Handle::Reader(r)) => Some(read(r)),
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The stream named by `t`.
+pub(super) fn stream(t: &Term) -> Option<Stream> {
+    match t {
+        Term::Atom(a) if a.name.0 == "$stream" && a.arity == 1 => match a.args[0] {
+            Term::Number(Number::Int(n)) if n >= 0 => {
+                let n = n as usize;
+                STREAMS
+                    .with(|streams| streams.borrow().handles.contains_key(&n))
+                    .then_some(Stream::Handle(n))
+            }
+            _ => None,
+        },
+        t => match text(t).as_deref() {
+            Some("user_input") => Some(Stream::UserInput),
+            Some("user_output") => Some(Stream::UserOutput),
+            Some("user_error") => Some(Stream::UserError),
+            _ => None,
+        },
+    }
+}
+
+/// Reads the source `t`, which is a file name, `stream(Stream)` or `string(Text)`, with `read`,
+/// or returns the error raised for a source that cannot be read.
+#[cfg(any(feature = "xml", feature = "yaml", feature = "toml"))]
+pub(super) fn read_source<T>(
+    t: &Term,
+    read: impl FnOnce(&mut dyn BufRead) -> T,
+) -> Result<T, Term> {
+    match t {
+        Term::Atom(a) if a.name.0 == "stream" && a.arity == 1 => match stream(&a.args[0]) {
+            Some(s) => s.read(read).ok_or_else(|| {
+                Term::Atom(Atom::new(
+                    "permission_error",
+                    vec![atom("input"), atom("stream"), a.args[0].clone()],
+                ))
+            }),
+            None => Err(existence_error("stream", a.args[0].clone())),
+        },
+        Term::Atom(a) if a.name.0 == "string" && a.arity == 1 => match text(&a.args[0]) {
+            Some(s) => Ok(read(&mut s.as_bytes())),
+            None => Err(instantiation_error()),
+        },
+        t => match text(t) {
+            Some(name) => match std::fs::File::open(&name) {
+                Ok(f) => Ok(read(&mut BufReader::new(f))),
+                Err(e) => Err(super::files::io_error("open", "source_sink", &name, &e)),
+            },
+            None => Err(instantiation_error()),
+        },
+    }
+}
+
+/// The stream named by `t`, or the error raised for a term that names none.
+fn target(env: &Environment, t: Option<&Term>) -> Result<Stream, Term> {
+    let t = match t {
+        None => return Ok(Stream::UserOutput),
+        Some(t) => env.substitute_term(t),
+    };
+
+    match (stream(&t), t) {
+        (Some(stream), _) => Ok(stream),
+        (None, Term::Var(_)) => Err(instantiation_error()),
+        (None, t) => Err(existence_error("stream", t)),
+    }
+}
+
+/// `tab(Stream, N)` writes as many spaces as the arithmetic expression `N` evaluates to.
+pub(super) fn tab(env: &Environment, t: Option<&Term>, n: &Term) -> Vec<Branch> {
+    let stream = match target(env, t) {
+        Ok(stream) => stream,
+        Err(formal) => return throw(env, formal),
+    };
+
+    match eval(env, n) {
+        Ok(Number::Int(n)) if stream.write(&" ".repeat(n.max(0) as usize)) => {
+            vec![(env.clone(), vec![])]
+        }
+        Ok(Number::Int(_)) => vec![],
+        Ok(n) => throw(env, type_error("integer", Term::Number(n))),
+        Err(formal) => throw(env, formal),
+    }
+}
+
+pub(super) fn line_count(env: &Environment, t: &Term, count: &Term) -> Vec<Branch> {
+    match target(env, Some(t)) {
+        Ok(stream) => {
+            let lines = stream.position().lines as i64 + 1;
+            unify(env, count, &Term::Number(Number::Int(lines)))
+        }
+        Err(formal) => throw(env, formal),
+    }
+}
+
+pub(super) fn line_position(env: &Environment, t: &Term, column: &Term) -> Vec<Branch> {
+    match target(env, Some(t)) {
+        Ok(stream) => unify(
+            env,
+            column,
+            &Term::Number(Number::Int(stream.column() as i64)),
+        ),
+        Err(formal) => throw(env, formal),
+    }
+}
+
+/// Reads the next line of `t` without its line terminator, or `end_of_file` at the end.
+pub(super) fn read_line_to_string(env: &Environment, t: &Term, line: &Term) -> Vec<Branch> {
+    let stream = match target(env, Some(t)) {
+        Ok(stream) => stream,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let read = stream.read(|r| {
+        let mut line = String::new();
+        r.read_line(&mut line).map(|n| (n, line))
+    });
+
+    match read {
+        Some(Ok((0, _))) => unify(env, line, &atom("end_of_file")),
+        Some(Ok((_, text))) => {
+            let text = text.strip_suffix('\n').unwrap_or(&text);
+            unify(env, line, &string(text.strip_suffix('\r').unwrap_or(text)))
+        }
+        _ => permission_error(env, "input", t),
+    }
+}
+
+/// `read_string(Stream, Length, String)` reads the rest of `Stream`.
+pub(super) fn read_string(env: &Environment, t: &Term, length: &Term, s: &Term) -> Vec<Branch> {
+    let stream = match target(env, Some(t)) {
+        Ok(stream) => stream,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let read = stream.read(|r| {
+        let mut text = String::new();
+        r.read_to_string(&mut text).map(|_| text)
+    });
+
+    match read {
+        Some(Ok(text)) => {
+            let n = Term::Number(Number::Int(text.chars().count() as i64));
+            unify(env, length, &n)
+                .into_iter()
+                .flat_map(|(env, _)| unify(&env, s, &string(&text)))
+                .collect()
+        }
+        _ => permission_error(env, "input", t),
+    }
+}
+
+/// `fast_read(Stream, Term)` reads the next term written by `fast_write/2`, or `end_of_file`
+/// at the end of `Stream`.
+pub(super) fn fast_read(env: &Environment, t: &Term, term: &Term) -> Vec<Branch> {
+    let stream = match target(env, Some(t)) {
+        Ok(stream) => stream,
+        Err(formal) => return throw(env, formal),
+    };
+
+    match stream.read(|r| fastrw::read(r)) {
+        Some(Ok(Some(read))) => unify(env, term, &renumber_term(fresh(), &read)),
+        Some(Ok(None)) => unify(env, term, &atom("end_of_file")),
+        Some(Err(_)) => throw(
+            env,
+            Term::Atom(Atom::new("syntax_error", vec![atom("illegal_fast_term")])),
+        ),
+        None => permission_error(env, "input", t),
+    }
+}
+
+/// `fast_write(Stream, Term)` writes `Term` to `Stream` in the binary format of `fast_read/2`.
+pub(super) fn fast_write(env: &Environment, t: &Term, term: &Term) -> Vec<Branch> {
+    let stream = match target(env, Some(t)) {
+        Ok(stream) => stream,
+        Err(formal) => return throw(env, formal),
+    };
+
+    if stream.write_bytes(&fastrw::encode(&env.substitute_term(term))) {
+        vec![(env.clone(), vec![])]
+    } else {
+        permission_error(env, "output", t)
+    }
+}
+
+fn permission_error(env: &Environment, action: &str, t: &Term) -> Vec<Branch> {
+    let culprit = env.substitute_term(t);
+    let formal = Term::Atom(Atom::new(
+        "permission_error",
+        vec![atom(action), atom("stream"), culprit],
+    ));
+
+    throw(env, formal)
+}
+
+pub(super) fn flush_output(env: &Environment, t: Option<&Term>) -> Vec<Branch> {
+    match target(env, t) {
+        Ok(Stream::Handle(n)) => {
+            STREAMS.with(|streams| {
+                if let Some(Handle::Writer(w)) = streams.borrow_mut().handles.get_mut(&n) {
+                    // A stream whose reader has gone away has nothing left to flush to.
+                    let _ = w.flush();
+                }
+            });
+            vec![(env.clone(), vec![])]
+        }
+        Ok(_) => vec![(env.clone(), vec![])],
+        Err(formal) => throw(env, formal),
+    }
+}
+
+/// Closes a stream opened at run time; closing a standard stream does nothing.
+pub(super) fn close(env: &Environment, t: &Term) -> Vec<Branch> {
+    match target(env, Some(t)) {
+        Ok(stream @ Stream::Handle(n)) => {
+            STREAMS.with(|streams| {
+                let mut streams = streams.borrow_mut();
+                streams.handles.remove(&n);
+                streams.positions.remove(&stream);
+            });
+            vec![(env.clone(), vec![])]
+        }
+        Ok(_) => vec![(env.clone(), vec![])],
+        Err(formal) => throw(env, formal),
+    }
+}
@@ -0,0 +1,407 @@
+use super::format::emit;
+use super::write::write_options;
+use super::{atom, domain_error, instantiation_error, text, throw, type_error, unify, Branch};
+use crate::ast::{Assertion, Atom, Number, Term, WriteOptions};
+use crate::xref::{check, Issue};
+use crate::{coverage, doc, library, signals, Environment};
+pub(crate) use bfg_prolog_core::unify::OccursCheck;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// The flags a program can set, each with the values it takes, the first of them being the one
+/// it starts with. `answer_write_options` can be set too, to a list of write options.
+const SETTABLE: &[(&str, &[&str])] = &[
+    ("autoload", &["true", "false"]),
+    ("double_quotes", &["string", "codes", "chars", "atom"]),
+    ("iso", &["false", "true"]),
+    ("occurs_check", &["false", "true", "error"]),
+    ("unknown", &["error", "fail", "warning"]),
+];
+
+thread_local! {
+    static ARGV: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    /// The flags set on this thread, by name, with the values they were set to.
+    static SET: RefCell<HashMap<String, Term>> = RefCell::new(HashMap::new());
+}
+
+/// The value of the flag `name`, one of those a program can set.
+pub(crate) fn flag(name: &str) -> Term {
+    SET.with(|set| set.borrow().get(name).cloned())
+        .unwrap_or_else(|| match SETTABLE.iter().find(|(flag, _)| *flag == name) {
+            Some((_, values)) => atom(values[0]),
+            None => Term::list(
+                vec![Term::Atom(Atom::new("quoted", vec![atom("true")]))],
+                Term::nil(),
+            ),
+        })
+}
+
+/// Whether the flag `name` can be set to `value`, or None if it cannot be set at all.
+fn takes(name: &str, value: &Term) -> Option<bool> {
+    if name == "answer_write_options" {
+        return Some(write_options(value, WriteOptions::default()).is_some());
+    }
+
+    let (_, values) = SETTABLE.iter().find(|(flag, _)| *flag == name)?;
+    Some(matches!(value, Term::Atom(a) if a.arity == 0 && values.contains(&&a.name.0[..])))
+}
+
+fn set_flag(name: &str, value: Term) -> Option<Term> {
+    SET.with(|set| set.borrow_mut().insert(String::from(name), value))
+}
+
+pub(crate) fn occurs_check() -> OccursCheck {
+    match text(&flag("occurs_check")).as_deref() {
+        Some("true") => OccursCheck::True,
+        Some("error") => OccursCheck::Error,
+        _ => OccursCheck::False,
+    }
+}
+
+/// Whether the `iso` flag asks for strict conformance, leaving out the extensions to arithmetic
+/// and raising the errors the standard mandates where the extensions would succeed.
+pub(crate) fn iso() -> bool {
+    text(&flag("iso")).as_deref() == Some("true")
+}
+
+/// Runs `f` with the occurs check set to `true`.
+pub(super) fn with_occurs_check<T>(f: impl FnOnce() -> T) -> T {
+    let saved = set_flag("occurs_check", atom("true"));
+    let result = f();
+
+    SET.with(|set| match saved {
+        Some(saved) => set.borrow_mut().insert(String::from("occurs_check"), saved),
+        None => set.borrow_mut().remove("occurs_check"),
+    });
+    result
+}
+
+/// How the toplevel writes the values of an answer, as the `answer_write_options` flag says.
+pub(crate) fn answer_options() -> WriteOptions {
+    let options = WriteOptions {
+        priority: 699,
+        ..WriteOptions::default()
+    };
+
+    write_options(&flag("answer_write_options"), options).unwrap_or(options)
+}
+
+/// Reads the double-quoted text in `t`, which the parser gives as strings, as the
+/// `double_quotes` value `how` says: as a string, a list of codes or characters, or an atom.
+pub(crate) fn double_quoted(t: &Term, how: &Term) -> Term {
+    match t {
+        Term::String(s) => match text(how).as_deref().unwrap_or_default() {
+            "codes" => Term::list(
+                s.chars()
+                    .map(|c| Term::Number(Number::Int(c as i64)))
+                    .collect(),
+                Term::nil(),
+            ),
+            "chars" => Term::list(
+                s.chars().map(|c| atom(&c.to_string())).collect(),
+                Term::nil(),
+            ),
+            "atom" => atom(s),
+            _ => t.clone(),
+        },
+        Term::Atom(a) => Term::Atom(Atom::new(
+            &a.name.0,
+            a.args.iter().map(|t| double_quoted(t, how)).collect(),
+        )),
+        Term::PartialString(text, tail) => {
+            Term::PartialString(text.clone(), Box::new(double_quoted(tail, how)))
+        }
+        t => t.clone(),
+    }
+}
+
+/// The flag a `:- set_prolog_flag(Flag, Value)` directive sets, with the value it sets it to,
+/// if it is a flag that can be set to that value.
+pub(crate) fn flag_directive(a: &Assertion) -> Option<(String, Term)> {
+    match (&a.head.name.0[..], &a.head.args[..]) {
+        (":-", [Term::Atom(d)]) => match (&d.name.0[..], &d.args[..]) {
+            ("set_prolog_flag", [flag, value]) => {
+                let name = text(flag)?;
+                takes(&name, value)?.then(|| (name, value.clone()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Sets the flags that `kb` sets with `:- set_prolog_flag(Flag, Value)` directives, leaving the
+/// others as they were.
+pub(crate) fn reset_flags(kb: &[Assertion]) {
+    // The program is kept last first, so it is read from the end for the last directive to win.
+    for (name, value) in kb.iter().rev().filter_map(flag_directive) {
+        set_flag(&name, value);
+    }
+}
+
+pub(crate) fn set_argv(args: Vec<String>) {
+    ARGV.with(|argv| *argv.borrow_mut() = Some(args));
+}
+
+/// The program arguments, which are those of the process unless they were set with `set_argv`.
+pub(crate) fn argv() -> Vec<String> {
+    ARGV.with(|argv| argv.borrow().clone())
+        .unwrap_or_else(|| std::env::args().collect())
+}
+
+fn atom_list(items: Vec<String>) -> Term {
+    Term::list(items.iter().map(|item| atom(item)).collect(), Term::nil())
+}
+
+fn instantiated(env: &Environment, t: &Term) -> Result<String, Term> {
+    text(&env.substitute_term(t)).ok_or_else(instantiation_error)
+}
+
+pub(super) fn getenv(env: &Environment, name: &Term, value: &Term) -> Vec<Branch> {
+    match instantiated(env, name) {
+        Ok(name) => match std::env::var(&name) {
+            Ok(v) => unify(env, value, &atom(&v)),
+            Err(_) => vec![],
+        },
+        Err(formal) => throw(env, formal),
+    }
+}
+
+pub(super) fn setenv(env: &Environment, name: &Term, value: &Term) -> Vec<Branch> {
+    match instantiated(env, name).and_then(|name| Ok((name, instantiated(env, value)?))) {
+        Ok((name, value)) => {
+            std::env::set_var(name, value);
+            vec![(env.clone(), vec![])]
+        }
+        Err(formal) => throw(env, formal),
+    }
+}
+
+pub(super) fn unsetenv(env: &Environment, name: &Term) -> Vec<Branch> {
+    match instantiated(env, name) {
+        Ok(name) => {
+            std::env::remove_var(name);
+            vec![(env.clone(), vec![])]
+        }
+        Err(formal) => throw(env, formal),
+    }
+}
+
+/// The flags, most of them read-only ones that describe the system and the environment it runs
+/// in.
+fn flags() -> Vec<(&'static str, Term)> {
+    let bool_flag = |b: bool| atom(if b { "true" } else { "false" });
+    let mut flags = vec![
+        ("bounded", atom("true")),
+        ("max_integer", Term::Number(Number::Int(i64::MAX))),
+        ("min_integer", Term::Number(Number::Int(i64::MIN))),
+        ("version", atom(env!("CARGO_PKG_VERSION"))),
+        ("arch", atom(std::env::consts::ARCH)),
+        ("unix", bool_flag(cfg!(unix))),
+        ("windows", bool_flag(cfg!(windows))),
+        (
+            "pid",
+            Term::Number(Number::Int(i64::from(std::process::id()))),
+        ),
+        ("integer_rounding_function", atom("toward_zero")),
+        ("max_arity", atom("unbounded")),
+        ("tmp_dir", atom(&std::env::temp_dir().to_string_lossy())),
+        (
+            "cpu_count",
+            Term::Number(Number::Int(
+                std::thread::available_parallelism().map_or(1, |n| n.get() as i64),
+            )),
+        ),
+    ];
+
+    flags.extend(SETTABLE.iter().map(|(name, _)| (*name, flag(name))));
+    flags.push(("answer_write_options", flag("answer_write_options")));
+    flags.push(("argv", atom_list(argv())));
+    flags.push(("os_argv", atom_list(std::env::args().collect())));
+
+    if let Ok(path) = std::env::current_exe() {
+        flags.push(("executable", atom(&path.to_string_lossy())));
+    }
+
+    if let Some(home) = std::env::var_os("HOME") {
+        flags.push(("home", atom(&home.to_string_lossy())));
+    }
+
+    flags.push((
+        "environment",
+        Term::list(
+            std::env::vars()
+                .map(|(name, value)| Term::Atom(Atom::new("=", vec![atom(&name), atom(&value)])))
+                .collect(),
+            Term::nil(),
+        ),
+    ));
+
+    flags
+}
+
+/// Enumerates the flags that unify with `flag`, one per branch.
+pub(super) fn current_prolog_flag(env: &Environment, flag: &Term, value: &Term) -> Vec<Branch> {
+    let flag = env.substitute_term(flag);
+    let wanted = text(&flag);
+
+    flags()
+        .into_iter()
+        .filter(|(name, _)| wanted.as_deref().is_none_or(|wanted| wanted == *name))
+        .flat_map(
+            |(name, v)| match env.clone().unify_terms(&flag, &atom(name)) {
+                Ok(env) => unify(&env, value, &v),
+                Err(_) => vec![],
+            },
+        )
+        .collect()
+}
+
+/// `set_prolog_flag(Flag, Value)` changes a flag that can be changed: `autoload`,
+/// `double_quotes`, `occurs_check`, `unknown` and `answer_write_options`.
+pub(super) fn set_prolog_flag(env: &Environment, flag: &Term, value: &Term) -> Vec<Branch> {
+    let (flag, value) = (env.substitute_term(flag), env.substitute_term(value));
+
+    if matches!(flag, Term::Var(_)) || matches!(value, Term::Var(_)) {
+        return throw(env, instantiation_error());
+    }
+
+    let name = match text(&flag) {
+        Some(name) => name,
+        None => return throw(env, super::type_error("atom", flag)),
+    };
+
+    match takes(&name, &value) {
+        Some(true) => {
+            set_flag(&name, value);
+            vec![(env.clone(), vec![])]
+        }
+        Some(false) => {
+            let culprit = Term::Atom(Atom::new("+", vec![flag, value]));
+            throw(env, domain_error("flag_value", culprit))
+        }
+        None if flags().iter().any(|(read_only, _)| *read_only == name) => {
+            let formal = Term::Atom(Atom::new(
+                "permission_error",
+                vec![atom("modify"), atom("flag"), flag],
+            ));
+            throw(env, formal)
+        }
+        None => throw(env, domain_error("prolog_flag", flag)),
+    }
+}
+
+/// `on_signal(Signal, Old, New)` unifies `Old` with the action taken when `Signal` arrives and
+/// replaces it with `New`, which is `default`, `abort` to throw `'$aborted'`, or a predicate to
+/// call with the name of the signal. An unbound `New` leaves the action as it is.
+pub(super) fn on_signal(env: &Environment, signal: &Term, old: &Term, new: &Term) -> Vec<Branch> {
+    let name = match instantiated(env, signal) {
+        Ok(name) => name,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let n = match signals::number(&name) {
+        Some(n) => n,
+        None => {
+            let error = domain_error("signal", atom(&name));
+            return throw(env, error);
+        }
+    };
+
+    let current = signals::action(n);
+
+    unify(env, old, &current)
+        .into_iter()
+        .flat_map(|(env, _)| match env.substitute_term(new) {
+            Term::Var(_) => unify(&env, new, &current),
+            action => match signals::install(n, action) {
+                Ok(()) => vec![(env, vec![])],
+                Err(_) => {
+                    let error = Term::Atom(Atom::new(
+                        "permission_error",
+                        vec![atom("modify"), atom("signal"), atom(&name)],
+                    ));
+                    throw(&env, error)
+                }
+            },
+        })
+        .collect()
+}
+
+/// Cross-references the program of `kb`, unifying `issues` with the list of what it finds, or
+/// writing each as a warning when there is no list to unify.
+pub(crate) fn xref(env: &Environment, kb: &[Assertion], issues: Option<&Term>) -> Vec<Branch> {
+    let found = check(&library::without_library(kb));
+
+    match issues {
+        Some(issues) => {
+            let found = found.iter().map(Issue::to_term).collect();
+            unify(env, issues, &Term::list(found, Term::nil()))
+        }
+        None => {
+            for issue in found {
+                eprintln!("Warning: {}", issue);
+            }
+
+            vec![(env.clone(), vec![])]
+        }
+    }
+}
+
+/// Writes the documentation of the predicates of the program of `kb` that `spec`, a name or a
+/// `Name/Arity` indicator, names. Fails with a warning if there is none.
+pub(crate) fn help(env: &Environment, kb: &[Assertion], spec: &Term) -> Vec<Branch> {
+    let (name, arity) = match env.substitute_term(spec) {
+        Term::Atom(a) if a.arity == 0 => (a.name.0, None),
+        Term::Atom(a) if a.name.0 == "/" && a.arity == 2 => match (&a.args[0], &a.args[1]) {
+            (Term::Atom(name), Term::Number(Number::Int(arity))) if name.arity == 0 => {
+                (name.name.0.clone(), Some(*arity as usize))
+            }
+            (Term::Var(_), _) | (_, Term::Var(_)) => return throw(env, instantiation_error()),
+            _ => {
+                return throw(
+                    env,
+                    type_error("predicate_indicator", Term::Atom(a.clone())),
+                )
+            }
+        },
+        Term::Var(_) => return throw(env, instantiation_error()),
+        spec => return throw(env, type_error("predicate_indicator", spec)),
+    };
+    let found: Vec<_> = doc::docs(kb)
+        .into_iter()
+        .filter(|d| d.name == name && arity.is_none_or(|arity| arity == d.arity))
+        .map(|d| d.help())
+        .collect();
+
+    if found.is_empty() {
+        match arity {
+            Some(arity) => eprintln!("Warning: no documentation for {}/{}", name, arity),
+            None => eprintln!("Warning: no documentation for {}", name),
+        }
+
+        return vec![];
+    }
+
+    emit(env, None, &found.join("\n"))
+}
+
+/// `'$start_coverage'` starts recording the clauses entered, for `show_coverage/1`.
+pub(super) fn start_coverage(env: &Environment) -> Vec<Branch> {
+    coverage::start();
+    vec![(env.clone(), vec![])]
+}
+
+/// `'$show_coverage'` stops recording the clauses entered and writes how many of the clauses of
+/// each predicate of the program of `kb` were, with how many of them all were.
+pub(crate) fn show_coverage(env: &Environment, kb: &[Assertion]) -> Vec<Branch> {
+    let report = coverage::report(kb, &coverage::stop());
+    let mut output = String::new();
+
+    for c in &report {
+        output.push_str(&format!("{}\n", c));
+    }
+
+    output.push_str(&format!("Total: {}\n", coverage::summary(&report)));
+    emit(env, None, &output)
+}
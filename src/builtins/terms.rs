@@ -0,0 +1,282 @@
+use super::{domain_error, instantiation_error, list_items, throw, type_error, unify, Branch};
+use crate::ast::{Atom, Number, Term, Var};
+use crate::{fresh, rename_fresh, term_vars, Environment};
+use std::cmp::Ordering;
+
+fn rank(t: &Term) -> u8 {
+    match t {
+        Term::Var(_) => 0,
+        Term::Number(_) => 1,
+        Term::Atom(a) if a.args.is_empty() => 2,
+        Term::String(_) => 3,
+        _ => 4,
+    }
+}
+
+/// Splits a compound term into its name and arguments, unfolding the first code of a partial
+/// string into a list cell.
+fn compound(t: &Term) -> (&str, usize, Vec<Term>) {
+    match t {
+        Term::Atom(a) => (&a.name.0, a.args.len(), a.args.clone()),
+        Term::PartialString(text, tail) => {
+            let mut chars = text.chars();
+            let head = Term::Number(Number::Int(chars.next().map_or(0, |c| c as i64)));
+            (
+                ".",
+                2,
+                vec![head, Term::partial_string(chars.as_str(), (**tail).clone())],
+            )
+        }
+        _ => ("", 0, vec![]),
+    }
+}
+
+/// Compares two substituted terms in the standard order of terms.
+pub(crate) fn compare(t1: &Term, t2: &Term) -> Ordering {
+    let by_rank = rank(t1).cmp(&rank(t2));
+
+    if by_rank != Ordering::Equal {
+        return by_rank;
+    }
+
+    match (t1, t2) {
+        (Term::Var(x), Term::Var(y)) => x.cmp(y),
+        (Term::Number(x), Term::Number(y)) => x.cmp(y),
+        (Term::String(x), Term::String(y)) => x.cmp(y),
+        _ if rank(t1) == 2 => atom_name(t1).cmp(atom_name(t2)),
+        _ => {
+            let (name1, arity1, args1) = compound(t1);
+            let (name2, arity2, args2) = compound(t2);
+
+            arity1
+                .cmp(&arity2)
+                .then_with(|| name1.cmp(name2))
+                .then_with(|| {
+                    args1
+                        .iter()
+                        .zip(args2.iter())
+                        .map(|(x, y)| compare(x, y))
+                        .find(|&o| o != Ordering::Equal)
+                        .unwrap_or(Ordering::Equal)
+                })
+        }
+    }
+}
+
+fn atom_name(t: &Term) -> &str {
+    match t {
+        Term::Atom(a) => &a.name.0,
+        _ => "",
+    }
+}
+
+fn succeed_if(env: &Environment, test: bool) -> Vec<Branch> {
+    if test {
+        vec![(env.clone(), vec![])]
+    } else {
+        vec![]
+    }
+}
+
+pub(super) fn compare_terms(
+    env: &Environment,
+    t1: &Term,
+    t2: &Term,
+    test: fn(Ordering) -> bool,
+) -> Vec<Branch> {
+    let order = compare(&env.substitute_term(t1), &env.substitute_term(t2));
+    succeed_if(env, test(order))
+}
+
+pub(super) fn compare3(env: &Environment, order: &Term, t1: &Term, t2: &Term) -> Vec<Branch> {
+    let name = match compare(&env.substitute_term(t1), &env.substitute_term(t2)) {
+        Ordering::Less => "<",
+        Ordering::Equal => "=",
+        Ordering::Greater => ">",
+    };
+
+    unify(env, order, &Term::Atom(Atom::new(name, vec![])))
+}
+
+pub(super) fn not_unifiable(env: &Environment, t1: &Term, t2: &Term) -> Vec<Branch> {
+    succeed_if(env, env.clone().unify_terms(t1, t2).is_err())
+}
+
+/// Succeeds if the substituted `t` passes the type test `test`.
+pub(super) fn type_test(env: &Environment, t: &Term, test: fn(&Term) -> bool) -> Vec<Branch> {
+    succeed_if(env, test(&env.substitute_term(t)))
+}
+
+pub(super) fn is_atom(t: &Term) -> bool {
+    rank(t) == 2
+}
+
+pub(super) fn is_compound(t: &Term) -> bool {
+    rank(t) == 4
+}
+
+pub(super) fn is_list(t: &Term) -> bool {
+    list_items(t).is_some()
+}
+
+/// Whether `t` is a variable or a list that ends in one.
+pub(super) fn is_partial_list(t: &Term) -> bool {
+    match t {
+        Term::Var(_) => true,
+        Term::Atom(a) if a.name.0 == "." && a.arity == 2 => is_partial_list(&a.args[1]),
+        Term::PartialString(_, tail) => is_partial_list(tail),
+        _ => false,
+    }
+}
+
+pub(super) fn functor(env: &Environment, t: &Term, name: &Term, arity: &Term) -> Vec<Branch> {
+    let t = env.substitute_term(t);
+
+    if let Term::Var(_) = t {
+        let arity = match env.substitute_term(arity) {
+            Term::Number(Number::Int(arity)) if arity >= 0 => arity as usize,
+            Term::Var(_) => return throw(env, instantiation_error()),
+            t @ Term::Number(Number::Int(_)) => {
+                return throw(env, domain_error("not_less_than_zero", t))
+            }
+            t => return throw(env, type_error("integer", t)),
+        };
+
+        let made = match env.substitute_term(name) {
+            Term::Var(_) => return throw(env, instantiation_error()),
+            name if is_compound(&name) => return throw(env, type_error("atomic", name)),
+            name if arity == 0 => name,
+            name if is_atom(&name) => {
+                let k = fresh();
+                let args = (0..arity)
+                    .map(|i| Term::Var(Var::new(&format!("_G{}", i), k)))
+                    .collect();
+                Term::Atom(Atom::new(atom_name(&name), args))
+            }
+            name => return throw(env, type_error("atom", name)),
+        };
+
+        return unify(env, &t, &made);
+    }
+
+    let (functor_name, functor_arity) = match rank(&t) {
+        4 => {
+            let (name, arity, _) = compound(&t);
+            (Term::Atom(Atom::new(name, vec![])), arity)
+        }
+        _ => (t.clone(), 0),
+    };
+
+    unify(env, name, &functor_name)
+        .into_iter()
+        .flat_map(|(env, _)| {
+            unify(
+                &env,
+                arity,
+                &Term::Number(Number::Int(functor_arity as i64)),
+            )
+        })
+        .collect()
+}
+
+pub(super) fn arg(env: &Environment, n: &Term, t: &Term, a: &Term) -> Vec<Branch> {
+    let t = env.substitute_term(t);
+
+    match t {
+        Term::Var(_) => return throw(env, instantiation_error()),
+        _ if !is_compound(&t) => return throw(env, type_error("compound", t)),
+        _ => (),
+    }
+
+    let (_, _, args) = compound(&t);
+
+    match env.substitute_term(n) {
+        Term::Number(Number::Int(n)) => match args.get((n as usize).wrapping_sub(1)) {
+            Some(arg) if n > 0 => unify(env, a, arg),
+            _ => vec![],
+        },
+        Term::Var(_) => args
+            .iter()
+            .enumerate()
+            .flat_map(|(i, arg)| {
+                unify(env, n, &Term::Number(Number::Int(i as i64 + 1)))
+                    .into_iter()
+                    .flat_map(|(env, _)| unify(&env, a, arg))
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+        n => throw(env, type_error("integer", n)),
+    }
+}
+
+pub(super) fn univ(env: &Environment, t: &Term, list: &Term) -> Vec<Branch> {
+    let t = env.substitute_term(t);
+
+    if let Term::Var(_) = t {
+        let list = env.substitute_term(list);
+        let items = match list_items(&list) {
+            Some(items) => items,
+            None if is_partial_list(&list) => return throw(env, instantiation_error()),
+            None => return throw(env, type_error("list", list)),
+        };
+
+        let made = match items.split_first() {
+            None => return throw(env, domain_error("non_empty_list", list)),
+            Some((Term::Var(_), _)) => return throw(env, instantiation_error()),
+            Some((name, _)) if rank(name) == 4 => {
+                return throw(env, type_error("atomic", name.clone()))
+            }
+            Some((name, [])) => name.clone(),
+            Some((name, args)) if is_atom(name) => {
+                Term::Atom(Atom::new(atom_name(name), args.to_vec()))
+            }
+            Some((name, _)) => return throw(env, type_error("atom", name.clone())),
+        };
+
+        return unify(env, &t, &made);
+    }
+
+    let items = match rank(&t) {
+        4 => {
+            let (name, _, args) = compound(&t);
+            let mut items = vec![Term::Atom(Atom::new(name, vec![]))];
+            items.extend(args);
+            items
+        }
+        _ => vec![t.clone()],
+    };
+
+    unify(env, list, &Term::list(items, Term::nil()))
+}
+
+/// Copies `t` with fresh variables, renamed apart from every other variable.
+pub(super) fn copy_term(env: &Environment, t: &Term, copy: &Term) -> Vec<Branch> {
+    unify(env, copy, &rename_fresh(&env.substitute_term(t)))
+}
+
+/// Binds the free variables of `t` to `'$VAR'(Start)`, `'$VAR'(Start + 1)`, ... in the order they
+/// first appear, unifying `end` with the next unused number.
+pub(super) fn numbervars(env: &Environment, t: &Term, start: &Term, end: &Term) -> Vec<Branch> {
+    let start = match env.substitute_term(start) {
+        Term::Number(Number::Int(start)) => start,
+        Term::Var(_) => return throw(env, instantiation_error()),
+        start => return throw(env, type_error("integer", start)),
+    };
+
+    let mut vars = Vec::new();
+    term_vars(&env.substitute_term(t), &mut vars);
+
+    let next = Term::Number(Number::Int(start + vars.len() as i64));
+    let mut env = env.clone();
+
+    for (i, x) in vars.into_iter().enumerate() {
+        let number = Term::Number(Number::Int(start + i as i64));
+
+        env = match env.unify_terms(&Term::Var(x), &Term::Atom(Atom::new("$VAR", vec![number]))) {
+            Ok(env) => env,
+            Err(_) => return vec![],
+        };
+    }
+
+    unify(&env, end, &next)
+}
@@ -0,0 +1,391 @@
+use super::locals::{self, Database};
+use super::{
+    atom, domain_error, existence_error, instantiation_error, list_items, throw, type_error, unify,
+    Branch,
+};
+use crate::ast::{Assertion, Atom, Number, Term};
+use crate::{rename_fresh, Environment, SolveErr};
+use std::cell::Cell;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How long `thread_get_message` waits before giving the solver a chance to handle signals.
+const POLL: Duration = Duration::from_millis(100);
+
+/// The messages sent to a thread and not yet taken.
+#[derive(Default)]
+struct Queue {
+    messages: Mutex<VecDeque<Term>>,
+    arrived: Condvar,
+}
+
+struct Thread {
+    alias: Option<String>,
+    queue: Arc<Queue>,
+    /// The running thread, which gives back its status; None for a thread that cannot be joined.
+    handle: Option<JoinHandle<Term>>,
+}
+
+static THREADS: Mutex<BTreeMap<usize, Thread>> = Mutex::new(BTreeMap::new());
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static SELF: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// The number of the running thread. A thread not started by `thread_create/3` is numbered the
+/// first time it asks, and the first of those is known as `main`.
+pub(super) fn current() -> usize {
+    if let Some(id) = SELF.with(Cell::get) {
+        return id;
+    }
+
+    let mut threads = THREADS.lock().unwrap_or_else(|e| e.into_inner());
+    let id = NEXT.fetch_add(1, Ordering::SeqCst);
+    let main = threads
+        .values()
+        .all(|thread| thread.alias.as_deref() != Some("main"));
+
+    threads.insert(
+        id,
+        Thread {
+            alias: main.then(|| String::from("main")),
+            queue: Arc::default(),
+            handle: None,
+        },
+    );
+    SELF.with(|s| s.set(Some(id)));
+
+    id
+}
+
+/// The term a thread is known by: its alias, or `'$thread'(N)`.
+fn handle_term(id: usize, alias: Option<&str>) -> Term {
+    match alias {
+        Some(alias) => atom(alias),
+        None => Term::Atom(Atom::new(
+            "$thread",
+            vec![Term::Number(Number::Int(id as i64))],
+        )),
+    }
+}
+
+/// The term the thread numbered `id` is known by.
+fn known_as(id: usize) -> Term {
+    let threads = THREADS.lock().unwrap_or_else(|e| e.into_inner());
+    let alias = threads.get(&id).and_then(|thread| thread.alias.clone());
+    handle_term(id, alias.as_deref())
+}
+
+/// The thread named by `t`, or the error raised for a term that names none.
+fn thread_id(env: &Environment, t: &Term) -> Result<usize, Term> {
+    let t = env.substitute_term(t);
+    let threads = THREADS.lock().unwrap_or_else(|e| e.into_inner());
+
+    let id = match &t {
+        Term::Var(_) => return Err(instantiation_error()),
+        Term::Atom(a) if a.name.0 == "$thread" && a.arity == 1 => match a.args[0] {
+            Term::Number(Number::Int(n)) if n >= 0 => Some(n as usize),
+            _ => None,
+        },
+        Term::Atom(a) if a.arity == 0 => threads
+            .iter()
+            .find(|(_, thread)| thread.alias.as_deref() == Some(&a.name.0[..]))
+            .map(|(&id, _)| id),
+        _ => None,
+    };
+
+    id.filter(|id| threads.contains_key(id))
+        .ok_or_else(|| existence_error("thread", t))
+}
+
+fn queue(id: usize) -> Option<Arc<Queue>> {
+    let threads = THREADS.lock().unwrap_or_else(|e| e.into_inner());
+    threads.get(&id).map(|thread| thread.queue.clone())
+}
+
+/// Runs `goal` on a thread of its own, renaming apart from the number `from` on and sharing
+/// `database` with the thread that started it, giving back `true`, `false` or `exception(Ball)`.
+fn run(
+    kb: Vec<Assertion>,
+    goal: Atom,
+    id: usize,
+    from: usize,
+    database: Arc<Mutex<Database>>,
+    detached: bool,
+) -> Term {
+    SELF.with(|s| s.set(Some(id)));
+    crate::reset(&kb);
+    crate::resume_fresh(from);
+    locals::share(database);
+
+    let status = match Environment::new().solve(Vec::new(), &kb, None, vec![goal], 1) {
+        Ok(_) => atom("true"),
+        Err(SolveErr::NoSolution) => atom("false"),
+        Err(SolveErr::Exception(ball)) => Term::Atom(Atom::new("exception", vec![ball])),
+    };
+
+    if detached {
+        let mut threads = THREADS.lock().unwrap_or_else(|e| e.into_inner());
+        threads.remove(&id);
+    }
+
+    status
+}
+
+/// `thread_create(Goal, Id, Options)` runs a copy of `Goal` once on a new thread, reading the
+/// same program. The options are `alias(Name)`, naming the thread, and `detached(true)`, for a
+/// thread that forgets its status when it ends instead of waiting for `thread_join/2`.
+pub(crate) fn thread_create(
+    env: &Environment,
+    kb: &[Assertion],
+    goal: &Term,
+    id: &Term,
+    options: &Term,
+) -> Vec<Branch> {
+    let goal = match env.substitute_term(goal) {
+        Term::Atom(goal) => goal,
+        Term::Var(_) => return throw(env, instantiation_error()),
+        t => {
+            let error = type_error("callable", t);
+            return throw(env, error);
+        }
+    };
+
+    let mut alias = None;
+    let mut detached = false;
+
+    for option in list_items(&env.substitute_term(options)).unwrap_or_default() {
+        match &option {
+            Term::Atom(a) if a.name.0 == "alias" && a.arity == 1 => match &a.args[0] {
+                Term::Atom(name) if name.arity == 0 => alias = Some(name.name.0.clone()),
+                _ => {
+                    let error = domain_error("thread_option", option.clone());
+                    return throw(env, error);
+                }
+            },
+            Term::Atom(a) if a.name.0 == "detached" && a.arity == 1 => {
+                detached = a.args[0] == atom("true");
+            }
+            _ => (),
+        }
+    }
+
+    let mut threads = THREADS.lock().unwrap_or_else(|e| e.into_inner());
+
+    if let Some(alias) = &alias {
+        if threads
+            .values()
+            .any(|thread| thread.alias.as_ref() == Some(alias))
+        {
+            let culprit = Term::Atom(Atom::new("alias", vec![atom(alias)]));
+            let error = Term::Atom(Atom::new(
+                "permission_error",
+                vec![atom("create"), atom("thread"), culprit],
+            ));
+            return throw(env, error);
+        }
+    }
+
+    let n = NEXT.fetch_add(1, Ordering::SeqCst);
+    let (kb, call) = (kb.to_vec(), Atom::new("call", vec![Term::Atom(goal)]));
+    let (from, database) = (crate::fresh(), locals::database());
+    let handle = std::thread::spawn(move || run(kb, call, n, from, database, detached));
+
+    threads.insert(
+        n,
+        Thread {
+            alias: alias.clone(),
+            queue: Arc::default(),
+            handle: if detached { None } else { Some(handle) },
+        },
+    );
+    drop(threads);
+
+    unify(env, id, &handle_term(n, alias.as_deref()))
+}
+
+/// `thread_join(Id, Status)` waits for the thread `Id` to end, unifying `Status` with `true`,
+/// `false` or `exception(Ball)` as its goal succeeded, failed or raised `Ball`.
+pub(super) fn thread_join(env: &Environment, id: &Term, status: &Term) -> Vec<Branch> {
+    let n = match thread_id(env, id) {
+        Ok(n) => n,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let handle = {
+        let mut threads = THREADS.lock().unwrap_or_else(|e| e.into_inner());
+        threads.get_mut(&n).and_then(|thread| thread.handle.take())
+    };
+
+    let handle = match handle {
+        Some(handle) => handle,
+        None => {
+            let error = Term::Atom(Atom::new(
+                "permission_error",
+                vec![atom("join"), atom("thread"), env.substitute_term(id)],
+            ));
+            return throw(env, error);
+        }
+    };
+
+    let result = handle.join().unwrap_or_else(|_| atom("false"));
+    THREADS.lock().unwrap_or_else(|e| e.into_inner()).remove(&n);
+
+    unify(env, status, &result)
+}
+
+/// `thread_self(Id)` unifies `Id` with the running thread.
+pub(super) fn thread_self(env: &Environment, id: &Term) -> Vec<Branch> {
+    unify(env, id, &known_as(current()))
+}
+
+/// `thread_send_message(Id, Message)` adds a copy of `Message` to the queue of the thread `Id`.
+pub(super) fn thread_send_message(env: &Environment, id: &Term, message: &Term) -> Vec<Branch> {
+    let queue = match thread_id(env, id).map(queue) {
+        Ok(Some(queue)) => queue,
+        Ok(None) => return vec![],
+        Err(formal) => return throw(env, formal),
+    };
+
+    let message = env.substitute_term(message);
+    let mut messages = queue.messages.lock().unwrap_or_else(|e| e.into_inner());
+    messages.push_back(message);
+    queue.arrived.notify_all();
+
+    vec![(env.clone(), vec![])]
+}
+
+/// `thread_get_message(Message)` and `thread_get_message(Id, Message)` take the first message
+/// in the queue of the running thread, or of the thread `Id`, that unifies with `Message`,
+/// waiting for one to arrive when there is none.
+pub(super) fn thread_get_message(
+    env: &Environment,
+    id: Option<&Term>,
+    message: &Term,
+) -> Vec<Branch> {
+    let owner = match id.map(|id| thread_id(env, id)) {
+        Some(Ok(owner)) => owner,
+        Some(Err(formal)) => return throw(env, formal),
+        None => current(),
+    };
+
+    let queue = match queue(owner) {
+        Some(queue) => queue,
+        None => return vec![],
+    };
+
+    let mut messages = queue.messages.lock().unwrap_or_else(|e| e.into_inner());
+    let mut waited = false;
+
+    loop {
+        let taken = messages.iter().enumerate().find_map(|(i, m)| {
+            let branches = unify(env, message, &rename_fresh(m));
+            (!branches.is_empty()).then_some((i, branches))
+        });
+
+        if let Some((i, branches)) = taken {
+            messages.remove(i);
+            return branches;
+        }
+
+        if waited {
+            break;
+        }
+
+        messages = match queue.arrived.wait_timeout(messages, POLL) {
+            Ok((messages, _)) => messages,
+            Err(e) => e.into_inner().0,
+        };
+        waited = true;
+    }
+
+    // Waits again as a new goal, so that a signal can interrupt the wait.
+    let retry = Atom::new("thread_get_message", vec![known_as(owner), message.clone()]);
+    vec![(env.clone(), vec![retry])]
+}
+
+/// `concurrent(N, Goals, Options)` runs the goals in the list `Goals` on `N` threads at most,
+/// each once, and then unifies every goal with the solution found for it, in the order of the
+/// list. It fails if a goal fails and raises the exception of the first goal that raises one.
+pub(crate) fn concurrent(
+    env: &Environment,
+    kb: &[Assertion],
+    workers: &Term,
+    goals: &Term,
+) -> Vec<Branch> {
+    let workers = match env.substitute_term(workers) {
+        Term::Number(Number::Int(w)) if w > 0 => w as usize,
+        Term::Var(_) => return throw(env, instantiation_error()),
+        t => return throw(env, type_error("positive_integer", t)),
+    };
+
+    let goals = match list_items(&env.substitute_term(goals)) {
+        Some(goals) => goals,
+        None => return throw(env, instantiation_error()),
+    };
+
+    let jobs: Mutex<VecDeque<(usize, Term)>> =
+        Mutex::new(goals.iter().cloned().enumerate().collect());
+    let solved: Mutex<Vec<Option<Result<Term, Term>>>> = Mutex::new(vec![None; goals.len()]);
+    let failed = AtomicBool::new(false);
+    let (from, database) = (crate::fresh(), locals::database());
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers.min(goals.len()) {
+            scope.spawn(|| {
+                crate::reset(kb);
+                crate::resume_fresh(from);
+                locals::share(database.clone());
+
+                while !failed.load(Ordering::SeqCst) {
+                    let next = jobs.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+                    let (i, goal) = match next {
+                        Some(job) => job,
+                        None => break,
+                    };
+
+                    let call = Atom::new("call", vec![goal.clone()]);
+                    let result = match Environment::new().solve(Vec::new(), kb, None, vec![call], 1)
+                    {
+                        Ok((solution, _)) => Ok(solution.substitute_term(&goal)),
+                        Err(SolveErr::NoSolution) => {
+                            failed.store(true, Ordering::SeqCst);
+                            continue;
+                        }
+                        Err(SolveErr::Exception(ball)) => {
+                            failed.store(true, Ordering::SeqCst);
+                            Err(ball)
+                        }
+                    };
+
+                    solved.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(result);
+                }
+            });
+        }
+    });
+
+    let solved = solved.into_inner().unwrap_or_else(|e| e.into_inner());
+
+    if let Some(Err(ball)) = solved.iter().flatten().find(|result| result.is_err()) {
+        return vec![(env.clone(), vec![Atom::new("throw", vec![ball.clone()])])];
+    }
+
+    let solutions: Option<Vec<Term>> = solved
+        .into_iter()
+        .map(|result| result.and_then(Result::ok))
+        .map(|solution| solution.map(|s| rename_fresh(&s)))
+        .collect();
+
+    match solutions {
+        Some(solutions) => unify(
+            env,
+            &Term::list(goals, Term::nil()),
+            &Term::list(solutions, Term::nil()),
+        ),
+        None => vec![],
+    }
+}
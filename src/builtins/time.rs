@@ -0,0 +1,204 @@
+use super::{throw, type_error, unify, Branch};
+use crate::ast::{Number, Term};
+use crate::{limits, Environment};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "time")]
+use super::{atom, format::emit, text};
+#[cfg(feature = "time")]
+use crate::ast::Atom;
+#[cfg(feature = "time")]
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, Offset, TimeZone, Timelike};
+
+/// How often `sleep/1` checks whether the query has been cancelled.
+const POLL: Duration = Duration::from_millis(50);
+
+/// Unifies `t` with the seconds since the Unix epoch as a float.
+pub(super) fn get_time(env: &Environment, t: &Term) -> Vec<Branch> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0);
+
+    unify(env, t, &Term::Number(Number::Float(now)))
+}
+
+pub(super) fn sleep(env: &Environment, seconds: &Term) -> Vec<Branch> {
+    let seconds = match env.substitute_term(seconds) {
+        Term::Number(Number::Int(i)) => i as f64,
+        Term::Number(Number::Float(f)) => f,
+        _ => return vec![],
+    };
+
+    if seconds > 0.0 {
+        // Wakes when a time limit runs out or the query is cancelled, so that the goal sleeping
+        // can be stopped.
+        let wait = Duration::from_secs_f64(seconds);
+        let until = Instant::now() + limits::remaining().map_or(wait, |left| left.min(wait));
+
+        while !limits::cancelled() {
+            let left = until.saturating_duration_since(Instant::now());
+
+            if left.is_zero() {
+                break;
+            }
+
+            std::thread::sleep(left.min(POLL));
+        }
+    }
+
+    vec![(env.clone(), vec![])]
+}
+
+/// `'$push_time_limit'(Time, Id)` limits the goals run from now on to `Time` seconds, until
+/// `'$pop_time_limit'(Id)`.
+pub(super) fn push_time_limit(env: &Environment, time: &Term, id: &Term) -> Vec<Branch> {
+    let seconds = match env.substitute_term(time) {
+        Term::Number(Number::Int(i)) => i as f64,
+        Term::Number(Number::Float(f)) => f,
+        Term::Var(_) => return throw(env, super::instantiation_error()),
+        t => return throw(env, type_error("number", t)),
+    };
+
+    let n = limits::push(Duration::from_secs_f64(seconds.max(0.0)));
+    unify(env, id, &Term::Number(Number::Int(n as i64)))
+}
+
+/// `'$pop_time_limit'(Id)` removes the time limit `Id`.
+pub(super) fn pop_time_limit(env: &Environment, id: &Term) -> Vec<Branch> {
+    if let Term::Number(Number::Int(n)) = env.substitute_term(id) {
+        limits::pop(n as usize);
+    }
+
+    vec![(env.clone(), vec![])]
+}
+
+#[cfg(feature = "time")]
+fn seconds(t: &Term) -> Option<f64> {
+    match t {
+        Term::Number(Number::Int(i)) => Some(*i as f64),
+        Term::Number(Number::Float(f)) => Some(*f),
+        _ => None,
+    }
+}
+
+/// `stamp_date_time(Stamp, date(Y, M, D, H, Mn, S, Off, TZ, DST), TimeZone)` breaks a time stamp
+/// down in the time zone `'UTC'`, `local` or the given offset in seconds west of Greenwich.
+#[cfg(feature = "time")]
+pub(super) fn stamp_date_time(
+    env: &Environment,
+    stamp: &Term,
+    date: &Term,
+    zone: &Term,
+) -> Vec<Branch> {
+    let stamp = match seconds(&env.substitute_term(stamp)) {
+        Some(stamp) => stamp,
+        None => return vec![],
+    };
+
+    let whole = stamp.floor();
+    let utc = match DateTime::from_timestamp(whole as i64, 0) {
+        Some(utc) => utc,
+        None => return vec![],
+    };
+
+    let zone = env.substitute_term(zone);
+    let (offset, name, dst) = match (&zone, text(&zone).as_deref()) {
+        (Term::Number(Number::Int(west)), _) => (FixedOffset::west_opt(*west as i32), "-", "-"),
+        (_, Some("UTC")) => (FixedOffset::east_opt(0), "UTC", "-"),
+        (_, Some("local")) => (
+            Some(Local.offset_from_utc_datetime(&utc.naive_utc()).fix()),
+            "-",
+            "-",
+        ),
+        _ => return vec![],
+    };
+
+    let date_time = match offset {
+        Some(offset) => utc.with_timezone(&offset),
+        None => return vec![],
+    };
+
+    let int = |i: i64| Term::Number(Number::Int(i));
+    let fields = vec![
+        int(i64::from(date_time.year())),
+        int(i64::from(date_time.month())),
+        int(i64::from(date_time.day())),
+        int(i64::from(date_time.hour())),
+        int(i64::from(date_time.minute())),
+        Term::Number(Number::Float(f64::from(date_time.second()) + stamp - whole)),
+        int(i64::from(-date_time.offset().local_minus_utc())),
+        atom(name),
+        atom(dst),
+    ];
+
+    unify(env, date, &Term::Atom(Atom::new("date", fields)))
+}
+
+/// The moment a time stamp, `date/9` or `date/3` term stands for; stamps are shown in local time.
+#[cfg(feature = "time")]
+fn date_time(t: &Term) -> Option<DateTime<FixedOffset>> {
+    if let Some(stamp) = seconds(t) {
+        let whole = stamp.floor();
+        let nanos = ((stamp - whole) * 1e9) as u32;
+        let utc = DateTime::from_timestamp(whole as i64, nanos)?;
+
+        return Some(utc.with_timezone(&Local).fixed_offset());
+    }
+
+    let args = match t {
+        Term::Atom(a) if a.name.0 == "date" && (a.arity == 3 || a.arity == 9) => &a.args,
+        _ => return None,
+    };
+
+    let int = |t: &Term| match t {
+        Term::Number(Number::Int(i)) => Some(*i),
+        _ => None,
+    };
+
+    let date = NaiveDate::from_ymd_opt(
+        int(&args[0])? as i32,
+        int(&args[1])? as u32,
+        int(&args[2])? as u32,
+    )?;
+
+    if args.len() == 3 {
+        return Some(date.and_hms_opt(0, 0, 0)?.and_utc().fixed_offset());
+    }
+
+    let second = seconds(&args[5])?;
+    let naive = date.and_hms_nano_opt(
+        int(&args[3])? as u32,
+        int(&args[4])? as u32,
+        second.floor() as u32,
+        (second.fract() * 1e9) as u32,
+    )?;
+    let offset = FixedOffset::west_opt(int(&args[6])? as i32)?;
+
+    offset.from_local_datetime(&naive).single()
+}
+
+/// `format_time(Out, Format, Stamp)` writes `Stamp` to `Out` following the `strftime` directives
+/// of `Format`.
+#[cfg(feature = "time")]
+pub(super) fn format_time(env: &Environment, out: &Term, fmt: &Term, stamp: &Term) -> Vec<Branch> {
+    use std::fmt::Write;
+
+    let fmt = match text(&env.substitute_term(fmt)) {
+        Some(fmt) => fmt,
+        None => return vec![],
+    };
+
+    let date_time = match date_time(&env.substitute_term(stamp)) {
+        Some(date_time) => date_time,
+        None => return vec![],
+    };
+
+    let mut output = String::new();
+
+    if write!(output, "{}", date_time.format(&fmt)).is_err() {
+        return vec![];
+    }
+
+    emit(env, Some(out), &output)
+}
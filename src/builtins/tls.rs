@@ -0,0 +1,244 @@
+use super::sockets::{address, connected, listen, port, socket_error};
+use super::streams::{open_reader, open_writer};
+use super::{
+    atom, domain_error, existence_error, instantiation_error, list_items, text, throw, type_error,
+    Branch,
+};
+use crate::ast::{Atom, Term};
+use crate::Environment;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::StreamOwned;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection};
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::io::{BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// A TLS connection over TCP, from either end.
+pub(super) trait TlsStream: Read + Write {
+    /// Tells the peer that no more data is coming.
+    fn close(&mut self);
+}
+
+impl TlsStream for StreamOwned<ClientConnection, TcpStream> {
+    fn close(&mut self) {
+        self.conn.send_close_notify();
+        // The peer may already have closed the connection.
+        let _ = self.flush();
+        let _ = self.sock.shutdown(Shutdown::Write);
+    }
+}
+
+impl TlsStream for StreamOwned<ServerConnection, TcpStream> {
+    fn close(&mut self) {
+        self.conn.send_close_notify();
+        let _ = self.flush();
+        let _ = self.sock.shutdown(Shutdown::Write);
+    }
+}
+
+/// One half of a TLS connection, which its input and output streams share.
+struct Half(Rc<RefCell<Box<dyn TlsStream>>>);
+
+impl Read for Half {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+impl Write for Half {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.borrow_mut().flush()
+    }
+}
+
+/// The writing half, which closes the connection for writing when its stream is closed.
+struct WriteHalf(Half);
+
+impl Write for WriteHalf {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Drop for WriteHalf {
+    fn drop(&mut self) {
+        (self.0).0.borrow_mut().close();
+    }
+}
+
+fn open_streams(stream: impl TlsStream + 'static) -> (Term, Term) {
+    let shared: Rc<RefCell<Box<dyn TlsStream>>> = Rc::new(RefCell::new(Box::new(stream)));
+
+    (
+        open_reader(Half(shared.clone())),
+        open_writer(WriteHalf(Half(shared))),
+    )
+}
+
+fn tls_error(reason: &str) -> Term {
+    Term::Atom(Atom::new("tls_error", vec![atom(reason)]))
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn certificates(file: &str) -> Result<Vec<CertificateDer<'static>>, Term> {
+    let pem = std::fs::File::open(file).map_err(|_| existence_error("source_sink", atom(file)))?;
+
+    rustls_pemfile::certs(&mut BufReader::new(pem))
+        .collect::<Result<_, _>>()
+        .map_err(|e| tls_error(&e.to_string()))
+}
+
+fn private_key(file: &str) -> Result<PrivateKeyDer<'static>, Term> {
+    let pem = std::fs::File::open(file).map_err(|_| existence_error("source_sink", atom(file)))?;
+
+    match rustls_pemfile::private_key(&mut BufReader::new(pem)) {
+        Ok(Some(key)) => Ok(key),
+        Ok(None) => Err(tls_error("no private key")),
+        Err(e) => Err(tls_error(&e.to_string())),
+    }
+}
+
+/// The value of the option `name(Value)` in `options`, as text.
+fn option(options: &[Term], name: &str) -> Option<String> {
+    options.iter().find_map(|option| match option {
+        Term::Atom(a) if a.name.0 == name && a.arity == 1 => text(&a.args[0]),
+        _ => None,
+    })
+}
+
+/// The configuration of a server that presents the `certificate_file(File)` and
+/// `key_file(File)` of `options`.
+pub(super) fn server_config(options: &[Term]) -> Result<Arc<ServerConfig>, Term> {
+    let (certificate, key) = match (
+        option(options, "certificate_file"),
+        option(options, "key_file"),
+    ) {
+        (Some(certificate), Some(key)) => (certificate, key),
+        _ => return Err(instantiation_error()),
+    };
+
+    ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| tls_error(&e.to_string()))?
+        .with_no_client_auth()
+        .with_single_cert(certificates(&certificate)?, private_key(&key)?)
+        .map(Arc::new)
+        .map_err(|e| tls_error(&e.to_string()))
+}
+
+/// Wraps an accepted connection in TLS, with the handshake taking place on first use.
+pub(super) fn accept(
+    config: &Arc<ServerConfig>,
+    stream: TcpStream,
+) -> std::io::Result<StreamOwned<ServerConnection, TcpStream>> {
+    let conn = ServerConnection::new(config.clone())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    Ok(StreamOwned::new(conn, stream))
+}
+
+/// `tls_connect(Host:Port, In, Out, Options)` connects to a server over TLS, verifying its
+/// certificate against the `cacert_file(File)` of `Options`, or the bundled root certificates.
+pub(super) fn tls_connect(
+    env: &Environment,
+    addr: &Term,
+    input: &Term,
+    output: &Term,
+    options: Option<&Term>,
+) -> Vec<Branch> {
+    let addr = env.substitute_term(addr);
+    let (host, port_term) = match address(&addr, "localhost") {
+        Ok(address) => address,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let port = match (port(port_term), port_term) {
+        (Some(port), _) => port,
+        (None, Term::Var(_)) => return throw(env, instantiation_error()),
+        (None, t) => return throw(env, type_error("integer", t.clone())),
+    };
+
+    let options = options
+        .and_then(|options| list_items(&env.substitute_term(options)))
+        .unwrap_or_default();
+
+    let mut roots = RootCertStore::empty();
+
+    match option(&options, "cacert_file") {
+        Some(file) => match certificates(&file) {
+            Ok(certificates) => {
+                roots.add_parsable_certificates(certificates);
+            }
+            Err(formal) => return throw(env, formal),
+        },
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+
+    let config = match ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+    {
+        Ok(builder) => Arc::new(builder.with_root_certificates(roots).with_no_client_auth()),
+        Err(e) => return throw(env, tls_error(&e.to_string())),
+    };
+
+    let name = match ServerName::try_from(host.clone()) {
+        Ok(name) => name,
+        Err(_) => {
+            let error = domain_error("host", atom(&host));
+            return throw(env, error);
+        }
+    };
+
+    let conn = match ClientConnection::new(config, name) {
+        Ok(conn) => conn,
+        Err(e) => return throw(env, tls_error(&e.to_string())),
+    };
+
+    let mut stream = match TcpStream::connect((host.as_str(), port)) {
+        Ok(stream) => StreamOwned::new(conn, stream),
+        Err(e) => return throw(env, socket_error(&e)),
+    };
+
+    // Shakes hands up front, so that an untrusted server is reported here and not on first use.
+    while stream.conn.is_handshaking() {
+        if let Err(e) = stream.conn.complete_io(&mut stream.sock) {
+            return throw(env, tls_error(&e.to_string()));
+        }
+    }
+
+    connected(env, Ok(open_streams(stream)), input, output)
+}
+
+/// `tls_listen(Address, Socket, Options)` is `tcp_listen/2` for connections that are accepted
+/// over TLS with the certificate and key of `Options`.
+pub(super) fn tls_listen(
+    env: &Environment,
+    addr: &Term,
+    socket: &Term,
+    options: &Term,
+) -> Vec<Branch> {
+    let options = list_items(&env.substitute_term(options)).unwrap_or_default();
+
+    let config = match server_config(&options) {
+        Ok(config) => config,
+        Err(formal) => return throw(env, formal),
+    };
+
+    let open = move |stream| accept(&config, stream).map(open_streams);
+    listen(env, addr, socket, Rc::new(open))
+}
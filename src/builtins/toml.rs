@@ -0,0 +1,50 @@
+use super::json::{object, Style};
+use super::streams::read_source;
+use super::{atom, throw, unify, Branch};
+use crate::ast::{Atom, Number, Term};
+use crate::Environment;
+use toml::Value;
+
+fn term(value: &Value, style: Style) -> Term {
+    match value {
+        Value::Integer(i) => Term::Number(Number::Int(*i)),
+        Value::Float(f) => Term::Number(Number::Float(*f)),
+        Value::String(s) => style.string(s),
+        Value::Datetime(d) => style.string(&d.to_string()),
+        Value::Boolean(b) => style.constant(&b.to_string()),
+        Value::Array(items) => Term::list(
+            items.iter().map(|item| term(item, style)).collect(),
+            Term::nil(),
+        ),
+        Value::Table(table) => object(
+            table
+                .iter()
+                .map(|(k, v)| (k.clone(), term(v, style)))
+                .collect(),
+        ),
+    }
+}
+
+/// `load_toml(Source, Term, Options)` reads the TOML document in `Source` like `load_yaml/3`,
+/// with tables as objects and dates and times as strings.
+pub(super) fn load_toml(env: &Environment, source: &Term, t: &Term, options: &Term) -> Vec<Branch> {
+    let style = Style::of(env, options);
+
+    let read = read_source(&env.substitute_term(source), |input| {
+        let mut text = String::new();
+        input.read_to_string(&mut text).ok().map(|_| text)
+    });
+
+    let value = match read {
+        Ok(text) => text.and_then(|text| text.parse::<Value>().ok()),
+        Err(formal) => return throw(env, formal),
+    };
+
+    match value {
+        Some(value) => unify(env, t, &term(&value, style)),
+        None => {
+            let toml = Term::Atom(Atom::new("toml", vec![atom("illegal_toml")]));
+            throw(env, Term::Atom(Atom::new("syntax_error", vec![toml])))
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use super::format::emit;
+use super::{
+    atom, domain_error, instantiation_error, list_items, text, throw, type_error, unify, Branch,
+};
+use crate::ast::{goals, Assertion, Atom, Number, Term, WriteOptions, Written, OPERATORS};
+use crate::{term_vars, Environment};
+
+/// Reads the options of `write_term/2` in the list `t` over `options`, or None if one of them is
+/// not a write option.
+pub(super) fn write_options(t: &Term, mut options: WriteOptions) -> Option<WriteOptions> {
+    for option in list_items(t)? {
+        let (name, value) = match &option {
+            Term::Atom(a) if a.arity == 1 => (&a.name.0[..], &a.args[0]),
+            _ => return None,
+        };
+        let flag = || match text(value).as_deref() {
+            Some("true") => Some(true),
+            Some("false") => Some(false),
+            _ => None,
+        };
+
+        match (name, value) {
+            ("quoted", _) => options.quoted = flag()?,
+            ("ignore_ops", _) => options.ignore_ops = flag()?,
+            ("max_depth", Term::Number(Number::Int(depth))) if *depth >= 0 => {
+                options.max_depth = *depth as usize
+            }
+            ("priority", Term::Number(Number::Int(p))) if (0..=1200).contains(p) => {
+                options.priority = *p as usize
+            }
+            _ => return None,
+        }
+    }
+
+    Some(options)
+}
+
+/// Writes `t` to `sink`, or to standard output if there is none, as `options` say.
+pub(super) fn write(
+    env: &Environment,
+    sink: Option<&Term>,
+    t: &Term,
+    options: WriteOptions,
+) -> Vec<Branch> {
+    emit(
+        env,
+        sink,
+        &Written(&env.substitute_term(t), options).to_string(),
+    )
+}
+
+/// The options `write/1` writes with, which `write_term/2` starts from.
+pub(super) fn unquoted() -> WriteOptions {
+    WriteOptions {
+        quoted: false,
+        ..WriteOptions::default()
+    }
+}
+
+/// The options `write_canonical/1` writes with, in functional notation.
+pub(super) fn canonical() -> WriteOptions {
+    WriteOptions {
+        ignore_ops: true,
+        ..WriteOptions::default()
+    }
+}
+
+pub(super) fn write_term(
+    env: &Environment,
+    sink: Option<&Term>,
+    t: &Term,
+    options: &Term,
+) -> Vec<Branch> {
+    let options = env.substitute_term(options);
+
+    match write_options(&options, unquoted()) {
+        Some(parsed) => write(env, sink, t, parsed),
+        None if matches!(options, Term::Var(_)) => throw(env, instantiation_error()),
+        None => throw(env, domain_error("write_option", options)),
+    }
+}
+
+/// The text of the clause `t` as `portray_clause/1` writes it, with its variables named `A`, `B`,
+/// ... and each goal of its body on a line of its own.
+fn portray(t: &Term) -> String {
+    let mut vars = Vec::new();
+    term_vars(t, &mut vars);
+
+    let names = (0..vars.len())
+        .map(|i| Term::Atom(Atom::new("$VAR", vec![Term::Number(Number::Int(i as i64))])))
+        .collect();
+    let vars = Term::list(vars.into_iter().map(Term::Var).collect(), Term::nil());
+    let named = Environment::new()
+        .unify_terms(&vars, &Term::list(names, Term::nil()))
+        .unwrap_or_else(|_| Environment::new());
+
+    let t = named.substitute_term(t);
+    let goal = WriteOptions {
+        priority: 999,
+        ..WriteOptions::default()
+    };
+
+    match &t {
+        Term::Atom(a) if a.name.0 == ":-" && a.arity == 2 => {
+            let body: Vec<_> = goals(a.args[1].clone())
+                .into_iter()
+                .map(|g| format!("    {}", Written(&Term::Atom(g), goal)))
+                .collect();
+
+            format!("{} :-\n{}.\n", Written(&a.args[0], goal), body.join(",\n"))
+        }
+        t => format!("{}.\n", Written(t, goal)),
+    }
+}
+
+pub(super) fn portray_clause(env: &Environment, t: &Term) -> Vec<Branch> {
+    emit(env, None, &portray(&env.substitute_term(t)))
+}
+
+/// Writes the clauses of the predicates of `kb` that `spec`, a name or a `Name/Arity` indicator,
+/// names, with a blank line after each predicate.
+pub(crate) fn listing(env: &Environment, kb: &[Assertion], spec: &Term) -> Vec<Branch> {
+    let (name, arity) = match env.substitute_term(spec) {
+        Term::Atom(a) if a.arity == 0 => (a.name.0, None),
+        Term::Atom(a) if a.name.0 == "/" && a.arity == 2 => match (&a.args[0], &a.args[1]) {
+            (Term::Atom(name), Term::Number(Number::Int(arity))) if name.arity == 0 => {
+                (name.name.0.clone(), Some(*arity as usize))
+            }
+            (Term::Var(_), _) | (_, Term::Var(_)) => return throw(env, instantiation_error()),
+            _ => {
+                return throw(
+                    env,
+                    type_error("predicate_indicator", Term::Atom(a.clone())),
+                )
+            }
+        },
+        Term::Var(_) => return throw(env, instantiation_error()),
+        spec => return throw(env, type_error("predicate_indicator", spec)),
+    };
+
+    let mut output = String::new();
+    let mut last = None;
+
+    // The program is kept last first, so it is read from the end to list clauses in order.
+    for a in kb.iter().rev() {
+        let head = &a.head;
+
+        if head.name.0 != name || arity.is_some_and(|arity| arity != head.arity) {
+            continue;
+        }
+
+        if last.is_some_and(|last| last != head.arity) {
+            output.push('\n');
+        }
+
+        last = Some(head.arity);
+        output.push_str(&portray(&a.to_term()));
+    }
+
+    if last.is_some() {
+        output.push('\n');
+    }
+
+    emit(env, None, &output)
+}
+
+pub(super) fn nl(env: &Environment, sink: Option<&Term>) -> Vec<Branch> {
+    emit(env, sink, "\n")
+}
+
+/// Enumerates the operators the parser reads and the writer writes, one per branch.
+pub(super) fn current_op(
+    env: &Environment,
+    priority: &Term,
+    kind: &Term,
+    name: &Term,
+) -> Vec<Branch> {
+    let op = |priority, kind, name| Term::Atom(Atom::new("op", vec![priority, kind, name]));
+    let wanted = op(priority.clone(), kind.clone(), name.clone());
+
+    OPERATORS
+        .iter()
+        .flat_map(|&(p, k, n)| {
+            let table = op(Term::Number(Number::Int(p as i64)), atom(k), atom(n));
+            unify(env, &wanted, &table)
+        })
+        .collect()
+}
@@ -0,0 +1,314 @@
+use super::format::emit;
+use super::streams::read_source;
+use super::{atom, list_items, text, throw, type_error, unify, Branch};
+use crate::ast::{Atom, Term};
+use crate::Environment;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::io::BufRead;
+
+/// The HTML elements that never have content, and so are never closed.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// The HTML elements that end an open element of the same name, as a new `p` ends the last.
+const SIBLINGS: &[&str] = &["dd", "dt", "li", "option", "p", "td", "th", "tr"];
+
+/// The entities of HTML that come up most besides those of XML.
+fn entity(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "nbsp" => "\u{a0}",
+        "copy" => "©",
+        "reg" => "®",
+        "mdash" => "—",
+        "ndash" => "–",
+        "hellip" => "…",
+        "laquo" => "«",
+        "raquo" => "»",
+        _ => return None,
+    })
+}
+
+fn element(name: &str, attributes: Vec<Term>, children: Vec<Term>) -> Term {
+    Term::Atom(Atom::new(
+        "element",
+        vec![
+            atom(name),
+            Term::list(attributes, Term::nil()),
+            Term::list(children, Term::nil()),
+        ],
+    ))
+}
+
+fn reason(error: &quick_xml::Error) -> &'static str {
+    match error {
+        quick_xml::Error::UnexpectedEof(_) => "unexpected_end_of_input",
+        quick_xml::Error::EndEventMismatch { .. } => "mismatched_end_tag",
+        _ => "illegal_xml",
+    }
+}
+
+/// An element that has been opened but not yet closed.
+struct Open {
+    name: String,
+    attributes: Vec<Term>,
+    children: Vec<Term>,
+}
+
+impl Open {
+    fn new(start: &BytesStart, html: bool) -> Result<Open, &'static str> {
+        let mut name = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+        let mut attributes = Vec::new();
+
+        let all = if html {
+            name = name.to_lowercase();
+            start.html_attributes()
+        } else {
+            start.attributes()
+        };
+
+        for attribute in all {
+            let attribute = attribute.map_err(|_| "illegal_xml")?;
+            let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+            let value = attribute
+                .unescape_value_with(|name| if html { entity(name) } else { None })
+                .map_err(|e| reason(&e))?;
+
+            attributes.push(Term::Atom(Atom::new("=", vec![atom(&key), atom(&value)])));
+        }
+
+        Ok(Open {
+            name,
+            attributes,
+            children: Vec::new(),
+        })
+    }
+
+    fn close(self) -> Term {
+        element(&self.name, self.attributes, self.children)
+    }
+}
+
+/// Adds `node` to the element that is open innermost, or to the top level.
+fn add(open: &mut [Open], top: &mut Vec<Term>, node: Term) {
+    match open.last_mut() {
+        Some(parent) => parent.children.push(node),
+        None => top.push(node),
+    }
+}
+
+/// Parses the document in `input` into a list of `element(Name, Attributes, Children)` and
+/// text. HTML is read leniently, closing void and unclosed elements, with names in lower case.
+fn parse(input: &mut dyn BufRead, html: bool, preserve: bool) -> Result<Vec<Term>, &'static str> {
+    let mut reader = Reader::from_reader(input);
+    reader.check_end_names(!html);
+
+    let mut buf = Vec::new();
+    let mut open: Vec<Open> = Vec::new();
+    let mut top = Vec::new();
+
+    loop {
+        buf.clear();
+
+        let node = match reader.read_event_into(&mut buf).map_err(|e| reason(&e))? {
+            Event::Start(start) => {
+                let element = Open::new(&start, html)?;
+                let name = element.name.as_str();
+
+                if html && VOID.contains(&name) {
+                    Some(element.close())
+                } else {
+                    if html
+                        && SIBLINGS.contains(&name)
+                        && open.last().map(|e| &e.name[..]) == Some(name)
+                    {
+                        if let Some(sibling) = open.pop() {
+                            add(&mut open, &mut top, sibling.close());
+                        }
+                    }
+
+                    open.push(element);
+                    None
+                }
+            }
+            Event::Empty(start) => Some(Open::new(&start, html)?.close()),
+            Event::End(end) => {
+                let name = String::from_utf8_lossy(end.name().as_ref()).to_lowercase();
+
+                // A stray end tag in HTML closes the elements up to the one it names, if any.
+                let depth = match open.iter().rposition(|e| !html || e.name == name) {
+                    Some(depth) => depth,
+                    None => continue,
+                };
+
+                let mut element = None;
+
+                while open.len() > depth {
+                    let closed = open.pop().map(Open::close);
+                    let inner = open.len() > depth;
+
+                    match open.last_mut() {
+                        Some(parent) if inner => parent.children.extend(closed),
+                        _ => element = closed,
+                    }
+                }
+
+                element
+            }
+            Event::Text(t) => {
+                let value = t
+                    .unescape_with(|name| if html { entity(name) } else { None })
+                    .map_err(|e| reason(&e))?;
+
+                if preserve || !value.trim().is_empty() {
+                    Some(atom(&value))
+                } else {
+                    None
+                }
+            }
+            Event::CData(t) => Some(atom(&String::from_utf8_lossy(&t))),
+            Event::Eof => break,
+            _ => None,
+        };
+
+        if let Some(node) = node {
+            add(&mut open, &mut top, node);
+        }
+    }
+
+    if !html && !open.is_empty() {
+        return Err("unexpected_end_of_input");
+    }
+
+    while let Some(element) = open.pop().map(Open::close) {
+        add(&mut open, &mut top, element);
+    }
+
+    Ok(top)
+}
+
+/// `load_xml(Source, DOM, Options)` reads the XML document in `Source`, which is a file name,
+/// `stream(Stream)` or `string(Text)`, into a list of `element(Name, Attributes, Children)`
+/// terms and text atoms, with attributes as `Name = Value`. Text that is only white space is
+/// dropped unless `Options` has `space(preserve)`. `load_html/3` reads HTML instead.
+pub(super) fn load_xml(
+    env: &Environment,
+    source: &Term,
+    dom: &Term,
+    options: &Term,
+    html: bool,
+) -> Vec<Branch> {
+    let preserve = list_items(&env.substitute_term(options))
+        .unwrap_or_default()
+        .iter()
+        .any(|option| match option {
+            Term::Atom(a) if a.name.0 == "space" && a.arity == 1 => {
+                text(&a.args[0]).as_deref() == Some("preserve")
+            }
+            _ => false,
+        });
+
+    let source = env.substitute_term(source);
+
+    let parsed = match read_source(&source, |input| parse(input, html, preserve)) {
+        Ok(parsed) => parsed,
+        Err(formal) => return throw(env, formal),
+    };
+
+    match parsed {
+        Ok(nodes) => unify(env, dom, &Term::list(nodes, Term::nil())),
+        Err(reason) => {
+            let xml = Term::Atom(Atom::new("xml", vec![atom(reason)]));
+            throw(env, Term::Atom(Atom::new("syntax_error", vec![xml])))
+        }
+    }
+}
+
+fn escape(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Writes `node`, an element or text, to `out`, returning None if it is neither.
+fn write_node(node: &Term, out: &mut String) -> Option<()> {
+    match node {
+        Term::Atom(a) if a.name.0 == "element" && a.arity == 3 => {
+            let name = text(&a.args[0])?;
+            out.push('<');
+            out.push_str(&name);
+
+            for attribute in list_items(&a.args[1])? {
+                match attribute {
+                    Term::Atom(p) if p.name.0 == "=" && p.arity == 2 => {
+                        out.push(' ');
+                        out.push_str(&text(&p.args[0])?);
+                        out.push_str("=\"");
+                        escape(&text(&p.args[1])?, out);
+                        out.push('"');
+                    }
+                    _ => return None,
+                }
+            }
+
+            let children = list_items(&a.args[2])?;
+
+            if children.is_empty() {
+                out.push_str("/>");
+            } else {
+                out.push('>');
+
+                for child in &children {
+                    write_node(child, out)?;
+                }
+
+                out.push_str("</");
+                out.push_str(&name);
+                out.push('>');
+            }
+        }
+        t => escape(&text(t)?, out),
+    }
+
+    Some(())
+}
+
+/// `xml_write(Out, DOM, Options)` writes `DOM`, an element or a list of elements and text as
+/// read by `load_xml/3`, as XML to the stream or sink `Out`. An XML declaration comes first
+/// unless `Options` has `header(false)`.
+pub(super) fn xml_write(env: &Environment, out: &Term, dom: &Term, options: &Term) -> Vec<Branch> {
+    let header = !list_items(&env.substitute_term(options))
+        .unwrap_or_default()
+        .iter()
+        .any(|option| match option {
+            Term::Atom(a) if a.name.0 == "header" && a.arity == 1 => {
+                text(&a.args[0]).as_deref() == Some("false")
+            }
+            _ => false,
+        });
+
+    let dom = env.substitute_term(dom);
+    let nodes = list_items(&dom).unwrap_or_else(|| vec![dom.clone()]);
+    let mut xml = String::new();
+
+    if header {
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    }
+
+    for node in &nodes {
+        if write_node(node, &mut xml).is_none() {
+            let error = type_error("xml_dom", node.clone());
+            return throw(env, error);
+        }
+    }
+
+    emit(env, Some(out), &xml)
+}
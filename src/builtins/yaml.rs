@@ -0,0 +1,70 @@
+use super::json::{object, Style};
+use super::streams::read_source;
+use super::{atom, throw, unify, Branch};
+use crate::ast::{Atom, Number, Term};
+use crate::Environment;
+use yaml_rust::{Yaml, YamlLoader};
+
+fn key(yaml: &Yaml) -> String {
+    match yaml {
+        Yaml::String(s) | Yaml::Real(s) => s.clone(),
+        Yaml::Integer(i) => i.to_string(),
+        Yaml::Boolean(b) => b.to_string(),
+        _ => String::from("null"),
+    }
+}
+
+fn term(yaml: &Yaml, style: Style) -> Term {
+    match yaml {
+        Yaml::Integer(i) => Term::Number(Number::Int(*i)),
+        Yaml::Real(r) => match r.parse() {
+            Ok(f) => Term::Number(Number::Float(f)),
+            Err(_) => style.string(r),
+        },
+        Yaml::String(s) => style.string(s),
+        Yaml::Boolean(b) => style.constant(&b.to_string()),
+        Yaml::Array(items) => Term::list(
+            items.iter().map(|item| term(item, style)).collect(),
+            Term::nil(),
+        ),
+        Yaml::Hash(pairs) => object(
+            pairs
+                .iter()
+                .map(|(k, v)| (key(k), term(v, style)))
+                .collect(),
+        ),
+        _ => style.constant("null"),
+    }
+}
+
+/// `load_yaml(Source, Term, Options)` reads the YAML document in `Source`, which is a file
+/// name, `stream(Stream)` or `string(Text)`, as `json_read/2` would read the same data as
+/// JSON, or as `json_read_dict/2` with the option `value_string_as(string)`. A source of many
+/// documents is read as a list of them.
+pub(super) fn load_yaml(env: &Environment, source: &Term, t: &Term, options: &Term) -> Vec<Branch> {
+    let style = Style::of(env, options);
+
+    let read = read_source(&env.substitute_term(source), |input| {
+        let mut text = String::new();
+        input.read_to_string(&mut text).ok().map(|_| text)
+    });
+
+    let docs = match read {
+        Ok(Some(text)) => YamlLoader::load_from_str(&text).ok(),
+        Ok(None) => None,
+        Err(formal) => return throw(env, formal),
+    };
+
+    match docs.as_deref() {
+        Some([]) => unify(env, t, &style.constant("null")),
+        Some([doc]) => unify(env, t, &term(doc, style)),
+        Some(docs) => {
+            let docs = docs.iter().map(|doc| term(doc, style)).collect();
+            unify(env, t, &Term::list(docs, Term::nil()))
+        }
+        None => {
+            let yaml = Term::Atom(Atom::new("yaml", vec![atom("illegal_yaml")]));
+            throw(env, Term::Atom(Atom::new("syntax_error", vec![yaml])))
+        }
+    }
+}
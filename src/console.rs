@@ -0,0 +1,149 @@
+//! What a query run by `solve_with_console` reports its answers to, and asks whether to look for
+//! more: the toplevel on a terminal, a list kept for a caller, or anything a program embedding
+//! the solver implements.
+
+use std::io::Write;
+
+/// The side of a query that receives its outcome.
+pub trait Console {
+    /// Reports an answer, written as `X = 1` or `Yes`. `last` is true when there are no other
+    /// answers to look for.
+    fn answer(&mut self, answer: &str, last: bool);
+
+    /// Asks whether to look for another answer after the one just reported.
+    fn more(&mut self) -> bool;
+
+    /// Reports that the query has no answer.
+    fn no(&mut self);
+
+    /// Reports an exception the query did not catch, written as `Unhandled exception: Ball` or,
+    /// for an interrupted query, `% Execution Aborted`.
+    fn exception(&mut self, message: &str);
+}
+
+/// Writes to standard output and reads a line for each question, where `;` asks for another
+/// answer.
+#[derive(Debug, Default)]
+pub struct Stdio;
+
+impl Stdio {
+    fn write(&mut self, text: &str) {
+        print!("{}", text);
+        std::io::stdout().flush().expect("Could not flush stdout");
+    }
+}
+
+impl Console for Stdio {
+    fn answer(&mut self, answer: &str, last: bool) {
+        if last {
+            self.write(&format!("\n{}.\n", answer));
+        } else {
+            self.write(&format!("\n{} ", answer));
+        }
+    }
+
+    fn more(&mut self) -> bool {
+        let mut input_buffer = String::new();
+        std::io::stdin()
+            .read_line(&mut input_buffer)
+            .expect("error reading input");
+
+        matches!(&input_buffer[..], ";\r\n" | ";\n")
+    }
+
+    fn no(&mut self) {
+        self.write("\nNo.\n");
+    }
+
+    fn exception(&mut self, message: &str) {
+        let end = if message.starts_with('%') { "" } else { "." };
+        self.write(&format!("\n{}{}\n", message, end));
+    }
+}
+
+/// Writes as `Stdio` does, but reads a single key press for each question: `;`, `n`, `r`, space
+/// or tab ask for another answer, and any other key stops.
+#[cfg(feature = "tui")]
+#[derive(Debug, Default)]
+pub struct Terminal;
+
+#[cfg(feature = "tui")]
+impl Console for Terminal {
+    fn answer(&mut self, answer: &str, last: bool) {
+        Stdio.answer(answer, last)
+    }
+
+    fn more(&mut self) -> bool {
+        use crossterm::event::{read, Event, KeyCode, KeyEventKind};
+        use crossterm::terminal;
+
+        if terminal::enable_raw_mode().is_err() {
+            return Stdio.more();
+        }
+
+        let more = loop {
+            match read() {
+                Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => {
+                    break matches!(
+                        key.code,
+                        KeyCode::Char(';' | 'n' | 'r' | ' ') | KeyCode::Tab
+                    );
+                }
+                Ok(_) => continue,
+                Err(_) => break false,
+            }
+        };
+
+        let _ = terminal::disable_raw_mode();
+        Stdio.write(if more { ";\n" } else { ".\n" });
+        more
+    }
+
+    fn no(&mut self) {
+        Stdio.no()
+    }
+
+    fn exception(&mut self, message: &str) {
+        Stdio.exception(message)
+    }
+}
+
+/// Keeps every answer, followed by `No` if there are none, or by the message of the exception
+/// that ended the query.
+#[derive(Debug, Default)]
+pub struct Collect {
+    pub answers: Vec<String>,
+}
+
+impl Console for Collect {
+    fn answer(&mut self, answer: &str, _last: bool) {
+        self.answers.push(String::from(answer));
+    }
+
+    fn more(&mut self) -> bool {
+        true
+    }
+
+    fn no(&mut self) {
+        self.answers.push(String::from("No"));
+    }
+
+    fn exception(&mut self, message: &str) {
+        self.answers.push(String::from(message));
+    }
+}
+
+/// The console of an interactive toplevel, which reads key presses when the crate is built with
+/// the `tui` feature and standard input is a terminal.
+pub fn toplevel() -> Box<dyn Console> {
+    #[cfg(feature = "tui")]
+    {
+        use std::io::IsTerminal;
+
+        if std::io::stdin().is_terminal() {
+            return Box::new(Terminal);
+        }
+    }
+
+    Box::new(Stdio)
+}
@@ -0,0 +1,210 @@
+//! Conversions between Rust values and terms, as implemented for structs and enums by
+//! `#[derive(PrologTerm)]`.
+//!
+//! A struct `Point { x: i64, y: i64 }` is the term `point(X, Y)`, with the arguments in the order
+//! the fields are declared, and a unit struct or a variant without fields is an atom. The name is
+//! the type or variant name in snake case, unless `#[prolog(name = "...")]` gives another.
+
+use crate::ast::{Atom, Number, Term};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[cfg(feature = "macros")]
+pub use bfg_prolog_macros::PrologTerm;
+
+/// A value that can be written as a term.
+pub trait ToTerm {
+    fn to_term(&self) -> Term;
+}
+
+/// A value that can be read back from a term.
+pub trait FromTerm: Sized {
+    fn from_term(t: &Term) -> Result<Self, TermError>;
+}
+
+/// The error for a term that does not encode a value of the type asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermError {
+    pub expected: String,
+    pub found: Term,
+}
+
+impl TermError {
+    pub fn new(expected: &str, found: &Term) -> Self {
+        TermError {
+            expected: String::from(expected),
+            found: found.clone(),
+        }
+    }
+}
+
+impl Display for TermError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "expected {}, found {}", self.expected, self.found)
+    }
+}
+
+impl Error for TermError {}
+
+/// The arguments of `t` if it is an atom or compound term named `name` with `arity` arguments.
+pub fn functor_args<'a>(t: &'a Term, name: &str, arity: usize) -> Option<&'a [Term]> {
+    match t {
+        Term::Atom(a) if a.name.0 == name && a.arity == arity => Some(&a.args),
+        _ => None,
+    }
+}
+
+impl ToTerm for Term {
+    fn to_term(&self) -> Term {
+        self.clone()
+    }
+}
+
+impl FromTerm for Term {
+    fn from_term(t: &Term) -> Result<Self, TermError> {
+        Ok(t.clone())
+    }
+}
+
+macro_rules! integer {
+    ($($t:ty),*) => {
+        $(
+            impl ToTerm for $t {
+                fn to_term(&self) -> Term {
+                    Term::Number(Number::Int(*self as i64))
+                }
+            }
+
+            impl FromTerm for $t {
+                fn from_term(t: &Term) -> Result<Self, TermError> {
+                    match t {
+                        Term::Number(Number::Int(i)) => {
+                            <$t>::try_from(*i).map_err(|_| TermError::new(stringify!($t), t))
+                        }
+                        _ => Err(TermError::new(stringify!($t), t)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer!(i8, i16, i32, i64, u8, u16, u32, usize);
+
+impl ToTerm for f64 {
+    fn to_term(&self) -> Term {
+        Term::Number(Number::Float(*self))
+    }
+}
+
+impl FromTerm for f64 {
+    fn from_term(t: &Term) -> Result<Self, TermError> {
+        match t {
+            Term::Number(Number::Float(f)) => Ok(*f),
+            Term::Number(Number::Int(i)) => Ok(*i as f64),
+            _ => Err(TermError::new("f64", t)),
+        }
+    }
+}
+
+impl ToTerm for bool {
+    fn to_term(&self) -> Term {
+        Term::Atom(Atom::new(if *self { "true" } else { "false" }, vec![]))
+    }
+}
+
+impl FromTerm for bool {
+    fn from_term(t: &Term) -> Result<Self, TermError> {
+        if functor_args(t, "true", 0).is_some() {
+            Ok(true)
+        } else if functor_args(t, "false", 0).is_some() {
+            Ok(false)
+        } else {
+            Err(TermError::new("bool", t))
+        }
+    }
+}
+
+/// A string is written as a Prolog string, and read back from a string or an atom.
+impl ToTerm for String {
+    fn to_term(&self) -> Term {
+        Term::String(self.clone())
+    }
+}
+
+impl FromTerm for String {
+    fn from_term(t: &Term) -> Result<Self, TermError> {
+        match t {
+            Term::String(s) => Ok(s.clone()),
+            Term::Atom(a) if a.arity == 0 && !t.is_nil() => Ok(a.name.0.clone()),
+            _ => Err(TermError::new("String", t)),
+        }
+    }
+}
+
+impl<T: ToTerm> ToTerm for Vec<T> {
+    fn to_term(&self) -> Term {
+        Term::list(self.iter().map(ToTerm::to_term).collect(), Term::nil())
+    }
+}
+
+impl<T: FromTerm> FromTerm for Vec<T> {
+    fn from_term(t: &Term) -> Result<Self, TermError> {
+        let mut items = Vec::new();
+        let mut rest = t;
+
+        loop {
+            match rest {
+                Term::PartialString(text, tail) => {
+                    for c in text.chars() {
+                        items.push(T::from_term(&Term::Number(Number::Int(c as i64)))?);
+                    }
+
+                    rest = tail;
+                }
+                Term::Atom(a) if a.name.0 == "." && a.arity == 2 => {
+                    items.push(T::from_term(&a.args[0])?);
+                    rest = &a.args[1];
+                }
+                _ if rest.is_nil() => return Ok(items),
+                _ => return Err(TermError::new("list", t)),
+            }
+        }
+    }
+}
+
+/// `None` is the atom `none` and `Some(x)` the term `some(X)`.
+impl<T: ToTerm> ToTerm for Option<T> {
+    fn to_term(&self) -> Term {
+        match self {
+            Some(x) => Term::Atom(Atom::new("some", vec![x.to_term()])),
+            None => Term::Atom(Atom::new("none", vec![])),
+        }
+    }
+}
+
+impl<T: FromTerm> FromTerm for Option<T> {
+    fn from_term(t: &Term) -> Result<Self, TermError> {
+        if functor_args(t, "none", 0).is_some() {
+            return Ok(None);
+        }
+
+        match functor_args(t, "some", 1) {
+            Some(args) => Ok(Some(T::from_term(&args[0])?)),
+            None => Err(TermError::new("option", t)),
+        }
+    }
+}
+
+impl<T: ToTerm> ToTerm for Box<T> {
+    fn to_term(&self) -> Term {
+        (**self).to_term()
+    }
+}
+
+impl<T: FromTerm> FromTerm for Box<T> {
+    fn from_term(t: &Term) -> Result<Self, TermError> {
+        T::from_term(t).map(Box::new)
+    }
+}
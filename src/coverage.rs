@@ -0,0 +1,116 @@
+//! Which clauses the goals run while coverage is recorded enter, as `show_coverage/1` and
+//! `bfg-prolog --coverage` report it.
+
+use crate::ast::Assertion;
+use crate::library;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+thread_local! {
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+    static ENTERED: RefCell<HashSet<Assertion>> = RefCell::new(HashSet::new());
+}
+
+/// How many of the clauses of a predicate were entered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    pub name: String,
+    pub arity: usize,
+    pub clauses: usize,
+    pub entered: usize,
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}/{}: {}",
+            self.name,
+            self.arity,
+            entered(self.entered, self.clauses)
+        )
+    }
+}
+
+/// How many of the clauses of all the predicates of `report` were entered.
+pub fn summary(report: &[Coverage]) -> String {
+    entered(
+        report.iter().map(|c| c.entered).sum(),
+        report.iter().map(|c| c.clauses).sum(),
+    )
+}
+
+fn entered(entered: usize, clauses: usize) -> String {
+    let percent = if clauses == 0 {
+        100.0
+    } else {
+        100.0 * entered as f64 / clauses as f64
+    };
+    let noun = if clauses == 1 { "clause" } else { "clauses" };
+
+    format!(
+        "{} of {} {} entered ({:.1}%)",
+        entered, clauses, noun, percent
+    )
+}
+
+/// Starts recording the clauses entered on this thread, forgetting those recorded before.
+pub fn start() {
+    ENTERED.with(|entered| entered.borrow_mut().clear());
+    RECORDING.with(|recording| recording.set(true));
+}
+
+/// Stops recording, giving back the clauses entered since `start`.
+pub fn stop() -> HashSet<Assertion> {
+    RECORDING.with(|recording| recording.set(false));
+    ENTERED.with(|entered| std::mem::take(&mut *entered.borrow_mut()))
+}
+
+/// Records that the clause `a` was entered, if coverage is being recorded.
+pub(crate) fn enter(a: &Assertion) {
+    if RECORDING.with(Cell::get) {
+        ENTERED.with(|entered| {
+            if !entered.borrow().contains(a) {
+                entered.borrow_mut().insert(a.clone());
+            }
+        });
+    }
+}
+
+/// How many of the clauses of each predicate of `kb` are among `entered`, in the order the program
+/// first defines them. Directives and the clauses of the library are left out.
+pub fn report(kb: &[Assertion], entered: &HashSet<Assertion>) -> Vec<Coverage> {
+    let mut report: Vec<Coverage> = Vec::new();
+
+    // Clauses are kept last first, so they are read from the end.
+    for a in library::without_library(kb).iter().rev() {
+        if a.head.name.0 == ":-" && a.head.arity == 1 {
+            continue;
+        }
+
+        let i = match report
+            .iter()
+            .position(|c| c.name == a.head.name.0 && c.arity == a.head.arity)
+        {
+            Some(i) => i,
+            None => {
+                report.push(Coverage {
+                    name: a.head.name.0.clone(),
+                    arity: a.head.arity,
+                    clauses: 0,
+                    entered: 0,
+                });
+                report.len() - 1
+            }
+        };
+
+        report[i].clauses += 1;
+
+        if entered.contains(a) {
+            report[i].entered += 1;
+        }
+    }
+
+    report
+}
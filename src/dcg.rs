@@ -0,0 +1,111 @@
+use crate::ast::{goals, op, Assertion, Atom, Const, Term, Var};
+
+/// Translates the grammar rule `head --> body`, where `head` may carry a pushback list as in
+/// `head, pushback --> body`, into a clause threading a difference list through the body.
+pub fn translate(head: &Term, body: &Term) -> Option<Assertion> {
+    let mut vars = 0;
+    let s0 = fresh(&mut vars);
+    let s = fresh(&mut vars);
+
+    let (head, pushback) = match head {
+        Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) if name == "," && args.len() == 2 => (&args[0], Some(&args[1])),
+        head => (head, None),
+    };
+
+    let head = match head {
+        Term::Atom(head) => head,
+        _ => return None,
+    };
+
+    let body = match pushback {
+        None => translate_body(body, s0.clone(), s.clone(), &mut vars),
+        Some(pushback) => {
+            let mid = fresh(&mut vars);
+            let body = translate_body(body, s0.clone(), mid.clone(), &mut vars);
+
+            op(",", body, op("=", s.clone(), terminals(pushback, mid)?))
+        }
+    };
+
+    Some(Assertion::new(extend(head, s0, s), goals(body)))
+}
+
+fn fresh(vars: &mut usize) -> Term {
+    *vars += 1;
+    Term::Var(Var::new(&format!("S#{}", vars), 0))
+}
+
+fn translate_body(body: &Term, s0: Term, s: Term, vars: &mut usize) -> Term {
+    let (name, args) = match body {
+        Term::Var(_) => return Term::Atom(Atom::new("call", vec![body.clone(), s0, s])),
+        Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) => (&name[..], &args[..]),
+        _ => match terminals(body, s.clone()) {
+            Some(list) => return op("=", s0, list),
+            None => return Term::Atom(Atom::new("fail", vec![])),
+        },
+    };
+
+    match (name, args) {
+        (",", [a, b]) => {
+            let mid = fresh(vars);
+            let a = translate_body(a, s0, mid.clone(), vars);
+            op(",", a, translate_body(b, mid, s, vars))
+        }
+        (";", [a, b]) => {
+            let a = translate_body(a, s0.clone(), s.clone(), vars);
+            op(";", a, translate_body(b, s0, s, vars))
+        }
+        ("->", [a, b]) => {
+            let mid = fresh(vars);
+            let a = translate_body(a, s0, mid.clone(), vars);
+            op("->", a, translate_body(b, mid, s, vars))
+        }
+        ("\\+", [a]) => {
+            let a = translate_body(a, s0.clone(), fresh(vars), vars);
+            op(",", Term::Atom(Atom::new("\\+", vec![a])), op("=", s0, s))
+        }
+        ("{}", [goal]) => op(",", goal.clone(), op("=", s0, s)),
+        ("!", []) => op(",", body.clone(), op("=", s0, s)),
+        _ => match terminals(body, s.clone()) {
+            Some(list) => op("=", s0, list),
+            None => match body {
+                Term::Atom(a) => Term::Atom(extend(a, s0, s)),
+                _ => unreachable!(),
+            },
+        },
+    }
+}
+
+fn extend(a: &Atom, s0: Term, s: Term) -> Atom {
+    let mut args = a.args.clone();
+    args.push(s0);
+    args.push(s);
+
+    Atom::new(&a.name.0, args)
+}
+
+/// Appends `tail` to the list, string or code list `list`, or returns None if `list` is none of
+/// these.
+fn terminals(list: &Term, tail: Term) -> Option<Term> {
+    match list {
+        Term::String(text) => Some(Term::partial_string(text, tail)),
+        Term::PartialString(text, rest) => Some(Term::partial_string(text, terminals(rest, tail)?)),
+        Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) if name == "." && args.len() == 2 => {
+            Some(Term::cons(args[0].clone(), terminals(&args[1], tail)?))
+        }
+        t if t.is_nil() => Some(tail),
+        _ => None,
+    }
+}
@@ -0,0 +1,138 @@
+//! Predicates declared `:- det(Name/Arity)`, which must succeed exactly once without leaving a
+//! choicepoint behind.
+
+use crate::ast::{Assertion, Atom, Const, Number, Term};
+use crate::{fresh, position, renumber_atom, Choicepoint, Environment};
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+type Key = (Const, usize);
+
+thread_local! {
+    static DECLARED: RefCell<HashSet<Key>> = RefCell::new(HashSet::new());
+}
+
+fn declare(spec: &Term, declared: &mut HashSet<Key>) {
+    if let Term::Atom(Atom { name, args, .. }) = spec {
+        match (&name.0[..], &args[..]) {
+            (",", [x, y]) => {
+                declare(x, declared);
+                declare(y, declared);
+            }
+            ("/", [Term::Atom(name), Term::Number(Number::Int(arity))])
+                if name.arity == 0 && *arity >= 0 =>
+            {
+                declared.insert((name.name.clone(), *arity as usize));
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The predicates that the `:- det(Spec)` directives of `kb` declare.
+fn declarations(kb: &[Assertion]) -> HashSet<Key> {
+    let mut declared = HashSet::new();
+
+    for a in kb {
+        if let (":-", [Term::Atom(directive)]) = (&a.head.name.0[..], &a.head.args[..]) {
+            if directive.name.0 == "det" && directive.arity == 1 {
+                declare(&directive.args[0], &mut declared);
+            }
+        }
+    }
+
+    declared
+}
+
+/// Reads the `:- det(Spec)` declarations of `kb`, where `Spec` is a `Name/Arity` indicator or a
+/// conjunction of them.
+pub(crate) fn reset(kb: &[Assertion]) {
+    let declared = declarations(kb);
+    DECLARED.with(|d| *d.borrow_mut() = declared);
+}
+
+/// Whether the predicate `goal` calls is declared `det`.
+pub(crate) fn is_det(goal: &Atom) -> bool {
+    DECLARED.with(|d| d.borrow().contains(&(goal.name.clone(), goal.arity)))
+}
+
+/// Whether backtracking into `choicepoint` can only fail, as it does when it holds the clauses
+/// left to try for a goal and none of their heads match it.
+fn exhausted(choicepoint: &Choicepoint) -> bool {
+    let (assertions, goal) = match (&choicepoint.assertions, choicepoint.clause.last()) {
+        (Some(assertions), Some(goal)) => (assertions, goal),
+        _ => return false,
+    };
+
+    !assertions.iter().any(|b| {
+        b.head.name == goal.name
+            && b.head.arity == goal.arity
+            && choicepoint
+                .environment
+                .unify_atoms(goal, &renumber_atom(fresh(), &b.head))
+                .is_ok()
+    })
+}
+
+/// Checks the exit of a call to a `det` predicate, which pushed the choicepoint numbered
+/// `barrier` to raise an error if it fails. Drops the choicepoints left above it that can only
+/// fail, and then that one if none are left, giving back whether any are.
+pub(crate) fn exit(ch: &mut Vec<Choicepoint>, barrier: usize) -> bool {
+    let at = position(ch, barrier);
+    // A search strategy may have gone back to that choicepoint already.
+    let above = match ch.get(at) {
+        Some(c) if c.id == barrier => at + 1,
+        _ => at,
+    };
+
+    while ch.len() > above && ch.last().is_some_and(exhausted) {
+        ch.pop();
+    }
+
+    if ch.len() > above {
+        return false;
+    }
+
+    ch.truncate(at);
+    true
+}
+
+/// Warns of the clauses of the predicates declared `det` in `kb` that leave a choicepoint behind,
+/// as one does when it does not cut and a later clause has a head that matches its own.
+pub(crate) fn warnings(kb: &[Assertion]) -> Vec<String> {
+    let declared = declarations(kb);
+    let mut warnings = Vec::new();
+
+    // Clauses are kept last first, so they are read from the end.
+    let clauses: Vec<_> = kb.iter().rev().collect();
+
+    for (i, a) in clauses.iter().enumerate() {
+        if !declared.contains(&(a.head.name.clone(), a.head.arity)) {
+            continue;
+        }
+
+        let cuts = a.clause.iter().any(|g| g.name.0 == "!" && g.arity == 0);
+        let head = renumber_atom(fresh(), &a.head);
+        let overlaps = clauses[i + 1..].iter().any(|b| {
+            b.head.name == a.head.name
+                && b.head.arity == a.head.arity
+                && Environment::new()
+                    .unify_atoms(&head, &renumber_atom(fresh(), &b.head))
+                    .is_ok()
+        });
+
+        if !cuts && overlaps {
+            let number = clauses[..=i]
+                .iter()
+                .filter(|b| b.head.name == a.head.name && b.head.arity == a.head.arity)
+                .count();
+
+            warnings.push(format!(
+                "{}/{} is declared det, but clause {} may leave a choicepoint",
+                a.head.name.0, a.head.arity, number
+            ));
+        }
+    }
+
+    warnings
+}
@@ -0,0 +1,281 @@
+//! Structured comments that document predicates, written before them as
+//!
+//! ```prolog
+//! %! len(+List, -Length) is det.
+//! %
+//! %  True when Length is the number of elements of List.
+//! ```
+//!
+//! with a `%!` line for each mode of the predicate followed by `%` lines describing it. The loader
+//! keeps each as a `:- '$doc'(Name/Arity, Modes, Description)` directive of the program, which
+//! `help/1` and `bfg-prolog --doc` read back.
+
+use crate::ast::{Assertion, Atom, Number, Term};
+use crate::builtins::list_items;
+use crate::tokenizer::{tokenize, Token, TokenKind};
+
+/// The documentation of a predicate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Doc {
+    pub name: String,
+    pub arity: usize,
+    /// The modes of the predicate, such as `len(+List, -Length) is det.`
+    pub modes: Vec<String>,
+    /// The text after the modes, with the indentation its lines share taken off.
+    pub description: String,
+}
+
+impl Doc {
+    /// The directive that keeps the documentation in a program.
+    fn to_assertion(&self) -> Assertion {
+        let atom = |text: &str| Term::Atom(Atom::new(text, vec![]));
+        let indicator = Term::Atom(Atom::new(
+            "/",
+            vec![
+                atom(&self.name),
+                Term::Number(Number::Int(self.arity as i64)),
+            ],
+        ));
+        let modes = self.modes.iter().map(|mode| atom(mode)).collect();
+        let doc = Atom::new(
+            "$doc",
+            vec![
+                indicator,
+                Term::list(modes, Term::nil()),
+                atom(&self.description),
+            ],
+        );
+
+        Assertion {
+            head: Atom::new(":-", vec![Term::Atom(doc)]),
+            clause: vec![],
+        }
+    }
+
+    /// The documentation that the directive `a` keeps, if it is a `'$doc'/3` directive.
+    fn from_assertion(a: &Assertion) -> Option<Doc> {
+        let doc = match (&a.head.name.0[..], &a.head.args[..]) {
+            (":-", [Term::Atom(doc)]) if doc.name.0 == "$doc" && doc.arity == 3 => doc,
+            _ => return None,
+        };
+        let text = |t: &Term| match t {
+            Term::Atom(a) if a.arity == 0 => Some(a.name.0.clone()),
+            _ => None,
+        };
+        let (name, arity) = match &doc.args[0] {
+            Term::Atom(a) if a.name.0 == "/" && a.arity == 2 => match &a.args[1] {
+                Term::Number(Number::Int(arity)) => (text(&a.args[0])?, *arity as usize),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(Doc {
+            name,
+            arity,
+            modes: list_items(&doc.args[1])?
+                .iter()
+                .map(text)
+                .collect::<Option<_>>()?,
+            description: text(&doc.args[2])?,
+        })
+    }
+
+    /// The documentation as `help/1` writes it: the modes, then the description indented.
+    pub fn help(&self) -> String {
+        let mut help = self.modes.join("\n");
+        help.push('\n');
+
+        if !self.description.is_empty() {
+            help.push('\n');
+
+            for line in self.description.lines() {
+                if !line.is_empty() {
+                    help.push_str("    ");
+                    help.push_str(line);
+                }
+
+                help.push('\n');
+            }
+        }
+
+        help
+    }
+}
+
+/// The name and arity of the predicate the mode `mode` is of, as `foo/2` for `foo(+X, -Y) is det.`
+fn indicator(mode: &str) -> Option<(String, usize)> {
+    let mode = mode.trim();
+    let end = mode
+        .find(|c: char| c == '(' || c.is_whitespace())
+        .unwrap_or_else(|| mode.trim_end_matches('.').len());
+    let name = &mode[..end];
+
+    if name.is_empty() {
+        return None;
+    }
+
+    if !mode[end..].starts_with('(') {
+        return Some((String::from(name), 0));
+    }
+
+    let mut depth = 0;
+    let mut arity = 1;
+    let mut quote = None;
+
+    for c in mode[end..].chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some((String::from(name), arity));
+                }
+            }
+            (None, ',') if depth == 1 => arity += 1,
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// The documentation of the block of line comments `block`, which starts with its `%!` lines.
+fn read_block(block: &[&Token]) -> Option<Doc> {
+    let lines: Vec<_> = block.iter().map(|token| &token.text[1..]).collect();
+    let count = lines
+        .iter()
+        .take_while(|line| line.starts_with('!'))
+        .count();
+    let modes: Vec<String> = lines[..count]
+        .iter()
+        .map(|line| line[1..].trim().to_string())
+        .collect();
+    let (name, arity) = indicator(&modes[0])?;
+
+    // The lines describing it lose the indentation they all have.
+    let rest = &lines[count..];
+    let indent = rest
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let description: Vec<_> = rest
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect();
+
+    Some(Doc {
+        name,
+        arity,
+        modes,
+        description: description.join("\n").trim().to_string(),
+    })
+}
+
+/// The `'$doc'/3` directives of the structured comments of `text`, kept last first as the clauses
+/// of a program are. A structured comment is a run of line comments on lines that follow each
+/// other, starting with one or more `%!` lines.
+pub(crate) fn comments(text: &str) -> Vec<Assertion> {
+    let tokens = match tokenize(text) {
+        Ok(tokens) => tokens,
+        Err(_) => return Vec::new(),
+    };
+    let line_comments: Vec<_> = tokens
+        .iter()
+        .filter(|token| token.kind == TokenKind::Comment && token.text.starts_with('%'))
+        .collect();
+    let mut blocks: Vec<Vec<&Token>> = Vec::new();
+
+    for token in line_comments {
+        let follows = blocks
+            .last()
+            .and_then(|block| block.last())
+            .is_some_and(|last| {
+                // A `%!` line after the lines describing a predicate starts the next block.
+                let same_block = last.text.starts_with("%!") || !token.text.starts_with("%!");
+                last.line + 1 == token.line && same_block
+            });
+
+        match blocks.last_mut() {
+            Some(block) if follows => block.push(token),
+            _ => blocks.push(vec![token]),
+        }
+    }
+
+    blocks
+        .iter()
+        .filter(|block| block[0].text.starts_with("%!"))
+        .filter_map(|block| read_block(block))
+        .map(|doc| doc.to_assertion())
+        .rev()
+        .collect()
+}
+
+/// The documentation the program `kb` keeps, in the order it has it.
+pub fn docs(kb: &[Assertion]) -> Vec<Doc> {
+    // Clauses are kept last first, so they are read from the end.
+    kb.iter().rev().filter_map(Doc::from_assertion).collect()
+}
+
+/// The documentation of the program `kb` as a Markdown document.
+pub fn markdown(kb: &[Assertion]) -> String {
+    let mut markdown = String::from("# Predicates\n");
+
+    for doc in docs(kb) {
+        markdown.push_str(&format!(
+            "\n## `{}/{}`\n\n```prolog\n{}\n```\n",
+            doc.name,
+            doc.arity,
+            doc.modes.join("\n")
+        ));
+
+        if !doc.description.is_empty() {
+            markdown.push_str(&format!("\n{}\n", doc.description));
+        }
+    }
+
+    markdown
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The documentation of the program `kb` as an HTML page, with each paragraph of a description a
+/// paragraph of its own.
+pub fn html(kb: &[Assertion]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Predicates</title>\n</head>\n<body>\n<h1>Predicates</h1>\n",
+    );
+
+    for doc in docs(kb) {
+        let indicator = escape(&format!("{}/{}", doc.name, doc.arity));
+
+        html.push_str(&format!(
+            "<h2 id=\"{0}\">{0}</h2>\n<pre>{1}</pre>\n",
+            indicator,
+            escape(&doc.modes.join("\n"))
+        ));
+
+        for paragraph in doc
+            .description
+            .split("\n\n")
+            .filter(|p| !p.trim().is_empty())
+        {
+            html.push_str(&format!("<p>{}</p>\n", escape(paragraph.trim())));
+        }
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
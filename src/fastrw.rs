@@ -0,0 +1,177 @@
+use crate::ast::{Atom, Number, Term, Var};
+use std::collections::HashMap;
+use std::io::Read;
+
+/// The bytes every encoded term starts with, the last of which is the version of the format.
+const MAGIC: [u8; 3] = [0xBF, b'T', 1];
+
+const VAR: u8 = 0;
+const INT: u8 = 1;
+const FLOAT: u8 = 2;
+const ATOM: u8 = 3;
+const STRING: u8 = 4;
+const PARTIAL_STRING: u8 = 5;
+const CONST: u8 = 6;
+
+struct Encoder {
+    bytes: Vec<u8>,
+    vars: HashMap<Var, u64>,
+}
+
+impl Encoder {
+    fn number(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.bytes.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+
+        self.bytes.push(n as u8);
+    }
+
+    fn text(&mut self, s: &str) {
+        self.number(s.len() as u64);
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    fn term(&mut self, t: &Term) {
+        match t {
+            Term::Var(v) => {
+                let next = self.vars.len() as u64;
+                let n = *self.vars.entry(v.clone()).or_insert(next);
+                self.bytes.push(VAR);
+                self.number(n);
+            }
+            Term::Number(Number::Int(i)) => {
+                self.bytes.push(INT);
+                self.bytes.extend_from_slice(&i.to_le_bytes());
+            }
+            Term::Number(Number::Float(f)) => {
+                self.bytes.push(FLOAT);
+                self.bytes.extend_from_slice(&f.to_le_bytes());
+            }
+            Term::Atom(a) => {
+                self.bytes.push(ATOM);
+                self.text(&a.name.0);
+                self.number(a.args.len() as u64);
+
+                for arg in &a.args {
+                    self.term(arg);
+                }
+            }
+            Term::String(s) => {
+                self.bytes.push(STRING);
+                self.text(s);
+            }
+            Term::PartialString(s, tail) => {
+                self.bytes.push(PARTIAL_STRING);
+                self.text(s);
+                self.term(tail);
+            }
+        }
+    }
+}
+
+/// Encodes `t` in the compact binary format read back by `decode` and `fast_read/2`. Variables
+/// are numbered in the order they first appear, so that the ones shared stay shared.
+pub fn encode(t: &Term) -> Vec<u8> {
+    let mut encoder = Encoder {
+        bytes: MAGIC.to_vec(),
+        vars: HashMap::new(),
+    };
+
+    encoder.term(t);
+    encoder.bytes
+}
+
+struct Decoder<'a> {
+    input: &'a mut dyn Read,
+}
+
+impl<'a> Decoder<'a> {
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let mut buf = [0; N];
+        self.input.read_exact(&mut buf).ok()?;
+        Some(buf)
+    }
+
+    fn number(&mut self) -> Option<u64> {
+        let mut n = 0;
+
+        for shift in (0..64).step_by(7) {
+            let [b] = self.bytes()?;
+            n |= u64::from(b & 0x7F) << shift;
+
+            if b < 0x80 {
+                return Some(n);
+            }
+        }
+
+        None
+    }
+
+    fn text(&mut self) -> Option<String> {
+        let len = self.number()?;
+        let mut buf = Vec::new();
+        self.input.take(len).read_to_end(&mut buf).ok()?;
+
+        if buf.len() as u64 != len {
+            return None;
+        }
+
+        String::from_utf8(buf).ok()
+    }
+
+    fn term(&mut self) -> Option<Term> {
+        let [tag] = self.bytes()?;
+
+        Some(match tag {
+            VAR => Term::Var(Var(format!("_F{}", self.number()?), 0)),
+            INT => Term::Number(Number::Int(i64::from_le_bytes(self.bytes()?))),
+            FLOAT => Term::Number(Number::Float(f64::from_le_bytes(self.bytes()?))),
+            ATOM => {
+                let name = self.text()?;
+                let arity = self.number()?;
+                let args = (0..arity)
+                    .map(|_| self.term())
+                    .collect::<Option<Vec<_>>>()?;
+
+                Term::Atom(Atom::new(&name, args))
+            }
+            STRING => Term::String(self.text()?),
+            PARTIAL_STRING => {
+                let s = self.text()?;
+                Term::PartialString(s, Box::new(self.term()?))
+            }
+            // Atoms were once written apart from compound terms, and are still read back.
+            CONST => Term::Atom(Atom::new(&self.text()?, vec![])),
+            _ => return None,
+        })
+    }
+}
+
+/// Reads the next encoded term from `input`, returning `Ok(None)` at the end of the input and
+/// an error of kind `InvalidData` if what comes next is not an encoded term.
+pub fn read(input: &mut dyn Read) -> std::io::Result<Option<Term>> {
+    let mut magic = [0; 3];
+
+    match input.read(&mut magic[..1])? {
+        0 => return Ok(None),
+        _ => input.read_exact(&mut magic[1..])?,
+    }
+
+    let term = match magic {
+        MAGIC => Decoder { input }.term(),
+        _ => None,
+    };
+
+    term.map(Some)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "not a fast term"))
+}
+
+/// Decodes a term encoded by `encode`, returning None if `bytes` hold anything else.
+pub fn decode(mut bytes: &[u8]) -> Option<Term> {
+    match read(&mut bytes) {
+        Ok(Some(t)) if bytes.is_empty() => Some(t),
+        _ => None,
+    }
+}
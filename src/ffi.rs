@@ -0,0 +1,246 @@
+//! A C interface for embedding the interpreter in programs written in other languages, declared
+//! in `include/bfg_prolog.h`.
+//!
+//! ```c
+//! BfgMachine *machine = bfg_machine_new();
+//! bfg_consult(machine, "parent(tom, bob). parent(tom, liz).");
+//!
+//! BfgQuery *query = bfg_query_open(machine, "parent(tom, X).");
+//! while (bfg_query_next(query) == 1) {
+//!     char *x = bfg_query_binding(query, "X");
+//!     puts(x);
+//!     bfg_string_free(x);
+//! }
+//! bfg_query_close(query);
+//! bfg_machine_free(machine);
+//! ```
+//!
+//! Strings are passed as UTF-8 and returned as strings the caller frees with `bfg_string_free`,
+//! except for error messages, which stay owned by the machine or query they belong to.
+
+use crate::loader::consult_into;
+use crate::parser::ClauseParser;
+use crate::search::Search;
+use crate::tokenizer::lex;
+use crate::{Environment, KnowledgeBase};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+/// A program, to which sources are added with `bfg_consult`.
+pub struct BfgMachine {
+    kb: KnowledgeBase,
+    /// Why the last call on the machine failed.
+    error: Option<CString>,
+}
+
+/// The search for the answers to a query, opened with `bfg_query_open`.
+pub struct BfgQuery {
+    search: Search,
+    answer: Option<Environment>,
+    /// The exception that ended the search.
+    error: Option<CString>,
+}
+
+/// The text of `s`, which is lost at a NUL character as a C string ends there.
+fn c_string(s: String) -> CString {
+    CString::new(s).unwrap_or_else(|e| {
+        let end = e.nul_position();
+        let mut s = e.into_vec();
+        s.truncate(end);
+        CString::new(s).unwrap_or_default()
+    })
+}
+
+/// Reads the string `s` passed in by the caller.
+///
+/// # Safety
+///
+/// `s` must be null or point to a string ending in a NUL character.
+unsafe fn text<'a>(s: *const c_char) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(String::from("null string"));
+    }
+
+    CStr::from_ptr(s).to_str().map_err(|e| e.to_string())
+}
+
+impl BfgMachine {
+    fn open(&self, goal: &str) -> Result<BfgQuery, String> {
+        let goals = ClauseParser::new()
+            .parse(lex(goal))
+            .map_err(|e| e.to_string())?;
+
+        Ok(BfgQuery {
+            search: Search::new(&self.kb, goals),
+            answer: None,
+            error: None,
+        })
+    }
+}
+
+/// Creates a machine with an empty program.
+#[no_mangle]
+pub extern "C" fn bfg_machine_new() -> *mut BfgMachine {
+    Box::into_raw(Box::new(BfgMachine {
+        kb: Vec::new(),
+        error: None,
+    }))
+}
+
+/// Frees `machine`. The queries opened on it stay usable.
+///
+/// # Safety
+///
+/// `machine` must be null or a machine from `bfg_machine_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bfg_machine_free(machine: *mut BfgMachine) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+/// Adds the clauses of `source` to the program of `machine`, after loading the files it asks for
+/// and running its `initialization/1` goals. Returns 0, or -1 with the reason given by
+/// `bfg_machine_error`.
+///
+/// # Safety
+///
+/// `machine` must be a machine from `bfg_machine_new`, and `source` a string ending in a NUL
+/// character.
+#[no_mangle]
+pub unsafe extern "C" fn bfg_consult(machine: *mut BfgMachine, source: *const c_char) -> c_int {
+    let machine = &mut *machine;
+
+    match text(source).and_then(|source| consult_into(&mut machine.kb, source)) {
+        Ok(()) => {
+            machine.error = None;
+            0
+        }
+        Err(e) => {
+            machine.error = Some(c_string(e));
+            -1
+        }
+    }
+}
+
+/// Why the last call on `machine` failed, or null if it succeeded. The message lasts until the
+/// next call on the machine.
+///
+/// # Safety
+///
+/// `machine` must be a machine from `bfg_machine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn bfg_machine_error(machine: *const BfgMachine) -> *const c_char {
+    (*machine)
+        .error
+        .as_ref()
+        .map_or(ptr::null(), |e| e.as_ptr())
+}
+
+/// Opens the query `goal`, such as `"parent(tom, X)."`, against the program `machine` has now.
+/// Returns null if the query cannot be read, with the reason given by `bfg_machine_error`.
+///
+/// # Safety
+///
+/// `machine` must be a machine from `bfg_machine_new`, and `goal` a string ending in a NUL
+/// character.
+#[no_mangle]
+pub unsafe extern "C" fn bfg_query_open(
+    machine: *mut BfgMachine,
+    goal: *const c_char,
+) -> *mut BfgQuery {
+    let machine = &mut *machine;
+
+    match text(goal).and_then(|goal| machine.open(goal)) {
+        Ok(query) => {
+            machine.error = None;
+            Box::into_raw(Box::new(query))
+        }
+        Err(e) => {
+            machine.error = Some(c_string(e));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Looks for the next answer to `query`. Returns 1 when one is found, 0 when there are no more,
+/// and -1 when the query raised an exception, which `bfg_query_error` then gives.
+///
+/// # Safety
+///
+/// `query` must be a query from `bfg_query_open` that has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn bfg_query_next(query: *mut BfgQuery) -> c_int {
+    let query = &mut *query;
+    query.answer = None;
+
+    match query.search.next() {
+        Ok(Some(env)) => {
+            query.answer = Some(env);
+            1
+        }
+        Ok(None) => 0,
+        Err(ball) => {
+            query.error = Some(c_string(ball.to_string()));
+            -1
+        }
+    }
+}
+
+/// The value the answer `query` is at gives the variable `name` of the query, written as the
+/// toplevel writes it, or null if there is no answer or no such variable. The caller frees the
+/// string with `bfg_string_free`.
+///
+/// # Safety
+///
+/// `query` must be a query from `bfg_query_open` that has not been closed, and `name` a string
+/// ending in a NUL character.
+#[no_mangle]
+pub unsafe extern "C" fn bfg_query_binding(
+    query: *const BfgQuery,
+    name: *const c_char,
+) -> *mut c_char {
+    let query = &*query;
+
+    let binding = match (&query.answer, text(name)) {
+        (Some(env), Ok(name)) => query.search.binding(env, name),
+        _ => None,
+    };
+
+    binding.map_or(ptr::null_mut(), |t| c_string(t.to_string()).into_raw())
+}
+
+/// The exception that ended `query`, or null if it raised none.
+///
+/// # Safety
+///
+/// `query` must be a query from `bfg_query_open` that has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn bfg_query_error(query: *const BfgQuery) -> *const c_char {
+    (*query).error.as_ref().map_or(ptr::null(), |e| e.as_ptr())
+}
+
+/// Closes `query`, ending its search.
+///
+/// # Safety
+///
+/// `query` must be null or a query from `bfg_query_open` that has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn bfg_query_close(query: *mut BfgQuery) {
+    if !query.is_null() {
+        drop(Box::from_raw(query));
+    }
+}
+
+/// Frees a string returned by `bfg_query_binding`.
+///
+/// # Safety
+///
+/// `s` must be null or a string from `bfg_query_binding` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bfg_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,232 @@
+//! The host the solver of the core crate runs in here, which gives it the builtins, the flags and
+//! the state kept for each thread that need `std`.
+
+use crate::ast::{Assertion, Atom, Clause, Number, Term, Var, Written};
+use crate::{
+    builtins, coverage, determinism, fresh, library, modules, parallel, renumber_term, sharing,
+    signals, strategy, tabling, Choicepoint, Environment, KnowledgeBase,
+};
+use bfg_prolog_core::solve::{determinism_error, push_goals, Branch, Host, State};
+use bfg_prolog_core::unify::OccursCheck;
+use std::fmt::Formatter;
+
+#[cfg(feature = "async")]
+use crate::query;
+
+/// The solver with everything this crate adds to it, which `Environment` names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Std {}
+
+impl Host for Std {
+    fn occurs_check() -> OccursCheck {
+        builtins::occurs_check()
+    }
+
+    fn expand(t: &Term) -> Option<Result<Term, Term>> {
+        crate::lazy_list::expand(t)
+    }
+
+    fn write_answer(env: &Environment, f: &mut Formatter) -> std::fmt::Result {
+        let mut bindings: Vec<_> = env
+            .bindings()
+            .iter()
+            .filter(|(x, _)| x.1 == 0 && !x.is_anonymous())
+            .collect();
+        bindings.sort();
+
+        let options = builtins::answer_options();
+        let mut lines: Vec<_> = bindings
+            .iter()
+            .map(|(Var(x, _), t)| format!("{} = {}", x, Written(&sharing::answer(env, t), options)))
+            .collect();
+        lines.extend(builtins::residual_goals(env).iter().map(Term::to_string));
+
+        if lines.is_empty() {
+            write!(f, "Yes")
+        } else {
+            write!(f, "\n{} ", lines.join("\n"))
+        }
+    }
+
+    fn fresh() -> usize {
+        fresh()
+    }
+
+    fn enter() {
+        #[cfg(feature = "async")]
+        query::enter();
+    }
+
+    fn leave() {
+        #[cfg(feature = "async")]
+        query::leave();
+    }
+
+    fn next_goal(goals: &mut Clause) -> Option<Atom> {
+        strategy::next_goal(goals)
+    }
+
+    fn next_choicepoint(ch: &mut Vec<Choicepoint>) -> Option<Choicepoint> {
+        strategy::next_choicepoint(ch)
+    }
+
+    fn interrupt() -> Option<Atom> {
+        signals::pending().or_else(crate::limits::expired)
+    }
+
+    #[cfg(feature = "async")]
+    fn pause() -> bool {
+        query::pause()
+    }
+
+    fn qualify(a: &Atom) -> Option<Atom> {
+        modules::qualify(a)
+    }
+
+    fn local_clauses(a: &Atom) -> Option<KnowledgeBase> {
+        builtins::locals::clauses(a)
+    }
+
+    fn order_clauses(a: &Atom, kb: &[Assertion]) -> Option<KnowledgeBase> {
+        strategy::clauses(a, kb)
+    }
+
+    fn suspend(env: &Environment, a: &Atom) -> bool {
+        if a.name.0 == "engine_yield" && a.arity == 1 && builtins::engines::running() {
+            builtins::engines::yield_term(env.substitute_term(&a.args[0]));
+            return true;
+        }
+
+        false
+    }
+
+    fn control(
+        env: &Environment,
+        kb: &[Assertion],
+        a: &Atom,
+        ch: &mut Vec<Choicepoint>,
+        c: &Clause,
+        n: usize,
+    ) -> Option<Option<State<Std>>> {
+        control(env, kb, a, ch, c, n)
+    }
+
+    fn call(env: &Environment, kb: &[Assertion], a: &Atom, n: usize) -> Option<Vec<Branch<Std>>> {
+        tabling::call(env, kb, a, n)
+            .or_else(|| parallel::call(env, kb, a, n))
+            .or_else(|| builtins::call(env, a))
+    }
+
+    fn admits(depth: usize) -> bool {
+        strategy::admits(depth)
+    }
+
+    fn loops(env: &Environment, a: &Atom, c: &Clause) -> bool {
+        tabling::loops(env, a, c)
+    }
+
+    fn loop_check() -> bool {
+        tabling::loop_check()
+    }
+
+    fn autoload(a: &Atom) -> Option<KnowledgeBase> {
+        library::autoload(a)
+    }
+
+    fn unknown_fails(a: &Atom) -> bool {
+        // The `unknown` flag says whether to raise the error, fail or warn and fail.
+        match builtins::flag("unknown") {
+            unknown if unknown == atom("error") => false,
+            unknown if unknown == atom("warning") => {
+                eprintln!("Warning: Unknown procedure {}/{}", a.name.0, a.arity);
+                true
+            }
+            _ => true,
+        }
+    }
+
+    fn enter_clause(clause: &Assertion) {
+        coverage::enter(clause);
+    }
+
+    fn is_det(a: &Atom) -> bool {
+        determinism::is_det(a)
+    }
+}
+
+fn atom(name: &str) -> Term {
+    Term::Atom(Atom::new(name, vec![]))
+}
+
+/// Runs the control constructs that need more than the core crate has, such as the ones that
+/// search the knowledge base from a builtin or end the process.
+fn control(
+    env: &Environment,
+    kb: &[Assertion],
+    a: &Atom,
+    ch: &mut Vec<Choicepoint>,
+    c: &Clause,
+    n: usize,
+) -> Option<Option<State<Std>>> {
+    let args = &a.args;
+    let proceed = |goals: Clause| Some(Some((env.clone(), push_goals(c.clone(), &goals), n)));
+    // Runs a builtin that needs the knowledge base, which has one branch at most.
+    let run = |branches: Vec<builtins::Branch>, depth| match builtins::in_context(a, branches).pop()
+    {
+        Some((env, goals)) => Some(Some((env, push_goals(c.clone(), &goals), depth))),
+        None => Some(None),
+    };
+
+    match (&a.name.0[..], a.arity) {
+        ("halt", 0) => std::process::exit(0),
+        ("$det_exit", 2) => match (&args[0], &args[1]) {
+            (Term::Number(Number::Int(barrier)), _) if determinism::exit(ch, *barrier as usize) => {
+                proceed(vec![])
+            }
+            (_, Term::Atom(goal)) => proceed(vec![Atom::new(
+                "throw",
+                vec![determinism_error(goal, "nondet")],
+            )]),
+            _ => proceed(vec![]),
+        },
+        (":", 2) => match env.substitute_term(&args[0]) {
+            Term::Atom(module) if module.name.0 == modules::USER && module.arity == 0 => {
+                proceed(vec![Atom::new("call", vec![args[1].clone()])])
+            }
+            _ => None,
+        },
+        ("memo", 1) => {
+            let goal = env.substitute_term(&args[0]);
+
+            match tabling::memo(kb, &goal).and_then(|answer| {
+                env.clone()
+                    .unify_terms(&goal, &renumber_term(fresh(), &answer))
+                    .ok()
+            }) {
+                Some(env) => Some(Some((env, c.clone(), n + 1))),
+                None => Some(None),
+            }
+        }
+        ("qsave_program", 1) => run(builtins::qsave_program(env, kb, &args[0], None), n),
+        ("qsave_program", 2) => run(
+            builtins::qsave_program(env, kb, &args[0], Some(&args[1])),
+            n,
+        ),
+        ("listing", 1) => run(builtins::listing(env, kb, &args[0]), n),
+        ("xref", 0) => run(builtins::xref(env, kb, None), n),
+        ("xref", 1) => run(builtins::xref(env, kb, Some(&args[0])), n),
+        ("$show_coverage", 0) => run(builtins::show_coverage(env, kb), n),
+        ("help", 1) => run(builtins::help(env, kb, &args[0]), n),
+        ("http_server", 2) => run(builtins::http_server(env, kb, &args[0], &args[1]), n),
+        ("engine_next", 2) => run(
+            builtins::engines::engine_next(env, kb, &args[0], &args[1]),
+            n + 1,
+        ),
+        ("concurrent", 3) => run(builtins::concurrent(env, kb, &args[0], &args[1]), n + 1),
+        ("thread_create", 3) => run(
+            builtins::thread_create(env, kb, &args[0], &args[1], &args[2]),
+            n,
+        ),
+        _ => None,
+    }
+}
@@ -0,0 +1,158 @@
+use crate::ast::{Atom, Const, Number, Term};
+use crate::builtins::io_error;
+use std::cell::RefCell;
+use std::collections::hash_map::{Entry, HashMap};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+const NAME: &str = "$lazy_codes";
+const CHUNK_SIZE: usize = 256;
+
+#[derive(Default)]
+struct Files {
+    handles: HashMap<i64, File>,
+    next: i64,
+}
+
+thread_local! {
+    /// The files lazy lists are read from, by the number each list has, open until a list reaches
+    /// the end of its file.
+    static FILES: RefCell<Files> = RefCell::new(Files::default());
+}
+
+/// Builds the code list of the file at `path`, which is read a chunk at a time as unification
+/// walks into its unread part, or gives back the error to raise if it cannot be opened.
+pub(crate) fn file_codes(path: &str) -> Result<Term, Term> {
+    let file = File::open(path).map_err(|e| io_error("input", "source_sink", path, &e))?;
+    let id = FILES.with(|files| {
+        let mut files = files.borrow_mut();
+        let id = files.next;
+        files.next += 1;
+        files.handles.insert(id, file);
+        id
+    });
+
+    Ok(lazy(path, id, 0))
+}
+
+fn lazy(path: &str, id: i64, offset: u64) -> Term {
+    Term::Atom(Atom::new(
+        NAME,
+        vec![
+            Term::Atom(Atom::new(path, vec![])),
+            Term::Number(Number::Int(id)),
+            Term::Number(Number::Int(offset as i64)),
+        ],
+    ))
+}
+
+/// Reads the next chunk of a lazy code list, returning it as a partial string ending in the rest
+/// of the lazy list, or `[]` at the end of the file. Returns None if `t` is not a lazy list, and
+/// the error to raise if the file cannot be read.
+pub(crate) fn expand(t: &Term) -> Option<Result<Term, Term>> {
+    let (path, id, offset) = match t {
+        Term::Atom(Atom {
+            name: Const(name),
+            args,
+            ..
+        }) if name == NAME && args.len() == 3 => match (&args[0], &args[1], &args[2]) {
+            (
+                Term::Atom(path),
+                Term::Number(Number::Int(id)),
+                Term::Number(Number::Int(offset)),
+            ) => (&path.name.0, *id, *offset as u64),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let (chunk, read) = match read_chunk(path, id, offset) {
+        Ok(read) => read,
+        Err(e) => return Some(Err(io_error("input", "source_sink", path, &e))),
+    };
+
+    if read == 0 {
+        FILES.with(|files| files.borrow_mut().handles.remove(&id));
+        return Some(Ok(Term::nil()));
+    }
+
+    let next = lazy(path, id, offset + read as u64);
+    Some(Ok(Term::partial_string(&chunk, next)))
+}
+
+/// Reads the rest of the lazy code list `t` is or ends in, giving back `t` as a list with no lazy
+/// part, or the error to raise if its file cannot be read.
+pub(crate) fn force(t: &Term) -> Result<Term, Term> {
+    let mut text = String::new();
+    let mut items = Vec::new();
+    let mut rest = t.clone();
+
+    loop {
+        rest = match rest {
+            Term::Atom(Atom {
+                name: Const(ref name),
+                ref args,
+                ..
+            }) if name == "." && args.len() == 2 => {
+                items.extend(text.drain(..).map(|c| Term::Number(Number::Int(c as i64))));
+                items.push(args[0].clone());
+                args[1].clone()
+            }
+            Term::PartialString(chunk, tail) => {
+                text.push_str(&chunk);
+                *tail
+            }
+            t => match expand(&t) {
+                Some(expanded) => expanded?,
+                None => {
+                    let tail = Term::partial_string(&text, t);
+                    return Ok(Term::list(items, tail));
+                }
+            },
+        };
+    }
+}
+
+/// Reads the chunk of the file of lazy list `id` that starts at `offset`, opening `path` again if
+/// the list was read to its end before and is now read over again.
+fn read_chunk(path: &str, id: i64, offset: u64) -> std::io::Result<(String, usize)> {
+    FILES.with(|files| {
+        let mut files = files.borrow_mut();
+        let file = match files.handles.entry(id) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(File::open(path)?),
+        };
+
+        file.seek(SeekFrom::Start(offset))?;
+        read_from(file)
+    })
+}
+
+fn read_from(file: &mut File) -> std::io::Result<(String, usize)> {
+    let mut bytes = Vec::with_capacity(CHUNK_SIZE + 3);
+    file.by_ref()
+        .take(CHUNK_SIZE as u64)
+        .read_to_end(&mut bytes)?;
+
+    loop {
+        match String::from_utf8(bytes) {
+            Ok(chunk) => {
+                let read = chunk.len();
+                return Ok((chunk, read));
+            }
+            Err(e)
+                if e.utf8_error().error_len().is_none() && e.as_bytes().len() < CHUNK_SIZE + 3 =>
+            {
+                bytes = e.into_bytes();
+
+                if file.by_ref().take(1).read_to_end(&mut bytes)? == 0 {
+                    return Ok((String::from_utf8_lossy(&bytes).into_owned(), bytes.len()));
+                }
+            }
+            Err(e) => {
+                let bytes = e.into_bytes();
+                return Ok((String::from_utf8_lossy(&bytes).into_owned(), bytes.len()));
+            }
+        }
+    }
+}
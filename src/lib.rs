@@ -1,449 +1,358 @@
-pub mod ast;
-
-use self::ast::{Assertion, Atom, Clause, Const, Term, Var};
+pub use bfg_prolog_core::ast;
+mod builtins;
+pub mod console;
+pub mod convert;
+pub mod coverage;
+pub mod dcg;
+mod determinism;
+pub mod doc;
+pub mod fastrw;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod host;
+mod lazy_list;
+mod library;
+mod limits;
+pub mod loader;
+mod modules;
+mod parallel;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "async")]
+mod query;
+pub mod saved;
+#[cfg(any(feature = "ffi", feature = "python"))]
+mod search;
+mod sharing;
+mod signals;
+pub mod strategy;
+mod tabling;
+pub mod tokenizer;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod xref;
+
+use self::ast::{Assertion, Atom, Clause, Term};
+use bfg_prolog_core::solve::{self, SolveErr};
+use bfg_prolog_core::solve::{position, replace_cut, solve_all};
+use bfg_prolog_core::unify::{renumber_atom, renumber_term, term_vars, UnifyErr};
+#[cfg(feature = "macros")]
+pub use bfg_prolog_macros::prolog;
+pub use console::Console;
+pub use host::Std;
 use lalrpop_util::lalrpop_mod;
+pub use limits::QueryHandle;
+#[cfg(feature = "async")]
+pub use query::{query_async, Answers};
+use std::cell::Cell;
 use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
-use std::io::Write;
+use std::time::Duration;
 
-lalrpop_mod!(pub parser);
+lalrpop_mod!(#[allow(clippy::all, unused_parens)] pub parser);
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Environment(HashMap<Var, Term>);
+/// The bindings of a search, with the builtins and state of this crate behind it.
+///
+/// cbindgen:ignore
+pub type Environment = bfg_prolog_core::unify::Environment<Std>;
+/// cbindgen:ignore
+type Choicepoint = solve::Choicepoint<Std>;
 pub type KnowledgeBase = Vec<Assertion>;
 pub type Assertions = Vec<Assertion>;
 
-#[derive(Debug, Copy, Clone)]
-enum UnifyErr {
-    NoUnify,
-}
-
-#[derive(Debug, Copy, Clone)]
-enum SolveErr {
-    NoSolution,
-}
-
 #[derive(Debug, Clone)]
 enum Solution {
     Answer(String),
     Choicepoint(String, Vec<Choicepoint>),
 }
 
-#[derive(Debug, Clone)]
-struct Choicepoint {
-    assertions: KnowledgeBase,
-    environment: Environment,
-    clause: Clause,
-    depth: usize,
-}
-
-impl Display for Environment {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
-        let mut env: Vec<_> = self.0.iter().filter(|(Var(_, n), _)| *n == 0).collect();
-        env.sort();
-        let mut response = String::from("\n");
-        let last = env.last().cloned();
-
-        match last {
-            None => Ok(write!(f, "Yes")?),
-            Some((Var(last_x, _), last_t)) => {
-                for (Var(x, _), t) in &env[..env.len() - 1] {
-                    response.push_str(&format!("{} = {}\n", x, self.substitute_term(t)))
-                }
-
-                response.push_str(&format!("{} = {} ", last_x, self.substitute_term(last_t)));
+impl Solution {
+    fn new(env: Environment, ch: Vec<Choicepoint>) -> Self {
+        let answer = env.to_string().trim().to_string();
 
-                Ok(write!(f, "{}", response)?)
-            }
+        if ch.is_empty() {
+            Solution::Answer(answer)
+        } else {
+            Solution::Choicepoint(answer, ch)
         }
     }
 }
 
-impl Environment {
-    fn new() -> Self {
-        Environment(HashMap::new())
-    }
-
-    fn insert(&mut self, x: Var, t: Term) {
-        self.0.insert(x, t);
-    }
-
-    fn lookup(&self, x: &Var) -> Term {
-        match self.0.get(x) {
-            Some(t) => t.clone(),
-            None => Term::Var(x.clone()),
-        }
-    }
-
-    fn substitute_term(&self, t: &Term) -> Term {
-        if let Term::Const(_) = t {
-            return t.clone();
-        }
-
-        let mut t = t.clone();
-        let mut temp = t;
-
-        loop {
-            match temp {
-                Term::Var(x) => {
-                    t = self.lookup(&x);
-
-                    if Term::Var(x) == t {
-                        return t;
-                    }
-
-                    temp = t;
-                }
-                Term::Atom(mut a) => {
-                    let mut next_atoms = Vec::new();
-                    self.substitute_atom(&mut a, &mut next_atoms);
-
-                    while let Some(a) = next_atoms.pop() {
-                        self.substitute_atom(a, &mut next_atoms);
-                    }
-
-                    return Term::Atom(a);
-                }
-                Term::Const(_) => return temp,
-            }
-        }
-    }
-
-    fn substitute_atom<'a>(&self, a: &'a mut Atom, next: &mut Vec<&'a mut Atom>) {
-        for arg in &mut a.args {
-            match arg {
-                ref t @ Term::Var(_) => {
-                    *arg = self.substitute_term(*t);
-                }
-                Term::Atom(ref mut a) => next.push(a),
-                _ => (),
-            }
-        }
-    }
-
-    fn unify_terms(self, t1: &Term, t2: &Term) -> Result<Self, UnifyErr> {
-        match (self.substitute_term(t1), self.substitute_term(t2)) {
-            (ref t1, ref t2) if t1 == t2 => Ok(self),
-            (Term::Var(y), t) | (t, Term::Var(y)) => {
-                if occurs(&y, &t) {
-                    return Err(UnifyErr::NoUnify);
-                }
-
-                let mut env = self;
-                env.insert(y, t);
-
-                Ok(env)
-            }
-            (
-                Term::Atom(Atom {
-                    name: ref c1,
-                    args: ref ts1,
-                    ..
-                }),
-                Term::Atom(Atom {
-                    name: ref c2,
-                    args: ref ts2,
-                    ..
-                }),
-            ) if c1 == c2 => {
-                let mut next_atoms = Vec::new();
-                let mut env = self.unify_list_level(ts1, ts2, &mut next_atoms)?;
-
-                while let Some((a1, a2)) = next_atoms.pop() {
-                    if a1.name != a2.name {
-                        return Err(UnifyErr::NoUnify);
-                    }
-
-                    let next_env = env.unify_list_level(&a1.args, &a2.args, &mut next_atoms)?;
-                    env = next_env;
-                }
-
-                Ok(env)
-            }
-            _ => Err(UnifyErr::NoUnify),
-        }
-    }
-
-    fn unify_list_level<'a>(
-        self,
-        l1: &'a [Term],
-        l2: &'a [Term],
-        next_atoms: &mut Vec<(&'a Atom, &'a Atom)>,
-    ) -> Result<Environment, UnifyErr> {
-        if l1.len() != l2.len() {
-            return Err(UnifyErr::NoUnify);
-        }
+thread_local! {
+    /// The number the next renaming apart on this thread takes.
+    static FRESH: Cell<usize> = const { Cell::new(1) };
+}
 
-        let terms = l1.iter().zip(l2.iter());
-        let mut env = self;
+/// Takes a number that no renaming apart on this thread has taken before, for the variables of a
+/// clause or a copy to be renamed apart with.
+pub(crate) fn fresh() -> usize {
+    FRESH.with(|fresh| fresh.replace(fresh.get() + 1))
+}
 
-        for (t1, t2) in terms {
-            if let (Term::Atom(ref a1), Term::Atom(ref a2)) = (t1, t2) {
-                next_atoms.push((a1, a2));
-            } else {
-                env = env.unify_terms(t1, t2)?;
-            }
-        }
+/// Renames the variables of a term copied out of a finished search or a store apart from every
+/// other variable, keeping the ones that were distinct apart from each other.
+pub(crate) fn rename_fresh(t: &Term) -> Term {
+    solve::rename_fresh::<Std>(t)
+}
 
-        Ok(env)
-    }
+/// Starts renaming apart on this thread over again, for a toplevel query whose variables share
+/// nothing with those of the queries before it.
+fn reset_fresh() {
+    FRESH.with(|fresh| fresh.set(1));
+}
 
-    fn unify_lists(&self, l1: &[Term], l2: &[Term]) -> Result<Self, UnifyErr> {
-        if l1.len() != l2.len() {
-            return Err(UnifyErr::NoUnify);
-        }
+/// Has renaming apart on this thread go on from no lower than `n`, a number taken on another
+/// thread whose variables the goals run here may share.
+pub(crate) fn resume_fresh(n: usize) {
+    FRESH.with(|fresh| fresh.set(fresh.get().max(n)));
+}
 
-        l1.iter()
-            .zip(l2.iter())
-            .fold(Ok(self.clone()), |env, (t1, t2)| env?.unify_terms(t1, t2))
+fn continue_search(kb: &[Assertion], mut ch: Vec<Choicepoint>) -> Result<Solution, SolveErr> {
+    match ch.pop() {
+        None => Err(SolveErr::NoSolution),
+        Some(Choicepoint {
+            assertions: asrl,
+            environment: env,
+            clause: gs,
+            depth: n,
+            ..
+        }) => env
+            .solve(ch, kb, asrl, gs, n)
+            .map(|(env, ch)| Solution::new(env, ch)),
     }
+}
 
-    fn unify_atoms(&self, a1: &Atom, a2: &Atom) -> Result<Self, UnifyErr> {
-        if a1.name == a2.name {
-            return self.unify_lists(&a1.args, &a2.args);
-        }
-
-        Err(UnifyErr::NoUnify)
-    }
-
-    fn reduce_atom(
-        &self,
-        n: usize,
-        a: &Atom,
-        asrl: &[Assertion],
-    ) -> Option<(KnowledgeBase, Environment, Clause)> {
-        let mut asrl = asrl.to_vec();
-
-        while let Some(Assertion {
-            head: ref b,
-            clause: ref lst,
-        }) = asrl.pop()
-        {
-            let next_env = self.unify_atoms(a, &renumber_atom(n, b));
-
-            match next_env {
-                Ok(next_env) => {
-                    return Some((
-                        asrl,
-                        next_env,
-                        lst.iter().map(|a| renumber_atom(n, a)).collect(),
-                    ));
-                }
-                Err(UnifyErr::NoUnify) => {
-                    continue;
-                }
-            }
-        }
-
-        None
-    }
-
-    fn solve(
-        self,
-        mut ch: Vec<Choicepoint>,
-        kb: &[Assertion],
-        asrl: &[Assertion],
-        mut c: Clause,
-        mut n: usize,
-    ) -> Result<Solution, SolveErr> {
-        let mut env = self;
-        let mut asrl = asrl;
-        let mut next_asrl = Some(asrl.to_vec());
-
-        while let Some(a) = c.pop() {
-            let Atom {
-                name: Const(ref atom_name),
-                arity,
-                ..
-            } = a;
-
-            if atom_name == "halt" && arity == 0 {
-                std::process::exit(0);
-            }
+/// Finds the first solution of `goal` against `kb`, returning the bindings it makes.
+fn solve_once(kb: &[Assertion], goal: Atom) -> Option<Environment> {
+    solve::solve_once(kb, goal)
+}
 
-            asrl = match next_asrl {
-                None => kb,
-                Some(ref assertions) => assertions,
-            };
-
-            match env.reduce_atom(n, &a, asrl) {
-                None => match ch.pop() {
-                    None => return Err(SolveErr::NoSolution),
-                    Some(Choicepoint {
-                        assertions: ch_asrl,
-                        environment: next_env,
-                        clause: gs,
-                        depth: next_n,
-                    }) => {
-                        env = next_env;
-                        next_asrl = Some(ch_asrl);
-                        c = gs;
-                        n = next_n;
-                    }
-                },
-                Some((ch_asrl, next_env, mut d)) => {
-                    let mut ch_clause = c.clone();
-                    ch_clause.push(a);
-
-                    let mut ch_buffer = vec![Choicepoint {
-                        assertions: ch_asrl,
-                        environment: env,
-                        clause: ch_clause,
-                        depth: n,
-                    }];
-
-                    ch_buffer.extend_from_slice(&ch);
-                    d.extend_from_slice(&c);
-
-                    env = next_env;
-                    ch = ch_buffer;
-                    next_asrl = None;
-                    c = d;
-                    n += 1;
-                }
-            }
-        }
+/// Reads the declarations of `kb` that the solver needs. The library predicates `kb` calls are
+/// loaded when they are first called.
+fn prepare(kb: &[Assertion]) -> KnowledgeBase {
+    reset(kb);
+    kb.to_vec()
+}
 
-        Ok(match (&env.to_string()[..], &ch[..]) {
-            (answer, []) => Solution::Answer(String::from(answer)),
-            (answer, _) => {
-                let answer = if answer == "Yes" { "Yes " } else { answer };
-                Solution::Choicepoint(String::from(answer), ch)
-            }
-        })
-    }
+/// Reads the declarations of `kb` that the solver keeps for each thread.
+fn reset(kb: &[Assertion]) {
+    builtins::reset_flags(kb);
+    tabling::reset(kb);
+    determinism::reset(kb);
+    modules::reset(kb);
+    builtins::persistency::reset(kb);
+    builtins::locals::reset(kb);
+    parallel::reset(kb);
 }
 
-fn occurs(x: &Var, t: &Term) -> bool {
-    match t {
-        Term::Var(y) => x == y,
-        Term::Const(_) => false,
-        Term::Atom(a) => occurs_atom(x, a),
-    }
+/// Sets the program arguments that `current_prolog_flag(argv, Args)` reports, which are the
+/// arguments of the process until they are set.
+pub fn set_argv(args: Vec<String>) {
+    builtins::set_argv(args)
 }
 
-fn occurs_atom(x: &Var, a: &Atom) -> bool {
-    let mut atom_queue = vec![a];
+/// The program arguments that `current_prolog_flag(argv, Args)` reports.
+pub fn argv() -> Vec<String> {
+    builtins::argv()
+}
 
-    while let Some(a) = atom_queue.pop() {
-        for t in &a.args {
-            match t {
-                Term::Var(y) if x == y => return true,
-                Term::Atom(ref q) => atom_queue.push(q),
-                _ => (),
-            }
-        }
-    }
+/// Sends what queries on this thread write to `user_output` to `writer` instead of standard
+/// output, or back to standard output when `writer` is None.
+pub fn set_user_output(writer: Option<Box<dyn std::io::Write>>) {
+    builtins::set_user_output(writer)
+}
 
-    false
+/// Makes queries on this thread search as `strategy` chooses, or depth first again when it is
+/// None. Queries run by `solve_toplevel` and the functions like it search the tree again for as
+/// long as the strategy asks them to once it is exhausted.
+pub fn set_search_strategy(strategy: Option<Box<dyn strategy::SearchStrategy>>) {
+    strategy::set(strategy)
 }
 
-fn renumber_term(n: usize, t: &Term) -> Term {
-    match t {
-        Term::Var(Var(x, _)) => Term::Var(Var(x.clone(), n)),
-        c @ Term::Const(_) => c.clone(),
-        Term::Atom(a) => Term::Atom(renumber_atom(n, a)),
-    }
+/// Makes an interrupt (Ctrl-C) abort the running query with the exception `'$aborted'` instead
+/// of ending the process. A second interrupt before the first is handled still ends it.
+pub fn catch_interrupts() -> std::io::Result<()> {
+    signals::catch_interrupts()
 }
 
-fn renumber_atom(n: usize, a: &Atom) -> Atom {
-    let mut a = a.clone();
-    let mut next_atoms = Vec::new();
-    renumber_atom_level(n, &mut a, &mut next_atoms);
+/// Runs `goal` against `kb` without printing anything, returning whether it succeeded.
+pub fn solve_quietly(kb: &[Assertion], goal: Atom) -> bool {
+    solve_once(&prepare(kb), replace_cut(&goal, 0)).is_some()
+}
 
-    while let Some(a) = next_atoms.pop() {
-        renumber_atom_level(n, a, &mut next_atoms);
+/// Runs the directive `goal` of a program loaded into `kb` as `solve_quietly` does, giving back
+/// the exception it raised as an error.
+pub fn solve_directive(kb: &[Assertion], goal: Atom) -> Result<bool, Term> {
+    match Environment::new().solve(
+        Vec::new(),
+        &prepare(kb),
+        None,
+        vec![replace_cut(&goal, 0)],
+        1,
+    ) {
+        Ok(_) => Ok(true),
+        Err(SolveErr::NoSolution) => Ok(false),
+        Err(SolveErr::Exception(ball)) => Err(ball),
     }
+}
 
-    a
+/// Settings for a query run with `solve_with_options`.
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    /// How long the query may run, waiting for input included, before it raises
+    /// `time_limit_exceeded`.
+    pub time_limit: Option<Duration>,
+    /// A handle another thread can stop the query with.
+    pub handle: Option<QueryHandle>,
 }
 
-fn renumber_atom_level<'a>(n: usize, a: &'a mut Atom, next: &mut Vec<&'a mut Atom>) {
-    for arg in &mut a.args {
-        match arg {
-            ref t @ Term::Var(_) => {
-                *arg = renumber_term(n, *t);
-            }
-            Term::Atom(ref mut a) => next.push(a),
-            _ => (),
-        }
-    }
+pub fn solve_toplevel(interactive: bool, kb: &[Assertion], c: Clause) -> Vec<String> {
+    solve_with_options(interactive, kb, c, &QueryOptions::default())
 }
 
-fn continue_search(kb: &[Assertion], mut ch: Vec<Choicepoint>) -> Result<Solution, SolveErr> {
-    match ch.pop() {
-        None => Err(SolveErr::NoSolution),
-        Some(Choicepoint {
-            assertions: asrl,
-            environment: env,
-            clause: gs,
-            depth: n,
-        }) => env.solve(ch, kb, &asrl, gs, n),
+/// Runs a query as `solve_toplevel` does, under the limits set by `options`. An interactive query
+/// talks to the user through `console::toplevel()`, and any other gives back every answer.
+pub fn solve_with_options(
+    interactive: bool,
+    kb: &[Assertion],
+    c: Clause,
+    options: &QueryOptions,
+) -> Vec<String> {
+    if interactive {
+        solve_with_console(&mut *console::toplevel(), kb, c, options);
+        Vec::new()
+    } else {
+        let mut answers = console::Collect::default();
+        solve_with_console(&mut answers, kb, c, options);
+        answers.answers
     }
 }
 
-pub fn solve_toplevel(interactive: bool, kb: &[Assertion], c: Clause) -> Vec<String> {
+/// Runs a query under the limits set by `options`, reporting its answers to `console` for as long
+/// as it asks for more.
+pub fn solve_with_console(
+    console: &mut dyn Console,
+    kb: &[Assertion],
+    c: Clause,
+    options: &QueryOptions,
+) {
+    let kb = &prepare(kb)[..];
     let env = Environment::new();
-    let asrl = kb;
-    let mut s = env.solve(Vec::new(), kb, asrl, c, 1);
-    let mut answers = Vec::new();
+    let how = builtins::flag("double_quotes");
+    let goals: Clause = c
+        .iter()
+        .rev()
+        .map(
+            |g| match builtins::double_quoted(&Term::Atom(g.clone()), &how) {
+                Term::Atom(g) => replace_cut(&g, 0),
+                _ => unreachable!("reading double quotes keeps atoms"),
+            },
+        )
+        .collect();
+    signals::clear();
+    limits::clear();
+    reset_fresh();
+
+    if let Some(limit) = options.time_limit {
+        limits::push(limit);
+    }
+
+    limits::watch(options.handle.clone());
+
+    let start = || {
+        reset_fresh();
+        env.clone()
+            .solve(Vec::new(), kb, None, goals.clone(), 1)
+            .map(|(env, ch)| Solution::new(env, ch))
+    };
+    let mut s = start();
     let mut found = false;
+    // A search of the tree again finds the answers of the searches before it, which are reported
+    // only as many times as they were reported before.
+    let mut reported: HashMap<String, usize> = HashMap::new();
+    let mut again: HashMap<String, usize> = HashMap::new();
 
     loop {
         match s {
+            // A search strategy may search the whole tree again once it is exhausted.
+            Err(SolveErr::NoSolution) if strategy::restart() => {
+                again.clear();
+                s = start();
+            }
             Err(SolveErr::NoSolution) if found => break,
             Err(SolveErr::NoSolution) => {
-                println!("\nNo.");
-                if !interactive {
-                    answers.push(String::from("No"))
+                console.no();
+                break;
+            }
+            Err(SolveErr::Exception(ball)) => {
+                match ball {
+                    Term::Atom(ref a) if a.name.0 == signals::ABORTED && a.arity == 0 => {
+                        console.exception("% Execution Aborted")
+                    }
+                    ball => console.exception(&format!("Unhandled exception: {}", ball)),
                 }
                 break;
             }
+            Ok(Solution::Choicepoint(answer, ch))
+                if !new_answer(&answer, &mut reported, &mut again) =>
+            {
+                s = continue_search(kb, ch)
+            }
             Ok(Solution::Choicepoint(answer, ch)) => {
                 found = true;
+                console.answer(&answer, false);
 
-                print!("{}", answer);
-                if !interactive {
-                    answers.push(answer)
-                }
-
-                std::io::stdout().flush().expect("Could not flush stdout");
-
-                if interactive {
-                    let mut input_buffer = String::new();
-                    std::io::stdin()
-                        .read_line(&mut input_buffer)
-                        .expect("error reading input");
-
-                    match &input_buffer[..] {
-                        ";\r\n" | ";\n" => {
-                            s = continue_search(kb, ch);
-                        }
-                        _ => break,
-                    }
-                } else {
+                if console.more() {
                     s = continue_search(kb, ch);
+                } else {
+                    break;
                 }
             }
+            // The last answer of a search reported before ends it as if there were no more.
+            Ok(Solution::Answer(answer)) if !new_answer(&answer, &mut reported, &mut again) => {
+                s = Err(SolveErr::NoSolution)
+            }
             Ok(Solution::Answer(answer)) => {
-                println!("\n{}.", answer);
-                if !interactive {
-                    answers.push(answer)
-                }
+                console.answer(&answer, true);
                 break;
             }
         }
     }
 
-    answers
+    limits::clear();
+}
+
+/// Counts `answer` as found once more in this search, and tells whether that is more times than
+/// it has been reported, counting it as reported if so.
+fn new_answer(
+    answer: &str,
+    reported: &mut HashMap<String, usize>,
+    again: &mut HashMap<String, usize>,
+) -> bool {
+    let times = again.entry(answer.to_string()).or_default();
+    *times += 1;
+    let before = reported.entry(answer.to_string()).or_default();
+
+    if *times > *before {
+        *before += 1;
+        true
+    } else {
+        false
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::{Number, Var};
+    use crate::tokenizer::{tokenize, TokenKind};
+    use bfg_prolog_core::unify::occurs;
 
     fn unification_result(env: &Environment, results: &mut [(Var, Term)]) {
-        let mut env: Vec<_> = env.0.iter().map(|(v, t)| (v.clone(), t.clone())).collect();
+        let mut env: Vec<_> = env
+            .bindings()
+            .iter()
+            .map(|(v, t)| (v.clone(), t.clone()))
+            .collect();
         env.sort();
         results.sort();
         assert_eq!(env, results);
@@ -462,14 +371,14 @@ mod tests {
             "foo",
             vec![Term::Atom(Atom::new(
                 "bar",
-                vec![Term::Const(Const::new("z"))],
+                vec![Term::Atom(Atom::new("z", vec![]))],
             ))],
         ));
 
         let env = Environment::new().unify_terms(&x, &f);
         unification_result(
             &env.unwrap(),
-            &mut [(Var::new("X", 0), Term::Const(Const::new("z")))],
+            &mut [(Var::new("X", 0), Term::Atom(Atom::new("z", vec![])))],
         );
     }
 
@@ -487,7 +396,7 @@ mod tests {
             "foo",
             vec![Term::Atom(Atom::new(
                 "bar",
-                vec![Term::Const(Const::new("z"))],
+                vec![Term::Atom(Atom::new("z", vec![]))],
             ))],
         ));
 
@@ -502,7 +411,7 @@ mod tests {
             "foo",
             vec![Term::Atom(Atom::new(
                 "bar",
-                vec![Term::Const(Const::new("a"))],
+                vec![Term::Atom(Atom::new("a", vec![]))],
             ))],
         ));
 
@@ -530,8 +439,8 @@ mod tests {
 
     #[test]
     fn test_unify_5_succeeds() {
-        let a1 = Term::Const(Const::new("a"));
-        let a2 = Term::Const(Const::new("a"));
+        let a1 = Term::Atom(Atom::new("a", vec![]));
+        let a2 = Term::Atom(Atom::new("a", vec![]));
 
         let env = Environment::new().unify_terms(&a1, &a2);
         unification_result(&env.unwrap(), &mut []);
@@ -540,8 +449,8 @@ mod tests {
     #[test]
     #[should_panic]
     fn test_unify_5_fails() {
-        let a1 = Term::Const(Const::new("a"));
-        let a2 = Term::Const(Const::new("b"));
+        let a1 = Term::Atom(Atom::new("a", vec![]));
+        let a2 = Term::Atom(Atom::new("b", vec![]));
 
         let env = Environment::new().unify_terms(&a1, &a2);
         env.unwrap();
@@ -553,14 +462,20 @@ mod tests {
             "foo",
             vec![Term::Atom(Atom::new(
                 "bar",
-                vec![Term::Var(Var::new("X", 0)), Term::Const(Const::new("q"))],
+                vec![
+                    Term::Var(Var::new("X", 0)),
+                    Term::Atom(Atom::new("q", vec![])),
+                ],
             ))],
         ));
         let f = Term::Atom(Atom::new(
             "foo",
             vec![Term::Atom(Atom::new(
                 "bar",
-                vec![Term::Const(Const::new("z")), Term::Var(Var::new("V", 0))],
+                vec![
+                    Term::Atom(Atom::new("z", vec![])),
+                    Term::Var(Var::new("V", 0)),
+                ],
             ))],
         ));
 
@@ -568,8 +483,8 @@ mod tests {
         unification_result(
             &env.unwrap(),
             &mut [
-                (Var::new("V", 0), Term::Const(Const::new("q"))),
-                (Var::new("X", 0), Term::Const(Const::new("z"))),
+                (Var::new("V", 0), Term::Atom(Atom::new("q", vec![]))),
+                (Var::new("X", 0), Term::Atom(Atom::new("z", vec![]))),
             ],
         );
     }
@@ -595,7 +510,7 @@ mod tests {
                     "h",
                     vec![
                         Term::Var(Var::new("Y", 0)),
-                        Term::Atom(Atom::new("f", vec![Term::Const(Const::new("a"))])),
+                        Term::Atom(Atom::new("f", vec![Term::Atom(Atom::new("a", vec![]))])),
                     ],
                 )),
                 Term::Var(Var::new("Y", 0)),
@@ -608,7 +523,7 @@ mod tests {
             &mut [
                 (
                     Var::new("W", 0),
-                    Term::Atom(Atom::new("f", vec![Term::Const(Const::new("a"))])),
+                    Term::Atom(Atom::new("f", vec![Term::Atom(Atom::new("a", vec![]))])),
                 ),
                 (Var::new("X", 0), Term::Var(Var::new("W", 0))),
                 (
@@ -645,7 +560,7 @@ mod tests {
                     "h",
                     vec![
                         Term::Var(Var::new("Y", 0)),
-                        Term::Atom(Atom::new("f", vec![Term::Const(Const::new("a"))])),
+                        Term::Atom(Atom::new("f", vec![Term::Atom(Atom::new("a", vec![]))])),
                     ],
                 )),
                 Term::Var(Var::new("Y", 0)),
@@ -810,7 +725,7 @@ mod tests {
                 "a",
                 vec![Term::Atom(Atom::new(
                     "x",
-                    vec![Term::Const(Const::new("c"))],
+                    vec![Term::Atom(Atom::new("c", vec![]))],
                 ))],
             )),
             Term::Atom(Atom::new("b", vec![])),
@@ -825,7 +740,7 @@ mod tests {
             &env.unwrap(),
             &mut [(
                 Var::new("X", 0),
-                Term::Atom(Atom::new("x", vec![Term::Const(Const::new("c"))])),
+                Term::Atom(Atom::new("x", vec![Term::Atom(Atom::new("c", vec![]))])),
             )],
         )
     }
@@ -838,7 +753,7 @@ mod tests {
                 "a",
                 vec![Term::Atom(Atom::new(
                     "x",
-                    vec![Term::Const(Const::new("c"))],
+                    vec![Term::Atom(Atom::new("c", vec![]))],
                 ))],
             )),
             Term::Atom(Atom::new("q", vec![])),
@@ -852,6 +767,50 @@ mod tests {
         env.unwrap();
     }
 
+    #[test]
+    fn test_unify_13_succeeds() {
+        let s = Term::partial_string("abc", Term::Var(Var::new("T", 0)));
+        let l = Term::list(
+            vec![
+                Term::Number(Number::Int(97)),
+                Term::Var(Var::new("B", 0)),
+                Term::Number(Number::Int(99)),
+            ],
+            Term::nil(),
+        );
+
+        let env = Environment::new().unify_terms(&s, &l);
+        unification_result(
+            &env.unwrap(),
+            &mut [
+                (Var::new("B", 0), Term::Number(Number::Int(98))),
+                (Var::new("T", 0), Term::nil()),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_unify_14_succeeds() {
+        let s1 = Term::partial_string("ab", Term::Var(Var::new("T", 0)));
+        let s2 = Term::partial_string("abcd", Term::nil());
+
+        let env = Environment::new().unify_terms(&s1, &s2);
+        unification_result(
+            &env.unwrap(),
+            &mut [(Var::new("T", 0), Term::partial_string("cd", Term::nil()))],
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unify_14_fails() {
+        let s1 = Term::partial_string("ab", Term::Var(Var::new("T", 0)));
+        let s2 = Term::partial_string("ac", Term::nil());
+
+        let env = Environment::new().unify_terms(&s1, &s2);
+        env.unwrap();
+    }
+
     #[test]
     fn test_occurs_1_succeeds() {
         let v = Var::new("X", 0);
@@ -903,4 +862,115 @@ mod tests {
 
         assert!(!occurs(&v, &t))
     }
+
+    #[test]
+    fn test_tokenize_1_succeeds() {
+        let tokens = tokenize("foo(X, 'b c') :-\n  % note\n  bar(1.5e3, \"s\").").unwrap();
+        let kinds: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.kind, &t.text[..], t.line, t.column))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (TokenKind::Atom, "foo", 1, 1),
+                (TokenKind::Punct, "(", 1, 4),
+                (TokenKind::Var, "X", 1, 5),
+                (TokenKind::Punct, ",", 1, 6),
+                (TokenKind::QuotedAtom, "'b c'", 1, 8),
+                (TokenKind::Punct, ")", 1, 13),
+                (TokenKind::Atom, ":-", 1, 15),
+                (TokenKind::Comment, "% note", 2, 3),
+                (TokenKind::Atom, "bar", 3, 3),
+                (TokenKind::Punct, "(", 3, 6),
+                (TokenKind::Float, "1.5e3", 3, 7),
+                (TokenKind::Punct, ",", 3, 12),
+                (TokenKind::String, "\"s\"", 3, 14),
+                (TokenKind::Punct, ")", 3, 17),
+                (TokenKind::End, ".", 3, 18),
+            ]
+        );
+        assert_eq!(tokens[4].span, 7..12);
+    }
+
+    #[test]
+    fn test_tokenize_1_fails() {
+        assert!(tokenize("foo('bar).").is_err());
+    }
+
+    #[test]
+    fn test_propagation_queue_1_succeeds() {
+        use crate::builtins::propagation::{Priority, Queue};
+
+        let priority = |p: &Term| match p {
+            Term::Atom(a) if a.name.0 == "slow" => Priority::Low,
+            _ => Priority::High,
+        };
+
+        let mut queue = Queue::new(priority);
+        let (slow, fast) = (
+            Term::Atom(Atom::new("slow", vec![])),
+            Term::Atom(Atom::new("fast", vec![])),
+        );
+        queue.extend(vec![slow.clone(), fast.clone(), slow.clone(), fast.clone()]);
+
+        assert_eq!(queue.pop(), Some(fast));
+        assert_eq!(queue.pop(), Some(slow));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn test_fastrw_1_succeeds() {
+        let x = Term::Var(Var(String::from("X"), 3));
+        let t = Term::Atom(Atom::new(
+            "f",
+            vec![
+                x.clone(),
+                Term::Number(Number::Int(-300)),
+                Term::Number(Number::Float(0.5)),
+                Term::String(String::from("ünïcode")),
+                Term::PartialString(String::from("ab"), Box::new(x)),
+            ],
+        ));
+
+        let decoded = fastrw::decode(&fastrw::encode(&t)).unwrap();
+        let y = Term::Var(Var(String::from("_F0"), 0));
+        let expected = Term::Atom(Atom::new(
+            "f",
+            vec![
+                y.clone(),
+                Term::Number(Number::Int(-300)),
+                Term::Number(Number::Float(0.5)),
+                Term::String(String::from("ünïcode")),
+                Term::PartialString(String::from("ab"), Box::new(y)),
+            ],
+        ));
+
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_fastrw_1_fails() {
+        let bytes = fastrw::encode(&Term::Atom(Atom::new("foo", vec![])));
+
+        assert_eq!(fastrw::decode(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(fastrw::decode(b"foo."), None);
+    }
+
+    #[test]
+    fn test_det_warnings_1_succeeds() {
+        let kb = parser::CodeParser::new()
+            .parse(tokenizer::lex(
+                ":- det(twice/1).\ntwice(X) :- X = 1.\ntwice(X) :- X = 2.\n\
+                 :- det(once/1).\nonce(X) :- X = 1, !.\nonce(X) :- X = 2.\n\
+                 :- det(len/2).\nlen([], 0).\nlen([H|T], N) :- len(T, M), N is M + 1.",
+            ))
+            .unwrap();
+
+        assert_eq!(
+            determinism::warnings(&kb),
+            ["twice/1 is declared det, but clause 1 may leave a choicepoint"]
+        );
+    }
 }
@@ -0,0 +1,122 @@
+use crate::ast::{Assertion, Atom, Const, Term};
+use crate::builtins;
+use crate::parser::CodeParser;
+use crate::tokenizer::lex;
+use crate::KnowledgeBase;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// The bundled libraries, by the name they are loaded with as in `library(dcg/basics)`.
+const SOURCES: &[(&str, &str)] = &[
+    ("assoc", include_str!("library/assoc.pl")),
+    ("coverage", include_str!("library/coverage.pl")),
+    ("dcg/basics", include_str!("library/dcg_basics.pl")),
+    ("lists", include_str!("library/lists.pl")),
+    ("ordsets", include_str!("library/ordsets.pl")),
+    ("pairs", include_str!("library/pairs.pl")),
+    ("thread", include_str!("library/thread.pl")),
+    ("time", include_str!("library/time.pl")),
+];
+
+/// The clauses of each bundled library, parsed the first time any is needed.
+static LIBRARY: OnceLock<Vec<(&str, KnowledgeBase)>> = OnceLock::new();
+
+/// The library that defines each of the predicates of the bundled libraries.
+static INDEX: OnceLock<HashMap<(Const, usize), &str>> = OnceLock::new();
+
+thread_local! {
+    /// The clauses of the predicates of the libraries that calls on this thread have autoloaded.
+    static LOADED: RefCell<HashMap<(Const, usize), KnowledgeBase>> = RefCell::new(HashMap::new());
+}
+
+fn library() -> &'static [(&'static str, KnowledgeBase)] {
+    LIBRARY.get_or_init(|| {
+        SOURCES
+            .iter()
+            .map(|(name, source)| (*name, parse(source)))
+            .collect()
+    })
+}
+
+fn index() -> &'static HashMap<(Const, usize), &'static str> {
+    INDEX.get_or_init(|| {
+        library()
+            .iter()
+            .flat_map(|(name, kb)| {
+                kb.iter()
+                    .map(move |a| ((a.head.name.clone(), a.head.arity), *name))
+            })
+            .filter(|((name, _), _)| name.0 != ":-")
+            .collect()
+    })
+}
+
+fn parse(source: &str) -> KnowledgeBase {
+    CodeParser::new()
+        .parse(lex(source))
+        .expect("invalid library source")
+}
+
+/// The names of the bundled libraries.
+pub(crate) fn names() -> impl Iterator<Item = &'static str> {
+    SOURCES.iter().map(|(name, _)| *name)
+}
+
+pub(crate) fn exists(name: &str) -> bool {
+    SOURCES.iter().any(|(library, _)| *library == name)
+}
+
+/// The clauses of the bundled library `name`, if there is one.
+pub(crate) fn source(name: &str) -> Option<KnowledgeBase> {
+    SOURCES
+        .iter()
+        .find(|(library, _)| *library == name)
+        .map(|(_, source)| parse(source))
+}
+
+/// The predicates the bundled libraries define.
+pub(crate) fn predicates() -> impl Iterator<Item = (&'static str, usize)> {
+    index().keys().map(|(name, arity)| (&name.0[..], *arity))
+}
+
+/// The clauses of the library predicate `goal` calls, for a program that does not define it.
+/// The library that defines it is loaded the first time one of its predicates is called, unless
+/// the `autoload` flag is `false`, as it is once a program sets it with
+/// `:- set_prolog_flag(autoload, false)`.
+pub(crate) fn autoload(goal: &Atom) -> Option<KnowledgeBase> {
+    if builtins::flag("autoload") != Term::Atom(Atom::new("true", vec![])) {
+        return None;
+    }
+
+    let key = (goal.name.clone(), goal.arity);
+
+    if let Some(clauses) = LOADED.with(|loaded| loaded.borrow().get(&key).cloned()) {
+        return Some(clauses);
+    }
+
+    let name = index().get(&key)?;
+
+    let (_, kb) = library().iter().find(|(library, _)| library == name)?;
+
+    LOADED.with(|loaded| {
+        let mut loaded = loaded.borrow_mut();
+
+        for a in kb.iter().filter(|a| a.head.name.0 != ":-") {
+            let key = (a.head.name.clone(), a.head.arity);
+            loaded.entry(key).or_default().push(a.clone());
+        }
+
+        loaded.get(&key).cloned()
+    })
+}
+
+/// The clauses of `kb` other than those of the bundled libraries it loaded.
+pub(crate) fn without_library(kb: &[Assertion]) -> KnowledgeBase {
+    let library = library();
+
+    kb.iter()
+        .filter(|a| !library.iter().any(|(_, clauses)| clauses.contains(a)))
+        .cloned()
+        .collect()
+}
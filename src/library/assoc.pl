@@ -0,0 +1,93 @@
+empty_assoc(t).
+
+get_assoc(Key, t(K, V, _H, L, R), Value) :-
+    compare(Order, Key, K),
+    '$get_assoc'(Order, Key, V, L, R, Value).
+
+'$get_assoc'(=, _Key, V, _L, _R, V).
+'$get_assoc'(<, Key, _V, L, _R, Value) :- get_assoc(Key, L, Value).
+'$get_assoc'(>, Key, _V, _L, R, Value) :- get_assoc(Key, R, Value).
+
+put_assoc(Key, Assoc0, Value, Assoc) :- '$put_assoc'(Assoc0, Key, Value, Assoc).
+
+'$put_assoc'(t, Key, Value, t(Key, Value, 1, t, t)).
+'$put_assoc'(t(K, V, H, L, R), Key, Value, Assoc) :-
+    compare(Order, Key, K),
+    '$put_assoc'(Order, t(K, V, H, L, R), Key, Value, Assoc).
+
+'$put_assoc'(=, t(K, _V, H, L, R), _Key, Value, t(K, Value, H, L, R)).
+'$put_assoc'(<, t(K, V, _H, L, R), Key, Value, Assoc) :-
+    '$put_assoc'(L, Key, Value, L1),
+    '$avl_balance'(K, V, L1, R, Assoc).
+'$put_assoc'(>, t(K, V, _H, L, R), Key, Value, Assoc) :-
+    '$put_assoc'(R, Key, Value, R1),
+    '$avl_balance'(K, V, L, R1, Assoc).
+
+'$avl_height'(t, 0).
+'$avl_height'(t(_K, _V, H, _L, _R), H).
+
+'$avl_node'(K, V, L, R, t(K, V, H, L, R)) :-
+    '$avl_height'(L, HL),
+    '$avl_height'(R, HR),
+    H is max(HL, HR) + 1.
+
+'$avl_balance'(K, V, L, R, Assoc) :-
+    '$avl_height'(L, HL),
+    '$avl_height'(R, HR),
+    D is HL - HR,
+    '$avl_balance'(D, K, V, L, R, Assoc).
+
+'$avl_balance'(2, K, V, t(LK, LV, _LH, LL, LR), R, Assoc) :-
+    !,
+    '$avl_height'(LL, HLL),
+    '$avl_height'(LR, HLR),
+    (   HLL >= HLR
+    ->  '$avl_node'(K, V, LR, R, A),
+        '$avl_node'(LK, LV, LL, A, Assoc)
+    ;   LR = t(XK, XV, _XH, XL, XR),
+        '$avl_node'(LK, LV, LL, XL, A),
+        '$avl_node'(K, V, XR, R, B),
+        '$avl_node'(XK, XV, A, B, Assoc)
+    ).
+'$avl_balance'(-2, K, V, L, t(RK, RV, _RH, RL, RR), Assoc) :-
+    !,
+    '$avl_height'(RL, HRL),
+    '$avl_height'(RR, HRR),
+    (   HRR >= HRL
+    ->  '$avl_node'(K, V, L, RL, A),
+        '$avl_node'(RK, RV, A, RR, Assoc)
+    ;   RL = t(XK, XV, _XH, XL, XR),
+        '$avl_node'(K, V, L, XL, A),
+        '$avl_node'(RK, RV, XR, RR, B),
+        '$avl_node'(XK, XV, A, B, Assoc)
+    ).
+'$avl_balance'(_D, K, V, L, R, Assoc) :- '$avl_node'(K, V, L, R, Assoc).
+
+list_to_assoc(Pairs, Assoc) :- '$list_to_assoc'(Pairs, t, Assoc).
+
+'$list_to_assoc'([], Assoc, Assoc).
+'$list_to_assoc'([K-V|Pairs], Assoc0, Assoc) :-
+    \+ get_assoc(K, Assoc0, _V0),
+    put_assoc(K, Assoc0, V, Assoc1),
+    '$list_to_assoc'(Pairs, Assoc1, Assoc).
+
+assoc_to_list(Assoc, Pairs) :- '$assoc_to_list'(Assoc, Pairs, []).
+
+'$assoc_to_list'(t, Pairs, Pairs).
+'$assoc_to_list'(t(K, V, _H, L, R), Pairs, Rest) :-
+    '$assoc_to_list'(L, Pairs, [K-V|Pairs1]),
+    '$assoc_to_list'(R, Pairs1, Rest).
+
+assoc_to_keys(Assoc, Keys) :- '$assoc_to_keys'(Assoc, Keys, []).
+
+'$assoc_to_keys'(t, Keys, Keys).
+'$assoc_to_keys'(t(K, _V, _H, L, R), Keys, Rest) :-
+    '$assoc_to_keys'(L, Keys, [K|Keys1]),
+    '$assoc_to_keys'(R, Keys1, Rest).
+
+assoc_to_values(Assoc, Values) :- '$assoc_to_values'(Assoc, Values, []).
+
+'$assoc_to_values'(t, Values, Values).
+'$assoc_to_values'(t(_K, V, _H, L, R), Values, Rest) :-
+    '$assoc_to_values'(L, Values, [V|Values1]),
+    '$assoc_to_values'(R, Values1, Rest).
@@ -0,0 +1,7 @@
+show_coverage(Goal) :-
+    '$start_coverage',
+    (   catch(Goal, E, ('$show_coverage', throw(E)))
+    ->  '$show_coverage'
+    ;   '$show_coverage',
+        fail
+    ).
@@ -0,0 +1,33 @@
+digits([D|T]) --> digit(D), digits(T).
+digits([]) --> [].
+
+digit(C) --> [C], {code_type(C, digit(_))}.
+
+integer(I) --> integer_codes(Codes), {number_codes(I, Codes)}.
+
+integer_codes([45, D|T]) --> "-", digit(D), digits(T).
+integer_codes([43, D|T]) --> "+", digit(D), digits(T).
+integer_codes([D|T]) --> digit(D), digits(T).
+
+blanks --> blank, blanks.
+blanks --> [].
+
+blank --> [C], {code_type(C, space)}.
+
+white --> [C], {code_type(C, white)}.
+
+whites --> white, whites.
+whites --> [].
+
+nonblanks([C|T]) --> [C], {code_type(C, graph)}, nonblanks(T).
+nonblanks([]) --> [].
+
+string_without(End, [C|T]) --> [C], {not(memberchk(C, End))}, string_without(End, T).
+string_without(End, []) --> [].
+
+string([]) --> [].
+string([C|T]) --> [C], string(T).
+
+remainder(Rest, S0, []) :- '$lazy_list_rest'(S0, Rest).
+
+eos([], []).
@@ -0,0 +1,73 @@
+append([], Ys, Ys).
+append([X|Xs], Ys, [X|Zs]) :- append(Xs, Ys, Zs).
+
+member(X, [X|_Xs]).
+member(X, [_Y|Xs]) :- member(X, Xs).
+
+length(Xs, N) :- var(N), !, '$length'(Xs, 0, N).
+length(Xs, N) :- integer(N), N >= 0, '$length_fixed'(Xs, N).
+
+'$length'([], N, N).
+'$length'([_X|Xs], N0, N) :- N1 is N0 + 1, '$length'(Xs, N1, N).
+
+'$length_fixed'(Xs, 0) :- !, Xs = [].
+'$length_fixed'([_X|Xs], N) :- N1 is N - 1, '$length_fixed'(Xs, N1).
+
+nth0(I, Xs, E) :- nth0(I, Xs, E, _Rest).
+
+nth1(I, Xs, E) :- nth1(I, Xs, E, _Rest).
+
+nth0(I, Xs, E, Rest) :- integer(I), !, I >= 0, '$nth_take'(I, Xs, E, Rest).
+nth0(I, Xs, E, Rest) :- var(I), '$nth_find'(Xs, E, Rest, 0, I).
+
+nth1(I, Xs, E, Rest) :- integer(I), !, I0 is I - 1, nth0(I0, Xs, E, Rest).
+nth1(I, Xs, E, Rest) :- var(I), nth0(I0, Xs, E, Rest), I is I0 + 1.
+
+'$nth_take'(0, [E|Rest], E, Rest) :- !.
+'$nth_take'(I, [X|Xs], E, [X|Rest]) :- I1 is I - 1, '$nth_take'(I1, Xs, E, Rest).
+
+'$nth_find'([E|Rest], E, Rest, I, I).
+'$nth_find'([X|Xs], E, [X|Rest], I0, I) :- I1 is I0 + 1, '$nth_find'(Xs, E, Rest, I1, I).
+
+last([X|Xs], Last) :- '$last'(Xs, X, Last).
+
+'$last'([], Last, Last).
+'$last'([X|Xs], _Y, Last) :- '$last'(Xs, X, Last).
+
+reverse(Xs, Ys) :- '$reverse'(Xs, [], Ys).
+
+'$reverse'([], Ys, Ys).
+'$reverse'([X|Xs], Acc, Ys) :- '$reverse'(Xs, [X|Acc], Ys).
+
+select(X, [X|Xs], Xs).
+select(X, [Y|Ys], [Y|Zs]) :- select(X, Ys, Zs).
+
+permutation(Xs, Ys) :- is_list(Xs), !, '$permutation'(Xs, Ys).
+permutation(Xs, Ys) :- is_list(Ys), !, '$permutation'(Ys, Xs).
+permutation(Xs, Ys) :- '$length'(Xs, 0, N), '$length_fixed'(Ys, N), '$permutation'(Xs, Ys).
+
+'$permutation'([], []).
+'$permutation'(Xs, [X|Ys]) :- '$select'(X, Xs, Rest), '$permutation'(Rest, Ys).
+
+'$select'(X, [X|Xs], Xs).
+'$select'(X, [Y|Ys], [Y|Zs]) :- '$select'(X, Ys, Zs).
+
+subtract([], _Ys, []).
+subtract([X|Xs], Ys, Zs) :- memberchk(X, Ys), !, subtract(Xs, Ys, Zs).
+subtract([X|Xs], Ys, [X|Zs]) :- subtract(Xs, Ys, Zs).
+
+include(_Goal, [], []).
+include(Goal, [X|Xs], Included) :-
+    (   call(Goal, X)
+    ->  Included = [X|Rest]
+    ;   Included = Rest
+    ),
+    include(Goal, Xs, Rest).
+
+exclude(_Goal, [], []).
+exclude(Goal, [X|Xs], Excluded) :-
+    (   call(Goal, X)
+    ->  Excluded = Rest
+    ;   Excluded = [X|Rest]
+    ),
+    exclude(Goal, Xs, Rest).
@@ -0,0 +1,36 @@
+ord_union([], Ys, Ys).
+ord_union([X|Xs], [], [X|Xs]).
+ord_union([X|Xs], [Y|Ys], Zs) :-
+    compare(Order, X, Y),
+    '$ord_union'(Order, X, Xs, Y, Ys, Zs).
+
+'$ord_union'(<, X, Xs, Y, Ys, [X|Zs]) :- ord_union(Xs, [Y|Ys], Zs).
+'$ord_union'(=, X, Xs, _Y, Ys, [X|Zs]) :- ord_union(Xs, Ys, Zs).
+'$ord_union'(>, X, Xs, Y, Ys, [Y|Zs]) :- ord_union([X|Xs], Ys, Zs).
+
+ord_intersection([], _Ys, []).
+ord_intersection([_X|_Xs], [], []).
+ord_intersection([X|Xs], [Y|Ys], Zs) :-
+    compare(Order, X, Y),
+    '$ord_intersection'(Order, X, Xs, Y, Ys, Zs).
+
+'$ord_intersection'(<, _X, Xs, Y, Ys, Zs) :- ord_intersection(Xs, [Y|Ys], Zs).
+'$ord_intersection'(=, X, Xs, _Y, Ys, [X|Zs]) :- ord_intersection(Xs, Ys, Zs).
+'$ord_intersection'(>, X, Xs, _Y, Ys, Zs) :- ord_intersection([X|Xs], Ys, Zs).
+
+ord_subtract([], _Ys, []).
+ord_subtract([X|Xs], [], [X|Xs]).
+ord_subtract([X|Xs], [Y|Ys], Zs) :-
+    compare(Order, X, Y),
+    '$ord_subtract'(Order, X, Xs, Y, Ys, Zs).
+
+'$ord_subtract'(<, X, Xs, Y, Ys, [X|Zs]) :- ord_subtract(Xs, [Y|Ys], Zs).
+'$ord_subtract'(=, _X, Xs, _Y, Ys, Zs) :- ord_subtract(Xs, Ys, Zs).
+'$ord_subtract'(>, X, Xs, _Y, Ys, Zs) :- ord_subtract([X|Xs], Ys, Zs).
+
+ord_memberchk(X, [Y|Ys]) :-
+    compare(Order, X, Y),
+    '$ord_memberchk'(Order, X, Ys).
+
+'$ord_memberchk'(=, _X, _Ys).
+'$ord_memberchk'(>, X, Ys) :- ord_memberchk(X, Ys).
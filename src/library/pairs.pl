@@ -0,0 +1,8 @@
+pairs_keys_values([], [], []).
+pairs_keys_values([K-V|Pairs], [K|Ks], [V|Vs]) :- pairs_keys_values(Pairs, Ks, Vs).
+
+pairs_keys([], []).
+pairs_keys([K-_V|Pairs], [K|Ks]) :- pairs_keys(Pairs, Ks).
+
+pairs_values([], []).
+pairs_values([_K-V|Pairs], [V|Vs]) :- pairs_values(Pairs, Vs).
@@ -0,0 +1,30 @@
+concurrent_maplist(Goal, Xs) :-
+    '$concurrent_goals'(Xs, Goal, Goals),
+    '$concurrent_all'(Goals).
+
+concurrent_maplist(Goal, Xs, Ys) :-
+    '$concurrent_goals'(Xs, Ys, Goal, Goals),
+    '$concurrent_all'(Goals).
+
+with_mutex(Mutex, Goal) :-
+    mutex_lock(Mutex),
+    (   catch(Goal, E, (mutex_unlock(Mutex), throw(E)))
+    ->  mutex_unlock(Mutex)
+    ;   mutex_unlock(Mutex),
+        fail
+    ).
+
+concurrent_forall(Cond, Action) :-
+    findall(Action, Cond, Actions),
+    '$concurrent_all'(Actions).
+
+'$concurrent_all'(Goals) :-
+    current_prolog_flag(cpu_count, N),
+    concurrent(N, Goals, []).
+
+'$concurrent_goals'([], _Goal, []).
+'$concurrent_goals'([X|Xs], Goal, [call(Goal, X)|Goals]) :- '$concurrent_goals'(Xs, Goal, Goals).
+
+'$concurrent_goals'([], [], _Goal, []).
+'$concurrent_goals'([X|Xs], [Y|Ys], Goal, [call(Goal, X, Y)|Goals]) :-
+    '$concurrent_goals'(Xs, Ys, Goal, Goals).
@@ -0,0 +1,7 @@
+call_with_time_limit(Time, Goal) :-
+    '$push_time_limit'(Time, Id),
+    (   catch(Goal, E, ('$pop_time_limit'(Id), throw(E)))
+    ->  '$pop_time_limit'(Id)
+    ;   '$pop_time_limit'(Id),
+        fail
+    ).
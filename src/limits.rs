@@ -0,0 +1,150 @@
+use crate::ast::{Atom, Term};
+use crate::signals::ABORTED;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The ball thrown into a goal that runs past its time limit.
+pub(crate) const EXCEEDED: &str = "time_limit_exceeded";
+
+/// A time limit on the goal being run, which is checked before each goal the solver runs.
+struct Deadline {
+    id: usize,
+    at: Instant,
+    /// Whether the limit has been reported, so that the goals run while the exception unwinds
+    /// are not stopped again.
+    fired: bool,
+}
+
+/// Stops a query from another thread. The query checks the handle before each goal it runs,
+/// and when it has been cancelled throws `'$aborted'` as an interrupt does.
+#[derive(Debug, Clone, Default)]
+pub struct QueryHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl QueryHandle {
+    pub fn new() -> Self {
+        QueryHandle::default()
+    }
+
+    /// Asks the query run with this handle to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+thread_local! {
+    static DEADLINES: RefCell<Vec<Deadline>> = const { RefCell::new(Vec::new()) };
+    static NEXT: Cell<usize> = const { Cell::new(0) };
+    /// The handle of the query running on this thread, until it has been cancelled.
+    static HANDLE: RefCell<Option<QueryHandle>> = const { RefCell::new(None) };
+}
+
+/// Makes `handle` stop the query that this thread runs next.
+pub(crate) fn watch(handle: Option<QueryHandle>) {
+    HANDLE.with(|h| *h.borrow_mut() = handle);
+}
+
+/// Limits the goals run from now on to `limit`, until the limit numbered by the result is
+/// removed.
+pub(crate) fn push(limit: Duration) -> usize {
+    let id = NEXT.with(|next| next.replace(next.get() + 1));
+    let at = Instant::now() + limit;
+
+    DEADLINES.with(|deadlines| {
+        deadlines.borrow_mut().push(Deadline {
+            id,
+            at,
+            fired: false,
+        })
+    });
+
+    id
+}
+
+/// Removes the limit numbered `id`, with any set after it that was not removed.
+pub(crate) fn pop(id: usize) {
+    DEADLINES.with(|deadlines| {
+        let mut deadlines = deadlines.borrow_mut();
+
+        if let Some(i) = deadlines.iter().position(|deadline| deadline.id == id) {
+            deadlines.truncate(i);
+        }
+    });
+}
+
+/// Removes every limit and the query handle, as when a new query starts.
+pub(crate) fn clear() {
+    DEADLINES.with(|deadlines| deadlines.borrow_mut().clear());
+    HANDLE.with(|handle| *handle.borrow_mut() = None);
+}
+
+/// Whether the query running on this thread has been cancelled and not yet stopped.
+pub(crate) fn cancelled() -> bool {
+    HANDLE.with(|handle| {
+        handle
+            .borrow()
+            .as_ref()
+            .is_some_and(QueryHandle::is_cancelled)
+    })
+}
+
+/// How long until the nearest limit runs out, if there is one.
+pub(crate) fn remaining() -> Option<Duration> {
+    DEADLINES.with(|deadlines| {
+        let deadlines = deadlines.borrow();
+        let now = Instant::now();
+
+        deadlines
+            .iter()
+            .filter(|deadline| !deadline.fired)
+            .map(|deadline| deadline.at.saturating_duration_since(now))
+            .min()
+    })
+}
+
+/// The goal that throws `'$aborted'`, if the query has been cancelled since the last check, or
+/// `time_limit_exceeded`, if a limit has run out.
+pub(crate) fn expired() -> Option<Atom> {
+    let cancelled = HANDLE.with(|handle| {
+        let mut handle = handle.borrow_mut();
+        let cancelled = handle.as_ref().is_some_and(QueryHandle::is_cancelled);
+
+        if cancelled {
+            *handle = None;
+        }
+
+        cancelled
+    });
+
+    if cancelled {
+        return Some(Atom::new(
+            "throw",
+            vec![Term::Atom(Atom::new(ABORTED, vec![]))],
+        ));
+    }
+
+    DEADLINES.with(|deadlines| {
+        let mut deadlines = deadlines.borrow_mut();
+
+        if deadlines.is_empty() {
+            return None;
+        }
+
+        let now = Instant::now();
+        let mut expired = false;
+
+        for deadline in deadlines.iter_mut().filter(|d| !d.fired && d.at <= now) {
+            deadline.fired = true;
+            expired = true;
+        }
+
+        expired.then(|| Atom::new("throw", vec![Term::Atom(Atom::new(EXCEEDED, vec![]))]))
+    })
+}
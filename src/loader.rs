@@ -0,0 +1,972 @@
+use crate::ast::{unquote, Assertion, Atom, Const, Term, Var};
+use crate::parser::CodeParser;
+use crate::tokenizer::{lex, tokenize, Token, TokenKind};
+use crate::{builtins, determinism, doc, library, saved, solve_once, solve_quietly, KnowledgeBase};
+use lalrpop_util::ParseError;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// How deeply `file_search_path/2` aliases may be defined in terms of each other.
+const MAX_ALIAS_DEPTH: usize = 16;
+
+/// The directives of the standard, the only ones a program may have where the `iso` flag is set.
+const ISO_DIRECTIVES: &[(&str, usize)] = &[
+    ("dynamic", 1),
+    ("multifile", 1),
+    ("discontiguous", 1),
+    ("op", 3),
+    ("char_conversion", 2),
+    ("initialization", 1),
+    ("include", 1),
+    ("ensure_loaded", 1),
+    ("set_prolog_flag", 2),
+];
+
+/// The directives that declare something the program is read for, rather than goals run once it
+/// is loaded.
+const DECLARATIONS: &[&str] = &[
+    "dynamic",
+    "discontiguous",
+    "multifile",
+    "thread_local",
+    "persistent",
+    "parallel",
+    "table",
+    "loop_check",
+    "meta_predicate",
+    "det",
+    "module",
+    "use_module",
+    "ensure_loaded",
+    "include",
+    "set_prolog_flag",
+    "op",
+    "char_conversion",
+    "$doc",
+];
+
+/// A clause that could not be read, and where in the text it failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// The line of the error, counted from 1.
+    pub line: usize,
+    /// The column of the error on its line, in characters counted from 1.
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// A variable that appears only once in its clause, which is most often a misspelling of another,
+/// and where it is in the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Singleton {
+    /// The line of the variable, counted from 1.
+    pub line: usize,
+    /// The column of the variable on its line, in characters counted from 1.
+    pub column: usize,
+    pub name: String,
+}
+
+impl Display for Singleton {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "{}:{}: singleton variable {}",
+            self.line, self.column, self.name
+        )
+    }
+}
+
+/// Where a file specification such as `library(dcg/basics)` or `'lib/util'` leads.
+enum Source {
+    Bundled(String),
+    File(PathBuf),
+}
+
+/// The files loaded so far while loading a program, so that each is loaded only once.
+#[derive(Default)]
+struct Loaded {
+    files: HashSet<PathBuf>,
+    libraries: HashSet<String>,
+}
+
+/// A file loaded into the program, with what `make` needs to reload it once it changes.
+struct LoadedFile {
+    path: PathBuf,
+    /// The file and those it includes, with when each was last modified as it was read.
+    read: Vec<(PathBuf, Option<SystemTime>)>,
+    /// The clauses of the file, without those of the files it loads.
+    clauses: KnowledgeBase,
+}
+
+/// A predicate by its name and arity, with the file and line, counted from 1, it is defined at.
+type Location = ((String, usize), (PathBuf, usize));
+
+thread_local! {
+    static FILES: RefCell<Vec<LoadedFile>> = const { RefCell::new(Vec::new()) };
+    static LOCATIONS: RefCell<Vec<Location>> = const { RefCell::new(Vec::new()) };
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Records that the file at `path` was loaded, in place of what was recorded when it was loaded
+/// before.
+fn record(file: LoadedFile) {
+    FILES.with(|files| {
+        let mut files = files.borrow_mut();
+
+        match files.iter_mut().find(|f| f.path == file.path) {
+            Some(f) => *f = file,
+            None => files.push(file),
+        }
+    })
+}
+
+/// The predicate each clause of `text` defines, with the line the clause starts at. Directives and
+/// clauses whose heads are not written as a name with its arguments are left out.
+fn heads(text: &str) -> Vec<((String, usize), usize)> {
+    let tokens: Vec<_> = match tokenize(text) {
+        Ok(tokens) => tokens
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Comment)
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    let mut heads = Vec::new();
+
+    for clause in tokens.split_inclusive(|token| token.kind == TokenKind::End) {
+        let first = &clause[0];
+        let name = match first.kind {
+            TokenKind::Atom if first.text != ":-" && first.text != "?-" => first.text.clone(),
+            TokenKind::QuotedAtom => unquote(&first.text),
+            _ => continue,
+        };
+        let mut arity = 0;
+        let mut neck = 1;
+
+        if clause.get(1).is_some_and(|open| {
+            open.kind == TokenKind::Punct && open.text == "(" && open.span.start == first.span.end
+        }) {
+            let mut depth = 0;
+            arity = 1;
+
+            for (i, token) in clause.iter().enumerate().skip(1) {
+                match (token.kind, &token.text[..]) {
+                    (TokenKind::Punct, "(" | "[" | "{") => depth += 1,
+                    (TokenKind::Punct, ")" | "]" | "}") => depth -= 1,
+                    (TokenKind::Punct, ",") if depth == 1 => arity += 1,
+                    _ => (),
+                }
+
+                if depth == 0 {
+                    neck = i + 1;
+                    break;
+                }
+            }
+        }
+
+        // A grammar rule defines a predicate with two arguments more than its head has.
+        if clause.get(neck).is_some_and(|token| token.text == "-->") {
+            arity += 2;
+        }
+
+        heads.push(((name, arity), first.line));
+    }
+
+    heads
+}
+
+/// Records where the predicates of the file at `path`, whose text is `text`, are defined, in place
+/// of what was recorded when it was read before.
+fn locate(path: &Path, text: &str) {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    LOCATIONS.with(|locations| {
+        let mut locations = locations.borrow_mut();
+        locations.retain(|(_, (file, _))| *file != path);
+
+        for (predicate, line) in heads(text) {
+            if !locations.iter().any(|(p, _)| *p == predicate) {
+                locations.push((predicate, (path.clone(), line)));
+            }
+        }
+    })
+}
+
+/// The file and line that a predicate named `name`, with `arity` arguments if given, is first
+/// defined at among the files loaded.
+pub fn source_location(name: &str, arity: Option<usize>) -> Option<(PathBuf, usize)> {
+    LOCATIONS.with(|locations| {
+        locations
+            .borrow()
+            .iter()
+            .find(|((n, a), _)| n == name && arity.is_none_or(|arity| arity == *a))
+            .map(|(_, location)| location.clone())
+    })
+}
+
+/// Reads the program in the file at `path`, loads the files it asks for and expands its quasi
+/// quotations. A saved state written by `qsave_program/1,2` is read as the program it holds. The
+/// files loaded before are forgotten, so that `make` reloads those of this program.
+pub fn consult(path: &str) -> Result<KnowledgeBase, String> {
+    FILES.with(|files| files.borrow_mut().clear());
+    LOCATIONS.with(|locations| locations.borrow_mut().clear());
+
+    if let Some(program) = std::fs::read(path)
+        .ok()
+        .and_then(|bytes| saved::program(&bytes))
+    {
+        return program;
+    }
+
+    expand_quasi_quotations(load_file(Path::new(path), &mut Loaded::default())?)
+}
+
+/// Reads a program from `text` as `consult` reads one from a file, with the files it asks for
+/// taken relative to the working directory. Where a file has the clauses with syntax errors
+/// skipped, any syntax error in `text` is an error.
+pub fn consult_text(text: &str) -> Result<KnowledgeBase, String> {
+    let kb = CodeParser::new()
+        .parse(lex(text))
+        .map_err(|e| e.to_string())?;
+    expand_quasi_quotations(load(read_double_quotes(kb), Path::new(""))?)
+}
+
+/// Whether `query` is `[user]` or `consult(user)`, which read clauses typed at the terminal.
+pub fn consults_user(query: &[Atom]) -> bool {
+    let user = Term::Atom(Atom::new("user", vec![]));
+
+    query.len() == 1
+        && (query[0] == Atom::new("consult", vec![user.clone()])
+            || query[0] == Atom::new(".", vec![user, Term::nil()]))
+}
+
+/// Reads the clauses typed at the terminal for `[user]` as a program of their own, as
+/// `consult_text` does, with no text at all read as no clauses.
+pub fn consult_user(text: &str) -> Result<KnowledgeBase, String> {
+    if text.trim().is_empty() {
+        Ok(Vec::new())
+    } else {
+        consult_text(text)
+    }
+}
+
+/// Reads the clauses of `text`, each on its own once the whole cannot be read, so that a syntax
+/// error leaves the clauses around it readable. Gives back the clauses that could be read with the
+/// errors of those that could not, or an error if the text cannot even be split into clauses.
+pub fn read_program(text: &str) -> Result<(KnowledgeBase, Vec<SyntaxError>), String> {
+    let error = match CodeParser::new().parse(lex(text)) {
+        Ok(kb) => return Ok((kb, Vec::new())),
+        Err(e) => e.to_string(),
+    };
+    let tokens: Vec<_> = match tokenize(text) {
+        Ok(tokens) => tokens
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Comment)
+            .collect(),
+        Err(_) => return Err(error),
+    };
+    let mut clauses = Vec::new();
+    let mut errors = Vec::new();
+
+    for tokens in tokens.split_inclusive(|token| token.kind == TokenKind::End) {
+        let start = tokens[0].span.start;
+        let end = tokens[tokens.len() - 1].span.end;
+
+        match CodeParser::new().parse(lex(&text[start..end])) {
+            Ok(kb) => clauses.push(kb),
+            Err(e) => {
+                let (at, message) = syntax_error(&e);
+                let before = &text[..start + at];
+                let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+                errors.push(SyntaxError {
+                    line: before.matches('\n').count() + 1,
+                    column: before[line_start..].chars().count() + 1,
+                    message,
+                });
+            }
+        }
+    }
+
+    // Each clause read is kept last first, as the whole program is.
+    Ok((clauses.into_iter().rev().flatten().collect(), errors))
+}
+
+/// Where a syntax error is in the clause it was found in, with its message.
+fn syntax_error<T: Display, E: Display>(error: &ParseError<usize, T, E>) -> (usize, String) {
+    match error {
+        ParseError::InvalidToken { location } => (*location, String::from("invalid token")),
+        ParseError::UnrecognizedEOF { location, .. } => {
+            (*location, String::from("unexpected end of clause"))
+        }
+        ParseError::UnrecognizedToken {
+            token: (start, token, _),
+            ..
+        }
+        | ParseError::ExtraToken {
+            token: (start, token, _),
+        } => (*start, format!("unexpected `{}`", token)),
+        ParseError::User { error } => (0, error.to_string()),
+    }
+}
+
+/// The tokens of the variables that appear only once in the clause read from `tokens`, other than
+/// those named with a leading underscore, in the order they appear.
+pub fn singleton_vars(tokens: &[Token]) -> Vec<&Token> {
+    let vars: Vec<_> = tokens
+        .iter()
+        .filter(|token| token.kind == TokenKind::Var)
+        .collect();
+
+    vars.iter()
+        .filter(|token| !token.text.starts_with('_'))
+        .filter(|token| vars.iter().filter(|t| t.text == token.text).count() == 1)
+        .copied()
+        .collect()
+}
+
+/// The singleton variables of each clause of `text`, in the order they appear, or none if the text
+/// cannot be split into clauses.
+pub fn singletons(text: &str) -> Vec<Singleton> {
+    let tokens: Vec<_> = match tokenize(text) {
+        Ok(tokens) => tokens
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Comment)
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+
+    tokens
+        .split_inclusive(|token| token.kind == TokenKind::End)
+        .flat_map(singleton_vars)
+        .map(|token| Singleton {
+            line: token.line,
+            column: token.column,
+            name: token.text.clone(),
+        })
+        .collect()
+}
+
+/// Reads the clauses of `text`, which comes from `origin`, reporting each syntax error and singleton
+/// variable where it is, and how many clauses were skipped once the ones around them are read.
+fn read_clauses(text: &str, origin: &str) -> Result<KnowledgeBase, String> {
+    let (kb, errors) = read_program(text).map_err(|e| format!("{}: {}", origin, e))?;
+
+    for error in &errors {
+        eprintln!("{}:{}", origin, error);
+    }
+
+    for singleton in singletons(text) {
+        eprintln!("Warning: {}:{}", origin, singleton);
+    }
+
+    match errors.len() {
+        0 => (),
+        1 => eprintln!("Warning: {}: 1 clause skipped for a syntax error", origin),
+        n => eprintln!(
+            "Warning: {}: {} clauses skipped for syntax errors",
+            origin, n
+        ),
+    }
+
+    Ok(kb)
+}
+
+/// Adds the program in `text` to `kb`, ahead of the clauses it has, and runs the goals it asks to
+/// run once it is loaded.
+#[cfg(any(feature = "ffi", feature = "python", feature = "wasm"))]
+pub(crate) fn consult_into(kb: &mut KnowledgeBase, text: &str) -> Result<(), String> {
+    let source = consult_text(text)?;
+    let goals = initialization_goals(&source);
+    // Later clauses come first, as the solver reads the program from the end.
+    *kb = source.into_iter().chain(kb.drain(..)).collect();
+
+    for (when, goal) in goals {
+        if when == Initialization::AfterLoad && !solve_quietly(kb, goal.clone()) {
+            return Err(format!("initialization goal {} failed", goal.args[0]));
+        }
+    }
+
+    Ok(())
+}
+
+fn dir_of(path: &Path) -> &Path {
+    path.parent().unwrap_or_else(|| Path::new(""))
+}
+
+fn read_code(path: &Path) -> Result<KnowledgeBase, String> {
+    let text = read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    locate(path, &text);
+
+    let mut kb = read_clauses(&text, &path.display().to_string())?;
+    kb.extend(doc::comments(&text));
+    Ok(read_double_quotes(kb))
+}
+
+/// Reads the double-quoted text of the clauses of `kb` as the `double_quotes` flag says, as it is
+/// when loading starts and then as the `set_prolog_flag/2` directives before each clause set it.
+fn read_double_quotes(kb: KnowledgeBase) -> KnowledgeBase {
+    let mut how = builtins::flag("double_quotes");
+    let mut read = Vec::with_capacity(kb.len());
+
+    // Clauses are kept last first, so they are read from the end.
+    for a in kb.into_iter().rev() {
+        if let Some((flag, value)) = builtins::flag_directive(&a) {
+            if flag == "double_quotes" {
+                how = value;
+            }
+        }
+
+        if how == Term::Atom(Atom::new("string", vec![])) {
+            read.push(a);
+            continue;
+        }
+
+        let quoted = |goal: Atom| match builtins::double_quoted(&Term::Atom(goal), &how) {
+            Term::Atom(goal) => goal,
+            _ => unreachable!("reading double quotes keeps atoms"),
+        };
+
+        read.push(Assertion {
+            head: quoted(a.head),
+            clause: a.clause.into_iter().map(quoted).collect(),
+        });
+    }
+
+    read.reverse();
+    read
+}
+
+/// Loads the file at `path` unless it has been loaded already, or is being loaded by one of the
+/// files that it loads in turn.
+fn load_file(path: &Path, loaded: &mut Loaded) -> Result<KnowledgeBase, String> {
+    let canonical = path.canonicalize().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => {
+            missing(Term::Atom(Atom::new(&path.to_string_lossy(), vec![])))
+        }
+        _ => format!("{}: {}", path.display(), e),
+    })?;
+
+    if !loaded.files.insert(canonical.clone()) {
+        return Ok(Vec::new());
+    }
+
+    let (kb, files) = read_file(path, canonical, loaded)?;
+    Ok(join(kb, files))
+}
+
+/// Reads the file at `path` and loads the files it asks for that are not loaded yet, recording it
+/// for `make`. Gives back its clauses and those of each file it loads.
+fn read_file(
+    path: &Path,
+    canonical: PathBuf,
+    loaded: &mut Loaded,
+) -> Result<(KnowledgeBase, Vec<KnowledgeBase>), String> {
+    let mut read = vec![path.to_path_buf()];
+    let kb = include(
+        read_code(path)?,
+        dir_of(path),
+        &mut vec![canonical.clone()],
+        &mut read,
+    )?;
+    let (kb, files) = load_files(kb, dir_of(path), loaded)?;
+
+    record(LoadedFile {
+        path: canonical,
+        read: read
+            .into_iter()
+            .map(|path| {
+                let when = modified(&path);
+                (path, when)
+            })
+            .collect(),
+        clauses: kb.clone(),
+    });
+
+    Ok((kb, files))
+}
+
+/// Reloads the files of the program `kb` that were modified since they were loaded. The clauses
+/// each has now take the place of those it had, followed by those of the files it now loads that
+/// were not loaded before. Gives back each file reloaded with its clauses, whose directives are
+/// still to be run.
+pub fn make(kb: &mut KnowledgeBase) -> Result<Vec<(PathBuf, KnowledgeBase)>, String> {
+    let changed: Vec<_> = FILES.with(|files| {
+        files
+            .borrow()
+            .iter()
+            .filter(|f| f.read.iter().any(|(path, when)| modified(path) != *when))
+            .map(|f| (f.path.clone(), f.clauses.clone()))
+            .collect()
+    });
+    let mut reloaded = Vec::new();
+
+    for (path, old) in changed {
+        // The files loaded already are left as they are, as are the libraries, which are loaded
+        // when the program calls them anyway.
+        let mut loaded = Loaded {
+            files: FILES.with(|files| files.borrow().iter().map(|f| f.path.clone()).collect()),
+            libraries: library::names().map(String::from).collect(),
+        };
+        let (clauses, files) = read_file(&path, path.clone(), &mut loaded)?;
+        let code = expand_quasi_quotations(join(clauses.clone(), files))?;
+
+        // The clauses of a file are kept together, unless the program changed them since.
+        let at = if old.is_empty() {
+            None
+        } else {
+            kb.windows(old.len()).position(|w| w == &old[..])
+        };
+
+        match at {
+            Some(at) => {
+                kb.splice(at..at + old.len(), code);
+            }
+            None => {
+                kb.retain(|a| !old.contains(a));
+                kb.splice(0..0, code);
+            }
+        }
+
+        reloaded.push((path, clauses));
+    }
+
+    Ok(reloaded)
+}
+
+/// Runs the `include/1`, `use_module/1,2` and `ensure_loaded/1` directives of `kb`, adding the
+/// clauses of the files they load. Relative file specifications are read from `dir`.
+pub fn load(kb: KnowledgeBase, dir: &Path) -> Result<KnowledgeBase, String> {
+    let kb = include(kb, dir, &mut Vec::new(), &mut Vec::new())?;
+    load_code(kb, dir, &mut Loaded::default())
+}
+
+/// Replaces each `:- include(File)` directive of `kb` by the clauses of `File`. `including` holds
+/// the files being included, which may not include themselves, and `read` gets each file read.
+fn include(
+    kb: KnowledgeBase,
+    dir: &Path,
+    including: &mut Vec<PathBuf>,
+    read: &mut Vec<PathBuf>,
+) -> Result<KnowledgeBase, String> {
+    let paths = search_paths(&kb);
+    let mut code = Vec::new();
+
+    for a in kb.into_iter().rev() {
+        let spec = match included_file(&a) {
+            Some(spec) => spec.clone(),
+            None => {
+                code.push(a);
+                continue;
+            }
+        };
+
+        let included = match resolve(&spec, dir, &paths)? {
+            Source::Bundled(name) => library::source(&name).unwrap_or_default(),
+            Source::File(path) => {
+                let canonical = path
+                    .canonicalize()
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+                if including.contains(&canonical) {
+                    return Err(format!("{} includes itself", path.display()));
+                }
+
+                including.push(canonical);
+                read.push(path.clone());
+                let included = include(read_code(&path)?, dir_of(&path), including, read)?;
+                including.pop();
+
+                included
+            }
+        };
+
+        code.extend(included.into_iter().rev());
+    }
+
+    Ok(code.into_iter().rev().collect())
+}
+
+fn included_file(a: &Assertion) -> Option<&Term> {
+    match (&a.head.name.0[..], &a.head.args[..]) {
+        (":-", [Term::Atom(directive)]) if directive.name.0 == "include" => {
+            match &directive.args[..] {
+                [spec] => Some(spec),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn load_code(kb: KnowledgeBase, dir: &Path, loaded: &mut Loaded) -> Result<KnowledgeBase, String> {
+    let (kb, files) = load_files(kb, dir, loaded)?;
+    Ok(join(kb, files))
+}
+
+/// Clauses are stored last first, so the files loaded go at the back, in reverse, to read as if
+/// they were written in place of the directives that load them.
+fn join(kb: KnowledgeBase, files: Vec<KnowledgeBase>) -> KnowledgeBase {
+    kb.into_iter()
+        .chain(files.into_iter().rev().flatten())
+        .collect()
+}
+
+/// Compiles `kb` and loads the files its directives ask for that are not loaded yet, giving back
+/// its clauses with those of each file.
+fn load_files(
+    kb: KnowledgeBase,
+    dir: &Path,
+    loaded: &mut Loaded,
+) -> Result<(KnowledgeBase, Vec<KnowledgeBase>), String> {
+    let kb = compile_conditionally(kb)?;
+    check_directives(&kb)?;
+
+    for warning in determinism::warnings(&kb) {
+        eprintln!("Warning: {}", warning);
+    }
+
+    let paths = search_paths(&kb);
+    let mut files = Vec::new();
+
+    for directive in directives(&kb) {
+        match (&directive.name.0[..], &directive.args[..]) {
+            ("use_module", [spec]) | ("use_module", [spec, _]) | ("ensure_loaded", [spec]) => {
+                files.push(match resolve(spec, dir, &paths)? {
+                    Source::Bundled(name) if loaded.libraries.insert(name.clone()) => {
+                        library::source(&name).unwrap_or_default()
+                    }
+                    Source::Bundled(_) => Vec::new(),
+                    Source::File(path) => load_file(&path, loaded)?,
+                });
+            }
+            _ => (),
+        }
+    }
+
+    Ok((kb, files))
+}
+
+/// When a goal given to `initialization/1,2` runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Initialization {
+    /// Once the program is loaded, as with `initialization(G)`.
+    AfterLoad,
+    /// As the entry point of a script, as with `initialization(G, main)`.
+    Main,
+}
+
+/// The `initialization/1,2` goals of a loaded program, in the order they were written.
+pub fn initialization_goals(kb: &[Assertion]) -> Vec<(Initialization, Atom)> {
+    directives(kb)
+        .filter_map(|directive| {
+            let when = match (&directive.name.0[..], &directive.args[..]) {
+                ("initialization", [_]) => Initialization::AfterLoad,
+                ("initialization", [_, Term::Atom(when)]) if when.name.0 == "main" => {
+                    Initialization::Main
+                }
+                ("initialization", [_, _]) => Initialization::AfterLoad,
+                _ => return None,
+            };
+
+            Some((when, Atom::new("call", vec![directive.args[0].clone()])))
+        })
+        .collect()
+}
+
+/// The goals the directives of a loaded program run once it is loaded, in the order they were
+/// written. Declarations are left out, as is `initialization(Goal, main)`, which runs as the entry
+/// point instead, and `initialization(Goal)` runs `Goal`.
+pub fn load_goals(kb: &[Assertion]) -> Vec<Atom> {
+    directives(kb)
+        .filter(|directive| !DECLARATIONS.contains(&&directive.name.0[..]))
+        .filter_map(
+            |directive| match (&directive.name.0[..], &directive.args[..]) {
+                ("initialization", [_, Term::Atom(when)]) if when.name.0 == "main" => None,
+                ("initialization", [goal]) | ("initialization", [goal, _]) => {
+                    Some(Atom::new("call", vec![goal.clone()]))
+                }
+                _ => Some(directive.clone()),
+            },
+        )
+        .collect()
+}
+
+/// Keeps the clauses of `kb` that `:- if(Cond)`, `:- elif(Cond)`, `:- else` and `:- endif`
+/// select, running each condition against the clauses kept before it.
+fn compile_conditionally(kb: KnowledgeBase) -> Result<KnowledgeBase, String> {
+    // For each open `if`, whether the current branch is kept and whether any branch was.
+    let mut open: Vec<(bool, bool)> = Vec::new();
+    let mut kept: KnowledgeBase = Vec::new();
+
+    for a in kb.into_iter().rev() {
+        let active = open.last().is_none_or(|&(active, _)| active);
+        let holds = |cond: &Term, kept: &KnowledgeBase| {
+            let kb: KnowledgeBase = kept.iter().rev().cloned().collect();
+            solve_quietly(&kb, Atom::new("call", vec![cond.clone()]))
+        };
+
+        let directive = match (&a.head.name.0[..], &a.head.args[..]) {
+            (":-", [Term::Atom(directive)]) => directive,
+            _ => {
+                if active {
+                    kept.push(a);
+                }
+
+                continue;
+            }
+        };
+
+        match (&directive.name.0[..], &directive.args[..]) {
+            ("if", [cond]) => {
+                let taken = active && holds(cond, &kept);
+                open.push((taken, taken || !active));
+            }
+            ("elif", [cond]) => match open.pop() {
+                Some((_, taken)) => {
+                    let now = !taken && holds(cond, &kept);
+                    open.push((now, taken || now));
+                }
+                None => return Err(String::from(":- elif without :- if")),
+            },
+            ("else", []) => match open.pop() {
+                Some((_, taken)) => open.push((!taken, true)),
+                None => return Err(String::from(":- else without :- if")),
+            },
+            ("endif", []) => {
+                open.pop()
+                    .ok_or_else(|| String::from(":- endif without :- if"))?;
+            }
+            _ if active => kept.push(a),
+            _ => (),
+        }
+    }
+
+    if !open.is_empty() {
+        return Err(String::from(":- if without :- endif"));
+    }
+
+    Ok(kept.into_iter().rev().collect())
+}
+
+/// Checks that the directives of `kb` are those of the standard where the `iso` flag is set, as it
+/// is when loading starts and then as the `set_prolog_flag/2` directives before each one set it.
+fn check_directives(kb: &[Assertion]) -> Result<(), String> {
+    let mut iso = builtins::iso();
+
+    for a in kb.iter().rev() {
+        if let Some((flag, value)) = builtins::flag_directive(a) {
+            if flag == "iso" {
+                iso = value == Term::Atom(Atom::new("true", vec![]));
+            }
+        }
+
+        match (&a.head.name.0[..], &a.head.args[..]) {
+            (":-", [Term::Atom(d)])
+                if iso
+                    && !ISO_DIRECTIVES.contains(&(&d.name.0[..], d.arity))
+                    && d.name.0 != "$doc" =>
+            {
+                return Err(format!(
+                    "{}/{} is not a directive of the standard",
+                    d.name.0, d.arity
+                ))
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// The directives of `kb` in the order they were written.
+fn directives(kb: &[Assertion]) -> impl Iterator<Item = &Atom> {
+    kb.iter()
+        .rev()
+        .filter_map(|a| match (&a.head.name.0[..], &a.head.args[..]) {
+            (":-", [Term::Atom(directive)]) => Some(directive),
+            _ => None,
+        })
+}
+
+/// The directories of each file search path alias, from the `file_search_path/2` facts of `kb`
+/// followed by the defaults.
+fn search_paths(kb: &[Assertion]) -> Vec<(String, Term)> {
+    let mut paths: Vec<_> = kb
+        .iter()
+        .rev()
+        .filter_map(
+            |a| match (&a.head.name.0[..], &a.head.args[..], &a.clause[..]) {
+                ("file_search_path", [Term::Atom(alias), dir], []) if alias.arity == 0 => {
+                    Some((alias.name.0.clone(), dir.clone()))
+                }
+                _ => None,
+            },
+        )
+        .collect();
+
+    paths.push((
+        String::from("foreign"),
+        Term::Atom(Atom::new("foreign", vec![])),
+    ));
+    paths
+}
+
+fn resolve(spec: &Term, dir: &Path, paths: &[(String, Term)]) -> Result<Source, String> {
+    for candidate in candidates(spec, dir, paths, 0) {
+        match candidate {
+            Source::Bundled(name) if library::exists(&name) => return Ok(Source::Bundled(name)),
+            Source::Bundled(_) => (),
+            Source::File(path) => {
+                let source_file = PathBuf::from(format!("{}.pl", path.display()));
+
+                for path in [source_file, path] {
+                    if path.is_file() {
+                        return Ok(Source::File(path));
+                    }
+                }
+            }
+        }
+    }
+
+    Err(missing(spec.clone()))
+}
+
+/// The error for a source `spec` that names no file or library, as the ISO
+/// `existence_error(source_sink, Spec)` writes it.
+fn missing(spec: Term) -> String {
+    let kind = Term::Atom(Atom::new("source_sink", vec![]));
+    Term::Atom(Atom::new("existence_error", vec![kind, spec])).to_string()
+}
+
+/// Every place `spec` may refer to, in the order they are tried. The `library` alias ends with
+/// the libraries bundled with the crate.
+fn candidates(spec: &Term, dir: &Path, paths: &[(String, Term)], depth: usize) -> Vec<Source> {
+    match spec {
+        Term::String(path) => vec![Source::File(dir.join(path))],
+        Term::Atom(a) if a.args.is_empty() || a.name.0 == "/" => segments(spec)
+            .map(|path| vec![Source::File(dir.join(path))])
+            .unwrap_or_default(),
+        Term::Atom(Atom { name, args, .. }) if args.len() == 1 && depth < MAX_ALIAS_DEPTH => {
+            let segments = match segments(&args[0]) {
+                Some(segments) => segments,
+                None => return vec![],
+            };
+
+            let mut found: Vec<_> = paths
+                .iter()
+                .filter(|(alias, _)| *alias == name.0)
+                .flat_map(|(_, alias_dir)| candidates(alias_dir, dir, paths, depth + 1))
+                .map(|source| match source {
+                    Source::File(path) => Source::File(path.join(&segments)),
+                    Source::Bundled(library) => {
+                        Source::Bundled(format!("{}/{}", library, segments))
+                    }
+                })
+                .collect();
+
+            if name.0 == "library" {
+                found.push(Source::Bundled(segments));
+            }
+
+            found
+        }
+        _ => vec![],
+    }
+}
+
+/// Reads a path such as `dcg/basics`.
+fn segments(t: &Term) -> Option<String> {
+    match t {
+        Term::Atom(a) if a.args.is_empty() => Some(a.name.0.clone()),
+        Term::Atom(Atom { name, args, .. }) if name.0 == "/" && args.len() == 2 => {
+            Some(format!("{}/{}", segments(&args[0])?, segments(&args[1])?))
+        }
+        _ => None,
+    }
+}
+
+/// Replaces every `{|Syntax||Text|}` quasi quotation in `kb` by the result of calling the handler
+/// `Syntax` with the quoted text and an unbound result, as in `call(Syntax, Text, Result)`.
+pub fn expand_quasi_quotations(kb: KnowledgeBase) -> Result<KnowledgeBase, String> {
+    // A directive of `kb` may turn autoloading of the library handlers off.
+    builtins::reset_flags(&kb);
+    let handlers = kb.clone();
+
+    kb.into_iter()
+        .map(|Assertion { head, clause }| {
+            let head = expand_atom(&handlers, head)?;
+            let clause = clause
+                .into_iter()
+                .map(|a| expand_atom(&handlers, a))
+                .collect::<Result<_, _>>()?;
+
+            Ok(Assertion::new(head, clause))
+        })
+        .collect()
+}
+
+fn expand_atom(handlers: &[Assertion], a: Atom) -> Result<Atom, String> {
+    let args = a
+        .args
+        .into_iter()
+        .map(|t| expand_term(handlers, t))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Atom::new(&a.name.0, args))
+}
+
+fn expand_term(handlers: &[Assertion], t: Term) -> Result<Term, String> {
+    match t {
+        Term::Atom(Atom {
+            name: Const(ref name),
+            ref args,
+            ..
+        }) if name == "$quasi_quotation" && args.len() == 2 => {
+            let result = Term::Var(Var::new("Result#", 0));
+            let goal = Atom::new(
+                "call",
+                vec![args[0].clone(), args[1].clone(), result.clone()],
+            );
+
+            match solve_once(handlers, goal) {
+                Some(env) => Ok(rename_quasi_vars(&env.substitute_term(&result))),
+                None => Err(format!("quasi quotation handler {} failed", args[0])),
+            }
+        }
+        Term::Atom(a) => Ok(Term::Atom(expand_atom(handlers, a)?)),
+        Term::PartialString(text, tail) => Ok(Term::PartialString(
+            text,
+            Box::new(expand_term(handlers, *tail)?),
+        )),
+        t => Ok(t),
+    }
+}
+
+/// Gives the variables a quasi quotation handler made the names of clause variables, so that the
+/// clause the expansion is read into renames them apart as it does its own.
+fn rename_quasi_vars(t: &Term) -> Term {
+    match t {
+        Term::Var(Var(name, n)) if *n > 0 => Term::Var(Var::new(&format!("{}#{}", name, n), 0)),
+        Term::Atom(a) => Term::Atom(Atom::new(
+            &a.name.0,
+            a.args.iter().map(rename_quasi_vars).collect(),
+        )),
+        Term::PartialString(text, tail) => {
+            Term::PartialString(text.clone(), Box::new(rename_quasi_vars(tail)))
+        }
+        t => t.clone(),
+    }
+}
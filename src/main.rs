@@ -1,31 +1,134 @@
 use bfg_prolog::ast;
-use bfg_prolog::ast::{Assertion, Atom, Clause, Const, Term};
-use bfg_prolog::solve_toplevel;
+use bfg_prolog::ast::{Assertion, Atom, Clause, Const, Number, Term};
+use bfg_prolog::dcg;
+use bfg_prolog::loader;
+use bfg_prolog::loader::{
+    consult, consult_user, consults_user, initialization_goals, load, load_goals, Initialization,
+};
+use bfg_prolog::tokenizer;
+use bfg_prolog::{
+    catch_interrupts, coverage, saved, set_argv, solve_directive, solve_quietly, solve_toplevel,
+};
 use lalrpop_util::lalrpop_mod;
-use std::fs::read_to_string;
 use std::io::Write;
+use std::path::Path;
+use std::process::Command;
 
-lalrpop_mod!(pub parser);
+lalrpop_mod!(#[allow(clippy::all, unused_parens)] pub parser);
 
 fn main() {
-    let mut source = Vec::new();
+    let mut source = match saved::embedded() {
+        // A saved executable runs its program, which is given every argument.
+        Some(program) => {
+            set_argv(std::env::args().collect());
+            program.unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            })
+        }
+        None => {
+            let mut args = std::env::args();
+            let program = args.next();
+            let file = args.next();
+
+            // The program sees its own name followed by the arguments after the file it runs.
+            let mut rest: Vec<_> = args.collect();
+            if rest.first().map(String::as_str) == Some("--") {
+                rest.remove(0);
+            }
+
+            match file.as_deref() {
+                Some("--xref") => std::process::exit(xref(&rest)),
+                Some("--coverage") => std::process::exit(coverage(&rest)),
+                Some("--doc") => std::process::exit(doc(&rest)),
+                _ => (),
+            }
+
+            set_argv(program.into_iter().chain(rest).collect());
+
+            // A program that cannot be loaded leaves the toplevel running without it.
+            match file.map(|path| read_source_code(&path)) {
+                Some(Ok(source)) => source,
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    Vec::new()
+                }
+                None => Vec::new(),
+            }
+        }
+    };
+
+    initialize(&source);
+
+    if let Some((_, goal)) = initialization_goals(&source)
+        .into_iter()
+        .find(|(when, _)| *when == Initialization::Main)
+    {
+        std::process::exit(if solve_quietly(&source, goal) { 0 } else { 1 });
+    }
+
+    if let Err(e) = catch_interrupts() {
+        eprintln!("Warning: interrupts cannot be caught: {}", e);
+    }
+
     let consult_const = Const::new("consult");
+    let use_module_const = Const::new("use_module");
 
     loop {
         print!("?- ");
         std::io::stdout().flush().expect("Could not flush stdout");
 
         let mut input_buffer = String::new();
-        std::io::stdin()
+        if std::io::stdin()
             .read_line(&mut input_buffer)
-            .expect("error reading input");
+            .expect("error reading input")
+            == 0
+        {
+            println!();
+            break;
+        }
 
-        let query = parse_query(&input_buffer);
+        let query = match parse_query(&input_buffer) {
+            Ok(query) => query,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
 
-        if query.len() == 1 && query[0].name == consult_const && query[0].arity == 1 {
+        if consults_user(&query) {
+            match read_user().and_then(|text| consult_user(&text)) {
+                Ok(kb) => {
+                    source = kb.iter().cloned().chain(source).collect();
+                    run_directives(&source, &kb);
+                    solve_toplevel(true, &source, vec![]);
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        } else if query.len() == 1 && query[0].name == consult_const && query[0].arity == 1 {
             if let Term::Atom(Atom { name: Const(p), .. }) = &query[0].args[0] {
-                source = read_source_code(p);
-                solve_toplevel(true, &source, (&query[1..]).to_vec());
+                match read_source_code(p) {
+                    Ok(kb) => {
+                        source = kb;
+                        initialize(&source);
+                        solve_toplevel(true, &source, query[1..].to_vec());
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        } else if query.len() == 1 && query[0] == Atom::new("make", vec![]) {
+            make(&mut source);
+        } else if query.len() == 1 && query[0].name.0 == "edit" && query[0].arity == 1 {
+            edit(&query[0].args[0], &mut source);
+        } else if query.len() == 1 && query[0].name == use_module_const && query[0].arity == 1 {
+            let directive = Atom::new(":-", vec![Term::Atom(query[0].clone())]);
+
+            match load(vec![Assertion::new(directive, vec![])], Path::new("")) {
+                Ok(kb) => {
+                    source = kb.into_iter().chain(source).collect();
+                    solve_toplevel(true, &source, vec![]);
+                }
+                Err(e) => eprintln!("{}", e),
             }
         } else {
             solve_toplevel(true, &source, query);
@@ -33,20 +136,212 @@ fn main() {
     }
 }
 
-fn read_source_code(path: &str) -> Vec<Assertion> {
-    let s = read_to_string(String::from(path)).unwrap();
-    let mut source = parse_code(&s);
-    source.reverse();
+/// Runs the directives of the program once it is loaded.
+fn initialize(source: &[Assertion]) {
+    run_directives(source, source);
+}
+
+/// Runs the directives of the clauses `loaded` of the program `source` in the order they were
+/// written, warning of each that fails or raises an exception.
+fn run_directives(source: &[Assertion], loaded: &[Assertion]) {
+    for goal in load_goals(loaded) {
+        match solve_directive(source, goal.clone()) {
+            Ok(true) => (),
+            Ok(false) => eprintln!("Warning: goal (directive) failed: {}", goal),
+            Err(ball) => eprintln!("Warning: goal (directive) raised {}: {}", ball, goal),
+        }
+    }
+}
+
+/// Reloads the files of the program that changed since they were loaded, as `make/0` asks, and runs
+/// the goals each asks to run once it is loaded again.
+fn make(source: &mut Vec<Assertion>) {
+    match loader::make(source) {
+        Ok(reloaded) => {
+            for (path, clauses) in reloaded {
+                eprintln!("% Reloaded {}", path.display());
+                run_directives(source, &clauses);
+            }
+
+            solve_toplevel(true, source, vec![]);
+        }
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+/// Opens the file that defines the predicate `spec`, a name or a `Name/Arity` indicator, at the
+/// line of its first clause in the editor that `EDITOR` names, as `edit/1` asks. The files that
+/// changed are reloaded once the editor exits.
+fn edit(spec: &Term, source: &mut Vec<Assertion>) {
+    let (name, arity) = match spec {
+        Term::Atom(a) if a.arity == 0 => (&a.name.0, None),
+        Term::Atom(a) if a.name.0 == "/" && a.arity == 2 => match (&a.args[0], &a.args[1]) {
+            (Term::Atom(name), Term::Number(Number::Int(arity))) if name.arity == 0 => {
+                (&name.name.0, Some(*arity as usize))
+            }
+            _ => {
+                eprintln!("Warning: {} is not a predicate indicator", spec);
+                return;
+            }
+        },
+        _ => {
+            eprintln!("Warning: {} is not a predicate indicator", spec);
+            return;
+        }
+    };
+    let (path, line) = match loader::source_location(name, arity) {
+        Some(location) => location,
+        None => {
+            eprintln!("Warning: no source for {}", spec);
+            return;
+        }
+    };
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| String::from("vi"));
+    // The editor may be given with arguments of its own, as in `code --wait`.
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+
+    match Command::new(program)
+        .args(words)
+        .arg(format!("+{}", line))
+        .arg(&path)
+        .status()
+    {
+        Ok(status) if status.success() => make(source),
+        Ok(status) => eprintln!("Warning: {} exited with {}", program, status),
+        Err(e) => eprintln!("{}: {}", program, e),
+    }
+}
+
+/// Cross-references the programs at `paths`, as `bfg-prolog --xref File...` asks, writing what it
+/// finds in each. Gives back the status to exit with, which is 1 if it found anything.
+fn xref(paths: &[String]) -> i32 {
+    let mut status = 0;
+
+    for path in paths {
+        match consult(path) {
+            Ok(kb) => {
+                for issue in bfg_prolog::xref::check(&kb) {
+                    println!("{}: {}", path, issue);
+                    status = 1;
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                status = 1;
+            }
+        }
+    }
+
+    status
+}
+
+/// Runs the programs at `paths` together, as `bfg-prolog --coverage File...` asks, recording the
+/// clauses their initialization goals enter, and writes how many of the clauses of each predicate
+/// of each program were entered. Gives back the status to exit with, which is 1 if a program
+/// cannot be read or its main goal fails.
+fn coverage(paths: &[String]) -> i32 {
+    let mut programs = Vec::new();
+
+    for path in paths {
+        match consult(path) {
+            Ok(kb) => programs.push((path, kb)),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        }
+    }
+
+    // Later programs come first, as the solver reads the program from the end.
+    let source: Vec<_> = programs
+        .iter()
+        .rev()
+        .flat_map(|(_, kb)| kb.iter().cloned())
+        .collect();
+
+    coverage::start();
+    initialize(&source);
+
+    let succeeded = initialization_goals(&source)
+        .into_iter()
+        .filter(|(when, _)| *when == Initialization::Main)
+        .all(|(_, goal)| solve_quietly(&source, goal));
+    let entered = coverage::stop();
+
+    for (path, kb) in &programs {
+        let report = coverage::report(kb, &entered);
+
+        for c in &report {
+            println!("{}: {}", path, c);
+        }
+
+        println!("{}: Total: {}", path, coverage::summary(&report));
+    }
+
+    if succeeded {
+        0
+    } else {
+        1
+    }
+}
+
+/// Writes the documentation of the programs at `paths` in the format `bfg-prolog --doc Format
+/// File...` asks for, `markdown` or `html`. Gives back the status to exit with.
+fn doc(args: &[String]) -> i32 {
+    let write = match args.first().map(String::as_str) {
+        Some("markdown") => bfg_prolog::doc::markdown,
+        Some("html") => bfg_prolog::doc::html,
+        _ => {
+            eprintln!("usage: bfg-prolog --doc markdown|html File...");
+            return 1;
+        }
+    };
+    let mut source = Vec::new();
+
+    for path in &args[1..] {
+        match consult(path) {
+            // Later programs come first, as the solver reads the program from the end.
+            Ok(kb) => source = kb.into_iter().chain(source).collect(),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        }
+    }
+
+    print!("{}", write(&source));
+    0
+}
+
+/// Reads the text typed at the terminal for `[user]` until the end of input, prompting for each
+/// line.
+fn read_user() -> Result<String, String> {
+    let mut text = String::new();
+
+    loop {
+        print!("|: ");
+        std::io::stdout().flush().expect("Could not flush stdout");
+
+        match std::io::stdin().read_line(&mut text) {
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
 
-    source
+    println!();
+    Ok(text)
 }
 
-fn parse_code(code: &str) -> Vec<Assertion> {
-    let code_parser = parser::CodeParser::new();
-    code_parser.parse(code).unwrap()
+fn read_source_code(path: &str) -> Result<Vec<Assertion>, String> {
+    consult(path)
 }
 
-fn parse_query(query: &str) -> Clause {
+/// Reads the query typed at the toplevel, where one that cannot be read is a syntax error.
+fn parse_query(query: &str) -> Result<Clause, String> {
     let clause_parser = parser::ClauseParser::new();
-    clause_parser.parse(query).unwrap()
+    clause_parser
+        .parse(tokenizer::lex(query))
+        .map_err(|e| format!("Syntax error: {}", e))
 }
@@ -0,0 +1,82 @@
+use crate::ast::{Assertion, Atom, Const, Term};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// The module that every predicate belongs to.
+pub(crate) const USER: &str = "user";
+
+thread_local! {
+    static META_PREDICATES: RefCell<HashMap<(Const, usize), Vec<Term>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Reads the `:- meta_predicate` declarations of `kb`, forgetting any earlier ones.
+pub(crate) fn reset(kb: &[Assertion]) {
+    let mut meta = HashMap::new();
+
+    for a in kb {
+        if a.head.name.0 == ":-" && a.head.arity == 1 {
+            if let Term::Atom(Atom { name, args, .. }) = &a.head.args[0] {
+                if name.0 == "meta_predicate" && args.len() == 1 {
+                    declare(&args[0], &mut meta);
+                }
+            }
+        }
+    }
+
+    META_PREDICATES.with(|m| *m.borrow_mut() = meta);
+}
+
+fn declare(spec: &Term, meta: &mut HashMap<(Const, usize), Vec<Term>>) {
+    if let Term::Atom(Atom { name, args, .. }) = spec {
+        match (&name.0[..], &args[..]) {
+            (",", [x, y]) => {
+                declare(x, meta);
+                declare(y, meta);
+            }
+            (_, []) => (),
+            _ => {
+                meta.insert((name.clone(), args.len()), args.clone());
+            }
+        }
+    }
+}
+
+/// Whether an argument declared with `spec` is a goal or other module-sensitive term.
+fn is_meta(spec: &Term) -> bool {
+    match spec {
+        Term::Number(_) => true,
+        Term::Atom(a) => matches!(&a.name.0[..], ":" | "^" | "//"),
+        _ => false,
+    }
+}
+
+fn is_qualified(t: &Term) -> bool {
+    matches!(t, Term::Atom(a) if a.name.0 == ":" && a.arity == 2)
+}
+
+/// Qualifies the meta-arguments of a call to a declared meta-predicate with the module of the
+/// caller, leaving arguments that are already qualified as they are. Returns None if `goal` is
+/// not a meta-predicate.
+pub(crate) fn qualify(goal: &Atom) -> Option<Atom> {
+    let specs =
+        META_PREDICATES.with(|m| m.borrow().get(&(goal.name.clone(), goal.arity)).cloned())?;
+
+    let args = goal
+        .args
+        .iter()
+        .zip(&specs)
+        .map(|(arg, spec)| {
+            if is_meta(spec) && !is_qualified(arg) {
+                Term::Atom(Atom::new(
+                    ":",
+                    vec![Term::Atom(Atom::new(USER, vec![])), arg.clone()],
+                ))
+            } else {
+                arg.clone()
+            }
+        })
+        .collect();
+
+    Some(Atom::new(&goal.name.0, args))
+}
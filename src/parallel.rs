@@ -0,0 +1,195 @@
+use crate::ast::{Assertion, Atom, Const, Number, Term};
+use crate::builtins::{locals, Branch};
+use crate::{
+    fresh, rename_fresh, renumber_atom, replace_cut, resume_fresh, Choicepoint, Environment,
+    SolveErr,
+};
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+thread_local! {
+    /// The predicates declared `:- parallel`.
+    static PARALLEL: RefCell<HashSet<(Const, usize)>> = RefCell::new(HashSet::new());
+    /// Whether this thread is a worker exploring a clause, where calls run one clause after
+    /// another.
+    static WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+fn declare(spec: &Term, parallel: &mut HashSet<(Const, usize)>) {
+    if let Term::Atom(Atom { name, args, .. }) = spec {
+        match (&name.0[..], &args[..]) {
+            (",", [x, y]) => {
+                declare(x, parallel);
+                declare(y, parallel);
+            }
+            ("/", [Term::Atom(name), Term::Number(Number::Int(arity))]) if *arity >= 0 => {
+                parallel.insert((name.name.clone(), *arity as usize));
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Reads the `:- parallel Name/Arity` declarations of `kb`.
+pub(crate) fn reset(kb: &[Assertion]) {
+    let mut parallel = HashSet::new();
+
+    for a in kb {
+        if a.head.name.0 == ":-" && a.head.arity == 1 {
+            if let Term::Atom(Atom { name, args, .. }) = &a.head.args[0] {
+                if name.0 == "parallel" && args.len() == 1 {
+                    declare(&args[0], &mut parallel);
+                }
+            }
+        }
+    }
+
+    PARALLEL.with(|p| *p.borrow_mut() = parallel);
+}
+
+/// What a worker found for one clause: an answer, as the goal it proves, or the exception that
+/// ended the search of the clause.
+type Found = (usize, Result<Term, Term>);
+
+/// Runs a call to a parallel predicate by looking for the answers of its clauses on worker
+/// threads, returning a branch for each answer in the order a sequential search would find them.
+/// Every answer is found before the first is given, and a cut in a clause only prunes the
+/// choices within that clause. An exception raised by a clause is raised after the answers of
+/// the clauses before it.
+pub(crate) fn call(
+    env: &Environment,
+    kb: &[Assertion],
+    goal: &Atom,
+    n: usize,
+) -> Option<Vec<Branch>> {
+    let declared = PARALLEL.with(|p| {
+        let p = p.borrow();
+        !p.is_empty() && p.contains(&(goal.name.clone(), goal.arity))
+    });
+
+    if !declared || WORKER.with(Cell::get) {
+        return None;
+    }
+
+    // The solver takes the clauses of the program from the end.
+    let clauses: Vec<&Assertion> = kb
+        .iter()
+        .rev()
+        .filter(|a| a.head.name == goal.name && a.head.arity == goal.arity)
+        .collect();
+
+    if clauses.len() < 2 {
+        return None;
+    }
+
+    let goal = match env.substitute_term(&Term::Atom(goal.clone())) {
+        Term::Atom(goal) => goal,
+        _ => unreachable!("substitution keeps atoms"),
+    };
+
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(clauses.len());
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel::<Found>();
+    // The workers rename apart from here on, clear of the variables of the goal.
+    let (from, database) = (fresh(), locals::database());
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (next, clauses, goal, database) = (&next, &clauses, &goal, database.clone());
+
+            scope.spawn(move || {
+                WORKER.with(|worker| worker.set(true));
+                crate::reset(kb);
+                resume_fresh(from);
+                locals::share(database);
+
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+
+                    match clauses.get(i) {
+                        Some(clause) => explore(kb, goal, clause, n, |found| {
+                            let _ = sender.send((i, found));
+                        }),
+                        None => break,
+                    }
+                }
+            });
+        }
+    });
+    drop(sender);
+
+    let mut found: Vec<Found> = receiver.into_iter().collect();
+    // Stable, so that the answers of each clause stay in the order they were found.
+    found.sort_by_key(|(i, _)| *i);
+
+    let goal = Term::Atom(goal);
+    let mut branches = Vec::new();
+
+    for (_, answer) in found {
+        match answer {
+            Ok(answer) => branches.extend(
+                env.clone()
+                    .unify_terms(&goal, &rename_fresh(&answer))
+                    .map(|env| (env, vec![])),
+            ),
+            Err(ball) => {
+                branches.push((env.clone(), vec![Atom::new("throw", vec![ball])]));
+                break;
+            }
+        }
+    }
+
+    Some(branches)
+}
+
+/// Finds every answer of `goal` by `clause` alone, passing each to `found`.
+fn explore(
+    kb: &[Assertion],
+    goal: &Atom,
+    clause: &Assertion,
+    n: usize,
+    mut found: impl FnMut(Result<Term, Term>),
+) {
+    let k = fresh();
+    let head = renumber_atom(k, &clause.head);
+
+    let env = match Environment::new().unify_atoms(goal, &head) {
+        Ok(env) => env,
+        Err(_) => return,
+    };
+
+    let body = clause
+        .clause
+        .iter()
+        .rev()
+        .map(|a| replace_cut(&renumber_atom(k, a), 0))
+        .collect();
+    let goal = Term::Atom(goal.clone());
+    let mut s = env.solve(Vec::new(), kb, None, body, n + 1);
+
+    loop {
+        let (env, mut ch) = match s {
+            Ok(solution) => solution,
+            Err(SolveErr::NoSolution) => return,
+            Err(SolveErr::Exception(ball)) => return found(Err(ball)),
+        };
+
+        found(Ok(env.substitute_term(&goal)));
+
+        s = match ch.pop() {
+            None => return,
+            Some(Choicepoint {
+                assertions,
+                environment,
+                clause,
+                depth,
+                ..
+            }) => environment.solve(ch, kb, assertions, clause, depth),
+        };
+    }
+}
@@ -0,0 +1,235 @@
+//! Python bindings, built into a module named `bfg_prolog` by `maturin build`, which turns on the
+//! `python` feature.
+//!
+//! ```python
+//! from bfg_prolog import Machine, Term
+//!
+//! machine = Machine()
+//! machine.consult("parent(tom, bob). parent(tom, liz).")
+//!
+//! for answer in machine.query("parent(tom, X), member(X, Xs).", {"Xs": [Term("bob")]}):
+//!     print(answer["X"])  # bob
+//! ```
+//!
+//! Python values passed in are converted as `ToTerm` converts the Rust values they correspond to:
+//! `True` and `False` are the atoms `true` and `false`, a `str` is a Prolog string, lists and
+//! tuples are lists, and a dict is the list of its `Key-Value` pairs. `Term.value()` converts the
+//! other way.
+
+use crate::ast::{Atom, Number, Term, Var};
+use crate::builtins::list_items;
+use crate::convert::{functor_args, ToTerm};
+use crate::loader::consult_into;
+use crate::parser::{ClauseParser, TermParser};
+use crate::search::Search;
+use crate::tokenizer::lex;
+use crate::KnowledgeBase;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PySyntaxError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+
+create_exception!(
+    bfg_prolog,
+    PrologError,
+    PyException,
+    "An exception raised by a query and not caught, with the ball thrown as its argument."
+);
+
+/// A term, read from text as in `Term("f(X, a)")` or found in an answer.
+#[pyclass(
+    name = "Term",
+    module = "bfg_prolog",
+    frozen,
+    eq,
+    hash,
+    skip_from_py_object
+)]
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct PyTerm(Term);
+
+/// The term a Python value passed in stands for.
+fn term(value: &Bound<PyAny>) -> PyResult<Term> {
+    if let Ok(t) = value.cast::<PyTerm>() {
+        Ok(t.get().0.clone())
+    } else if let Ok(b) = value.cast::<PyBool>() {
+        Ok(b.is_true().to_term())
+    } else if let Ok(i) = value.cast::<PyInt>() {
+        Ok(i.extract::<i64>()?.to_term())
+    } else if let Ok(x) = value.cast::<PyFloat>() {
+        Ok(x.value().to_term())
+    } else if let Ok(s) = value.cast::<PyString>() {
+        Ok(s.to_str()?.to_string().to_term())
+    } else if value.is_none() {
+        Ok(None::<Term>.to_term())
+    } else if let Ok(items) = value.cast::<PyList>() {
+        let items = items.iter().map(|x| term(&x)).collect::<PyResult<_>>()?;
+        Ok(Term::list(items, Term::nil()))
+    } else if let Ok(items) = value.cast::<PyTuple>() {
+        let items = items.iter().map(|x| term(&x)).collect::<PyResult<_>>()?;
+        Ok(Term::list(items, Term::nil()))
+    } else if let Ok(dict) = value.cast::<PyDict>() {
+        let pairs = dict
+            .iter()
+            .map(|(k, v)| Ok(Term::Atom(Atom::new("-", vec![term(&k)?, term(&v)?]))))
+            .collect::<PyResult<_>>()?;
+        Ok(Term::list(pairs, Term::nil()))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "cannot convert {} to a term",
+            value.get_type().name()?
+        )))
+    }
+}
+
+/// The Python value for `t`, which is `t` itself unless it is a number, an atom, a string or a
+/// list.
+fn value<'py>(py: Python<'py>, t: &Term) -> PyResult<Bound<'py, PyAny>> {
+    match t {
+        Term::Number(Number::Int(i)) => Ok(i.into_pyobject(py)?.into_any()),
+        Term::Number(Number::Float(x)) => Ok(x.into_pyobject(py)?.into_any()),
+        Term::String(s) => Ok(PyString::new(py, s).into_any()),
+        _ if functor_args(t, "true", 0).is_some() => {
+            Ok(PyBool::new(py, true).to_owned().into_any())
+        }
+        _ if functor_args(t, "false", 0).is_some() => {
+            Ok(PyBool::new(py, false).to_owned().into_any())
+        }
+        _ if functor_args(t, "none", 0).is_some() => Ok(py.None().into_bound(py)),
+        Term::Atom(a) if a.arity == 0 && !t.is_nil() => Ok(PyString::new(py, &a.name.0).into_any()),
+        _ => match list_items(t) {
+            Some(items) => {
+                let items = items
+                    .iter()
+                    .map(|t| value(py, t))
+                    .collect::<PyResult<Vec<_>>>()?;
+                Ok(PyList::new(py, items)?.into_any())
+            }
+            None => Ok(Bound::new(py, PyTerm(t.clone()))?.into_any()),
+        },
+    }
+}
+
+#[pymethods]
+impl PyTerm {
+    #[new]
+    fn new(text: &str) -> PyResult<Self> {
+        TermParser::new()
+            .parse(lex(text))
+            .map(PyTerm)
+            .map_err(|e| PySyntaxError::new_err(e.to_string()))
+    }
+
+    /// The name of an atom or compound term, or `None` for other terms.
+    #[getter]
+    fn name(&self) -> Option<String> {
+        match &self.0 {
+            Term::Atom(a) => Some(a.name.0.clone()),
+            _ => None,
+        }
+    }
+
+    /// The arguments of a compound term, which other terms have none of.
+    #[getter]
+    fn args(&self) -> Vec<PyTerm> {
+        match &self.0 {
+            Term::Atom(a) => a.args.iter().cloned().map(PyTerm).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The term as a Python value: a number, `bool`, `None` for the atom `none`, a `str` for
+    /// another atom or a string, a list for a list, and the term itself otherwise.
+    fn value<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        value(py, &self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Term({:?})", self.0.to_string())
+    }
+}
+
+/// A program, to which sources are added with `consult` and which answers queries with `query`.
+#[pyclass(module = "bfg_prolog")]
+#[derive(Default)]
+struct Machine {
+    kb: KnowledgeBase,
+}
+
+#[pymethods]
+impl Machine {
+    #[new]
+    fn new() -> Self {
+        Machine::default()
+    }
+
+    /// Adds the clauses of `source` to the program, after loading the files it asks for and
+    /// running its `initialization/1` goals.
+    fn consult(&mut self, source: &str) -> PyResult<()> {
+        consult_into(&mut self.kb, source).map_err(PyValueError::new_err)
+    }
+
+    /// The answers to `goal`, each a dict from the names of the variables of the query to the
+    /// terms they are bound to. The values of `bindings` are given to the variables they are the
+    /// names of before the query runs.
+    #[pyo3(signature = (goal, bindings = None))]
+    fn query(&self, goal: &str, bindings: Option<&Bound<PyDict>>) -> PyResult<Query> {
+        let mut goals = ClauseParser::new()
+            .parse(lex(goal))
+            .map_err(|e| PySyntaxError::new_err(e.to_string()))?;
+
+        for (name, value) in bindings.into_iter().flat_map(|b| b.iter()) {
+            let x = Term::Var(Var::new(&name.extract::<String>()?, 0));
+            goals.insert(0, Atom::new("=", vec![x, term(&value)?]));
+        }
+
+        Ok(Query {
+            search: Search::new(&self.kb, goals),
+        })
+    }
+}
+
+/// The answers to a query, found one at a time as the query is iterated over.
+#[pyclass(module = "bfg_prolog")]
+struct Query {
+    search: Search,
+}
+
+#[pymethods]
+impl Query {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        // Other Python threads run while the search does.
+        let env = match py.detach(|| self.search.next()) {
+            Ok(Some(env)) => env,
+            Ok(None) => return Ok(None),
+            Err(ball) => return Err(PrologError::new_err(PyTerm(ball))),
+        };
+
+        let answer = PyDict::new(py);
+
+        for Var(name, _) in self.search.vars() {
+            if let Some(t) = self.search.binding(&env, name) {
+                answer.set_item(name, PyTerm(t))?;
+            }
+        }
+
+        Ok(Some(answer))
+    }
+}
+
+#[pymodule]
+fn bfg_prolog(m: &Bound<PyModule>) -> PyResult<()> {
+    m.add_class::<Machine>()?;
+    m.add_class::<Query>()?;
+    m.add_class::<PyTerm>()?;
+    m.add("PrologError", m.py().get_type::<PrologError>())?;
+    Ok(())
+}
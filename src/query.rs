@@ -0,0 +1,159 @@
+//! Queries answered as a `Stream`, for programs embedding the solver in an async runtime.
+//!
+//! Each poll runs the search for a bounded number of goals, and returns `Poll::Pending` after
+//! waking its task when the budget runs out before an answer is found, so that a long search
+//! shares the executor thread it runs on with the other tasks.
+
+use crate::ast::{Assertion, Clause, Term};
+use crate::{
+    fresh, prepare, replace_cut, reset, resume_fresh, signals, Choicepoint, Environment,
+    KnowledgeBase, SolveErr,
+};
+use futures_core::Stream;
+use std::cell::Cell;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread::ThreadId;
+
+/// How many goals a poll runs before it gives the executor back.
+const STEPS: usize = 1024;
+
+thread_local! {
+    /// How many searches are running on this thread, one inside the other.
+    static LEVEL: Cell<usize> = const { Cell::new(0) };
+    /// The level of the search a poll is running, and the goals it may still run.
+    static BUDGET: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+    static PAUSED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Counts a search as running until `leave` is called as it returns.
+pub(crate) fn enter() {
+    LEVEL.with(|level| level.set(level.get() + 1));
+}
+
+pub(crate) fn leave() {
+    LEVEL.with(|level| level.set(level.get() - 1));
+}
+
+/// Whether the search polled by a stream should stop before its next goal. Only the search the
+/// stream started pauses, since a search inside a builtin such as `findall/3` has no way to
+/// resume.
+pub(crate) fn pause() -> bool {
+    let level = LEVEL.with(Cell::get);
+
+    match BUDGET.with(Cell::get) {
+        Some((at, 0)) if at == level => {
+            BUDGET.with(|budget| budget.set(None));
+            PAUSED.with(|paused| paused.set(true));
+            true
+        }
+        Some((at, steps)) if at == level => {
+            BUDGET.with(|budget| budget.set(Some((at, steps - 1))));
+            false
+        }
+        _ => false,
+    }
+}
+
+enum State {
+    Start(Clause),
+    /// Between polls, with the choicepoints to go on from.
+    Search(Vec<Choicepoint>),
+    Done,
+}
+
+/// The answers to a query, as returned by `query_async`.
+///
+/// An answer is the text `solve_toplevel` prints for it, such as `X = 1` or `Yes`, and an
+/// exception the query does not catch is given as an error, after which the stream ends.
+pub struct Answers {
+    kb: KnowledgeBase,
+    state: State,
+    /// The thread of the last poll, whose declarations of the program were read.
+    thread: Option<ThreadId>,
+    /// Where renaming apart had got to when the last poll ended, which the next goes on from on
+    /// any thread, after whatever other queries ran in between.
+    fresh: usize,
+}
+
+/// Starts answering the query `goals` against `kb`. The search only runs while the stream is
+/// polled, and may go on from one thread to another between polls.
+pub fn query_async(kb: &[Assertion], goals: Clause) -> Answers {
+    let goals = goals.iter().rev().map(|g| replace_cut(g, 0)).collect();
+
+    Answers {
+        kb: prepare(kb),
+        state: State::Start(goals),
+        thread: Some(std::thread::current().id()),
+        fresh: 0,
+    }
+}
+
+impl Answers {
+    fn step(&mut self) -> Option<Poll<Option<Result<String, Term>>>> {
+        let here = std::thread::current().id();
+
+        if self.thread != Some(here) {
+            reset(&self.kb);
+            self.thread = Some(here);
+        }
+
+        resume_fresh(self.fresh);
+
+        let level = LEVEL.with(Cell::get);
+        BUDGET.with(|budget| budget.set(Some((level + 1, STEPS))));
+        PAUSED.with(|paused| paused.set(false));
+
+        let result = match std::mem::replace(&mut self.state, State::Done) {
+            State::Start(goals) => {
+                signals::clear();
+                Environment::new().solve(Vec::new(), &self.kb, None, goals, 1)
+            }
+            State::Search(mut ch) => match ch.pop() {
+                Some(Choicepoint {
+                    assertions,
+                    environment,
+                    clause,
+                    depth,
+                    ..
+                }) => environment.solve(ch, &self.kb, assertions, clause, depth),
+                None => Err(SolveErr::NoSolution),
+            },
+            State::Done => return Some(Poll::Ready(None)),
+        };
+
+        BUDGET.with(|budget| budget.set(None));
+        self.fresh = fresh();
+
+        match result {
+            Ok((_, ch)) if PAUSED.with(Cell::get) => {
+                self.state = State::Search(ch);
+                None
+            }
+            Ok((env, ch)) => {
+                if !ch.is_empty() {
+                    self.state = State::Search(ch);
+                }
+
+                let answer = env.to_string().trim().to_string();
+                Some(Poll::Ready(Some(Ok(answer))))
+            }
+            Err(SolveErr::NoSolution) => Some(Poll::Ready(None)),
+            Err(SolveErr::Exception(ball)) => Some(Poll::Ready(Some(Err(ball)))),
+        }
+    }
+}
+
+impl Stream for Answers {
+    type Item = Result<String, Term>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        match self.get_mut().step() {
+            Some(poll) => poll,
+            None => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+}
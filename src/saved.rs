@@ -0,0 +1,120 @@
+//! Saved states: a program written by `qsave_program/1,2` as an image that boots without reading
+//! its sources, either on its own or appended to a copy of the toplevel executable.
+//!
+//! An image is `MAGIC` followed by each clause of the program encoded by `fastrw`, in the order
+//! the knowledge base keeps them. An executable carrying one ends with the image, its length as
+//! eight little-endian bytes and `TRAILER`.
+
+use crate::ast::Assertion;
+use crate::{fastrw, KnowledgeBase};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// The bytes an image starts with, the last of which is the version of the format.
+const MAGIC: [u8; 4] = [0xBF, b'Q', b'S', 1];
+
+/// The bytes an executable carrying an image ends with.
+const TRAILER: [u8; 8] = *b"BFGSAVED";
+
+/// Encodes the program `kb` as an image.
+pub fn image(kb: &[Assertion]) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+
+    for a in kb {
+        bytes.extend(fastrw::encode(&a.to_term()));
+    }
+
+    bytes
+}
+
+/// The program in `bytes`, or None if they are not an image.
+pub fn program(bytes: &[u8]) -> Option<Result<KnowledgeBase, String>> {
+    let mut input = bytes.strip_prefix(&MAGIC[..])?;
+    let mut kb = Vec::new();
+
+    loop {
+        match fastrw::read(&mut input) {
+            Ok(Some(t)) => match Assertion::from_term(t) {
+                Some(a) => kb.push(a),
+                None => {
+                    return Some(Err(String::from(
+                        "Saved state holds a term that is not a clause",
+                    )))
+                }
+            },
+            Ok(None) => return Some(Ok(kb)),
+            Err(e) => return Some(Err(format!("Saved state is damaged: {}", e))),
+        }
+    }
+}
+
+/// The length of the image at the end of `file` with the sixteen bytes that follow it, which is
+/// zero if it carries none.
+fn embedded_len(file: &mut File) -> std::io::Result<u64> {
+    let size = file.seek(SeekFrom::End(0))?;
+    let (mut len, mut trailer) = ([0; 8], [0; 8]);
+
+    if size < 16 {
+        return Ok(0);
+    }
+
+    file.seek(SeekFrom::End(-16))?;
+    file.read_exact(&mut len)?;
+    file.read_exact(&mut trailer)?;
+
+    let len = u64::from_le_bytes(len);
+
+    if trailer != TRAILER || len > size - 16 {
+        return Ok(0);
+    }
+
+    Ok(len + 16)
+}
+
+/// The program appended to the running executable, if it was written by
+/// `qsave_program(File, [stand_alone(true)])`.
+pub fn embedded() -> Option<Result<KnowledgeBase, String>> {
+    let mut file = File::open(std::env::current_exe().ok()?).ok()?;
+    let len = embedded_len(&mut file).ok()?;
+
+    if len == 0 {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::End(-(len as i64))).ok()?;
+    file.take(len - 16).read_to_end(&mut bytes).ok()?;
+
+    program(&bytes)
+}
+
+/// Writes an image of `kb` to `path` or, if `stand_alone`, a copy of the running executable
+/// that boots into the program instead of its own toplevel.
+pub fn save(kb: &[Assertion], path: &Path, stand_alone: bool) -> std::io::Result<()> {
+    let image = image(kb);
+
+    if !stand_alone {
+        return std::fs::write(path, image);
+    }
+
+    // An executable that is itself a saved program leaves its own image behind.
+    let mut file = File::open(std::env::current_exe()?)?;
+    let keep = file.seek(SeekFrom::End(0))? - embedded_len(&mut file)?;
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.take(keep).read_to_end(&mut bytes)?;
+
+    bytes.extend_from_slice(&image);
+    bytes.extend_from_slice(&(image.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&TRAILER);
+    std::fs::write(path, bytes)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use crate::ast::{Assertion, Clause, Term, Var};
+use crate::{
+    fresh, prepare, replace_cut, reset, resume_fresh, signals, term_vars, Choicepoint, Environment,
+    KnowledgeBase, SolveErr,
+};
+use std::thread::ThreadId;
+
+enum State {
+    Start(Clause),
+    /// Between answers, with the choicepoints to go on from.
+    Search(Vec<Choicepoint>),
+    Done,
+}
+
+/// A query whose answers are found one at a time, on whichever thread asks for the next, for the
+/// interfaces that give them to programs written in other languages.
+pub(crate) struct Search {
+    kb: KnowledgeBase,
+    /// The variables of the query, in the order they appear in it.
+    vars: Vec<Var>,
+    state: State,
+    /// The thread of the last call, whose declarations of the program were read.
+    thread: ThreadId,
+    /// Where renaming apart had got to when the last call ended, which the next goes on from on
+    /// any thread, after whatever other queries ran in between.
+    fresh: usize,
+}
+
+impl Search {
+    pub(crate) fn new(kb: &[Assertion], goals: Clause) -> Self {
+        let mut vars = Vec::new();
+
+        for goal in &goals {
+            term_vars(&Term::Atom(goal.clone()), &mut vars);
+        }
+
+        Search {
+            kb: prepare(kb),
+            vars,
+            state: State::Start(goals.iter().rev().map(|g| replace_cut(g, 0)).collect()),
+            thread: std::thread::current().id(),
+            fresh: 0,
+        }
+    }
+
+    pub(crate) fn vars(&self) -> &[Var] {
+        &self.vars
+    }
+
+    /// Looks for the next answer, giving the bindings it makes, or the exception that ended the
+    /// search.
+    pub(crate) fn next(&mut self) -> Result<Option<Environment>, Term> {
+        let here = std::thread::current().id();
+
+        if self.thread != here {
+            reset(&self.kb);
+            self.thread = here;
+        }
+
+        resume_fresh(self.fresh);
+
+        let result = match std::mem::replace(&mut self.state, State::Done) {
+            State::Start(goals) => {
+                signals::clear();
+                Environment::new().solve(Vec::new(), &self.kb, None, goals, 1)
+            }
+            State::Search(mut ch) => match ch.pop() {
+                Some(Choicepoint {
+                    assertions,
+                    environment,
+                    clause,
+                    depth,
+                    ..
+                }) => environment.solve(ch, &self.kb, assertions, clause, depth),
+                None => Err(SolveErr::NoSolution),
+            },
+            State::Done => return Ok(None),
+        };
+        self.fresh = fresh();
+
+        match result {
+            Ok((env, ch)) => {
+                if !ch.is_empty() {
+                    self.state = State::Search(ch);
+                }
+
+                Ok(Some(env))
+            }
+            Err(SolveErr::NoSolution) => Ok(None),
+            Err(SolveErr::Exception(ball)) => Err(ball),
+        }
+    }
+
+    /// The value `env` gives the variable `name` of the query.
+    pub(crate) fn binding(&self, env: &Environment, name: &str) -> Option<Term> {
+        let x = self.vars().iter().find(|Var(x, _)| x == name)?;
+        Some(env.substitute_term(&Term::Var(x.clone())))
+    }
+}
@@ -0,0 +1,160 @@
+use crate::ast::{Atom, Term, Var};
+use crate::Environment;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// How many nodes an answer may have written out in full before the subterms it shares are named.
+const LIMIT: usize = 10_000;
+
+/// The value of `t` as the toplevel writes it. When its bindings share subterms so much that
+/// writing them out in full would be too large, or would never end because they are cyclic, each
+/// subterm bound to a variable that is reached more than once is named `_S1`, `_S2`, ... and the
+/// value is `@(Template, [_S1 = Subterm, ...])`.
+pub(crate) fn answer(env: &Environment, t: &Term) -> Term {
+    let (nodes, vars) = shallow(t);
+    let mut sizes = HashMap::new();
+    let size = vars.iter().fold(nodes, |size, x| {
+        size.saturating_add(expanded_size(env, x, &mut sizes))
+    });
+
+    if size <= LIMIT {
+        return env.substitute_term(t);
+    }
+
+    let shared = shared_vars(env, vars);
+
+    if shared.is_empty() {
+        return env.substitute_term(t);
+    }
+
+    let mut named = env.clone();
+
+    for (i, x) in shared.iter().enumerate() {
+        let name = Term::Var(Var(format!("_S{}", i + 1), 0));
+        named.bind(x.clone(), name);
+    }
+
+    let substitutions = shared
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let name = Term::Var(Var(format!("_S{}", i + 1), 0));
+            let value = named.substitute_term(&env.bindings()[x]);
+            Term::Atom(Atom::new("=", vec![name, value]))
+        })
+        .collect();
+
+    Term::Atom(Atom::new(
+        "@",
+        vec![
+            named.substitute_term(t),
+            Term::list(substitutions, Term::nil()),
+        ],
+    ))
+}
+
+/// Adds the variables left unbound in the value of `t` to `vars`, as `term_vars` does for the
+/// value written out in full, but reading each binding only once.
+pub(crate) fn free_vars(env: &Environment, t: &Term, vars: &mut Vec<Var>) {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from(shallow(t).1);
+
+    while let Some(x) = queue.pop_front() {
+        if !seen.insert(x.clone()) {
+            continue;
+        }
+
+        match env.bindings().get(&x) {
+            Some(t) => queue.extend(shallow(t).1),
+            None if !vars.contains(&x) => vars.push(x),
+            None => (),
+        }
+    }
+}
+
+/// How many nodes of `t` are not variables, with the variables in it, bound or not, in the order
+/// they are found.
+fn shallow(t: &Term) -> (usize, Vec<Var>) {
+    let mut nodes = 0;
+    let mut vars = Vec::new();
+    let mut terms = vec![t];
+
+    while let Some(t) = terms.pop() {
+        match t {
+            Term::Var(x) => vars.push(x.clone()),
+            Term::Atom(a) => {
+                nodes += 1;
+                terms.extend(a.args.iter().rev());
+            }
+            Term::PartialString(_, tail) => {
+                nodes += 1;
+                terms.push(tail);
+            }
+            _ => nodes += 1,
+        }
+    }
+
+    (nodes, vars)
+}
+
+/// How many nodes the value of `x` has written out in full, or `usize::MAX` if it is cyclic.
+fn expanded_size(env: &Environment, x: &Var, sizes: &mut HashMap<Var, usize>) -> usize {
+    let mut open = HashSet::new();
+    let mut stack = vec![(x.clone(), false)];
+
+    while let Some((x, visited)) = stack.pop() {
+        if sizes.contains_key(&x) {
+            continue;
+        }
+
+        let (nodes, vars) = match env.bindings().get(&x) {
+            Some(t) => shallow(t),
+            None => {
+                sizes.insert(x, 1);
+                continue;
+            }
+        };
+
+        if visited {
+            let size = vars.iter().fold(nodes, |size, y| {
+                size.saturating_add(sizes.get(y).copied().unwrap_or(usize::MAX))
+            });
+            sizes.insert(x, size);
+        } else if !open.insert(x.clone()) {
+            sizes.insert(x, usize::MAX);
+        } else {
+            stack.push((x, true));
+            stack.extend(vars.into_iter().rev().map(|y| (y, false)));
+        }
+    }
+
+    sizes[x]
+}
+
+/// The variables reached from `vars` through the bindings of `env` more than once that are bound
+/// to compound terms, in the order they are first reached.
+fn shared_vars(env: &Environment, vars: Vec<Var>) -> Vec<Var> {
+    let mut reached: HashMap<Var, usize> = HashMap::new();
+    let mut order = Vec::new();
+    let mut queue = VecDeque::from(vars);
+
+    while let Some(x) = queue.pop_front() {
+        let count = reached.entry(x.clone()).or_default();
+        *count += 1;
+
+        if *count > 1 {
+            continue;
+        }
+
+        if let Some(t) = env.bindings().get(&x) {
+            queue.extend(shallow(t).1);
+        }
+
+        order.push(x);
+    }
+
+    order
+        .into_iter()
+        .filter(|x| reached[x] > 1)
+        .filter(|x| matches!(env.dereference(&Term::Var(x.clone())), Term::Atom(a) if a.arity > 0))
+        .collect()
+}
@@ -0,0 +1,186 @@
+use crate::ast::{Atom, Term};
+#[cfg(feature = "signals")]
+use signal_hook::consts::signal::*;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
+
+/// The ball thrown into a query that is interrupted.
+pub(crate) const ABORTED: &str = "$aborted";
+
+/// The signals that handlers can be installed for, by the names `on_signal/3` knows them by.
+#[cfg(feature = "signals")]
+fn signals() -> Vec<(&'static str, i32)> {
+    #[allow(unused_mut)]
+    let mut signals = vec![("int", SIGINT), ("term", SIGTERM)];
+
+    #[cfg(unix)]
+    signals.extend([
+        ("hup", SIGHUP),
+        ("quit", SIGQUIT),
+        ("alrm", SIGALRM),
+        ("pipe", SIGPIPE),
+        ("usr1", SIGUSR1),
+        ("usr2", SIGUSR2),
+        ("winch", SIGWINCH),
+    ]);
+
+    signals
+}
+
+/// Without the `signals` feature, as on WebAssembly, no signal can be caught.
+#[cfg(not(feature = "signals"))]
+fn signals() -> Vec<(&'static str, i32)> {
+    Vec::new()
+}
+
+/// The number of the signal named `name`.
+pub(crate) fn number(name: &str) -> Option<i32> {
+    signals()
+        .into_iter()
+        .find(|(signal, _)| *signal == name)
+        .map(|(_, n)| n)
+}
+
+fn name(n: i32) -> &'static str {
+    signals()
+        .into_iter()
+        .find(|(_, signal)| *signal == n)
+        .map_or("unknown", |(name, _)| name)
+}
+
+/// A signal that is caught, and what to do when it arrives: `default`, `abort` to throw
+/// `'$aborted'`, or a predicate to call with the name of the signal. Only the thread that
+/// installed the handler runs it.
+struct Handler {
+    pending: Arc<AtomicBool>,
+    action: Term,
+    thread: ThreadId,
+}
+
+static HANDLERS: Mutex<BTreeMap<i32, Handler>> = Mutex::new(BTreeMap::new());
+
+/// Whether any caught signal may be waiting to be handled.
+static RAISED: AtomicBool = AtomicBool::new(false);
+
+fn atom(name: &str) -> Term {
+    Term::Atom(Atom::new(name, vec![]))
+}
+
+/// The action taken on signal `n`, which is `default` until a handler is installed.
+pub(crate) fn action(n: i32) -> Term {
+    let handlers = HANDLERS.lock().unwrap_or_else(|e| e.into_inner());
+
+    handlers
+        .get(&n)
+        .map_or_else(|| atom("default"), |handler| handler.action.clone())
+}
+
+/// Sets the action taken on signal `n` when it arrives while this thread runs a query.
+#[cfg(feature = "signals")]
+pub(crate) fn install(n: i32, action: Term) -> std::io::Result<()> {
+    let mut handlers = HANDLERS.lock().unwrap_or_else(|e| e.into_inner());
+    let thread = std::thread::current().id();
+
+    if let Some(handler) = handlers.get_mut(&n) {
+        handler.action = action;
+        handler.thread = thread;
+        return Ok(());
+    }
+
+    let pending = Arc::new(AtomicBool::new(false));
+
+    if n == SIGINT {
+        // A second interrupt before the first is handled, as when waiting for input, ends the
+        // process.
+        signal_hook::flag::register_conditional_shutdown(n, 1, pending.clone())?;
+    }
+
+    signal_hook::flag::register(n, pending.clone())?;
+    // Safe because the action only stores to an atomic.
+    unsafe { signal_hook::low_level::register(n, || RAISED.store(true, Ordering::SeqCst))? };
+
+    handlers.insert(
+        n,
+        Handler {
+            pending,
+            action,
+            thread,
+        },
+    );
+
+    Ok(())
+}
+
+#[cfg(not(feature = "signals"))]
+fn unsupported() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "built without the signals feature",
+    )
+}
+
+#[cfg(not(feature = "signals"))]
+pub(crate) fn install(_n: i32, _action: Term) -> std::io::Result<()> {
+    Err(unsupported())
+}
+
+/// Makes interrupts abort the query the current thread is running instead of ending the
+/// process.
+#[cfg(feature = "signals")]
+pub(crate) fn catch_interrupts() -> std::io::Result<()> {
+    install(SIGINT, atom("abort"))
+}
+
+#[cfg(not(feature = "signals"))]
+pub(crate) fn catch_interrupts() -> std::io::Result<()> {
+    Err(unsupported())
+}
+
+/// Forgets the signals of this thread that arrived while it was not running a query.
+pub(crate) fn clear() {
+    let handlers = HANDLERS.lock().unwrap_or_else(|e| e.into_inner());
+    let thread = std::thread::current().id();
+
+    for handler in handlers.values().filter(|handler| handler.thread == thread) {
+        handler.pending.store(false, Ordering::SeqCst);
+    }
+}
+
+/// The goal that handles the next signal that arrived for this thread, if there is one.
+pub(crate) fn pending() -> Option<Atom> {
+    if !RAISED.swap(false, Ordering::SeqCst) {
+        return None;
+    }
+
+    let handlers = HANDLERS.lock().unwrap_or_else(|e| e.into_inner());
+    let thread = std::thread::current().id();
+
+    let arrived = handlers.iter().find(|(_, handler)| {
+        handler.thread == thread && handler.pending.swap(false, Ordering::SeqCst)
+    });
+
+    if handlers
+        .values()
+        .any(|handler| handler.pending.load(Ordering::SeqCst))
+    {
+        RAISED.store(true, Ordering::SeqCst);
+    }
+
+    let (&n, handler) = arrived?;
+
+    match &handler.action {
+        Term::Atom(a) if a.name.0 == "default" && a.arity == 0 => {
+            // Does what the signal would have done had it not been caught, which is usually
+            // to end the process.
+            #[cfg(feature = "signals")]
+            let _ = signal_hook::low_level::emulate_default_handler(n);
+            None
+        }
+        Term::Atom(a) if a.name.0 == "abort" && a.arity == 0 => {
+            Some(Atom::new("throw", vec![atom(ABORTED)]))
+        }
+        action => Some(Atom::new("call", vec![action.clone(), atom(name(n))])),
+    }
+}
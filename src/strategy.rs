@@ -0,0 +1,117 @@
+//! How the solver searches the proof tree of a query: leftmost goal first, the clauses of a
+//! predicate in the order of the program, back to the latest choicepoint when a branch fails,
+//! unless a strategy set with `set_search_strategy` chooses otherwise.
+
+use crate::ast::{Assertion, Atom, Clause, Term};
+use crate::Choicepoint;
+use std::cell::RefCell;
+
+/// The choices the solver makes while it searches, each of which a strategy may make its own way.
+/// Those it leaves to the defaults are made as depth-first search makes them.
+///
+/// Cuts and exceptions drop the choicepoints made since a point of the search, whichever order
+/// the choicepoints are gone back to in.
+pub trait SearchStrategy {
+    /// Which of `goals`, the goals left to prove with the first of them last, to prove next.
+    fn select_goal(&mut self, goals: &[Atom]) -> usize {
+        goals.len() - 1
+    }
+
+    /// Orders `clauses`, those of the predicate `goal` calls, which are tried last first as the
+    /// program keeps them.
+    fn order_clauses(&mut self, _goal: &Atom, _clauses: &mut [Assertion]) {}
+
+    /// Which choicepoint to go back to when a branch fails, given how many steps into the proof
+    /// each was left, the latest last.
+    fn select_choicepoint(&mut self, depths: &[usize]) -> usize {
+        depths.len() - 1
+    }
+
+    /// Whether a goal `depth` steps into the proof may be resolved, which a strategy that bounds
+    /// its search refuses past the bound, failing the branch.
+    fn admits(&mut self, _depth: usize) -> bool {
+        true
+    }
+
+    /// Whether to search the tree of the query again once it is exhausted, as restarts and
+    /// iterative deepening do. The answers found before are not reported again.
+    fn restart(&mut self) -> bool {
+        false
+    }
+}
+
+/// Depth-first search, as the solver searches when no strategy is set.
+pub struct DepthFirst;
+
+impl SearchStrategy for DepthFirst {}
+
+thread_local! {
+    static STRATEGY: RefCell<Option<Box<dyn SearchStrategy>>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn set(strategy: Option<Box<dyn SearchStrategy>>) {
+    STRATEGY.with(|s| *s.borrow_mut() = strategy);
+}
+
+/// Runs `f` with the strategy set on this thread, if there is one.
+fn with<R>(f: impl FnOnce(&mut dyn SearchStrategy) -> R) -> Option<R> {
+    STRATEGY.with(|s| s.borrow_mut().as_mut().map(|s| f(s.as_mut())))
+}
+
+/// Takes the goal to prove next from `goals`.
+pub(crate) fn next_goal(goals: &mut Clause) -> Option<Atom> {
+    if goals.is_empty() {
+        return None;
+    }
+
+    match with(|s| s.select_goal(goals)) {
+        Some(i) if i + 1 < goals.len() => Some(goals.remove(i)),
+        _ => goals.pop(),
+    }
+}
+
+/// The clauses of `kb` that `goal` may be resolved with in the order the strategy tries them, or
+/// None if there is no strategy to order them.
+pub(crate) fn clauses(goal: &Atom, kb: &[Assertion]) -> Option<Vec<Assertion>> {
+    // Tables run the clauses of a tabled predicate through `$tabled/1`.
+    let callee = match (&goal.name.0[..], &goal.args[..]) {
+        ("$tabled", [Term::Atom(a)]) => a,
+        _ => goal,
+    };
+
+    with(|s| {
+        let mut clauses: Vec<_> = kb
+            .iter()
+            .filter(|b| b.head.name == callee.name && b.head.arity == callee.arity)
+            .cloned()
+            .collect();
+
+        s.order_clauses(goal, &mut clauses);
+        clauses
+    })
+}
+
+/// Takes the choicepoint to go back to from `ch`.
+pub(crate) fn next_choicepoint(ch: &mut Vec<Choicepoint>) -> Option<Choicepoint> {
+    if ch.is_empty() {
+        return None;
+    }
+
+    let selected = with(|s| {
+        let depths: Vec<_> = ch.iter().map(|c| c.depth).collect();
+        s.select_choicepoint(&depths)
+    });
+
+    match selected {
+        Some(i) if i + 1 < ch.len() => Some(ch.remove(i)),
+        _ => ch.pop(),
+    }
+}
+
+pub(crate) fn admits(depth: usize) -> bool {
+    with(|s| s.admits(depth)).unwrap_or(true)
+}
+
+pub(crate) fn restart() -> bool {
+    with(|s| s.restart()).unwrap_or(false)
+}
@@ -0,0 +1,539 @@
+use crate::ast::{Assertion, Atom, Const, Number, Term, Var};
+use crate::builtins::terms::compare;
+use crate::builtins::{locals, Branch};
+use crate::{fresh, renumber_term, solve_all, solve_once, Environment};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// The dynamic predicate `memo/1` keeps the goals it has run in.
+const MEMO: &str = "$memo";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    /// Every answer has been found.
+    Complete,
+    /// The clauses of the call are being run, with its frame at this height of the stack.
+    Evaluating(usize),
+    /// Evaluated once, but it depends on a call that is still being evaluated, so it may find
+    /// more answers when that call is run again.
+    Incomplete,
+}
+
+/// How an argument of a mode-directed tabled predicate combines the answers that agree on the
+/// indexed arguments.
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+    Index,
+    Min,
+    Max,
+    First,
+    Last,
+    /// Joins the old and the new value with the named predicate of arity 3.
+    Lattice(Const),
+}
+
+/// The answers found so far for one call variant, with the position of each answer by the text
+/// of its indexed arguments and the dynamic predicates they were found from. A table is stale
+/// when one of those predicates changed while it was being evaluated.
+struct Table {
+    status: Status,
+    answers: Vec<Term>,
+    seen: HashMap<String, usize>,
+    dynamic: HashSet<(Const, usize)>,
+    stale: bool,
+}
+
+/// A call being evaluated, with the lowest stack height of the calls it consumed answers from.
+struct Frame {
+    key: String,
+    lowlink: usize,
+}
+
+/// What to do when a call is a variant of one of its ancestors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LoopCheck {
+    Fail,
+    Warn,
+}
+
+#[derive(Default)]
+struct Tables {
+    tabled: HashMap<(Const, usize), Vec<Mode>>,
+    tables: HashMap<String, Table>,
+    stack: Vec<Frame>,
+    incomplete: Vec<String>,
+    added: usize,
+    loop_check: Option<LoopCheck>,
+}
+
+thread_local! {
+    static TABLES: RefCell<Tables> = RefCell::new(Tables::default());
+}
+
+/// Forgets every table and reads the `:- table` and `:- loop_check` declarations of `kb`.
+pub(crate) fn reset(kb: &[Assertion]) {
+    let mut tabled = HashMap::new();
+    let mut loop_check = None;
+
+    for a in kb {
+        if a.head.name.0 == ":-" && a.head.arity == 1 {
+            if let Term::Atom(Atom { name, args, .. }) = &a.head.args[0] {
+                match (&name.0[..], &args[..]) {
+                    ("table", [spec]) => declare(spec, &mut tabled),
+                    ("loop_check", [Term::Atom(mode)]) => {
+                        loop_check = match &mode.name.0[..] {
+                            "fail" => Some(LoopCheck::Fail),
+                            "warn" => Some(LoopCheck::Warn),
+                            _ => None,
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    TABLES.with(|tables| {
+        *tables.borrow_mut() = Tables {
+            tabled,
+            loop_check,
+            ..Tables::default()
+        }
+    });
+}
+
+/// Whether calls are checked against their ancestors, which the solver then records as
+/// `$exit/1` goals after the body of each clause.
+pub(crate) fn loop_check() -> bool {
+    TABLES.with(|tables| tables.borrow().loop_check.is_some())
+}
+
+/// Whether `goal` should fail because it is a variant of an ancestor recorded in `c`. In warning
+/// mode the loop is reported and the call goes ahead.
+pub(crate) fn loops(env: &Environment, goal: &Atom, c: &[Atom]) -> bool {
+    let mode = match TABLES.with(|tables| tables.borrow().loop_check) {
+        Some(mode) => mode,
+        None => return false,
+    };
+
+    let call = variant(&env.substitute_term(&Term::Atom(goal.clone())));
+    let looping = c.iter().any(|a| match (&a.name.0[..], &a.args[..]) {
+        ("$exit", [ancestor]) => variant(&env.substitute_term(ancestor)) == call,
+        _ => false,
+    });
+
+    match mode {
+        LoopCheck::Warn if looping => {
+            eprintln!("Warning: {} is a variant of one of its ancestors", call);
+            false
+        }
+        _ => looping,
+    }
+}
+
+/// Reads a table declaration, either `Name/Arity` or a term such as `path(_, _, min)` that gives
+/// the mode of each argument.
+fn declare(spec: &Term, tabled: &mut HashMap<(Const, usize), Vec<Mode>>) {
+    if let Term::Atom(Atom { name, args, .. }) = spec {
+        match (&name.0[..], &args[..]) {
+            (",", [x, y]) => {
+                declare(x, tabled);
+                declare(y, tabled);
+            }
+            ("/", [Term::Atom(p), Term::Number(Number::Int(arity))])
+                if p.arity == 0 && *arity >= 0 =>
+            {
+                tabled.insert((p.name.clone(), *arity as usize), vec![]);
+            }
+            (_, modes) if !modes.is_empty() => {
+                let modes = modes.iter().map(mode).collect();
+                tabled.insert((name.clone(), args.len()), modes);
+            }
+            _ => (),
+        }
+    }
+}
+
+fn mode(t: &Term) -> Mode {
+    match t {
+        Term::Atom(Atom { name, args, .. }) => match (&name.0[..], &args[..]) {
+            ("min", []) => Mode::Min,
+            ("max", []) => Mode::Max,
+            ("first", []) => Mode::First,
+            ("last", []) => Mode::Last,
+            ("lattice", [Term::Atom(join)]) => match (&join.name.0[..], &join.args[..]) {
+                ("/", [Term::Atom(p), _]) => Mode::Lattice(p.name.clone()),
+                _ => Mode::Lattice(join.name.clone()),
+            },
+            _ => Mode::Index,
+        },
+        _ => Mode::Index,
+    }
+}
+
+/// Removes every answer table and memoized goal.
+pub(crate) fn abolish_all() {
+    TABLES.with(|tables| {
+        let mut tables = tables.borrow_mut();
+
+        if tables.stack.is_empty() {
+            tables.tables.clear();
+        }
+    });
+
+    locals::clear_dynamic(MEMO, 2);
+}
+
+/// Records that the calls being evaluated read the dynamic predicate `Name/Arity`, so that
+/// their tables are dropped when it changes.
+pub(crate) fn reads(name: &Const, arity: usize) {
+    TABLES.with(|tables| {
+        let mut tables = tables.borrow_mut();
+        let Tables { tables, stack, .. } = &mut *tables;
+
+        for frame in stack.iter() {
+            if let Some(table) = tables.get_mut(&frame.key) {
+                table.dynamic.insert((name.clone(), arity));
+            }
+        }
+    });
+}
+
+/// Drops every table whose answers were found from the dynamic predicate `Name/Arity`, which
+/// has been asserted to or retracted from, so that the next call evaluates it again. The tables
+/// of calls still being evaluated are marked stale instead, and dropped once they complete.
+pub(crate) fn changed(name: &Const, arity: usize) {
+    let key = (name.clone(), arity);
+
+    TABLES.with(|tables| {
+        tables.borrow_mut().tables.retain(|_, table| {
+            if !table.dynamic.contains(&key) {
+                return true;
+            }
+
+            table.stale = table.status != Status::Complete;
+            table.stale
+        })
+    });
+}
+
+/// Finds the first solution of `goal`, or None if it fails, remembering the result for every
+/// variant of `goal` called later as a `'$memo'(Call, Answer)` clause in the dynamic database,
+/// where `Answer` is `some(Solution)` or `none`.
+pub(crate) fn memo(kb: &[Assertion], goal: &Term) -> Option<Term> {
+    let call = variant(goal);
+
+    let stored = locals::dynamic_clauses(MEMO, 2).into_iter().find_map(|a| {
+        match (&a.head.args[0], &a.head.args[1]) {
+            (c, Term::Atom(answer)) if *c == call => Some(answer.args.first().cloned()),
+            _ => None,
+        }
+    });
+
+    if let Some(answer) = stored {
+        return answer;
+    }
+
+    let answer = solve_once(kb, Atom::new("call", vec![call.clone()]))
+        .map(|solution| variant(&solution.substitute_term(&call)));
+    let stored = match &answer {
+        Some(solution) => Term::Atom(Atom::new("some", vec![solution.clone()])),
+        None => Term::Atom(Atom::new("none", vec![])),
+    };
+
+    locals::add_dynamic(Assertion::new(Atom::new(MEMO, vec![call, stored]), vec![]));
+    answer
+}
+
+/// Renames the variables of `t` in order of appearance, so that variants of a term are equal.
+fn variant(t: &Term) -> Term {
+    fn rename(t: &Term, vars: &mut Vec<Var>) -> Term {
+        match t {
+            Term::Var(x) => {
+                let i = match vars.iter().position(|y| y == x) {
+                    Some(i) => i,
+                    None => {
+                        vars.push(x.clone());
+                        vars.len() - 1
+                    }
+                };
+
+                Term::Var(Var(format!("_#{}", i), 0))
+            }
+            Term::Atom(a) => Term::Atom(Atom::new(
+                &a.name.0,
+                a.args.iter().map(|t| rename(t, vars)).collect(),
+            )),
+            Term::PartialString(text, tail) => {
+                Term::PartialString(text.clone(), Box::new(rename(tail, vars)))
+            }
+            t => t.clone(),
+        }
+    }
+
+    rename(t, &mut Vec::new())
+}
+
+/// Answers a call to a tabled predicate from its table, filling the table first if this variant
+/// has not been called before. Returns None if `goal` is not tabled.
+pub(crate) fn call(
+    env: &Environment,
+    kb: &[Assertion],
+    goal: &Atom,
+    n: usize,
+) -> Option<Vec<Branch>> {
+    let modes = TABLES.with(|tables| {
+        tables
+            .borrow()
+            .tabled
+            .get(&(goal.name.clone(), goal.arity))
+            .cloned()
+    })?;
+
+    let goal = match env.substitute_term(&Term::Atom(goal.clone())) {
+        Term::Atom(goal) => goal,
+        _ => unreachable!("substitution keeps atoms"),
+    };
+    // Aggregated arguments are left open in the call, and only unified with the final answer.
+    let k = fresh();
+    let open: Vec<_> = goal
+        .args
+        .iter()
+        .enumerate()
+        .map(|(i, t)| match modes.get(i) {
+            Some(Mode::Index) | None => t.clone(),
+            Some(_) => Term::Var(Var(format!("_#{}", i), k)),
+        })
+        .collect();
+    let call = variant(&Term::Atom(Atom::new(&goal.name.0, open)));
+    let goal = Term::Atom(goal);
+    let key = call.to_string();
+
+    let status = TABLES.with(|tables| {
+        let mut tables = tables.borrow_mut();
+        let status = tables.tables.get(&key).map(|table| table.status);
+
+        if let Some(Status::Evaluating(height)) = status {
+            let top = tables
+                .stack
+                .last_mut()
+                .expect("evaluating call without a frame");
+            top.lowlink = top.lowlink.min(height);
+        }
+
+        status
+    });
+
+    if let None | Some(Status::Incomplete) = status {
+        evaluate(kb, key.clone(), call, &modes, n);
+    }
+
+    // The calls this one is evaluated for depend on what its answers were found from.
+    let answers = TABLES.with(|tables| {
+        let mut tables = tables.borrow_mut();
+        let Tables { tables, stack, .. } = &mut *tables;
+        let table = &tables[&key];
+        let (answers, dynamic) = (table.answers.clone(), table.dynamic.clone());
+
+        // A stale table answers the call that completed it, but no later one.
+        if table.stale && table.status == Status::Complete {
+            tables.remove(&key);
+        }
+
+        for frame in stack.iter() {
+            if let Some(table) = tables.get_mut(&frame.key) {
+                table.dynamic.extend(dynamic.iter().cloned());
+            }
+        }
+
+        answers
+    });
+
+    Some(
+        answers
+            .iter()
+            .filter_map(|answer| {
+                env.clone()
+                    .unify_terms(&goal, &renumber_term(fresh(), answer))
+                    .ok()
+                    .map(|env| (env, vec![]))
+            })
+            .collect(),
+    )
+}
+
+/// Adds `answer` to the table under `key`, or combines it with the answer that agrees with it on
+/// the indexed arguments. Counts the answers that change a table.
+fn add_answer(kb: &[Assertion], key: &str, answer: Term, modes: &[Mode], n: usize) {
+    let group = match &answer {
+        Term::Atom(a) if !modes.is_empty() => a
+            .args
+            .iter()
+            .zip(modes)
+            .filter(|(_, mode)| **mode == Mode::Index)
+            .map(|(t, _)| t.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        t => t.to_string(),
+    };
+
+    let old = TABLES.with(|tables| {
+        let tables = tables.borrow();
+        let table = &tables.tables[key];
+
+        table
+            .seen
+            .get(&group)
+            .map(|&i| (i, table.answers[i].clone()))
+    });
+
+    let (i, answer) = match old {
+        None => (None, answer),
+        Some((i, old)) => match aggregate(kb, &old, &answer, modes, n) {
+            Some(answer) => (Some(i), answer),
+            None => return,
+        },
+    };
+
+    TABLES.with(|tables| {
+        let mut tables = tables.borrow_mut();
+        let table = tables.tables.get_mut(key).expect("missing table");
+
+        match i {
+            Some(i) => table.answers[i] = answer,
+            None => {
+                table.seen.insert(group, table.answers.len());
+                table.answers.push(answer);
+            }
+        }
+
+        tables.added += 1;
+    });
+}
+
+/// Combines the aggregated arguments of two answers, returning None if the old answer stands.
+fn aggregate(kb: &[Assertion], old: &Term, new: &Term, modes: &[Mode], n: usize) -> Option<Term> {
+    let (old, new) = match (old, new) {
+        (Term::Atom(old), Term::Atom(new)) => (old, new),
+        _ => return None,
+    };
+
+    let args: Vec<_> = old
+        .args
+        .iter()
+        .zip(&new.args)
+        .zip(modes)
+        .map(|((x, y), mode)| match mode {
+            Mode::Index | Mode::First => x.clone(),
+            Mode::Last => y.clone(),
+            Mode::Min if compare(y, x) == Ordering::Less => y.clone(),
+            Mode::Max if compare(y, x) == Ordering::Greater => y.clone(),
+            Mode::Min | Mode::Max => x.clone(),
+            Mode::Lattice(join) => {
+                let (x, y) = (renumber_term(fresh(), x), renumber_term(fresh(), y));
+                let z = Term::Var(Var(String::from("_#join"), fresh()));
+                let goal = Atom::new(&join.0, vec![x.clone(), y, z.clone()]);
+
+                match solve_all(&Environment::new(), kb, goal, n)
+                    .unwrap_or_default()
+                    .first()
+                {
+                    Some(solution) => variant(&solution.substitute_term(&z)),
+                    None => variant(&x),
+                }
+            }
+        })
+        .collect();
+
+    if args
+        .iter()
+        .zip(&old.args)
+        .all(|(x, y)| compare(x, y) == Ordering::Equal)
+    {
+        return None;
+    }
+
+    Some(Term::Atom(Atom::new(&old.name.0, args)))
+}
+
+/// Runs the clauses of `call` until no call evaluated along with it finds a new answer. A call
+/// that consumed answers from a call further down the stack is left incomplete, to be run again
+/// by that call, which completes them all once it reaches its fixpoint.
+fn evaluate(kb: &[Assertion], key: String, call: Term, modes: &[Mode], n: usize) {
+    let (height, mark) = TABLES.with(|tables| {
+        let mut tables = tables.borrow_mut();
+        let height = tables.stack.len();
+
+        tables
+            .tables
+            .entry(key.clone())
+            .or_insert_with(|| Table {
+                status: Status::Incomplete,
+                answers: Vec::new(),
+                seen: HashMap::new(),
+                dynamic: HashSet::new(),
+                stale: false,
+            })
+            .status = Status::Evaluating(height);
+        tables.stack.push(Frame {
+            key: key.clone(),
+            lowlink: height,
+        });
+
+        (height, tables.incomplete.len())
+    });
+
+    let goal = match &call {
+        Term::Atom(a) => Atom::new("$tabled", vec![Term::Atom(a.clone())]),
+        _ => unreachable!("tabled calls are atoms"),
+    };
+
+    loop {
+        let before = TABLES.with(|tables| tables.borrow().added);
+
+        for solution in solve_all(&Environment::new(), kb, goal.clone(), n).unwrap_or_default() {
+            let answer = variant(&solution.substitute_term(&call));
+            add_answer(kb, &key, answer, modes, n);
+        }
+
+        let done = TABLES.with(|tables| {
+            let tables = tables.borrow();
+            tables.stack[height].lowlink < height || tables.added == before
+        });
+
+        if done {
+            break;
+        }
+    }
+
+    TABLES.with(|tables| {
+        let mut tables = tables.borrow_mut();
+        let Frame { key, lowlink } = tables.stack.pop().expect("missing frame");
+
+        if lowlink == height {
+            let completed: Vec<_> = tables.incomplete.drain(mark..).collect();
+
+            for k in &completed {
+                if tables.tables.get(k).is_some_and(|table| table.stale) {
+                    tables.tables.remove(k);
+                } else if let Some(table) = tables.tables.get_mut(k) {
+                    table.status = Status::Complete;
+                }
+            }
+
+            if let Some(table) = tables.tables.get_mut(&key) {
+                table.status = Status::Complete;
+            }
+        } else {
+            tables.tables.get_mut(&key).expect("missing table").status = Status::Incomplete;
+            tables.incomplete.push(key);
+
+            let parent = tables
+                .stack
+                .last_mut()
+                .expect("incomplete call without a leader");
+            parent.lowlink = parent.lowlink.min(lowlink);
+        }
+    });
+}
@@ -0,0 +1,527 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    Atom,
+    QuotedAtom,
+    Var,
+    Int,
+    Float,
+    String,
+    BackQuoted,
+    Punct,
+    End,
+    Comment,
+    QuasiQuotation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Range<usize>,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenizeErr {
+    UnexpectedChar(char, usize),
+    Unterminated(TokenKind, usize),
+}
+
+impl Display for TokenizeErr {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            TokenizeErr::UnexpectedChar(c, at) => write!(f, "unexpected {:?} at {}", c, at),
+            TokenizeErr::Unterminated(kind, at) => write!(f, "unterminated {:?} at {}", kind, at),
+        }
+    }
+}
+
+impl std::error::Error for TokenizeErr {}
+
+impl TokenKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TokenKind::Atom => "atom",
+            TokenKind::QuotedAtom => "quoted_atom",
+            TokenKind::Var => "var",
+            TokenKind::Int => "int",
+            TokenKind::Float => "float",
+            TokenKind::String => "string",
+            TokenKind::BackQuoted => "back_quoted",
+            TokenKind::Punct => "punct",
+            TokenKind::End => "end",
+            TokenKind::Comment => "comment",
+            TokenKind::QuasiQuotation => "quasi_quotation",
+        }
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    "+-*/\\^<>=~:.?@#&$".contains(c)
+}
+
+fn is_alnum(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Splits Prolog source text into tokens, skipping layout but keeping comments. Spans are byte
+/// offsets into `source`; lines and columns count characters from 1.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, TokenizeErr> {
+    let mut tokens = Vec::new();
+    scan(source, &mut tokens)?;
+    Ok(tokens)
+}
+
+/// Adds the tokens of `source` to `tokens` up to the first that cannot be read, if any.
+fn scan(source: &str, tokens: &mut Vec<Token>) -> Result<(), TokenizeErr> {
+    let mut chars = source.char_indices().peekable();
+    let (mut line, mut line_start) = (1, 0);
+
+    while let Some(&(start, c)) = chars.peek() {
+        let (token_line, token_line_start) = (line, line_start);
+        chars.next();
+
+        let kind = match c {
+            '\n' => {
+                line += 1;
+                line_start = start + 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '%' => {
+                while chars.peek().is_some_and(|&(_, c)| c != '\n') {
+                    chars.next();
+                }
+
+                TokenKind::Comment
+            }
+            '/' if chars.peek().map(|&(_, c)| c) == Some('*') => {
+                chars.next();
+                let mut last = ' ';
+
+                loop {
+                    match chars.next() {
+                        Some((_, '/')) if last == '*' => break,
+                        Some((i, '\n')) => {
+                            line += 1;
+                            line_start = i + 1;
+                            last = '\n';
+                        }
+                        Some((_, c)) => last = c,
+                        None => return Err(TokenizeErr::Unterminated(TokenKind::Comment, start)),
+                    }
+                }
+
+                TokenKind::Comment
+            }
+            '{' if chars.peek().map(|&(_, c)| c) == Some('|') => {
+                let mut last = ' ';
+
+                loop {
+                    match chars.next() {
+                        Some((_, '}')) if last == '|' => break,
+                        Some((i, '\n')) => {
+                            line += 1;
+                            line_start = i + 1;
+                            last = '\n';
+                        }
+                        Some((_, c)) => last = c,
+                        None => {
+                            return Err(TokenizeErr::Unterminated(TokenKind::QuasiQuotation, start))
+                        }
+                    }
+                }
+
+                TokenKind::QuasiQuotation
+            }
+            '\'' | '"' | '`' => {
+                let kind = match c {
+                    '\'' => TokenKind::QuotedAtom,
+                    '"' => TokenKind::String,
+                    _ => TokenKind::BackQuoted,
+                };
+
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => {
+                            chars.next();
+                        }
+                        Some((i, '\n')) => {
+                            line += 1;
+                            line_start = i + 1;
+                        }
+                        Some((_, q)) if q == c => match chars.peek() {
+                            Some(&(_, q)) if q == c => {
+                                chars.next();
+                            }
+                            _ => break,
+                        },
+                        Some(_) => (),
+                        None => return Err(TokenizeErr::Unterminated(kind, start)),
+                    }
+                }
+
+                kind
+            }
+            // A character code, as `0'a` or `0'\n`, with a quote written twice as `0'''`.
+            '0' if chars.peek().map(|&(_, c)| c) == Some('\'') => {
+                chars.next();
+
+                match chars.next() {
+                    Some((_, '\\')) => {
+                        chars.next();
+                    }
+                    Some((_, '\'')) if chars.peek().map(|&(_, c)| c) == Some('\'') => {
+                        chars.next();
+                    }
+                    Some((_, c)) if c != '\n' => (),
+                    _ => return Err(TokenizeErr::Unterminated(TokenKind::Int, start)),
+                }
+
+                TokenKind::Int
+            }
+            c if c.is_ascii_digit() => {
+                let mut kind = TokenKind::Int;
+
+                while chars.peek().is_some_and(|&(_, c)| c.is_ascii_digit()) {
+                    chars.next();
+                }
+
+                let mut lookahead = chars.clone();
+
+                if let (Some((_, '.')), Some((_, d))) = (lookahead.next(), lookahead.next()) {
+                    if d.is_ascii_digit() {
+                        kind = TokenKind::Float;
+                        chars.next();
+
+                        while chars.peek().is_some_and(|&(_, c)| c.is_ascii_digit()) {
+                            chars.next();
+                        }
+
+                        let mut lookahead = chars.clone();
+                        let exponent = match (lookahead.next(), lookahead.next(), lookahead.next())
+                        {
+                            (Some((_, 'e')), Some((_, d)), _)
+                            | (Some((_, 'E')), Some((_, d)), _)
+                                if d.is_ascii_digit() =>
+                            {
+                                1
+                            }
+                            (Some((_, 'e')), Some((_, '+')), Some((_, d)))
+                            | (Some((_, 'e')), Some((_, '-')), Some((_, d)))
+                            | (Some((_, 'E')), Some((_, '+')), Some((_, d)))
+                            | (Some((_, 'E')), Some((_, '-')), Some((_, d)))
+                                if d.is_ascii_digit() =>
+                            {
+                                2
+                            }
+                            _ => 0,
+                        };
+
+                        if exponent > 0 {
+                            for _ in 0..exponent {
+                                chars.next();
+                            }
+
+                            while chars.peek().is_some_and(|&(_, c)| c.is_ascii_digit()) {
+                                chars.next();
+                            }
+                        }
+                    }
+                }
+
+                kind
+            }
+            c if c == '_' || c.is_uppercase() => {
+                while chars.peek().is_some_and(|&(_, c)| is_alnum(c)) {
+                    chars.next();
+                }
+
+                TokenKind::Var
+            }
+            c if c.is_alphabetic() => {
+                while chars.peek().is_some_and(|&(_, c)| is_alnum(c)) {
+                    chars.next();
+                }
+
+                TokenKind::Atom
+            }
+            '.' if chars
+                .peek()
+                .is_none_or(|&(_, c)| c.is_whitespace() || c == '%') =>
+            {
+                TokenKind::End
+            }
+            c if is_symbol_char(c) => {
+                while chars.peek().is_some_and(|&(_, c)| is_symbol_char(c)) {
+                    chars.next();
+                }
+
+                TokenKind::Atom
+            }
+            '(' | ')' | '[' | ']' | '{' | '}' | ',' | '|' => TokenKind::Punct,
+            '!' | ';' => TokenKind::Atom,
+            c => return Err(TokenizeErr::UnexpectedChar(c, start)),
+        };
+
+        let end = chars.peek().map_or(source.len(), |&(i, _)| i);
+
+        tokens.push(Token {
+            kind,
+            text: String::from(&source[start..end]),
+            span: start..end,
+            line: token_line,
+            column: source[token_line_start..start].chars().count() + 1,
+        });
+    }
+
+    Ok(())
+}
+
+/// The value of an integer token, which is a run of digits or a character code such as `0'a`,
+/// either of them after a `-` the parser reads as part of the number, or None if it is out of
+/// range.
+pub fn int_value(text: &str) -> Option<i64> {
+    if text.starts_with("-0'") {
+        return int_value(&text[1..]).map(|c| -c);
+    }
+
+    match text.strip_prefix("0'") {
+        Some("''") => Some('\'' as i64),
+        Some(c) => crate::ast::unquote(&format!("'{}'", c))
+            .chars()
+            .next()
+            .map(|c| c as i64),
+        None => text.parse().ok(),
+    }
+}
+
+macro_rules! operators {
+    ($($op:ident => $text:literal,)*) => {
+        /// A name or punctuation mark the grammar gives a meaning to.
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub enum Op {
+            $($op,)*
+        }
+
+        impl Op {
+            pub fn text(self) -> &'static str {
+                match self {
+                    $(Op::$op => $text,)*
+                }
+            }
+
+            fn from_text(text: &str) -> Option<Op> {
+                match text {
+                    $($text => Some(Op::$op),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+operators! {
+    Open => "(",
+    Close => ")",
+    OpenList => "[",
+    CloseList => "]",
+    OpenCurly => "{",
+    CloseCurly => "}",
+    Comma => ",",
+    Bar => "|",
+    Cut => "!",
+    Semicolon => ";",
+    Is => "is",
+    Mod => "mod",
+    Rem => "rem",
+    Div => "div",
+    Xor => "xor",
+    In => "in",
+    Ins => "ins",
+    Table => "table",
+    MetaPredicate => "meta_predicate",
+    Persistent => "persistent",
+    ThreadLocal => "thread_local",
+    Dynamic => "dynamic",
+    Parallel => "parallel",
+    Unify => "=",
+    NotUnify => "\\=",
+    Equal => "==",
+    NotEqual => "\\==",
+    Before => "@<",
+    After => "@>",
+    BeforeOrEqual => "@=<",
+    AfterOrEqual => "@>=",
+    Univ => "=..",
+    ArithEqual => "=:=",
+    ArithNotEqual => "=\\=",
+    Less => "<",
+    Greater => ">",
+    LessOrEqual => "=<",
+    GreaterOrEqual => ">=",
+    FdEqual => "#=",
+    FdNotEqual => "#\\=",
+    FdLess => "#<",
+    FdGreater => "#>",
+    FdLessOrEqual => "#=<",
+    FdGreaterOrEqual => "#>=",
+    FdNot => "#\\",
+    FdAnd => "#/\\",
+    FdOr => "#\\/",
+    FdImplies => "#==>",
+    FdImpliedBy => "#<==",
+    FdEquivalent => "#<==>",
+    Times => "*",
+    Power => "**",
+    Divide => "/",
+    IntDivide => "//",
+    Caret => "^",
+    Colon => ":",
+    Minus => "-",
+    Plus => "+",
+    Backslash => "\\",
+    ShiftLeft => "<<",
+    ShiftRight => ">>",
+    Range => "..",
+    BitAnd => "/\\",
+    BitOr => "\\/",
+    NotProvable => "\\+",
+    IfThen => "->",
+    SoftCut => "*->",
+    Neck => ":-",
+    Arrow => "-->",
+    Query => "?-",
+    Question => "?",
+}
+
+/// A token as the parser reads it, borrowing its text from the source.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tok<'input> {
+    Op(Op),
+    /// Any other unquoted atom.
+    Name(&'input str),
+    /// A quoted atom, with its quotes.
+    Quoted(&'input str),
+    /// A name followed right away by the `(` of a compound term, without the `(`.
+    Functor(&'input str),
+    /// A quoted atom followed right away by `(`, with its quotes but without the `(`.
+    QuotedFunctor(&'input str),
+    Var(&'input str),
+    /// The anonymous variable `_`.
+    Anonymous,
+    Int(&'input str),
+    Float(&'input str),
+    Str(&'input str),
+    BackQuoted(&'input str),
+    QuasiQuotation(&'input str),
+    End,
+    /// The rest of the text from where it cannot be read as tokens, which no rule accepts.
+    Invalid(&'input str),
+}
+
+impl Display for Tok<'_> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Tok::Op(op) => write!(f, "{}", op.text()),
+            Tok::Functor(text) | Tok::QuotedFunctor(text) => write!(f, "{}(", text),
+            Tok::Anonymous => write!(f, "_"),
+            Tok::End => write!(f, "."),
+            Tok::Name(text)
+            | Tok::Quoted(text)
+            | Tok::Var(text)
+            | Tok::Int(text)
+            | Tok::Float(text)
+            | Tok::Str(text)
+            | Tok::BackQuoted(text)
+            | Tok::QuasiQuotation(text)
+            | Tok::Invalid(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Whether a token ends a term, so that an operator after it is an infix one.
+fn operand(spanned: &Spanned) -> bool {
+    match spanned {
+        Ok((_, Tok::Op(op), _)) => matches!(op, Op::Close | Op::CloseList | Op::CloseCurly),
+        Ok((_, Tok::Functor(_) | Tok::QuotedFunctor(_) | Tok::End | Tok::Invalid(_), _)) => false,
+        Ok(_) => true,
+        Err(_) => false,
+    }
+}
+
+/// A token with the byte offsets it starts and ends at, as the parser reads it.
+pub type Spanned<'input> = Result<(usize, Tok<'input>, usize), &'static str>;
+
+/// The tokens the parser reads from `source`, which are those `tokenize` splits it into without
+/// the comments, so that the parser and everything that reads tokens agree on them.
+pub fn lex(source: &str) -> impl Iterator<Item = Spanned<'_>> {
+    let mut tokens = Vec::new();
+    let error = scan(source, &mut tokens).err();
+    let mut tokens = tokens
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .peekable();
+    let mut lexed = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        let (start, mut end) = (token.span.start, token.span.end);
+        let text = &source[start..end];
+
+        // A `-` right before a number is its sign, unless it follows an operand it subtracts from.
+        if text == "-" && !lexed.last().is_some_and(operand) {
+            let number = tokens.next_if(|next| {
+                next.span.start == end && matches!(next.kind, TokenKind::Int | TokenKind::Float)
+            });
+
+            if let Some(number) = number {
+                let text = &source[start..number.span.end];
+                let tok = match number.kind {
+                    TokenKind::Int => Tok::Int(text),
+                    _ => Tok::Float(text),
+                };
+                lexed.push(Ok((start, tok, number.span.end)));
+                continue;
+            }
+        }
+        let functor = tokens
+            .peek()
+            .is_some_and(|next| next.span.start == end && next.text == "(");
+
+        let tok = match token.kind {
+            TokenKind::Atom if functor && text.starts_with(char::is_alphabetic) => {
+                end = tokens.next().map_or(end, |paren| paren.span.end);
+                Tok::Functor(text)
+            }
+            TokenKind::QuotedAtom if functor => {
+                end = tokens.next().map_or(end, |paren| paren.span.end);
+                Tok::QuotedFunctor(text)
+            }
+            TokenKind::Atom | TokenKind::Punct => {
+                Op::from_text(text).map_or(Tok::Name(text), Tok::Op)
+            }
+            TokenKind::QuotedAtom => Tok::Quoted(text),
+            TokenKind::Var if text == "_" => Tok::Anonymous,
+            TokenKind::Var => Tok::Var(text),
+            TokenKind::Int => Tok::Int(text),
+            TokenKind::Float => Tok::Float(text),
+            TokenKind::String => Tok::Str(text),
+            TokenKind::BackQuoted => Tok::BackQuoted(text),
+            TokenKind::QuasiQuotation => Tok::QuasiQuotation(text),
+            TokenKind::End => Tok::End,
+            TokenKind::Comment => continue,
+        };
+
+        lexed.push(Ok((start, tok, end)));
+    }
+
+    if let Some(TokenizeErr::UnexpectedChar(_, at) | TokenizeErr::Unterminated(_, at)) = error {
+        lexed.push(Ok((at, Tok::Invalid(&source[at..]), source.len())));
+    }
+
+    lexed.into_iter()
+}
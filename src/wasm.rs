@@ -0,0 +1,45 @@
+//! Bindings for running the interpreter in a browser, built with `wasm-pack build --features wasm
+//! --no-default-features`.
+//!
+//! ```js
+//! const machine = new Machine();
+//! machine.consult("parent(tom, bob). parent(bob, ann).");
+//! machine.query("parent(tom, X), parent(X, Y).");  // ["X = bob\nY = ann"]
+//! ```
+
+use crate::ast::Clause;
+use crate::loader::consult_into;
+use crate::parser::ClauseParser;
+use crate::tokenizer::lex;
+use crate::{solve_toplevel, KnowledgeBase};
+use wasm_bindgen::prelude::*;
+
+/// A program, to which sources are added with `consult` and which answers queries with `query`.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Machine {
+    kb: KnowledgeBase,
+}
+
+#[wasm_bindgen]
+impl Machine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Machine {
+        Machine::default()
+    }
+
+    /// Adds the clauses of `source` to the program, after loading the bundled libraries it asks
+    /// for and running its `initialization/1` goals.
+    pub fn consult(&mut self, source: &str) -> Result<(), JsError> {
+        consult_into(&mut self.kb, source).map_err(|e| JsError::new(&e))
+    }
+
+    /// The answers to `goal`, written as the toplevel writes them, such as `X = 1` or `No`.
+    pub fn query(&self, goal: &str) -> Result<Vec<String>, JsError> {
+        let query: Clause = ClauseParser::new()
+            .parse(lex(goal))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+
+        Ok(solve_toplevel(false, &self.kb, query))
+    }
+}
@@ -0,0 +1,333 @@
+//! Cross-referencing a program: the predicates its clauses call, and the calls that no clause,
+//! library predicate or builtin can answer.
+
+use crate::ast::{Assertion, Atom, Number, Term, Var};
+use crate::library;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+include!(concat!(env!("OUT_DIR"), "/builtins.rs"));
+
+/// The arguments of control constructs and meta-predicates that are goals, each with the number
+/// of arguments the goal is called with added.
+fn goal_args(name: &str, arity: usize) -> &'static [(usize, usize)] {
+    match (name, arity) {
+        (",", 2) | (";", 2) | ("->", 2) | ("*->", 2) => &[(0, 0), (1, 0)],
+        ("\\+", 1) | ("not", 1) | ("memo", 1) => &[(0, 0)],
+        (":", 2) => &[(1, 0)],
+        ("forall", 2) => &[(0, 0), (1, 0)],
+        ("findall", 3) => &[(1, 0)],
+        ("catch", 3) => &[(0, 0), (2, 0)],
+        ("thread_create", 3) => &[(0, 0)],
+        ("include", 3) | ("exclude", 3) | ("concurrent_maplist", 2) => &[(0, 1)],
+        ("concurrent_maplist", 3) => &[(0, 2)],
+        ("with_mutex", 2) => &[(1, 0)],
+        ("initialization", 1) | ("initialization", 2) => &[(0, 0)],
+        _ => &[],
+    }
+}
+
+/// Whether `name/arity` is built into the solver rather than defined by clauses.
+pub fn is_builtin(name: &str, arity: usize) -> bool {
+    matches!(
+        (name, arity),
+        ("halt", 0) | ("throw", 1) | ("engine_yield", 1)
+    ) || (name == "call" && arity > 0)
+        || BUILTINS.binary_search(&(name, arity)).is_ok()
+}
+
+/// The builtins that are called by name and arity, as `halt/0` is but `call/N` is not.
+pub fn builtins() -> impl Iterator<Item = (&'static str, usize)> {
+    BUILTINS
+        .iter()
+        .copied()
+        .chain([("halt", 0), ("throw", 1), ("engine_yield", 1)])
+}
+
+/// Adds the goals that calling `goal` with `extra` more arguments calls to `calls`.
+fn goal_calls(goal: &Term, extra: usize, calls: &mut Vec<Atom>) {
+    let goal = match goal {
+        Term::Atom(a) => a,
+        _ => return,
+    };
+
+    if goal.name.0 == "call" && goal.arity > 0 {
+        return goal_calls(&goal.args[0], extra + goal.arity - 1, calls);
+    }
+
+    let mut args = goal.args.clone();
+    args.extend((0..extra).map(|i| Term::Var(Var::new(&format!("_X{}", i), 0))));
+    let called = Atom::new(&goal.name.0, args);
+
+    for &(i, more) in goal_args(&goal.name.0, goal.arity) {
+        goal_calls(&goal.args[i], extra + more, calls);
+    }
+
+    calls.push(called);
+}
+
+/// The goals the clause `a` calls, found inside control constructs and the goals passed to
+/// meta-predicates such as `findall/3`, as well as the goals of `initialization/1,2`. Goals only
+/// known at run time, such as the `G` of `call(G)`, are left out.
+pub fn calls(a: &Assertion) -> Vec<Atom> {
+    let mut calls = Vec::new();
+
+    match (&a.head.name.0[..], &a.head.args[..]) {
+        (":-", [Term::Atom(directive)]) => {
+            for &(i, more) in goal_args(&directive.name.0, directive.arity) {
+                goal_calls(&directive.args[i], more, &mut calls);
+            }
+        }
+        _ => {
+            for goal in &a.clause {
+                goal_calls(&Term::Atom(goal.clone()), 0, &mut calls);
+            }
+        }
+    }
+
+    calls
+}
+
+/// Adds the predicates that the declaration `spec`, such as `foo/1, bar/2`, names to `declared`.
+fn declare(spec: &Term, declared: &mut HashSet<(String, usize)>) {
+    match spec {
+        Term::Atom(a) if (a.name.0 == "," || a.name.0 == ".") && a.arity == 2 => {
+            declare(&a.args[0], declared);
+            declare(&a.args[1], declared);
+        }
+        Term::Atom(a) if a.name.0 == "/" && a.arity == 2 => {
+            if let (Term::Atom(name), Term::Number(Number::Int(n))) = (&a.args[0], &a.args[1]) {
+                declared.insert((name.name.0.clone(), *n as usize));
+            }
+        }
+        // A persistent predicate is declared by a head with the types of its arguments.
+        Term::Atom(a) if a.arity > 0 => {
+            declared.insert((a.name.0.clone(), a.arity));
+        }
+        _ => (),
+    }
+}
+
+/// The predicates a program can call: those it defines or declares, those of the library, and
+/// the builtins.
+pub struct Known {
+    defined: HashSet<(String, usize)>,
+}
+
+/// The predicates the clauses of `kb` define or declare.
+fn definitions<'a>(kb: impl IntoIterator<Item = &'a Assertion>) -> HashSet<(String, usize)> {
+    let mut defined = HashSet::new();
+
+    for a in kb {
+        match (&a.head.name.0[..], &a.head.args[..]) {
+            (":-", [Term::Atom(d)]) if d.arity == 1 => match &d.name.0[..] {
+                "dynamic" | "discontiguous" | "thread_local" | "persistent" | "table" => {
+                    declare(&d.args[0], &mut defined)
+                }
+                _ => (),
+            },
+            (":-", _) => (),
+            (name, args) => {
+                defined.insert((String::from(name), args.len()));
+            }
+        }
+    }
+
+    defined
+}
+
+impl Known {
+    pub fn new(kb: &[Assertion]) -> Self {
+        Known {
+            defined: definitions(kb)
+                .into_iter()
+                .chain(library::predicates().map(|(name, arity)| (String::from(name), arity)))
+                .collect(),
+        }
+    }
+
+    /// The predicates the program defines or declares, with those of the library.
+    pub fn defined(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.defined.iter().map(|(name, arity)| (&name[..], *arity))
+    }
+
+    pub fn contains(&self, name: &str, arity: usize) -> bool {
+        is_builtin(name, arity) || self.defined.contains(&(String::from(name), arity))
+    }
+}
+
+/// The calls in `kb` to predicates it cannot call, each with the clause that makes it.
+pub fn undefined(kb: &[Assertion]) -> Vec<(&Assertion, Atom)> {
+    let known = Known::new(kb);
+
+    kb.iter()
+        .rev()
+        .flat_map(|a| calls(a).into_iter().map(move |goal| (a, goal)))
+        .filter(|(_, goal)| !known.contains(&goal.name.0, goal.arity))
+        .collect()
+}
+
+/// What cross-referencing a program finds wrong with it, with predicates named by their name and
+/// arity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// A clause of the first predicate calls the second, which nothing defines. Directives are
+    /// clauses of `:-/1`.
+    Undefined((String, usize), (String, usize)),
+    /// The module exports a predicate that the program does not define.
+    NotDefined(String, (String, usize)),
+    /// The program defines the predicate, but does not call it other than from its own clauses,
+    /// nor export it.
+    Unused((String, usize)),
+}
+
+impl Issue {
+    /// The issue as a term: `undefined(Caller, Callee)`, `not_defined(Module, PI)` or `unused(PI)`.
+    pub fn to_term(&self) -> Term {
+        let pi = |(name, arity): &(String, usize)| {
+            Term::Atom(Atom::new(
+                "/",
+                vec![
+                    Term::Atom(Atom::new(name, vec![])),
+                    Term::Number(Number::Int(*arity as i64)),
+                ],
+            ))
+        };
+
+        match self {
+            Issue::Undefined(caller, callee) => {
+                Term::Atom(Atom::new("undefined", vec![pi(caller), pi(callee)]))
+            }
+            Issue::NotDefined(module, exported) => Term::Atom(Atom::new(
+                "not_defined",
+                vec![Term::Atom(Atom::new(module, vec![])), pi(exported)],
+            )),
+            Issue::Unused(defined) => Term::Atom(Atom::new("unused", vec![pi(defined)])),
+        }
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Issue::Undefined((caller, _), (callee, n)) if caller == ":-" => {
+                write!(
+                    f,
+                    "a directive calls {}/{}, which is not defined",
+                    callee, n
+                )
+            }
+            Issue::Undefined((caller, m), (callee, n)) => write!(
+                f,
+                "{}/{} calls {}/{}, which is not defined",
+                caller, m, callee, n
+            ),
+            Issue::NotDefined(module, (name, arity)) => write!(
+                f,
+                "module {} exports {}/{}, which is not defined",
+                module, name, arity
+            ),
+            Issue::Unused((name, arity)) => {
+                write!(f, "{}/{} is defined but never called", name, arity)
+            }
+        }
+    }
+}
+
+/// The predicates that the `:- module(Name, Exports)` directives of `kb` export, each with the
+/// module exporting it. A grammar rule exported as `Name//Arity` takes two more arguments.
+fn exports(kb: &[Assertion]) -> Vec<(String, (String, usize))> {
+    let mut exported = Vec::new();
+
+    for a in kb.iter().rev() {
+        let (module, list) = match (&a.head.name.0[..], &a.head.args[..]) {
+            (":-", [Term::Atom(d)]) if d.name.0 == "module" && d.arity == 2 => match &d.args[0] {
+                Term::Atom(module) => (&module.name.0, &d.args[1]),
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        let mut list = list;
+
+        while let Term::Atom(cell) = list {
+            if cell.name.0 != "." || cell.arity != 2 {
+                break;
+            }
+
+            if let Term::Atom(pi) = &cell.args[0] {
+                let extra = match &pi.name.0[..] {
+                    "/" => 0,
+                    "//" => 2,
+                    _ => {
+                        list = &cell.args[1];
+                        continue;
+                    }
+                };
+
+                if let [Term::Atom(name), Term::Number(Number::Int(n))] = &pi.args[..] {
+                    exported.push((module.clone(), (name.name.0.clone(), *n as usize + extra)));
+                }
+            }
+
+            list = &cell.args[1];
+        }
+    }
+
+    exported
+}
+
+/// Cross-references the program `kb`, finding the calls to predicates that nothing defines, the
+/// exported predicates that it does not define, and the predicates it defines but never calls,
+/// each in the order the program first has them.
+pub fn check(kb: &[Assertion]) -> Vec<Issue> {
+    let known = Known::new(kb);
+    let own = definitions(kb);
+    let exported = exports(kb);
+    let mut issues = Vec::new();
+    let mut called = HashSet::new();
+
+    // Clauses are kept last first, so they are read from the end.
+    for a in kb.iter().rev() {
+        let caller = (a.head.name.0.clone(), a.head.arity);
+
+        for goal in calls(a) {
+            let callee = (goal.name.0.clone(), goal.arity);
+
+            if !known.contains(&callee.0, callee.1) {
+                let issue = Issue::Undefined(caller.clone(), callee.clone());
+
+                if !issues.contains(&issue) {
+                    issues.push(issue);
+                }
+            }
+
+            if callee != caller {
+                called.insert(callee);
+            }
+        }
+    }
+
+    for (module, pi) in &exported {
+        if !own.contains(pi) {
+            issues.push(Issue::NotDefined(module.clone(), pi.clone()));
+        }
+    }
+
+    let mut unused = Vec::new();
+
+    for a in kb.iter().rev() {
+        let pi = (a.head.name.0.clone(), a.head.arity);
+
+        if pi.0 != ":-"
+            && !called.contains(&pi)
+            && !exported.iter().any(|(_, e)| *e == pi)
+            && !unused.contains(&pi)
+        {
+            unused.push(pi);
+        }
+    }
+
+    issues.extend(unused.into_iter().map(Issue::Unused));
+    issues
+}
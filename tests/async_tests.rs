@@ -0,0 +1,67 @@
+#![cfg(feature = "async")]
+
+use bfg_prolog::ast;
+use bfg_prolog::ast::Clause;
+use bfg_prolog::dcg;
+use bfg_prolog::loader::consult;
+use bfg_prolog::query_async;
+use bfg_prolog::tokenizer;
+use futures::executor::block_on;
+use futures::stream::{Stream, StreamExt};
+use futures::task::noop_waker;
+use lalrpop_util::lalrpop_mod;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+lalrpop_mod!(#[allow(clippy::all, unused_parens)] pub parser);
+
+fn parse_query(query: &str) -> Clause {
+    let clause_parser = parser::ClauseParser::new();
+    clause_parser.parse(tokenizer::lex(query)).unwrap()
+}
+
+#[test]
+fn test_query_async_1_succeeds() {
+    let source = consult("tests/example_programs/async/async.pl").unwrap();
+    let answers = query_async(&source, parse_query("colour(X)."));
+
+    let results: Vec<_> = block_on(answers.collect());
+
+    assert_eq!(
+        results,
+        vec![
+            Ok(String::from("X = red")),
+            Ok(String::from("X = green")),
+            Ok(String::from("X = blue")),
+        ]
+    );
+}
+
+#[test]
+fn test_query_async_2_succeeds() {
+    let source = consult("tests/example_programs/async/async.pl").unwrap();
+    let mut answers = query_async(&source, parse_query("slow(X)."));
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut pending = 0;
+
+    let answer = loop {
+        match Pin::new(&mut answers).poll_next(&mut cx) {
+            Poll::Pending => pending += 1,
+            Poll::Ready(answer) => break answer,
+        }
+    };
+
+    assert!(pending > 0);
+    assert_eq!(answer, Some(Ok(String::from("X = done"))));
+}
+
+#[test]
+fn test_query_async_3_succeeds() {
+    let source = consult("tests/example_programs/async/async.pl").unwrap();
+    let answers = query_async(&source, parse_query("colour(X), throw(oops(X))."));
+
+    let results: Vec<_> = block_on(answers.map(|a| a.map_err(|e| e.to_string())).collect());
+
+    assert_eq!(results, vec![Err(String::from("oops(red)"))]);
+}
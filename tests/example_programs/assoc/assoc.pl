@@ -0,0 +1,17 @@
+squares(N, Assoc) :-
+    findall(I-S, (between(1, N, I), S is I * I), Pairs),
+    list_to_assoc(Pairs, Assoc).
+
+depth(t, 0).
+depth(t(_K, _V, _H, L, R), D) :-
+    depth(L, DL),
+    depth(R, DR),
+    D is max(DL, DR) + 1.
+
+update_six(D, S, X, Vs) :-
+    squares(7, A),
+    depth(A, D),
+    get_assoc(6, A, S),
+    put_assoc(6, A, six, A1),
+    get_assoc(6, A1, X),
+    assoc_to_values(A1, Vs).
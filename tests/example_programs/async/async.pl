@@ -0,0 +1,13 @@
+colour(red).
+colour(green).
+colour(blue).
+
+count(N, N).
+count(I, N) :-
+    I < N,
+    J is I + 1,
+    count(J, N).
+
+slow(X) :-
+    count(0, 600),
+    X = done.
@@ -0,0 +1,6 @@
+domain(X, Dom) :- put_attr(X, domain, Dom).
+
+':'(domain, attr_unify_hook(Dom, Y)) :- nonvar(Y), memberchk(Y, Dom).
+':'(domain, attr_unify_hook(Dom, Y)) :- var(Y), put_attr(Y, domain, Dom).
+
+unify(X, X).
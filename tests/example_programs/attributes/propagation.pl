@@ -0,0 +1,10 @@
+:- thread_local(noted/1).
+
+% Binding a variable watched in `order` queues the propagators it keeps.
+':'(order, attr_unify_hook(Props, _)) :- propagate(low, Props).
+
+watch(X, Props) :- put_attr(X, order, Props).
+
+note(Name) :- assertz(noted(Name)).
+
+bind(X, V, Name) :- note(Name), X = V.
@@ -7,3 +7,8 @@ member(X, list(_Y, Rest)) :-
 append(nil, Zs, Zs).
 append(list(X, Xs), Ys, list(X, Zs)) :-
     append(Xs, Ys, Zs).
+
+product(X, Y, Z) :-
+    member(X, list(a, list(b, nil))),
+    member(Y, list(c, list(d, nil))),
+    member(Z, list(e, list(f, nil))).
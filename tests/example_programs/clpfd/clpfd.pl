@@ -0,0 +1,81 @@
+steps([X, Y, Z]) :-
+    [X, Y, Z] ins 0..9,
+    X + Y + Z #= 21,
+    X #> Y,
+    Y #> Z,
+    X - Z #= 2.
+
+factors(N, X, Y) :-
+    [X, Y] ins 1..N,
+    X * Y #= N,
+    X #=< Y,
+    labeling([ff], [X, Y]).
+
+first(X, [X|Xs]) :- !.
+
+count(N, N).
+count(N, X) :- M is N + 1, M =< 3, count(M, X).
+
+queens(Qs) :-
+    length(Qs, N),
+    Qs ins 1..N,
+    safe(Qs),
+    labeling([ff], Qs).
+
+length([], 0).
+length([X|Xs], N) :- length(Xs, M), N is M + 1.
+
+safe([]).
+safe([Q|Qs]) :- no_attack(Q, Qs, 1), safe(Qs).
+
+no_attack(Q, [], D).
+no_attack(Q, [Q1|Qs], D) :-
+    Q #\= Q1,
+    Q #\= Q1 + D,
+    Q #\= Q1 - D,
+    D1 is D + 1,
+    no_attack(Q, Qs, D1).
+
+n_queens(N, Qs) :-
+    places(N, Qs),
+    Qs ins 1..N,
+    all_different(Qs),
+    safe_queens(Qs).
+
+solutions(N, Count, First) :-
+    findall(Qs, (n_queens(N, Qs), labeling([ff], Qs)), L),
+    length(L, Count),
+    first(First, L).
+
+places(0, []).
+places(N, [Q|Qs]) :- N > 0, M is N - 1, places(M, Qs).
+
+safe_queens([]).
+safe_queens([Q|Qs]) :- safe_queens(Qs, Q, 1), safe_queens(Qs).
+
+safe_queens([], Q0, D0).
+safe_queens([Q|Qs], Q0, D0) :-
+    abs(Q0 - Q) #\= D0,
+    D1 is D0 + 1,
+    safe_queens(Qs, Q0, D1).
+
+sudoku([A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P]) :-
+    [A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P] ins 1..4,
+    all_distinct([A, B, C, D]),
+    all_distinct([E, F, G, H]),
+    all_distinct([I, J, K, L]),
+    all_distinct([M, N, O, P]),
+    all_distinct([A, E, I, M]),
+    all_distinct([B, F, J, N]),
+    all_distinct([C, G, K, O]),
+    all_distinct([D, H, L, P]),
+    all_distinct([A, B, E, F]),
+    all_distinct([C, D, G, H]),
+    all_distinct([I, J, M, N]),
+    all_distinct([K, L, O, P]).
+
+count_over([], T, 0).
+count_over([X|Xs], T, N) :-
+    B #<==> (X #> T),
+    N #= B + M,
+    count_over(Xs, T, M).
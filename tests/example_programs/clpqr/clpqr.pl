@@ -0,0 +1,9 @@
+mortgage(P, T, I, B, MP) :-
+    {T = 0, B = P}.
+mortgage(P, T, I, B, MP) :-
+    {T >= 1, P1 = P * (1 + I / 100) - MP, T1 = T - 1},
+    mortgage(P1, T1, I, B, MP).
+
+production(X, Y, Profit) :-
+    {X >= 0, Y >= 0, X + Y =< 4, X + 3 * Y =< 6, Profit = 3 * X + 4 * Y},
+    maximize(Profit).
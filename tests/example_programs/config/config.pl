@@ -0,0 +1,23 @@
+yaml(Term) :-
+    load_yaml('tests/example_programs/config/app.yaml', Term, []).
+
+yaml_dict(Term) :-
+    load_yaml('tests/example_programs/config/app.yaml', Term, [value_string_as(string)]).
+
+yaml_documents(Docs) :-
+    load_yaml(string("a: 1\n---\n- x\n- 2.5\n"), Docs, []).
+
+toml(Term) :-
+    load_toml('tests/example_programs/config/app.toml', Term, []).
+
+toml_dict(Host, Ports) :-
+    load_toml('tests/example_programs/config/app.toml', json(Pairs), [value_string_as(string)]),
+    member(database=json(Database), Pairs),
+    member(host=Host, Database),
+    member(ports=Ports, Database).
+
+bad_yaml(E) :-
+    catch(load_yaml(string("a: [1, 2"), _Term, []), error(E, _Context), true).
+
+bad_toml(E) :-
+    catch(load_toml(string("a = "), _Term, []), error(E, _Context), true).
@@ -0,0 +1,8 @@
+fresh_names(A, B, C) :-
+    gensym(tmp_, A),
+    gensym(tmp_, B),
+    gensym(label, C).
+
+count(Key, N) :-
+    flag(Key, N0, N0 + 1),
+    N is N0 + 1.
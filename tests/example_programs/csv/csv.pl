@@ -0,0 +1,27 @@
+read_all(File, Rows) :-
+    csv_read_file(File, Rows, []).
+
+read_with(File, Options, Rows) :-
+    csv_read_file(File, Rows, Options).
+
+each_row(File, Row) :-
+    csv_read_file_row(File, Row, []).
+
+first_rows(File, Row1, Row2) :-
+    process_create(path(cat), [File], [stdout(pipe(Out))]),
+    csv_read_row(Out, Row1, []),
+    csv_read_row(Out, Row2, [functor(r), convert(false)]),
+    close(Out).
+
+round_trip(Rows, Text, Back) :-
+    File = 'target/csv_round_trip.csv',
+    csv_write_file(File, Rows, [separator(59)]),
+    csv_read_file(File, Back, [separator(';')]),
+    csv_write_stream(atom(Text), Rows, []),
+    delete_file(File).
+
+bad_arity(E) :-
+    catch(csv_read_file('tests/example_programs/csv/short.csv', _Rows, [separator(';'), arity(2)]), error(E, _Context), true).
+
+missing(E) :-
+    catch(csv_read_file('tests/example_programs/csv/none.csv', _Rows, []), error(E, _Context), true).
@@ -0,0 +1,51 @@
+member_of(X, [X|_T]).
+member_of(X, [_H|T]) :-
+    member_of(X, T).
+
+take(0, _E, []) :-
+    !.
+take(N, E, [X|Xs]) :-
+    engine_next(E, X),
+    !,
+    M is N - 1,
+    take(M, E, Xs).
+take(_N, _E, []).
+
+firsts(Xs, Rest) :-
+    engine_create(X-Y, member_of(X-Y, [a-1, b-2, c-3]), E),
+    take(2, E, Xs),
+    take(5, E, Rest).
+
+nat(N) :-
+    nat_from(0, N).
+
+nat_from(N, N).
+nat_from(N, M) :-
+    N1 is N + 1,
+    nat_from(N1, M).
+
+naturals(Xs) :-
+    engine_create(N, nat(N), E, [alias(naturals)]),
+    take(4, naturals, Xs),
+    engine_destroy(E).
+
+counter(N) :-
+    engine_yield(N),
+    N1 is N + 1,
+    counter(N1).
+
+yields(Xs) :-
+    engine_create(_T, counter(10), E),
+    take(3, E, Xs).
+
+failing(Result, Error) :-
+    engine_create(X, member_of(X, []), E1),
+    (   engine_next(E1, _A)
+    ->  Result = answer
+    ;   Result = none
+    ),
+    engine_create(X, throw(oops), E2),
+    catch(engine_next(E2, _B), Error, true).
+
+outside(E) :-
+    catch(engine_yield(1), error(E, _Context), true).
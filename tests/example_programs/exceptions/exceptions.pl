@@ -0,0 +1,15 @@
+error_of(Goal, E) :-
+    catch(Goal, error(E, _Context), true).
+
+eval_error(Expr, E) :-
+    error_of(_X is Expr, E).
+
+rethrown(B) :-
+    catch(catch(throw(outer), inner, true), B, true).
+
+safe_div(X, Y, Z) :-
+    catch(Z is X / Y, error(evaluation_error(zero_divisor), _Context), Z = infinity).
+
+first_positive(Xs, X) :-
+    catch(member(X, Xs), _Ball, true),
+    X > 0.
@@ -0,0 +1,31 @@
+round_trip(Terms, Read) :-
+    process_create(path(cat), [], [stdin(pipe(In)), stdout(pipe(Out))]),
+    write_all(Terms, In),
+    close(In),
+    read_all(Out, Read),
+    close(Out).
+
+write_all([], _Out).
+write_all([T | Ts], Out) :-
+    fast_write(Out, T),
+    write_all(Ts, Out).
+
+read_all(In, Terms) :-
+    fast_read(In, T),
+    (   T == end_of_file
+    ->  Terms = []
+    ;   Terms = [T | Rest],
+        read_all(In, Rest)
+    ).
+
+shared(Same, Different) :-
+    round_trip([f(X, Y, X)], [f(A, B, C)]),
+    X = 1,
+    Y = 2,
+    ( A == C -> Same = yes ; Same = no ),
+    ( A == B -> Different = no ; Different = yes ).
+
+not_fast(E) :-
+    process_create(path(echo), ['plain text'], [stdout(pipe(Out))]),
+    catch(fast_read(Out, _T), error(E, _Context), true),
+    close(Out).
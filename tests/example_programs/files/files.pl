@@ -0,0 +1,18 @@
+fixture(Name, Path) :-
+    atom_concat('tests/example_programs/files/', Name, Path).
+
+relative_name(Spec, Options, Rest) :-
+    absolute_file_name('.', Cwd),
+    absolute_file_name(Spec, Absolute, Options),
+    atom_concat(Cwd, Rest, Absolute).
+
+scratch_directory(Dir, Before, During, After) :-
+    Dir = 'target/files_test_directory',
+    ( exists_directory(Dir) -> Before = yes ; Before = no ),
+    make_directory(Dir),
+    ( exists_directory(Dir) -> During = yes ; During = no ),
+    delete_directory(Dir),
+    ( exists_directory(Dir) -> After = yes ; After = no ).
+
+file_error(Goal, E) :-
+    catch(Goal, error(E, _Context), true).
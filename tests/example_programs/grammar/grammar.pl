@@ -0,0 +1,48 @@
+expr(plus(A, B)) --> term(A), ['+'], expr(B).
+expr(minus(A, B)) --> term(A), ['-'], expr(B).
+expr(A) --> term(A).
+
+term(times(A, B)) --> factor(A), ['*'], term(B).
+term(A) --> factor(A).
+
+factor(A) --> ['('], expr(A), [')'].
+factor(A) --> digit(A).
+
+digit(0) --> ['0'].
+digit(1) --> ['1'].
+digit(2) --> ['2'].
+digit(3) --> ['3'].
+
+greeting --> [hello], name.
+
+name --> [world].
+name --> [prolog].
+
+abc --> `abc`.
+
+number(N) --> digit(D), {digits(D, N)}.
+
+digits(D, d(D)).
+
+twice(G) --> G, G.
+
+word(W) --> call(letters, W).
+
+letters([C], [C|S], S) :- letter(C).
+
+letter(a).
+letter(b).
+
+peek(C), [C] --> [C].
+
+hello --> "hello".
+
+lines([L|Ls]) --> string_without(`\n`, L), "\n", lines(Ls).
+lines([]) --> eos.
+
+first_last([F|Ls], F, L) :- last([F|Ls], L).
+
+last([X], X).
+last([Y|T], X) :- last(T, X).
+
+first_line(L, Rest) --> string_without(`\n`, L), "\n", remainder(Rest).
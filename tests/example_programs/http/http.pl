@@ -0,0 +1,80 @@
+hello(Request, reply(200, Body)) :-
+    member(path('/hello'), Request),
+    member(search(Search), Request),
+    member(name=Name, Search),
+    format(atom(Body), "Hello, ~w!", [Name]).
+hello(Request, reply(201, ['X-Echo'=Body], Body)) :-
+    member(method(post), Request),
+    member(body(Body), Request).
+hello(Request, _Reply) :-
+    member(path('/error'), Request),
+    throw(oops).
+
+get(Port, Request, Response) :-
+    tcp_connect(localhost:Port, In, Out),
+    format(Out, "~w HTTP/1.1\r\nHost: localhost\r\n\r\n", [Request]),
+    close(Out),
+    read_response(In, Response),
+    close(In).
+
+post(Port, Path, Body, Response) :-
+    tcp_connect(localhost:Port, In, Out),
+    string_length(Body, Length),
+    format(Out, "POST ~w HTTP/1.1\r\nContent-Length: ~w\r\n\r\n~w", [Path, Length, Body]),
+    close(Out),
+    read_response(In, Response),
+    close(In).
+
+read_response(In, Status-Body) :-
+    read_line_to_string(In, Status),
+    skip_headers(In),
+    read_string(In, _Length, Body).
+
+skip_headers(In) :-
+    read_line_to_string(In, Line),
+    ( Line == "" -> true ; skip_headers(In) ).
+
+serve(Hello, Missing, Failed, Posted) :-
+    http_server(hello, [port(Port), workers(2)]),
+    get(Port, 'GET /hello?name=world', Hello),
+    get(Port, 'GET /missing', Missing),
+    get(Port, 'GET /error', Failed),
+    post(Port, '/echo', "ping", Posted),
+    http_stop_server(Port, []).
+
+send(Port, Text, Response) :-
+    tcp_connect(localhost:Port, In, Out),
+    write(Out, Text),
+    close(Out),
+    read_response(In, Response),
+    close(In).
+
+headers(0, "") :- !.
+headers(N, Headers) :-
+    M is N - 1,
+    headers(M, Rest),
+    format(string(Headers), "X-A: b\r\n~w", [Rest]).
+
+% Each request ends where the server stops reading it, so that nothing is left unread when it
+% closes the connection.
+limits(TooLarge, TooManyHeaders, TooLong) :-
+    http_server(hello, [port(Port)]),
+    send(Port, "POST /echo HTTP/1.1\r\nContent-Length: 2000000\r\n\r\n", TooLarge),
+    headers(101, Headers),
+    format(string(Many), "GET /hello HTTP/1.1\r\n~w", [Headers]),
+    send(Port, Many, TooManyHeaders),
+    format(string(Long), "GET /~*c", [8187, 0'a]),
+    send(Port, Long, TooLong),
+    http_stop_server(Port, []).
+
+:- dynamic started/1.
+
+% The server is started and stopped by threads other than the one that calls it.
+decoded(Hello, Stopped, Workers) :-
+    thread_create((http_server(hello, [port(Port)]), assertz(started(Port))), Starter, []),
+    thread_join(Starter, true),
+    started(Port),
+    get(Port, 'GET /hello?name=caf%C3%A9+au+lait%21', Hello),
+    thread_create(http_stop_server(Port, []), Stopper, []),
+    thread_join(Stopper, Stopped),
+    catch(http_server(hello, [workers(0)]), error(Workers, _), true).
@@ -0,0 +1,29 @@
+:- set_prolog_flag(iso, true).
+
+conforms([Goal, success]) :-
+    !,
+    catch(Goal, _Ball, fail),
+    !.
+conforms([Goal, failure]) :-
+    !,
+    catch(\+ Goal, _Ball, fail).
+conforms([Goal, [Answer | Answers]]) :-
+    !,
+    bound(Answer, Vars, _Values),
+    catch(findall(Vars, Goal, Found), _Ball, fail),
+    values([Answer | Answers], Vars, Expected),
+    \+ \+ Found = Expected.
+conforms([Goal, Error]) :-
+    catch((Goal, fail), error(Found, _Context), true),
+    nonvar(Found),
+    \+ \+ Found = Error.
+
+bound([], [], []).
+bound([Var = Value | Bindings], [Var | Vars], [Value | Values]) :-
+    bound(Bindings, Vars, Values).
+
+values([], _Vars, []).
+values([Answer | Answers], Vars, [Values | Rest]) :-
+    copy_term(Vars-Answer, Vars1-Answer1),
+    bound(Answer1, Vars1, Values),
+    values(Answers, Vars, Rest).
@@ -0,0 +1,333 @@
+% A subset of the tests of the INRIA ISO conformance suite (inriasuite.pl, by J. P. E. Hodgson,
+% after the examples of ISO/IEC 13211-1), copied by hand rather than vendored, one to a line as
+% [Goal, Expected]. Expected is success, failure, the substitutions of each answer written
+% [[X <-- Value, ...], ...], or the formal term of the error the goal raises. The tests that do
+% not conform yet are listed in known_failures.pl.
+
+% 7.8.1 true/0, 7.8.2 fail/0
+[true, success].
+[fail, failure].
+
+% 7.8.3 call/1
+[call(!), success].
+[call(fail), failure].
+[call((fail, X)), failure].
+[call((fail, call(1))), failure].
+[call(_), instantiation_error].
+[call(1), type_error(callable, 1)].
+[call((fail, 1)), type_error(callable, (fail, 1))].
+[call((write(3), 1)), type_error(callable, (write(3), 1))].
+[call((1; true)), type_error(callable, (1; true))].
+
+% 7.8.4 ,/2
+[','(X = 1, var(X)), failure].
+[','(var(X), X = 1), [[X <-- 1]]].
+[','(X = true, call(X)), [[X <-- true]]].
+
+% 7.8.5 ;/2
+[';'(true, fail), success].
+[';'((!, fail), true), failure].
+[';'(!, call(3)), success].
+[';'((X = 1, !), X = 2), [[X <-- 1]]].
+[';'(X = 1, X = 2), [[X <-- 1], [X <-- 2]]].
+
+% 7.8.7 ->/2
+['->'(true, fail), failure].
+['->'(fail, true), failure].
+['->'(true, X = 1), [[X <-- 1]]].
+['->'(';'(X = 1, X = 2), true), [[X <-- 1]]].
+
+% 7.8.8 ;/2 with ->/2
+[';'('->'(true, fail), fail), failure].
+[';'('->'(fail, true), true), success].
+[';'('->'(true, X = 1), X = 2), [[X <-- 1]]].
+[';'('->'(fail, X = 1), X = 2), [[X <-- 2]]].
+[';'('->'(';'(X = 1, X = 2), true), true), [[X <-- 1]]].
+
+% 7.8.9 catch/3, 7.8.10 throw/1
+[catch(true, _, true), success].
+[catch(number_codes(X, L), error(instantiation_error, _), fail), failure].
+[catch(throw(ball), B, true), [[B <-- ball]]].
+[throw(_), instantiation_error].
+
+% 8.2.1 =/2
+['='(1, 1), success].
+['='(X, 1), [[X <-- 1]]].
+['='(f(X, def), f(def, Y)), [[X <-- def, Y <-- def]]].
+['='(1, 2), failure].
+['='(1, 1.0), failure].
+['='(g(X), f(f(X))), failure].
+['='(f(X, 1), f(a(X), 2)), failure].
+
+% 8.2.2 unify_with_occurs_check/2
+[unify_with_occurs_check(1, 1), success].
+[unify_with_occurs_check(X, 1), [[X <-- 1]]].
+[unify_with_occurs_check(X, a(X)), failure].
+[unify_with_occurs_check(f(X, 1), f(a(X), 2)), failure].
+
+% 8.2.3 \=/2
+['\\='(1, 1), failure].
+['\\='(X, 1), failure].
+['\\='(1, 2), success].
+['\\='(1, 1.0), success].
+
+% 8.3 type testing
+[var(_), success].
+[var(foo), failure].
+[atom(atom), success].
+[atom('string'), success].
+[atom(a(b)), failure].
+[atom([]), success].
+[atom(6), failure].
+[number(3), success].
+[number(3.3), success].
+[number(a), failure].
+[atomic(2.5), success].
+[atomic(f(a)), failure].
+[compound(-(a)), success].
+[compound([a]), success].
+[compound([]), failure].
+[callable(a), success].
+[callable(3), failure].
+[nonvar(33.3), success].
+[nonvar(_), failure].
+[integer(3), success].
+[integer(3.3), failure].
+[float(3.3), success].
+[float(3), failure].
+
+% 8.4.1 compare/3 and the term order
+['@=<'(1.0, 1), success].
+['@<'(1.0, 1), success].
+['\\=='(1, 1), failure].
+['@=<'(aardvark, zebra), success].
+['@=<'(short, shorter), success].
+['@<'(foo(a, b), north(a)), failure].
+['@>'(foo(b), foo(a)), success].
+['=='(X, X), success].
+['=='(X, Y), failure].
+[compare(O, 1, 1.0), [[O <-- (>)]]].
+[compare(O, aardvark, zebra), [[O <-- (<)]]].
+
+% 8.5.1 functor/3
+[functor(foo(a, b, c), foo, 3), success].
+[functor(foo(a, b, c), X, Y), [[X <-- foo, Y <-- 3]]].
+[functor(X, foo, 3), [[X <-- foo(_, _, _)]]].
+[functor(X, foo, 0), [[X <-- foo]]].
+[functor(mats(A, B), A, B), [[A <-- mats, B <-- 2]]].
+[functor(foo(a), foo, 2), failure].
+[functor(1, X, Y), [[X <-- 1, Y <-- 0]]].
+[functor(X, Y, 3), instantiation_error].
+[functor(X, foo, a), type_error(integer, a)].
+[functor(X, 1.5, 1), type_error(atomic, 1.5)].
+[functor(X, foo(a), 1), type_error(atomic, foo(a))].
+
+% 8.5.2 arg/3
+[arg(1, foo(a, b), a), success].
+[arg(1, foo(X, b), a), [[X <-- a]]].
+[arg(1, foo(a, b), b), failure].
+[arg(0, foo(a, b), foo), failure].
+[arg(3, foo(3, 4), N), failure].
+[arg(X, foo(a, b), a), instantiation_error].
+[arg(1, X, a), instantiation_error].
+[arg(0, atom, A), type_error(compound, atom)].
+[arg(0, 3, A), type_error(compound, 3)].
+[arg(a, foo(a, b), X), type_error(integer, a)].
+
+% 8.5.3 =../2
+['=..'(foo(a, b), [foo, a, b]), success].
+['=..'(X, [foo, a, b]), [[X <-- foo(a, b)]]].
+['=..'(foo(a, b), L), [[L <-- [foo, a, b]]]].
+['=..'(foo(X, b), [foo, a, Y]), [[X <-- a, Y <-- b]]].
+['=..'(1, [1]), success].
+['=..'(foo(a, b), [foo, b, a]), failure].
+['=..'(X, Y), instantiation_error].
+['=..'(X, [foo, a | Y]), instantiation_error].
+['=..'(X, [foo | bar]), type_error(list, [foo | bar])].
+['=..'(X, [Foo, bar]), instantiation_error].
+['=..'(X, [3, 1]), type_error(atom, 3)].
+['=..'(X, [1.1, foo]), type_error(atom, 1.1)].
+['=..'(X, [a(b), 1]), type_error(atom, a(b))].
+['=..'(X, 4), type_error(list, 4)].
+
+% 8.5.4 copy_term/2
+[copy_term(X, Y), success].
+[copy_term(X, 3), success].
+[copy_term(_, a), success].
+[copy_term(a + X, X + b), [[X <-- a]]].
+[copy_term(_, _), success].
+[copy_term(a, b), failure].
+[copy_term(a + X, b + Y), failure].
+
+% 8.6.1 is/2
+['is'(Result, 3 + 11.0), [[Result <-- 14.0]]].
+['is'(foo, 77), failure].
+['is'(77, N), instantiation_error].
+['is'(X, foo), type_error(evaluable, foo / 0)].
+
+% 8.7.1 arithmetic comparison
+['=:='(0, 1), failure].
+['=\\='(0, 1), success].
+['<'(0, 1), success].
+['>'(0, 1), failure].
+['>='(0, 1), failure].
+['=<'(0, 1), success].
+['=:='(1.0, 1), success].
+['<'(1.0, 1), failure].
+['=:='(3 * 2, 7 - 1), success].
+['=:='(X, 5), instantiation_error].
+['<'(X, 5), instantiation_error].
+
+% 8.8.1 clause/2
+[clause(x, Body), failure].
+[clause(_, B), instantiation_error].
+[clause(4, B), type_error(callable, 4)].
+[clause(f(_), 5), type_error(callable, 5)].
+[clause(atom(_), Body), permission_error(access, private_procedure, atom / 1)].
+
+% 8.9 database
+[asserta(_), instantiation_error].
+[asserta(4), type_error(callable, 4)].
+[asserta((foo :- 4)), type_error(callable, (foo :- 4))].
+[asserta((atom(_) :- true)), permission_error(modify, static_procedure, atom / 1)].
+[assertz(_), instantiation_error].
+[assertz(4), type_error(callable, 4)].
+[assertz((foo :- 4)), type_error(callable, (foo :- 4))].
+[retract((x :- in_eq(_, _))), failure].
+[retract((4 :- X)), type_error(callable, 4)].
+[retract((atom(_) :- X == '[]')), permission_error(modify, static_procedure, atom / 1)].
+[abolish(foo / a), type_error(integer, a)].
+[abolish(foo / (-1)), domain_error(not_less_than_zero, -1)].
+[abolish(5 / 2), type_error(atom, 5)].
+[abolish(foo / _), instantiation_error].
+
+% 8.10 all solutions
+[findall(X, (X = 1; X = 2), S), [[S <-- [1, 2]]]].
+[findall(X + Y, (X = 1), S), [[S <-- [1 + _]]]].
+[findall(X, fail, L), [[L <-- []]]].
+[findall(X, (X = 1; X = 1), S), [[S <-- [1, 1]]]].
+[findall(X, (X = 2; X = 1), [1, 2]), failure].
+[findall(X, (X = 1; X = 2), [X, Y]), [[X <-- 1, Y <-- 2]]].
+[findall(X, G, S), instantiation_error].
+[findall(X, 4, S), type_error(callable, 4)].
+[bagof(X, (X = 1; X = 2), S), [[S <-- [1, 2]]]].
+[bagof(X, (X = 1; X = 2), X), [[X <-- [1, 2]]]].
+[bagof(X, fail, S), failure].
+[bagof(X, G, S), instantiation_error].
+[bagof(X, 1, S), type_error(callable, 1)].
+[setof(X, (X = 1; X = 2), S), [[S <-- [1, 2]]]].
+[setof(X, (X = 2; X = 1), S), [[S <-- [1, 2]]]].
+[setof(X, (X = 2; X = 2), S), [[S <-- [2]]]].
+[setof(X, fail, S), failure].
+
+% 8.15 logic and control
+['\\+'(true), failure].
+['\\+'(!), failure].
+['\\+'((!, fail)), success].
+['\\+'(4 = 5), success].
+['\\+'(3), type_error(callable, 3)].
+['\\+'(_), instantiation_error].
+[once(!), success].
+[once(repeat), success].
+[once(fail), failure].
+[once(3), type_error(callable, 3)].
+[once(_), instantiation_error].
+
+% 8.16 atomic term processing
+[atom_length('enchanted evening', N), [[N <-- 17]]].
+[atom_length('', N), [[N <-- 0]]].
+[atom_length('scarlet', 5), failure].
+[atom_length(Atom, 4), instantiation_error].
+[atom_length(123, 4), type_error(atom, 123)].
+[atom_length(atom, '4'), type_error(integer, '4')].
+[atom_concat('hello', ' world', A), [[A <-- 'hello world']]].
+[atom_concat(T, ' world', 'small world'), [[T <-- small]]].
+[atom_concat(hello, ' world', 'small world'), failure].
+[atom_concat(T1, T2, 'hello'), [[T1 <-- '', T2 <-- hello], [T1 <-- h, T2 <-- ello], [T1 <-- he, T2 <-- llo], [T1 <-- hel, T2 <-- lo], [T1 <-- hell, T2 <-- o], [T1 <-- hello, T2 <-- '']]].
+[atom_concat(A1, iso, A3), instantiation_error].
+[atom_concat(iso, A2, A3), instantiation_error].
+[atom_chars('', L), [[L <-- []]]].
+[atom_chars([], L), [[L <-- ['[', ']']]]].
+[atom_chars('''', L), [[L <-- ['''']]]].
+[atom_chars('ant', L), [[L <-- [a, n, t]]]].
+[atom_chars(Str, ['s', 'o', 'p']), [[Str <-- 'sop']]].
+[atom_chars('North', ['N' | X]), [[X <-- ['o', 'r', 't', 'h']]]].
+[atom_chars('soap', ['s', 'o', 'p']), failure].
+[atom_chars(X, Y), instantiation_error].
+[atom_codes('', L), [[L <-- []]]].
+[atom_codes([], L), [[L <-- [0'[, 0']]]]].
+[atom_codes('ant', L), [[L <-- [0'a, 0'n, 0't]]]].
+[atom_codes(Str, [0's, 0'o, 0'p]), [[Str <-- 'sop']]].
+[atom_codes(X, Y), instantiation_error].
+[char_code(a, Code), [[Code <-- 0'a]]].
+[char_code(Char, 0'c), [[Char <-- c]]].
+[char_code(b, 0'b), success].
+[char_code('ab', Code), type_error(character, 'ab')].
+[char_code(a, x), type_error(integer, x)].
+[char_code(Char, Code), instantiation_error].
+[number_codes(33, L), [[L <-- [0'3, 0'3]]]].
+[number_codes(33, [0'3, 0'3]), success].
+[number_codes(33.0, L), [[L <-- [0'3, 0'3, 0'., 0'0]]]].
+[number_codes(A, [0'-, 0'2, 0'5]), [[A <-- -25]]].
+[number_codes(A, [0'0, 0'x, 0'f]), [[A <-- 15]]].
+[number_codes(a, L), type_error(number, a)].
+[number_codes(A, L), instantiation_error].
+[number_chars(33, L), [[L <-- ['3', '3']]]].
+[number_chars(A, ['3', '.', '3', 'E', '+', '0']), [[A <-- 3.3]]].
+[number_chars(A, ['-', '2', '5']), [[A <-- -25]]].
+[number_chars(A, ['3', ' ']), syntax_error(_)].
+[number_chars(a, L), type_error(number, a)].
+[number_chars(A, L), instantiation_error].
+[sub_atom(abracadabra, 0, 5, _, S2), [[S2 <-- 'abrac']]].
+[sub_atom(abracadabra, _, 5, 0, S2), [[S2 <-- 'dabra']]].
+[sub_atom(abracadabra, 3, L, 3, S2), [[L <-- 5, S2 <-- 'acada']]].
+[sub_atom(abracadabra, B, 2, A, ab), [[B <-- 0, A <-- 9], [B <-- 7, A <-- 2]]].
+[sub_atom('Banana', 3, 2, _, S2), [[S2 <-- 'an']]].
+[sub_atom(Banana, 3, 2, _, S2), instantiation_error].
+[sub_atom(f(a), 2, 2, _, S2), type_error(atom, f(a))].
+
+% 9 evaluable functors
+['is'(X, 7 + 35), [[X <-- 42]]].
+['is'(X, 0 + 3.2), [[X <-- 3.2]]].
+['is'(X, 3 + 1.0), [[X <-- 4.0]]].
+['is'(X, 7 - 35), [[X <-- -28]]].
+['is'(X, 7 * 35), [[X <-- 245]]].
+['is'(X, 7 // 35), [[X <-- 0]]].
+['is'(X, -7 // 2), [[X <-- -3]]].
+['is'(X, 7 / 35.0), [[X <-- 0.2]]].
+['is'(X, 7 / 7), [[X <-- 1.0]]].
+['is'(X, 7 mod 0), evaluation_error(zero_divisor)].
+['is'(X, 7 // 0), evaluation_error(zero_divisor)].
+['is'(X, 7 mod 3), [[X <-- 1]]].
+['is'(X, -7 mod 3), [[X <-- 2]]].
+['is'(X, 7 rem -3), [[X <-- 1]]].
+['is'(X, -(7)), [[X <-- -7]]].
+['is'(X, abs(-7)), [[X <-- 7]]].
+['is'(X, sign(-7)), [[X <-- -1]]].
+['is'(X, min(2, 3)), [[X <-- 2]]].
+['is'(X, max(2, 3.0)), [[X <-- 3.0]]].
+['is'(X, float(7)), [[X <-- 7.0]]].
+['is'(X, float_integer_part(3.5)), [[X <-- 3.0]]].
+['is'(X, float_fractional_part(-3.5)), [[X <-- -0.5]]].
+['is'(X, truncate(3.5)), [[X <-- 3]]].
+['is'(X, round(3.5)), [[X <-- 4]]].
+['is'(X, ceiling(-0.5)), [[X <-- 0]]].
+['is'(X, floor(-0.5)), [[X <-- -1]]].
+['is'(X, float_integer_part(a)), type_error(evaluable, a / 0)].
+['is'(X, foo(77)), type_error(evaluable, foo / 1)].
+['is'(X, 5 ** 3), [[X <-- 125.0]]].
+['is'(X, 5 ^ 3), [[X <-- 125]]].
+['is'(X, 2 ^ -1), type_error(float, 2)].
+['is'(X, sqrt(-1.0)), evaluation_error(undefined)].
+['is'(X, log(0)), evaluation_error(undefined)].
+['is'(X, 16 >> 2), [[X <-- 4]]].
+['is'(X, 16 << 2), [[X <-- 64]]].
+['is'(X, 10 /\ 12), [[X <-- 8]]].
+['is'(X, 10 \/ 12), [[X <-- 14]]].
+['is'(X, \(10)), [[X <-- -11]]].
+['is'(X, xor(10, 12)), [[X <-- 6]]].
+['is'(X, atan2(1, 0)), [[X <-- 1.5707963267948966]]].
+['is'(X, msb(8)), type_error(evaluable, msb / 1)].
+['is'(X, gcd(4, 6)), type_error(evaluable, gcd / 2)].
+['is'(X, e), type_error(evaluable, e / 0)].
+['is'(X, "a"), type_error(evaluable, _)].
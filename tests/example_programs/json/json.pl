@@ -0,0 +1,23 @@
+read_file(File, Dict, Classic, Rest) :-
+    process_create(path(cat), [File], [stdout(pipe(Out))]),
+    json_read_dict(Out, Dict),
+    json_read(Out, Rest),
+    close(Out),
+    process_create(path(cat), [File], [stdout(pipe(Again))]),
+    json_read(Again, Classic),
+    close(Again).
+
+round_trip(Text, Out) :-
+    atom_json_term(Text, Term, [value_string_as(string)]),
+    atom_json_term(Out, Term, []).
+
+write_terms(A, B, C) :-
+    json_write(atom(A), json([name="x", size=3, ok='@'(true), tags=[a, "b"], none=null])),
+    json_write(string(B), [1.5, -2, "quote\"d", json([])]),
+    atom_json_term(C, json([k-v]), []).
+
+bad_json(E) :-
+    catch(atom_json_term('{"a": }', _Term, []), error(E, _Context), true).
+
+bad_term(E) :-
+    catch(json_write(atom(_A), foo(bar)), error(E, _Context), true).
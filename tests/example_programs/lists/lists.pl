@@ -0,0 +1 @@
+pairs_up(Xs, Pairs) :- findall(X-Y, (member(X, Xs), member(Y, Xs), X @< Y), Pairs).
@@ -0,0 +1,6 @@
+:- initialization(main, main).
+
+main :-
+    current_prolog_flag(argv, [_Program, Name, Count]),
+    format("~a ~a~n", [Name, Count]),
+    Name == hello.
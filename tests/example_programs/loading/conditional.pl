@@ -0,0 +1,18 @@
+feature(tabling).
+
+:- if(feature(threads)).
+backend(threads).
+:- elif(feature(tabling)).
+backend(tabling).
+:- if(fail).
+nested(wrong).
+:- else.
+nested(right).
+:- endif.
+:- else.
+backend(plain).
+:- endif.
+
+:- if(backend(tabling)).
+checked(yes).
+:- endif.
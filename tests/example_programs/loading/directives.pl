@@ -0,0 +1,12 @@
+:- dynamic seen/1.
+:- table path/2.
+:- set_prolog_flag(double_quotes, codes).
+:- assertz(seen(first)).
+:- initialization(assertz(seen(second))).
+:- assertz(seen(third)).
+:- initialization(main, main).
+:- fail.
+:- throw(oops).
+
+path(X, Y) :- edge(X, Y).
+edge(a, b).
@@ -0,0 +1,14 @@
+%! len(+List, -Length) is det.
+%
+%  True when Length is the number of elements of List.
+len([], 0).
+len([_H|T], N) :- len(T, M), N is M + 1.
+
+% Not documentation.
+sign(X, pos) :- X > 0.
+sign(X, neg) :- X =< 0.
+
+%! first(?List, ?First) is semidet.
+%! first(-List, +First) is det.
+%  First is the first element of List.
+first([X|_T], X).
@@ -0,0 +1,4 @@
+:- set_prolog_flag(autoload, false).
+:- use_module(library(dcg/basics)).
+
+number(N) --> integer(N).
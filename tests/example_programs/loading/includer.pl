@@ -0,0 +1,7 @@
+:- include(parts/part).
+:- ensure_loaded(lib/util).
+:- ensure_loaded(lib/util).
+:- use_module(lib/util).
+:- ensure_loaded(lib/ping).
+
+part(three).
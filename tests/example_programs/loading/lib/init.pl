@@ -0,0 +1 @@
+:- initialization(format("library~n")).
@@ -0,0 +1,3 @@
+:- ensure_loaded(ping).
+
+pong.
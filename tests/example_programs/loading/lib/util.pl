@@ -0,0 +1 @@
+shout(X, Y) :- atom_concat(X, '!', Y).
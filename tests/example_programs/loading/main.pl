@@ -0,0 +1,7 @@
+file_search_path(app, lib).
+
+:- use_module(library(dcg/basics)).
+:- use_module(app(util)).
+:- use_module(app(nested/colours), [colour/1]).
+
+greeting(G) :- shout(hello, G).
@@ -0,0 +1 @@
+:- use_module(library(no_such_library)).
@@ -0,0 +1,3 @@
+:- set_prolog_flag(autoload, false).
+
+number(N) --> integer(N).
@@ -0,0 +1,2 @@
+part(one).
+:- include(part2).
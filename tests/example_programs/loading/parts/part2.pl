@@ -0,0 +1 @@
+part(two).
@@ -0,0 +1,5 @@
+:- use_module(lib/init).
+:- initialization(format("loaded~n")).
+:- initialization(main, main).
+
+main :- format("hello~n").
@@ -0,0 +1,3 @@
+r(1).
+r(2) :- .
+r(3).
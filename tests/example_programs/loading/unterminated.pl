@@ -0,0 +1,2 @@
+:- if(true).
+fact(a).
@@ -0,0 +1,10 @@
+:- meta_predicate twice(0), apply_to(1, ?), goal_of(0, -).
+
+twice(G) :- call(G), call(G).
+
+apply_to(G, X) :- call(G, X).
+
+goal_of(G, G).
+
+count(a, 1).
+count(b, 2).
@@ -0,0 +1,42 @@
+:- parallel route/2, where/1, risky/1.
+
+route(X, a) :-
+    between(1, 3, X).
+route(X, b) :-
+    member(X, [10, 20]),
+    X > 5.
+route(oops, c) :-
+    fail.
+route(99, d).
+
+where(T) :-
+    thread_self(T).
+where(T) :-
+    thread_self(T).
+
+risky(1).
+risky(_X) :-
+    throw(boom).
+risky(3).
+
+routes(Routes) :-
+    findall(X-Y, route(X, Y), Routes).
+
+workers(Answers, Main) :-
+    thread_self(Me),
+    findall(T, where(T), Ts),
+    length(Ts, Answers),
+    (   member(Me, Ts)
+    ->  Main = yes
+    ;   Main = no
+    ).
+
+first_risky(X, E) :-
+    (   risky(X)
+    ->  true
+    ),
+    catch(findall(Y, risky(Y), _Ys), E, true).
+
+cut_first(X) :-
+    route(X, _Y),
+    !.
@@ -0,0 +1,42 @@
+:- persistent visited(url:atom, hits:nonneg), tag(name:atom).
+
+count(Url) :-
+    (   retract_visited(Url, N)
+    ->  M is N + 1
+    ;   M = 1
+    ),
+    assert_visited(Url, M).
+
+session(Visits, Tags) :-
+    count(home),
+    count(about),
+    count(home),
+    assert_tag(new),
+    assert_tag(old),
+    retractall_tag(old),
+    findall(U-N, visited(U, N), Visits),
+    findall(T, tag(T), Tags).
+
+journal(Before, After, Entries) :-
+    File = 'target/persistency_journal.db',
+    db_attach(File, []),
+    count(home),
+    count(home),
+    assert_tag(kept),
+    db_detach,
+    retractall_visited(_U, _N),
+    findall(U-N, visited(U, N), Before),
+    db_attach(File, []),
+    findall(U-N, visited(U, N), After),
+    db_sync(gc),
+    db_detach,
+    process_create(path(wc), ['-l', File], [stdout(pipe(Out))]),
+    read_line_to_string(Out, Entries),
+    close(Out),
+    delete_file(File).
+
+bad_type(E) :-
+    catch(assert_visited(home, -1), error(E, _Context), true).
+
+unbound(E) :-
+    catch(assert_tag(_T), error(E, _Context), true).
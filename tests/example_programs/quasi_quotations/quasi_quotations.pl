@@ -0,0 +1,14 @@
+csv(Text, Fields) :- string_codes(Text, Codes), phrase(fields(Fields), Codes).
+
+fields([F|Fs]) --> field(F), ",", fields(Fs).
+fields([F]) --> field(F).
+
+field(F) --> string_without(`,`, Codes), {atom_codes(F, Codes)}.
+
+sql(Table, Text, query(Table, Text)).
+
+row(R) :- unify(R, {|csv||a,b,c|}).
+
+users({|sql(users)||SELECT name FROM users|}).
+
+unify(X, X).
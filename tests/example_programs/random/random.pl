@@ -0,0 +1,19 @@
+repeatable(Xs, Ys) :-
+    set_random(seed(42)),
+    draws(Xs),
+    set_random(seed(42)),
+    draws(Ys).
+
+draws([X, Y, Z, P]) :-
+    random(X),
+    random_between(1, 6, Y),
+    random_member(Z, [a, b, c]),
+    random_permutation([1, 2, 3, 4], P).
+
+in_range :-
+    random(X), X >= 0.0, X < 1.0,
+    random_between(-3, 3, Y), Y >= -3, Y =< 3.
+
+permutes(P) :-
+    random_permutation([c, a, b], P),
+    member(a, P), member(b, P), member(c, P).
@@ -0,0 +1,28 @@
+queue(Items) :-
+    recordz(jobs, second),
+    recordz(jobs, third),
+    recorda(jobs, first),
+    findall(X, recorded(jobs, X), Items).
+
+fresh(A, Shared) :-
+    recordz(point(_A, _B), p(X, X, _Y)),
+    recorded(point(1, 2), p(1, A, _Z)),
+    recorded(point(_A, _B), p(C, D, _W)),
+    (   C == D, var(C)
+    ->  Shared = yes
+    ;   Shared = no
+    ).
+
+erased(Before, After, Again) :-
+    recordz(tasks, a, Ref),
+    recordz(tasks, b),
+    instance(Ref, Before),
+    erase(Ref),
+    findall(X, recorded(tasks, X), After),
+    (   erase(Ref)
+    ->  Again = yes
+    ;   Again = no
+    ).
+
+bad_key(E) :-
+    catch(recorda(1.5, x), error(E, _Context), true).
@@ -0,0 +1,4 @@
+:- use_module(library(pairs)).
+:- use_module(library(ordsets)).
+
+ages([alice-31, bob-27, carol-45]).
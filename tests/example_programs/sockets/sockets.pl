@@ -0,0 +1,25 @@
+echo_once(Reply) :-
+    tcp_listen(localhost:Port, Socket),
+    tcp_connect(localhost:Port, ClientIn, ClientOut),
+    tcp_accept(Socket, ServerIn, ServerOut),
+    format(ClientOut, "ping~n", []),
+    close(ClientOut),
+    read_line_to_string(ServerIn, Line),
+    string_concat(Line, " pong", Answer),
+    format(ServerOut, "~w~n", [Answer]),
+    close(ServerOut),
+    close(ServerIn),
+    read_line_to_string(ClientIn, Reply),
+    read_line_to_string(ClientIn, end_of_file),
+    close(ClientIn),
+    tcp_close_socket(Socket).
+
+refused(E) :-
+    tcp_listen(localhost:Port, Socket),
+    tcp_close_socket(Socket),
+    catch(tcp_connect(localhost:Port, _In, _Out), error(E, _Context), true).
+
+closed_socket(E) :-
+    tcp_listen(localhost:_Port, Socket),
+    tcp_close_socket(Socket),
+    catch(tcp_accept(Socket, _In, _Out), error(E, _Context), true).
@@ -0,0 +1,40 @@
+setup(Db) :-
+    sqlite_open(':memory:', Db),
+    sqlite_query(Db, 'CREATE TABLE people (name TEXT, age INTEGER, score REAL)', [], _Created),
+    sqlite_query(Db, 'INSERT INTO people VALUES (?, ?, ?)', [alice, 30, 9.5], _A),
+    sqlite_query(Db, 'INSERT INTO people VALUES (?, ?, ?)', ["bob", 25, '$null$'], _B),
+    sqlite_query(Db, 'INSERT INTO people VALUES (?, ?, ?)', [carol, 41, 7.25], _C).
+
+people(Row) :-
+    setup(Db),
+    sqlite_query(Db, 'SELECT name, age, score FROM people ORDER BY age', [], Row).
+
+older(Min, Name) :-
+    setup(Db),
+    sqlite_query(Db, "SELECT name FROM people WHERE age > ?", [Min], row(Name)).
+
+update(Affected, Count) :-
+    setup(Db),
+    sqlite_query(Db, 'UPDATE people SET score = 0 WHERE age < 35', [], Affected),
+    sqlite_query(Db, 'SELECT count(*) FROM people WHERE score = 0', [], row(Count)),
+    sqlite_close(Db).
+
+file(Rows) :-
+    File = 'target/sqlite_test.db',
+    sqlite_open(File, Db),
+    sqlite_query(Db, 'CREATE TABLE IF NOT EXISTS kv (k TEXT PRIMARY KEY, v TEXT)', [], _Created),
+    sqlite_query(Db, 'INSERT OR REPLACE INTO kv VALUES (?, ?)', [key, value], _Inserted),
+    sqlite_close(Db),
+    sqlite_open(File, Again),
+    findall(K-V, sqlite_query(Again, 'SELECT k, v FROM kv', [], row(K, V)), Rows),
+    sqlite_close(Again),
+    delete_file(File).
+
+bad_sql(E) :-
+    sqlite_open(':memory:', Db),
+    catch(sqlite_query(Db, 'SELEKT 1', [], _Row), error(E, _Context), true).
+
+closed(Kind) :-
+    sqlite_open(':memory:', Db),
+    sqlite_close(Db),
+    catch(sqlite_query(Db, 'SELECT 1', [], _Row), error(existence_error(Kind, Db), _Context), true).
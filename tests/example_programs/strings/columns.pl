@@ -0,0 +1,20 @@
+row(Name, Qty) :-
+    format("~w", [Name]),
+    format("~t~d~12|~n", [Qty]).
+
+report(Positions) :-
+    line_count(user_output, L0),
+    format("ab"),
+    tab(1 + 2),
+    line_position(user_output, P1),
+    row(apples, 3),
+    line_position(user_output, P2),
+    format("~a~t~8|", [xyz]),
+    line_position(user_output, P3),
+    format("~n"),
+    line_count(user_output, L1),
+    Lines is L1 - L0,
+    Positions = [P1, P2, P3, Lines].
+
+tab_error(E) :-
+    catch(tab(foo), error(E, _Context), true).
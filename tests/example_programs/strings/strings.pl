@@ -0,0 +1,15 @@
+greeting(Name, Greeting) :-
+    atom_string(Name, NameString),
+    term_string("hello(X)", Term),
+    unify(Term, hello(NameString)),
+    term_string(Greeting, Term).
+
+unify(X, X).
+
+app([], Ys, Ys).
+app([X|Xs], Ys, [X|Zs]) :-
+    app(Xs, Ys, Zs).
+
+codes_length([], 0).
+codes_length([_C|Cs], s(N)) :-
+    codes_length(Cs, N).
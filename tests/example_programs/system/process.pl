@@ -0,0 +1,28 @@
+echo_lines(Lines) :-
+    process_create(path(echo), [hello, world], [stdout(pipe(Out))]),
+    read_line_to_string(Out, L1),
+    read_line_to_string(Out, L2),
+    close(Out),
+    Lines = [L1, L2].
+
+round_trip(N, S, Status) :-
+    process_create(path(cat), [], [stdin(pipe(In)), stdout(pipe(Out)), process(Pid)]),
+    format(In, "one~ntwo~n", []),
+    close(In),
+    read_string(Out, N, S),
+    close(Out),
+    process_wait(Pid, Status).
+
+exit_status(Code, Status) :-
+    atom_concat('exit ', Code, Script),
+    process_create(path(sh), ['-c', Script], [process(Pid)]),
+    wait(Pid, Status).
+
+process_failure(E) :-
+    catch(process_create(path(false), [], []), error(E, _Context), true).
+
+shell_results(Status, Output) :-
+    shell('true'),
+    \+ shell('exit 1'),
+    shell('exit 4', Status),
+    shell('echo captured; echo lines', 0, Output).
@@ -0,0 +1,26 @@
+count_signal(Signal) :-
+    flag(Signal, N, N + 1).
+
+wait_for_signal(Signal, Count) :-
+    flag(Signal, Count, Count),
+    Count > 0,
+    !.
+wait_for_signal(Signal, Count) :-
+    sleep(0.01),
+    wait_for_signal(Signal, Count).
+
+spin :-
+    spin.
+
+handled(Old, Current, Count) :-
+    on_signal(usr1, Old, count_signal),
+    on_signal(usr1, Current, Current),
+    shell('kill -USR1 $PPID'),
+    wait_for_signal(usr1, Count).
+
+interrupted(E) :-
+    on_signal(int, _Old, abort),
+    catch((shell('kill -INT $PPID'), spin), E, true).
+
+unknown_signal(E) :-
+    catch(on_signal(nosuchsignal, _Old, abort), error(E, _Context), true).
@@ -0,0 +1,7 @@
+env_roundtrip(Name, V, W, Gone) :-
+    setenv(Name, 42),
+    getenv(Name, V),
+    current_prolog_flag(environment, Env),
+    memberchk(Name = W, Env),
+    unsetenv(Name),
+    ( getenv(Name, _Unset) -> Gone = no ; Gone = yes ).
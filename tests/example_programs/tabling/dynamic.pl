@@ -0,0 +1,8 @@
+:- dynamic edge/2.
+
+edge(a, b).
+
+:- table path/2.
+
+path(X, Y) :- edge(X, Y).
+path(X, Y) :- path(X, Z), edge(Z, Y).
@@ -0,0 +1,17 @@
+:- thread_local link/2.
+
+:- table reach/2.
+
+reach(X, Y) :- link(X, Y).
+reach(X, Y) :- reach(X, Z), link(Z, Y).
+
+:- table beyond/1.
+
+beyond(Y) :- reach(a, Y), Y \== b.
+
+:- thread_local item/1.
+
+:- table drained/1.
+
+drained(X) :- item(X).
+drained(b) :- retract(item(a)).
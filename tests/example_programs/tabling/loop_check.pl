@@ -0,0 +1,10 @@
+:- loop_check(fail).
+
+ancestor(X, Y) :- ancestor(X, Z), parent(Z, Y).
+ancestor(X, Y) :- parent(X, Y).
+
+parent(ann, bob).
+parent(bob, cid).
+
+connected(X, Y) :- connected(Y, X).
+connected(a, b).
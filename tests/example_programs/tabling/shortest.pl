@@ -0,0 +1,31 @@
+:- table path(_, _, min).
+
+path(X, Y, C) :- edge(X, Y, C).
+path(X, Y, C) :- path(X, Z, C0), edge(Z, Y, C1), C is C0 + C1.
+
+edge(a, b, 4).
+edge(a, c, 1).
+edge(c, b, 2).
+edge(b, d, 1).
+edge(d, a, 3).
+
+:- table widest(_, _, max).
+
+widest(X, Y, W) :- edge(X, Y, W).
+widest(X, Y, W) :- widest(X, Z, W0), edge(Z, Y, W1), W is min(W0, W1).
+
+:- table route(_, _, lattice(shorter/3)).
+
+route(X, Y, [X, Y]) :- edge(X, Y, _W).
+route(X, Y, P) :- route(X, Z, P0), edge(Z, Y, _W), append(P0, [Y], P).
+
+shorter(P0, P1, P) :-
+    length(P0, L0),
+    length(P1, L1),
+    (L1 < L0 -> P = P1 ; P = P0).
+
+append([], Ys, Ys).
+append([X|Xs], Ys, [X|Zs]) :- append(Xs, Ys, Zs).
+
+length([], 0).
+length([_X|Xs], N) :- length(Xs, N0), N is N0 + 1.
@@ -0,0 +1,28 @@
+:- table path/2.
+
+path(X, Y) :- path(X, Z), edge(Z, Y).
+path(X, Y) :- edge(X, Y).
+
+edge(a, b).
+edge(b, c).
+edge(c, a).
+edge(c, d).
+
+:- table even/1, odd/1.
+
+even(0).
+even(N) :- odd(M), M < 10, N is M + 1.
+
+odd(N) :- even(M), M < 10, N is M + 1.
+
+:- table(fib/2).
+
+fib(0, 0).
+fib(1, 1).
+fib(N, F) :-
+    N > 1,
+    N1 is N - 1,
+    N2 is N - 2,
+    fib(N1, F1),
+    fib(N2, F2),
+    F is F1 + F2.
@@ -0,0 +1,47 @@
+:- thread_local counter/1, scaled/2.
+
+bump :-
+    retract(counter(N)),
+    M is N + 1,
+    assertz(counter(M)).
+
+count(Empty, N) :-
+    (   counter(_Any)
+    ->  Empty = no
+    ;   Empty = yes
+    ),
+    assertz(counter(0)),
+    bump,
+    bump,
+    bump,
+    counter(N).
+
+report(Parent) :-
+    count(Empty, N),
+    thread_send_message(Parent, count(Empty, N)).
+
+separate(Mine, Theirs) :-
+    assertz(counter(10)),
+    thread_self(Me),
+    thread_create(report(Me), T, []),
+    thread_get_message(Theirs),
+    thread_join(T, _Status),
+    counter(Mine).
+
+rules(Ys, Left) :-
+    assertz((scaled(X, Y) :- Y is X * 2)),
+    asserta((scaled(0, zero) :- !)),
+    findall(Y, (member(X, [0, 1, 2]), scaled(X, Y)), Ys),
+    retractall(scaled(_X, _Y)),
+    findall(X, scaled(X, _Z), Left).
+
+square(2, 4).
+
+static(E) :-
+    catch(assertz(square(1, 1)), error(E, _Context), true).
+
+asserted(Xs, E) :-
+    assertz(fresh(1)),
+    asserta(fresh(0)),
+    findall(X, fresh(X), Xs),
+    catch(assertz(atom(x)), error(E, _Context), true).
@@ -0,0 +1,116 @@
+square(X, Y) :-
+    Y is X * X.
+
+statuses(S1, S2, S3) :-
+    thread_create(square(3, 9), T1, []),
+    thread_create(square(3, 10), T2, []),
+    thread_create(throw(oops), T3, []),
+    thread_join(T1, S1),
+    thread_join(T2, S2),
+    thread_join(T3, S3).
+
+worker(Parent) :-
+    thread_get_message(job(X)),
+    square(X, Y),
+    thread_send_message(Parent, result(X, Y)),
+    worker(Parent).
+worker(_Parent).
+
+squares(Results) :-
+    thread_self(Me),
+    thread_create(worker(Me), W, [alias(squarer)]),
+    thread_send_message(squarer, job(2)),
+    thread_send_message(W, job(5)),
+    thread_get_message(result(2, A)),
+    thread_get_message(result(5, B)),
+    thread_send_message(W, stop),
+    thread_get_message(W, stop),
+    Results = [A, B].
+
+selective(X) :-
+    thread_self(Me),
+    thread_send_message(Me, first),
+    thread_send_message(Me, pick(7)),
+    thread_get_message(pick(X)),
+    thread_get_message(first).
+
+unknown(E) :-
+    catch(thread_join(nobody, _S), error(E, _Context), true).
+
+double(X, Y) :-
+    Y is X * 2.
+
+positive(X) :-
+    X > 0.
+
+doubled(Ys) :-
+    concurrent_maplist(double, [1, 2, 3, 4, 5, 6, 7, 8], Ys).
+
+checks(All, Some) :-
+    (   concurrent_maplist(positive, [3, 1, 2])
+    ->  All = yes
+    ;   All = no
+    ),
+    (   concurrent_maplist(positive, [3, -1, 2])
+    ->  Some = yes
+    ;   Some = no
+    ).
+
+pool(Xs, Forall, E) :-
+    concurrent(2, [X = 1, Y is 40 + 2, atom_length(abc, Z)], []),
+    Xs = [X, Y, Z],
+    (   concurrent_forall(member(N, [1, 2, 3]), N < 3)
+    ->  Forall = yes
+    ;   Forall = no
+    ),
+    catch(concurrent(2, [true, throw(oops)], []), E, true).
+
+try_gate :-
+    mutex_trylock(gate),
+    mutex_unlock(gate).
+
+gated(Before, After) :-
+    mutex_lock(gate),
+    thread_create(try_gate, T1, []),
+    thread_join(T1, Before),
+    mutex_unlock(gate),
+    thread_create(try_gate, T2, []),
+    thread_join(T2, After).
+
+guarded(X, Released, E) :-
+    mutex_create(M),
+    with_mutex(M, with_mutex(M, X = inner)),
+    (   with_mutex(M, fail)
+    ->  true
+    ;   true
+    ),
+    catch(with_mutex(M, throw(oops)), E, true),
+    thread_create((mutex_trylock(M), mutex_unlock(M)), T, []),
+    thread_join(T, Released).
+
+not_owner(E) :-
+    catch(mutex_unlock(gate), error(E, _Context), true).
+
+:- dynamic tally/1, visit/1.
+
+tally(0).
+
+bump :-
+    with_mutex(tally, (retract(tally(N)), M is N + 1, assertz(tally(M)))).
+
+bumps(0) :- !.
+bumps(K) :-
+    bump,
+    assertz(visit(K)),
+    K1 is K - 1,
+    bumps(K1).
+
+shared(N, Visits, Left) :-
+    findall(T, (between(1, 4, _I), thread_create(bumps(25), T, [])), Ts),
+    forall(member(T, Ts), thread_join(T, true)),
+    tally(N),
+    concurrent_forall(between(1, 25, K), (retract(visit(K)), retract(visit(K)))),
+    findall(V, visit(V), Vs),
+    length(Vs, Visits),
+    retractall(visit(_Any)),
+    findall(V, visit(V), Left).
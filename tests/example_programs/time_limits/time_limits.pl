@@ -0,0 +1,16 @@
+spin :-
+    between(1, inf, _X),
+    fail.
+
+limited(Fast, Slept, Spun) :-
+    call_with_time_limit(5, member(Fast, [a, b])),
+    catch(call_with_time_limit(0.2, sleep(10)), Slept, true),
+    catch(call_with_time_limit(0.2, spin), Spun, true).
+
+nested(E) :-
+    catch(call_with_time_limit(10, call_with_time_limit(0.1, spin)), E, true).
+
+outlived(X) :-
+    call_with_time_limit(0.1, true),
+    sleep(0.2),
+    X = done.
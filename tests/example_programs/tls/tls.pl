@@ -0,0 +1,41 @@
+hello(Request, reply(200, Body)) :-
+    member(path(Path), Request),
+    format(atom(Body), "secure ~w", [Path]).
+
+certificates([
+    certificate_file('tests/example_programs/tls/server.pem'),
+    key_file('tests/example_programs/tls/server.key')
+]).
+
+get(Port, Path, Options, Status-Body) :-
+    tls_connect(localhost:Port, In, Out, Options),
+    format(Out, "GET ~w HTTP/1.1\r\nHost: localhost\r\n\r\n", [Path]),
+    flush_output(Out),
+    read_line_to_string(In, Status),
+    skip_headers(In),
+    read_string(In, _Length, Body),
+    close(Out),
+    close(In).
+
+skip_headers(In) :-
+    read_line_to_string(In, Line),
+    ( Line == "" -> true ; skip_headers(In) ).
+
+serve_https(Response) :-
+    certificates(Certificates),
+    http_server(hello, [port(Port), ssl(Certificates)]),
+    get(Port, '/private', [cacert_file('tests/example_programs/tls/ca.pem')], Response),
+    http_stop_server(Port, []).
+
+untrusted(E) :-
+    certificates(Certificates),
+    http_server(hello, [port(Port), ssl(Certificates)]),
+    catch(get(Port, '/', [], _Response), error(E, _Context), true),
+    http_stop_server(Port, []).
+
+missing_key(E) :-
+    catch(
+        tls_listen(localhost:_Port, _Socket, [certificate_file('tests/example_programs/tls/server.pem'), key_file('missing.key')]),
+        error(E, _Context),
+        true
+    ).
@@ -0,0 +1,31 @@
+catalog(DOM) :-
+    load_xml('tests/example_programs/xml/catalog.xml', DOM, []).
+
+titles(Titles) :-
+    catalog([element(catalog, _Attrs, Books)]),
+    findall(T, member(element(book, _A, [element(title, [], [T]) | _Rest]), Books), Titles).
+
+page(DOM) :-
+    load_html('tests/example_programs/xml/page.html', DOM, []).
+
+from_stream(DOM) :-
+    process_create(path(cat), ['tests/example_programs/xml/catalog.xml'], [stdout(pipe(Out))]),
+    load_xml(stream(Out), [element(_Name, Attrs, _Children)], []),
+    close(Out),
+    DOM = Attrs.
+
+spaces(DOM) :-
+    load_xml(string("<a> <b>x</b> </a>"), DOM, [space(preserve)]).
+
+write_dom(Text) :-
+    xml_write(atom(Text), element(a, [href='x&y', title="say \"hi\""], [element(br, [], []), 'a < b', "c"]), [header(false)]).
+
+round_trip(Text) :-
+    catalog(DOM),
+    xml_write(atom(Text), DOM, []).
+
+bad(E) :-
+    catch(load_xml(string("<a><b></a>"), _DOM, []), error(E, _Context), true).
+
+unclosed(E) :-
+    catch(load_xml(string("<a><b></b>"), _DOM, []), error(E, _Context), true).
@@ -0,0 +1,83 @@
+#![cfg(feature = "ffi")]
+
+use bfg_prolog::ffi::*;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+fn c(s: &str) -> CString {
+    CString::new(s).unwrap()
+}
+
+unsafe fn text(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(s).to_str().unwrap().to_string())
+    }
+}
+
+unsafe fn binding(query: *const BfgQuery, name: &str) -> Option<String> {
+    let s = bfg_query_binding(query, c(name).as_ptr());
+    let binding = text(s);
+    bfg_string_free(s);
+    binding
+}
+
+#[test]
+fn test_ffi_1_succeeds() {
+    unsafe {
+        let machine = bfg_machine_new();
+        let source = c("parent(tom, bob). parent(tom, liz). parent(bob, ann).");
+        assert_eq!(bfg_consult(machine, source.as_ptr()), 0);
+
+        let query = bfg_query_open(machine, c("parent(tom, X), parent(X, Y).").as_ptr());
+        bfg_machine_free(machine);
+
+        let mut answers = Vec::new();
+        while bfg_query_next(query) == 1 {
+            answers.push((binding(query, "X"), binding(query, "Y")));
+        }
+
+        assert_eq!(
+            answers,
+            vec![(Some(String::from("bob")), Some(String::from("ann")))]
+        );
+        assert_eq!(binding(query, "X"), None);
+        bfg_query_close(query);
+    }
+}
+
+#[test]
+fn test_ffi_2_succeeds() {
+    unsafe {
+        let machine = bfg_machine_new();
+        let query = bfg_query_open(machine, c("X = f(Y, \"text\"), Y = [1, 2].").as_ptr());
+
+        assert_eq!(bfg_query_next(query), 1);
+        assert_eq!(binding(query, "Y"), Some(String::from("[1, 2]")));
+        assert_eq!(binding(query, "Z"), None);
+        assert_eq!(bfg_query_next(query), 0);
+
+        bfg_query_close(query);
+        bfg_machine_free(machine);
+    }
+}
+
+#[test]
+fn test_ffi_3_succeeds() {
+    unsafe {
+        let machine = bfg_machine_new();
+
+        assert_eq!(bfg_consult(machine, c("broken(.").as_ptr()), -1);
+        assert!(text(bfg_machine_error(machine)).is_some());
+        assert!(bfg_query_open(machine, c("X = ").as_ptr()).is_null());
+
+        let query = bfg_query_open(machine, c("throw(oops(1)).").as_ptr());
+        assert_eq!(text(bfg_machine_error(machine)), None);
+        assert_eq!(bfg_query_next(query), -1);
+        assert_eq!(text(bfg_query_error(query)), Some(String::from("oops(1)")));
+
+        bfg_query_close(query);
+        bfg_machine_free(machine);
+    }
+}
@@ -0,0 +1,72 @@
+use bfg_prolog::ast;
+use bfg_prolog::ast::Atom;
+use bfg_prolog::dcg;
+use bfg_prolog::loader::consult;
+use bfg_prolog::solve_quietly;
+use bfg_prolog::tokenizer;
+use lalrpop_util::lalrpop_mod;
+use std::fs::read_to_string;
+
+/// The tests in the file at `path`, one to a line, leaving out blank lines and comments.
+fn read_tests(path: &str) -> Vec<String> {
+    read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('%'))
+        .map(String::from)
+        .collect()
+}
+
+lalrpop_mod!(#[allow(clippy::all, unused_parens)] pub parser);
+
+/// The goal checking a test of the suite, with the substitutions it expects written with `=`, or
+/// None if the test cannot be read.
+fn conformance_goal(test: &str) -> Option<Atom> {
+    let query = format!(
+        "conforms({}).",
+        test.trim_end_matches('.').replace("<--", "=")
+    );
+    let mut goals = parser::ClauseParser::new()
+        .parse(tokenizer::lex(&query))
+        .ok()?;
+
+    goals.pop()
+}
+
+#[test]
+fn test_inria_suite_conforms() {
+    let driver = consult("tests/example_programs/iso/conformance.pl").unwrap();
+    let tests = read_tests("tests/example_programs/iso/inria_subset.pl");
+    let known = read_tests("tests/example_programs/iso/known_failures.pl");
+
+    let failing: Vec<&String> = tests
+        .iter()
+        .filter(|test| !conformance_goal(test).is_some_and(|goal| solve_quietly(&driver, goal)))
+        .collect();
+    let regressed: Vec<&String> = failing
+        .iter()
+        .copied()
+        .filter(|test| !known.contains(test))
+        .collect();
+    let fixed: Vec<&String> = known
+        .iter()
+        .filter(|test| !failing.contains(test))
+        .collect();
+
+    for test in &regressed {
+        println!("does not conform: {}", test);
+    }
+
+    for test in &fixed {
+        println!("conforms, so take it off known_failures.pl: {}", test);
+    }
+
+    println!(
+        "{} of {} tests conform",
+        tests.len() - failing.len(),
+        tests.len()
+    );
+
+    assert!(regressed.is_empty() && fixed.is_empty());
+}
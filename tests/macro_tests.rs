@@ -0,0 +1,162 @@
+#![cfg(feature = "macros")]
+
+use bfg_prolog::ast;
+use bfg_prolog::ast::{Atom, Term};
+use bfg_prolog::convert::{FromTerm, PrologTerm, TermError};
+use bfg_prolog::dcg;
+use bfg_prolog::tokenizer;
+use bfg_prolog::{prolog, solve_toplevel, KnowledgeBase};
+use lalrpop_util::lalrpop_mod;
+use std::convert::TryFrom;
+
+lalrpop_mod!(#[allow(clippy::all, unused_parens)] pub parser);
+
+fn parse_term(term: &str) -> Term {
+    let term_parser = parser::TermParser::new();
+    term_parser.parse(tokenizer::lex(term)).unwrap()
+}
+
+fn compare_answers(answers: Vec<String>, expected: &[&str]) {
+    let answers: Vec<&str> = answers.iter().map(|s| s.trim()).collect();
+    assert_eq!(answers, expected);
+}
+
+#[derive(PrologTerm, Debug, Clone, PartialEq)]
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+#[derive(PrologTerm, Debug, Clone, PartialEq)]
+enum Shape {
+    Circle {
+        center: Point,
+        radius: f64,
+    },
+    #[prolog(name = "poly")]
+    Polygon(Vec<Point>),
+    Empty,
+}
+
+#[derive(PrologTerm, Debug, Clone, PartialEq)]
+struct Tagged<T> {
+    label: String,
+    value: Option<T>,
+}
+
+#[test]
+fn test_derive_1_succeeds() {
+    let shapes = vec![
+        Shape::Circle {
+            center: Point { x: 1, y: 2 },
+            radius: 0.5,
+        },
+        Shape::Polygon(vec![Point { x: 0, y: 0 }, Point { x: 3, y: 4 }]),
+        Shape::Empty,
+    ];
+
+    let terms: Vec<Term> = shapes.iter().cloned().map(Term::from).collect();
+    let written: Vec<String> = terms.iter().map(|t| t.to_string()).collect();
+
+    assert_eq!(
+        written,
+        [
+            "circle(point(1, 2), 0.5)",
+            "poly([point(0, 0), point(3, 4)])",
+            "empty",
+        ]
+    );
+
+    let read: Vec<Shape> = terms
+        .into_iter()
+        .map(|t| Shape::try_from(t).unwrap())
+        .collect();
+
+    assert_eq!(read, shapes);
+}
+
+#[test]
+fn test_derive_2_succeeds() {
+    let tagged = Tagged {
+        label: String::from("origin"),
+        value: Some(Point { x: 0, y: 0 }),
+    };
+
+    let query = vec![Atom::new(
+        "=",
+        vec![
+            Term::from(tagged),
+            parse_term("tagged(L, some(point(X, Y)))"),
+        ],
+    )];
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["L = \"origin\"\nX = 0\nY = 0"]);
+}
+
+#[test]
+fn test_derive_3_succeeds() {
+    let wrong = parse_term("point(1, two)");
+
+    assert_eq!(
+        Point::from_term(&wrong),
+        Err(TermError {
+            expected: String::from("i64"),
+            found: parse_term("two"),
+        })
+    );
+
+    assert_eq!(
+        Shape::try_from(parse_term("square(1)"))
+            .unwrap_err()
+            .to_string(),
+        "expected shape, found square(1)"
+    );
+}
+
+fn family() -> KnowledgeBase {
+    prolog!(
+        r#"
+        parent(tom, bob).
+        parent(bob, 'Ann').
+        parent(bob, "pat").
+
+        ancestor(X, Y) :- parent(X, Y).
+        ancestor(X, Z) :- parent(X, Y), ancestor(Y, Z).
+
+        weight(1.25).
+        digits(`12`).
+        "#
+    )
+}
+
+#[test]
+fn test_prolog_macro_1_succeeds() {
+    let kb = family();
+    let query = vec![Atom::new(
+        "findall",
+        vec![
+            parse_term("D"),
+            parse_term("ancestor(tom, D)"),
+            parse_term("Ds"),
+        ],
+    )];
+
+    let results = solve_toplevel(false, &kb, query);
+
+    compare_answers(results, &["Ds = [bob, 'Ann', \"pat\"]"]);
+}
+
+#[test]
+fn test_prolog_macro_2_succeeds() {
+    let kb = family();
+    let query = vec![
+        Atom::new("weight", vec![parse_term("W")]),
+        Atom::new("digits", vec![parse_term("Cs")]),
+    ];
+
+    let results = solve_toplevel(false, &kb, query);
+
+    compare_answers(results, &["Cs = [49, 50]\nW = 1.25"]);
+}
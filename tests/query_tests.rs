@@ -1,24 +1,45 @@
 use bfg_prolog::ast;
-use bfg_prolog::ast::{Assertion, Clause};
-use bfg_prolog::solve_toplevel;
+use bfg_prolog::ast::{Assertion, Clause, Term};
+use bfg_prolog::dcg;
+use bfg_prolog::doc;
+use bfg_prolog::fastrw;
+use bfg_prolog::loader::{
+    consult, consult_text, consult_user, consults_user, expand_quasi_quotations,
+    initialization_goals, load_goals, make, read_program, singletons, source_location,
+    Initialization,
+};
+use bfg_prolog::strategy::SearchStrategy;
+use bfg_prolog::tokenizer;
+use bfg_prolog::{
+    argv, set_argv, set_search_strategy, set_user_output, solve_directive, solve_quietly,
+    solve_toplevel, solve_with_console, solve_with_options, Console, QueryHandle, QueryOptions,
+};
 use lalrpop_util::lalrpop_mod;
 use std::fs::read_to_string;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
 
-lalrpop_mod!(pub parser);
+lalrpop_mod!(#[allow(clippy::all, unused_parens)] pub parser);
 
 fn read_source_code(path: &str) -> Vec<Assertion> {
     let s = read_to_string(String::from(path)).unwrap();
-    parse_code(&s)
+    expand_quasi_quotations(parse_code(&s)).unwrap()
 }
 
 fn parse_code(code: &str) -> Vec<Assertion> {
     let code_parser = parser::CodeParser::new();
-    code_parser.parse(code).unwrap()
+    code_parser.parse(tokenizer::lex(code)).unwrap()
 }
 
 fn parse_query(query: &str) -> Clause {
     let clause_parser = parser::ClauseParser::new();
-    clause_parser.parse(query).unwrap()
+    clause_parser.parse(tokenizer::lex(query)).unwrap()
+}
+
+fn parse_term(term: &str) -> Term {
+    let term_parser = parser::TermParser::new();
+    term_parser.parse(tokenizer::lex(term)).unwrap()
 }
 
 fn compare_answers(answers: Vec<String>, expected: &[&str]) {
@@ -238,7 +259,7 @@ fn test_basic_16_succeeds() {
 #[test]
 fn test_basic_17_succeeds() {
     let source = read_source_code("tests/example_programs/basic/basic.pl");
-    let query = parse_query("unify(f(X), X).");
+    let query = parse_query("set_prolog_flag(occurs_check, true), unify(f(X), X).");
 
     let results = solve_toplevel(false, &source, query);
 
@@ -291,7 +312,10 @@ fn test_the_expanse_program_2_fails() {
 
     let results = solve_toplevel(false, &source, query);
 
-    compare_answers(results, &["No"])
+    compare_answers(
+        results,
+        &["Unhandled exception: error(existence_error(procedure, captain/1), context(captain/1, _1))"],
+    )
 }
 
 #[test]
@@ -339,3 +363,4101 @@ fn test_the_expanse_program_5_succeeds() {
         ],
     )
 }
+
+#[test]
+fn test_basic_18_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("product(X, Y, Z).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &[
+            "X = a\nY = c\nZ = e",
+            "X = a\nY = c\nZ = f",
+            "X = a\nY = d\nZ = e",
+            "X = a\nY = d\nZ = f",
+            "X = b\nY = c\nZ = e",
+            "X = b\nY = c\nZ = f",
+            "X = b\nY = d\nZ = e",
+            "X = b\nY = d\nZ = f",
+        ],
+    );
+}
+
+#[test]
+fn test_strings_1_succeeds() {
+    let query = parse_query("atom_string(A, \"hello world\"), atom_string(abc, S).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["A = 'hello world'\nS = \"abc\""]);
+}
+
+#[test]
+fn test_strings_2_succeeds() {
+    let query = parse_query("number_string(N, \" 42\"), number_string(-1.5, S).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["N = 42\nS = \"-1.5\""]);
+}
+
+#[test]
+fn test_strings_1_fails() {
+    let query = parse_query(
+        "catch(atom_string(_A, _S), error(E1, _), true), catch(atom_string(f(x), _T), error(E2, _), true).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["E1 = instantiation_error\nE2 = type_error(atom, f(x))"],
+    );
+}
+
+#[test]
+fn test_strings_2_fails() {
+    let query = parse_query(
+        "catch(number_string(_N, \"forty-two\"), error(E1, _), true), catch(number_string(_M, _S), error(E2, _), true).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["E1 = syntax_error(illegal_number)\nE2 = instantiation_error"],
+    );
+}
+
+#[test]
+fn test_strings_3_succeeds() {
+    let query = parse_query("term_string(\"foo(X, 'Bar', \\\"baz\\\")\", T).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["T = foo(X1, 'Bar', \"baz\")"]);
+}
+
+#[test]
+fn test_strings_3_fails() {
+    let query = parse_query("catch(term_string(\"foo(\", _T), error(E, _), true).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["E = syntax_error(illegal_term)"]);
+}
+
+#[test]
+fn test_strings_4_succeeds() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query = parse_query("greeting(world, G).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["G = \"hello(\\\"world\\\")\""]);
+}
+
+#[test]
+fn test_strings_5_succeeds() {
+    let query = parse_query(
+        "atom_string(abc, S), string(S), \\+ string(abc), \\+ string(_X), \\+ atom(S).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["S = \"abc\""]);
+}
+
+#[test]
+fn test_numbers_1_succeeds() {
+    use ast::Number::{Float, Int};
+
+    assert!(Float(1.5) < Int(2));
+    assert!(Int(2) < Float(2.5));
+    assert!(Float(1.0) < Int(1));
+
+    let query = parse_query("compare(O1, 1.5, 2), compare(O2, 1.0, 1), compare(O3, 3, 2.5).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["O1 = <\nO2 = <\nO3 = >"]);
+}
+
+#[test]
+fn test_numbers_1_fails() {
+    assert!(parser::TermParser::new()
+        .parse(tokenizer::lex("99999999999999999999"))
+        .is_err());
+    assert!(parser::TermParser::new()
+        .parse(tokenizer::lex("1.0e999"))
+        .is_err());
+}
+
+#[test]
+fn test_char_type_1_succeeds() {
+    let query = parse_query("char_type(a, alpha), code_type(55, digit(W)), char_type('_', csym).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["W = 7"]);
+}
+
+#[test]
+fn test_char_type_1_fails() {
+    let query = parse_query("char_type('-', alnum).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_char_type_2_succeeds() {
+    let query = parse_query("char_type('é', to_upper(U)), char_type('Ж', upper(L)).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["L = ж\nU = 'É'"]);
+}
+
+#[test]
+fn test_char_type_3_succeeds() {
+    let query = parse_query("char_type(X, to_lower(a)).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["X = a", "X = 'A'"]);
+}
+
+#[test]
+fn test_char_type_4_succeeds() {
+    let query = parse_query("char_type('Ω', T).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &[
+            "T = alnum",
+            "T = alpha",
+            "T = csym",
+            "T = csymf",
+            "T = graph",
+            "T = print",
+            "T = upper",
+            "T = to_lower(ω)",
+            "T = to_upper('Ω')",
+            "T = upper(ω)",
+            "T = code(937)",
+        ],
+    );
+}
+
+#[test]
+fn test_unicode_1_succeeds() {
+    let query =
+        parse_query("atom_length(日本語, L), atom_length('😀👍', E), string_length(\"né\", S).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["E = 2\nL = 3\nS = 2"]);
+}
+
+#[test]
+fn test_unicode_2_succeeds() {
+    let query = parse_query("atom_concat(X, Y, 'é😀').");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["X = ''\nY = 'é😀'", "X = é\nY = '😀'", "X = 'é😀'\nY = ''"],
+    );
+}
+
+#[test]
+fn test_unicode_3_succeeds() {
+    let query = parse_query("atom_concat(日本, 語, A), atom_concat(straße, x, Ärger).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["A = 日本語\nÄrger = straßex"]);
+}
+
+#[test]
+fn test_unicode_4_succeeds() {
+    let query = parse_query(
+        "string_concat(\"🦀\", X, \"🦀rust\"), term_string(S, f('Ärger', straße, '🦀')).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["S = \"f('Ärger', straße, '🦀')\"\nX = \"rust\""]);
+}
+
+#[test]
+fn test_codes_1_succeeds() {
+    let query = parse_query("atom_codes(hello, L), atom_codes(A, `héllo`), atom_chars(B, [o, k]).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["A = héllo\nB = ok\nL = [104, 101, 108, 108, 111]"],
+    );
+}
+
+#[test]
+fn test_codes_2_succeeds() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query = parse_query("app(X, `c`, `abc`), unify(`ab`, [A|T]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["A = 97\nT = [98]\nX = [97, 98]"]);
+}
+
+#[test]
+fn test_codes_3_fails() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query = parse_query("unify(`abc`, [98|T]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_codes_4_succeeds() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query =
+        parse_query("app(`ab`, T, L), unify(L, `abcd`), string_codes(S, T), codes_length(L, N).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["L = [97, 98, 99, 100]\nN = s(s(s(s(0))))\nS = \"cd\"\nT = [99, 100]"],
+    );
+}
+
+#[test]
+fn test_codes_5_succeeds() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query = parse_query("unify([A, B|T], `xyz`), number_codes(N, `42`), char_code(C, 65).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["A = 120\nB = 121\nC = 'A'\nN = 42\nT = [122]"]);
+}
+
+#[test]
+#[cfg(feature = "re")]
+fn test_re_1_succeeds() {
+    let query = parse_query("re_match(\"^[a-z]+$\", hello), re_match('\\\\d', \"r2d2\").");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["Yes"]);
+}
+
+#[test]
+#[cfg(feature = "re")]
+fn test_re_1_fails() {
+    let query = parse_query("re_match(\"[0-9]\", \"abc\").");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+#[cfg(feature = "re")]
+fn test_re_2_fails() {
+    let query = parse_query(
+        "catch(re_match(_P, \"abc\"), error(E1, _), true), catch(re_match(\"(\", \"abc\", _G), error(E2, _), true).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["E1 = instantiation_error\nE2 = syntax_error(illegal_regex)"],
+    );
+}
+
+#[test]
+#[cfg(feature = "re")]
+fn test_re_2_succeeds() {
+    let query =
+        parse_query("re_match(\"(\\\\w+)@(\\\\w+)\\\\.com\", \"mail bob@example.com\", C).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["C = [\"bob@example.com\", \"bob\", \"example\"]"],
+    );
+}
+
+#[test]
+#[cfg(feature = "re")]
+fn test_re_3_succeeds() {
+    let query = parse_query(
+        "re_replace(\"o\", \"0\", \"foo boo\", R), re_replace(\"(\\\\w+) (\\\\w+)\", \"$2 $1\", \"hello world\", S).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["R = \"f0o boo\"\nS = \"world hello\""]);
+}
+
+#[test]
+#[cfg(feature = "re")]
+fn test_re_4_succeeds() {
+    let query = parse_query(
+        "forall(between(1, 200, N), (format(atom(P), \"^x~d$\", [N]), format(atom(S), \"x~d\", [N]), re_match(P, S))), \
+         re_match(\"^x1$\", x1).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["Yes"]);
+}
+
+#[test]
+#[cfg(feature = "re")]
+fn test_re_5_succeeds() {
+    let query =
+        parse_query("re_match(\"(?P<y>\\\\d+)-(\\\\d+)\", \"on 2024-05\", C), member(y=Y, C).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["C = [\"2024-05\", y = \"2024\", \"05\"]\nY = \"2024\""],
+    );
+}
+
+#[test]
+fn test_codecs_1_succeeds() {
+    let query = parse_query("base64(hello, E), base64(P, 'w7xiZXI='), base64(\"ab\", F).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["E = 'aGVsbG8='\nF = 'YWI='\nP = über"]);
+}
+
+#[test]
+fn test_codecs_1_fails() {
+    let query = parse_query("base64(P, 'not base64!').");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_codecs_2_succeeds() {
+    let query = parse_query("hex_bytes('CAFE01', B), hex_bytes(H, [222, 173, 190, 239]).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["B = [202, 254, 1]\nH = deadbeef"]);
+}
+
+#[test]
+fn test_codecs_3_succeeds() {
+    let query = parse_query(
+        "uri_encoded(query_value, 'a b&c=ü', Q), uri_encoded(path, '/a b/c', P), uri_encoded(segment, S, 'x%2Fy%20z').",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["P = '/a%20b/c'\nQ = 'a%20b%26c%3D%C3%BC'\nS = 'x/y z'"],
+    );
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn test_crypto_1_succeeds() {
+    let query = parse_query(
+        "crypto_data_hash(abc, H, [algorithm(A)]), crypto_data_hash(\"abc\", M, [algorithm(md5)]).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["A = sha256\nH = ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\nM = '900150983cd24fb0d6963f7d28e17f72'"],
+    );
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn test_crypto_2_succeeds() {
+    let query = parse_query(
+        "crypto_data_hash([97, 98, 99], S, [algorithm(sha1)]), crypto_data_hash(`abc`, C, [algorithm(crc32)]).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["C = '352441c2'\nS = a9993e364706816aba3e25717850c26c9cd0d89d"],
+    );
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn test_crypto_1_fails() {
+    let query = parse_query("crypto_data_hash(abc, H, [algorithm(rot13)]).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_format_1_succeeds() {
+    let query = parse_query(
+        "format(string(E), \"~e ~3e\", [1.5, 12345.678]), format(string(G), \"~g ~g ~g\", [0.0001, 100000.0, 1000000.0]).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["E = \"1.500000e+00 1.235e+04\"\nG = \"0.0001 100000 1e+06\""],
+    );
+}
+
+#[test]
+fn test_format_2_succeeds() {
+    let query = parse_query(
+        "format(atom(R), \"~8r ~16R ~2r\", [255, 255, -5]), format(atom(C), \"~3c~c\", [120, 121]), format(atom(D), \"~2d ~D\", [1234, 1234567]).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["C = xxxy\nD = '12.34 1,234,567'\nR = '377 FF -101'"],
+    );
+}
+
+#[test]
+fn test_format_3_succeeds() {
+    let query = parse_query(
+        "format(string(L), \"~w~t~10|~w\", [abc, def]), format(string(R), \"~t~w~6|\", [42]), format(string(C), \"[~t~w~t~9|]\", [abc]).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["C = \"[  abc   ]\"\nL = \"abc       def\"\nR = \"    42\""],
+    );
+}
+
+#[test]
+fn test_format_4_succeeds() {
+    let query =
+        parse_query("format(string(S), \"~w~t~8+~w~t~8+~w~n~`-t~12|\", [a, 'B c', \"d\"]).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["S = \"a       B c     d\\n------------\""]);
+}
+
+#[test]
+fn test_format_5_succeeds() {
+    let query = parse_query(
+        "I is inf, N is nan, format(atom(A), \"~e ~g ~e\", [I, I, N]), \
+         findall(E, catch(format(atom(_B), \"~*c\", [-1, 120]), error(E, _C), true), Es).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["A = 'inf inf NaN'\nEs = [domain_error(not_less_than_zero, -1)]\nI = inf\nN = NaN"],
+    );
+}
+
+#[test]
+fn test_format_4_fails() {
+    let query = parse_query("format(atom(A), \"~w ~w\", [only_one]).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_dcg_1_succeeds() {
+    let source = read_source_code("tests/example_programs/grammar/grammar.pl");
+    let query = parse_query("phrase(expr(E), ['1', '+', '2', '*', '3']).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = plus(1, times(2, 3))"]);
+}
+
+#[test]
+fn test_dcg_2_succeeds() {
+    let source = read_source_code("tests/example_programs/grammar/grammar.pl");
+    let query = parse_query("phrase(expr(E), ['(', '1', '-', '2', ')', '*', '0'], R).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &[
+            "E = times(minus(1, 2), 0)\nR = []",
+            "E = minus(1, 2)\nR = [*, '0']",
+        ],
+    );
+}
+
+#[test]
+fn test_dcg_3_succeeds() {
+    let source = read_source_code("tests/example_programs/grammar/grammar.pl");
+    let query = parse_query("phrase(greeting, [hello, X]), phrase(abc, `abc`).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = world", "X = prolog"]);
+}
+
+#[test]
+fn test_dcg_4_succeeds() {
+    let source = read_source_code("tests/example_programs/grammar/grammar.pl");
+    let query = parse_query(
+        "phrase(number(N), ['2']), phrase(twice(digit(X)), ['3', '3']), phrase(word(W), [b]).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["N = d(2)\nW = [b]\nX = 3"]);
+}
+
+#[test]
+fn test_dcg_5_succeeds() {
+    let source = read_source_code("tests/example_programs/grammar/grammar.pl");
+    let query = parse_query("phrase(peek(C), [a, b], R), phrase(hello, `hello`).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["C = a\nR = [a, b]"]);
+}
+
+#[test]
+fn test_dcg_basics_1_succeeds() {
+    let query = parse_query(
+        "phrase(integer(I), `-42`), phrase(digits(Ds), `12ab`, R), phrase(blanks, `  `, []).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results[..1].to_vec(),
+        &["Ds = [49, 50]\nI = -42\nR = [97, 98]"],
+    );
+}
+
+#[test]
+fn test_dcg_basics_2_succeeds() {
+    let query = parse_query(
+        "phrase(string_without(`,`, S), `ab,c`, R), atom_codes(A, S), phrase(eos, []).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results[..1].to_vec(),
+        &["A = ab\nR = [44, 99]\nS = [97, 98]"],
+    );
+}
+
+#[test]
+fn test_dcg_2_fails() {
+    let query = parse_query(
+        "catch(phrase(_G, [a]), error(E1, _), true), catch(phrase(1, [a], _R), error(E2, _), true).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["E1 = instantiation_error\nE2 = type_error(callable, 1)"],
+    );
+}
+
+#[test]
+fn test_dcg_basics_1_fails() {
+    let query = parse_query("phrase(integer(I), `4x`).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_not_1_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("not(unify(a, b)), memberchk(X, [c, d]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = c"]);
+}
+
+#[test]
+fn test_dcg_6_succeeds() {
+    let source = read_source_code("tests/example_programs/grammar/grammar.pl");
+    let query = parse_query(
+        "phrase_from_file(lines(Ls), 'tests/example_programs/grammar/lines.txt'), first_last(Ls, F, L), atom_codes(First, F), atom_codes(Last, L).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    assert!(results[0].contains("First = 'line 1 — ünïcödé'\n"));
+    assert!(results[0].contains("Last = 'line 12 — ünïcödé'\n"));
+}
+
+#[test]
+fn test_dcg_7_succeeds() {
+    let source = read_source_code("tests/example_programs/grammar/grammar.pl");
+    let query = parse_query(
+        "phrase_from_file(first_line(_L, Rest), 'tests/example_programs/grammar/lines.txt'), length(Rest, N), Rest = [C|_T], atom_codes(A, [C]).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    assert!(results[0].contains("A = l\n"), "{}", results[0]);
+    assert!(!results[0].contains("$lazy_codes"), "{}", results[0]);
+}
+
+#[test]
+fn test_dcg_7_fails() {
+    let query = parse_query(
+        "catch(phrase_from_file(string(_S), 'tests/example_programs/grammar/missing.txt'), error(E, _C), true).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    assert!(results[0].contains(
+        "E = existence_error(source_sink, 'tests/example_programs/grammar/missing.txt')"
+    ));
+}
+
+#[test]
+fn test_dcg_1_fails() {
+    let source = read_source_code("tests/example_programs/grammar/grammar.pl");
+    let query = parse_query("phrase(expr(E), ['1', '+']).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_tokens_1_succeeds() {
+    let query = parse_query("prolog_tokens(\"p(X) :- q('a b', 12).\", T).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["T = [atom(p, 1, 1), punct('(', 1, 2), var('X', 1, 3), punct(')', 1, 4), atom(:-, 1, 6), atom(q, 1, 9), punct('(', 1, 10), quoted_atom('a b', 1, 11), punct(',', 1, 16), int(12, 1, 18), punct(')', 1, 20), end('.', 1, 21)]"],
+    );
+}
+
+#[test]
+fn test_tokens_2_succeeds() {
+    let source =
+        parse_code("% codes\nletters([0'a, 0'\\n, 0''', 0' ]). /* _ */\nfirst([X|_], X).\n");
+    let query =
+        parse_query("letters(L), first(L, F), first([_, _], _), prolog_tokens(\"0'a _\", T).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["F = 97\nL = [97, 10, 39, 32]\nT = [int(97, 1, 1), var('_', 1, 5)]"],
+    );
+}
+
+#[test]
+fn test_tokens_1_fails() {
+    let query = parse_query(
+        "catch(prolog_tokens(_, _), error(E1, _), true), \
+         catch(prolog_tokens(f(x), _), error(E2, _), true), \
+         catch(prolog_tokens(\"'abc\", _), error(E3, _), true).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["E1 = instantiation_error\nE2 = type_error(string, f(x))\nE3 = syntax_error(end_of_file)"],
+    );
+}
+
+#[test]
+fn test_quasi_quotations_1_succeeds() {
+    let source = read_source_code("tests/example_programs/quasi_quotations/quasi_quotations.pl");
+    let query = parse_query("row(R), users(Q).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["Q = query(users, \"SELECT name FROM users\")\nR = [a, b, c]"],
+    );
+}
+
+#[test]
+fn test_dif_1_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("dif(X, a), unify(X, b).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = b"]);
+}
+
+#[test]
+fn test_dif_2_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("dif(f(X, Y), f(a, b)), unify(X, a), dif(Z, W).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = a\ndif(Z, W)\ndif(f(a, Y), f(a, b))"]);
+}
+
+#[test]
+fn test_dif_3_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("dif(X, b), member(X, list(a, list(b, list(c, nil)))).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = a", "X = c"]);
+}
+
+#[test]
+fn test_dif_1_fails() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("dif(X, Y), unify(X, Z), unify(Y, Z).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_dif_2_fails() {
+    let query = parse_query("dif(f(X), f(X)).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_freeze_1_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("freeze(X, unify(Y, done)), unify(Y, Z), unify(X, go).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = go\nY = done\nZ = done"]);
+}
+
+#[test]
+fn test_freeze_2_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("freeze(X, unify(Y, a)), unify(X, W), freeze(W, unify(Z, b)).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["W = X\nfreeze(X, unify(Y, a))\nfreeze(X, unify(Z, b))"],
+    );
+}
+
+#[test]
+fn test_freeze_1_fails() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("freeze(X, unify(X, b)), unify(X, a).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_when_1_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query(
+        "when(ground(f(X, Y)), unify(Z, both)), unify(X, a), when(nonvar(W), unify(V, w)).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["X = a\nwhen(nonvar(W), unify(V, w))\nwhen(ground(f(a, Y)), unify(Z, both))"],
+    );
+}
+
+#[test]
+fn test_when_2_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("when(ground(f(X, Y)), unify(Z, both)), unify(X, a), unify(Y, b).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = a\nY = b\nZ = both"]);
+}
+
+#[test]
+fn test_attributes_1_succeeds() {
+    let source = read_source_code("tests/example_programs/attributes/attributes.pl");
+    let query = parse_query("domain(X, [a, b]), get_attr(X, domain, D), unify(X, b).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["D = [a, b]\nX = b"]);
+}
+
+#[test]
+fn test_attributes_2_succeeds() {
+    let source = read_source_code("tests/example_programs/attributes/attributes.pl");
+    let query = parse_query(
+        "domain(X, [a, b]), unify(X, Y), get_attr(Y, domain, D), del_attr(Y, domain), unify(Y, c).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["D = [a, b]\nX = c\nY = c"]);
+}
+
+#[test]
+fn test_attributes_3_succeeds() {
+    let source = read_source_code("tests/example_programs/attributes/attributes.pl");
+    let query = parse_query("domain(X, [a, b]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = X1\nput_attr(X1, domain, [a, b])"]);
+}
+
+#[test]
+fn test_attributes_4_succeeds() {
+    let source = read_source_code("tests/example_programs/attributes/propagation.pl");
+    let query = parse_query(
+        "watch(X, [note(late)]), propagate(high, [bind(X, 1, first), note(second), note(second)]), findall(N, noted(N), Ns).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Ns = [first, second, late]\nX = 1"]);
+}
+
+#[test]
+fn test_copy_term_1_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query(
+        "freeze(X, unify(Y, a)), copy_term(f(X, Y), f(A, B), Gs), Gs = [freeze(V, G)], A == V, unify(A, b).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = b\nB = Y2\nG = unify(Y2, a)\nGs = [freeze(b, unify(Y2, a))]\nV = b\nfreeze(X, unify(Y, a))"],
+    );
+}
+
+#[test]
+fn test_copy_term_2_succeeds() {
+    let source = read_source_code("tests/example_programs/basic/basic.pl");
+    let query = parse_query("X in 1..3, dif(X, Y), copy_term(X, C, Gs), C = 2, Gs = [G1, G2].");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["C = 2\nG1 = (2 in 1..3)\nG2 = dif(2, Y2)\nGs = [2 in 1..3, dif(2, Y2)]\nX in 1..3\ndif(X, Y)"],
+    );
+}
+
+#[test]
+fn test_attributes_1_fails() {
+    let source = read_source_code("tests/example_programs/attributes/attributes.pl");
+    let query = parse_query("domain(X, [a, b]), unify(X, c).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_operators_1_succeeds() {
+    let query = parse_query(
+        "1 + 2 * 3 - 4 = '-'('+'(1, '*'(2, 3)), 4), (a :- b, c ; d) = ':-'(a, ';'(','(b, c), d)).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["Yes"]);
+}
+
+#[test]
+fn test_operators_1_fails() {
+    let query = parse_query("1 - 2 - 3 = '-'(1, '-'(2, 3)).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_cut_1_succeeds() {
+    let source = parse_code("color(red).\ncolor(green).\ncolor(blue).\nfirst(X) :- color(X), !.\n");
+    let query = parse_query("first(X), ( color(Y), \\+ Y = red -> Z = Y ; Z = none ).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = red\nY = green\nZ = green"]);
+}
+
+#[test]
+fn test_cut_2_succeeds() {
+    let source = parse_code("color(red).\ncolor(green).\ncolor(blue).\n");
+    let query = parse_query("call(color, X), \\+ X = red, !.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = green"]);
+}
+
+#[test]
+fn test_findall_1_succeeds() {
+    let source = parse_code("color(red).\ncolor(green).\ncolor(blue).\n");
+    let query =
+        parse_query("findall(X, (color(X), \\+ X = red), L), forall(color(C), \\+ C = white).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["L = [green, blue]"]);
+}
+
+#[test]
+fn test_findall_1_fails() {
+    let source = parse_code("color(red).\ncolor(green).\ncolor(blue).\n");
+    let query = parse_query("forall(color(C), \\+ C = blue).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_is_1_succeeds() {
+    let query =
+        parse_query("between(1, 3, X), X * X > 3, succ(P, X), plus(P, Q, 10), R is Q mod 4.");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["P = 1\nQ = 9\nR = 1\nX = 2", "P = 2\nQ = 8\nR = 0\nX = 3"],
+    );
+}
+
+#[test]
+fn test_terms_1_succeeds() {
+    let query = parse_query(
+        "functor(T, point, 2), arg(1, T, a), arg(2, T, c), T =.. [F|Args], copy_term(T, point(A, C)), \
+         compare('>', T, point(a, b)), atom(F), is_list(Args), a @< b, T \\== point(a, b).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["A = a\nArgs = [a, c]\nC = c\nF = point\nT = point(a, c)"],
+    );
+}
+
+#[test]
+fn test_dcg_control_1_succeeds() {
+    let source = parse_code(
+        "as --> [a], !, as.\nas --> [].\nsign(S) --> ( [m] -> { S = neg } ; { S = pos } ).\n\
+         not_b --> \\+ [b].\n",
+    );
+    let query = parse_query(
+        "phrase(as, [a, a]), phrase(sign(S), [m]), phrase(sign(T), []), phrase(not_b, []).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["S = neg\nT = pos"]);
+}
+
+#[test]
+fn test_arithmetic_1_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("X is 7 / 2, Y is -7 // 2, Z is -7 mod 2, W is 2 ** 10.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["W = 1024\nX = 3.5\nY = -3\nZ = 1"]);
+}
+
+#[test]
+fn test_arithmetic_2_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("count(1, X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = 1", "X = 2", "X = 3"]);
+}
+
+#[test]
+fn test_arithmetic_3_succeeds() {
+    let query = parse_query(
+        "A is 2 ** (-1), B is 2 ** 3.0, C is 0.0 ** 0, D is 2 ** 3, \
+         E is 0 ^ 0, F is 3 ^ 1.0, G is 3 ^ 27, H is 1 ^ (-1), I is (-1) ^ (-1), \
+         set_prolog_flag(iso, true), J is 2 ** 3.",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["A = 0.5\nB = 8.0\nC = 1.0\nD = 8\nE = 1\nF = 3.0\nG = 7625597484987\nH = 1\nI = -1\nJ = 8.0"],
+    );
+}
+
+#[test]
+fn test_control_1_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("findall(X, (between(1, 5, X), X mod 2 =:= 1), L), first(F, L).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["F = 1\nL = [1, 3, 5]"]);
+}
+
+#[test]
+fn test_control_2_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("( between(1, 3, X), X > 1 -> Y = X ; Y = none ), \\+ Y == 3.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = 2\nY = 2"]);
+}
+
+#[test]
+fn test_clpfd_1_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("steps(L), label(L).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["L = [8, 7, 6]"]);
+}
+
+#[test]
+fn test_clpfd_2_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("factors(12, X, Y).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = 1\nY = 12", "X = 2\nY = 6", "X = 3\nY = 4"]);
+}
+
+#[test]
+fn test_clpfd_3_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("X in 1..10, X #> 7.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X in 8..10\n8 #=< X"]);
+}
+
+#[test]
+fn test_clpfd_4_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query(
+        "Y in 2..3, Y #= abs(X), X in 0..20, max(X, 1) #= Z, X mod 3 #= 0, X // 2 #= W, label([X]), \
+         V #= min(-7 mod 2, 7 // -2).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["V = -3\nW = 1\nX = 3\nY = 3\nZ = 3"]);
+}
+
+#[test]
+fn test_clpfd_5_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query(
+        "catch(X #= foo(1), E1, true), catch(X #= a + 1, E2, true), catch(X #> 1.5, E3, true).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &[
+            "E1 = error(type_error(evaluable, foo/1), context((#=)/2, _2))\n\
+           E2 = error(type_error(evaluable, a/0), context((#=)/2, _4))\n\
+           E3 = error(type_error(evaluable, 1.5), context((#>)/2, _6))",
+        ],
+    );
+}
+
+#[test]
+fn test_clpfd_1_fails() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("[X, Y] ins 0..3, X #= Y + 4.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_clpfd_globals_1_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("queens([A, B, C, D]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = 2\nB = 4\nC = 1\nD = 3", "A = 3\nB = 1\nC = 4\nD = 2"],
+    );
+}
+
+#[test]
+fn test_clpfd_globals_5_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("solutions(8, N, F).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["F = [1, 5, 8, 6, 3, 7, 2, 4]\nN = 92"]);
+}
+
+#[test]
+fn test_clpfd_globals_2_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("sudoku([1, B, C, D, E, F, 3, H, I, 4, K, L, M, N, O, 2]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["B = 3\nC = 2\nD = 4\nE = 4\nF = 2\nH = 1\nI = 2\nK = 1\nL = 3\nM = 3\nN = 1\nO = 4"],
+    );
+}
+
+#[test]
+fn test_clpfd_globals_3_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query(
+        "sum([X, Y], #=, 5), [X, Y] ins 1..4, global_cardinality([X, Y], [1-C, 4-1, 2-0, 3-0]), label([X, Y]).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["C = 1\nX = 1\nY = 4", "C = 1\nX = 4\nY = 1"]);
+}
+
+#[test]
+fn test_clpfd_globals_4_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("element(I, [3, 5, 7], V), V #> 6.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["I = 3\nV = 7"]);
+}
+
+#[test]
+fn test_clpfd_globals_1_fails() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("[X, Y, Z] ins 1..2, all_distinct([X, Y, Z]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_clpfd_reified_1_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("X in 0..5, B #<==> (X #> 3), X = 4.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["B = 1\nX = 4"]);
+}
+
+#[test]
+fn test_clpfd_reified_2_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("X in 0..9, (X #< 2) #\\/ (X #> 7), X #> 3, X #\\= 9.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = 8"]);
+}
+
+#[test]
+fn test_clpfd_reified_1_fails() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("[X, Y] ins 0..3, (X #= 3) #==> (Y #= 0), count_over([X, Y], 2, 2).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_clpfd_reified_3_succeeds() {
+    let source = read_source_code("tests/example_programs/clpfd/clpfd.pl");
+    let query = parse_query("L = [X, Y, Z], L ins 1..3, count_over(L, 2, 1), X #< Y, Y #< Z.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["L = [1, 2, 3]\nX = 1\nY = 2\nZ = 3"]);
+}
+
+#[test]
+fn test_clpqr_1_succeeds() {
+    let source = read_source_code("tests/example_programs/clpqr/clpqr.pl");
+    let query = parse_query("mortgage(100, 2, 10, B, 50).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["B = 16"]);
+}
+
+#[test]
+fn test_clpqr_2_succeeds() {
+    let source = read_source_code("tests/example_programs/clpqr/clpqr.pl");
+    let query = parse_query("production(X, Y, Profit).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Profit = 13\nX = 3\nY = 1"]);
+}
+
+#[test]
+fn test_clpqr_3_succeeds() {
+    let source = read_source_code("tests/example_programs/clpqr/clpqr.pl");
+    let query = parse_query("{X >= 1, X >= 2, X =< 5}, {X + Y = 10}, Y = Z.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Z = Y\n{X+Y = 10}\n{X >= 2}\n{X =< 5}"]);
+}
+
+#[test]
+fn test_clpqr_1_fails() {
+    let source = read_source_code("tests/example_programs/clpqr/clpqr.pl");
+    let query = parse_query("{X > Y}, {Y >= X}.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_tabling_1_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/tabling.pl");
+    let query = parse_query("path(a, X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = b", "X = c", "X = a", "X = d"]);
+}
+
+#[test]
+fn test_tabling_2_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/tabling.pl");
+    let query = parse_query("findall(N, odd(N), Ns).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Ns = [1, 3, 5, 7, 9]"]);
+}
+
+#[test]
+fn test_tabling_3_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/tabling.pl");
+    let query = parse_query("fib(60, F).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["F = 1548008755920"]);
+}
+
+#[test]
+fn test_tabling_incremental_1_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/incremental.pl");
+    let query = parse_query(
+        "findall(Y, reach(a, Y), L1), findall(Y, beyond(Y), B1), assertz(link(a, b)), \
+         findall(Y, reach(a, Y), L2), assertz(link(b, c)), findall(Y, reach(a, Y), L3), \
+         findall(Y, beyond(Y), B3), retract(link(a, b)), findall(Y, reach(a, Y), L4), \
+         findall(Y, beyond(Y), B4).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["B1 = []\nB3 = [c]\nB4 = []\nL1 = []\nL2 = [b]\nL3 = [b, c]\nL4 = []"],
+    );
+}
+
+#[test]
+fn test_tabling_incremental_2_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/incremental.pl");
+    let query =
+        parse_query("assertz(item(a)), findall(X, drained(X), L1), findall(X, drained(X), L2).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["L1 = [a, b]\nL2 = []"]);
+}
+
+#[test]
+fn test_tabling_incremental_3_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/dynamic.pl");
+    let query = parse_query(
+        "findall(Y, path(a, Y), L1), assertz(edge(b, c)), findall(Y, path(a, Y), L2), \
+         retract(edge(a, b)), findall(Y, path(a, Y), L3).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["L1 = [b]\nL2 = [b, c]\nL3 = []"]);
+}
+
+#[test]
+fn test_dynamic_1_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/dynamic.pl");
+    let query = parse_query(
+        "dynamic(seen/1), findall(X, seen(X), L1), assertz(seen(a)), asserta(seen(b)), \
+         findall(X, seen(X), L2).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["L1 = []\nL2 = [b, a]"]);
+}
+
+#[test]
+fn test_tabling_1_fails() {
+    let source = read_source_code("tests/example_programs/tabling/tabling.pl");
+    let query = parse_query("path(d, X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_tabling_4_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/shortest.pl");
+    let query = parse_query("path(a, Y, C).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &[
+            "C = 3\nY = b",
+            "C = 1\nY = c",
+            "C = 4\nY = d",
+            "C = 7\nY = a",
+        ],
+    );
+}
+
+#[test]
+fn test_tabling_5_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/shortest.pl");
+    let query = parse_query("widest(a, d, W), route(a, d, P).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["P = [a, b, d]\nW = 1"]);
+}
+
+#[test]
+fn test_tabling_2_fails() {
+    let source = read_source_code("tests/example_programs/tabling/shortest.pl");
+    let query = parse_query("path(a, d, 5).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_loop_check_1_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/loop_check.pl");
+    let query = parse_query("ancestor(ann, Y).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Y = bob"]);
+}
+
+#[test]
+fn test_loop_check_2_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/loop_check.pl");
+    let query = parse_query("connected(b, a).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Yes"]);
+}
+
+#[test]
+fn test_loop_check_1_fails() {
+    let source = read_source_code("tests/example_programs/tabling/loop_check.pl");
+    let query = parse_query("connected(a, c).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_memo_1_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/memo.pl");
+    let query = parse_query("fib(50, F).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["F = 12586269025"]);
+}
+
+#[test]
+fn test_memo_2_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/memo.pl");
+    let query = parse_query("memo(colour(C)).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["C = red"]);
+}
+
+#[test]
+fn test_memo_3_succeeds() {
+    let source = read_source_code("tests/example_programs/tabling/memo.pl");
+    let query = parse_query(
+        "memo(colour(red)), memo(colour(blue)) ; findall(A, '$memo'(_, A), As), \
+         abolish_all_tables, findall(A, '$memo'(_, A), Bs).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["As = [some(colour(red)), none]\nBs = []"]);
+}
+
+#[test]
+fn test_memo_1_fails() {
+    let source = read_source_code("tests/example_programs/tabling/memo.pl");
+    let query = parse_query("memo(colour(blue)).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_meta_predicate_1_succeeds() {
+    let source = read_source_code("tests/example_programs/modules/meta.pl");
+    let query = parse_query("goal_of(count(a, N), G).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["G = user:count(a, N)"]);
+}
+
+#[test]
+fn test_meta_predicate_2_succeeds() {
+    let source = read_source_code("tests/example_programs/modules/meta.pl");
+    let query = parse_query("apply_to(count(b), X), call(user:count, a, Y).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = 2\nY = 1"]);
+}
+
+#[test]
+fn test_meta_predicate_1_fails() {
+    let source = read_source_code("tests/example_programs/modules/meta.pl");
+    let query = parse_query("apply_to(user:count(c), X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_use_module_1_succeeds() {
+    let source = consult("tests/example_programs/loading/main.pl").unwrap();
+    let query = parse_query("greeting(G), colour(C).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["C = red\nG = 'hello!'", "C = green\nG = 'hello!'"],
+    );
+}
+
+#[test]
+fn test_use_module_2_succeeds() {
+    let source = consult("tests/example_programs/loading/main.pl").unwrap();
+    let query = parse_query("phrase(integer(X), `42`).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = 42"]);
+}
+
+#[test]
+fn test_use_module_1_fails() {
+    let error = consult("tests/example_programs/loading/missing.pl").unwrap_err();
+
+    assert_eq!(
+        error,
+        "existence_error(source_sink, library(no_such_library))"
+    );
+}
+
+#[test]
+fn test_autoload_1_succeeds() {
+    let source = consult("tests/example_programs/loading/explicit.pl").unwrap();
+    let query = parse_query("phrase(number(X), `42`).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = 42"]);
+}
+
+#[test]
+fn test_autoload_2_succeeds() {
+    let query = parse_query("set_prolog_flag(autoload, false), current_prolog_flag(autoload, X).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["X = false"]);
+
+    let query = parse_query("phrase(number(X), `42`).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["Unhandled exception: error(existence_error(procedure, number/3), context(number/3, _1))"],
+    );
+}
+
+#[test]
+fn test_autoload_3_succeeds() {
+    let source = consult("tests/example_programs/loading/shadowed.pl").unwrap();
+    let query = parse_query("last([1, 2, 3], X), reverse([1, 2], R).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["R = [2, 1]\nX = mine"]);
+}
+
+#[test]
+fn test_autoload_1_fails() {
+    let source = consult("tests/example_programs/loading/no_autoload.pl").unwrap();
+    let query = parse_query("phrase(number(X), `42`).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["Unhandled exception: error(existence_error(procedure, integer/3), context(integer/3, _2))"],
+    );
+}
+
+#[test]
+fn test_consult_user_1_succeeds() {
+    assert!(consults_user(&parse_query("[user].")));
+    assert!(consults_user(&parse_query("consult(user).")));
+    assert!(!consults_user(&parse_query("consult(user), true.")));
+    assert!(consult_user("\n").unwrap().is_empty());
+
+    let source = consult_user(":- initialization(greet).\ngreet :- true.\nname(world).\n").unwrap();
+    let goals = initialization_goals(&source);
+
+    assert_eq!(goals.len(), 1);
+    assert_eq!(goals[0].1.to_string(), "call(greet)");
+
+    let results = solve_toplevel(false, &source, parse_query("name(X)."));
+
+    compare_answers(results, &["X = world"]);
+}
+
+#[test]
+fn test_initialization_1_succeeds() {
+    let source = consult("tests/example_programs/loading/script.pl").unwrap();
+    let goals = initialization_goals(&source);
+
+    let whens: Vec<_> = goals.iter().map(|(when, _)| *when).collect();
+    assert_eq!(
+        whens,
+        &[
+            Initialization::AfterLoad,
+            Initialization::AfterLoad,
+            Initialization::Main
+        ]
+    );
+    assert_eq!(goals[0].1.to_string(), "call(format(\"library~n\"))");
+    assert!(goals
+        .into_iter()
+        .all(|(_, goal)| solve_quietly(&source, goal)));
+}
+
+#[test]
+fn test_load_goals_1_succeeds() {
+    let source = consult("tests/example_programs/loading/directives.pl").unwrap();
+    let goals = load_goals(&source);
+
+    let written: Vec<_> = goals.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        written,
+        &[
+            "assertz(seen(first))",
+            "call(assertz(seen(second)))",
+            "assertz(seen(third))",
+            "fail",
+            "throw(oops)"
+        ]
+    );
+
+    let outcomes: Vec<_> = goals
+        .into_iter()
+        .map(|goal| solve_directive(&source, goal))
+        .collect();
+    let oops = Term::Atom(ast::Atom::new("oops", vec![]));
+    assert_eq!(
+        outcomes,
+        &[Ok(true), Ok(true), Ok(true), Ok(false), Err(oops)]
+    );
+
+    let query = parse_query("findall(X, seen(X), Xs), path(a, Y).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Xs = [first, second, third]\nY = b"]);
+}
+
+#[test]
+fn test_conditional_compilation_1_succeeds() {
+    let source = consult("tests/example_programs/loading/conditional.pl").unwrap();
+    let query = parse_query("findall(B, backend(B), Bs), nested(N), checked(C).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Bs = [tabling]\nC = yes\nN = right"]);
+}
+
+#[test]
+fn test_conditional_compilation_1_fails() {
+    let error = consult("tests/example_programs/loading/unterminated.pl").unwrap_err();
+
+    assert_eq!(error, ":- if without :- endif");
+}
+
+/// Runs the toplevel on the program at `path` with `input` typed at it, giving back the errors it
+/// reported on standard error, without its warnings, and whether it exited normally.
+fn toplevel(path: &str, input: &str) -> (String, bool) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bfg-prolog"))
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let errors = String::from_utf8(output.stderr).unwrap();
+
+    (
+        errors
+            .lines()
+            .filter(|line| !line.starts_with("Warning: "))
+            .map(|line| format!("{}\n", line))
+            .collect(),
+        output.status.success(),
+    )
+}
+
+#[test]
+fn test_conditional_compilation_2_fails() {
+    let (errors, exited) = toplevel("tests/example_programs/loading/unterminated.pl", "true.\n");
+
+    assert_eq!(errors, ":- if without :- endif\n");
+    assert!(exited);
+}
+
+#[test]
+fn test_toplevel_1_fails() {
+    let (errors, exited) = toplevel(
+        "tests/example_programs/loading/no_such_file.pl",
+        "consult('tests/example_programs/loading/no_such_file.pl').\nfoo(.\ntrue.\n",
+    );
+    let lines: Vec<_> = errors.lines().collect();
+
+    assert_eq!(
+        lines[..2],
+        [
+            "existence_error(source_sink, 'tests/example_programs/loading/no_such_file.pl')",
+            "existence_error(source_sink, 'tests/example_programs/loading/no_such_file.pl')",
+        ]
+    );
+    assert!(lines[2].starts_with("Syntax error: "));
+    assert!(exited);
+}
+
+#[test]
+fn test_include_1_succeeds() {
+    let source = consult("tests/example_programs/loading/includer.pl").unwrap();
+    let query = parse_query("findall(P, part(P), Ps), findall(Y, shout(hi, Y), Ys), ping, pong.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Ps = [one, two, three]\nYs = ['hi!']"]);
+}
+
+#[test]
+fn test_include_1_fails() {
+    let error = consult("tests/example_programs/loading/cycle_a.pl").unwrap_err();
+
+    assert_eq!(
+        error,
+        "tests/example_programs/loading/cycle_a.pl includes itself"
+    );
+}
+
+#[test]
+fn test_lists_1_succeeds() {
+    let source = read_source_code("tests/example_programs/lists/lists.pl");
+    let query = parse_query("length(L, N), N >= 2, !.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["L = [_X3, _X5]\nN = 2"]);
+}
+
+#[test]
+fn test_lists_2_succeeds() {
+    let source = read_source_code("tests/example_programs/lists/lists.pl");
+    let query = parse_query("length([a, b|T], 3), length([a, b, c], N).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["N = 3\nT = [_X5]"]);
+}
+
+#[test]
+fn test_lists_3_succeeds() {
+    let source = read_source_code("tests/example_programs/lists/lists.pl");
+    let query = parse_query("nth1(I, L, x, [a, b]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &[
+            "I = 1\nL = [x, a, b]",
+            "I = 2\nL = [a, x, b]",
+            "I = 3\nL = [a, b, x]",
+        ],
+    );
+}
+
+#[test]
+fn test_lists_4_succeeds() {
+    let source = read_source_code("tests/example_programs/lists/lists.pl");
+    let query = parse_query(
+        "findall(P, permutation(P, [1, 2]), Ps), last([1, 2, 3], X), reverse([1, 2, 3], R).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Ps = [[1, 2], [2, 1]]\nR = [3, 2, 1]\nX = 3"]);
+}
+
+#[test]
+fn test_lists_5_succeeds() {
+    let source = read_source_code("tests/example_programs/lists/lists.pl");
+    let query = parse_query(
+        "subtract([1, 2, 3, 4], [2, 4], S), include(integer, [a, 1, b], I), exclude(integer, [a, 1, b], E), pairs_up([a, b, c], P).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = [a, b]\nI = [1]\nP = [a-b, a-c, b-c]\nS = [1, 3]"],
+    );
+}
+
+#[test]
+fn test_lists_6_succeeds() {
+    let query = parse_query("memberchk(a, [a|T]), memberchk(c, [a, b|U]), memberchk(0'b, `abc`).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["U = [c|_1]"]);
+}
+
+#[test]
+fn test_lists_1_fails() {
+    let source = read_source_code("tests/example_programs/lists/lists.pl");
+    let query = parse_query("length(L, -1).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_assoc_1_succeeds() {
+    let source = read_source_code("tests/example_programs/assoc/assoc.pl");
+    let query =
+        parse_query("list_to_assoc([c-3, a-1, b-2], A), get_assoc(b, A, V), assoc_to_list(A, L).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = t(b, 2, 2, t(a, 1, 1, t, t), t(c, 3, 1, t, t))\nL = [a-1, b-2, c-3]\nV = 2"],
+    );
+}
+
+#[test]
+fn test_assoc_2_succeeds() {
+    let source = read_source_code("tests/example_programs/assoc/assoc.pl");
+    let query = parse_query("update_six(D, S, X, Vs).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["D = 3\nS = 36\nVs = [1, 4, 9, 16, 25, six, 49]\nX = six"],
+    );
+}
+
+#[test]
+fn test_assoc_1_fails() {
+    let source = read_source_code("tests/example_programs/assoc/assoc.pl");
+    let query = parse_query("list_to_assoc([a-1, b-2, a-3], A).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_pairs_1_succeeds() {
+    let source = consult("tests/example_programs/sets/sets.pl").unwrap();
+    let query = parse_query("ages(Ps), pairs_keys_values(Ps, Ks, Vs), pairs_keys(Ps, Ks1).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["Ks = [alice, bob, carol]\nKs1 = [alice, bob, carol]\nPs = [alice-31, bob-27, carol-45]\nVs = [31, 27, 45]"],
+    );
+}
+
+#[test]
+fn test_pairs_2_succeeds() {
+    let source = consult("tests/example_programs/sets/sets.pl").unwrap();
+    let query = parse_query("pairs_keys_values(Ps, [a, b], [1, 2]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Ps = [a-1, b-2]"]);
+}
+
+#[test]
+fn test_ordsets_1_succeeds() {
+    let source = consult("tests/example_programs/sets/sets.pl").unwrap();
+    let query = parse_query(
+        "ord_union([a, c, e], [b, c, f], U), ord_intersection([a, c, e], [b, c, e], I), ord_subtract([a, b, c, d], [b, d, x], S).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["I = [c, e]\nS = [a, c]\nU = [a, b, c, e, f]"]);
+}
+
+#[test]
+fn test_ordsets_1_fails() {
+    let source = consult("tests/example_programs/sets/sets.pl").unwrap();
+    let query = parse_query("ord_memberchk(c, [a, b, d]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_random_1_succeeds() {
+    let source = consult("tests/example_programs/random/random.pl").unwrap();
+    let query =
+        parse_query("repeatable(Xs, Ys), Xs == Ys, in_range, permutes(P), length(P, 3), !.");
+
+    let results = solve_toplevel(false, &source, query);
+
+    assert_eq!(results.len(), 1);
+    assert_ne!(results[0], "No");
+}
+
+#[test]
+fn test_random_1_fails() {
+    let source = consult("tests/example_programs/random/random.pl").unwrap();
+    let query = parse_query("random_member(X, []).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_random_2_fails() {
+    let source = consult("tests/example_programs/random/random.pl").unwrap();
+    let query = parse_query("random_between(5, 1, X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_counters_1_succeeds() {
+    let source = consult("tests/example_programs/counters/counters.pl").unwrap();
+    let query = parse_query("fresh_names(A, B, C), reset_gensym(tmp_), gensym(tmp_, D).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["A = tmp_1\nB = tmp_2\nC = label1\nD = tmp_1"]);
+}
+
+#[test]
+fn test_counters_2_succeeds() {
+    let source = consult("tests/example_programs/counters/counters.pl").unwrap();
+    let query = parse_query("count(hits, A), count(hits, B), count(misses, C), flag(hits, N, N).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["A = 1\nB = 2\nC = 1\nN = 2"]);
+}
+
+#[test]
+fn test_numbervars_1_succeeds() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query = parse_query("T = f(X, g(Y), X, Z), numbervars(T, 0, End), term_string(S, T).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["End = 3\nS = \"f(A, g(B), A, C)\"\nT = f(A, g(B), A, C)\nX = A\nY = B\nZ = C"],
+    );
+}
+
+#[test]
+fn test_numbervars_2_succeeds() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query = parse_query("numbervars(p(X, Y), 25, End), format(\"~w ~q~n\", [X, Y]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["End = 27\nX = Z\nY = A1"]);
+}
+
+#[test]
+#[cfg(feature = "time")]
+fn test_time_1_succeeds() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query = parse_query("stamp_date_time(86400.5, D, 'UTC'), stamp_date_time(0, E, 3600).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["D = date(1970, 1, 2, 0, 0, 0.5, 0, 'UTC', -)\nE = date(1969, 12, 31, 23, 0, 0.0, 3600, -, -)"],
+    );
+}
+
+#[test]
+#[cfg(feature = "time")]
+fn test_time_2_succeeds() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query = parse_query(
+        "format_time(atom(A), '%Y-%m-%d %H:%M:%S %z', date(2024, 2, 29, 13, 5, 7.0, -3600, '-', '-')), format_time(string(S), '%d %b %Y', date(2000, 1, 2)).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = '2024-02-29 13:05:07 +0100'\nS = \"02 Jan 2000\""],
+    );
+}
+
+#[test]
+fn test_time_3_succeeds() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query = parse_query("\\+ \\+ (get_time(T0), sleep(0.01), get_time(T1), T1 > T0).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Yes"]);
+}
+
+#[test]
+#[cfg(feature = "time")]
+fn test_time_1_fails() {
+    let source = read_source_code("tests/example_programs/strings/strings.pl");
+    let query = parse_query("format_time(atom(A), '%Q', 0).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_exceptions_1_succeeds() {
+    let source = consult("tests/example_programs/exceptions/exceptions.pl").unwrap();
+    let query = parse_query(
+        "eval_error(foo + 1, A), eval_error(_N + 1, B), eval_error(1 / 0, C), eval_error(7 mod 0, D), eval_error(7.0 // 2, F).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = type_error(evaluable, foo/0)\nB = instantiation_error\nC = evaluation_error(zero_divisor)\nD = evaluation_error(zero_divisor)\nF = type_error(integer, 7.0)"],
+    );
+}
+
+#[test]
+fn test_exceptions_2_succeeds() {
+    let source = consult("tests/example_programs/exceptions/exceptions.pl").unwrap();
+    let query = parse_query(
+        "error_of(_X =:= 5, A), error_of(1 < bar(2), B), error_of(succ(_Y, _Z), C), error_of(succ(a, _W), D), error_of(succ(-1, _V), F).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = instantiation_error\nB = type_error(evaluable, bar/1)\nC = instantiation_error\nD = type_error(integer, a)\nF = type_error(not_less_than_zero, -1)"],
+    );
+}
+
+#[test]
+fn test_exceptions_3_succeeds() {
+    let source = consult("tests/example_programs/exceptions/exceptions.pl").unwrap();
+    let query = parse_query("rethrown(B), safe_div(1, 0, Z), safe_div(6, 3, W).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["B = outer\nW = 2\nZ = infinity"]);
+}
+
+#[test]
+fn test_exceptions_4_succeeds() {
+    let source = consult("tests/example_programs/exceptions/exceptions.pl").unwrap();
+    let query = parse_query("first_positive([-1, 0, 2, 3], X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = 2", "X = 3"]);
+}
+
+#[test]
+fn test_exceptions_5_succeeds() {
+    let source = consult("tests/example_programs/exceptions/exceptions.pl").unwrap();
+    let query = parse_query(
+        "eval_error(10.0 ** 400, A), eval_error(exp(1000), B), eval_error(1.0e308 * 10, C), \
+         eval_error(0.0 ** (-1), D), eval_error(2 ^ (-1), F), X is inf + 1.",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = evaluation_error(float_overflow)\nB = evaluation_error(float_overflow)\nC = evaluation_error(float_overflow)\nD = evaluation_error(undefined)\nF = type_error(float, 2)\nX = inf"],
+    );
+}
+
+#[test]
+fn test_exceptions_1_fails() {
+    let source = consult("tests/example_programs/exceptions/exceptions.pl").unwrap();
+    let query = parse_query("succ(X, 0).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_exceptions_2_fails() {
+    let source = consult("tests/example_programs/exceptions/exceptions.pl").unwrap();
+    let query = parse_query("throw(oops).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Unhandled exception: oops"]);
+}
+
+#[test]
+fn test_exceptions_3_fails() {
+    let source = consult("tests/example_programs/exceptions/exceptions.pl").unwrap();
+    let query = parse_query("catch(member(_X, [1, 2]), E, true), (var(E) -> throw(oops) ; true).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Unhandled exception: oops"]);
+}
+
+#[test]
+fn test_exceptions_6_succeeds() {
+    let source = consult("tests/example_programs/exceptions/exceptions.pl").unwrap();
+    let query = parse_query(
+        "catch((member(X, [1, 2]), (X =:= 2 -> throw(two) ; true)), two, X = caught), X \\== 1.",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = caught"]);
+}
+
+#[test]
+fn test_columns_1_succeeds() {
+    let source = consult("tests/example_programs/strings/columns.pl").unwrap();
+    let query = parse_query("report(P).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["P = [5, 0, 8, 2]"]);
+}
+
+#[test]
+fn test_columns_2_succeeds() {
+    let source = consult("tests/example_programs/strings/columns.pl").unwrap();
+    let query = parse_query("tab_error(E), tab(user_error, 0).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = type_error(evaluable, foo/0)"]);
+}
+
+#[test]
+fn test_files_1_succeeds() {
+    let source = consult("tests/example_programs/files/files.pl").unwrap();
+    let query = parse_query(
+        "fixture('data.txt', F), fixture(sub, D), exists_file(F), \\+ exists_file(D), exists_directory(D), size_file(F, S), directory_files(D, Fs).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["D = 'tests/example_programs/files/sub'\nF = 'tests/example_programs/files/data.txt'\nFs = ['.', .., 'notes.txt']\nS = 6"],
+    );
+}
+
+#[test]
+fn test_files_2_succeeds() {
+    let source = consult("tests/example_programs/files/files.pl").unwrap();
+    let query = parse_query(
+        "relative_name('tests/example_programs/files/data', [extensions([txt]), access(read)], A), relative_name(tests/example_programs/files/sub/'..'/'data.txt', [], B), relative_name(sub, [relative_to('tests/example_programs/files'), file_type(directory), access(exist)], C).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = '/tests/example_programs/files/data.txt'\nB = '/tests/example_programs/files/data.txt'\nC = '/tests/example_programs/files/sub'"],
+    );
+}
+
+#[test]
+fn test_files_3_succeeds() {
+    let source = consult("tests/example_programs/files/files.pl").unwrap();
+    let query = parse_query("scratch_directory(Dir, Before, During, After).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["After = no\nBefore = no\nDir = 'target/files_test_directory'\nDuring = yes"],
+    );
+}
+
+#[test]
+fn test_files_4_succeeds() {
+    let source = consult("tests/example_programs/files/files.pl").unwrap();
+    let query = parse_query(
+        "file_error(delete_file('no/such/file'), E), file_error(absolute_file_name(nofile, _A, [access(read)]), F).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = existence_error(file, 'no/such/file')\nF = existence_error(source_sink, nofile)"],
+    );
+}
+
+#[test]
+fn test_files_1_fails() {
+    let source = consult("tests/example_programs/files/files.pl").unwrap();
+    let query = parse_query("absolute_file_name(nofile, _A, [access(read), file_errors(fail)]).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_system_1_succeeds() {
+    let source = consult("tests/example_programs/system/system.pl").unwrap();
+    let query = parse_query("env_roundtrip(bfg_prolog_test_var, V, W, Gone).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Gone = yes\nV = '42'\nW = '42'"]);
+}
+
+#[test]
+fn test_system_2_succeeds() {
+    let source = consult("tests/example_programs/system/system.pl").unwrap();
+    let query = parse_query(
+        "current_prolog_flag(bounded, B), current_prolog_flag(max_integer, M), current_prolog_flag(unix, U), current_prolog_flag(windows, W), U \\== W.",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0]
+        .trim()
+        .starts_with("B = true\nM = 9223372036854775807\n"));
+}
+
+#[test]
+fn test_system_1_fails() {
+    let source = consult("tests/example_programs/system/system.pl").unwrap();
+    let query = parse_query("getenv(bfg_prolog_unset_test_var, V).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_process_1_succeeds() {
+    let source = consult("tests/example_programs/system/process.pl").unwrap();
+    let query = parse_query("echo_lines(L), round_trip(N, S, Status).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["L = [\"hello world\", end_of_file]\nN = 8\nS = \"one\\ntwo\\n\"\nStatus = exit(0)"],
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_process_2_succeeds() {
+    let source = consult("tests/example_programs/system/process.pl").unwrap();
+    let query = parse_query(
+        "exit_status(3, S), process_failure(E), process_create(path(true), [], [stdout(null)]).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = process_error(path(false), exit(1))\nS = exit(3)"],
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_shell_1_succeeds() {
+    let source = consult("tests/example_programs/system/process.pl").unwrap();
+    let query = parse_query("shell_results(S, O).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["O = \"captured\\nlines\\n\"\nS = 4"]);
+}
+
+#[test]
+fn test_sockets_1_succeeds() {
+    let source = consult("tests/example_programs/sockets/sockets.pl").unwrap();
+    let query = parse_query("echo_once(R).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["R = \"ping pong\""]);
+}
+
+#[test]
+fn test_sockets_2_succeeds() {
+    let source = consult("tests/example_programs/sockets/sockets.pl").unwrap();
+    let query = parse_query("refused(E), closed_socket(F).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = socket_error(connection_refused)\nF = existence_error(socket, '$socket'(1))"],
+    );
+}
+
+#[test]
+fn test_http_1_succeeds() {
+    let source = consult("tests/example_programs/http/http.pl").unwrap();
+    let query = parse_query("serve(A, B, C, D).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = \"HTTP/1.1 200 OK\"-\"Hello, world!\"\n\
+           B = \"HTTP/1.1 404 Not Found\"-\"Not Found\"\n\
+           C = \"HTTP/1.1 500 Internal Server Error\"-\"oops\"\n\
+           D = \"HTTP/1.1 201 Created\"-\"ping\""],
+    );
+}
+
+#[test]
+fn test_http_2_succeeds() {
+    let source = consult("tests/example_programs/http/http.pl").unwrap();
+    let query = parse_query("limits(A, B, C).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = \"HTTP/1.1 413 Payload Too Large\"-\"Payload Too Large\"\n\
+           B = \"HTTP/1.1 431 Request Header Fields Too Large\"-\"Request Header Fields Too Large\"\n\
+           C = \"HTTP/1.1 414 URI Too Long\"-\"URI Too Long\""],
+    );
+}
+
+#[test]
+fn test_http_3_succeeds() {
+    let source = consult("tests/example_programs/http/http.pl").unwrap();
+    let query = parse_query("decoded(A, B, C).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = \"HTTP/1.1 200 OK\"-\"Hello, café au lait!!\"\n\
+           B = true\n\
+           C = domain_error(http_option, workers(0))"],
+    );
+}
+
+#[test]
+#[cfg(feature = "tls")]
+fn test_tls_1_succeeds() {
+    let source = consult("tests/example_programs/tls/tls.pl").unwrap();
+    let query = parse_query("serve_https(R).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["R = \"HTTP/1.1 200 OK\"-\"secure /private\""]);
+}
+
+#[test]
+#[cfg(feature = "tls")]
+fn test_tls_2_succeeds() {
+    let source = consult("tests/example_programs/tls/tls.pl").unwrap();
+    let query = parse_query("untrusted(E), missing_key(F).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = tls_error('invalid peer certificate: UnknownIssuer')\n\
+           F = existence_error(source_sink, 'missing.key')"],
+    );
+}
+
+#[test]
+#[cfg(all(unix, feature = "signals"))]
+fn test_signals_1_succeeds() {
+    let source = consult("tests/example_programs/system/signals.pl").unwrap();
+    let query = parse_query("handled(O, C, N), unknown_signal(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["C = count_signal\nE = domain_error(signal, nosuchsignal)\nN = 1\nO = default"],
+    );
+}
+
+#[test]
+#[cfg(all(unix, feature = "signals"))]
+fn test_signals_2_succeeds() {
+    let source = consult("tests/example_programs/system/signals.pl").unwrap();
+
+    let query = parse_query("interrupted(E).");
+    let results = solve_toplevel(false, &source, query);
+    compare_answers(results, &["E = '$aborted'"]);
+
+    let query = parse_query("on_signal(int, _Old, abort), shell('kill -INT $PPID'), spin.");
+    let results = solve_toplevel(false, &source, query);
+    compare_answers(results, &["% Execution Aborted"]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_json_1_succeeds() {
+    let source = consult("tests/example_programs/json/json.pl").unwrap();
+    let query = parse_query("read_file('tests/example_programs/json/data.json', D, C, R).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["C = json([name = bfg, tags = [prolog, rust], version = 0.7, stars = 42, active = @(true), license = @(null), nested = json([empty = [], text = 'line\\nbreak é 😀'])])\n\
+           D = json([name = \"bfg\", tags = [\"prolog\", \"rust\"], version = 0.7, stars = 42, active = true, license = null, nested = json([empty = [], text = \"line\\nbreak é 😀\"])])\n\
+           R = [1, 2]"],
+    );
+}
+
+#[test]
+fn test_json_2_succeeds() {
+    let source = consult("tests/example_programs/json/json.pl").unwrap();
+    let query =
+        parse_query("round_trip('{ \"a\" : [1, 2.5e3, \"s\"], \"b\": {\"c\": false}}', O).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["O = '{\"a\":[1,2500.0,\"s\"],\"b\":{\"c\":false}}'"],
+    );
+}
+
+#[test]
+fn test_json_3_succeeds() {
+    let source = consult("tests/example_programs/json/json.pl").unwrap();
+    let query = parse_query("write_terms(A, B, C).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &[
+            "A = '{\"name\":\"x\",\"size\":3,\"ok\":true,\"tags\":[\"a\",\"b\"],\"none\":null}'\n\
+           B = \"[1.5,-2,\\\"quote\\\\\\\"d\\\",{}]\"\n\
+           C = '{\"k\":\"v\"}'",
+        ],
+    );
+}
+
+#[test]
+fn test_json_4_succeeds() {
+    let source = consult("tests/example_programs/json/json.pl").unwrap();
+    let query = parse_query("bad_json(E), bad_term(F).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = syntax_error(json(illegal_json))\nF = type_error(json_term, foo(bar))"],
+    );
+}
+
+#[test]
+fn test_csv_1_succeeds() {
+    let source = consult("tests/example_programs/csv/csv.pl").unwrap();
+    let query = parse_query("read_all('tests/example_programs/csv/data.csv', Rows).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["Rows = [row(name, age, city), row(alice, 30, 'Paris, France'), row(bob, 25.5, 'say \"hi\"'), row('multi\\nline', ' -3 ', x)]"],
+    );
+}
+
+#[test]
+fn test_csv_2_succeeds() {
+    let source = consult("tests/example_programs/csv/csv.pl").unwrap();
+    let query = parse_query("read_with('tests/example_programs/csv/data.csv', [strip(true), functor(person), arity(3)], Rows).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["Rows = [person(name, age, city), person(alice, 30, 'Paris, France'), person(bob, 25.5, 'say \"hi\"'), person('multi\\nline', -3, x)]"],
+    );
+}
+
+#[test]
+fn test_csv_3_succeeds() {
+    let source = consult("tests/example_programs/csv/csv.pl").unwrap();
+    let query = parse_query("each_row('tests/example_programs/csv/data.csv', Row).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &[
+            "Row = row(name, age, city)",
+            "Row = row(alice, 30, 'Paris, France')",
+            "Row = row(bob, 25.5, 'say \"hi\"')",
+            "Row = row('multi\\nline', ' -3 ', x)",
+        ],
+    );
+}
+
+#[test]
+fn test_csv_4_succeeds() {
+    let source = consult("tests/example_programs/csv/csv.pl").unwrap();
+    let query = parse_query("first_rows('tests/example_programs/csv/data.csv', A, B).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["A = row(name, age, city)\nB = r(alice, '30', 'Paris, France')"],
+    );
+}
+
+#[test]
+fn test_csv_5_succeeds() {
+    let source = consult("tests/example_programs/csv/csv.pl").unwrap();
+    let query =
+        parse_query("round_trip([row(a, 'b;c', 1), row('q\"t', ' pad', 2.5)], Text, Back).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["Back = [row(a, 'b;c', 1), row('q\"t', ' pad', 2.5)]\n\
+           Text = 'a,b;c,1\\n\"q\"\"t\",\" pad\",2.5\\n'"],
+    );
+}
+
+#[test]
+fn test_csv_6_succeeds() {
+    let source = consult("tests/example_programs/csv/csv.pl").unwrap();
+    let query = parse_query("bad_arity(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = domain_error(row_arity(2), 1)"]);
+}
+
+#[test]
+fn test_csv_7_succeeds() {
+    let source = consult("tests/example_programs/csv/csv.pl").unwrap();
+    let query = parse_query("missing(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = existence_error(source_sink, 'tests/example_programs/csv/none.csv')"],
+    );
+}
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_xml_1_succeeds() {
+    let source = consult("tests/example_programs/xml/xml.pl").unwrap();
+    let query = parse_query("catalog(DOM).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["DOM = [element(catalog, [version = '2'], [element(book, [id = b1, lang = en], [element(title, [], ['Programming in Prolog']), element(price, [currency = 'EUR'], ['42.50'])]), element(book, [id = b2], [element(title, [], ['Fish & Chips']), element(note, [], ['<raw> text']), element('out-of-print', [], [])])])]"]);
+}
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_xml_2_succeeds() {
+    let source = consult("tests/example_programs/xml/xml.pl").unwrap();
+    let query = parse_query("titles(T).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["T = ['Programming in Prolog', 'Fish & Chips']"]);
+}
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_xml_3_succeeds() {
+    let source = consult("tests/example_programs/xml/xml.pl").unwrap();
+    let query = parse_query("page(DOM).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["DOM = [element(html, [], [element(head, [], [element(meta, [charset = 'utf-8'], []), element(title, [], ['Page'])]), element(body, [], [element(p, [class = intro], ['Hello\u{a0}there', element(br, [], []), 'world\\n']), element(p, [], ['Second'])])])]"],
+    );
+}
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_xml_4_succeeds() {
+    let source = consult("tests/example_programs/xml/xml.pl").unwrap();
+    let query = parse_query("from_stream(A).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["A = [version = '2']"]);
+}
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_xml_5_succeeds() {
+    let source = consult("tests/example_programs/xml/xml.pl").unwrap();
+    let query = parse_query("spaces(DOM).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["DOM = [element(a, [], [' ', element(b, [], [x]), ' '])]"],
+    );
+}
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_xml_6_succeeds() {
+    let source = consult("tests/example_programs/xml/xml.pl").unwrap();
+    let query = parse_query("write_dom(T).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["T = '<a href=\"x&amp;y\" title=\"say &quot;hi&quot;\"><br/>a &lt; bc</a>'"],
+    );
+}
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_xml_7_succeeds() {
+    let source = consult("tests/example_programs/xml/xml.pl").unwrap();
+    let query = parse_query("round_trip(T).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["T = '<?xml version=\"1.0\" encoding=\"UTF-8\"?>\\n<catalog version=\"2\"><book id=\"b1\" lang=\"en\"><title>Programming in Prolog</title><price currency=\"EUR\">42.50</price></book><book id=\"b2\"><title>Fish &amp; Chips</title><note>&lt;raw&gt; text</note><out-of-print/></book></catalog>'"],
+    );
+}
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_xml_8_succeeds() {
+    let source = consult("tests/example_programs/xml/xml.pl").unwrap();
+    let query = parse_query("bad(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = syntax_error(xml(mismatched_end_tag))"]);
+}
+
+#[test]
+#[cfg(feature = "xml")]
+fn test_xml_9_succeeds() {
+    let source = consult("tests/example_programs/xml/xml.pl").unwrap();
+    let query = parse_query("unclosed(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = syntax_error(xml(unexpected_end_of_input))"]);
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn test_config_1_succeeds() {
+    let source = consult("tests/example_programs/config/config.pl").unwrap();
+    let query = parse_query("yaml(T).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["T = json([name = bfg, version = 0.7, debug = @(false), ports = [8080, 8443], owner = @(null), database = json([host = localhost, retries = 3])])"]);
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn test_config_2_succeeds() {
+    let source = consult("tests/example_programs/config/config.pl").unwrap();
+    let query = parse_query("yaml_dict(T).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["T = json([name = \"bfg\", version = 0.7, debug = false, ports = [8080, 8443], owner = null, database = json([host = \"localhost\", retries = 3])])"]);
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn test_config_3_succeeds() {
+    let source = consult("tests/example_programs/config/config.pl").unwrap();
+    let query = parse_query("yaml_documents(D).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["D = [json([a = 1]), [x, 2.5]]"]);
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_config_4_succeeds() {
+    let source = consult("tests/example_programs/config/config.pl").unwrap();
+    let query = parse_query("toml(T).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["T = json([name = bfg, version = 0.7, released = '2019-06-01', database = json([host = localhost, ports = [5432, 5433], enabled = @(true)])])"]);
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_config_5_succeeds() {
+    let source = consult("tests/example_programs/config/config.pl").unwrap();
+    let query = parse_query("toml_dict(H, P).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["H = \"localhost\"\nP = [5432, 5433]"]);
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn test_config_6_succeeds() {
+    let source = consult("tests/example_programs/config/config.pl").unwrap();
+    let query = parse_query("bad_yaml(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = syntax_error(yaml(illegal_yaml))"]);
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_config_7_succeeds() {
+    let source = consult("tests/example_programs/config/config.pl").unwrap();
+    let query = parse_query("bad_toml(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = syntax_error(toml(illegal_toml))"]);
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_sqlite_1_succeeds() {
+    let source = consult("tests/example_programs/sqlite/sqlite.pl").unwrap();
+    let query = parse_query("people(R).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &[
+            "R = row(bob, 25, '$null$')",
+            "R = row(alice, 30, 9.5)",
+            "R = row(carol, 41, 7.25)",
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_sqlite_2_succeeds() {
+    let source = consult("tests/example_programs/sqlite/sqlite.pl").unwrap();
+    let query = parse_query("older(28, N).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["N = alice", "N = carol"]);
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_sqlite_3_succeeds() {
+    let source = consult("tests/example_programs/sqlite/sqlite.pl").unwrap();
+    let query = parse_query("update(A, C).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["A = affected(2)\nC = 2"]);
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_sqlite_4_succeeds() {
+    let source = consult("tests/example_programs/sqlite/sqlite.pl").unwrap();
+    let query = parse_query("file(R).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["R = [key-value]"]);
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_sqlite_5_succeeds() {
+    let source = consult("tests/example_programs/sqlite/sqlite.pl").unwrap();
+    let query = parse_query("bad_sql(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = sqlite_error('near \"SELEKT\": syntax error in SELEKT 1 at offset 0')"],
+    );
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_sqlite_6_succeeds() {
+    let source = consult("tests/example_programs/sqlite/sqlite.pl").unwrap();
+    let query = parse_query("closed(K).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["K = sqlite_connection"]);
+}
+
+#[test]
+fn test_fastrw_1_succeeds() {
+    let source = consult("tests/example_programs/fastrw/fastrw.pl").unwrap();
+    let query = parse_query("round_trip([foo, 42, -7, 3.25, \"text\", 'quoted atom', point(1, [a, b]), é(\"ü\"), []], R).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["R = [foo, 42, -7, 3.25, \"text\", 'quoted atom', point(1, [a, b]), é(\"ü\"), []]"],
+    );
+}
+
+#[test]
+fn test_fastrw_2_succeeds() {
+    let source = consult("tests/example_programs/fastrw/fastrw.pl").unwrap();
+    let query = parse_query("shared(S, D).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["D = yes\nS = yes"]);
+}
+
+#[test]
+fn test_fastrw_3_succeeds() {
+    let source = consult("tests/example_programs/fastrw/fastrw.pl").unwrap();
+    let query = parse_query("not_fast(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = syntax_error(illegal_fast_term)"]);
+}
+
+#[test]
+fn test_fastrw_4_succeeds() {
+    let mut bytes = fastrw::encode(&parse_term("foo"));
+    // The tag atoms were once written with, apart from compound terms.
+    bytes.truncate(3);
+    bytes.extend_from_slice(&[6, 3, b'f', b'o', b'o']);
+
+    assert_eq!(fastrw::decode(&bytes), Some(parse_term("foo")));
+}
+
+#[test]
+fn test_persistency_1_succeeds() {
+    let source = consult("tests/example_programs/persistency/persistency.pl").unwrap();
+    let query = parse_query("session(Visits, Tags).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Tags = [new]\nVisits = [about-1, home-2]"]);
+}
+
+#[test]
+fn test_persistency_2_succeeds() {
+    let source = consult("tests/example_programs/persistency/persistency.pl").unwrap();
+    let query = parse_query("journal(Before, After, Entries).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["After = [home-2]\nBefore = []\nEntries = \"2 target/persistency_journal.db\""],
+    );
+}
+
+#[test]
+fn test_persistency_3_succeeds() {
+    let source = consult("tests/example_programs/persistency/persistency.pl").unwrap();
+    let query = parse_query("bad_type(E1), unbound(E2).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E1 = type_error(nonneg, -1)\nE2 = instantiation_error"],
+    );
+}
+
+#[test]
+fn test_records_1_succeeds() {
+    let source = consult("tests/example_programs/records/records.pl").unwrap();
+    let query = parse_query("queue(Items).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Items = [first, second, third]"]);
+}
+
+#[test]
+fn test_records_2_succeeds() {
+    let source = consult("tests/example_programs/records/records.pl").unwrap();
+    let query = parse_query("fresh(A, Shared).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["A = 1\nShared = yes"]);
+}
+
+#[test]
+fn test_records_3_succeeds() {
+    let source = consult("tests/example_programs/records/records.pl").unwrap();
+    let query = parse_query("erased(Before, After, Again), bad_key(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["After = [b]\nAgain = no\nBefore = a\nE = type_error(key, 1.5)"],
+    );
+}
+
+#[test]
+fn test_threads_1_succeeds() {
+    let source = consult("tests/example_programs/threads/threads.pl").unwrap();
+    let query = parse_query("statuses(S1, S2, S3).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["S1 = true\nS2 = false\nS3 = exception(oops)"]);
+}
+
+#[test]
+fn test_threads_2_succeeds() {
+    let source = consult("tests/example_programs/threads/threads.pl").unwrap();
+    let query = parse_query("squares(Results).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Results = [4, 25]"]);
+}
+
+#[test]
+fn test_threads_3_succeeds() {
+    let source = consult("tests/example_programs/threads/threads.pl").unwrap();
+    let query = parse_query("selective(X), unknown(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = existence_error(thread, nobody)\nX = 7"]);
+}
+
+#[test]
+fn test_threads_4_succeeds() {
+    let source = consult("tests/example_programs/threads/threads.pl").unwrap();
+    let query = parse_query("doubled(Ys), checks(All, Some).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["All = yes\nSome = no\nYs = [2, 4, 6, 8, 10, 12, 14, 16]"],
+    );
+}
+
+#[test]
+fn test_threads_5_succeeds() {
+    let source = consult("tests/example_programs/threads/threads.pl").unwrap();
+    let query = parse_query("pool(Xs, Forall, E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = oops\nForall = no\nXs = [1, 42, 3]"]);
+}
+
+#[test]
+fn test_mutexes_1_succeeds() {
+    let source = consult("tests/example_programs/threads/threads.pl").unwrap();
+    let query = parse_query("gated(Before, After).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["After = true\nBefore = false"]);
+}
+
+#[test]
+fn test_mutexes_2_succeeds() {
+    let source = consult("tests/example_programs/threads/threads.pl").unwrap();
+    let query = parse_query("guarded(X, Released, E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = oops\nReleased = true\nX = inner"]);
+}
+
+#[test]
+fn test_mutexes_3_succeeds() {
+    let source = consult("tests/example_programs/threads/threads.pl").unwrap();
+    let query = parse_query("not_owner(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = permission_error(unlock, mutex, gate)"]);
+}
+
+#[test]
+fn test_mutexes_4_succeeds() {
+    let source = consult("tests/example_programs/threads/threads.pl").unwrap();
+    let query = parse_query("shared(N, Visits, Left).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Left = []\nN = 100\nVisits = 50"]);
+}
+
+#[test]
+fn test_thread_local_1_succeeds() {
+    let source = consult("tests/example_programs/thread_local/thread_local.pl").unwrap();
+    let query = parse_query("count(Empty, N).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Empty = yes\nN = 3"]);
+}
+
+#[test]
+fn test_thread_local_2_succeeds() {
+    let source = consult("tests/example_programs/thread_local/thread_local.pl").unwrap();
+    let query = parse_query("separate(Mine, Theirs).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Mine = 10\nTheirs = count(yes, 3)"]);
+}
+
+#[test]
+fn test_thread_local_3_succeeds() {
+    let source = consult("tests/example_programs/thread_local/thread_local.pl").unwrap();
+    let query = parse_query("rules(Ys, Left), static(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = permission_error(modify, static_procedure, square/2)\nLeft = []\nYs = [zero, 2, 4]"],
+    );
+}
+
+#[test]
+fn test_thread_local_4_succeeds() {
+    let source = consult("tests/example_programs/thread_local/thread_local.pl").unwrap();
+    let query = parse_query("asserted(Xs, E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = permission_error(modify, static_procedure, atom/1)\nXs = [0, 1]"],
+    );
+}
+
+#[test]
+fn test_engines_1_succeeds() {
+    let source = consult("tests/example_programs/engines/engines.pl").unwrap();
+    let query = parse_query("firsts(Xs, Rest).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Rest = [c-3]\nXs = [a-1, b-2]"]);
+}
+
+#[test]
+fn test_engines_2_succeeds() {
+    let source = consult("tests/example_programs/engines/engines.pl").unwrap();
+    let query = parse_query("naturals(Ns), yields(Ys).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Ns = [0, 1, 2, 3]\nYs = [10, 11, 12]"]);
+}
+
+#[test]
+fn test_engines_3_succeeds() {
+    let source = consult("tests/example_programs/engines/engines.pl").unwrap();
+    let query = parse_query("failing(Result, Error), outside(E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["E = permission_error(yield, engine, none)\nError = oops\nResult = none"],
+    );
+}
+
+#[test]
+fn test_time_limits_1_succeeds() {
+    let source = consult("tests/example_programs/time_limits/time_limits.pl").unwrap();
+    let query = parse_query("limited(Fast, Slept, Spun).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["Fast = a\nSlept = time_limit_exceeded\nSpun = time_limit_exceeded"],
+    );
+}
+
+#[test]
+fn test_time_limits_2_succeeds() {
+    let source = consult("tests/example_programs/time_limits/time_limits.pl").unwrap();
+    let query = parse_query("nested(E), outlived(X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = time_limit_exceeded\nX = done"]);
+}
+
+#[test]
+fn test_time_limits_3_succeeds() {
+    let source = consult("tests/example_programs/time_limits/time_limits.pl").unwrap();
+    let query = parse_query("spin.");
+    let options = QueryOptions {
+        time_limit: Some(Duration::from_millis(200)),
+        ..QueryOptions::default()
+    };
+
+    let results = solve_with_options(false, &source, query, &options);
+
+    compare_answers(results, &["Unhandled exception: time_limit_exceeded"]);
+}
+
+#[test]
+fn test_query_handle_1_succeeds() {
+    let source = consult("tests/example_programs/time_limits/time_limits.pl").unwrap();
+    let handle = QueryHandle::new();
+    let options = QueryOptions {
+        handle: Some(handle.clone()),
+        ..QueryOptions::default()
+    };
+
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        handle.cancel();
+    });
+
+    let spun = solve_with_options(false, &source, parse_query("spin."), &options);
+    canceller.join().unwrap();
+    let slept = solve_with_options(false, &source, parse_query("sleep(10)."), &options);
+
+    compare_answers(spun, &["% Execution Aborted"]);
+    compare_answers(slept, &["% Execution Aborted"]);
+}
+
+#[test]
+fn test_query_handle_2_succeeds() {
+    let source = consult("tests/example_programs/time_limits/time_limits.pl").unwrap();
+    let options = QueryOptions {
+        handle: Some(QueryHandle::new()),
+        ..QueryOptions::default()
+    };
+
+    let results = solve_with_options(false, &source, parse_query("outlived(X)."), &options);
+
+    compare_answers(results, &["X = done"]);
+}
+
+/// Keeps what a query reports, asking for no more than `wanted` answers.
+struct Transcript {
+    events: Vec<String>,
+    wanted: usize,
+}
+
+impl Transcript {
+    fn new(wanted: usize) -> Self {
+        Transcript {
+            events: Vec::new(),
+            wanted,
+        }
+    }
+}
+
+impl Console for Transcript {
+    fn answer(&mut self, answer: &str, last: bool) {
+        let kind = if last { "last" } else { "answer" };
+        self.events.push(format!("{}: {}", kind, answer));
+    }
+
+    fn more(&mut self) -> bool {
+        self.wanted = self.wanted.saturating_sub(1);
+        self.wanted > 0
+    }
+
+    fn no(&mut self) {
+        self.events.push(String::from("no"));
+    }
+
+    fn exception(&mut self, message: &str) {
+        self.events.push(String::from(message));
+    }
+}
+
+#[test]
+fn test_console_1_succeeds() {
+    let source = parse_code("colour(red). colour(green). colour(blue).");
+    let mut console = Transcript::new(2);
+
+    solve_with_console(
+        &mut console,
+        &source,
+        parse_query("colour(X)."),
+        &QueryOptions::default(),
+    );
+
+    assert_eq!(console.events, ["answer: X = red", "answer: X = green"]);
+}
+
+#[test]
+fn test_console_2_succeeds() {
+    let source = parse_code("colour(red).");
+    let mut console = Transcript::new(usize::MAX);
+
+    for query in ["X = red.", "colour(blue).", "throw(oops)."] {
+        solve_with_console(
+            &mut console,
+            &source,
+            parse_query(query),
+            &QueryOptions::default(),
+        );
+    }
+
+    assert_eq!(
+        console.events,
+        ["last: X = red", "no", "Unhandled exception: oops"]
+    );
+}
+
+#[test]
+fn test_qsave_1_succeeds() {
+    let source = parse_code(
+        "twice(X, Y) :- Y is X * 2.
+         greet(Name, Text) :- format(atom(Text), \"hello ~w\", [Name]).",
+    );
+    let image = std::env::temp_dir().join(format!("bfg_qsave_{}.qlf", std::process::id()));
+    let query = parse_query(&format!(
+        "qsave_program('{}', [goal(greet(world, _T))]).",
+        image.display()
+    ));
+
+    compare_answers(solve_toplevel(false, &source, query), &["Yes"]);
+
+    let saved = consult(&image.to_string_lossy()).unwrap();
+    std::fs::remove_file(&image).unwrap();
+    let query = parse_query("twice(21, X), greet(saved, Y).");
+
+    compare_answers(
+        solve_toplevel(false, &saved, query),
+        &["X = 42\nY = 'hello saved'"],
+    );
+    assert!(initialization_goals(&saved)
+        .iter()
+        .any(|(when, _)| *when == Initialization::Main));
+}
+
+#[derive(Clone, Default)]
+struct Captured(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+impl std::io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_user_output_1_succeeds() {
+    let captured = Captured::default();
+    set_user_output(Some(Box::new(captured.clone())));
+
+    let query = parse_query("format(\"~w and ~w~n\", [a, b]), format(\"done\").");
+    let results = solve_toplevel(false, &[], query);
+    set_user_output(None);
+
+    compare_answers(results, &["Yes"]);
+    assert_eq!(&captured.0.borrow()[..], b"a and b\ndone");
+}
+
+#[test]
+fn test_xref_undefined_1_succeeds() {
+    let kb = parse_code(
+        ":- dynamic(seen/1).
+         :- initialization(main).
+         main :- findall(X, (item(X), \\+ seen(X)), Xs), include(show, Xs, _Ys), missing(Xs).
+         item(1).
+         show(X) :- format(\"~w~n\", [X]), call(shown, X, _Y), length(_Xs, 2).",
+    );
+    let undefined: Vec<_> = bfg_prolog::xref::undefined(&kb)
+        .into_iter()
+        .map(|(a, goal)| format!("{}: {}/{}", a.head.name.0, goal.name.0, goal.arity))
+        .collect();
+
+    assert_eq!(undefined, vec!["main: missing/1", "show: shown/2"]);
+}
+
+#[test]
+fn test_iso_errors_1_succeeds() {
+    let query = parse_query("catch(atom_length(X, L), error(E, C), true).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["C = context(atom_length/2, _1)\nE = instantiation_error"],
+    );
+}
+
+#[test]
+fn test_iso_errors_2_succeeds() {
+    let source = parse_code("p(X) :- q(X).");
+    let query = parse_query("catch(p(1), error(existence_error(procedure, PI), C), true).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["C = context(q/1, _2)\nPI = q/1"]);
+}
+
+#[test]
+fn test_iso_errors_3_succeeds() {
+    let query = parse_query(
+        "catch(X =.. [f(a), b], error(E1, context(P1, _A)), true), \
+         catch(functor(_T, foo, -1), error(E2, context(P2, _B)), true), \
+         catch(call(1), error(E3, context(P3, _C)), true).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["E1 = type_error(atomic, f(a))\nE2 = domain_error(not_less_than_zero, -1)\nE3 = type_error(callable, 1)\nP1 = (=..)/2\nP2 = functor/3\nP3 = call/1\n_A = _1\n_B = _2\n_C = _3"],
+    );
+}
+
+#[test]
+fn test_occurs_check_1_succeeds() {
+    let query = parse_query(
+        "current_prolog_flag(occurs_check, F), unify_with_occurs_check(X, f(Y)), \
+         \\+ unify_with_occurs_check(Z, f(Z)).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["F = false\nX = f(Y)"]);
+}
+
+#[test]
+fn test_occurs_check_2_fails() {
+    let source = parse_code(":- set_prolog_flag(occurs_check, true).\np(X, X).");
+    let query = parse_query("p(Y, g(Y)).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_occurs_check_3_succeeds() {
+    let source = parse_code("p(X, X).");
+    let query = parse_query(
+        "set_prolog_flag(occurs_check, error), \
+         catch(p(Y, g(Y)), error(occurs_check(_V, T), _C), true).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["T = g(X2)\n_C = _3\n_V = X2"]);
+}
+
+#[test]
+fn test_fresh_variables_1_fails() {
+    let query = parse_query("findall(f(Z), member(_A, [1, 2]), [f(X), f(Y)]), X == Y.");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_fresh_variables_1_succeeds() {
+    let query = "copy_term(f(X, Y, X), A), copy_term(A, B), copy_term(B, C).";
+
+    let first = solve_toplevel(false, &[], parse_query(query));
+    let second = solve_toplevel(false, &[], parse_query(query));
+
+    assert_eq!(first, second);
+    compare_answers(
+        first,
+        &["A = f(X1, Y2, X1)\nB = f(X3, Y4, X3)\nC = f(X5, Y6, X5)"],
+    );
+}
+
+#[test]
+fn test_fresh_variables_2_succeeds() {
+    let query =
+        parse_query("recorda(k, f(X)), recorded(k, f(A)), recorded(k, f(B)), A \\== B, X \\== A.");
+
+    let results = solve_toplevel(false, &[], query);
+
+    assert_eq!(results.len(), 1);
+    assert_ne!(results[0], "No");
+}
+
+#[test]
+fn test_cyclic_terms_1_succeeds() {
+    let query = parse_query(
+        "set_prolog_flag(occurs_check, true), X = f(Y), \\+ Y = g(X), acyclic_term(X).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["X = f(Y)"]);
+}
+
+#[test]
+fn test_cyclic_terms_2_succeeds() {
+    let source = parse_code("p(X, X).");
+    let query = parse_query(
+        "set_prolog_flag(occurs_check, error), \
+         catch(p(Y, g(Y)), error(E, _C), true), acyclic_term(Y).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    assert!(results[0].starts_with("E = occurs_check("));
+}
+
+#[test]
+fn test_prolog_flags_1_succeeds() {
+    let source = consult_text(":- set_prolog_flag(double_quotes, codes).\np(\"ab\").").unwrap();
+    let query = parse_query("p(X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["X = [97, 98]"]);
+}
+
+#[test]
+fn test_prolog_flags_2_fails() {
+    let query = parse_query("set_prolog_flag(unknown, fail), undefined_predicate.");
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["No"]);
+}
+
+#[test]
+fn test_prolog_flags_3_succeeds() {
+    let query = parse_query(
+        "set_prolog_flag(answer_write_options, [max_depth(3)]), X = f(g(h(i)), [1, 2, 3, 4]).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(results, &["X = f(g(...), [1, 2|...])"]);
+}
+
+#[test]
+fn test_prolog_flags_4_succeeds() {
+    let query = parse_query("catch(set_prolog_flag(max_integer, 3), error(E, _C), true).");
+
+    let results = solve_toplevel(false, &[], query);
+
+    assert!(results[0].starts_with("E = permission_error(modify, flag, max_integer)\n"));
+}
+
+#[test]
+fn test_iso_mode_1_fails() {
+    let error = consult_text(":- set_prolog_flag(iso, true).\n:- table p/1.\np(1).").unwrap_err();
+
+    assert_eq!(error, "table/1 is not a directive of the standard");
+}
+
+#[test]
+fn test_iso_mode_2_succeeds() {
+    let query = parse_query(
+        "X is 7 / 7, set_prolog_flag(iso, true), Y is 7 / 7, catch(Z is msb(8), error(E, _C), true).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    assert!(results[0].starts_with("E = type_error(evaluable, msb/1)\nX = 1\nY = 1.0\n"));
+}
+
+#[test]
+fn test_write_1_succeeds() {
+    let query = parse_query(
+        "write(atom(A), 1-(2-3)), writeq(atom(B), 'hello world'-[a|T]), \
+         write_canonical(atom(C), f(X, 'A', 1+2*3)), writeq(atom(D), (1-2)-3).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["A = '1-(2-3)'\n\
+           B = '\\'hello world\\'-[a|T]'\n\
+           C = 'f(X, \\'A\\', +(1, *(2, 3)))'\n\
+           D = '1-2-3'"],
+    );
+}
+
+#[test]
+fn test_write_2_succeeds() {
+    let query = parse_query(
+        "writeq(atom(A), (a :- b, c ; d -> e)), writeq(atom(B), f((a :- b), [(x, y)], ':-')), \
+         writeq(atom(C), a = (\\+ b)), T =.. ['-', 1], writeq(atom(D), 1 - (-1) - T).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["A = 'a :- b, c ; d -> e'\n\
+           B = 'f((a :- b), [(x, y)], :-)'\n\
+           C = 'a = (\\\\+b)'\n\
+           D = '1- -1- - 1'\n\
+           T = - 1"],
+    );
+}
+
+#[test]
+fn test_write_3_succeeds() {
+    let query = parse_query(
+        "write_term(atom(A), [1, 2+3], [ignore_ops(true)]), \
+         write_term(atom(B), f(g(h(i))), [max_depth(2)]), \
+         catch(write_term(atom(C), a, [bogus]), error(E, _C), true), \
+         findall(Type, current_op(700, Type, =), Ts).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["A = '[1, +(2, 3)]'\n\
+           B = 'f(...)'\n\
+           E = domain_error(write_option, [bogus])\n\
+           Ts = [xfx]\n\
+           _C = context(write_term/3, _1)"],
+    );
+}
+
+#[test]
+fn test_write_4_succeeds() {
+    let query = parse_query(
+        "writeq(atom(A), -(1)), writeq(atom(B), [-1, -(-(1)), 1 - -1, -(1.5), -a]), \
+         term_string(A, T), T = -(N), integer(N), X = -1, integer(X).",
+    );
+
+    let results = solve_toplevel(false, &[], query);
+
+    compare_answers(
+        results,
+        &["A = '- 1'\nB = '[-1, - - 1, 1- -1, - 1.5, -a]'\nN = 1\nT = - 1\nX = -1"],
+    );
+}
+
+#[test]
+fn test_sharing_1_succeeds() {
+    let source =
+        consult_text("grow(0, a).\ngrow(N, f(T, T)) :- N > 0, M is N - 1, grow(M, T).").unwrap();
+    let query = parse_query("grow(40, X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    let substitutions: Vec<String> = (1..39)
+        .map(|i| format!("_S{} = f(_S{}, _S{})", i, i + 1, i + 1))
+        .chain(Some(String::from("_S39 = f(a, a)")))
+        .collect();
+    let expected = format!("X = @(f(_S1, _S1), [{}])", substitutions.join(", "));
+
+    compare_answers(results, &[&expected]);
+}
+
+#[test]
+fn test_sharing_2_succeeds() {
+    let source =
+        consult_text("grow(0, a).\ngrow(N, f(T, T)) :- N > 0, M is N - 1, grow(M, T).").unwrap();
+    let query = parse_query("grow(2, X), Y = g(X, X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["X = f(f(a, a), f(a, a))\nY = g(f(f(a, a), f(a, a)), f(f(a, a), f(a, a)))"],
+    );
+}
+
+#[test]
+fn test_syntax_errors_1_succeeds() {
+    let (source, errors) = read_program("p(1).\np(2 oops).\np(3).\nq(.\nq(a).").unwrap();
+    let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+
+    assert_eq!(errors, ["2:5: unexpected `oops`", "4:3: unexpected `.`"]);
+
+    let query = parse_query("findall(X, p(X), Xs), findall(Y, q(Y), Ys).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Xs = [1, 3]\nYs = [a]"]);
+}
+
+#[test]
+fn test_syntax_errors_2_succeeds() {
+    let source = consult("tests/example_programs/loading/syntax_errors.pl").unwrap();
+    let query = parse_query("findall(X, r(X), Xs).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Xs = [1, 3]"]);
+}
+
+#[test]
+fn test_singletons_1_succeeds() {
+    let singletons: Vec<String> = singletons(
+        "append([], L, L).\nappend([H|T], L, [H|R]) :- append(T, L, Rest).\n\
+         len([_H|T], N) :- len(T, M), N is M + 1.\nfirst(X, _) :- member(X, [_Y]).",
+    )
+    .iter()
+    .map(ToString::to_string)
+    .collect();
+
+    assert_eq!(
+        singletons,
+        [
+            "2:21: singleton variable R",
+            "2:41: singleton variable Rest"
+        ]
+    );
+}
+
+#[test]
+fn test_det_1_succeeds() {
+    let source = consult_text(
+        ":- det(len/2).\nlen([], 0).\nlen([_H|T], N) :- len(T, M), N is M + 1.\n\
+         :- det(first/1).\nfirst(X) :- member(X, [1, 2]), !.",
+    )
+    .unwrap();
+    let query = parse_query("len([a, b, c], N), first(X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["N = 3\nX = 1"]);
+}
+
+#[test]
+fn test_det_2_succeeds() {
+    let source = consult_text(
+        ":- det(p/1).\np(1).\np(2).\n:- det(q/1).\nq(1).\n:- det(r/1).\nr(X) :- X > 1.",
+    )
+    .unwrap();
+    let query = parse_query(
+        "findall(E, catch(p(_X), error(E, _C), true), Es), \
+         findall(F, catch(q(2), error(F, _D), true), Fs), \
+         findall(G, catch(r(0), error(G, _B), true), Gs).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &[
+            "Es = [determinism_error(p/1, det, nondet, property_declaration)]\n\
+           Fs = [determinism_error(q/1, det, fail, property_declaration)]\n\
+           Gs = [determinism_error(r/1, det, fail, property_declaration)]",
+        ],
+    );
+}
+
+#[test]
+fn test_xref_1_succeeds() {
+    let source = consult_text(
+        ":- module(shapes, [area/2, perimeter/2]).\n\
+         area(square(S), A) :- A is S * S.\n\
+         area(circle(R), A) :- A is pi * R * R, check(R).\n\
+         helper(X) :- area(X, _A).\n\
+         main :- area(square(2), A), write(A).\n\
+         :- initialization(main).",
+    )
+    .unwrap();
+    let query = parse_query("xref(Issues).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &[
+            "Issues = [undefined(area/2, check/1), not_defined(shapes, perimeter/2), \
+           unused(helper/1)]",
+        ],
+    );
+}
+
+#[test]
+fn test_xref_2_succeeds() {
+    let source = consult_text("len([], 0).\nlen([_H|T], N) :- len(T, M), N is M + 1.").unwrap();
+    let query = parse_query("xref(Issues).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Issues = [unused(len/2)]"]);
+}
+
+#[test]
+fn test_coverage_1_succeeds() {
+    let source = consult_text(
+        "len([], 0).\nlen([_H|T], N) :- len(T, M), N is M + 1.\n\
+         sign(X, pos) :- X > 0.\nsign(X, neg) :- X < 0.\nsign(0, zero).\nunused.",
+    )
+    .unwrap();
+    let captured = Captured::default();
+    set_user_output(Some(Box::new(captured.clone())));
+
+    let query = parse_query("show_coverage((len([a, b], N), sign(N, S))).");
+    let results = solve_toplevel(false, &source, query);
+    set_user_output(None);
+
+    compare_answers(results, &["N = 2\nS = pos"]);
+    assert_eq!(
+        String::from_utf8_lossy(&captured.0.borrow()),
+        "len/2: 2 of 2 clauses entered (100.0%)\n\
+         sign/2: 1 of 3 clauses entered (33.3%)\n\
+         unused/0: 0 of 1 clause entered (0.0%)\n\
+         Total: 3 of 6 clauses entered (50.0%)\n"
+    );
+}
+
+#[test]
+fn test_help_1_succeeds() {
+    let source = consult("tests/example_programs/loading/docs.pl").unwrap();
+    let captured = Captured::default();
+    set_user_output(Some(Box::new(captured.clone())));
+
+    let query = parse_query("help(len/2), \\+ help(sign), xref(Issues).");
+    let results = solve_toplevel(false, &source, query);
+    set_user_output(None);
+
+    compare_answers(
+        results,
+        &["Issues = [unused(len/2), unused(sign/2), unused(first/2)]"],
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&captured.0.borrow()),
+        "len(+List, -Length) is det.\n\n    \
+         True when Length is the number of elements of List.\n"
+    );
+}
+
+#[test]
+fn test_doc_markdown_1_succeeds() {
+    let source = consult("tests/example_programs/loading/docs.pl").unwrap();
+
+    assert_eq!(
+        doc::markdown(&source),
+        "# Predicates\n\n\
+         ## `len/2`\n\n```prolog\nlen(+List, -Length) is det.\n```\n\n\
+         True when Length is the number of elements of List.\n\n\
+         ## `first/2`\n\n```prolog\n\
+         first(?List, ?First) is semidet.\nfirst(-List, +First) is det.\n```\n\n\
+         First is the first element of List.\n"
+    );
+}
+
+#[test]
+fn test_make_1_succeeds() {
+    let dir = std::env::temp_dir().join(format!("bfg_make_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("a.pl"),
+        ":- ensure_loaded('b.pl').\na(1).\na(2).\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("b.pl"), "b(1).\n").unwrap();
+
+    let mut source = consult(&dir.join("a.pl").to_string_lossy()).unwrap();
+    assert!(make(&mut source).unwrap().is_empty());
+
+    // The file is marked as modified later, as it could be written within the same tick.
+    std::fs::write(dir.join("b.pl"), "b(2).\nb(3).\n:- initialization(c).\n").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(dir.join("b.pl"))
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(10))
+        .unwrap();
+
+    let reloaded = make(&mut source).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(reloaded.len(), 1);
+    assert!(reloaded[0].0.ends_with("b.pl"));
+    assert_eq!(initialization_goals(&reloaded[0].1).len(), 1);
+
+    let query = parse_query("findall(X, a(X), Xs), findall(Y, b(Y), Ys).");
+
+    compare_answers(
+        solve_toplevel(false, &source, query),
+        &["Xs = [1, 2]\nYs = [2, 3]"],
+    );
+}
+
+#[test]
+fn test_source_location_1_succeeds() {
+    let path = std::env::temp_dir().join(format!("bfg_locations_{}.pl", std::process::id()));
+    std::fs::write(
+        &path,
+        ":- dynamic(seen/1).\n% A comment.\nlen([], 0).\nlen([_H|T], N) :-\n    len(T, M),\n    \
+         N is M + 1.\n\n'quoted name'(f(a, b), [c, d]).\ndigits([D|T]) --> [D], digits(T).\n",
+    )
+    .unwrap();
+
+    consult(&path.to_string_lossy()).unwrap();
+    let canonical = path.canonicalize().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        source_location("len", Some(2)),
+        Some((canonical.clone(), 3))
+    );
+    assert_eq!(source_location("len", None), Some((canonical.clone(), 3)));
+    assert_eq!(
+        source_location("quoted name", Some(2)),
+        Some((canonical.clone(), 8))
+    );
+    assert_eq!(source_location("digits", Some(3)), Some((canonical, 9)));
+    assert_eq!(source_location("digits", Some(1)), None);
+    assert_eq!(source_location("seen", None), None);
+}
+
+/// Tries the clauses of every predicate last first.
+struct Reversed;
+
+impl SearchStrategy for Reversed {
+    fn order_clauses(&mut self, _goal: &ast::Atom, clauses: &mut [Assertion]) {
+        clauses.reverse();
+    }
+}
+
+#[test]
+fn test_search_strategy_1_succeeds() {
+    let source = consult_text("color(red).\ncolor(green).\ncolor(blue).\n").unwrap();
+    let query = parse_query("color(X).");
+
+    set_search_strategy(Some(Box::new(Reversed)));
+    let results = solve_toplevel(false, &source, query);
+    set_search_strategy(None);
+
+    compare_answers(results, &["X = blue", "X = green", "X = red"]);
+}
+
+/// Searches no deeper than a bound that grows each time the tree is exhausted, up to `max`.
+struct IterativeDeepening {
+    bound: usize,
+    max: usize,
+}
+
+impl SearchStrategy for IterativeDeepening {
+    fn admits(&mut self, depth: usize) -> bool {
+        depth <= self.bound
+    }
+
+    fn restart(&mut self) -> bool {
+        self.bound += 1;
+        self.bound <= self.max
+    }
+}
+
+#[test]
+fn test_search_strategy_2_succeeds() {
+    // Left recursion that depth-first search never gets out of.
+    let source = consult_text(
+        "parent(a, b).\nparent(b, c).\nparent(c, d).\n\
+         anc(X, Y) :- anc(X, Z), parent(Z, Y).\nanc(X, Y) :- parent(X, Y).\n",
+    )
+    .unwrap();
+
+    set_search_strategy(Some(Box::new(IterativeDeepening { bound: 1, max: 20 })));
+    let found = solve_toplevel(false, &source, parse_query("anc(a, X)."));
+    let missing = solve_toplevel(false, &source, parse_query("anc(d, a)."));
+    set_search_strategy(None);
+
+    compare_answers(found, &["X = b", "X = c", "X = d"]);
+    compare_answers(missing, &["No"]);
+}
+
+/// Goes back to the earliest choicepoint when a branch fails.
+struct BreadthFirst;
+
+impl SearchStrategy for BreadthFirst {
+    fn select_choicepoint(&mut self, _depths: &[usize]) -> usize {
+        0
+    }
+}
+
+#[test]
+fn test_search_strategy_3_succeeds() {
+    let source = consult_text(
+        "q(1).\nq(2).\nq(3).\n\
+         p(X) :- q(X), !.\np(0).\n",
+    )
+    .unwrap();
+
+    set_search_strategy(Some(Box::new(BreadthFirst)));
+    let results = solve_toplevel(false, &source, parse_query("p(X), q(Y)."));
+    set_search_strategy(None);
+
+    compare_answers(results, &["X = 1\nY = 1", "X = 1\nY = 2", "X = 1\nY = 3"]);
+}
+
+#[test]
+fn test_parallel_1_succeeds() {
+    let source = consult("tests/example_programs/parallel/parallel.pl").unwrap();
+    let query = parse_query("routes(Routes), cut_first(X).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(
+        results,
+        &["Routes = [1-a, 2-a, 3-a, 10-b, 20-b, 99-d]\nX = 1"],
+    );
+}
+
+#[test]
+fn test_parallel_2_succeeds() {
+    let source = consult("tests/example_programs/parallel/parallel.pl").unwrap();
+    let query = parse_query("workers(Answers, Main).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["Answers = 2\nMain = no"]);
+}
+
+#[test]
+fn test_parallel_3_succeeds() {
+    let source = consult("tests/example_programs/parallel/parallel.pl").unwrap();
+    let query = parse_query("first_risky(X, E).");
+
+    let results = solve_toplevel(false, &source, query);
+
+    compare_answers(results, &["E = boom\nX = 1"]);
+}
+
+#[test]
+fn test_argv_1_succeeds() {
+    let source = consult("tests/example_programs/loading/argv.pl").unwrap();
+    let (_, main) = initialization_goals(&source).pop().unwrap();
+
+    set_argv(vec![
+        String::from("bfg-prolog"),
+        String::from("hello"),
+        String::from("3"),
+    ]);
+
+    assert_eq!(argv()[1], "hello");
+    assert!(solve_quietly(&source, main.clone()));
+
+    set_argv(vec![
+        String::from("bfg-prolog"),
+        String::from("bye"),
+        String::from("3"),
+    ]);
+
+    assert!(!solve_quietly(&source, main));
+}
+
+#[test]
+fn test_argv_2_succeeds() {
+    let source = consult("tests/example_programs/loading/argv.pl").unwrap();
+    let query = parse_query(
+        "current_prolog_flag(argv, [P|_Args]), current_prolog_flag(os_argv, [P|_OsArgs]).",
+    );
+
+    let results = solve_toplevel(false, &source, query);
+
+    assert_eq!(results.len(), 1);
+    assert_ne!(results[0], "No");
+}